@@ -0,0 +1,247 @@
+//! Pairing, Fibonacci, leftist and skew heaps, through the shared
+//! `MeldableHeap` interface, with `BinaryHeap` as a baseline where it
+//! applies.
+//!
+//! Run with `cargo bench --bench heaps`.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use datastructures::fibonacci_heap::FibonacciHeap;
+use datastructures::heap::MeldableHeap;
+use datastructures::leftist_heap::{LeftistHeap, SkewHeap};
+use datastructures::pairing_heap::PairingHeap;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// A xorshift generator, so every run sees the same inputs.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn keys(n: usize) -> Vec<u64> {
+    let mut rng = Rng(0x5eed);
+    (0..n).map(|_| rng.next() >> 16).collect()
+}
+
+/// A random graph with `n` vertices and about `4n` weighted edges, as
+/// adjacency lists.
+fn graph(n: usize) -> Vec<Vec<(usize, u64)>> {
+    let mut rng = Rng(0xd1ce);
+    let mut adjacency = vec![Vec::new(); n];
+    for (u, edges) in adjacency.iter_mut().enumerate() {
+        // A path keeps every vertex reachable.
+        if u + 1 < n {
+            edges.push((u + 1, 1000));
+        }
+        for _ in 0..3 {
+            edges.push((rng.below(n), rng.below(1000) as u64 + 1));
+        }
+    }
+    adjacency
+}
+
+fn push_pop<H: MeldableHeap<u64>>(keys: &[u64]) -> u64 {
+    let mut heap = H::default();
+    for &k in keys {
+        heap.push(k);
+    }
+    let mut sum = 0;
+    while let Some(k) = heap.pop() {
+        sum ^= k;
+    }
+    sum
+}
+
+/// Dijkstra's algorithm with decrease-key.
+fn dijkstra<H: MeldableHeap<(u64, usize)>>(adjacency: &[Vec<(usize, u64)>]) -> u64 {
+    let n = adjacency.len();
+    let mut dist = vec![u64::MAX; n];
+    let mut handles = vec![None; n];
+    let mut heap = H::default();
+    dist[0] = 0;
+    handles[0] = Some(heap.push((0, 0)));
+    while let Some((d, u)) = heap.pop() {
+        handles[u] = None;
+        for &(v, w) in &adjacency[u] {
+            let candidate = d + w;
+            if candidate < dist[v] {
+                dist[v] = candidate;
+                match handles[v] {
+                    Some(h) => heap.decrease_key(h, (candidate, v)),
+                    None => handles[v] = Some(heap.push((candidate, v))),
+                }
+            }
+        }
+    }
+    dist.iter().filter(|&&d| d != u64::MAX).sum()
+}
+
+/// Melds `parts` heaps into one, then drains it.
+fn meld_all<H: MeldableHeap<u64>>(parts: Vec<H>) -> usize {
+    let mut parts = parts.into_iter();
+    let mut heap = parts.next().unwrap_or_default();
+    for part in parts {
+        heap.meld(part);
+    }
+    let mut count = 0;
+    while heap.pop().is_some() {
+        count += 1;
+    }
+    count
+}
+
+/// Builds a heap by melding it, each round, into a new one-item heap:
+/// the large-into-small order that copying the melded arena made
+/// quadratic.
+fn meld_into_small<H: MeldableHeap<u64>>(keys: &[u64]) -> usize {
+    let mut heap = H::default();
+    for &k in keys {
+        let mut one = H::default();
+        one.push(k);
+        one.meld(heap);
+        heap = one;
+    }
+    heap.len()
+}
+
+fn split<H: MeldableHeap<u64>>(keys: &[u64], parts: usize) -> Vec<H> {
+    keys.chunks(keys.len().div_ceil(parts))
+        .map(|chunk| {
+            let mut heap = H::default();
+            for &k in chunk {
+                heap.push(k);
+            }
+            heap
+        })
+        .collect()
+}
+
+fn bench_push_pop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("push_pop");
+    for n in SIZES {
+        let keys = keys(n);
+        group.bench_with_input(BenchmarkId::new("pairing", n), &keys, |b, keys| {
+            b.iter(|| push_pop::<PairingHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("fibonacci", n), &keys, |b, keys| {
+            b.iter(|| push_pop::<FibonacciHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("leftist", n), &keys, |b, keys| {
+            b.iter(|| push_pop::<LeftistHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("skew", n), &keys, |b, keys| {
+            b.iter(|| push_pop::<SkewHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("binary", n), &keys, |b, keys| {
+            b.iter(|| {
+                let mut heap: BinaryHeap<Reverse<u64>> =
+                    black_box(keys).iter().map(|&k| Reverse(k)).collect();
+                let mut sum = 0;
+                while let Some(Reverse(k)) = heap.pop() {
+                    sum ^= k;
+                }
+                sum
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_dijkstra(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dijkstra");
+    for n in SIZES {
+        let adjacency = graph(n);
+        group.bench_with_input(BenchmarkId::new("pairing", n), &adjacency, |b, g| {
+            b.iter(|| dijkstra::<PairingHeap<_>>(black_box(g)))
+        });
+        group.bench_with_input(BenchmarkId::new("fibonacci", n), &adjacency, |b, g| {
+            b.iter(|| dijkstra::<FibonacciHeap<_>>(black_box(g)))
+        });
+        group.bench_with_input(BenchmarkId::new("leftist", n), &adjacency, |b, g| {
+            b.iter(|| dijkstra::<LeftistHeap<_>>(black_box(g)))
+        });
+        group.bench_with_input(BenchmarkId::new("skew", n), &adjacency, |b, g| {
+            b.iter(|| dijkstra::<SkewHeap<_>>(black_box(g)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_meld(c: &mut Criterion) {
+    let mut group = c.benchmark_group("meld");
+    for n in SIZES {
+        let keys = keys(n);
+        group.bench_with_input(BenchmarkId::new("pairing", n), &keys, |b, keys| {
+            b.iter_batched(
+                || split::<PairingHeap<u64>>(keys, 64),
+                meld_all,
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("fibonacci", n), &keys, |b, keys| {
+            b.iter_batched(
+                || split::<FibonacciHeap<u64>>(keys, 64),
+                meld_all,
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("leftist", n), &keys, |b, keys| {
+            b.iter_batched(
+                || split::<LeftistHeap<u64>>(keys, 64),
+                meld_all,
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("skew", n), &keys, |b, keys| {
+            b.iter_batched(
+                || split::<SkewHeap<u64>>(keys, 64),
+                meld_all,
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_meld_into_small(c: &mut Criterion) {
+    let mut group = c.benchmark_group("meld_into_small");
+    for n in SIZES {
+        let keys = keys(n);
+        group.bench_with_input(BenchmarkId::new("pairing", n), &keys, |b, keys| {
+            b.iter(|| meld_into_small::<PairingHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("fibonacci", n), &keys, |b, keys| {
+            b.iter(|| meld_into_small::<FibonacciHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("leftist", n), &keys, |b, keys| {
+            b.iter(|| meld_into_small::<LeftistHeap<u64>>(black_box(keys)))
+        });
+        group.bench_with_input(BenchmarkId::new("skew", n), &keys, |b, keys| {
+            b.iter(|| meld_into_small::<SkewHeap<u64>>(black_box(keys)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_push_pop,
+    bench_dijkstra,
+    bench_meld,
+    bench_meld_into_small
+);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+//! Drives random insert, remove, relocate and query sequences against an
+//! octree, checking its structural invariants after every step and every
+//! query against the brute-force reference index.
+//!
+//! Run with `cargo fuzz run octree_ops` from the repository root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use datastructures::geom::Aabb;
+use datastructures::naive::NaiveIndex;
+use datastructures::octree::{Octree, PointId};
+use datastructures::spatial_index::SpatialIndex;
+
+const SIZE: f64 = 1024.0;
+
+/// A coordinate: mostly on a lattice inside the tree's bounds, so that
+/// points collide and land on cell boundaries, and occasionally an
+/// arbitrary float to exercise the bounds checks.
+#[derive(Arbitrary, Debug, Clone, Copy)]
+enum Coord {
+    Lattice(u16),
+    Raw(f64),
+}
+
+impl Coord {
+    fn value(self) -> f64 {
+        match self {
+            Coord::Lattice(v) => v as f64 * SIZE / u16::MAX as f64,
+            Coord::Raw(v) => v,
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Insert([Coord; 3]),
+    /// Removes the point at this position among the live handles.
+    Remove(u16),
+    Relocate(u16, [Coord; 3]),
+    Nearest([Coord; 3], u8),
+    WithinRadius([Coord; 3], u16),
+    WithinBox([Coord; 3], [Coord; 3]),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    bucket: u8,
+    depth: u8,
+    ops: Vec<Op>,
+}
+
+fn point(c: [Coord; 3]) -> [f64; 3] {
+    c.map(Coord::value)
+}
+
+fn sorted(mut v: Vec<usize>) -> Vec<usize> {
+    v.sort_unstable();
+    v
+}
+
+fuzz_target!(|input: Input| {
+    let bounds = Aabb::new([0.0; 3], [SIZE; 3]);
+    let bucket = 1 + input.bucket as usize % 16;
+    let depth = input.depth % 21;
+    let mut tree: Octree<[f64; 3], usize> = Octree::with_limits(bounds, bucket, depth);
+    let mut naive: NaiveIndex<[f64; 3], usize, 3> = NaiveIndex::new();
+    // Live handles, with the naive index's handle for each.
+    let mut live: Vec<(PointId, usize)> = Vec::new();
+    let to_naive = |live: &[(PointId, usize)], id: PointId| {
+        live.iter()
+            .find(|e| e.0 == id)
+            .expect("returned handle is live")
+            .1
+    };
+
+    for op in input.ops {
+        match op {
+            Op::Insert(c) => {
+                let p = point(c);
+                match tree.insert(p, live.len()) {
+                    Ok(id) => {
+                        assert!(bounds.contains_point(&p));
+                        let n = naive.insert(p, live.len()).unwrap();
+                        live.push((id, n));
+                    }
+                    Err(_) => assert!(!bounds.contains_point(&p)),
+                }
+            }
+            Op::Remove(i) if !live.is_empty() => {
+                let (id, n) = live.swap_remove(i as usize % live.len());
+                assert_eq!(tree.remove(id), naive.remove(n));
+                assert!(tree.remove(id).is_none());
+            }
+            Op::Relocate(i, c) if !live.is_empty() => {
+                let (id, n) = live[i as usize % live.len()];
+                let p = point(c);
+                match tree.relocate(id, p) {
+                    Ok(old) => assert_eq!(old, naive.relocate(n, p)),
+                    Err(_) => assert!(!bounds.contains_point(&p)),
+                }
+            }
+            Op::Nearest(c, k) => {
+                let q = point(c);
+                if q.iter().all(|x| x.is_finite()) {
+                    let got = tree.nearest(q, k as usize);
+                    let want = SpatialIndex::nearest(&naive, &q, k as usize);
+                    assert_eq!(got.len(), want.len());
+                    for (g, w) in got.iter().zip(&want) {
+                        assert_eq!(g.1, w.1);
+                    }
+                }
+            }
+            Op::WithinRadius(c, r) => {
+                let (q, r) = (point(c), r as f64);
+                let got = tree
+                    .within_radius(q, r)
+                    .map(|(id, _, _)| to_naive(&live, id));
+                assert_eq!(sorted(got.collect()), sorted(naive.within_radius(&q, r)));
+            }
+            Op::WithinBox(a, b) => {
+                let (a, b) = (point(a), point(b));
+                let got = tree
+                    .within_box(Aabb::new(a, b))
+                    .map(|(id, _, _)| to_naive(&live, id));
+                assert_eq!(sorted(got.collect()), sorted(naive.query_aabb(&a, &b)));
+            }
+            _ => {}
+        }
+        tree.check_invariants();
+        assert_eq!(tree.len(), live.len());
+    }
+
+    // Every point is findable, both by handle and by a query at its own
+    // position.
+    for &(id, n) in &live {
+        let (p, d) = naive.get(n).unwrap();
+        assert_eq!(tree.get(id), Some((&p, d)));
+        assert!(tree
+            .within_box(Aabb::point(p))
+            .any(|(found, _, _)| found == id));
+    }
+});
@@ -0,0 +1,477 @@
+//! A dynamic AABB tree for broad-phase collision detection.
+//!
+//! This is the incrementally maintained binary hierarchy popularized by
+//! Box2D and Bullet. Each object is stored under a *fat* box: its bounds
+//! enlarged by a fixed margin, and additionally stretched in the direction
+//! of travel when the caller supplies a displacement. While an object stays
+//! inside its fat box, moving it touches nothing but the object itself;
+//! only when it escapes is its leaf removed and reinserted.
+//!
+//! Insertion descends toward the sibling that minimizes the total perimeter
+//! of the boxes it would enlarge, and every node on the way back up is
+//! rebalanced with AVL-style rotations, so the tree stays shallow under
+//! arbitrary insertion orders. Compared to a static [`Bvh`], the tree trades
+//! some query efficiency for cheap updates of many moving objects.
+//!
+//! [`Bvh`]: crate::bvh::Bvh
+
+use crate::geom::{inverse, slab, Aabb, Ray};
+
+/// Handle to an object stored in an [`AabbTree`].
+///
+/// Handles of removed objects may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProxyId(u32);
+
+const NONE: u32 = u32::MAX;
+
+/// How far ahead of a moving object its fat box reaches, in multiples of
+/// the displacement passed to [`AabbTree::relocate`].
+const DISPLACEMENT_MULTIPLIER: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug)]
+struct Node<const N: usize> {
+    /// The fat box, for leaves; the union of the children, otherwise.
+    bbox: Aabb<N>,
+    parent: u32,
+    /// Children, or `[NONE, NONE]` for a leaf.
+    children: [u32; 2],
+    /// Zero for leaves.
+    height: u32,
+    /// The object stored at a leaf.
+    proxy: u32,
+}
+
+impl<const N: usize> Node<N> {
+    fn is_leaf(&self) -> bool {
+        self.children[0] == NONE
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Proxy<T, const N: usize> {
+    bbox: Aabb<N>,
+    data: T,
+    leaf: u32,
+}
+
+/// A dynamic AABB tree over objects in `N` dimensions carrying data of type
+/// `T`.
+#[derive(Clone, Debug)]
+pub struct AabbTree<T, const N: usize> {
+    nodes: Vec<Node<N>>,
+    free_nodes: Vec<u32>,
+    proxies: Vec<Option<Proxy<T, N>>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+    margin: f64,
+}
+
+impl<T, const N: usize> AabbTree<T, N> {
+    /// Creates an empty tree whose fat boxes extend `margin` beyond each
+    /// object on every side.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `margin` is negative or not finite.
+    pub fn new(margin: f64) -> Self {
+        assert!(
+            margin >= 0.0 && margin.is_finite(),
+            "margin must be non-negative and finite"
+        );
+        AabbTree {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            proxies: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+            margin,
+        }
+    }
+
+    /// The enlargement margin.
+    pub fn margin(&self) -> f64 {
+        self.margin
+    }
+
+    /// Number of stored objects.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no objects.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Height of the tree: zero when empty, one for a single leaf.
+    pub fn height(&self) -> usize {
+        if self.root == NONE {
+            0
+        } else {
+            self.nodes[self.root as usize].height as usize + 1
+        }
+    }
+
+    /// The fat box around every object.
+    pub fn bounds(&self) -> Aabb<N> {
+        if self.root == NONE {
+            Aabb::empty()
+        } else {
+            self.nodes[self.root as usize].bbox
+        }
+    }
+
+    /// Removes every object, invalidating all handles.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.margin);
+    }
+
+    /// Inserts an object, returning its handle.
+    pub fn insert(&mut self, bbox: Aabb<N>, data: T) -> ProxyId {
+        let fat = self.fatten(&bbox, &[0.0; N]);
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.proxies.push(None);
+                (self.proxies.len() - 1) as u32
+            }
+        };
+        let leaf = self.alloc(Node {
+            bbox: fat,
+            parent: NONE,
+            children: [NONE; 2],
+            height: 0,
+            proxy: id,
+        });
+        self.proxies[id as usize] = Some(Proxy { bbox, data, leaf });
+        self.insert_leaf(leaf);
+        self.len += 1;
+        ProxyId(id)
+    }
+
+    /// Removes an object, returning its box and data.
+    pub fn remove(&mut self, id: ProxyId) -> Option<(Aabb<N>, T)> {
+        let proxy = self.proxies.get_mut(id.0 as usize)?.take()?;
+        self.remove_leaf(proxy.leaf);
+        self.free_nodes.push(proxy.leaf);
+        self.free.push(id.0);
+        self.len -= 1;
+        Some((proxy.bbox, proxy.data))
+    }
+
+    /// Moves an object to `bbox`, having travelled `displacement` since its
+    /// last update, and returns whether its leaf was reinserted.
+    ///
+    /// Nothing but the object changes while `bbox` stays inside the fat box.
+    /// Otherwise the fat box is rebuilt around `bbox`, stretched by a
+    /// multiple of `displacement` to anticipate further motion; pass zeros
+    /// when the motion is unknown. A fat box that has grown much larger than
+    /// needed is also rebuilt. Returns `None` if the handle is stale.
+    pub fn relocate(&mut self, id: ProxyId, bbox: Aabb<N>, displacement: [f64; N]) -> Option<bool> {
+        let proxy = self.proxies.get_mut(id.0 as usize)?.as_mut()?;
+        proxy.bbox = bbox;
+        let leaf = proxy.leaf;
+        let fat = self.fatten(&bbox, &displacement);
+        let current = self.nodes[leaf as usize].bbox;
+        if current.contains(&bbox) {
+            let slack = 4.0 * self.margin;
+            let huge = Aabb::new(fat.min.map(|v| v - slack), fat.max.map(|v| v + slack));
+            if huge.contains(&current) {
+                return Some(false);
+            }
+        }
+        self.remove_leaf(leaf);
+        self.nodes[leaf as usize].bbox = fat;
+        self.insert_leaf(leaf);
+        Some(true)
+    }
+
+    /// Returns the box and data of an object.
+    pub fn get(&self, id: ProxyId) -> Option<(&Aabb<N>, &T)> {
+        let p = self.proxies.get(id.0 as usize)?.as_ref()?;
+        Some((&p.bbox, &p.data))
+    }
+
+    /// Returns the box and mutable data of an object.
+    pub fn get_mut(&mut self, id: ProxyId) -> Option<(&Aabb<N>, &mut T)> {
+        let p = self.proxies.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&p.bbox, &mut p.data))
+    }
+
+    /// The fat box an object is filed under.
+    pub fn fat_bounds(&self, id: ProxyId) -> Option<Aabb<N>> {
+        let p = self.proxies.get(id.0 as usize)?.as_ref()?;
+        Some(self.nodes[p.leaf as usize].bbox)
+    }
+
+    /// Iterates over every object in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (ProxyId, &Aabb<N>, &T)> + '_ {
+        self.proxies
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| p.as_ref().map(|p| (ProxyId(i as u32), &p.bbox, &p.data)))
+    }
+
+    /// Iterates over the objects whose boxes intersect `query`.
+    pub fn intersecting(
+        &self,
+        query: Aabb<N>,
+    ) -> impl Iterator<Item = (ProxyId, &Aabb<N>, &T)> + '_ {
+        let mut stack = if self.root == NONE {
+            Vec::new()
+        } else {
+            vec![self.root]
+        };
+        std::iter::from_fn(move || {
+            while let Some(n) = stack.pop() {
+                let node = &self.nodes[n as usize];
+                if !node.bbox.intersects(&query) {
+                    continue;
+                }
+                if !node.is_leaf() {
+                    stack.extend(node.children);
+                    continue;
+                }
+                let p = self.proxy(node.proxy);
+                if p.bbox.intersects(&query) {
+                    return Some((ProxyId(node.proxy), &p.bbox, &p.data));
+                }
+            }
+            None
+        })
+    }
+
+    /// Every pair of objects whose boxes intersect, each pair once with the
+    /// smaller handle first, in unspecified order.
+    pub fn overlapping_pairs(&self) -> Vec<(ProxyId, ProxyId)> {
+        let mut pairs = Vec::new();
+        for (a, bbox, _) in self.iter() {
+            pairs.extend(
+                self.intersecting(*bbox)
+                    .filter(|&(b, _, _)| a < b)
+                    .map(|(b, _, _)| (a, b)),
+            );
+        }
+        pairs
+    }
+
+    /// Finds the nearest object hit by `ray` within parameter `max_t`.
+    ///
+    /// `hit` is called on objects whose boxes the ray enters, and returns
+    /// the parameter at which the ray hits the object itself, if it does.
+    pub fn closest_hit(
+        &self,
+        ray: &Ray<N>,
+        max_t: f64,
+        mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+    ) -> Option<(ProxyId, &T, f64)> {
+        if self.root == NONE {
+            return None;
+        }
+        let inv = inverse(ray);
+        let mut best: Option<(u32, f64)> = None;
+        let mut limit = max_t;
+        let mut stack = vec![self.root];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n as usize];
+            if slab(&node.bbox, ray, &inv, limit).is_none() {
+                continue;
+            }
+            if !node.is_leaf() {
+                stack.extend(node.children);
+                continue;
+            }
+            let p = self.proxy(node.proxy);
+            if slab(&p.bbox, ray, &inv, limit).is_none() {
+                continue;
+            }
+            if let Some(t) = hit(&p.data, ray).filter(|&t| t >= 0.0 && t <= limit) {
+                limit = t;
+                best = Some((node.proxy, t));
+            }
+        }
+        best.map(|(id, t)| (ProxyId(id), &self.proxy(id).data, t))
+    }
+
+    fn proxy(&self, id: u32) -> &Proxy<T, N> {
+        self.proxies[id as usize].as_ref().expect("live proxy")
+    }
+
+    fn fatten(&self, bbox: &Aabb<N>, displacement: &[f64; N]) -> Aabb<N> {
+        let m = self.margin;
+        let mut fat = Aabb::new(bbox.min.map(|v| v - m), bbox.max.map(|v| v + m));
+        for (a, &d) in displacement.iter().enumerate() {
+            let d = DISPLACEMENT_MULTIPLIER * d;
+            if d < 0.0 {
+                fat.min[a] += d;
+            } else {
+                fat.max[a] += d;
+            }
+        }
+        fat
+    }
+
+    fn alloc(&mut self, node: Node<N>) -> u32 {
+        match self.free_nodes.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Perimeter-style cost of a box, as used by Box2D.
+    fn cost(b: &Aabb<N>) -> f64 {
+        b.margin()
+    }
+
+    fn insert_leaf(&mut self, leaf: u32) {
+        if self.root == NONE {
+            self.root = leaf;
+            self.nodes[leaf as usize].parent = NONE;
+            return;
+        }
+        let bbox = self.nodes[leaf as usize].bbox;
+        // Descend while pushing the leaf further down is cheaper than
+        // pairing it with the current node.
+        let mut n = self.root;
+        while !self.nodes[n as usize].is_leaf() {
+            let node = self.nodes[n as usize];
+            let combined = Self::cost(&node.bbox.union(&bbox));
+            let here = 2.0 * combined;
+            // Every ancestor grows by this much whichever child is chosen.
+            let inherited = 2.0 * (combined - Self::cost(&node.bbox));
+            let descend = node.children.map(|c| {
+                let child = &self.nodes[c as usize];
+                let grown = Self::cost(&child.bbox.union(&bbox));
+                if child.is_leaf() {
+                    grown + inherited
+                } else {
+                    grown - Self::cost(&child.bbox) + inherited
+                }
+            });
+            if here < descend[0] && here < descend[1] {
+                break;
+            }
+            n = if descend[0] <= descend[1] {
+                node.children[0]
+            } else {
+                node.children[1]
+            };
+        }
+        let sibling = n;
+        let old_parent = self.nodes[sibling as usize].parent;
+        let parent = self.alloc(Node {
+            bbox: self.nodes[sibling as usize].bbox.union(&bbox),
+            parent: old_parent,
+            children: [sibling, leaf],
+            height: self.nodes[sibling as usize].height + 1,
+            proxy: NONE,
+        });
+        self.nodes[sibling as usize].parent = parent;
+        self.nodes[leaf as usize].parent = parent;
+        if old_parent == NONE {
+            self.root = parent;
+        } else {
+            self.replace_child(old_parent, sibling, parent);
+        }
+        self.refit_upward(parent);
+    }
+
+    /// Unlinks a leaf, leaving its node allocated.
+    fn remove_leaf(&mut self, leaf: u32) {
+        if leaf == self.root {
+            self.root = NONE;
+            return;
+        }
+        let parent = self.nodes[leaf as usize].parent;
+        let grandparent = self.nodes[parent as usize].parent;
+        let [a, b] = self.nodes[parent as usize].children;
+        let sibling = if a == leaf { b } else { a };
+        self.nodes[sibling as usize].parent = grandparent;
+        self.free_nodes.push(parent);
+        if grandparent == NONE {
+            self.root = sibling;
+        } else {
+            self.replace_child(grandparent, parent, sibling);
+            self.refit_upward(grandparent);
+        }
+    }
+
+    fn replace_child(&mut self, parent: u32, old: u32, new: u32) {
+        let children = &mut self.nodes[parent as usize].children;
+        if children[0] == old {
+            children[0] = new;
+        } else {
+            children[1] = new;
+        }
+    }
+
+    /// Rebalances and refits every node from `n` to the root.
+    fn refit_upward(&mut self, mut n: u32) {
+        while n != NONE {
+            n = self.balance(n);
+            self.refresh(n);
+            n = self.nodes[n as usize].parent;
+        }
+    }
+
+    fn refresh(&mut self, n: u32) {
+        let [a, b] = self.nodes[n as usize].children;
+        let (na, nb) = (self.nodes[a as usize], self.nodes[b as usize]);
+        let node = &mut self.nodes[n as usize];
+        node.bbox = na.bbox.union(&nb.bbox);
+        node.height = 1 + na.height.max(nb.height);
+    }
+
+    /// Rotates the taller grandchild of `a` up if `a`'s children differ in
+    /// height by more than one, returning the node now in `a`'s place.
+    fn balance(&mut self, a: u32) -> u32 {
+        let node = self.nodes[a as usize];
+        if node.is_leaf() || node.height < 2 {
+            return a;
+        }
+        let [b, c] = node.children;
+        let diff = self.nodes[c as usize].height as i64 - self.nodes[b as usize].height as i64;
+        if diff > 1 {
+            self.rotate_up(a, 1)
+        } else if diff < -1 {
+            self.rotate_up(a, 0)
+        } else {
+            a
+        }
+    }
+
+    /// Lifts child `side` of `a` into `a`'s position. Its taller child stays
+    /// with it and its shorter child takes its old place under `a`.
+    fn rotate_up(&mut self, a: u32, side: usize) -> u32 {
+        let up = self.nodes[a as usize].children[side];
+        let [f, g] = self.nodes[up as usize].children;
+        let (tall, short) = if self.nodes[f as usize].height > self.nodes[g as usize].height {
+            (f, g)
+        } else {
+            (g, f)
+        };
+        let parent = self.nodes[a as usize].parent;
+        self.nodes[up as usize].parent = parent;
+        if parent == NONE {
+            self.root = up;
+        } else {
+            self.replace_child(parent, a, up);
+        }
+        self.nodes[up as usize].children = [a, tall];
+        self.nodes[a as usize].parent = up;
+        self.nodes[a as usize].children[side] = short;
+        self.nodes[short as usize].parent = a;
+        self.refresh(a);
+        self.refresh(up);
+        up
+    }
+}
@@ -0,0 +1,568 @@
+//! A mutable directed graph with generational node and edge handles.
+//!
+//! Nodes and edges live in slot arrays and are named by a handle holding
+//! their slot index and a generation. Removing a node or edge frees its
+//! slot for reuse and bumps the slot's generation, so the old handle stops
+//! matching: lookups through a stale handle fail instead of silently
+//! reaching whatever was stored there next. Removals never move other
+//! nodes or edges, so every other handle stays valid.
+//!
+//! Every node keeps its outgoing and incoming edges in two lists, in the
+//! order they were added, so successors and predecessors are both a scan.
+//! Removing an edge costs the degree of its endpoints, and removing a node
+//! removes its edges too.
+//!
+//! Traversals borrow the graph: [`AdjacencyGraph::dfs`] and
+//! [`AdjacencyGraph::bfs`] visit the nodes reachable from a start in
+//! depth-first preorder and breadth-first order, and
+//! [`AdjacencyGraph::topological`] yields the nodes in an order where every
+//! edge goes forward, by Kahn's algorithm (1962).
+//!
+//! Compared with the static [`CsrGraph`], this trades neighbor locality
+//! for in-place changes.
+//!
+//! [`CsrGraph`]: crate::csr_graph::CsrGraph
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+/// Handle to a node of an [`AdjacencyGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Handle to an edge of an [`AdjacencyGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Returned when a topological order is asked of a graph with a cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// A node that lies on a cycle.
+    pub node: NodeId,
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("graph contains a cycle")
+    }
+}
+
+impl Error for Cycle {}
+
+#[derive(Clone, Debug)]
+struct Node<N> {
+    data: N,
+    /// Outgoing edge slots, in insertion order.
+    out: Vec<u32>,
+    /// Incoming edge slots, in insertion order.
+    into: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+struct Edge<E> {
+    data: E,
+    source: u32,
+    target: u32,
+}
+
+/// A slot and the generation of its current or next occupant.
+#[derive(Clone, Debug)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// A mutable directed graph with node payloads `N` and edge payloads `E`.
+#[derive(Clone, Debug)]
+pub struct AdjacencyGraph<N, E> {
+    nodes: Vec<Slot<Node<N>>>,
+    edges: Vec<Slot<Edge<E>>>,
+    free_nodes: Vec<u32>,
+    free_edges: Vec<u32>,
+    node_count: usize,
+    edge_count: usize,
+}
+
+impl<N, E> Default for AdjacencyGraph<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stores `value` in a free slot or a new one, returning the slot and its
+/// generation.
+fn occupy<T>(slots: &mut Vec<Slot<T>>, free: &mut Vec<u32>, value: T) -> (u32, u32) {
+    match free.pop() {
+        Some(index) => {
+            let slot = &mut slots[index as usize];
+            slot.value = Some(value);
+            (index, slot.generation)
+        }
+        None => {
+            slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            ((slots.len() - 1) as u32, 0)
+        }
+    }
+}
+
+/// Empties a slot, retiring its generation.
+fn vacate<T>(slots: &mut [Slot<T>], free: &mut Vec<u32>, index: u32) -> T {
+    let slot = &mut slots[index as usize];
+    slot.generation = slot.generation.wrapping_add(1);
+    free.push(index);
+    slot.value.take().expect("vacated slot is occupied")
+}
+
+impl<N, E> AdjacencyGraph<N, E> {
+    /// Creates an empty graph.
+    pub fn new() -> Self {
+        AdjacencyGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            free_nodes: Vec::new(),
+            free_edges: Vec::new(),
+            node_count: 0,
+            edge_count: 0,
+        }
+    }
+
+    /// Number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /// Number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Whether the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.node_count == 0
+    }
+
+    /// Removes every node and edge, invalidating all handles.
+    pub fn clear(&mut self) {
+        for (index, slot) in self.nodes.iter_mut().enumerate() {
+            if slot.value.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_nodes.push(index as u32);
+            }
+        }
+        for (index, slot) in self.edges.iter_mut().enumerate() {
+            if slot.value.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free_edges.push(index as u32);
+            }
+        }
+        self.node_count = 0;
+        self.edge_count = 0;
+    }
+
+    /// Adds a node, returning its handle.
+    pub fn add_node(&mut self, data: N) -> NodeId {
+        let node = Node {
+            data,
+            out: Vec::new(),
+            into: Vec::new(),
+        };
+        let (index, generation) = occupy(&mut self.nodes, &mut self.free_nodes, node);
+        self.node_count += 1;
+        NodeId { index, generation }
+    }
+
+    /// Adds an edge from `source` to `target`, returning its handle, or
+    /// `None` if either node is not in the graph. Parallel edges and
+    /// self-loops are allowed.
+    pub fn add_edge(&mut self, source: NodeId, target: NodeId, data: E) -> Option<EdgeId> {
+        if !self.contains_node(source) || !self.contains_node(target) {
+            return None;
+        }
+        let edge = Edge {
+            data,
+            source: source.index,
+            target: target.index,
+        };
+        let (index, generation) = occupy(&mut self.edges, &mut self.free_edges, edge);
+        self.node_slot_mut(source.index).out.push(index);
+        self.node_slot_mut(target.index).into.push(index);
+        self.edge_count += 1;
+        Some(EdgeId { index, generation })
+    }
+
+    /// Removes a node and every edge touching it, returning its payload.
+    pub fn remove_node(&mut self, id: NodeId) -> Option<N> {
+        let node = self.live_node(id)?;
+        let mut incident = node.out.clone();
+        incident.extend(&node.into);
+        incident.sort_unstable();
+        incident.dedup();
+        for edge in incident {
+            self.unlink_edge(edge);
+        }
+        self.node_count -= 1;
+        Some(vacate(&mut self.nodes, &mut self.free_nodes, id.index).data)
+    }
+
+    /// Removes an edge, returning its payload.
+    pub fn remove_edge(&mut self, id: EdgeId) -> Option<E> {
+        self.live_edge(id)?;
+        Some(self.unlink_edge(id.index))
+    }
+
+    /// Whether `id` refers to a node in the graph.
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.live_node(id).is_some()
+    }
+
+    /// Whether `id` refers to an edge in the graph.
+    pub fn contains_edge(&self, id: EdgeId) -> bool {
+        self.live_edge(id).is_some()
+    }
+
+    /// The payload of a node.
+    pub fn node(&self, id: NodeId) -> Option<&N> {
+        self.live_node(id).map(|n| &n.data)
+    }
+
+    /// The mutable payload of a node.
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut N> {
+        let slot = self.nodes.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_mut().map(|n| &mut n.data)
+    }
+
+    /// The payload of an edge.
+    pub fn edge(&self, id: EdgeId) -> Option<&E> {
+        self.live_edge(id).map(|e| &e.data)
+    }
+
+    /// The mutable payload of an edge.
+    pub fn edge_mut(&mut self, id: EdgeId) -> Option<&mut E> {
+        let slot = self.edges.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.value.as_mut().map(|e| &mut e.data)
+    }
+
+    /// The source and target of an edge.
+    pub fn endpoints(&self, id: EdgeId) -> Option<(NodeId, NodeId)> {
+        let edge = self.live_edge(id)?;
+        Some((self.node_id(edge.source), self.node_id(edge.target)))
+    }
+
+    /// The first edge added from `source` to `target` that is still in the
+    /// graph.
+    pub fn find_edge(&self, source: NodeId, target: NodeId) -> Option<EdgeId> {
+        self.contains_node(target).then_some(())?;
+        self.live_node(source)?
+            .out
+            .iter()
+            .find(|&&e| self.edge_slot(e).target == target.index)
+            .map(|&e| self.edge_id(e))
+    }
+
+    /// Iterates over the nodes and their payloads, in slot order.
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &N)> + '_ {
+        self.nodes.iter().enumerate().filter_map(|(i, slot)| {
+            let node = slot.value.as_ref()?;
+            let id = NodeId {
+                index: i as u32,
+                generation: slot.generation,
+            };
+            Some((id, &node.data))
+        })
+    }
+
+    /// Iterates over the edges as `(edge, source, target, payload)`, in
+    /// slot order.
+    pub fn edges(&self) -> impl Iterator<Item = (EdgeId, NodeId, NodeId, &E)> + '_ {
+        self.edges.iter().enumerate().filter_map(|(i, slot)| {
+            let edge = slot.value.as_ref()?;
+            let id = EdgeId {
+                index: i as u32,
+                generation: slot.generation,
+            };
+            Some((
+                id,
+                self.node_id(edge.source),
+                self.node_id(edge.target),
+                &edge.data,
+            ))
+        })
+    }
+
+    /// The edges leaving a node as `(edge, target, payload)`, in the order
+    /// they were added. Empty if the node is not in the graph.
+    pub fn out_edges(&self, id: NodeId) -> impl Iterator<Item = (EdgeId, NodeId, &E)> + '_ {
+        let out = self.live_node(id).map_or(&[][..], |n| &n.out);
+        out.iter().map(|&e| {
+            let edge = self.edge_slot(e);
+            (self.edge_id(e), self.node_id(edge.target), &edge.data)
+        })
+    }
+
+    /// The edges entering a node as `(edge, source, payload)`, in the order
+    /// they were added. Empty if the node is not in the graph.
+    pub fn in_edges(&self, id: NodeId) -> impl Iterator<Item = (EdgeId, NodeId, &E)> + '_ {
+        let into = self.live_node(id).map_or(&[][..], |n| &n.into);
+        into.iter().map(|&e| {
+            let edge = self.edge_slot(e);
+            (self.edge_id(e), self.node_id(edge.source), &edge.data)
+        })
+    }
+
+    /// The targets of the edges leaving a node, once per edge.
+    pub fn successors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.out_edges(id).map(|(_, target, _)| target)
+    }
+
+    /// The sources of the edges entering a node, once per edge.
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.in_edges(id).map(|(_, source, _)| source)
+    }
+
+    /// Number of edges leaving a node.
+    pub fn out_degree(&self, id: NodeId) -> usize {
+        self.live_node(id).map_or(0, |n| n.out.len())
+    }
+
+    /// Number of edges entering a node.
+    pub fn in_degree(&self, id: NodeId) -> usize {
+        self.live_node(id).map_or(0, |n| n.into.len())
+    }
+
+    /// Visits the nodes reachable from `start` in depth-first preorder,
+    /// taking edges in the order they were added.
+    pub fn dfs(&self, start: NodeId) -> Dfs<'_, N, E> {
+        let stack = if self.contains_node(start) {
+            vec![start.index]
+        } else {
+            Vec::new()
+        };
+        Dfs {
+            graph: self,
+            stack,
+            visited: vec![false; self.nodes.len()],
+        }
+    }
+
+    /// Visits the nodes reachable from `start` in breadth-first order.
+    pub fn bfs(&self, start: NodeId) -> Bfs<'_, N, E> {
+        let mut visited = vec![false; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        if self.contains_node(start) {
+            visited[start.index as usize] = true;
+            queue.push_back(start.index);
+        }
+        Bfs {
+            graph: self,
+            queue,
+            visited,
+        }
+    }
+
+    /// Visits every node in an order where each edge goes from an earlier
+    /// node to a later one. Nodes on or behind a cycle are never reached;
+    /// [`topological_order`](Self::topological_order) reports the cycle.
+    pub fn topological(&self) -> Topo<'_, N, E> {
+        let mut pending = vec![0; self.nodes.len()];
+        let mut ready = VecDeque::new();
+        for (i, slot) in self.nodes.iter().enumerate() {
+            if let Some(node) = &slot.value {
+                pending[i] = node.into.len();
+                if node.into.is_empty() {
+                    ready.push_back(i as u32);
+                }
+            }
+        }
+        Topo {
+            graph: self,
+            pending,
+            ready,
+        }
+    }
+
+    /// All nodes in topological order, or a [`Cycle`] if there is none.
+    pub fn topological_order(&self) -> Result<Vec<NodeId>, Cycle> {
+        let mut topo = self.topological();
+        let order: Vec<NodeId> = topo.by_ref().collect();
+        if order.len() == self.node_count {
+            return Ok(order);
+        }
+        // Walk back along unfinished predecessors until a node repeats.
+        let start = (0..self.nodes.len())
+            .find(|&i| topo.pending[i] > 0)
+            .expect("some node is unfinished");
+        let mut seen = vec![false; self.nodes.len()];
+        let mut n = start as u32;
+        while !std::mem::replace(&mut seen[n as usize], true) {
+            n = self
+                .node_slot(n)
+                .into
+                .iter()
+                .map(|&e| self.edge_slot(e).source)
+                .find(|&s| topo.pending[s as usize] > 0)
+                .expect("an unfinished node has an unfinished predecessor");
+        }
+        Err(Cycle {
+            node: self.node_id(n),
+        })
+    }
+
+    fn live_node(&self, id: NodeId) -> Option<&Node<N>> {
+        let slot = self.nodes.get(id.index as usize)?;
+        (slot.generation == id.generation)
+            .then_some(slot.value.as_ref())
+            .flatten()
+    }
+
+    fn live_edge(&self, id: EdgeId) -> Option<&Edge<E>> {
+        let slot = self.edges.get(id.index as usize)?;
+        (slot.generation == id.generation)
+            .then_some(slot.value.as_ref())
+            .flatten()
+    }
+
+    fn node_slot(&self, index: u32) -> &Node<N> {
+        self.nodes[index as usize]
+            .value
+            .as_ref()
+            .expect("live node")
+    }
+
+    fn node_slot_mut(&mut self, index: u32) -> &mut Node<N> {
+        self.nodes[index as usize]
+            .value
+            .as_mut()
+            .expect("live node")
+    }
+
+    fn edge_slot(&self, index: u32) -> &Edge<E> {
+        self.edges[index as usize]
+            .value
+            .as_ref()
+            .expect("live edge")
+    }
+
+    fn node_id(&self, index: u32) -> NodeId {
+        NodeId {
+            index,
+            generation: self.nodes[index as usize].generation,
+        }
+    }
+
+    fn edge_id(&self, index: u32) -> EdgeId {
+        EdgeId {
+            index,
+            generation: self.edges[index as usize].generation,
+        }
+    }
+
+    /// Removes a live edge from its endpoints' lists and frees its slot.
+    fn unlink_edge(&mut self, index: u32) -> E {
+        let (source, target) = {
+            let edge = self.edge_slot(index);
+            (edge.source, edge.target)
+        };
+        let out = &mut self.node_slot_mut(source).out;
+        let at = out.iter().position(|&e| e == index).expect("edge in list");
+        out.remove(at);
+        let into = &mut self.node_slot_mut(target).into;
+        let at = into.iter().position(|&e| e == index).expect("edge in list");
+        into.remove(at);
+        self.edge_count -= 1;
+        vacate(&mut self.edges, &mut self.free_edges, index).data
+    }
+}
+
+/// A depth-first traversal of an [`AdjacencyGraph`].
+pub struct Dfs<'a, N, E> {
+    graph: &'a AdjacencyGraph<N, E>,
+    stack: Vec<u32>,
+    visited: Vec<bool>,
+}
+
+impl<N, E> Iterator for Dfs<'_, N, E> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let graph = self.graph;
+        loop {
+            let n = self.stack.pop()?;
+            // A node pushed twice is visited at its first pop.
+            if std::mem::replace(&mut self.visited[n as usize], true) {
+                continue;
+            }
+            // Reversed, so the first edge is explored first.
+            for &e in graph.node_slot(n).out.iter().rev() {
+                let target = graph.edge_slot(e).target;
+                if !self.visited[target as usize] {
+                    self.stack.push(target);
+                }
+            }
+            return Some(graph.node_id(n));
+        }
+    }
+}
+
+/// A breadth-first traversal of an [`AdjacencyGraph`].
+pub struct Bfs<'a, N, E> {
+    graph: &'a AdjacencyGraph<N, E>,
+    queue: VecDeque<u32>,
+    visited: Vec<bool>,
+}
+
+impl<N, E> Iterator for Bfs<'_, N, E> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let n = self.queue.pop_front()?;
+        let graph = self.graph;
+        for &e in &graph.node_slot(n).out {
+            let target = graph.edge_slot(e).target;
+            if !std::mem::replace(&mut self.visited[target as usize], true) {
+                self.queue.push_back(target);
+            }
+        }
+        Some(graph.node_id(n))
+    }
+}
+
+/// A topological traversal of an [`AdjacencyGraph`].
+pub struct Topo<'a, N, E> {
+    graph: &'a AdjacencyGraph<N, E>,
+    /// Incoming edges from nodes not yet yielded.
+    pending: Vec<usize>,
+    ready: VecDeque<u32>,
+}
+
+impl<N, E> Iterator for Topo<'_, N, E> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let n = self.ready.pop_front()?;
+        let graph = self.graph;
+        for &e in &graph.node_slot(n).out {
+            let target = graph.edge_slot(e).target as usize;
+            self.pending[target] -= 1;
+            if self.pending[target] == 0 {
+                self.ready.push_back(target as u32);
+            }
+        }
+        Some(graph.node_id(n))
+    }
+}
@@ -0,0 +1,305 @@
+//! Algebraic building blocks for the aggregate structures.
+//!
+//! Range-query structures are generic over how values combine. A
+//! [`Monoid`] supplies an associative operation and its identity; an
+//! [`Action`] describes a range update that can be applied to a monoid
+//! summary without visiting the elements underneath, which is what lazy
+//! propagation needs. An [`AbelianGroup`] is a commutative monoid with
+//! inverses, so a range can be summarised as the difference of two
+//! prefixes. An [`Idempotent`] monoid may combine overlapping ranges,
+//! which is what sparse tables rely on. Sum, minimum, maximum and gcd
+//! monoids and increment and assignment actions over the primitive numbers
+//! are provided, and sums over the signed numbers are groups.
+
+use std::marker::PhantomData;
+use std::ops::{Add, Neg};
+
+/// An associative binary operation with an identity element.
+///
+/// Implementations must satisfy `combine(a, combine(b, c)) ==
+/// combine(combine(a, b), c)` and `combine(identity(), a) == a ==
+/// combine(a, identity())`. The monoid is a value rather than a type so it
+/// can carry runtime configuration such as a modulus.
+pub trait Monoid {
+    /// The element type.
+    type Value: Clone;
+
+    /// The identity element.
+    fn identity(&self) -> Self::Value;
+
+    /// Combines two elements, `a` on the left.
+    fn combine(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// A commutative monoid in which every element has an inverse.
+///
+/// Implementations must satisfy `combine(a, b) == combine(b, a)` and
+/// `combine(a, inverse(a)) == identity()`. Structures built on prefix sums
+/// need both: they combine elements out of order, and recover a range by
+/// removing one prefix from another.
+pub trait AbelianGroup: Monoid {
+    /// The inverse of `a`.
+    fn inverse(&self, a: &Self::Value) -> Self::Value;
+
+    /// The element that combined with `b` gives `a`.
+    fn difference(&self, a: &Self::Value, b: &Self::Value) -> Self::Value {
+        self.combine(a, &self.inverse(b))
+    }
+}
+
+/// A monoid in which combining an element with itself gives it back.
+///
+/// Implementations must satisfy `combine(a, a) == a`, so a range may be
+/// covered by pieces that overlap without counting anything twice.
+pub trait Idempotent: Monoid {}
+
+/// A range update that can be applied directly to the summary of a range.
+pub trait Action<M: Monoid>: Clone {
+    /// Applies the update to `summary`, the combination of `len` elements.
+    fn apply(&self, monoid: &M, summary: &M::Value, len: usize) -> M::Value;
+
+    /// The single update equivalent to applying `earlier` and then `self`.
+    fn compose(&self, earlier: &Self) -> Self;
+}
+
+/// The update that changes nothing, for structures used with point updates
+/// only.
+impl<M: Monoid> Action<M> for () {
+    fn apply(&self, _: &M, summary: &M::Value, _: usize) -> M::Value {
+        summary.clone()
+    }
+
+    fn compose(&self, _: &Self) -> Self {}
+}
+
+/// Numbers with an additive identity.
+pub trait Zero {
+    /// The additive identity.
+    const ZERO: Self;
+}
+
+/// Numbers with a least and a greatest value.
+///
+/// For floats these are the infinities.
+pub trait Bounded {
+    /// The least value.
+    const MIN: Self;
+    /// The greatest value.
+    const MAX: Self;
+}
+
+macro_rules! impl_int {
+    ($($t:ty)*) => {$(
+        impl Zero for $t {
+            const ZERO: Self = 0;
+        }
+        impl Bounded for $t {
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+        }
+    )*};
+}
+
+impl_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+macro_rules! impl_float {
+    ($($t:ty)*) => {$(
+        impl Zero for $t {
+            const ZERO: Self = 0.0;
+        }
+        impl Bounded for $t {
+            const MIN: Self = <$t>::NEG_INFINITY;
+            const MAX: Self = <$t>::INFINITY;
+        }
+    )*};
+}
+
+impl_float!(f32 f64);
+
+macro_rules! marker_monoid {
+    ($(#[$doc:meta] $name:ident)*) => {$(
+        #[$doc]
+        #[derive(Debug)]
+        pub struct $name<T>(PhantomData<fn() -> T>);
+
+        impl<T> $name<T> {
+            /// Creates the monoid.
+            pub fn new() -> Self {
+                $name(PhantomData)
+            }
+        }
+
+        impl<T> Default for $name<T> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<T> Clone for $name<T> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<T> Copy for $name<T> {}
+    )*};
+}
+
+marker_monoid! {
+    /// Addition, with identity zero.
+    Sum
+    /// Minimum, with the greatest value as identity.
+    Min
+    /// Maximum, with the least value as identity.
+    Max
+    /// Greatest common divisor, with identity zero.
+    Gcd
+}
+
+impl<T: Copy + Add<Output = T> + Zero> Monoid for Sum<T> {
+    type Value = T;
+
+    fn identity(&self) -> T {
+        T::ZERO
+    }
+
+    fn combine(&self, a: &T, b: &T) -> T {
+        *a + *b
+    }
+}
+
+impl<T: Copy + Add<Output = T> + Neg<Output = T> + Zero> AbelianGroup for Sum<T> {
+    fn inverse(&self, a: &T) -> T {
+        -*a
+    }
+}
+
+impl<T: Copy + PartialOrd + Bounded> Monoid for Min<T> {
+    type Value = T;
+
+    fn identity(&self) -> T {
+        T::MAX
+    }
+
+    fn combine(&self, a: &T, b: &T) -> T {
+        if *b < *a {
+            *b
+        } else {
+            *a
+        }
+    }
+}
+
+impl<T: Copy + PartialOrd + Bounded> Monoid for Max<T> {
+    type Value = T;
+
+    fn identity(&self) -> T {
+        T::MIN
+    }
+
+    fn combine(&self, a: &T, b: &T) -> T {
+        if *b > *a {
+            *b
+        } else {
+            *a
+        }
+    }
+}
+
+impl<T: Copy + PartialOrd + Bounded> Idempotent for Min<T> {}
+
+impl<T: Copy + PartialOrd + Bounded> Idempotent for Max<T> {}
+
+macro_rules! impl_gcd {
+    ($($t:ty)*) => {$(
+        impl Monoid for Gcd<$t> {
+            type Value = $t;
+
+            fn identity(&self) -> $t {
+                0
+            }
+
+            fn combine(&self, a: &$t, b: &$t) -> $t {
+                let (mut a, mut b) = (*a, *b);
+                while b != 0 {
+                    (a, b) = (b, a % b);
+                }
+                a
+            }
+        }
+
+        impl Idempotent for Gcd<$t> {}
+    )*};
+}
+
+impl_gcd!(u8 u16 u32 u64 u128 usize);
+
+/// Adds a constant to every element of a range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Increment<T>(pub T);
+
+/// Overwrites every element of a range with a constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Assign<T>(pub T);
+
+impl<T: Copy + Add<Output = T> + Zero> Action<Sum<T>> for Increment<T> {
+    fn apply(&self, _: &Sum<T>, summary: &T, len: usize) -> T {
+        *summary + times(self.0, len)
+    }
+
+    fn compose(&self, earlier: &Self) -> Self {
+        Increment(earlier.0 + self.0)
+    }
+}
+
+impl<T: Copy + Add<Output = T> + Zero> Action<Sum<T>> for Assign<T> {
+    fn apply(&self, _: &Sum<T>, _: &T, len: usize) -> T {
+        times(self.0, len)
+    }
+
+    fn compose(&self, _: &Self) -> Self {
+        *self
+    }
+}
+
+macro_rules! order_actions {
+    ($($m:ident)*) => {$(
+        impl<T: Copy + PartialOrd + Bounded + Add<Output = T>> Action<$m<T>> for Increment<T> {
+            fn apply(&self, _: &$m<T>, summary: &T, _: usize) -> T {
+                *summary + self.0
+            }
+
+            fn compose(&self, earlier: &Self) -> Self {
+                Increment(earlier.0 + self.0)
+            }
+        }
+
+        impl<T: Copy + PartialOrd + Bounded> Action<$m<T>> for Assign<T> {
+            fn apply(&self, _: &$m<T>, _: &T, _: usize) -> T {
+                self.0
+            }
+
+            fn compose(&self, _: &Self) -> Self {
+                *self
+            }
+        }
+    )*};
+}
+
+order_actions!(Min Max);
+
+/// `x` added to itself `n` times, by doubling, so only `Add` is needed.
+fn times<T: Copy + Add<Output = T> + Zero>(x: T, mut n: usize) -> T {
+    let mut acc = T::ZERO;
+    let mut pow = x;
+    while n > 0 {
+        if n & 1 == 1 {
+            acc = acc + pow;
+        }
+        n >>= 1;
+        if n > 0 {
+            pow = pow + pow;
+        }
+    }
+    acc
+}
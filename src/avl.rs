@@ -0,0 +1,495 @@
+//! An ordered map stored as an AVL tree, with user-defined subtree
+//! augmentation.
+//!
+//! The AVL tree keeps the heights of every node's two subtrees within one of
+//! each other, rebalancing with at most two rotations per level after an
+//! update. That keeps it within about 1.44 log n levels, so lookups are
+//! about as cheap as they get for a binary tree.
+//!
+//! Each node also carries an [`Augment`] value computed from its own entry
+//! and its children's augments, such as a subtree size, a maximum, or a sum.
+//! Updates recompute it along their path and through every rotation, so every
+//! node's augment always describes the subtree under it. A custom descent from
+//! [`root_node`](AvlTree::root_node) can then answer questions about ranges
+//! of keys in `O(log n)`. With [`Size`] the tree becomes an order-statistic
+//! tree, via [`select`](AvlTree::select) and [`rank`](AvlTree::rank). With
+//! intervals as keys ordered by start and the maximum end as the augment, it
+//! becomes an interval tree. The default augment `()` costs nothing.
+//!
+//! Values that feed the augment must not change behind its back, so there
+//! is no `get_mut`; [`modify`](AvlTree::modify) edits a value in place and
+//! recomputes the path above it.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+/// A summary of a subtree, recomputed whenever the subtree changes.
+pub trait Augment<K, V>: Sized {
+    /// The summary of a node from its entry and its children's summaries.
+    fn compute(key: &K, value: &V, left: Option<&Self>, right: Option<&Self>) -> Self;
+}
+
+/// No augmentation.
+impl<K, V> Augment<K, V> for () {
+    fn compute(_: &K, _: &V, _: Option<&Self>, _: Option<&Self>) -> Self {}
+}
+
+/// The number of entries in a subtree, for order statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Size(pub usize);
+
+impl<K, V> Augment<K, V> for Size {
+    fn compute(_: &K, _: &V, left: Option<&Self>, right: Option<&Self>) -> Self {
+        Size(1 + left.map_or(0, |s| s.0) + right.map_or(0, |s| s.0))
+    }
+}
+
+type Tree<K, V, A> = Box<Node<K, V, A>>;
+type Link<K, V, A> = Option<Tree<K, V, A>>;
+
+#[derive(Clone, Debug)]
+struct Node<K, V, A> {
+    key: K,
+    value: V,
+    height: u8,
+    augment: A,
+    left: Link<K, V, A>,
+    right: Link<K, V, A>,
+}
+
+/// An ordered map from keys of type `K` to values of type `V`, with every
+/// subtree summarized by an augment of type `A`.
+#[derive(Clone, Debug)]
+pub struct AvlTree<K, V, A = ()> {
+    root: Link<K, V, A>,
+    len: usize,
+}
+
+impl<K: Ord, V, A: Augment<K, V>> Default for AvlTree<K, V, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V, A: Augment<K, V>> AvlTree<K, V, A> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        AvlTree { root: None, len: 0 }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of node levels; zero for an empty tree.
+    pub fn height(&self) -> usize {
+        height(&self.root) as usize
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// The root of the tree, for custom descents over the augments.
+    pub fn root_node(&self) -> Option<NodeRef<'_, K, V, A>> {
+        self.root.as_deref().map(|node| NodeRef { node })
+    }
+
+    /// The augment of the whole tree.
+    pub fn augment(&self) -> Option<&A> {
+        self.root.as_ref().map(|n| &n.augment)
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Some(&n.value),
+            };
+        }
+        None
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Calls `f` on the value stored under `key`, then recomputes the
+    /// augments above it. Returns what `f` returned, or `None` if there is
+    /// no such entry.
+    pub fn modify<Q, R>(&mut self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        modify(&mut self.root, key, f)
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_deref()?;
+        while let Some(l) = n.left.as_deref() {
+            n = l;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_deref()?;
+        while let Some(r) = n.right.as_deref() {
+            n = r;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let (root, old) = insert(self.root.take(), key, value);
+        self.root = Some(root);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (root, removed) = remove(self.root.take(), key);
+        self.root = root;
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order.
+    pub fn range<'a, Q, R>(&'a self, range: R) -> impl Iterator<Item = (&'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<Q> + 'a,
+    {
+        // The stack holds the nodes at or above the start bound whose left
+        // subtrees have been dealt with.
+        let mut stack: Vec<&Node<K, V, A>> = Vec::new();
+        let mut link = self.root.as_deref();
+        while let Some(n) = link {
+            let above = match range.start_bound() {
+                Bound::Included(b) => n.key.borrow() >= b,
+                Bound::Excluded(b) => n.key.borrow() > b,
+                Bound::Unbounded => true,
+            };
+            if above {
+                stack.push(n);
+                link = n.left.as_deref();
+            } else {
+                link = n.right.as_deref();
+            }
+        }
+        std::iter::from_fn(move || {
+            let n = stack.pop()?;
+            let inside = match range.end_bound() {
+                Bound::Included(b) => n.key.borrow() <= b,
+                Bound::Excluded(b) => n.key.borrow() < b,
+                Bound::Unbounded => true,
+            };
+            if !inside {
+                stack.clear();
+                return None;
+            }
+            let mut link = n.right.as_deref();
+            while let Some(c) = link {
+                stack.push(c);
+                link = c.left.as_deref();
+            }
+            Some((&n.key, &n.value))
+        })
+    }
+}
+
+impl<K: Ord, V> AvlTree<K, V, Size> {
+    /// The entry with the `index`-th smallest key, counting from zero.
+    pub fn select(&self, mut index: usize) -> Option<(&K, &V)> {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let left = n.left.as_ref().map_or(0, |l| l.augment.0);
+            link = match index.cmp(&left) {
+                Ordering::Less => &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    index -= left + 1;
+                    &n.right
+                }
+            };
+        }
+        None
+    }
+
+    /// The number of keys below `key`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            if n.key.borrow() < key {
+                rank += n.left.as_ref().map_or(0, |l| l.augment.0) + 1;
+                link = &n.right;
+            } else {
+                link = &n.left;
+            }
+        }
+        rank
+    }
+}
+
+impl<K: Ord, V, A: Augment<K, V>> FromIterator<(K, V)> for AvlTree<K, V, A> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = AvlTree::new();
+        for (k, v) in iter {
+            tree.insert(k, v);
+        }
+        tree
+    }
+}
+
+/// A read-only view of one node of an [`AvlTree`].
+#[derive(Debug)]
+pub struct NodeRef<'a, K, V, A> {
+    node: &'a Node<K, V, A>,
+}
+
+impl<K, V, A> Clone for NodeRef<'_, K, V, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V, A> Copy for NodeRef<'_, K, V, A> {}
+
+impl<'a, K, V, A> NodeRef<'a, K, V, A> {
+    /// The node's key.
+    pub fn key(self) -> &'a K {
+        &self.node.key
+    }
+
+    /// The node's value.
+    pub fn value(self) -> &'a V {
+        &self.node.value
+    }
+
+    /// The augment of the subtree under this node.
+    pub fn augment(self) -> &'a A {
+        &self.node.augment
+    }
+
+    /// The root of the subtree of smaller keys.
+    pub fn left(self) -> Option<Self> {
+        self.node.left.as_deref().map(|node| NodeRef { node })
+    }
+
+    /// The root of the subtree of larger keys.
+    pub fn right(self) -> Option<Self> {
+        self.node.right.as_deref().map(|node| NodeRef { node })
+    }
+}
+
+fn height<K, V, A>(link: &Link<K, V, A>) -> u8 {
+    link.as_ref().map_or(0, |n| n.height)
+}
+
+/// Recomputes a node's height and augment from its children.
+fn fix<K, V, A: Augment<K, V>>(n: &mut Node<K, V, A>) {
+    n.height = 1 + height(&n.left).max(height(&n.right));
+    n.augment = A::compute(
+        &n.key,
+        &n.value,
+        n.left.as_ref().map(|l| &l.augment),
+        n.right.as_ref().map(|r| &r.augment),
+    );
+}
+
+fn rotate_right<K, V, A: Augment<K, V>>(mut n: Tree<K, V, A>) -> Tree<K, V, A> {
+    let mut l = n.left.take().expect("rotation needs a left child");
+    n.left = l.right.take();
+    fix(&mut n);
+    l.right = Some(n);
+    fix(&mut l);
+    l
+}
+
+fn rotate_left<K, V, A: Augment<K, V>>(mut n: Tree<K, V, A>) -> Tree<K, V, A> {
+    let mut r = n.right.take().expect("rotation needs a right child");
+    n.right = r.left.take();
+    fix(&mut n);
+    r.left = Some(n);
+    fix(&mut r);
+    r
+}
+
+/// Restores the balance of a node whose subtrees' heights differ by at
+/// most two, and recomputes it.
+fn balance<K, V, A: Augment<K, V>>(mut n: Tree<K, V, A>) -> Tree<K, V, A> {
+    let (hl, hr) = (height(&n.left), height(&n.right));
+    if hl > hr + 1 {
+        let l = n.left.take().expect("taller side exists");
+        n.left = Some(if height(&l.left) < height(&l.right) {
+            rotate_left(l)
+        } else {
+            l
+        });
+        return rotate_right(n);
+    }
+    if hr > hl + 1 {
+        let r = n.right.take().expect("taller side exists");
+        n.right = Some(if height(&r.right) < height(&r.left) {
+            rotate_right(r)
+        } else {
+            r
+        });
+        return rotate_left(n);
+    }
+    fix(&mut n);
+    n
+}
+
+fn insert<K: Ord, V, A: Augment<K, V>>(
+    link: Link<K, V, A>,
+    key: K,
+    value: V,
+) -> (Tree<K, V, A>, Option<V>) {
+    let Some(mut n) = link else {
+        let n = Box::new(Node {
+            augment: A::compute(&key, &value, None, None),
+            key,
+            value,
+            height: 1,
+            left: None,
+            right: None,
+        });
+        return (n, None);
+    };
+    let old = match key.cmp(&n.key) {
+        Ordering::Less => {
+            let (l, old) = insert(n.left.take(), key, value);
+            n.left = Some(l);
+            old
+        }
+        Ordering::Greater => {
+            let (r, old) = insert(n.right.take(), key, value);
+            n.right = Some(r);
+            old
+        }
+        Ordering::Equal => Some(std::mem::replace(&mut n.value, value)),
+    };
+    (balance(n), old)
+}
+
+fn remove<K, V, A, Q>(link: Link<K, V, A>, key: &Q) -> (Link<K, V, A>, Option<(K, V)>)
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    A: Augment<K, V>,
+{
+    let Some(mut n) = link else {
+        return (None, None);
+    };
+    let removed = match key.cmp(n.key.borrow()) {
+        Ordering::Less => {
+            let (l, removed) = remove(n.left.take(), key);
+            n.left = l;
+            removed
+        }
+        Ordering::Greater => {
+            let (r, removed) = remove(n.right.take(), key);
+            n.right = r;
+            removed
+        }
+        Ordering::Equal => {
+            let (left, right) = (n.left.take(), n.right.take());
+            let Some(right) = right else {
+                return (left, Some((n.key, n.value)));
+            };
+            // The successor takes the removed node's place.
+            let (right, mut successor) = remove_min(right);
+            successor.left = left;
+            successor.right = right;
+            return (Some(balance(successor)), Some((n.key, n.value)));
+        }
+    };
+    (Some(balance(n)), removed)
+}
+
+/// Detaches the smallest node of a subtree, returning the rest and it.
+fn remove_min<K, V, A: Augment<K, V>>(mut n: Tree<K, V, A>) -> (Link<K, V, A>, Tree<K, V, A>) {
+    match n.left.take() {
+        None => (n.right.take(), n),
+        Some(l) => {
+            let (l, min) = remove_min(l);
+            n.left = l;
+            (Some(balance(n)), min)
+        }
+    }
+}
+
+fn modify<K, V, A, Q, R>(
+    link: &mut Link<K, V, A>,
+    key: &Q,
+    f: impl FnOnce(&mut V) -> R,
+) -> Option<R>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+    A: Augment<K, V>,
+{
+    let n = link.as_mut()?;
+    let out = match key.cmp(n.key.borrow()) {
+        Ordering::Less => modify(&mut n.left, key, f)?,
+        Ordering::Greater => modify(&mut n.right, key, f)?,
+        Ordering::Equal => f(&mut n.value),
+    };
+    fix(n);
+    Some(out)
+}
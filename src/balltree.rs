@@ -0,0 +1,240 @@
+//! A static ball tree for nearest-neighbor search under any [`Metric`].
+//!
+//! Each node covers its points with a ball around a pivot, itself one of the
+//! points, so the tree never needs coordinates or means and works for any
+//! metric space. A query skips a node whenever the triangle inequality puts
+//! every point of its ball farther away than the current answer. Unlike the
+//! axis-aligned k-d tree, ball bounds stay tight in higher dimensions and
+//! under non-Euclidean metrics.
+//!
+//! Construction splits each node between the two points farthest apart
+//! (approximately), assigning every point to the nearer of them.
+//!
+//! [`Metric`]: crate::metric::Metric
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::metric::Metric;
+use crate::util::{permute, Total};
+
+const LEAF_SIZE: usize = 16;
+
+#[derive(Clone, Debug)]
+struct Node {
+    /// Storage index of the pivot point.
+    pivot: usize,
+    radius: f64,
+    start: usize,
+    end: usize,
+    /// Index of the left child, followed by the right; zero for leaves.
+    children: usize,
+}
+
+/// A ball tree mapping points of type `P` to data of type `T` under the
+/// metric `M`.
+#[derive(Clone, Debug)]
+pub struct BallTree<P, T, M> {
+    points: Vec<P>,
+    data: Vec<T>,
+    nodes: Vec<Node>,
+    metric: M,
+}
+
+impl<P, T, M: Metric<P>> BallTree<P, T, M> {
+    /// Builds a tree from `(point, data)` pairs.
+    pub fn build(items: Vec<(P, T)>, metric: M) -> Self {
+        let (points, data): (Vec<P>, Vec<T>) = items.into_iter().unzip();
+        let mut tree = BallTree {
+            points,
+            data,
+            nodes: Vec::new(),
+            metric,
+        };
+        if tree.points.is_empty() {
+            return tree;
+        }
+        let mut order: Vec<usize> = (0..tree.points.len()).collect();
+        tree.nodes.push(tree.make_node(&order, 0, order.len()));
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let (start, end) = (tree.nodes[n].start, tree.nodes[n].end);
+            if end - start <= LEAF_SIZE {
+                continue;
+            }
+            let mid = start + tree.split(&mut order[start..end]);
+            let left = tree.nodes.len();
+            tree.nodes.push(tree.make_node(&order, start, mid));
+            tree.nodes.push(tree.make_node(&order, mid, end));
+            tree.nodes[n].children = left;
+            stack.extend([left, left + 1]);
+        }
+        // Pivots were recorded as original indices; map them to storage.
+        let mut position = vec![0; order.len()];
+        for (pos, &orig) in order.iter().enumerate() {
+            position[orig] = pos;
+        }
+        for node in &mut tree.nodes {
+            node.pivot = position[node.pivot];
+        }
+        permute(&mut tree.points, &order);
+        permute(&mut tree.data, &order);
+        tree
+    }
+
+    /// Partitions `ids` around two far-apart points, returning the size of
+    /// the first group. Both groups are kept non-empty.
+    fn split(&self, ids: &mut [usize]) -> usize {
+        let d = |a: usize, b: usize| self.metric.distance(&self.points[a], &self.points[b]);
+        let farthest = |from: usize, ids: &[usize]| {
+            ids.iter()
+                .copied()
+                .max_by(|&a, &b| d(from, a).total_cmp(&d(from, b)))
+                .expect("non-empty")
+        };
+        let a = farthest(ids[0], ids);
+        let b = farthest(a, ids);
+        let mut mid = 0;
+        for i in 0..ids.len() {
+            if d(ids[i], a) <= d(ids[i], b) {
+                ids.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == ids.len() {
+            // Every point coincides with `a`; any split will do.
+            return ids.len() / 2;
+        }
+        // Lead each group with its seed so it becomes the child's pivot.
+        for (seed, range) in [(a, 0..mid), (b, mid..ids.len())] {
+            let first = range.start;
+            let at = range
+                .into_iter()
+                .find(|&i| ids[i] == seed)
+                .expect("seed in its group");
+            ids.swap(first, at);
+        }
+        mid
+    }
+
+    fn make_node(&self, order: &[usize], start: usize, end: usize) -> Node {
+        let ids = &order[start..end];
+        // `split` leads each group with the seed it was gathered around.
+        let pivot = ids[0];
+        let radius = ids
+            .iter()
+            .map(|&i| self.metric.distance(&self.points[pivot], &self.points[i]))
+            .fold(0.0, f64::max);
+        Node {
+            pivot,
+            radius,
+            start,
+            end,
+            children: 0,
+        }
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The metric the tree was built with.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// The point and data stored at `index`.
+    pub fn get(&self, index: usize) -> Option<(&P, &T)> {
+        Some((self.points.get(index)?, &self.data[index]))
+    }
+
+    /// Iterates over all points in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &P, &T)> + '_ {
+        self.points
+            .iter()
+            .zip(&self.data)
+            .enumerate()
+            .map(|(i, (p, d))| (i, p, d))
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// distances.
+    pub fn nearest(&self, query: &P, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let mut best: BinaryHeap<(Total, usize)> = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((Total(self.lower_bound(0, query)), 0)));
+        while let Some(Reverse((Total(bound), n))) = frontier.pop() {
+            if best.len() == k && bound >= best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                break;
+            }
+            let node = &self.nodes[n];
+            if node.children == 0 {
+                for i in node.start..node.end {
+                    let d = self.metric.distance(query, &self.points[i]);
+                    if best.len() < k {
+                        best.push((Total(d), i));
+                    } else if d < best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                        best.pop();
+                        best.push((Total(d), i));
+                    }
+                }
+            } else {
+                for c in [node.children, node.children + 1] {
+                    frontier.push(Reverse((Total(self.lower_bound(c, query)), c)));
+                }
+            }
+        }
+        let mut out: Vec<_> = best.into_iter().map(|(d, i)| (i, d.0)).collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one(&self, query: &P) -> Option<(usize, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Returns every point within `radius` of `query` with its distance, in
+    /// unspecified order.
+    pub fn within_radius(&self, query: &P, radius: f64) -> Vec<(usize, f64)> {
+        let mut out = Vec::new();
+        let mut stack = if self.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            let to_pivot = self.metric.distance(query, &self.points[node.pivot]);
+            if to_pivot - node.radius > radius {
+                continue;
+            }
+            if to_pivot + node.radius <= radius {
+                // The whole ball is inside the query; no pruning left to do.
+                for i in node.start..node.end {
+                    out.push((i, self.metric.distance(query, &self.points[i])));
+                }
+            } else if node.children == 0 {
+                for i in node.start..node.end {
+                    let d = self.metric.distance(query, &self.points[i]);
+                    if d <= radius {
+                        out.push((i, d));
+                    }
+                }
+            } else {
+                stack.extend([node.children, node.children + 1]);
+            }
+        }
+        out
+    }
+
+    fn lower_bound(&self, n: usize, query: &P) -> f64 {
+        let node = &self.nodes[n];
+        (self.metric.distance(query, &self.points[node.pivot]) - node.radius).max(0.0)
+    }
+}
@@ -0,0 +1,396 @@
+//! A bounding interval hierarchy for ray tracing.
+//!
+//! A BIH is a binary tree over primitive boxes, like a [`Bvh`], but its
+//! nodes store no boxes. Each interior node records one axis and two clip
+//! planes along it. The left child's boxes all end at or before the first
+//! plane, and the right child's boxes all start at or after the second. The
+//! planes may overlap, or leave an empty gap between the children.
+//!
+//! The build splits a candidate cell at its spatial midpoint and sorts
+//! primitives by the side their center falls on, in the manner of a k-d
+//! tree. It never evaluates a cost function, so it runs in `O(n log n)` with
+//! a small constant, several times faster than the SAH build. That suits
+//! dynamic scenes that are rebuilt every frame. Rays walk the tree by
+//! clipping their parameter interval against the planes, the same way they
+//! walk a k-d tree, and share the slab test with the rest of the crate for
+//! the scene bounds and primitive boxes.
+//!
+//! [`Bvh`]: crate::bvh::Bvh
+
+use std::ops::Range;
+
+pub use crate::geom::Ray;
+use crate::geom::{inverse, slab, Aabb};
+use crate::util::permute;
+
+const MAX_LEAF: usize = 4;
+/// Midpoint splits in a row that may leave one side empty before a node
+/// gives up and stays a leaf; this stops coincident centers from recursing
+/// forever.
+const MAX_EMPTY_SPLITS: usize = 32;
+/// The `axis` of a leaf.
+const LEAF: u8 = u8::MAX;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    /// Split axis of an interior node, or `LEAF`.
+    axis: u8,
+    /// The largest coordinate along `axis` of any box in the left child,
+    /// and the smallest of any box in the right child.
+    clip: [f64; 2],
+    /// Index of the left child for interior nodes (the right child follows
+    /// it), or of the first primitive for leaves.
+    start: u32,
+    /// Number of primitives in a leaf.
+    count: u32,
+}
+
+impl Node {
+    fn leaf(start: usize, count: usize) -> Self {
+        Node {
+            axis: LEAF,
+            clip: [0.0; 2],
+            start: start as u32,
+            count: count as u32,
+        }
+    }
+}
+
+/// A bounding interval hierarchy over primitives in `N` dimensions carrying
+/// data of type `T`.
+#[derive(Clone, Debug)]
+pub struct Bih<T, const N: usize> {
+    nodes: Vec<Node>,
+    boxes: Vec<Aabb<N>>,
+    data: Vec<T>,
+    bounds: Aabb<N>,
+}
+
+impl<T, const N: usize> Bih<T, N> {
+    /// Builds a hierarchy over `(bounds, data)` primitives.
+    pub fn build(primitives: Vec<(Aabb<N>, T)>) -> Self {
+        let (boxes, data) = primitives.into_iter().unzip();
+        let mut bih = Bih {
+            nodes: Vec::new(),
+            boxes,
+            data,
+            bounds: Aabb::empty(),
+        };
+        bih.rebuild();
+        bih
+    }
+
+    /// Number of primitives.
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    /// Whether the hierarchy holds no primitives.
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// The bounding box and data of a primitive, by storage index.
+    pub fn get(&self, index: usize) -> Option<(&Aabb<N>, &T)> {
+        Some((self.boxes.get(index)?, &self.data[index]))
+    }
+
+    /// Iterates over primitives in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Aabb<N>, &T)> + '_ {
+        self.boxes
+            .iter()
+            .zip(&self.data)
+            .enumerate()
+            .map(|(i, (b, d))| (i, b, d))
+    }
+
+    /// The bounding box of all primitives.
+    pub fn bounds(&self) -> Aabb<N> {
+        self.bounds
+    }
+
+    /// Recomputes every primitive's box with `bounds` and moves the clip
+    /// planes to fit them, keeping the topology.
+    ///
+    /// Refitting skips the partitioning pass but lets the children's
+    /// intervals grow and overlap as primitives move; since the build is
+    /// cheap, [`Bih::rebuild`] is usually the better choice each frame.
+    pub fn refit(&mut self, mut bounds: impl FnMut(&T) -> Aabb<N>) {
+        for (b, d) in self.boxes.iter_mut().zip(&self.data) {
+            *b = bounds(d);
+        }
+        // Children follow their parents, so a reverse sweep sees every
+        // child's extent before its parent needs it.
+        let mut extents = vec![Aabb::empty(); self.nodes.len()];
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            let start = node.start as usize;
+            if node.axis == LEAF {
+                extents[i] = union_all(&self.boxes[start..start + node.count as usize]);
+                continue;
+            }
+            let a = node.axis as usize;
+            let (left, right) = (extents[start], extents[start + 1]);
+            self.nodes[i].clip = [left.max[a], right.min[a]];
+            extents[i] = left.union(&right);
+        }
+        self.bounds = extents.first().copied().unwrap_or(Aabb::empty());
+    }
+
+    /// Rebuilds the hierarchy from the current primitive boxes.
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        self.bounds = union_all(&self.boxes);
+        if self.boxes.is_empty() {
+            return;
+        }
+        let centers: Vec<[f64; N]> = self.boxes.iter().map(Aabb::center).collect();
+        let mut order: Vec<usize> = (0..self.boxes.len()).collect();
+        self.nodes.push(Node::leaf(0, order.len()));
+        // Each pending node carries the cell its split planes are drawn in.
+        let mut stack = vec![(0usize, self.bounds)];
+        while let Some((n, mut cell)) = stack.pop() {
+            let Node { start, count, .. } = self.nodes[n];
+            let range = start as usize..(start + count) as usize;
+            if range.len() <= MAX_LEAF {
+                continue;
+            }
+            let prims = &mut order[range.clone()];
+            let mut split = None;
+            for _ in 0..MAX_EMPTY_SPLITS {
+                let axis = (0..N)
+                    .max_by(|&a, &b| {
+                        (cell.max[a] - cell.min[a]).total_cmp(&(cell.max[b] - cell.min[b]))
+                    })
+                    .unwrap_or(0);
+                let plane = 0.5 * (cell.min[axis] + cell.max[axis]);
+                let mut mid = 0;
+                for i in 0..prims.len() {
+                    if centers[prims[i]][axis] < plane {
+                        prims.swap(i, mid);
+                        mid += 1;
+                    }
+                }
+                // With everything on one side, narrow the cell to that side
+                // and try again rather than spend a node on nothing.
+                if mid == 0 {
+                    cell.min[axis] = plane;
+                } else if mid == prims.len() {
+                    cell.max[axis] = plane;
+                } else {
+                    split = Some((axis, plane, mid));
+                    break;
+                }
+            }
+            let Some((axis, plane, mid)) = split else {
+                continue;
+            };
+            let (left, right) = prims.split_at(mid);
+            let clip = [
+                left.iter()
+                    .map(|&p| self.boxes[p].max[axis])
+                    .fold(f64::NEG_INFINITY, f64::max),
+                right
+                    .iter()
+                    .map(|&p| self.boxes[p].min[axis])
+                    .fold(f64::INFINITY, f64::min),
+            ];
+            let child = self.nodes.len();
+            self.nodes.push(Node::leaf(range.start, mid));
+            self.nodes
+                .push(Node::leaf(range.start + mid, range.len() - mid));
+            self.nodes[n] = Node {
+                axis: axis as u8,
+                clip,
+                start: child as u32,
+                count: 0,
+            };
+            let (mut below, mut above) = (cell, cell);
+            below.max[axis] = plane;
+            above.min[axis] = plane;
+            stack.push((child, below));
+            stack.push((child + 1, above));
+        }
+        permute(&mut self.boxes, &order);
+        permute(&mut self.data, &order);
+    }
+
+    /// Finds the nearest primitive along `ray` within `max_t`.
+    ///
+    /// `hit` performs the exact primitive test, returning the ray parameter
+    /// of its intersection if any; it's only called for primitives whose
+    /// boxes the ray crosses. Returns the storage index, data and parameter
+    /// of the closest hit.
+    pub fn closest_hit(
+        &self,
+        ray: &Ray<N>,
+        max_t: f64,
+        mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+    ) -> Option<(usize, &T, f64)> {
+        let inv = inverse(ray);
+        let mut best = None;
+        self.walk(ray, &inv, max_t, |prims, limit| {
+            for i in prims {
+                if slab(&self.boxes[i], ray, &inv, *limit).is_none() {
+                    continue;
+                }
+                if let Some(t) = hit(&self.data[i], ray).filter(|&t| t >= 0.0 && t <= *limit) {
+                    *limit = t;
+                    best = Some((i, t));
+                }
+            }
+            false
+        });
+        best.map(|(i, t)| (i, &self.data[i], t))
+    }
+
+    /// Finds any primitive hit by `ray` within `max_t`, stopping at the first
+    /// one; suited to shadow rays and occlusion tests.
+    pub fn any_hit(
+        &self,
+        ray: &Ray<N>,
+        max_t: f64,
+        mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+    ) -> Option<(usize, &T)> {
+        let inv = inverse(ray);
+        let mut found = None;
+        self.walk(ray, &inv, max_t, |prims, _| {
+            found = prims.into_iter().find(|&i| {
+                slab(&self.boxes[i], ray, &inv, max_t).is_some()
+                    && hit(&self.data[i], ray).is_some_and(|t| t >= 0.0 && t <= max_t)
+            });
+            found.is_some()
+        });
+        found.map(|i| (i, &self.data[i]))
+    }
+
+    /// Iterates over the primitives whose boxes intersect `query`.
+    pub fn intersecting(&self, query: Aabb<N>) -> impl Iterator<Item = (usize, &Aabb<N>, &T)> + '_ {
+        let mut stack = if self.bounds.intersects(&query) {
+            vec![0usize]
+        } else {
+            Vec::new()
+        };
+        let mut run = 0..0;
+        std::iter::from_fn(move || loop {
+            for i in run.by_ref() {
+                if self.boxes[i].intersects(&query) {
+                    return Some((i, &self.boxes[i], &self.data[i]));
+                }
+            }
+            let node = self.nodes[stack.pop()?];
+            let start = node.start as usize;
+            if node.axis == LEAF {
+                run = start..start + node.count as usize;
+                continue;
+            }
+            let a = node.axis as usize;
+            if query.max[a] >= node.clip[1] {
+                stack.push(start + 1);
+            }
+            if query.min[a] <= node.clip[0] {
+                stack.push(start);
+            }
+        })
+    }
+
+    /// Walks the leaves `ray` may reach front to back, passing each leaf's
+    /// primitives to `leaf` along with the current parameter limit, which
+    /// `leaf` may tighten. Stops early when `leaf` returns `true`.
+    fn walk(
+        &self,
+        ray: &Ray<N>,
+        inv: &[f64; N],
+        max_t: f64,
+        mut leaf: impl FnMut(Range<usize>, &mut f64) -> bool,
+    ) {
+        let Some((t0, t1)) = span(&self.bounds, ray, inv, max_t).filter(|_| !self.nodes.is_empty())
+        else {
+            return;
+        };
+        let mut limit = max_t;
+        let mut stack = vec![(0usize, t0, t1)];
+        while let Some((n, lo, hi)) = stack.pop() {
+            // The limit may have tightened since this node was pushed.
+            let hi = hi.min(limit);
+            if lo > hi {
+                continue;
+            }
+            let node = self.nodes[n];
+            let start = node.start as usize;
+            if node.axis == LEAF {
+                if leaf(start..start + node.count as usize, &mut limit) {
+                    return;
+                }
+                continue;
+            }
+            let a = node.axis as usize;
+            let (o, d) = (ray.origin[a], ray.direction[a]);
+            if d == 0.0 {
+                // Parallel to the planes: the origin's side decides.
+                if o >= node.clip[1] {
+                    stack.push((start + 1, lo, hi));
+                }
+                if o <= node.clip[0] {
+                    stack.push((start, lo, hi));
+                }
+                continue;
+            }
+            let t_left = (node.clip[0] - o) * inv[a];
+            let t_right = (node.clip[1] - o) * inv[a];
+            // The near child holds the part of the interval before its
+            // plane, the far child the part after.
+            let (near, far) = if d > 0.0 {
+                (
+                    (start, lo, hi.min(t_left)),
+                    (start + 1, lo.max(t_right), hi),
+                )
+            } else {
+                (
+                    (start + 1, lo, hi.min(t_right)),
+                    (start, lo.max(t_left), hi),
+                )
+            };
+            if far.1 <= far.2 {
+                stack.push(far);
+            }
+            if near.1 <= near.2 {
+                stack.push(near);
+            }
+        }
+    }
+}
+
+fn union_all<const N: usize>(boxes: &[Aabb<N>]) -> Aabb<N> {
+    boxes.iter().fold(Aabb::empty(), |acc, b| acc.union(b))
+}
+
+/// The parameters at which `ray` enters and leaves `bbox` within
+/// `0..=max_t`: the slab test, keeping the exit as well. Interior nodes
+/// only clip along their own axis, so the walk must start from the whole
+/// span inside the scene bounds.
+#[allow(clippy::needless_range_loop)]
+fn span<const N: usize>(
+    bbox: &Aabb<N>,
+    ray: &Ray<N>,
+    inv: &[f64; N],
+    max_t: f64,
+) -> Option<(f64, f64)> {
+    let (mut t0, mut t1) = (0.0f64, max_t);
+    for a in 0..N {
+        if ray.direction[a] == 0.0 {
+            if ray.origin[a] < bbox.min[a] || bbox.max[a] < ray.origin[a] {
+                return None;
+            }
+            continue;
+        }
+        let near = (bbox.min[a] - ray.origin[a]) * inv[a];
+        let far = (bbox.max[a] - ray.origin[a]) * inv[a];
+        t0 = t0.max(near.min(far));
+        t1 = t1.min(near.max(far));
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
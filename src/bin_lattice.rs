@@ -0,0 +1,436 @@
+//! A double-buffered bin lattice for particle simulations.
+//!
+//! Particle codes typically move every particle every step and then ask, for
+//! each one, which others lie within a fixed interaction radius. Updating a
+//! tree or a hash grid point by point is wasted work there; it is cheaper to
+//! throw the structure away and rebuild it from the new positions. A
+//! [`BinLattice`] is built for exactly that. Its bounds are cut into a dense
+//! array of cubic bins, and [`BinLattice::rebuild`] counting-sorts all the
+//! positions into bin order in two passes.
+//!
+//! Each bin carries a frame stamp. A bin whose stamp is stale simply counts
+//! as empty, so a rebuild touches only the bins that gain particles. It
+//! never sweeps the whole lattice, which pays off when the particles fill
+//! only a few of the bins. Positions are stored as a structure of arrays,
+//! one coordinate slice per axis, in bin order. The particles of a bin and
+//! of its row-neighbors are then contiguous in memory.
+//!
+//! There are two buffers. A rebuild writes the back one and then swaps
+//! them, so the previous frame stays queryable through
+//! [`BinLattice::previous`]. That is handy for integrators that need last
+//! step's neighborhoods, and for readers that lag a frame behind the
+//! writer.
+//!
+//! Particles are identified by their index in the slice passed to
+//! `rebuild`. Positions outside the bounds are filed in the nearest border
+//! bin, which keeps queries exact at some cost in speed.
+
+use crate::geom::Aabb;
+
+/// The fixed geometry shared by both buffers.
+#[derive(Clone, Copy, Debug)]
+struct Layout<const N: usize> {
+    bounds: Aabb<N>,
+    cell_size: f64,
+    dims: [usize; N],
+    strides: [usize; N],
+}
+
+impl<const N: usize> Layout<N> {
+    fn coord(&self, axis: usize, x: f64) -> usize {
+        let t = ((x - self.bounds.min[axis]) / self.cell_size).floor();
+        t.clamp(0.0, (self.dims[axis] - 1) as f64) as usize
+    }
+
+    fn bin(&self, p: &[f64; N]) -> usize {
+        (0..N).map(|a| self.coord(a, p[a]) * self.strides[a]).sum()
+    }
+
+    /// The inclusive range of bin coordinates overlapping the ball.
+    fn range(&self, p: &[f64; N], radius: f64) -> ([usize; N], [usize; N]) {
+        (
+            std::array::from_fn(|a| self.coord(a, p[a] - radius)),
+            std::array::from_fn(|a| self.coord(a, p[a] + radius)),
+        )
+    }
+
+    fn index(&self, cell: &[usize; N]) -> usize {
+        (0..N).map(|a| cell[a] * self.strides[a]).sum()
+    }
+}
+
+/// Steps `cell` through the box from `lo` to `hi`, axis 0 fastest, returning
+/// false once it wraps around.
+fn advance<const N: usize>(cell: &mut [usize; N], lo: &[usize; N], hi: &[usize; N]) -> bool {
+    for a in 0..N {
+        if cell[a] < hi[a] {
+            cell[a] += 1;
+            return true;
+        }
+        cell[a] = lo[a];
+    }
+    false
+}
+
+/// One frame's worth of sorted particles.
+#[derive(Clone, Debug)]
+struct Buffer<const N: usize> {
+    /// The frame this buffer was built in, or zero if never.
+    frame: u64,
+    /// Stamp compared against `stamps`; wraps independently of `frame`.
+    stamp: u32,
+    /// Per bin: the stamp of the last build that filled it.
+    stamps: Vec<u32>,
+    /// Per bin: the first slot, valid while the bin's stamp is current.
+    start: Vec<u32>,
+    /// Per bin: the number of slots, valid while the bin's stamp is current.
+    count: Vec<u32>,
+    /// The bins filled in this frame, ascending.
+    occupied: Vec<u32>,
+    /// Per axis, the coordinate of each slot.
+    coords: [Vec<f64>; N],
+    /// Slot to particle index.
+    ids: Vec<u32>,
+    /// Particle index to slot.
+    slots: Vec<u32>,
+    /// Particle index to bin, used while building.
+    bins: Vec<u32>,
+}
+
+impl<const N: usize> Buffer<N> {
+    fn new(bins: usize) -> Self {
+        Buffer {
+            frame: 0,
+            stamp: 0,
+            stamps: vec![0; bins],
+            start: vec![0; bins],
+            count: vec![0; bins],
+            occupied: Vec::new(),
+            coords: std::array::from_fn(|_| Vec::new()),
+            ids: Vec::new(),
+            slots: Vec::new(),
+            bins: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, layout: &Layout<N>, frame: u64, positions: &[[f64; N]]) {
+        self.frame = frame;
+        self.stamp = self.stamp.wrapping_add(1);
+        if self.stamp == 0 {
+            // Crossing zero could revive bins stamped a full cycle ago.
+            self.stamps.fill(0);
+            self.stamp = 1;
+        }
+        let stamp = self.stamp;
+        self.occupied.clear();
+        self.bins.clear();
+        for p in positions {
+            let b = layout.bin(p);
+            if self.stamps[b] != stamp {
+                self.stamps[b] = stamp;
+                self.count[b] = 0;
+                self.occupied.push(b as u32);
+            }
+            self.count[b] += 1;
+            self.bins.push(b as u32);
+        }
+        self.occupied.sort_unstable();
+        let mut at = 0;
+        for &b in &self.occupied {
+            self.start[b as usize] = at;
+            at += self.count[b as usize];
+            self.count[b as usize] = 0;
+        }
+        let n = positions.len();
+        for axis in &mut self.coords {
+            axis.resize(n, 0.0);
+        }
+        self.ids.resize(n, 0);
+        self.slots.resize(n, 0);
+        for (i, p) in positions.iter().enumerate() {
+            let b = self.bins[i] as usize;
+            let s = (self.start[b] + self.count[b]) as usize;
+            self.count[b] += 1;
+            for (axis, &x) in self.coords.iter_mut().zip(p) {
+                axis[s] = x;
+            }
+            self.ids[s] = i as u32;
+            self.slots[i] = s as u32;
+        }
+    }
+
+    /// The slots of bin `b`, empty unless it was filled in this frame.
+    fn slots_of(&self, b: usize) -> std::ops::Range<usize> {
+        if self.stamps[b] == self.stamp && self.frame != 0 {
+            let start = self.start[b] as usize;
+            start..start + self.count[b] as usize
+        } else {
+            0..0
+        }
+    }
+
+    fn distance_squared(&self, s: usize, p: &[f64; N]) -> f64 {
+        (0..N).map(|a| (self.coords[a][s] - p[a]).powi(2)).sum()
+    }
+
+    fn point(&self, s: usize) -> [f64; N] {
+        std::array::from_fn(|a| self.coords[a][s])
+    }
+}
+
+/// A dense lattice of bins over fixed bounds in `N` dimensions, rebuilt
+/// wholesale from particle positions every frame.
+#[derive(Clone, Debug)]
+pub struct BinLattice<const N: usize> {
+    layout: Layout<N>,
+    frame: u64,
+    front: Buffer<N>,
+    back: Buffer<N>,
+}
+
+/// A read-only view of one frame of a [`BinLattice`].
+#[derive(Clone, Copy, Debug)]
+pub struct BinFrame<'a, const N: usize> {
+    layout: &'a Layout<N>,
+    buffer: &'a Buffer<N>,
+}
+
+impl<const N: usize> BinLattice<N> {
+    /// Creates an empty lattice of cubic bins with edge length `cell_size`
+    /// covering `bounds`.
+    ///
+    /// Neighbor queries are fastest when the cell size is close to the
+    /// interaction radius.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `cell_size` is positive and finite and `bounds` is
+    /// finite and non-empty, or if the lattice would need more than
+    /// `u32::MAX` bins.
+    pub fn new(bounds: Aabb<N>, cell_size: f64) -> Self {
+        assert!(
+            cell_size > 0.0 && cell_size.is_finite(),
+            "cell size must be positive and finite"
+        );
+        assert!(
+            (0..N).all(|a| bounds.min[a].is_finite()
+                && bounds.max[a].is_finite()
+                && bounds.min[a] <= bounds.max[a]),
+            "bounds must be finite and non-empty"
+        );
+        let dims: [usize; N] = std::array::from_fn(|a| {
+            (((bounds.max[a] - bounds.min[a]) / cell_size).ceil() as usize).max(1)
+        });
+        let mut strides = [0; N];
+        let mut bins: usize = 1;
+        for a in 0..N {
+            strides[a] = bins;
+            bins = bins
+                .checked_mul(dims[a])
+                .filter(|&b| b <= u32::MAX as usize)
+                .expect("too many bins");
+        }
+        BinLattice {
+            layout: Layout {
+                bounds,
+                cell_size,
+                dims,
+                strides,
+            },
+            frame: 0,
+            front: Buffer::new(bins),
+            back: Buffer::new(bins),
+        }
+    }
+
+    /// The region the bins cover.
+    pub fn bounds(&self) -> &Aabb<N> {
+        &self.layout.bounds
+    }
+
+    /// The edge length of each bin.
+    pub fn cell_size(&self) -> f64 {
+        self.layout.cell_size
+    }
+
+    /// Number of bins along each axis.
+    pub fn dims(&self) -> [usize; N] {
+        self.layout.dims
+    }
+
+    /// The bin containing `p`, clamped to the lattice.
+    pub fn bin_of(&self, p: &[f64; N]) -> [usize; N] {
+        std::array::from_fn(|a| self.layout.coord(a, p[a]))
+    }
+
+    /// Number of rebuilds so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Replaces the current frame with `positions`, keeping the frame it
+    /// replaces as the previous one. Particle `i` is `positions[i]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` positions.
+    pub fn rebuild(&mut self, positions: &[[f64; N]]) {
+        assert!(
+            positions.len() <= u32::MAX as usize,
+            "too many particles for one lattice"
+        );
+        self.frame += 1;
+        self.back.build(&self.layout, self.frame, positions);
+        std::mem::swap(&mut self.front, &mut self.back);
+    }
+
+    /// The most recently built frame.
+    pub fn current(&self) -> BinFrame<'_, N> {
+        BinFrame {
+            layout: &self.layout,
+            buffer: &self.front,
+        }
+    }
+
+    /// The frame built before the current one; empty until the second
+    /// rebuild.
+    pub fn previous(&self) -> BinFrame<'_, N> {
+        BinFrame {
+            layout: &self.layout,
+            buffer: &self.back,
+        }
+    }
+
+    /// Number of particles in the current frame.
+    pub fn len(&self) -> usize {
+        self.current().len()
+    }
+
+    /// Whether the current frame holds no particles.
+    pub fn is_empty(&self) -> bool {
+        self.current().is_empty()
+    }
+
+    /// The particles of the current frame within `radius` of `p`; see
+    /// [`BinFrame::neighbors`].
+    pub fn neighbors(&self, p: &[f64; N], radius: f64) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.current().neighbors(p, radius)
+    }
+
+    /// Calls `f` on every pair of particles of the current frame within
+    /// `radius` of each other; see [`BinFrame::for_each_pair`].
+    pub fn for_each_pair(&self, radius: f64, f: impl FnMut(usize, usize, f64)) {
+        self.current().for_each_pair(radius, f)
+    }
+}
+
+impl<'a, const N: usize> BinFrame<'a, N> {
+    /// The rebuild that produced this frame, counting from one, or zero if
+    /// the buffer has never been built.
+    pub fn frame(&self) -> u64 {
+        self.buffer.frame
+    }
+
+    /// Number of particles.
+    pub fn len(&self) -> usize {
+        self.buffer.ids.len()
+    }
+
+    /// Whether the frame holds no particles.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.ids.is_empty()
+    }
+
+    /// The position of particle `i`.
+    pub fn position(&self, i: usize) -> Option<[f64; N]> {
+        let &s = self.buffer.slots.get(i)?;
+        Some(self.buffer.point(s as usize))
+    }
+
+    /// Per axis, the particle coordinates in bin order, parallel to
+    /// [`BinFrame::order`].
+    pub fn coords(&self) -> [&'a [f64]; N] {
+        std::array::from_fn(|a| &self.buffer.coords[a][..])
+    }
+
+    /// The particle indices in bin order.
+    pub fn order(&self) -> &'a [u32] {
+        &self.buffer.ids
+    }
+
+    /// The particles filed in bin `cell`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cell` lies outside the lattice.
+    pub fn bin_items(&self, cell: [usize; N]) -> impl Iterator<Item = usize> + 'a {
+        assert!(
+            (0..N).all(|a| cell[a] < self.layout.dims[a]),
+            "bin outside the lattice"
+        );
+        let ids = &self.buffer.ids;
+        self.buffer
+            .slots_of(self.layout.index(&cell))
+            .map(move |s| ids[s] as usize)
+    }
+
+    /// Iterates over the particles within `radius` of `p`, in bin order,
+    /// with their squared distances.
+    pub fn neighbors(&self, p: &[f64; N], radius: f64) -> impl Iterator<Item = (usize, f64)> + 'a {
+        let (layout, buffer) = (self.layout, self.buffer);
+        let p = *p;
+        let r2 = radius * radius;
+        let (lo, hi) = layout.range(&p, radius);
+        let mut cell = lo;
+        let mut done = radius.is_nan() || radius < 0.0;
+        let mut slots = 0..0;
+        std::iter::from_fn(move || loop {
+            for s in slots.by_ref() {
+                let d2 = buffer.distance_squared(s, &p);
+                if d2 <= r2 {
+                    return Some((buffer.ids[s] as usize, d2));
+                }
+            }
+            if done {
+                return None;
+            }
+            slots = buffer.slots_of(layout.index(&cell));
+            done = !advance(&mut cell, &lo, &hi);
+        })
+    }
+
+    /// Calls `f(i, j, d2)` once for every pair of distinct particles `i`
+    /// and `j` within `radius` of each other, where `d2` is their squared
+    /// distance. The order of pairs, and of the two particles within a
+    /// pair, is unspecified.
+    pub fn for_each_pair(&self, radius: f64, mut f: impl FnMut(usize, usize, f64)) {
+        if radius.is_nan() || radius < 0.0 {
+            return;
+        }
+        let (layout, buffer) = (self.layout, self.buffer);
+        let r2 = radius * radius;
+        for &own in &buffer.occupied {
+            for s in buffer.slots_of(own as usize) {
+                let p = buffer.point(s);
+                let (lo, hi) = layout.range(&p, radius);
+                let mut cell = lo;
+                loop {
+                    // Slots are in bin order, so each pair is seen once from
+                    // its lower slot by scanning only bins from its own on.
+                    let b = layout.index(&cell);
+                    if b >= own as usize {
+                        for t in buffer.slots_of(b).filter(|&t| t > s) {
+                            let d2 = buffer.distance_squared(t, &p);
+                            if d2 <= r2 {
+                                f(buffer.ids[s] as usize, buffer.ids[t] as usize, d2);
+                            }
+                        }
+                    }
+                    if !advance(&mut cell, &lo, &hi) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! A succinct bit vector with constant-time rank and fast select.
+//!
+//! `rank1(i)`, the number of ones before position `i`, and `select1(k)`,
+//! the position of the `k`th one, are the primitives most compressed
+//! structures are built from: wavelet trees, Elias–Fano sequences, FM
+//! indexes and succinct trees all reduce their queries to them. Answering
+//! them by scanning costs `O(n)`; a directory of precomputed counts makes
+//! them fast for a small fraction of extra space.
+//!
+//! The directory here has two levels, after Jacobson (1989) and González
+//! et al. (2005). Every 2^16 bits a superblock records the ones before it
+//! in a `u64`, and every 512 bits a block records the ones since its
+//! superblock in a `u16`. A rank adds the two and counts the rest of at
+//! most eight words with `count_ones`, in constant time, for 3.2% extra
+//! space. Select samples the block holding every 4096th one, or zero,
+//! binary searches the blocks between two samples, and finishes within a
+//! block by popcounts; the samples add at most 0.8% between `select1` and
+//! `select0`.
+//!
+//! Bit vectors are immutable once built; construct one from an iterator
+//! of `bool`s or from packed words.
+
+/// Bits per block.
+const BLOCK: usize = 512;
+/// Bits per superblock.
+const SUPERBLOCK: usize = 1 << 16;
+/// Ones, or zeros, between select samples.
+const SAMPLE: usize = 4096;
+
+/// An immutable bit vector supporting rank and select.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitVector {
+    words: Vec<u64>,
+    len: usize,
+    ones: usize,
+    /// Ones before each superblock.
+    superblocks: Vec<u64>,
+    /// Ones before each block, since its superblock.
+    blocks: Vec<u16>,
+    /// The block holding every `SAMPLE`th one.
+    select1: Vec<u32>,
+    /// The block holding every `SAMPLE`th zero.
+    select0: Vec<u32>,
+}
+
+impl BitVector {
+    /// Builds a bit vector of the first `len` bits of `words`, least
+    /// significant bit first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `words` holds fewer than `len` bits.
+    pub fn from_words(mut words: Vec<u64>, len: usize) -> Self {
+        assert!(words.len() * 64 >= len, "fewer than len bits");
+        words.truncate(len.div_ceil(64));
+        if !len.is_multiple_of(64) {
+            *words.last_mut().expect("nonempty") &= (1 << (len % 64)) - 1;
+        }
+        let block_count = len.div_ceil(BLOCK);
+        let mut superblocks = Vec::with_capacity(len.div_ceil(SUPERBLOCK));
+        let mut blocks = Vec::with_capacity(block_count);
+        let (mut total, mut since_super) = (0u64, 0u64);
+        for b in 0..block_count {
+            if b % (SUPERBLOCK / BLOCK) == 0 {
+                superblocks.push(total);
+                since_super = 0;
+            }
+            blocks.push(since_super as u16);
+            let ones: u64 = words[b * 8..((b + 1) * 8).min(words.len())]
+                .iter()
+                .map(|w| u64::from(w.count_ones()))
+                .sum();
+            total += ones;
+            since_super += ones;
+        }
+        let mut bv = BitVector {
+            words,
+            len,
+            ones: total as usize,
+            superblocks,
+            blocks,
+            select1: Vec::new(),
+            select0: Vec::new(),
+        };
+        let (mut next1, mut next0) = (0, 0);
+        for b in 0..block_count {
+            let end1 = bv.block_rank(b + 1);
+            let end0 = ((b + 1) * BLOCK).min(len) - end1;
+            while next1 < end1 {
+                bv.select1.push(b as u32);
+                next1 += SAMPLE;
+            }
+            while next0 < end0 {
+                bv.select0.push(b as u32);
+                next0 += SAMPLE;
+            }
+        }
+        bv
+    }
+
+    /// Number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of ones.
+    pub fn count_ones(&self) -> usize {
+        self.ones
+    }
+
+    /// Number of zeros.
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.ones
+    }
+
+    /// The packed bits, least significant first.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// The bit at `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> bool {
+        assert!(
+            i < self.len,
+            "index {i} out of bounds for length {}",
+            self.len
+        );
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    /// Iterates over the bits.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.words[i / 64] >> (i % 64) & 1 == 1)
+    }
+
+    /// Number of ones before position `i`, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is greater than the length.
+    pub fn rank1(&self, i: usize) -> usize {
+        assert!(
+            i <= self.len,
+            "rank {i} out of bounds for length {}",
+            self.len
+        );
+        let block = i / BLOCK;
+        if block == self.blocks.len() {
+            return self.ones;
+        }
+        let mut rank = self.block_rank(block);
+        let word = i / 64;
+        for w in &self.words[block * 8..word] {
+            rank += w.count_ones() as usize;
+        }
+        if !i.is_multiple_of(64) {
+            rank += (self.words[word] & ((1 << (i % 64)) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+
+    /// Number of zeros before position `i`, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is greater than the length.
+    pub fn rank0(&self, i: usize) -> usize {
+        i - self.rank1(i)
+    }
+
+    /// The position of the one with `k` ones before it, or `None` if there
+    /// are `k` ones or fewer.
+    pub fn select1(&self, k: usize) -> Option<usize> {
+        if k >= self.ones {
+            return None;
+        }
+        let block = self.select_block(k, &self.select1, |b| self.block_rank(b));
+        let mut remaining = k - self.block_rank(block);
+        for (i, &w) in self.words[block * 8..].iter().enumerate() {
+            let ones = w.count_ones() as usize;
+            if remaining < ones {
+                return Some((block * 8 + i) * 64 + select_in_word(w, remaining));
+            }
+            remaining -= ones;
+        }
+        unreachable!("the directory counts every one")
+    }
+
+    /// The position of the zero with `k` zeros before it, or `None` if
+    /// there are `k` zeros or fewer.
+    pub fn select0(&self, k: usize) -> Option<usize> {
+        if k >= self.count_zeros() {
+            return None;
+        }
+        let zeros_before = |b: usize| b * BLOCK - self.block_rank(b);
+        let block = self.select_block(k, &self.select0, zeros_before);
+        let mut remaining = k - zeros_before(block);
+        for (i, &w) in self.words[block * 8..].iter().enumerate() {
+            let zeros = w.count_zeros() as usize;
+            if remaining < zeros {
+                return Some((block * 8 + i) * 64 + select_in_word(!w, remaining));
+            }
+            remaining -= zeros;
+        }
+        unreachable!("the directory counts every zero")
+    }
+
+    /// Ones before block `b`.
+    fn block_rank(&self, b: usize) -> usize {
+        if b == self.blocks.len() {
+            return self.ones;
+        }
+        self.superblocks[b * BLOCK / SUPERBLOCK] as usize + usize::from(self.blocks[b])
+    }
+
+    /// The last block with at most `k` matching bits before it, given a
+    /// count of those bits before each block.
+    fn select_block(&self, k: usize, samples: &[u32], before: impl Fn(usize) -> usize) -> usize {
+        let lo = samples[k / SAMPLE] as usize;
+        let hi = samples
+            .get(k / SAMPLE + 1)
+            .map_or(self.blocks.len(), |&b| b as usize + 1);
+        // The first block in `lo..hi` with more than `k` bits before it,
+        // less one.
+        let (mut lo, mut hi) = (lo, hi);
+        while hi - lo > 1 {
+            let mid = (lo + hi) / 2;
+            if before(mid) <= k {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+}
+
+/// The position of the one bit with `k` ones below it in `w`.
+fn select_in_word(mut w: u64, mut k: usize) -> usize {
+    let mut base = 0;
+    // Skip whole bytes, then clear the low ones.
+    loop {
+        let ones = (w & 0xff).count_ones() as usize;
+        if k < ones {
+            break;
+        }
+        k -= ones;
+        w >>= 8;
+        base += 8;
+    }
+    for _ in 0..k {
+        w &= w - 1;
+    }
+    base + w.trailing_zeros() as usize
+}
+
+impl FromIterator<bool> for BitVector {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut words = Vec::new();
+        let mut len = 0;
+        for bit in iter {
+            if len % 64 == 0 {
+                words.push(0);
+            }
+            if bit {
+                *words.last_mut().expect("pushed") |= 1 << (len % 64);
+            }
+            len += 1;
+        }
+        BitVector::from_words(words, len)
+    }
+}
@@ -0,0 +1,351 @@
+//! A growable bitset over `usize` members.
+//!
+//! The set stores one bit per possible member in a vector of `u64` words,
+//! growing as larger members are inserted. Membership is one shift and
+//! mask; union, intersection, difference and symmetric difference combine
+//! whole words, 64 members per instruction, and counting is one
+//! `count_ones` per word. Iteration finds each member with
+//! `trailing_zeros` and clears it from a copy of the word, so its cost
+//! follows the number of members plus the number of words, not the span
+//! of the bits.
+//!
+//! Unlike the immutable [`BitVector`], the set is freely editable; its
+//! [`rank`](BitSet::rank) scans the words below the position, so it costs
+//! `O(n / 64)` rather than `O(1)`. The orthtree reports which children of
+//! a cell hold points as a bitset, from
+//! [`Orthtree::occupancy`](crate::orthtree::Orthtree::occupancy).
+//!
+//! [`BitVector`]: crate::bit_vector::BitVector
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Sub, SubAssign};
+
+const BITS: usize = u64::BITS as usize;
+
+/// A set of `usize`, stored as a bitmap.
+#[derive(Clone, Default)]
+pub struct BitSet {
+    /// Bit `i % 64` of word `i / 64` is set when `i` is a member; words
+    /// past the last member may be zero.
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set with room for members below `bits` without
+    /// growing.
+    pub fn with_capacity(bits: usize) -> Self {
+        BitSet {
+            words: Vec::with_capacity(bits.div_ceil(BITS)),
+        }
+    }
+
+    /// Creates a set from packed words, bit `i % 64` of word `i / 64`
+    /// standing for member `i`.
+    pub fn from_words(words: Vec<u64>) -> Self {
+        BitSet { words }
+    }
+
+    /// The packed words, as for [`from_words`](Self::from_words).
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Number of members, in `O(n / 64)`.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Whether the set has no members.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Removes every member.
+    pub fn clear(&mut self) {
+        self.words.clear();
+    }
+
+    /// Whether `i` is a member.
+    pub fn contains(&self, i: usize) -> bool {
+        self.words
+            .get(i / BITS)
+            .is_some_and(|w| w >> (i % BITS) & 1 == 1)
+    }
+
+    /// Adds `i`, returning whether it was absent.
+    pub fn insert(&mut self, i: usize) -> bool {
+        let w = i / BITS;
+        if w >= self.words.len() {
+            self.words.resize(w + 1, 0);
+        }
+        let bit = 1 << (i % BITS);
+        let absent = self.words[w] & bit == 0;
+        self.words[w] |= bit;
+        absent
+    }
+
+    /// Removes `i`, returning whether it was present.
+    pub fn remove(&mut self, i: usize) -> bool {
+        let Some(word) = self.words.get_mut(i / BITS) else {
+            return false;
+        };
+        let bit = 1 << (i % BITS);
+        let present = *word & bit != 0;
+        *word &= !bit;
+        present
+    }
+
+    /// Adds every member of `start..end`, a word at a time.
+    pub fn insert_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let (first, last) = (start / BITS, (end - 1) / BITS);
+        if last >= self.words.len() {
+            self.words.resize(last + 1, 0);
+        }
+        for w in first..=last {
+            let lo = if w == first { start % BITS } else { 0 };
+            let hi = if w == last {
+                (end - 1) % BITS + 1
+            } else {
+                BITS
+            };
+            self.words[w] |= (u64::MAX >> (BITS - (hi - lo))) << lo;
+        }
+    }
+
+    /// The number of members below `i`.
+    pub fn rank(&self, i: usize) -> usize {
+        let w = (i / BITS).min(self.words.len());
+        let below: usize = self.words[..w]
+            .iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+        let partial = self.words.get(i / BITS).map_or(0, |&word| {
+            (word & ((1 << (i % BITS)) - 1)).count_ones() as usize
+        });
+        below + partial
+    }
+
+    /// The least member.
+    pub fn min(&self) -> Option<usize> {
+        let (w, &word) = self.words.iter().enumerate().find(|(_, &w)| w != 0)?;
+        Some(w * BITS + word.trailing_zeros() as usize)
+    }
+
+    /// The greatest member.
+    pub fn max(&self) -> Option<usize> {
+        let (w, &word) = self.words.iter().enumerate().rfind(|(_, &w)| w != 0)?;
+        Some(w * BITS + (BITS - 1 - word.leading_zeros() as usize))
+    }
+
+    /// Iterates over the members in ascending order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            words: &self.words,
+            next: 0,
+            word: 0,
+        }
+    }
+
+    /// Adds every member of `other`.
+    pub fn union_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a |= b;
+        }
+    }
+
+    /// Removes every member not in `other`.
+    pub fn intersect_with(&mut self, other: &BitSet) {
+        self.words.truncate(other.words.len());
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= b;
+        }
+    }
+
+    /// Removes every member of `other`.
+    pub fn difference_with(&mut self, other: &BitSet) {
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a &= !b;
+        }
+    }
+
+    /// Keeps the members in exactly one of the two sets.
+    pub fn symmetric_difference_with(&mut self, other: &BitSet) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (a, b) in self.words.iter_mut().zip(&other.words) {
+            *a ^= b;
+        }
+    }
+
+    /// The members of either set.
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        let mut out = self.clone();
+        out.union_with(other);
+        out
+    }
+
+    /// The members of both sets.
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        let mut out = self.clone();
+        out.intersect_with(other);
+        out
+    }
+
+    /// The members of `self` not in `other`.
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        let mut out = self.clone();
+        out.difference_with(other);
+        out
+    }
+
+    /// The members of exactly one of the two sets.
+    pub fn symmetric_difference(&self, other: &BitSet) -> BitSet {
+        let mut out = self.clone();
+        out.symmetric_difference_with(other);
+        out
+    }
+
+    /// The number of members of both sets, without building their
+    /// intersection.
+    pub fn intersection_len(&self, other: &BitSet) -> usize {
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a & b).count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether every member of `self` is in `other`.
+    pub fn is_subset(&self, other: &BitSet) -> bool {
+        self.words.iter().enumerate().all(|(i, &a)| {
+            let b = other.words.get(i).copied().unwrap_or(0);
+            a & !b == 0
+        })
+    }
+
+    /// Whether the sets share no member.
+    pub fn is_disjoint(&self, other: &BitSet) -> bool {
+        self.words.iter().zip(&other.words).all(|(a, b)| a & b == 0)
+    }
+
+    /// The words up to the last nonzero one, which determine the set.
+    fn significant(&self) -> &[u64] {
+        let len = self
+            .words
+            .iter()
+            .rposition(|&w| w != 0)
+            .map_or(0, |i| i + 1);
+        &self.words[..len]
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.significant() == other.significant()
+    }
+}
+
+impl Eq for BitSet {}
+
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.significant().hash(state);
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+macro_rules! bit_ops {
+    ($($op:ident $method:ident $assign:ident $assign_method:ident $with:ident;)*) => {$(
+        impl $op<&BitSet> for &BitSet {
+            type Output = BitSet;
+
+            fn $method(self, other: &BitSet) -> BitSet {
+                let mut out = self.clone();
+                out.$with(other);
+                out
+            }
+        }
+
+        impl $assign<&BitSet> for BitSet {
+            fn $assign_method(&mut self, other: &BitSet) {
+                self.$with(other);
+            }
+        }
+    )*};
+}
+
+bit_ops! {
+    BitOr bitor BitOrAssign bitor_assign union_with;
+    BitAnd bitand BitAndAssign bitand_assign intersect_with;
+    BitXor bitxor BitXorAssign bitxor_assign symmetric_difference_with;
+    Sub sub SubAssign sub_assign difference_with;
+}
+
+/// Iterator over the members of a [`BitSet`] in ascending order.
+pub struct Iter<'a> {
+    /// Words not yet loaded.
+    words: &'a [u64],
+    /// The member that bit 0 of the first word of `words` stands for.
+    next: usize,
+    /// The members of the current word not yet yielded.
+    word: u64,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            let (&word, rest) = self.words.split_first()?;
+            self.words = rest;
+            self.word = word;
+            self.next += BITS;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some(self.next - BITS + bit)
+    }
+}
+
+impl<'a> IntoIterator for &'a BitSet {
+    type Item = usize;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl Extend<usize> for BitSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for i in iter {
+            self.insert(i);
+        }
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        set.extend(iter);
+        set
+    }
+}
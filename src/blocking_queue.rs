@@ -0,0 +1,316 @@
+//! A bounded blocking multi-producer multi-consumer queue.
+//!
+//! The queue is a `VecDeque` behind a mutex, with two condition variables:
+//! producers wait on one while the queue is full and consumers on the
+//! other while it is empty, and every push or pop wakes one waiter on the
+//! opposite side. The fixed capacity is the backpressure: a producer that
+//! runs ahead of its consumers blocks, rather than letting the queue grow
+//! without bound.
+//!
+//! Each side comes in three forms: one that blocks until it can proceed,
+//! a `try_` form that never blocks, and a `_timeout` form that gives up
+//! after a while. Closing the queue wakes everyone. Pushes then fail and
+//! hand their value back, while pops drain what is left and only fail
+//! once the queue is empty, so no value that was accepted is lost. That is
+//! the usual way to shut down a pool of workers.
+//!
+//! Where the lock-free [`MpmcQueue`] leaves waiting to the caller, this
+//! queue parks threads in the operating system, which is the better trade
+//! when operations are rare next to the work between them, or when
+//! threads may wait for a long time; it is the core of a bounded channel,
+//! without pulling in a channel crate.
+//!
+//! [`MpmcQueue`]: crate::mpmc::MpmcQueue
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Why a push failed, with the value that was not pushed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PushError<T> {
+    /// The queue was full, for [`BlockingQueue::try_push`].
+    Full(T),
+    /// The queue stayed full until the deadline, for
+    /// [`BlockingQueue::push_timeout`].
+    Timeout(T),
+    /// The queue was closed.
+    Closed(T),
+}
+
+impl<T> PushError<T> {
+    /// The value that was not pushed.
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(v) | PushError::Timeout(v) | PushError::Closed(v) => v,
+        }
+    }
+}
+
+impl<T> fmt::Debug for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PushError::Full(_) => "Full(..)",
+            PushError::Timeout(_) => "Timeout(..)",
+            PushError::Closed(_) => "Closed(..)",
+        })
+    }
+}
+
+impl<T> fmt::Display for PushError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PushError::Full(_) => "queue is full",
+            PushError::Timeout(_) => "timed out waiting for room in the queue",
+            PushError::Closed(_) => "queue is closed",
+        })
+    }
+}
+
+impl<T> Error for PushError<T> {}
+
+/// Why a pop failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopError {
+    /// The queue was empty, for [`BlockingQueue::try_pop`].
+    Empty,
+    /// The queue stayed empty until the deadline, for
+    /// [`BlockingQueue::pop_timeout`].
+    Timeout,
+    /// The queue was closed and empty.
+    Closed,
+}
+
+impl fmt::Display for PopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PopError::Empty => "queue is empty",
+            PopError::Timeout => "timed out waiting for a value",
+            PopError::Closed => "queue is closed and empty",
+        })
+    }
+}
+
+impl Error for PopError {}
+
+struct State<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+/// A fixed-capacity queue that any number of threads can push to and pop
+/// from through a shared reference, waiting when it is full or empty.
+pub struct BlockingQueue<T> {
+    state: Mutex<State<T>>,
+    /// Signalled when a value is pushed, or the queue closed.
+    not_empty: Condvar,
+    /// Signalled when a value is popped, or the queue closed.
+    not_full: Condvar,
+    capacity: usize,
+}
+
+impl<T> BlockingQueue<T> {
+    /// Creates an empty queue holding at most `capacity` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be positive");
+        BlockingQueue {
+            state: Mutex::new(State {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// The most values the queue holds at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of values in the queue; other threads may change it at once.
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    /// Whether the queue holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the queue holds `capacity` values.
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity
+    }
+
+    /// Whether the queue has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock().closed
+    }
+
+    /// Closes the queue, waking every waiting thread. Later pushes fail;
+    /// pops take the values left and then fail. Returns `false` if it was
+    /// already closed.
+    pub fn close(&self) -> bool {
+        let mut state = self.lock();
+        if std::mem::replace(&mut state.closed, true) {
+            return false;
+        }
+        drop(state);
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+        true
+    }
+
+    /// Adds `value` at the back, waiting while the queue is full.
+    ///
+    /// Fails only with [`PushError::Closed`].
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let state = self.lock();
+        let state = self
+            .not_full
+            .wait_while(state, |s| !s.closed && s.items.len() == self.capacity)
+            .unwrap_or_else(PoisonError::into_inner);
+        self.push_locked(state, value)
+    }
+
+    /// Adds `value` at the back if there is room, without waiting.
+    ///
+    /// Fails with [`PushError::Full`] or [`PushError::Closed`].
+    pub fn try_push(&self, value: T) -> Result<(), PushError<T>> {
+        let state = self.lock();
+        if !state.closed && state.items.len() == self.capacity {
+            return Err(PushError::Full(value));
+        }
+        self.push_locked(state, value)
+    }
+
+    /// Adds `value` at the back, waiting at most `timeout` while the queue
+    /// is full.
+    ///
+    /// Fails with [`PushError::Timeout`] or [`PushError::Closed`].
+    pub fn push_timeout(&self, value: T, timeout: Duration) -> Result<(), PushError<T>> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state = self.lock();
+        while !state.closed && state.items.len() == self.capacity {
+            match wait(&self.not_full, state, deadline) {
+                Some(s) => state = s,
+                None => return Err(PushError::Timeout(value)),
+            }
+        }
+        self.push_locked(state, value)
+    }
+
+    /// Removes the front value, waiting while the queue is empty, or
+    /// returns `None` once it is closed and empty.
+    pub fn pop(&self) -> Option<T> {
+        let state = self.lock();
+        let state = self
+            .not_empty
+            .wait_while(state, |s| !s.closed && s.items.is_empty())
+            .unwrap_or_else(PoisonError::into_inner);
+        self.pop_locked(state).ok()
+    }
+
+    /// Removes the front value if there is one, without waiting.
+    ///
+    /// Fails with [`PopError::Empty`] or [`PopError::Closed`].
+    pub fn try_pop(&self) -> Result<T, PopError> {
+        let state = self.lock();
+        if !state.closed && state.items.is_empty() {
+            return Err(PopError::Empty);
+        }
+        self.pop_locked(state)
+    }
+
+    /// Removes the front value, waiting at most `timeout` while the queue
+    /// is empty.
+    ///
+    /// Fails with [`PopError::Timeout`] or [`PopError::Closed`].
+    pub fn pop_timeout(&self, timeout: Duration) -> Result<T, PopError> {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut state = self.lock();
+        while !state.closed && state.items.is_empty() {
+            match wait(&self.not_empty, state, deadline) {
+                Some(s) => state = s,
+                None => return Err(PopError::Timeout),
+            }
+        }
+        self.pop_locked(state)
+    }
+
+    /// Removes every value in the queue, front first, without waiting.
+    pub fn drain(&self) -> Vec<T> {
+        let items: Vec<T> = self.lock().items.drain(..).collect();
+        if !items.is_empty() {
+            self.not_full.notify_all();
+        }
+        items
+    }
+
+    /// Pushes under the lock, once the queue is closed or has room.
+    fn push_locked(
+        &self,
+        mut state: MutexGuard<'_, State<T>>,
+        value: T,
+    ) -> Result<(), PushError<T>> {
+        if state.closed {
+            return Err(PushError::Closed(value));
+        }
+        state.items.push_back(value);
+        drop(state);
+        self.not_empty.notify_one();
+        Ok(())
+    }
+
+    /// Pops under the lock, once the queue is closed or holds a value.
+    fn pop_locked(&self, mut state: MutexGuard<'_, State<T>>) -> Result<T, PopError> {
+        let value = state.items.pop_front().ok_or(PopError::Closed)?;
+        drop(state);
+        self.not_full.notify_one();
+        Ok(value)
+    }
+
+    /// Locks the state, ignoring poisoning: no update leaves it half done.
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> fmt::Debug for BlockingQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("BlockingQueue")
+            .field("len", &state.items.len())
+            .field("capacity", &self.capacity)
+            .field("closed", &state.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Waits on `condvar` until woken or `deadline` passes, returning `None`
+/// if it has passed; no deadline means waiting as long as it takes.
+fn wait<'a, T>(
+    condvar: &Condvar,
+    state: MutexGuard<'a, State<T>>,
+    deadline: Option<Instant>,
+) -> Option<MutexGuard<'a, State<T>>> {
+    let Some(deadline) = deadline else {
+        return Some(condvar.wait(state).unwrap_or_else(PoisonError::into_inner));
+    };
+    let left = deadline.checked_duration_since(Instant::now())?;
+    if left.is_zero() {
+        return None;
+    }
+    let (state, _) = condvar
+        .wait_timeout(state, left)
+        .unwrap_or_else(PoisonError::into_inner);
+    Some(state)
+}
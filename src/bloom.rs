@@ -0,0 +1,440 @@
+//! A Bloom filter: approximate set membership in a few bits per item.
+//!
+//! A Bloom filter (Bloom, 1970) is an array of `m` bits and `k` hash
+//! functions. Inserting an item sets the `k` bits it hashes to; a lookup
+//! reports the item present if all of its bits are set. Lookups never miss
+//! an inserted item, but can report one that was never inserted when other
+//! items happened to set all its bits. With `n` items the chance of that
+//! is about `(1 - e^(-kn/m))^k`, so about 9.6 bits per item give a 1% false
+//! positive rate and every further 4.8 bits divide it by ten, whatever the
+//! items' size.
+//!
+//! [`BloomFilter::new`] sizes the array for an expected number of items and
+//! a target rate: `m = -n ln p / ln² 2` bits and `k = (m / n) ln 2` hashes.
+//! The `k` positions come from one [`StableHasher`] hash by double hashing,
+//! so a filter serialized with [`to_bytes`](BloomFilter::to_bytes) answers
+//! the same way in any process that reads it back.
+//!
+//! Filters with the same size, hash count and seed combine bitwise: the
+//! union holds exactly the bits a filter of both sets would, and the
+//! intersection may report somewhat more false positives than a filter
+//! built from the common items alone.
+//!
+//! Items cannot be removed, since clearing a bit might drop other items
+//! too. [`CountingBloomFilter`] (Fan et al.) replaces each bit with a
+//! 4-bit counter, so removal decrements instead, at four times the space.
+//! A counter that reaches 15 saturates and stays there: further removals
+//! cannot tell how many items share it, and decrementing might later clear
+//! it under an item still present. With a sensibly sized filter a counter
+//! saturates with probability below `10^-15` per counter.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::hash::Hash;
+
+use crate::probabilistic::{self, DecodeError, Incompatible, Reader, DEFAULT_SEED};
+
+const TAG: &[u8; 4] = b"BLM1";
+
+/// The most hash functions a filter probes with. Beyond this each extra
+/// probe costs more time than the bits it saves are worth, and decoding
+/// rejects anything larger so untrusted bytes cannot make every lookup
+/// billions of probes long.
+const MAX_HASHES: u32 = 32;
+
+/// A Bloom filter over items of any `Hash` type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    bits: u64,
+    hashes: u32,
+    seed: u64,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized to hold `expected_items` items with a false
+    /// positive rate of about `false_positive_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `false_positive_rate` is strictly between 0 and 1.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let (bits, hashes) = optimal_size(expected_items, false_positive_rate);
+        Self::with_size(bits, hashes, DEFAULT_SEED)
+    }
+
+    /// Creates a filter of `bits` bits probed by `hashes` hash functions,
+    /// seeded with `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` or `hashes` is zero, or `hashes` is more than 32.
+    pub fn with_size(bits: u64, hashes: u32, seed: u64) -> Self {
+        assert!(bits > 0, "Bloom filter needs at least one bit");
+        assert!(hashes > 0, "Bloom filter needs at least one hash");
+        assert!(hashes <= MAX_HASHES, "Bloom filter takes at most 32 hashes");
+        BloomFilter {
+            words: vec![0; bits.div_ceil(64) as usize],
+            bits,
+            hashes,
+            seed,
+        }
+    }
+
+    /// Number of bits.
+    pub fn bit_count(&self) -> u64 {
+        self.bits
+    }
+
+    /// Number of bits each item sets.
+    pub fn hash_count(&self) -> u32 {
+        self.hashes
+    }
+
+    /// The hash seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Number of set bits.
+    pub fn count_ones(&self) -> u64 {
+        self.words.iter().map(|w| u64::from(w.count_ones())).sum()
+    }
+
+    /// Whether nothing has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Inserts an item. Returns `false` if it was possibly present already,
+    /// that is, if all its bits were set.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        let mut changed = false;
+        for bit in probabilistic::probes(hash, self.hashes, self.bits) {
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            changed |= self.words[word] & mask == 0;
+            self.words[word] |= mask;
+        }
+        changed
+    }
+
+    /// Whether the item may have been inserted. Never `false` for an
+    /// inserted item.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        probabilistic::probes(hash, self.hashes, self.bits)
+            .all(|bit| self.words[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Estimates the number of distinct items inserted from the share of
+    /// set bits (Swamidass and Baldi).
+    pub fn estimated_len(&self) -> f64 {
+        let (m, k) = (self.bits as f64, f64::from(self.hashes));
+        let ones = self.count_ones() as f64;
+        if ones >= m {
+            return f64::INFINITY;
+        }
+        -m / k * (1.0 - ones / m).ln()
+    }
+
+    /// The false positive rate at the current fill, `(ones / m)^k`.
+    pub fn false_positive_rate(&self) -> f64 {
+        (self.count_ones() as f64 / self.bits as f64).powi(self.hashes as i32)
+    }
+
+    /// Whether `other` has the same size, hash count and seed, so the two
+    /// can be combined.
+    pub fn is_compatible(&self, other: &BloomFilter) -> bool {
+        self.bits == other.bits && self.hashes == other.hashes && self.seed == other.seed
+    }
+
+    /// Adds every item of `other` to this filter.
+    pub fn union(&mut self, other: &BloomFilter) -> Result<(), Incompatible> {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Keeps only the bits also set in `other`. The result holds every item
+    /// inserted into both filters.
+    pub fn intersection(&mut self, other: &BloomFilter) -> Result<(), Incompatible> {
+        self.combine(other, |a, b| a & b)
+    }
+
+    fn combine(
+        &mut self,
+        other: &BloomFilter,
+        op: fn(u64, u64) -> u64,
+    ) -> Result<(), Incompatible> {
+        if !self.is_compatible(other) {
+            return Err(Incompatible);
+        }
+        for (a, &b) in self.words.iter_mut().zip(&other.words) {
+            *a = op(*a, b);
+        }
+        Ok(())
+    }
+
+    /// Serializes the filter: a tag, the hash count, seed and bit count,
+    /// then the bits as little-endian 64-bit words.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24 + 8 * self.words.len());
+        bytes.extend_from_slice(TAG);
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.bits.to_le_bytes());
+        for word in &self.words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads back a filter written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, TAG)?;
+        let hashes = reader.u32()?;
+        let seed = reader.u64()?;
+        let bits = reader.u64()?;
+        if hashes == 0 || hashes > MAX_HASHES || bits == 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let len = usize::try_from(bits.div_ceil(64)).map_err(|_| DecodeError::Invalid)?;
+        let data = reader.take(len.checked_mul(8).ok_or(DecodeError::Truncated)?)?;
+        reader.finish()?;
+        let words: Vec<u64> = data
+            .chunks_exact(8)
+            .map(|w| u64::from_le_bytes(w.try_into().expect("chunk of 8")))
+            .collect();
+        // Bits past the end are never set.
+        if bits % 64 != 0 && words[len - 1] >> (bits % 64) != 0 {
+            return Err(DecodeError::Invalid);
+        }
+        Ok(BloomFilter {
+            words,
+            bits,
+            hashes,
+            seed,
+        })
+    }
+}
+
+/// The bit and hash counts that hold `items` items at `rate` false
+/// positives with the fewest bits.
+fn optimal_size(items: usize, rate: f64) -> (u64, u32) {
+    assert!(
+        rate > 0.0 && rate < 1.0,
+        "false positive rate must be strictly between 0 and 1"
+    );
+    let ln2 = std::f64::consts::LN_2;
+    let n = items.max(1) as f64;
+    let bits = (-n * rate.ln() / (ln2 * ln2)).ceil().max(1.0);
+    let hashes = (bits / n * ln2).round().clamp(1.0, f64::from(MAX_HASHES));
+    (bits as u64, hashes as u32)
+}
+
+impl<T: Hash> Extend<T> for BloomFilter {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(&item);
+        }
+    }
+}
+
+const COUNTING_TAG: &[u8; 4] = b"CBF1";
+
+/// The largest value of a counter; it sticks once reached.
+const SATURATED: u64 = 15;
+
+/// A Bloom filter of 4-bit counters, supporting removal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountingBloomFilter {
+    /// Sixteen counters to a word, the first in the low bits.
+    words: Vec<u64>,
+    counters: u64,
+    hashes: u32,
+    seed: u64,
+}
+
+impl CountingBloomFilter {
+    /// Creates a filter sized to hold `expected_items` items with a false
+    /// positive rate of about `false_positive_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `false_positive_rate` is strictly between 0 and 1.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let (counters, hashes) = optimal_size(expected_items, false_positive_rate);
+        Self::with_size(counters, hashes, DEFAULT_SEED)
+    }
+
+    /// Creates a filter of `counters` counters probed by `hashes` hash
+    /// functions, seeded with `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `counters` or `hashes` is zero, or `hashes` is more than
+    /// 32.
+    pub fn with_size(counters: u64, hashes: u32, seed: u64) -> Self {
+        assert!(counters > 0, "Bloom filter needs at least one counter");
+        assert!(hashes > 0, "Bloom filter needs at least one hash");
+        assert!(hashes <= MAX_HASHES, "Bloom filter takes at most 32 hashes");
+        CountingBloomFilter {
+            words: vec![0; counters.div_ceil(16) as usize],
+            counters,
+            hashes,
+            seed,
+        }
+    }
+
+    /// Number of counters.
+    pub fn counter_count(&self) -> u64 {
+        self.counters
+    }
+
+    /// Number of counters each item increments.
+    pub fn hash_count(&self) -> u32 {
+        self.hashes
+    }
+
+    /// The hash seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether every counter is zero.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Number of counters stuck at their maximum.
+    pub fn saturated_count(&self) -> u64 {
+        (0..self.counters)
+            .filter(|&i| self.counter(i) == SATURATED)
+            .count() as u64
+    }
+
+    /// Inserts an item.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        for i in probabilistic::probes(hash, self.hashes, self.counters) {
+            let c = self.counter(i);
+            if c < SATURATED {
+                self.set_counter(i, c + 1);
+            }
+        }
+    }
+
+    /// Removes one insertion of an item. Returns `false`, changing nothing,
+    /// if the item is certainly absent.
+    ///
+    /// Removing an item that was never inserted but reported present, a
+    /// false positive, decrements counters other items rely on and can make
+    /// the filter miss them.
+    pub fn remove<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        if !self.contains(item) {
+            return false;
+        }
+        let hash = probabilistic::stable_hash(self.seed, item);
+        for i in probabilistic::probes(hash, self.hashes, self.counters) {
+            let c = self.counter(i);
+            if c < SATURATED {
+                self.set_counter(i, c - 1);
+            }
+        }
+        true
+    }
+
+    /// Whether the item may have been inserted and not removed.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.count(item) > 0
+    }
+
+    /// An upper bound on how many times the item is present, the smallest
+    /// of its counters. Saturates at 15.
+    pub fn count<T: Hash + ?Sized>(&self, item: &T) -> u32 {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        probabilistic::probes(hash, self.hashes, self.counters)
+            .map(|i| self.counter(i))
+            .min()
+            .unwrap_or(0) as u32
+    }
+
+    /// The plain filter with a bit set wherever a counter is nonzero. It
+    /// answers every lookup the same way as this one.
+    pub fn to_bloom(&self) -> BloomFilter {
+        let mut bloom = BloomFilter::with_size(self.counters, self.hashes, self.seed);
+        for i in (0..self.counters).filter(|&i| self.counter(i) > 0) {
+            bloom.words[(i / 64) as usize] |= 1 << (i % 64);
+        }
+        bloom
+    }
+
+    /// Serializes the filter: a tag, the hash count, seed and counter
+    /// count, then the counters packed sixteen to a little-endian word.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24 + 8 * self.words.len());
+        bytes.extend_from_slice(COUNTING_TAG);
+        bytes.extend_from_slice(&self.hashes.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.counters.to_le_bytes());
+        for word in &self.words {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads back a filter written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, COUNTING_TAG)?;
+        let hashes = reader.u32()?;
+        let seed = reader.u64()?;
+        let counters = reader.u64()?;
+        if hashes == 0 || hashes > MAX_HASHES || counters == 0 {
+            return Err(DecodeError::Invalid);
+        }
+        let len = usize::try_from(counters.div_ceil(16)).map_err(|_| DecodeError::Invalid)?;
+        let data = reader.take(len.checked_mul(8).ok_or(DecodeError::Truncated)?)?;
+        reader.finish()?;
+        let words: Vec<u64> = data
+            .chunks_exact(8)
+            .map(|w| u64::from_le_bytes(w.try_into().expect("chunk of 8")))
+            .collect();
+        // Counters past the end are always zero.
+        if counters % 16 != 0 && words[len - 1] >> (4 * (counters % 16)) != 0 {
+            return Err(DecodeError::Invalid);
+        }
+        Ok(CountingBloomFilter {
+            words,
+            counters,
+            hashes,
+            seed,
+        })
+    }
+
+    fn counter(&self, i: u64) -> u64 {
+        (self.words[(i / 16) as usize] >> (4 * (i % 16))) & 0xf
+    }
+
+    fn set_counter(&mut self, i: u64, value: u64) {
+        let shift = 4 * (i % 16);
+        let word = &mut self.words[(i / 16) as usize];
+        *word = (*word & !(0xf << shift)) | (value << shift);
+    }
+}
+
+impl<T: Hash> Extend<T> for CountingBloomFilter {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(&item);
+        }
+    }
+}
@@ -0,0 +1,670 @@
+//! An ordered map stored as a B+ tree with linked leaves.
+//!
+//! Unlike a [`BTree`], whose interior nodes carry entries of their own, a
+//! B+ tree keeps every entry in its leaves. The interior nodes hold only
+//! copies of keys, used to route searches. Each leaf links to its
+//! neighbors on both sides, so after one descent to the first key of a
+//! range, a scan just walks the leaf chain in either direction. It never
+//! climbs back into the index, and each step touches one contiguous run
+//! of entries.
+//!
+//! Interior nodes and leaves live in separate arenas and address each
+//! other by index. A node's level tells which arena its children are in,
+//! since all leaves sit at the same depth. There are no parent links:
+//! updates record their path on the way down. Every node refers to others
+//! only by number, so the layout maps directly onto fixed-size pages for
+//! a future on-disk version.
+//!
+//! [`BTree`]: crate::btree::BTree
+
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Leaf<K, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+    prev: u32,
+    next: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Interior<K> {
+    /// `keys[i]` is at most every key under `children[i + 1]` and above
+    /// every key under `children[i]`.
+    keys: Vec<K>,
+    children: Vec<u32>,
+}
+
+/// An ordered map from keys of type `K` to values of type `V`, with all
+/// entries in a doubly linked chain of leaves.
+#[derive(Clone, Debug)]
+pub struct BPlusTree<K, V> {
+    leaves: Vec<Leaf<K, V>>,
+    free_leaves: Vec<u32>,
+    interiors: Vec<Interior<K>>,
+    free_interiors: Vec<u32>,
+    /// The root: a leaf when `height` is 1, an interior node when greater.
+    root: u32,
+    /// Number of levels, leaves included; zero for an empty tree.
+    height: usize,
+    /// First and last leaves of the chain.
+    head: u32,
+    tail: u32,
+    len: usize,
+    order: usize,
+}
+
+impl<K, V> Default for BPlusTree<K, V> {
+    fn default() -> Self {
+        BPlusTree {
+            leaves: Vec::new(),
+            free_leaves: Vec::new(),
+            interiors: Vec::new(),
+            free_interiors: Vec::new(),
+            root: NONE,
+            height: 0,
+            head: NONE,
+            tail: NONE,
+            len: 0,
+            order: 32,
+        }
+    }
+}
+
+impl<K: Ord + Clone, V> BPlusTree<K, V> {
+    /// Creates an empty tree of order 32.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty tree whose interior nodes have at most `order`
+    /// children and whose leaves hold at most `order` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is less than 3.
+    pub fn with_order(order: usize) -> Self {
+        assert!(order >= 3, "B+ tree order must be at least 3");
+        BPlusTree {
+            order,
+            ..Self::default()
+        }
+    }
+
+    /// Builds a tree of the given order from entries sorted by strictly
+    /// increasing key, in `O(n)`, with every node filled evenly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is less than 3 or the keys are not strictly
+    /// increasing.
+    pub fn bulk_load(order: usize, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut tree = Self::with_order(order);
+        let (keys, vals): (Vec<K>, Vec<V>) = entries.into_iter().unzip();
+        assert!(
+            keys.windows(2).all(|w| w[0] < w[1]),
+            "bulk-loaded keys must be strictly increasing"
+        );
+        if keys.is_empty() {
+            return tree;
+        }
+        tree.len = keys.len();
+        // Each level is a list of subtrees with the smallest key under each.
+        let mut level: Vec<(K, u32)> = Vec::new();
+        let mut keys = keys.into_iter();
+        let mut vals = vals.into_iter();
+        for size in even_groups(tree.len, order) {
+            let leaf = Leaf {
+                keys: keys.by_ref().take(size).collect(),
+                vals: vals.by_ref().take(size).collect(),
+                prev: tree.tail,
+                next: NONE,
+            };
+            let first = leaf.keys[0].clone();
+            let n = tree.alloc_leaf(leaf);
+            match tree.tail {
+                NONE => tree.head = n,
+                t => tree.leaves[t as usize].next = n,
+            }
+            tree.tail = n;
+            level.push((first, n));
+        }
+        tree.height = 1;
+        while level.len() > 1 {
+            let mut subtrees = level.into_iter();
+            let mut up = Vec::new();
+            for size in even_groups(subtrees.len(), order) {
+                let group: Vec<(K, u32)> = subtrees.by_ref().take(size).collect();
+                let mut group = group.into_iter();
+                let (first, c0) = group.next().expect("groups are non-empty");
+                let (keys, rest): (Vec<K>, Vec<u32>) = group.unzip();
+                let mut children = vec![c0];
+                children.extend(rest);
+                up.push((first, tree.alloc_interior(Interior { keys, children })));
+            }
+            level = up;
+            tree.height += 1;
+        }
+        tree.root = level[0].1;
+        tree
+    }
+
+    /// The maximum number of children per interior node and entries per
+    /// leaf.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of node levels, leaves included; zero for an empty tree.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        *self = BPlusTree {
+            order: self.order,
+            ..Self::default()
+        };
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let leaf = &self.leaves[self.leaf_for(key)? as usize];
+        let i = leaf.keys.binary_search_by(|k| k.borrow().cmp(key)).ok()?;
+        Some(&leaf.vals[i])
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let n = self.leaf_for(key)?;
+        let leaf = &mut self.leaves[n as usize];
+        let i = leaf.keys.binary_search_by(|k| k.borrow().cmp(key)).ok()?;
+        Some(&mut leaf.vals[i])
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let leaf = self.leaves.get(self.head as usize)?;
+        Some((leaf.keys.first()?, leaf.vals.first()?))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let leaf = self.leaves.get(self.tail as usize)?;
+        Some((leaf.keys.last()?, leaf.vals.last()?))
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if self.height == 0 {
+            let n = self.alloc_leaf(Leaf {
+                keys: vec![key],
+                vals: vec![value],
+                prev: NONE,
+                next: NONE,
+            });
+            (self.root, self.head, self.tail, self.height, self.len) = (n, n, n, 1, 1);
+            return None;
+        }
+        let (path, n) = self.descend(&key);
+        let leaf = &mut self.leaves[n as usize];
+        let i = match leaf.keys.binary_search(&key) {
+            Ok(i) => return Some(std::mem::replace(&mut leaf.vals[i], value)),
+            Err(i) => i,
+        };
+        leaf.keys.insert(i, key);
+        leaf.vals.insert(i, value);
+        self.len += 1;
+        if leaf.keys.len() <= self.order {
+            return None;
+        }
+        // Split the leaf, then push separators up the path while nodes
+        // overflow.
+        let mid = leaf.keys.len() / 2;
+        let right = Leaf {
+            keys: leaf.keys.split_off(mid),
+            vals: leaf.vals.split_off(mid),
+            prev: n,
+            next: leaf.next,
+        };
+        let mut sep = right.keys[0].clone();
+        let r = self.alloc_leaf(right);
+        match self.leaves[r as usize].next {
+            NONE => self.tail = r,
+            next => self.leaves[next as usize].prev = r,
+        }
+        self.leaves[n as usize].next = r;
+        let mut right = r;
+        for &(p, ci) in path.iter().rev() {
+            let node = &mut self.interiors[p as usize];
+            node.keys.insert(ci, sep);
+            node.children.insert(ci + 1, right);
+            if node.children.len() <= self.order {
+                return None;
+            }
+            let mid = node.keys.len() / 2;
+            let keys = node.keys.split_off(mid + 1);
+            let children = node.children.split_off(mid + 1);
+            sep = node.keys.pop().expect("overfull node has a median");
+            right = self.alloc_interior(Interior { keys, children });
+        }
+        // The root split: grow a level.
+        let root = self.alloc_interior(Interior {
+            keys: vec![sep],
+            children: vec![self.root, right],
+        });
+        self.root = root;
+        self.height += 1;
+        None
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if self.height == 0 {
+            return None;
+        }
+        let (path, n) = self.descend(key);
+        let leaf = &mut self.leaves[n as usize];
+        let i = leaf.keys.binary_search_by(|k| k.borrow().cmp(key)).ok()?;
+        let entry = (leaf.keys.remove(i), leaf.vals.remove(i));
+        self.len -= 1;
+        self.rebalance_leaf(&path, n);
+        Some(entry)
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Iterates over the entries in key order, from either end.
+    pub fn iter(&self) -> Range<'_, K, V> {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order,
+    /// from either end.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let empty = Range {
+            tree: self,
+            front: (self.head, 0),
+            back: (self.head, 0),
+        };
+        if self.height == 0 {
+            return empty;
+        }
+        let front = self.locate(range.start_bound(), false);
+        let back = self.locate(range.end_bound(), true);
+        let (leaf, i) = front;
+        let first = self.leaves[leaf as usize].keys.get(i);
+        let inside = first.is_some_and(|k| match range.end_bound() {
+            Bound::Included(b) => k.borrow() <= b,
+            Bound::Excluded(b) => k.borrow() < b,
+            Bound::Unbounded => true,
+        });
+        if !inside {
+            return empty;
+        }
+        Range {
+            tree: self,
+            front,
+            back,
+        }
+    }
+
+    /// The leaf that would hold `key`.
+    fn leaf_for<Q>(&self, key: &Q) -> Option<u32>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if self.height == 0 {
+            return None;
+        }
+        let mut n = self.root;
+        for _ in 1..self.height {
+            let node = &self.interiors[n as usize];
+            n = node.children[node.keys.partition_point(|s| s.borrow() <= key)];
+        }
+        Some(n)
+    }
+
+    /// The path of `(interior, child index)` steps to the leaf that would
+    /// hold `key`, and that leaf.
+    fn descend<Q>(&self, key: &Q) -> (Vec<(u32, usize)>, u32)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut path = Vec::with_capacity(self.height);
+        let mut n = self.root;
+        for _ in 1..self.height {
+            let node = &self.interiors[n as usize];
+            let ci = node.keys.partition_point(|s| s.borrow() <= key);
+            path.push((n, ci));
+            n = node.children[ci];
+        }
+        (path, n)
+    }
+
+    /// The position of the first entry past a range's start bound, or, with
+    /// `end` set, past its end bound; in the canonical form of [`Range`].
+    fn locate<Q>(&self, bound: Bound<&Q>, end: bool) -> (u32, usize)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (n, i) = match (bound, end) {
+            (Bound::Unbounded, false) => (self.head, 0),
+            (Bound::Unbounded, true) => (self.tail, self.leaves[self.tail as usize].keys.len()),
+            (Bound::Included(b), false) | (Bound::Excluded(b), true) => {
+                let n = self.leaf_for(b).expect("tree is non-empty");
+                (
+                    n,
+                    self.leaves[n as usize]
+                        .keys
+                        .partition_point(|k| k.borrow() < b),
+                )
+            }
+            (Bound::Excluded(b), false) | (Bound::Included(b), true) => {
+                let n = self.leaf_for(b).expect("tree is non-empty");
+                (
+                    n,
+                    self.leaves[n as usize]
+                        .keys
+                        .partition_point(|k| k.borrow() <= b),
+                )
+            }
+        };
+        self.canonical((n, i))
+    }
+
+    /// Moves a position at the end of a leaf to the start of the next one,
+    /// so that every gap between entries has one name.
+    fn canonical(&self, (n, i): (u32, usize)) -> (u32, usize) {
+        let leaf = &self.leaves[n as usize];
+        if i == leaf.keys.len() && leaf.next != NONE {
+            (leaf.next, 0)
+        } else {
+            (n, i)
+        }
+    }
+
+    /// Restores the size bounds after a removal from leaf `n`, reached by
+    /// `path`.
+    fn rebalance_leaf(&mut self, path: &[(u32, usize)], n: u32) {
+        let min = self.order.div_ceil(2);
+        let Some(&(p, ci)) = path.last() else {
+            if self.leaves[n as usize].keys.is_empty() {
+                self.release_leaf(n);
+                (self.root, self.head, self.tail, self.height) = (NONE, NONE, NONE, 0);
+            }
+            return;
+        };
+        if self.leaves[n as usize].keys.len() >= min {
+            return;
+        }
+        let siblings = &self.interiors[p as usize].children;
+        let left = (ci > 0).then(|| siblings[ci - 1]);
+        let right = siblings.get(ci + 1).copied();
+        if let Some(l) = left.filter(|&l| self.leaves[l as usize].keys.len() > min) {
+            let from = &mut self.leaves[l as usize];
+            let (k, v) = (from.keys.pop(), from.vals.pop());
+            let (Some(k), Some(v)) = (k, v) else {
+                unreachable!("lending leaf has entries");
+            };
+            self.interiors[p as usize].keys[ci - 1] = k.clone();
+            let leaf = &mut self.leaves[n as usize];
+            leaf.keys.insert(0, k);
+            leaf.vals.insert(0, v);
+            return;
+        }
+        if let Some(r) = right.filter(|&r| self.leaves[r as usize].keys.len() > min) {
+            let from = &mut self.leaves[r as usize];
+            let (k, v) = (from.keys.remove(0), from.vals.remove(0));
+            self.interiors[p as usize].keys[ci] = from.keys[0].clone();
+            let leaf = &mut self.leaves[n as usize];
+            leaf.keys.push(k);
+            leaf.vals.push(v);
+            return;
+        }
+        // Merge the right one of the pair into the left and unlink it.
+        let (l, r, sep) = match left {
+            Some(l) => (l, n, ci - 1),
+            None => (n, right.expect("non-root leaf has a sibling"), ci),
+        };
+        let gone = std::mem::replace(
+            &mut self.leaves[r as usize],
+            Leaf {
+                keys: Vec::new(),
+                vals: Vec::new(),
+                prev: NONE,
+                next: NONE,
+            },
+        );
+        let into = &mut self.leaves[l as usize];
+        into.keys.extend(gone.keys);
+        into.vals.extend(gone.vals);
+        into.next = gone.next;
+        match gone.next {
+            NONE => self.tail = l,
+            next => self.leaves[next as usize].prev = l,
+        }
+        self.release_leaf(r);
+        let node = &mut self.interiors[p as usize];
+        node.keys.remove(sep);
+        node.children.remove(sep + 1);
+        self.rebalance_interior(&path[..path.len() - 1], p);
+    }
+
+    /// Restores the size bounds of interior node `n`, reached by `path`,
+    /// after it lost a child.
+    fn rebalance_interior(&mut self, path: &[(u32, usize)], n: u32) {
+        let min = self.order.div_ceil(2);
+        let Some(&(p, ci)) = path.last() else {
+            // A root left with one child hands the role down.
+            let node = &self.interiors[n as usize];
+            if node.children.len() == 1 {
+                self.root = node.children[0];
+                self.height -= 1;
+                self.release_interior(n);
+            }
+            return;
+        };
+        if self.interiors[n as usize].children.len() >= min {
+            return;
+        }
+        let siblings = &self.interiors[p as usize].children;
+        let left = (ci > 0).then(|| siblings[ci - 1]);
+        let right = siblings.get(ci + 1).copied();
+        if let Some(l) = left.filter(|&l| self.interiors[l as usize].children.len() > min) {
+            let from = &mut self.interiors[l as usize];
+            let child = from.children.pop().expect("lending node has children");
+            let key = from.keys.pop().expect("lending node has keys");
+            let sep = std::mem::replace(&mut self.interiors[p as usize].keys[ci - 1], key);
+            let node = &mut self.interiors[n as usize];
+            node.keys.insert(0, sep);
+            node.children.insert(0, child);
+            return;
+        }
+        if let Some(r) = right.filter(|&r| self.interiors[r as usize].children.len() > min) {
+            let from = &mut self.interiors[r as usize];
+            let child = from.children.remove(0);
+            let key = from.keys.remove(0);
+            let sep = std::mem::replace(&mut self.interiors[p as usize].keys[ci], key);
+            let node = &mut self.interiors[n as usize];
+            node.keys.push(sep);
+            node.children.push(child);
+            return;
+        }
+        let (l, r, sep) = match left {
+            Some(l) => (l, n, ci - 1),
+            None => (n, right.expect("non-root node has a sibling"), ci),
+        };
+        let parent = &mut self.interiors[p as usize];
+        let key = parent.keys.remove(sep);
+        parent.children.remove(sep + 1);
+        let gone = std::mem::replace(
+            &mut self.interiors[r as usize],
+            Interior {
+                keys: Vec::new(),
+                children: Vec::new(),
+            },
+        );
+        let into = &mut self.interiors[l as usize];
+        into.keys.push(key);
+        into.keys.extend(gone.keys);
+        into.children.extend(gone.children);
+        self.release_interior(r);
+        self.rebalance_interior(&path[..path.len() - 1], p);
+    }
+
+    fn alloc_leaf(&mut self, leaf: Leaf<K, V>) -> u32 {
+        match self.free_leaves.pop() {
+            Some(n) => {
+                self.leaves[n as usize] = leaf;
+                n
+            }
+            None => {
+                self.leaves.push(leaf);
+                (self.leaves.len() - 1) as u32
+            }
+        }
+    }
+
+    fn alloc_interior(&mut self, node: Interior<K>) -> u32 {
+        match self.free_interiors.pop() {
+            Some(n) => {
+                self.interiors[n as usize] = node;
+                n
+            }
+            None => {
+                self.interiors.push(node);
+                (self.interiors.len() - 1) as u32
+            }
+        }
+    }
+
+    fn release_leaf(&mut self, n: u32) {
+        self.leaves[n as usize] = Leaf {
+            keys: Vec::new(),
+            vals: Vec::new(),
+            prev: NONE,
+            next: NONE,
+        };
+        self.free_leaves.push(n);
+    }
+
+    fn release_interior(&mut self, n: u32) {
+        self.interiors[n as usize] = Interior {
+            keys: Vec::new(),
+            children: Vec::new(),
+        };
+        self.free_interiors.push(n);
+    }
+}
+
+/// Sizes that split `n` items into as few groups of at most `max` as
+/// possible, as evenly as possible.
+fn even_groups(n: usize, max: usize) -> impl Iterator<Item = usize> {
+    let groups = n.div_ceil(max);
+    (0..groups).map(move |g| n / groups + usize::from(g < n % groups))
+}
+
+impl<K: Ord + Clone, V> FromIterator<(K, V)> for BPlusTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = BPlusTree::new();
+        for (k, v) in iter {
+            tree.insert(k, v);
+        }
+        tree
+    }
+}
+
+/// An iterator over a key range of a [`BPlusTree`], walking the leaf chain
+/// from either end.
+#[derive(Clone, Debug)]
+pub struct Range<'a, K, V> {
+    tree: &'a BPlusTree<K, V>,
+    /// The next entry to yield from the front.
+    front: (u32, usize),
+    /// One past the next entry to yield from the back. Both ends stay in
+    /// canonical form, so the range is exhausted when they meet.
+    back: (u32, usize),
+}
+
+impl<'a, K: Ord + Clone, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let (n, i) = self.front;
+        let leaf = &self.tree.leaves[n as usize];
+        self.front = self.tree.canonical((n, i + 1));
+        Some((&leaf.keys[i], &leaf.vals[i]))
+    }
+}
+
+impl<K: Ord + Clone, V> DoubleEndedIterator for Range<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let (n, i) = self.back;
+        let (n, i) = if i > 0 {
+            (n, i - 1)
+        } else {
+            let prev = self.tree.leaves[n as usize].prev;
+            (prev, self.tree.leaves[prev as usize].keys.len() - 1)
+        };
+        self.back = (n, i);
+        let leaf = &self.tree.leaves[n as usize];
+        Some((&leaf.keys[i], &leaf.vals[i]))
+    }
+}
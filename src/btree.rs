@@ -0,0 +1,998 @@
+//! An ordered map stored as a B-tree of configurable order.
+//!
+//! Every node holds a sorted run of keys, and an interior node with `k`
+//! keys has `k + 1` children. A tree of order `m` allows at most `m - 1`
+//! keys per node and, except at the root, at least `ceil(m / 2) - 1`, so
+//! all leaves sit at the same depth of `O(log_m n)`. Nodes live in one
+//! arena and link to their parents, which is what lets cursors walk the
+//! map in both directions without keeping a stack.
+//!
+//! Beyond the usual map operations the tree offers four things the
+//! standard library's `BTreeMap` does not:
+//!
+//! - the order is chosen per tree, so node size can match a page or cache
+//!   line budget;
+//! - [`CursorMut`] walks the entries in order and removes or inserts at its
+//!   position, and [`BTree::drain_range`] removes a whole key range;
+//! - [`BTree::bulk_load`] builds a tree from sorted input in `O(n)`, with
+//!   every node filled evenly;
+//! - every node counts the entries under it, so [`BTree::select`] finds the
+//!   entry at a position and [`BTree::rank`] the position of a key, each in
+//!   `O(log n)`, as in an order-statistic tree.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+const NONE: u32 = u32::MAX;
+
+/// A position in the tree: a node and a key index within it, or `None` for
+/// the ghost position past the last entry.
+type Pos = Option<(u32, usize)>;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    keys: Vec<K>,
+    vals: Vec<V>,
+    /// Child nodes; empty for a leaf.
+    children: Vec<u32>,
+    parent: u32,
+    /// Number of entries in the subtree rooted here.
+    size: usize,
+}
+
+impl<K, V> Node<K, V> {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// An ordered map from keys of type `K` to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct BTree<K, V> {
+    nodes: Vec<Node<K, V>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+    order: usize,
+}
+
+impl<K, V> Default for BTree<K, V> {
+    fn default() -> Self {
+        BTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+            order: 16,
+        }
+    }
+}
+
+impl<K: Ord, V> BTree<K, V> {
+    /// Creates an empty tree of order 16.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty tree whose nodes have at most `order` children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is less than 3.
+    pub fn with_order(order: usize) -> Self {
+        assert!(order >= 3, "B-tree order must be at least 3");
+        BTree {
+            order,
+            ..Self::default()
+        }
+    }
+
+    /// Builds a tree of the given order from entries sorted by strictly
+    /// increasing key, in `O(n)`.
+    ///
+    /// Nodes are filled as evenly as the order allows, and the result is
+    /// the same map that inserting the entries one by one would give.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is less than 3 or the keys are not strictly
+    /// increasing.
+    pub fn bulk_load(order: usize, entries: impl IntoIterator<Item = (K, V)>) -> Self {
+        let mut tree = Self::with_order(order);
+        let (mut keys, mut vals): (Vec<K>, Vec<V>) = entries.into_iter().unzip();
+        assert!(
+            keys.windows(2).all(|w| w[0] < w[1]),
+            "bulk-loaded keys must be strictly increasing"
+        );
+        tree.len = keys.len();
+        if keys.is_empty() {
+            return tree;
+        }
+        let max = tree.max_keys();
+        let mut below: Vec<u32> = Vec::new();
+        loop {
+            // Use as few nodes as fit; one entry between each pair of them
+            // moves up to the next level as a separator.
+            let groups = (keys.len() + 1).div_ceil(max + 1);
+            let stored = keys.len() + 1 - groups;
+            let mut level = Vec::with_capacity(groups);
+            let (mut up_keys, mut up_vals) = (Vec::new(), Vec::new());
+            let mut key_iter = keys.into_iter();
+            let mut val_iter = vals.into_iter();
+            let mut child_iter = below.into_iter();
+            for g in 0..groups {
+                let take = stored / groups + usize::from(g < stored % groups);
+                let node = Node {
+                    keys: key_iter.by_ref().take(take).collect(),
+                    vals: val_iter.by_ref().take(take).collect(),
+                    children: child_iter.by_ref().take(take + 1).collect(),
+                    parent: NONE,
+                    size: 0,
+                };
+                let n = tree.alloc(node);
+                for i in 0..tree.nodes[n as usize].children.len() {
+                    let c = tree.nodes[n as usize].children[i];
+                    tree.nodes[c as usize].parent = n;
+                }
+                tree.resize(n);
+                level.push(n);
+                if g + 1 < groups {
+                    up_keys.extend(key_iter.next());
+                    up_vals.extend(val_iter.next());
+                }
+            }
+            if level.len() == 1 {
+                tree.root = level[0];
+                return tree;
+            }
+            keys = up_keys;
+            vals = up_vals;
+            below = level;
+        }
+    }
+
+    /// The maximum number of children per node.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of node levels; zero for an empty tree.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut n = self.root;
+        while n != NONE {
+            height += 1;
+            n = self.nodes[n as usize]
+                .children
+                .first()
+                .copied()
+                .unwrap_or(NONE);
+        }
+        height
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NONE;
+        self.len = 0;
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (n, i) = self.find(key)?;
+        Some(&self.nodes[n as usize].vals[i])
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (n, i) = self.find(key)?;
+        Some(&mut self.nodes[n as usize].vals[i])
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.entry(self.seek_lower::<K>(Bound::Unbounded))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.entry(self.seek_upper::<K>(Bound::Unbounded))
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_at(key, value).1
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self.find(key)?;
+        Some(self.remove_at(pos))
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Removes every entry whose key lies in `range`, returning them in
+    /// key order.
+    pub fn drain_range<Q, R>(&mut self, range: R) -> Vec<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let mut out = Vec::new();
+        let mut pos = self.seek_lower(range.start_bound());
+        while let Some((n, i)) = pos {
+            if !below_end(self.nodes[n as usize].keys[i].borrow(), range.end_bound()) {
+                break;
+            }
+            let (k, v) = self.remove_at((n, i));
+            pos = self.seek_lower(Bound::Excluded(k.borrow()));
+            out.push((k, v));
+        }
+        out
+    }
+
+    /// The entry with the `index`-th smallest key, counting from zero, in
+    /// `O(log n)`.
+    pub fn select(&self, mut index: usize) -> Option<(&K, &V)> {
+        if index >= self.len {
+            return None;
+        }
+        let mut n = self.root;
+        loop {
+            let node = &self.nodes[n as usize];
+            if node.is_leaf() {
+                return Some((&node.keys[index], &node.vals[index]));
+            }
+            // Each child's entries come before the key of the same index.
+            for (i, &c) in node.children.iter().enumerate() {
+                let size = self.nodes[c as usize].size;
+                if index < size {
+                    n = c;
+                    break;
+                }
+                index -= size;
+                if index == 0 {
+                    return Some((&node.keys[i], &node.vals[i]));
+                }
+                index -= 1;
+            }
+        }
+    }
+
+    /// The number of keys below `key`, in `O(log n)`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut n = self.root;
+        while n != NONE {
+            let node = &self.nodes[n as usize];
+            let i = node.keys.partition_point(|k| k.borrow() < key);
+            rank += i;
+            rank += node
+                .children
+                .iter()
+                .take(i)
+                .map(|&c| self.nodes[c as usize].size)
+                .sum::<usize>();
+            if node.keys.get(i).is_some_and(|k| k.borrow() == key) {
+                rank += node
+                    .children
+                    .get(i)
+                    .map_or(0, |&c| self.nodes[c as usize].size);
+                break;
+            }
+            n = node.children.get(i).copied().unwrap_or(NONE);
+        }
+        rank
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order.
+    pub fn range<'a, Q, R>(&'a self, range: R) -> impl Iterator<Item = (&'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<Q> + 'a,
+    {
+        let mut pos = self.seek_lower(range.start_bound());
+        std::iter::from_fn(move || {
+            let (k, v) = self.entry(pos)?;
+            if !below_end(k.borrow(), range.end_bound()) {
+                return None;
+            }
+            pos = self.next(pos);
+            Some((k, v))
+        })
+    }
+
+    /// A cursor at the entry with the smallest key.
+    pub fn cursor_front(&self) -> Cursor<'_, K, V> {
+        let pos = self.seek_lower::<K>(Bound::Unbounded);
+        Cursor { tree: self, pos }
+    }
+
+    /// A cursor at the entry with the largest key.
+    pub fn cursor_back(&self) -> Cursor<'_, K, V> {
+        let pos = self.seek_upper::<K>(Bound::Unbounded);
+        Cursor { tree: self, pos }
+    }
+
+    /// A cursor at the first entry whose key is above `bound`: at or after
+    /// the key for [`Bound::Included`], after it for [`Bound::Excluded`].
+    pub fn lower_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self.seek_lower(bound);
+        Cursor { tree: self, pos }
+    }
+
+    /// A cursor at the last entry whose key is below `bound`: at or before
+    /// the key for [`Bound::Included`], before it for [`Bound::Excluded`].
+    pub fn upper_bound<Q>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self.seek_upper(bound);
+        Cursor { tree: self, pos }
+    }
+
+    /// A mutable cursor at the entry with the smallest key.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, K, V> {
+        let pos = self.seek_lower::<K>(Bound::Unbounded);
+        CursorMut { tree: self, pos }
+    }
+
+    /// A mutable cursor at the entry with the largest key.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, K, V> {
+        let pos = self.seek_upper::<K>(Bound::Unbounded);
+        CursorMut { tree: self, pos }
+    }
+
+    /// A mutable cursor at the first entry whose key is above `bound`, as
+    /// for [`BTree::lower_bound`].
+    pub fn lower_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self.seek_lower(bound);
+        CursorMut { tree: self, pos }
+    }
+
+    /// A mutable cursor at the last entry whose key is below `bound`, as
+    /// for [`BTree::upper_bound`].
+    pub fn upper_bound_mut<Q>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self.seek_upper(bound);
+        CursorMut { tree: self, pos }
+    }
+
+    fn max_keys(&self) -> usize {
+        self.order - 1
+    }
+
+    fn min_keys(&self) -> usize {
+        self.order.div_ceil(2) - 1
+    }
+
+    fn alloc(&mut self, node: Node<K, V>) -> u32 {
+        match self.free.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn release(&mut self, n: u32) {
+        let node = &mut self.nodes[n as usize];
+        node.keys = Vec::new();
+        node.vals = Vec::new();
+        node.children = Vec::new();
+        node.parent = NONE;
+        node.size = 0;
+        self.free.push(n);
+    }
+
+    /// Recomputes the size of `n` from its keys and children.
+    fn resize(&mut self, n: u32) {
+        let node = &self.nodes[n as usize];
+        let below: usize = node
+            .children
+            .iter()
+            .map(|&c| self.nodes[c as usize].size)
+            .sum();
+        let size = node.keys.len() + below;
+        self.nodes[n as usize].size = size;
+    }
+
+    /// Applies `f` to the size of `n` and of every node above it.
+    fn adjust_sizes(&mut self, mut n: u32, f: impl Fn(usize) -> usize) {
+        while n != NONE {
+            let node = &mut self.nodes[n as usize];
+            node.size = f(node.size);
+            n = node.parent;
+        }
+    }
+
+    fn entry(&self, pos: Pos) -> Option<(&K, &V)> {
+        let (n, i) = pos?;
+        let node = &self.nodes[n as usize];
+        Some((&node.keys[i], &node.vals[i]))
+    }
+
+    fn find<Q>(&self, key: &Q) -> Pos
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut n = self.root;
+        while n != NONE {
+            let node = &self.nodes[n as usize];
+            match node.keys.binary_search_by(|k| k.borrow().cmp(key)) {
+                Ok(i) => return Some((n, i)),
+                Err(i) => n = node.children.get(i).copied().unwrap_or(NONE),
+            }
+        }
+        None
+    }
+
+    /// The position of the first key above `bound`.
+    fn seek_lower<Q>(&self, bound: Bound<&Q>) -> Pos
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = None;
+        let mut n = self.root;
+        while n != NONE {
+            let node = &self.nodes[n as usize];
+            let i = match bound {
+                Bound::Included(b) => node.keys.partition_point(|k| k.borrow() < b),
+                Bound::Excluded(b) => node.keys.partition_point(|k| k.borrow() <= b),
+                Bound::Unbounded => 0,
+            };
+            if i < node.keys.len() {
+                best = Some((n, i));
+            }
+            n = node.children.get(i).copied().unwrap_or(NONE);
+        }
+        best
+    }
+
+    /// The position of the last key below `bound`.
+    fn seek_upper<Q>(&self, bound: Bound<&Q>) -> Pos
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut best = None;
+        let mut n = self.root;
+        while n != NONE {
+            let node = &self.nodes[n as usize];
+            // `i` keys qualify; the last of them is the candidate.
+            let i = match bound {
+                Bound::Included(b) => node.keys.partition_point(|k| k.borrow() <= b),
+                Bound::Excluded(b) => node.keys.partition_point(|k| k.borrow() < b),
+                Bound::Unbounded => node.keys.len(),
+            };
+            if i > 0 {
+                best = Some((n, i - 1));
+            }
+            n = node.children.get(i).copied().unwrap_or(NONE);
+        }
+        best
+    }
+
+    /// The in-order successor of `pos`; the ghost moves to the first entry.
+    fn next(&self, pos: Pos) -> Pos {
+        let Some((mut n, mut i)) = pos else {
+            return self.seek_lower::<K>(Bound::Unbounded);
+        };
+        let node = &self.nodes[n as usize];
+        if !node.is_leaf() {
+            let mut c = node.children[i + 1];
+            while !self.nodes[c as usize].is_leaf() {
+                c = self.nodes[c as usize].children[0];
+            }
+            return Some((c, 0));
+        }
+        if i + 1 < node.keys.len() {
+            return Some((n, i + 1));
+        }
+        // Climb until we arrive from a child that has a key after it.
+        loop {
+            let p = self.nodes[n as usize].parent;
+            if p == NONE {
+                return None;
+            }
+            i = self.child_index(p, n);
+            if i < self.nodes[p as usize].keys.len() {
+                return Some((p, i));
+            }
+            n = p;
+        }
+    }
+
+    /// The in-order predecessor of `pos`; the ghost moves to the last entry.
+    fn prev(&self, pos: Pos) -> Pos {
+        let Some((mut n, i)) = pos else {
+            return self.seek_upper::<K>(Bound::Unbounded);
+        };
+        let node = &self.nodes[n as usize];
+        if !node.is_leaf() {
+            let mut c = node.children[i];
+            while !self.nodes[c as usize].is_leaf() {
+                c = *self.nodes[c as usize]
+                    .children
+                    .last()
+                    .expect("interior node");
+            }
+            return Some((c, self.nodes[c as usize].keys.len() - 1));
+        }
+        if i > 0 {
+            return Some((n, i - 1));
+        }
+        loop {
+            let p = self.nodes[n as usize].parent;
+            if p == NONE {
+                return None;
+            }
+            let ci = self.child_index(p, n);
+            if ci > 0 {
+                return Some((p, ci - 1));
+            }
+            n = p;
+        }
+    }
+
+    fn child_index(&self, parent: u32, child: u32) -> usize {
+        self.nodes[parent as usize]
+            .children
+            .iter()
+            .position(|&c| c == child)
+            .expect("child links to parent")
+    }
+
+    /// Inserts an entry, returning where it now lives and the value it
+    /// replaced.
+    fn insert_at(&mut self, key: K, value: V) -> ((u32, usize), Option<V>) {
+        if self.root == NONE {
+            self.root = self.alloc(Node {
+                keys: vec![key],
+                vals: vec![value],
+                children: Vec::new(),
+                parent: NONE,
+                size: 1,
+            });
+            self.len = 1;
+            return ((self.root, 0), None);
+        }
+        let mut n = self.root;
+        loop {
+            let node = &mut self.nodes[n as usize];
+            match node.keys.binary_search(&key) {
+                Ok(i) => return ((n, i), Some(std::mem::replace(&mut node.vals[i], value))),
+                Err(i) if node.is_leaf() => {
+                    node.keys.insert(i, key);
+                    node.vals.insert(i, value);
+                    self.len += 1;
+                    self.adjust_sizes(n, |s| s + 1);
+                    return (self.split_upward(n, (n, i)), None);
+                }
+                Err(i) => n = node.children[i],
+            }
+        }
+    }
+
+    /// Splits overfull nodes from `n` up to the root, following the entry
+    /// at `track` as it moves and returning its final position.
+    fn split_upward(&mut self, mut n: u32, mut track: (u32, usize)) -> (u32, usize) {
+        while self.nodes[n as usize].keys.len() > self.max_keys() {
+            let node = &mut self.nodes[n as usize];
+            let mid = node.keys.len() / 2;
+            let keys = node.keys.split_off(mid + 1);
+            let vals = node.vals.split_off(mid + 1);
+            let children = if node.is_leaf() {
+                Vec::new()
+            } else {
+                node.children.split_off(mid + 1)
+            };
+            let median = (node.keys.pop(), node.vals.pop());
+            let (Some(mk), Some(mv)) = median else {
+                unreachable!("overfull node has a median");
+            };
+            let parent = node.parent;
+            let right = self.alloc(Node {
+                keys,
+                vals,
+                children,
+                parent,
+                size: 0,
+            });
+            for i in 0..self.nodes[right as usize].children.len() {
+                let c = self.nodes[right as usize].children[i];
+                self.nodes[c as usize].parent = right;
+            }
+            self.resize(n);
+            self.resize(right);
+            let (p, at) = if parent == NONE {
+                let size = self.nodes[n as usize].size + self.nodes[right as usize].size + 1;
+                let root = self.alloc(Node {
+                    keys: vec![mk],
+                    vals: vec![mv],
+                    children: vec![n, right],
+                    parent: NONE,
+                    size,
+                });
+                self.nodes[n as usize].parent = root;
+                self.nodes[right as usize].parent = root;
+                self.root = root;
+                (root, 0)
+            } else {
+                let at = self.child_index(parent, n);
+                let pnode = &mut self.nodes[parent as usize];
+                pnode.keys.insert(at, mk);
+                pnode.vals.insert(at, mv);
+                pnode.children.insert(at + 1, right);
+                (parent, at)
+            };
+            if track.0 == n {
+                track = match track.1.cmp(&mid) {
+                    Ordering::Less => track,
+                    Ordering::Equal => (p, at),
+                    Ordering::Greater => (right, track.1 - mid - 1),
+                };
+            } else if track.0 == p && track.1 >= at {
+                // Keys after the new separator shifted right by one.
+                track.1 += 1;
+            }
+            n = p;
+        }
+        track
+    }
+
+    /// Removes the entry at `pos` and restores the node size bounds.
+    fn remove_at(&mut self, (n, i): (u32, usize)) -> (K, V) {
+        let (leaf, entry) = if self.nodes[n as usize].is_leaf() {
+            let node = &mut self.nodes[n as usize];
+            (n, (node.keys.remove(i), node.vals.remove(i)))
+        } else {
+            // Swap in the predecessor, which sits at the end of a leaf.
+            let mut m = self.nodes[n as usize].children[i];
+            while !self.nodes[m as usize].is_leaf() {
+                m = *self.nodes[m as usize]
+                    .children
+                    .last()
+                    .expect("interior node");
+            }
+            let leaf = &mut self.nodes[m as usize];
+            let (pk, pv) = (leaf.keys.pop(), leaf.vals.pop());
+            let (Some(pk), Some(pv)) = (pk, pv) else {
+                unreachable!("leaves are never empty");
+            };
+            let node = &mut self.nodes[n as usize];
+            let k = std::mem::replace(&mut node.keys[i], pk);
+            let v = std::mem::replace(&mut node.vals[i], pv);
+            (m, (k, v))
+        };
+        self.len -= 1;
+        self.adjust_sizes(leaf, |s| s - 1);
+        self.rebalance(leaf);
+        entry
+    }
+
+    /// Refills an underfull node from a sibling, or merges it into one,
+    /// working up the tree as merges drain parents.
+    fn rebalance(&mut self, mut n: u32) {
+        loop {
+            if n == self.root {
+                let node = &self.nodes[n as usize];
+                if node.keys.is_empty() {
+                    self.root = node.children.first().copied().unwrap_or(NONE);
+                    if self.root != NONE {
+                        self.nodes[self.root as usize].parent = NONE;
+                    }
+                    self.release(n);
+                }
+                return;
+            }
+            if self.nodes[n as usize].keys.len() >= self.min_keys() {
+                return;
+            }
+            let p = self.nodes[n as usize].parent;
+            let ci = self.child_index(p, n);
+            let siblings = &self.nodes[p as usize].children;
+            let left = (ci > 0).then(|| siblings[ci - 1]);
+            let right = siblings.get(ci + 1).copied();
+            if let Some(l) = left.filter(|&l| self.nodes[l as usize].keys.len() > self.min_keys()) {
+                self.rotate_right(p, ci - 1, l, n);
+                return;
+            }
+            if let Some(r) = right.filter(|&r| self.nodes[r as usize].keys.len() > self.min_keys())
+            {
+                self.rotate_left(p, ci, n, r);
+                return;
+            }
+            match left {
+                Some(l) => self.merge(p, ci - 1, l, n),
+                None => self.merge(p, ci, n, right.expect("non-root node has a sibling")),
+            }
+            n = p;
+        }
+    }
+
+    /// Moves separator `sep` of `p` down into `right` and the last entry of
+    /// `left` up in its place.
+    fn rotate_right(&mut self, p: u32, sep: usize, left: u32, right: u32) {
+        let l = &mut self.nodes[left as usize];
+        let (k, v) = (l.keys.pop(), l.vals.pop());
+        let child = l.children.pop();
+        let (Some(k), Some(v)) = (k, v) else {
+            unreachable!("lending sibling has entries");
+        };
+        let pn = &mut self.nodes[p as usize];
+        let k = std::mem::replace(&mut pn.keys[sep], k);
+        let v = std::mem::replace(&mut pn.vals[sep], v);
+        let r = &mut self.nodes[right as usize];
+        r.keys.insert(0, k);
+        r.vals.insert(0, v);
+        if let Some(c) = child {
+            r.children.insert(0, c);
+            self.nodes[c as usize].parent = right;
+        }
+        self.resize(left);
+        self.resize(right);
+    }
+
+    /// Moves separator `sep` of `p` down into `left` and the first entry of
+    /// `right` up in its place.
+    fn rotate_left(&mut self, p: u32, sep: usize, left: u32, right: u32) {
+        let r = &mut self.nodes[right as usize];
+        let (k, v) = (r.keys.remove(0), r.vals.remove(0));
+        let child = (!r.is_leaf()).then(|| r.children.remove(0));
+        let pn = &mut self.nodes[p as usize];
+        let k = std::mem::replace(&mut pn.keys[sep], k);
+        let v = std::mem::replace(&mut pn.vals[sep], v);
+        let l = &mut self.nodes[left as usize];
+        l.keys.push(k);
+        l.vals.push(v);
+        if let Some(c) = child {
+            l.children.push(c);
+            self.nodes[c as usize].parent = left;
+        }
+        self.resize(left);
+        self.resize(right);
+    }
+
+    /// Folds separator `sep` of `p` and all of `right` into `left`.
+    fn merge(&mut self, p: u32, sep: usize, left: u32, right: u32) {
+        let pn = &mut self.nodes[p as usize];
+        let k = pn.keys.remove(sep);
+        let v = pn.vals.remove(sep);
+        pn.children.remove(sep + 1);
+        let r = std::mem::replace(
+            &mut self.nodes[right as usize],
+            Node {
+                keys: Vec::new(),
+                vals: Vec::new(),
+                children: Vec::new(),
+                parent: NONE,
+                size: 0,
+            },
+        );
+        for &c in &r.children {
+            self.nodes[c as usize].parent = left;
+        }
+        let l = &mut self.nodes[left as usize];
+        l.keys.push(k);
+        l.vals.push(v);
+        l.keys.extend(r.keys);
+        l.vals.extend(r.vals);
+        l.children.extend(r.children);
+        l.size += 1 + r.size;
+        self.release(right);
+    }
+}
+
+/// Whether `key` lies before the end `bound` of a range.
+fn below_end<Q: Ord + ?Sized>(key: &Q, bound: Bound<&Q>) -> bool {
+    match bound {
+        Bound::Included(b) => key <= b,
+        Bound::Excluded(b) => key < b,
+        Bound::Unbounded => true,
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for BTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = BTree::new();
+        for (k, v) in iter {
+            tree.insert(k, v);
+        }
+        tree
+    }
+}
+
+/// A read-only cursor over a [`BTree`].
+///
+/// The cursor sits at an entry or at the ghost position, which lies past
+/// the last entry and before the first: moving forward from the ghost
+/// reaches the first entry, and moving back reaches the last.
+#[derive(Clone, Debug)]
+pub struct Cursor<'a, K, V> {
+    tree: &'a BTree<K, V>,
+    pos: Pos,
+}
+
+impl<'a, K: Ord, V> Cursor<'a, K, V> {
+    /// The entry at the cursor, or `None` at the ghost position.
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        self.tree.entry(self.pos)
+    }
+
+    /// Moves to the next entry in key order.
+    pub fn move_next(&mut self) {
+        self.pos = self.tree.next(self.pos);
+    }
+
+    /// Moves to the previous entry in key order.
+    pub fn move_prev(&mut self) {
+        self.pos = self.tree.prev(self.pos);
+    }
+}
+
+/// A cursor over a [`BTree`] that can edit the tree at its position.
+///
+/// Positions behave as for [`Cursor`], including the ghost position.
+#[derive(Debug)]
+pub struct CursorMut<'a, K, V> {
+    tree: &'a mut BTree<K, V>,
+    pos: Pos,
+}
+
+impl<K: Ord, V> CursorMut<'_, K, V> {
+    /// The entry at the cursor, or `None` at the ghost position.
+    pub fn peek(&self) -> Option<(&K, &V)> {
+        self.tree.entry(self.pos)
+    }
+
+    /// The entry at the cursor with its value mutable.
+    pub fn peek_mut(&mut self) -> Option<(&K, &mut V)> {
+        let (n, i) = self.pos?;
+        let node = &mut self.tree.nodes[n as usize];
+        Some((&node.keys[i], &mut node.vals[i]))
+    }
+
+    /// Moves to the next entry in key order.
+    pub fn move_next(&mut self) {
+        self.pos = self.tree.next(self.pos);
+    }
+
+    /// Moves to the previous entry in key order.
+    pub fn move_prev(&mut self) {
+        self.pos = self.tree.prev(self.pos);
+    }
+
+    /// Removes the entry at the cursor and moves to the one after it.
+    /// Returns `None`, changing nothing, at the ghost position.
+    pub fn remove_current(&mut self) -> Option<(K, V)> {
+        let pos = self.pos?;
+        let (k, v) = self.tree.remove_at(pos);
+        self.pos = self.tree.seek_lower(Bound::Excluded(&k));
+        Some((k, v))
+    }
+
+    /// Inserts an entry just before the cursor, which stays on its current
+    /// entry. At the ghost position the entry goes last.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `key` sorts strictly between the previous entry's key
+    /// and the current one's.
+    pub fn insert_before(&mut self, key: K, value: V) {
+        let prev = self.tree.prev(self.pos);
+        let after_prev = self.tree.entry(prev).is_none_or(|(k, _)| *k < key);
+        let before_cur = self.peek().is_none_or(|(k, _)| key < *k);
+        assert!(
+            after_prev && before_cur,
+            "inserted key must fall between the cursor's neighbors"
+        );
+        let at_ghost = self.pos.is_none();
+        let (pos, _) = self.tree.insert_at(key, value);
+        self.pos = if at_ghost {
+            None
+        } else {
+            self.tree.next(Some(pos))
+        };
+    }
+
+    /// Inserts an entry just after the cursor, which stays on its current
+    /// entry. At the ghost position the entry goes first.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `key` sorts strictly between the current entry's key
+    /// and the next one's.
+    pub fn insert_after(&mut self, key: K, value: V) {
+        let next = self.tree.next(self.pos);
+        let before_next = self.tree.entry(next).is_none_or(|(k, _)| key < *k);
+        let after_cur = self.peek().is_none_or(|(k, _)| *k < key);
+        assert!(
+            after_cur && before_next,
+            "inserted key must fall between the cursor's neighbors"
+        );
+        let at_ghost = self.pos.is_none();
+        let (pos, _) = self.tree.insert_at(key, value);
+        self.pos = if at_ghost {
+            None
+        } else {
+            self.tree.prev(Some(pos))
+        };
+    }
+
+    /// A read-only view of the tree.
+    pub fn tree(&self) -> &BTree<K, V> {
+        self.tree
+    }
+}
@@ -0,0 +1,353 @@
+//! A bounding volume hierarchy built with the surface area heuristic.
+//!
+//! The hierarchy is built top-down over a fixed set of primitives, each given
+//! as a bounding box plus user data. Every split is chosen by binning
+//! primitive centroids along each axis and picking the plane that minimizes
+//! the expected cost of tracing a random ray (the SAH), which is what makes
+//! BVHs the standard ray-tracing acceleration structure.
+//!
+//! Nodes live in one flat array with children after their parents, so
+//! [`Bvh::refit`] can recompute boxes for moved primitives in a single
+//! reverse sweep without changing the topology.
+
+pub use crate::geom::Ray;
+use crate::geom::{inverse, slab, Aabb};
+use crate::util::permute;
+
+const BINS: usize = 16;
+const MAX_LEAF: usize = 4;
+/// Cost of one traversal step relative to one primitive test.
+const TRAVERSAL_COST: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug)]
+struct Node<const N: usize> {
+    bbox: Aabb<N>,
+    /// Index of the left child for interior nodes (the right child follows
+    /// it), or of the first primitive for leaves.
+    start: u32,
+    /// Number of primitives; zero marks an interior node.
+    count: u32,
+}
+
+/// A BVH over primitives in `N` dimensions carrying data of type `T`.
+#[derive(Clone, Debug)]
+pub struct Bvh<T, const N: usize> {
+    nodes: Vec<Node<N>>,
+    boxes: Vec<Aabb<N>>,
+    data: Vec<T>,
+}
+
+impl<T, const N: usize> Bvh<T, N> {
+    /// Builds a hierarchy over `(bounds, data)` primitives.
+    pub fn build(primitives: Vec<(Aabb<N>, T)>) -> Self {
+        let (boxes, data) = primitives.into_iter().unzip();
+        let mut bvh = Bvh {
+            nodes: Vec::new(),
+            boxes,
+            data,
+        };
+        bvh.rebuild();
+        bvh
+    }
+
+    /// Number of primitives.
+    pub fn len(&self) -> usize {
+        self.boxes.len()
+    }
+
+    /// Whether the hierarchy holds no primitives.
+    pub fn is_empty(&self) -> bool {
+        self.boxes.is_empty()
+    }
+
+    /// The bounding box and data of a primitive, by storage index.
+    pub fn get(&self, index: usize) -> Option<(&Aabb<N>, &T)> {
+        Some((self.boxes.get(index)?, &self.data[index]))
+    }
+
+    /// Iterates over primitives in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Aabb<N>, &T)> + '_ {
+        self.boxes
+            .iter()
+            .zip(&self.data)
+            .enumerate()
+            .map(|(i, (b, d))| (i, b, d))
+    }
+
+    /// The bounding box of all primitives.
+    pub fn bounds(&self) -> Aabb<N> {
+        self.nodes.first().map_or(Aabb::empty(), |n| n.bbox)
+    }
+
+    /// Recomputes every primitive's box with `bounds` and refits the
+    /// hierarchy around them, keeping its topology.
+    ///
+    /// Refitting is much cheaper than rebuilding but lets node boxes grow
+    /// and overlap as primitives move; call [`Bvh::rebuild`] when query
+    /// performance degrades.
+    pub fn refit(&mut self, mut bounds: impl FnMut(&T) -> Aabb<N>) {
+        for (b, d) in self.boxes.iter_mut().zip(&self.data) {
+            *b = bounds(d);
+        }
+        for i in (0..self.nodes.len()).rev() {
+            let node = self.nodes[i];
+            let start = node.start as usize;
+            self.nodes[i].bbox = if node.count > 0 {
+                union_all(&self.boxes[start..start + node.count as usize])
+            } else {
+                self.nodes[start].bbox.union(&self.nodes[start + 1].bbox)
+            };
+        }
+    }
+
+    /// Rebuilds the hierarchy from the current primitive boxes.
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        if self.boxes.is_empty() {
+            return;
+        }
+        let mut order: Vec<usize> = (0..self.boxes.len()).collect();
+        self.nodes.push(Node {
+            bbox: union_all(&self.boxes),
+            start: 0,
+            count: order.len() as u32,
+        });
+        let mut stack = vec![0usize];
+        while let Some(n) = stack.pop() {
+            let Node { start, count, .. } = self.nodes[n];
+            let range = start as usize..(start + count) as usize;
+            let Some(mid) = self.partition(&mut order[range.clone()], self.nodes[n].bbox) else {
+                continue;
+            };
+            let left = self.nodes.len();
+            for sub in [range.start..range.start + mid, range.start + mid..range.end] {
+                self.nodes.push(Node {
+                    bbox: union_all_indexed(&self.boxes, &order[sub.clone()]),
+                    start: sub.start as u32,
+                    count: sub.len() as u32,
+                });
+            }
+            self.nodes[n].start = left as u32;
+            self.nodes[n].count = 0;
+            stack.push(left);
+            stack.push(left + 1);
+        }
+        permute(&mut self.boxes, &order);
+        permute(&mut self.data, &order);
+    }
+
+    /// Finds the SAH-best binned split of `prims`, reorders them so the left
+    /// side comes first, and returns the left side's size. Returns `None`
+    /// when keeping a leaf is cheaper.
+    fn partition(&self, prims: &mut [usize], bbox: Aabb<N>) -> Option<usize> {
+        if prims.len() <= 1 {
+            return None;
+        }
+        let centroids = prims.iter().fold(Aabb::empty(), |acc, &p| {
+            acc.union(&Aabb::point(self.boxes[p].center()))
+        });
+        let leaf_cost = prims.len() as f64;
+        let parent_area = surface(&bbox);
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..N {
+            let (lo, hi) = (centroids.min[axis], centroids.max[axis]);
+            if hi <= lo {
+                continue;
+            }
+            let bin_of = |p: usize| {
+                let c = self.boxes[p].center()[axis];
+                (((c - lo) / (hi - lo) * BINS as f64) as usize).min(BINS - 1)
+            };
+            let mut counts = [0usize; BINS];
+            let mut boxes = [Aabb::empty(); BINS];
+            for &p in prims.iter() {
+                let b = bin_of(p);
+                counts[b] += 1;
+                boxes[b] = boxes[b].union(&self.boxes[p]);
+            }
+            // Sweep from the right to get suffix costs, then from the left.
+            let mut right_cost = [0.0; BINS];
+            let (mut acc, mut n) = (Aabb::empty(), 0);
+            for b in (1..BINS).rev() {
+                acc = acc.union(&boxes[b]);
+                n += counts[b];
+                right_cost[b] = surface(&acc) * n as f64;
+            }
+            let (mut acc, mut n) = (Aabb::empty(), 0);
+            for b in 0..BINS - 1 {
+                acc = acc.union(&boxes[b]);
+                n += counts[b];
+                if n == 0 || n == prims.len() {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + (surface(&acc) * n as f64 + right_cost[b + 1])
+                        / parent_area.max(f64::MIN_POSITIVE);
+                if best.is_none_or(|(c, _, _)| cost < c) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+        let Some((cost, axis, bin)) = best else {
+            // Every centroid coincides, so no plane separates the primitives.
+            return (prims.len() > MAX_LEAF).then_some(prims.len() / 2);
+        };
+        if cost >= leaf_cost && prims.len() <= MAX_LEAF {
+            return None;
+        }
+        let (lo, hi) = (centroids.min[axis], centroids.max[axis]);
+        let mut mid = 0;
+        for i in 0..prims.len() {
+            let c = self.boxes[prims[i]].center()[axis];
+            if (((c - lo) / (hi - lo) * BINS as f64) as usize).min(BINS - 1) <= bin {
+                prims.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some(mid)
+    }
+
+    /// Finds the nearest primitive along `ray` within `max_t`.
+    ///
+    /// `hit` performs the exact primitive test, returning the ray parameter
+    /// of its intersection if any; it's only called for primitives whose
+    /// boxes the ray crosses. Returns the storage index, data and parameter
+    /// of the closest hit.
+    pub fn closest_hit(
+        &self,
+        ray: &Ray<N>,
+        max_t: f64,
+        mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+    ) -> Option<(usize, &T, f64)> {
+        let inv = inverse(ray);
+        let mut best: Option<(usize, f64)> = None;
+        let mut limit = max_t;
+        let mut stack = Vec::new();
+        if slab(&self.nodes.first()?.bbox, ray, &inv, limit).is_some() {
+            stack.push(0usize);
+        }
+        while let Some(n) = stack.pop() {
+            let node = self.nodes[n];
+            // The limit may have tightened since this node was pushed.
+            if slab(&node.bbox, ray, &inv, limit).is_none() {
+                continue;
+            }
+            let start = node.start as usize;
+            if node.count > 0 {
+                for i in start..start + node.count as usize {
+                    if slab(&self.boxes[i], ray, &inv, limit).is_none() {
+                        continue;
+                    }
+                    if let Some(t) = hit(&self.data[i], ray).filter(|&t| t >= 0.0 && t <= limit) {
+                        limit = t;
+                        best = Some((i, t));
+                    }
+                }
+                continue;
+            }
+            let l = slab(&self.nodes[start].bbox, ray, &inv, limit);
+            let r = slab(&self.nodes[start + 1].bbox, ray, &inv, limit);
+            match (l, r) {
+                (Some(tl), Some(tr)) => {
+                    // Push the farther child first so the nearer pops first.
+                    let (near, far) = if tl <= tr {
+                        (start, start + 1)
+                    } else {
+                        (start + 1, start)
+                    };
+                    stack.push(far);
+                    stack.push(near);
+                }
+                (Some(_), None) => stack.push(start),
+                (None, Some(_)) => stack.push(start + 1),
+                (None, None) => {}
+            }
+        }
+        best.map(|(i, t)| (i, &self.data[i], t))
+    }
+
+    /// Finds any primitive hit by `ray` within `max_t`, stopping at the first
+    /// one; suited to shadow rays and occlusion tests.
+    pub fn any_hit(
+        &self,
+        ray: &Ray<N>,
+        max_t: f64,
+        mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+    ) -> Option<(usize, &T)> {
+        let inv = inverse(ray);
+        let mut stack = vec![0usize];
+        if self.nodes.is_empty() {
+            return None;
+        }
+        while let Some(n) = stack.pop() {
+            let node = self.nodes[n];
+            if slab(&node.bbox, ray, &inv, max_t).is_none() {
+                continue;
+            }
+            let start = node.start as usize;
+            if node.count == 0 {
+                stack.push(start);
+                stack.push(start + 1);
+                continue;
+            }
+            for i in start..start + node.count as usize {
+                if slab(&self.boxes[i], ray, &inv, max_t).is_some()
+                    && hit(&self.data[i], ray).is_some_and(|t| t >= 0.0 && t <= max_t)
+                {
+                    return Some((i, &self.data[i]));
+                }
+            }
+        }
+        None
+    }
+
+    /// Iterates over the primitives whose boxes intersect `query`.
+    pub fn intersecting(&self, query: Aabb<N>) -> impl Iterator<Item = (usize, &Aabb<N>, &T)> + '_ {
+        let mut stack = if self.nodes.is_empty() {
+            Vec::new()
+        } else {
+            vec![0usize]
+        };
+        let mut run = 0..0;
+        std::iter::from_fn(move || loop {
+            for i in run.by_ref() {
+                if self.boxes[i].intersects(&query) {
+                    return Some((i, &self.boxes[i], &self.data[i]));
+                }
+            }
+            let node = self.nodes[stack.pop()?];
+            if !node.bbox.intersects(&query) {
+                continue;
+            }
+            let start = node.start as usize;
+            if node.count > 0 {
+                run = start..start + node.count as usize;
+            } else {
+                stack.push(start);
+                stack.push(start + 1);
+            }
+        })
+    }
+}
+
+/// Surface measure used by the SAH: for each axis, the product of the other
+/// extents. In three dimensions this is half the surface area.
+fn surface<const N: usize>(b: &Aabb<N>) -> f64 {
+    if b.is_empty() {
+        return 0.0;
+    }
+    let e: [f64; N] = std::array::from_fn(|a| b.max[a] - b.min[a]);
+    (0..N)
+        .map(|skip| (0..N).filter(|&a| a != skip).map(|a| e[a]).product::<f64>())
+        .sum()
+}
+
+fn union_all<const N: usize>(boxes: &[Aabb<N>]) -> Aabb<N> {
+    boxes.iter().fold(Aabb::empty(), |acc, b| acc.union(b))
+}
+
+fn union_all_indexed<const N: usize>(boxes: &[Aabb<N>], order: &[usize]) -> Aabb<N> {
+    order
+        .iter()
+        .fold(Aabb::empty(), |acc, &i| acc.union(&boxes[i]))
+}
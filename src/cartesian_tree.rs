@@ -0,0 +1,228 @@
+//! Cartesian trees, and range-minimum queries answered as lowest common
+//! ancestors.
+//!
+//! The Cartesian tree of a sequence (Vuillemin, 1980) has the minimum at
+//! its root, the tree of everything left of the minimum as its left
+//! subtree and the tree of everything right of it as its right subtree.
+//! An in-order walk gives back the positions in order, and the minimum of
+//! any range `i..=j` sits at the lowest common ancestor of `i` and `j`,
+//! because that is the first node to separate them. The tree is built in
+//! `O(n)` with one stack holding its rightmost path: each new element pops
+//! the larger ones, which become its left subtree, and hangs off the right
+//! of whatever remains.
+//!
+//! The reduction also runs the other way (Gabow, Bentley and Tarjan,
+//! 1984). Every lowest common ancestor query becomes a range-minimum query
+//! over the depths along an Euler tour of the tree, which here is answered
+//! by a [`FischerHeun`] table, so after `O(n)` preprocessing both
+//! [`lca`](CartesianTree::lca) and [`argmin`](CartesianTree::argmin) take
+//! `O(1)`. The `FischerHeun` structure itself reaches the same bounds
+//! directly, keeping the Cartesian tree of each block only implicitly; the
+//! explicit tree is for when its shape is wanted too, for instance to
+//! build a treap from sorted keys or to walk the nested ranges of minima.
+//!
+//! Ties go to the leftmost position, which becomes the ancestor of the
+//! equal values after it, as with [`FischerHeun::argmin`].
+//!
+//! [`FischerHeun`]: crate::sparse_table::FischerHeun
+//! [`FischerHeun::argmin`]: crate::sparse_table::FischerHeun::argmin
+
+use std::ops::RangeBounds;
+
+use crate::sparse_table::FischerHeun;
+use crate::util::bounds;
+
+const NONE: u32 = u32::MAX;
+
+/// The Cartesian tree of a sequence, with `O(1)` lowest common ancestors
+/// and range minima.
+#[derive(Clone, Debug)]
+pub struct CartesianTree {
+    parent: Vec<u32>,
+    left: Vec<u32>,
+    right: Vec<u32>,
+    root: u32,
+    /// The step of the Euler tour at which each node is first visited.
+    first: Vec<u32>,
+    /// The node visited at each step of the Euler tour.
+    tour: Vec<u32>,
+    /// The depth of the node at each step of the tour.
+    depths: FischerHeun<u32>,
+}
+
+impl CartesianTree {
+    /// Builds the tree of `values` in `O(n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `2^31` values or more.
+    pub fn new<T: Ord>(values: &[T]) -> Self {
+        let n = values.len();
+        assert!(n < 1 << 31, "too many values");
+        let mut parent = vec![NONE; n];
+        let mut left = vec![NONE; n];
+        let mut right = vec![NONE; n];
+        // The rightmost path, root first.
+        let mut spine: Vec<u32> = Vec::new();
+        for (i, v) in values.iter().enumerate() {
+            let mut last = NONE;
+            while let Some(&top) = spine.last() {
+                if values[top as usize] <= *v {
+                    break;
+                }
+                last = top;
+                spine.pop();
+            }
+            left[i] = last;
+            if last != NONE {
+                parent[last as usize] = i as u32;
+            }
+            if let Some(&top) = spine.last() {
+                right[top as usize] = i as u32;
+                parent[i] = top;
+            }
+            spine.push(i as u32);
+        }
+        let root = spine.first().copied().unwrap_or(NONE);
+
+        let mut first = vec![0; n];
+        let mut tour = Vec::with_capacity((2 * n).saturating_sub(1));
+        let mut depths = Vec::with_capacity(tour.capacity());
+        if root != NONE {
+            tour.push(root);
+            depths.push(0);
+        }
+        // Each entry is a node and how many of its children it has visited.
+        let mut stack = if root == NONE {
+            Vec::new()
+        } else {
+            vec![(root, 0)]
+        };
+        while let Some((node, visited)) = stack.last_mut() {
+            let child = match visited {
+                0 => left[*node as usize],
+                1 => right[*node as usize],
+                _ => {
+                    stack.pop();
+                    if let Some(&(p, _)) = stack.last() {
+                        tour.push(p);
+                        depths.push(stack.len() as u32 - 1);
+                    }
+                    continue;
+                }
+            };
+            *visited += 1;
+            if child != NONE {
+                first[child as usize] = tour.len() as u32;
+                tour.push(child);
+                depths.push(stack.len() as u32);
+                stack.push((child, 0));
+            }
+        }
+
+        CartesianTree {
+            parent,
+            left,
+            right,
+            root,
+            first,
+            tour,
+            depths: FischerHeun::new(depths),
+        }
+    }
+
+    /// Number of nodes, one per position of the sequence.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Whether the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// The position of the leftmost minimum of the whole sequence.
+    pub fn root(&self) -> Option<usize> {
+        some(self.root)
+    }
+
+    /// The parent of position `i`, the nearer of the smaller values on
+    /// either side of it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn parent(&self, i: usize) -> Option<usize> {
+        some(self.parent[i])
+    }
+
+    /// The left child of position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn left(&self, i: usize) -> Option<usize> {
+        some(self.left[i])
+    }
+
+    /// The right child of position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn right(&self, i: usize) -> Option<usize> {
+        some(self.right[i])
+    }
+
+    /// The number of ancestors of position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn depth(&self, i: usize) -> usize {
+        self.depths.values()[self.first[i] as usize] as usize
+    }
+
+    /// The lowest common ancestor of positions `a` and `b`, which is the
+    /// position of the leftmost minimum between them, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` is out of bounds.
+    pub fn lca(&self, a: usize, b: usize) -> usize {
+        let (x, y) = (self.first[a], self.first[b]);
+        let (x, y) = (x.min(y) as usize, x.max(y) as usize);
+        let step = self.depths.argmin(x..=y).expect("nonempty range");
+        self.tour[step] as usize
+    }
+
+    /// The position of the leftmost minimum in `range`, or `None` if it is
+    /// empty, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn argmin(&self, range: impl RangeBounds<usize>) -> Option<usize> {
+        let (lo, hi) = bounds(range, self.len());
+        (lo < hi).then(|| self.lca(lo, hi - 1))
+    }
+
+    /// The positions in pre-order, each before its subtrees, without
+    /// recursion.
+    pub fn pre_order(&self) -> impl Iterator<Item = usize> + '_ {
+        let mut stack: Vec<u32> = some(self.root).map(|r| r as u32).into_iter().collect();
+        std::iter::from_fn(move || {
+            let n = stack.pop()?;
+            for child in [self.right[n as usize], self.left[n as usize]] {
+                if child != NONE {
+                    stack.push(child);
+                }
+            }
+            Some(n as usize)
+        })
+    }
+}
+
+fn some(i: u32) -> Option<usize> {
+    (i != NONE).then_some(i as usize)
+}
@@ -0,0 +1,236 @@
+//! A double-ended queue with `O(1)` concatenation, over finger trees.
+//!
+//! The finger-tree [`Deque`] concatenates two deques in `O(log n)`, by
+//! regrouping the digits where they meet. This wrapper defers that work:
+//! it keeps the sequence as a linked list of nonempty finger-tree pieces,
+//! so appending a deque splices its pieces onto the end of the list in
+//! `O(1)` worst case. Pushes and pops at either end touch only the first
+//! or last piece, in `O(1)` amortized.
+//!
+//! Indexing and splitting need the pieces joined. [`split_off`] first
+//! joins them all into one piece, paying `O(log n)` for each concatenation
+//! that was deferred, and then splits it in `O(log n)`; each piece is
+//! joined at most once, so over a run of operations on one deque
+//! splitting costs `O(log n)` amortized. The bound does not survive
+//! cloning: a clone shares the unjoined pieces, and splitting the original
+//! and the clone joins each of them twice, so a deque cloned `c` times
+//! with `k` pieces outstanding may pay `O(c k log n)` for them in all.
+//! Compacting before cloning keeps deferred work from being repeated.
+//! [`get`] only reads, walking the pieces in
+//! `O(k + log n)` for `k` pieces, and [`compact`] joins them ahead of a
+//! burst of reads.
+//!
+//! That suits workloads dominated by concatenation: a work-stealing
+//! scheduler whose workers hand whole queues of tasks to each other and
+//! occasionally steal half of one, or a rope of arbitrary elements built
+//! by splicing many fragments together before it is read. Each piece is a
+//! persistent finger tree, so cloning the deque copies only the list of
+//! pieces.
+//!
+//! [`Deque`]: crate::finger_tree::Deque
+//! [`split_off`]: CatenableDeque::split_off
+//! [`get`]: CatenableDeque::get
+//! [`compact`]: CatenableDeque::compact
+
+use std::collections::LinkedList;
+use std::fmt;
+
+use crate::finger_tree::Deque;
+
+/// A double-ended queue of `T` with `O(1)` concatenation.
+#[derive(Clone)]
+pub struct CatenableDeque<T> {
+    /// The pieces, in order, none of them empty.
+    pieces: LinkedList<Deque<T>>,
+    len: usize,
+}
+
+impl<T: Clone> Default for CatenableDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> CatenableDeque<T> {
+    /// Creates an empty deque.
+    pub fn new() -> Self {
+        CatenableDeque {
+            pieces: LinkedList::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the deque holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every element.
+    pub fn clear(&mut self) {
+        self.pieces.clear();
+        self.len = 0;
+    }
+
+    /// Number of pieces the elements are held in: appends add to them, and
+    /// [`compact`](Self::compact) joins them into one.
+    pub fn pieces(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Adds `item` at the front.
+    pub fn push_front(&mut self, item: T) {
+        match self.pieces.front_mut() {
+            Some(piece) => piece.push_front(item),
+            None => self.pieces.push_front(Deque::from_iter([item])),
+        }
+        self.len += 1;
+    }
+
+    /// Adds `item` at the back.
+    pub fn push_back(&mut self, item: T) {
+        match self.pieces.back_mut() {
+            Some(piece) => piece.push_back(item),
+            None => self.pieces.push_back(Deque::from_iter([item])),
+        }
+        self.len += 1;
+    }
+
+    /// Removes and returns the front element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let piece = self.pieces.front_mut()?;
+        let item = piece.pop_front();
+        if piece.is_empty() {
+            self.pieces.pop_front();
+        }
+        self.len -= 1;
+        item
+    }
+
+    /// Removes and returns the back element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        let piece = self.pieces.back_mut()?;
+        let item = piece.pop_back();
+        if piece.is_empty() {
+            self.pieces.pop_back();
+        }
+        self.len -= 1;
+        item
+    }
+
+    /// The front element.
+    pub fn front(&self) -> Option<&T> {
+        self.pieces.front()?.front()
+    }
+
+    /// The back element.
+    pub fn back(&self) -> Option<&T> {
+        self.pieces.back()?.back()
+    }
+
+    /// The element at `index`, in `O(k + log n)` for `k` pieces.
+    pub fn get(&self, mut index: usize) -> Option<&T> {
+        for piece in &self.pieces {
+            if index < piece.len() {
+                return piece.get(index);
+            }
+            index -= piece.len();
+        }
+        None
+    }
+
+    /// Moves the elements of `other` to the back, in `O(1)`.
+    pub fn append(&mut self, mut other: Self) {
+        self.len += other.len;
+        self.pieces.append(&mut other.pieces);
+    }
+
+    /// Joins the pieces into one, in `O(log n)` for each.
+    pub fn compact(&mut self) {
+        let Some(mut whole) = self.pieces.pop_front() else {
+            return;
+        };
+        while let Some(piece) = self.pieces.pop_front() {
+            whole.append(piece);
+        }
+        self.pieces.push_back(whole);
+    }
+
+    /// Splits the deque at `at`, keeping `[0, at)` and returning the rest,
+    /// in `O(log n)` amortized over the operations on this deque.
+    ///
+    /// The pieces appended since the last split or [`compact`] are joined
+    /// first, `O(log n)` each. Clones share those pieces but not the work
+    /// of joining them, so every clone that is split pays for them again.
+    ///
+    /// [`compact`]: CatenableDeque::compact
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len, "split index out of bounds");
+        self.compact();
+        let mut rest = CatenableDeque::new();
+        if let Some(piece) = self.pieces.back_mut() {
+            let right = piece.split_off(at);
+            if piece.is_empty() {
+                self.pieces.clear();
+            }
+            if !right.is_empty() {
+                rest.pieces.push_back(right);
+            }
+        }
+        rest.len = self.len - at;
+        self.len = at;
+        rest
+    }
+
+    /// Iterates over the elements, front to back.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.pieces.iter().flat_map(|piece| piece.iter())
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for CatenableDeque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for CatenableDeque<T> {}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for CatenableDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> From<Deque<T>> for CatenableDeque<T> {
+    fn from(deque: Deque<T>) -> Self {
+        let mut pieces = LinkedList::new();
+        let len = deque.len();
+        if len > 0 {
+            pieces.push_back(deque);
+        }
+        CatenableDeque { pieces, len }
+    }
+}
+
+impl<T: Clone> Extend<T> for CatenableDeque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for CatenableDeque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Deque::from_iter(iter).into()
+    }
+}
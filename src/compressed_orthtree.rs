@@ -0,0 +1,619 @@
+//! A path-compressed orthtree over `N`-dimensional points.
+//!
+//! A plain [`Orthtree`] pays one level per coordinate bit. Two points that
+//! agree on their first twenty bits sit twenty levels down, under a chain
+//! of nodes with one occupied child each, and the depth limit caps how
+//! finely tight clusters in a huge coordinate space can be told apart. The
+//! compressed orthtree skips those chains. Each node records the smallest
+//! grid cell holding everything beneath it, and an interior node always
+//! has occupied children in at least two of its orthants. A node's children
+//! may therefore sit any number of levels below it. The tree has at most
+//! one interior node per point, so its depth is bounded by the number of
+//! points rather than by the coordinate precision. Points are quantized to
+//! the full 32 bits per axis at no cost in depth.
+//!
+//! Leaves hold up to `bucket_capacity` points, and a leaf's cell shrinks
+//! to fit them. Inserting a point outside a node's cell creates a new
+//! interior node at the smallest cell holding both. Removal undoes this:
+//! an interior node left with one child is spliced out, and a subtree that
+//! falls under the bucket capacity collapses into a leaf.
+//!
+//! [`CompressedQuadtree`] and [`CompressedOctree`] are the two- and
+//! three-dimensional aliases.
+//!
+//! [`Orthtree`]: crate::orthtree::Orthtree
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::geom::Aabb;
+use crate::orthtree::{OutOfBounds, Point, MAX_DIMENSION};
+use crate::util::Total;
+
+/// Handle to a point stored in a [`CompressedOrthtree`].
+///
+/// Handles of removed points may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompressedId(u32);
+
+/// A compressed quadtree mapping 2D points of type `P` to data of type `D`.
+pub type CompressedQuadtree<P, D> = CompressedOrthtree<P, D, 2>;
+
+/// A compressed octree mapping 3D points of type `P` to data of type `D`.
+pub type CompressedOctree<P, D> = CompressedOrthtree<P, D, 3>;
+
+const NONE: u32 = u32::MAX;
+
+/// Coordinate bits per axis.
+const BITS: u8 = 32;
+
+#[derive(Clone, Debug)]
+struct Node<const N: usize> {
+    parent: u32,
+    /// Lowest corner of the node's cell; bits below `level` are zero.
+    corner: [u32; N],
+    /// Number of leading coordinate bits the cell fixes: zero for the
+    /// whole space, [`BITS`] for a single grid cell.
+    level: u8,
+    /// Child nodes, in at least two distinct orthants; empty for a leaf.
+    children: Vec<u32>,
+    /// Entry indices; only populated on leaves.
+    items: Vec<u32>,
+    /// Number of points in this subtree.
+    count: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<P, D, const N: usize> {
+    point: P,
+    data: D,
+    cell: [u32; N],
+    leaf: u32,
+    /// Position within the leaf's item list.
+    slot: u32,
+}
+
+/// A compressed orthtree mapping `N`-dimensional points of type `P` to data
+/// of type `D`.
+#[derive(Clone, Debug)]
+pub struct CompressedOrthtree<P, D, const N: usize> {
+    bounds: Aabb<N>,
+    bucket: usize,
+    /// Size of a grid cell along each axis.
+    cell: [f64; N],
+    /// Rounding allowance applied when turning cells back into coordinates.
+    slack: [f64; N],
+    nodes: Vec<Node<N>>,
+    free_nodes: Vec<u32>,
+    root: u32,
+    entries: Vec<Option<Entry<P, D, N>>>,
+    free_entries: Vec<u32>,
+    len: usize,
+}
+
+impl<P: Point<N>, D, const N: usize> CompressedOrthtree<P, D, N> {
+    /// Creates an empty tree covering `bounds`, with a bucket capacity of 8.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite, or if `N` is zero or
+    /// exceeds [`MAX_DIMENSION`].
+    pub fn new(bounds: Aabb<N>) -> Self {
+        Self::with_bucket_capacity(bounds, 8)
+    }
+
+    /// Creates an empty tree whose leaves hold up to `bucket_capacity`
+    /// points. Leaves whose points all share one grid cell never split and
+    /// may exceed it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite, if `N` is zero or exceeds
+    /// [`MAX_DIMENSION`], or if `bucket_capacity` is zero.
+    pub fn with_bucket_capacity(bounds: Aabb<N>, bucket_capacity: usize) -> Self {
+        assert!(
+            (1..=MAX_DIMENSION).contains(&N),
+            "dimension must be between 1 and {MAX_DIMENSION}"
+        );
+        assert!(
+            (0..N).all(|a| bounds.min[a].is_finite()
+                && bounds.max[a].is_finite()
+                && bounds.min[a] < bounds.max[a]),
+            "orthtree bounds must be finite and non-empty"
+        );
+        assert!(bucket_capacity > 0, "bucket capacity must be positive");
+        let cells = (1u64 << BITS) as f64;
+        CompressedOrthtree {
+            bounds,
+            bucket: bucket_capacity,
+            cell: std::array::from_fn(|a| (bounds.max[a] - bounds.min[a]) / cells),
+            slack: std::array::from_fn(|a| {
+                8.0 * f64::EPSILON * (bounds.min[a].abs() + bounds.max[a].abs())
+            }),
+            nodes: vec![Self::empty_root()],
+            free_nodes: Vec::new(),
+            root: 0,
+            entries: Vec::new(),
+            free_entries: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn empty_root() -> Node<N> {
+        Node {
+            parent: NONE,
+            corner: [0; N],
+            level: 0,
+            children: Vec::new(),
+            items: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// The box covered by the tree.
+    pub fn bounds(&self) -> Aabb<N> {
+        self.bounds
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of node levels; an empty or single-leaf tree has height 1.
+    pub fn height(&self) -> usize {
+        let mut stack = vec![(self.root, 1)];
+        let mut height = 1;
+        while let Some((n, depth)) = stack.pop() {
+            height = height.max(depth);
+            stack.extend(
+                self.nodes[n as usize]
+                    .children
+                    .iter()
+                    .map(|&c| (c, depth + 1)),
+            );
+        }
+        height
+    }
+
+    /// Removes every point, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.nodes.push(Self::empty_root());
+        self.free_nodes.clear();
+        self.root = 0;
+        self.entries.clear();
+        self.free_entries.clear();
+        self.len = 0;
+    }
+
+    /// Inserts a point, returning its handle.
+    pub fn insert(&mut self, point: P, data: D) -> Result<CompressedId, OutOfBounds> {
+        let cell = self.cell_of(&point).ok_or(OutOfBounds)?;
+        let entry = Entry {
+            point,
+            data,
+            cell,
+            leaf: NONE,
+            slot: 0,
+        };
+        let id = match self.free_entries.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.place(id);
+        self.len += 1;
+        Ok(CompressedId(id))
+    }
+
+    /// Removes a point, returning its coordinates and data.
+    pub fn remove(&mut self, id: CompressedId) -> Option<(P, D)> {
+        self.entries.get(id.0 as usize)?.as_ref()?;
+        self.detach(id.0);
+        let entry = self.entries[id.0 as usize].take()?;
+        self.free_entries.push(id.0);
+        self.len -= 1;
+        Some((entry.point, entry.data))
+    }
+
+    /// Moves a point to a new position, keeping its handle and data.
+    ///
+    /// Returns the previous position, or `None` if `id` is not in the tree.
+    /// Fails without modifying the tree if `to` is out of bounds.
+    pub fn relocate(&mut self, id: CompressedId, to: P) -> Result<Option<P>, OutOfBounds> {
+        let cell = self.cell_of(&to).ok_or(OutOfBounds)?;
+        if self
+            .entries
+            .get(id.0 as usize)
+            .and_then(Option::as_ref)
+            .is_none()
+        {
+            return Ok(None);
+        }
+        self.detach(id.0);
+        let entry = self.entries[id.0 as usize]
+            .as_mut()
+            .expect("entry checked above");
+        let old = std::mem::replace(&mut entry.point, to);
+        entry.cell = cell;
+        self.place(id.0);
+        Ok(Some(old))
+    }
+
+    /// Returns the position and data of a point.
+    pub fn get(&self, id: CompressedId) -> Option<(&P, &D)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((&e.point, &e.data))
+    }
+
+    /// Returns the position and mutable data of a point.
+    pub fn get_mut(&mut self, id: CompressedId) -> Option<(&P, &mut D)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&e.point, &mut e.data))
+    }
+
+    /// Iterates over every stored point in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (CompressedId, &P, &D)> + '_ {
+        self.entries.iter().enumerate().filter_map(|(i, e)| {
+            e.as_ref()
+                .map(|e| (CompressedId(i as u32), &e.point, &e.data))
+        })
+    }
+
+    /// Iterates over the points inside the closed box `query`.
+    pub fn within_box(&self, query: Aabb<N>) -> impl Iterator<Item = (CompressedId, &P, &D)> + '_ {
+        self.query(
+            move |b| b.intersects(&query),
+            move |p| query.contains_point(&p.coords()),
+        )
+    }
+
+    /// Iterates over the points within `radius` of `center`.
+    pub fn within_radius<Q: Point<N>>(
+        &self,
+        center: Q,
+        radius: f64,
+    ) -> impl Iterator<Item = (CompressedId, &P, &D)> + '_ {
+        let c = center.coords();
+        let r2 = radius * radius;
+        self.query(
+            move |b| b.distance_squared(&c) <= r2,
+            move |p| distance_squared(&c, &p.coords()) <= r2,
+        )
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// Euclidean distances.
+    pub fn nearest<Q: Point<N>>(&self, query: Q, k: usize) -> Vec<(CompressedId, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let q = query.coords();
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((Total(0.0), self.root)));
+        while let Some(Reverse((Total(bound), n))) = frontier.pop() {
+            if best.len() == k && bound > best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                break;
+            }
+            let node = &self.nodes[n as usize];
+            for &i in &node.items {
+                let d2 = distance_squared(&q, &self.entry(i).point.coords());
+                if best.len() < k {
+                    best.push((Total(d2), i));
+                } else if d2 < best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                    best.pop();
+                    best.push((Total(d2), i));
+                }
+            }
+            for &c in &node.children {
+                let d2 = self.cell_box(c).distance_squared(&q);
+                frontier.push(Reverse((Total(d2), c)));
+            }
+        }
+        let mut out: Vec<_> = best
+            .into_iter()
+            .map(|(d2, i)| (CompressedId(i), d2.0.sqrt()))
+            .collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one<Q: Point<N>>(&self, query: Q) -> Option<(CompressedId, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Depth-first traversal entering nodes whose cell boxes pass `enter`
+    /// and yielding points that pass `keep`.
+    fn query<'a>(
+        &'a self,
+        enter: impl Fn(&Aabb<N>) -> bool + 'a,
+        keep: impl Fn(&P) -> bool + 'a,
+    ) -> impl Iterator<Item = (CompressedId, &'a P, &'a D)> + 'a {
+        let mut stack = if self.is_empty() {
+            Vec::new()
+        } else {
+            vec![self.root]
+        };
+        let mut items: std::slice::Iter<'a, u32> = [].iter();
+        std::iter::from_fn(move || loop {
+            for &i in items.by_ref() {
+                let e = self.entry(i);
+                if keep(&e.point) {
+                    return Some((CompressedId(i), &e.point, &e.data));
+                }
+            }
+            let n = stack.pop()?;
+            if !enter(&self.cell_box(n)) {
+                continue;
+            }
+            let node = &self.nodes[n as usize];
+            items = node.items.iter();
+            stack.extend(&node.children);
+        })
+    }
+
+    fn entry(&self, i: u32) -> &Entry<P, D, N> {
+        self.entries[i as usize].as_ref().expect("live entry")
+    }
+
+    fn entry_mut(&mut self, i: u32) -> &mut Entry<P, D, N> {
+        self.entries[i as usize].as_mut().expect("live entry")
+    }
+
+    fn cell_of(&self, p: &P) -> Option<[u32; N]> {
+        let c = p.coords();
+        if !self.bounds.contains_point(&c) {
+            return None;
+        }
+        Some(std::array::from_fn(|a| {
+            let t = (c[a] - self.bounds.min[a]) / self.cell[a];
+            if t <= 0.0 {
+                0
+            } else {
+                (t as u64).min(u32::MAX as u64) as u32
+            }
+        }))
+    }
+
+    /// The coordinate box of a node's cell, padded to absorb rounding.
+    fn cell_box(&self, n: u32) -> Aabb<N> {
+        let node = &self.nodes[n as usize];
+        let side = 1u64 << (BITS - node.level);
+        Aabb::new(
+            std::array::from_fn(|a| {
+                self.bounds.min[a] + node.corner[a] as f64 * self.cell[a] - self.slack[a]
+            }),
+            std::array::from_fn(|a| {
+                self.bounds.min[a]
+                    + (node.corner[a] as u64 + side) as f64 * self.cell[a]
+                    + self.slack[a]
+            }),
+        )
+    }
+
+    fn alloc_node(&mut self, node: Node<N>) -> u32 {
+        match self.free_nodes.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn new_leaf(&mut self, parent: u32, items: Vec<u32>) -> u32 {
+        let n = self.alloc_node(Node {
+            parent,
+            corner: [0; N],
+            level: BITS,
+            children: Vec::new(),
+            items: Vec::new(),
+            count: 0,
+        });
+        self.fill_leaf(n, items);
+        n
+    }
+
+    /// Makes `n` a leaf holding `items`, shrinking its cell to fit them.
+    fn fill_leaf(&mut self, n: u32, items: Vec<u32>) {
+        let mut cell = (self.entry(items[0]).cell, BITS);
+        for (slot, &i) in items.iter().enumerate() {
+            cell = common(cell, &self.entry(i).cell);
+            let e = self.entry_mut(i);
+            e.leaf = n;
+            e.slot = slot as u32;
+        }
+        let node = &mut self.nodes[n as usize];
+        node.corner = cell.0;
+        node.level = cell.1;
+        node.count = items.len() as u32;
+        node.items = items;
+        node.children.clear();
+    }
+
+    /// Puts `new` in the place of child `old` of `parent`, or at the root.
+    fn replace_child(&mut self, parent: u32, old: u32, new: u32) {
+        self.nodes[new as usize].parent = parent;
+        if parent == NONE {
+            self.root = new;
+        } else {
+            for c in &mut self.nodes[parent as usize].children {
+                if *c == old {
+                    *c = new;
+                }
+            }
+        }
+    }
+
+    /// Routes a detached entry to its leaf, creating nodes as needed.
+    fn place(&mut self, id: u32) {
+        let cell = self.entry(id).cell;
+        let mut n = self.root;
+        loop {
+            let node = &self.nodes[n as usize];
+            if node.children.is_empty() {
+                break;
+            }
+            let level = node.level;
+            if common((node.corner, level), &cell).1 < level {
+                // The point lies outside this interior node: both go under
+                // a new node at the smallest cell holding them.
+                let (corner, level) = common((node.corner, level), &cell);
+                let (parent, count) = (node.parent, node.count);
+                let fork = self.alloc_node(Node {
+                    parent,
+                    corner,
+                    level,
+                    children: vec![n],
+                    items: Vec::new(),
+                    count: count + 1,
+                });
+                self.replace_child(parent, n, fork);
+                self.nodes[n as usize].parent = fork;
+                let leaf = self.new_leaf(fork, vec![id]);
+                self.nodes[fork as usize].children.push(leaf);
+                return;
+            }
+            let digit = orthant(&cell, level);
+            let child = node
+                .children
+                .iter()
+                .copied()
+                .find(|&c| orthant(&self.nodes[c as usize].corner, level) == digit);
+            self.nodes[n as usize].count += 1;
+            match child {
+                Some(c) => n = c,
+                None => {
+                    let leaf = self.new_leaf(n, vec![id]);
+                    self.nodes[n as usize].children.push(leaf);
+                    return;
+                }
+            }
+        }
+        // A leaf in the point's orthant absorbs it, growing its cell.
+        let mut items = std::mem::take(&mut self.nodes[n as usize].items);
+        items.push(id);
+        self.fill_leaf(n, items);
+        self.split_if_full(n);
+    }
+
+    fn split_if_full(&mut self, leaf: u32) {
+        let node = &self.nodes[leaf as usize];
+        if node.items.len() <= self.bucket || node.level >= BITS {
+            return;
+        }
+        // The cell fits its points tightly, so they span several orthants.
+        let level = node.level;
+        let items = std::mem::take(&mut self.nodes[leaf as usize].items);
+        let mut groups: Vec<(u32, Vec<u32>)> = Vec::new();
+        for id in items {
+            let digit = orthant(&self.entry(id).cell, level);
+            match groups.iter_mut().find(|g| g.0 == digit) {
+                Some(g) => g.1.push(id),
+                None => groups.push((digit, vec![id])),
+            }
+        }
+        for (_, group) in groups {
+            let child = self.new_leaf(leaf, group);
+            self.nodes[leaf as usize].children.push(child);
+            self.split_if_full(child);
+        }
+    }
+
+    /// Unlinks an entry from the tree, restoring compression and
+    /// collapsing subtrees that fall under the bucket capacity.
+    fn detach(&mut self, id: u32) {
+        let (leaf, slot) = {
+            let e = self.entry(id);
+            (e.leaf, e.slot as usize)
+        };
+        let items = &mut self.nodes[leaf as usize].items;
+        items.swap_remove(slot);
+        if let Some(&moved) = items.get(slot) {
+            self.entry_mut(moved).slot = slot as u32;
+        }
+        let mut collapse = NONE;
+        let mut n = leaf;
+        while n != NONE {
+            let node = &mut self.nodes[n as usize];
+            node.count -= 1;
+            if !node.children.is_empty() && node.count as usize <= self.bucket {
+                collapse = n;
+            }
+            n = node.parent;
+        }
+        if collapse != NONE {
+            let items = self.drain(collapse);
+            self.fill_leaf(collapse, items);
+            return;
+        }
+        let items = std::mem::take(&mut self.nodes[leaf as usize].items);
+        if !items.is_empty() {
+            self.fill_leaf(leaf, items);
+            return;
+        }
+        let parent = self.nodes[leaf as usize].parent;
+        if parent == NONE {
+            return;
+        }
+        self.free_nodes.push(leaf);
+        let siblings = &mut self.nodes[parent as usize].children;
+        siblings.retain(|&c| c != leaf);
+        if siblings.len() == 1 {
+            let only = siblings[0];
+            let grand = self.nodes[parent as usize].parent;
+            self.replace_child(grand, parent, only);
+            self.free_nodes.push(parent);
+        }
+    }
+
+    /// Frees every node below `n`, returning the entries they held.
+    fn drain(&mut self, n: u32) -> Vec<u32> {
+        let mut items = Vec::new();
+        let mut stack = std::mem::take(&mut self.nodes[n as usize].children);
+        while let Some(c) = stack.pop() {
+            let node = &mut self.nodes[c as usize];
+            items.append(&mut node.items);
+            stack.append(&mut node.children);
+            self.free_nodes.push(c);
+        }
+        items
+    }
+}
+
+/// The smallest cell holding both the cell `(corner, level)` and the grid
+/// cell `cell`.
+fn common<const N: usize>((corner, level): ([u32; N], u8), cell: &[u32; N]) -> ([u32; N], u8) {
+    let shared = (0..N)
+        .map(|a| (corner[a] ^ cell[a]).leading_zeros() as u8)
+        .fold(level, u8::min);
+    let mask = u32::MAX.checked_shl((BITS - shared) as u32).unwrap_or(0);
+    (corner.map(|c| c & mask), shared)
+}
+
+/// Which orthant of a cell at `level` the grid cell `cell` lies in: bit `a`
+/// is the coordinate bit of axis `a` just below the cell's fixed prefix.
+fn orthant<const N: usize>(cell: &[u32; N], level: u8) -> u32 {
+    let shift = BITS - level - 1;
+    (0..N).fold(0, |acc, a| acc | ((cell[a] >> shift) & 1) << a)
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
@@ -0,0 +1,509 @@
+//! A concurrent hash map with lock-free reads and per-shard write locks.
+//!
+//! The map is split into shards by the top bits of each key's hash, and
+//! each shard is a chained hash table whose buckets are linked lists of
+//! immutable nodes. A read never locks: it pins the thread, follows the
+//! shard's table pointer and the bucket's list, and compares keys.
+//! Writers take the shard's mutex, so writes to different shards run in
+//! parallel, and never modify a node a reader might see. Inserting a new
+//! key pushes a node onto its bucket's list; replacing or removing one
+//! copies the nodes before it onto the rest of the list and swings the
+//! bucket to the copy, so a reader sees either the old list or the new
+//! one. Growing a shard rehashes into a new table and swings the table
+//! pointer the same way.
+//!
+//! Displaced nodes and tables are retired through the crate's epoch-based
+//! reclamation, which frees them only once no pinned reader can still be
+//! walking them. Values are therefore read in place, by
+//! [`read`](ConcurrentHashMap::read) with a closure or by
+//! [`get`](ConcurrentHashMap::get) as a clone, and keys and values must be
+//! `Clone` for the copying. They must also be `'static`: a retired node
+//! may be freed on any thread after the map itself is gone, so it cannot
+//! borrow anything that might be dropped first.
+//!
+//! Read-modify-write updates run as closures under the shard's lock, so
+//! [`compute`](ConcurrentHashMap::compute) and its relatives are atomic
+//! with respect to every other write of that key, the way an `entry` is
+//! for a `HashMap`.
+
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::epoch::{self, Guard};
+use crate::probabilistic::{stable_hash, DEFAULT_SEED};
+
+/// Shards per available core, by default.
+const SHARDS_PER_CORE: usize = 4;
+
+/// Buckets in a new shard.
+const INITIAL_BUCKETS: usize = 8;
+
+struct Node<K, V> {
+    hash: u64,
+    key: K,
+    value: V,
+    next: *mut Node<K, V>,
+}
+
+// SAFETY: nodes are only freed whole, by the epoch collector, possibly on
+// another thread; the link is never followed after a node is retired.
+unsafe impl<K: Send, V: Send> Send for Node<K, V> {}
+
+struct Table<K, V> {
+    buckets: Box<[AtomicPtr<Node<K, V>>]>,
+}
+
+impl<K, V> Table<K, V> {
+    fn new(buckets: usize) -> Self {
+        Table {
+            buckets: (0..buckets)
+                .map(|_| AtomicPtr::new(ptr::null_mut()))
+                .collect(),
+        }
+    }
+
+    fn bucket(&self, hash: u64) -> &AtomicPtr<Node<K, V>> {
+        &self.buckets[hash as usize & (self.buckets.len() - 1)]
+    }
+}
+
+struct Shard<K, V> {
+    write: Mutex<()>,
+    table: AtomicPtr<Table<K, V>>,
+    len: AtomicUsize,
+}
+
+/// A hash map from `K` to `V` that any number of threads can read and
+/// write through a shared reference.
+///
+/// Entries cannot borrow, since a displaced entry may outlive the map:
+///
+/// ```compile_fail
+/// use datastructures::concurrent_map::ConcurrentHashMap;
+///
+/// let name = String::from("borrowed");
+/// let map = ConcurrentHashMap::new();
+/// map.insert(1, name.as_str());
+/// ```
+pub struct ConcurrentHashMap<K, V> {
+    shards: Box<[Shard<K, V>]>,
+    /// Hash bits that pick the shard: `64 - log2(shards)`.
+    shift: u32,
+}
+
+// SAFETY: readers on several threads share `&K` and `&V`, and writers
+// move keys and values between threads through the map.
+unsafe impl<K: Send + Sync, V: Send + Sync> Send for ConcurrentHashMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for ConcurrentHashMap<K, V> {}
+
+impl<K, V> Default for ConcurrentHashMap<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> ConcurrentHashMap<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Creates an empty map with four shards per available core.
+    pub fn new() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(cores * SHARDS_PER_CORE)
+    }
+
+    /// Creates an empty map with `shards` shards, rounded up to a power of
+    /// two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "a map needs at least one shard");
+        let shards = shards.next_power_of_two();
+        ConcurrentHashMap {
+            shards: (0..shards)
+                .map(|_| Shard {
+                    write: Mutex::new(()),
+                    table: AtomicPtr::new(Box::into_raw(Box::new(Table::new(INITIAL_BUCKETS)))),
+                    len: AtomicUsize::new(0),
+                })
+                .collect(),
+            shift: 64 - shards.trailing_zeros(),
+        }
+    }
+
+    /// Number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Number of entries, summed shard by shard, so only a snapshot while
+    /// other threads write.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.len.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Whether the map held no entries when checked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls `f` with the value stored under `key`, without locking, and
+    /// returns its result.
+    pub fn read<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = stable_hash(DEFAULT_SEED, key);
+        let _guard = epoch::pin();
+        let node = self.find(hash, key)?;
+        // SAFETY: the guard keeps the node alive while `f` runs.
+        Some(f(unsafe { &(*node).value }))
+    }
+
+    /// Returns a clone of the value stored under `key`, without locking.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read(key, V::clone)
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read(key, |_| ()).is_some()
+    }
+
+    /// Inserts `value` under `key`, returning the value previously stored
+    /// under it.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut old = None;
+        self.compute(key, |current| {
+            old = current.cloned();
+            Some(value)
+        });
+        old
+    }
+
+    /// Removes the entry stored under `key` and returns its value.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = stable_hash(DEFAULT_SEED, key);
+        let shard = self.shard(hash);
+        let _write = lock(&shard.write);
+        let guard = epoch::pin();
+        let node = self.find(hash, key)?;
+        // SAFETY: found under the write lock, so still linked.
+        let value = unsafe { (*node).value.clone() };
+        // SAFETY: the write lock is held and `node` is in its bucket.
+        unsafe { self.replace(shard, &guard, node, None) };
+        shard.len.fetch_sub(1, Ordering::Relaxed);
+        Some(value)
+    }
+
+    /// Atomically replaces the entry under `key` with what `f` returns,
+    /// given the current value: `Some` stores a value, `None` removes the
+    /// entry. Returns the value stored afterwards.
+    ///
+    /// `f` runs with the key's shard locked for writing; it must not write
+    /// to the map itself.
+    pub fn compute(&self, key: K, f: impl FnOnce(Option<&V>) -> Option<V>) -> Option<V> {
+        let hash = stable_hash(DEFAULT_SEED, &key);
+        let shard = self.shard(hash);
+        let _write = lock(&shard.write);
+        let guard = epoch::pin();
+        let node = self.find(hash, &key);
+        // SAFETY: found under the write lock, so still linked.
+        let current = node.map(|n| unsafe { &(*n).value });
+        let new = f(current);
+        let result = new.clone();
+        match (node, new) {
+            (None, None) => {}
+            (None, Some(value)) => {
+                // SAFETY: the write lock is held.
+                unsafe { self.push(shard, &guard, hash, key, value) };
+            }
+            (Some(node), new) => {
+                let removed = new.is_none();
+                let replacement = new.map(|value| Node {
+                    hash,
+                    key,
+                    value,
+                    next: ptr::null_mut(),
+                });
+                // SAFETY: the write lock is held and `node` is in its
+                // bucket.
+                unsafe { self.replace(shard, &guard, node, replacement) };
+                if removed {
+                    shard.len.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+        result
+    }
+
+    /// Atomically replaces the value under `key` with `f` of it, if there
+    /// is one, and returns the new value.
+    ///
+    /// `f` runs with the key's shard locked for writing.
+    pub fn update(&self, key: K, f: impl FnOnce(&V) -> V) -> Option<V> {
+        self.compute(key, |current| current.map(f))
+    }
+
+    /// Returns the value stored under `key`, first inserting the one `f`
+    /// makes if there is none. Concurrent callers for the same key see the
+    /// same value, and `f` runs at most once between them.
+    ///
+    /// `f` runs with the key's shard locked for writing.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        self.compute(key, |current| Some(current.cloned().unwrap_or_else(f)))
+            .expect("always stores a value")
+    }
+
+    /// Removes every entry.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let _write = lock(&shard.write);
+            let guard = epoch::pin();
+            let fresh = Box::into_raw(Box::new(Table::new(INITIAL_BUCKETS)));
+            let old = shard.table.swap(fresh, Ordering::AcqRel);
+            // SAFETY: the write lock is held and the old table is
+            // unreachable for later pins.
+            unsafe { retire_table(&guard, old) };
+            shard.len.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Calls `f` on every entry, without locking. Entries written during
+    /// the walk may or may not be seen.
+    pub fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+        let _guard = epoch::pin();
+        for shard in self.shards.iter() {
+            // SAFETY: the guard keeps the table and its nodes alive.
+            let table = unsafe { &*shard.table.load(Ordering::Acquire) };
+            for bucket in table.buckets.iter() {
+                let mut node = bucket.load(Ordering::Acquire);
+                while !node.is_null() {
+                    // SAFETY: as above.
+                    let n = unsafe { &*node };
+                    f(&n.key, &n.value);
+                    node = n.next;
+                }
+            }
+        }
+    }
+
+    fn shard(&self, hash: u64) -> &Shard<K, V> {
+        // Shifting by 64 is an error, so a single shard takes no bits.
+        let index = hash.checked_shr(self.shift).unwrap_or(0) as usize;
+        &self.shards[index]
+    }
+
+    /// The node holding `key`. The caller must be pinned, and the node
+    /// stays valid while it is.
+    fn find<Q>(&self, hash: u64, key: &Q) -> Option<*mut Node<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let shard = self.shard(hash);
+        // SAFETY: the caller's pin keeps the table and nodes alive.
+        let table = unsafe { &*shard.table.load(Ordering::Acquire) };
+        let mut node = table.bucket(hash).load(Ordering::Acquire);
+        while !node.is_null() {
+            // SAFETY: as above.
+            let n = unsafe { &*node };
+            if n.hash == hash && n.key.borrow() == key {
+                return Some(node);
+            }
+            node = n.next;
+        }
+        None
+    }
+
+    /// Pushes a new entry onto its bucket, growing the shard if it is
+    /// full.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the shard's write lock.
+    unsafe fn push(&self, shard: &Shard<K, V>, guard: &Guard, hash: u64, key: K, value: V) {
+        // SAFETY: writers are serialised by the lock, so the table is
+        // current.
+        let table = unsafe { &*shard.table.load(Ordering::Acquire) };
+        let bucket = table.bucket(hash);
+        let node = Box::into_raw(Box::new(Node {
+            hash,
+            key,
+            value,
+            next: bucket.load(Ordering::Relaxed),
+        }));
+        bucket.store(node, Ordering::Release);
+        let len = shard.len.fetch_add(1, Ordering::Relaxed) + 1;
+        if len > table.buckets.len() {
+            // SAFETY: the caller holds the lock.
+            unsafe { self.grow(shard, guard) };
+        }
+    }
+
+    /// Unlinks `target` from its bucket, putting `replacement` in its
+    /// place if given, by copying the nodes before it.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the shard's write lock, and `target` must be
+    /// in the shard's current table.
+    unsafe fn replace(
+        &self,
+        shard: &Shard<K, V>,
+        guard: &Guard,
+        target: *mut Node<K, V>,
+        replacement: Option<Node<K, V>>,
+    ) {
+        // SAFETY: the lock keeps the table current and `target` linked.
+        let (table, target_ref) = unsafe { (&*shard.table.load(Ordering::Acquire), &*target) };
+        let bucket = table.bucket(target_ref.hash);
+        let mut tail = target_ref.next;
+        if let Some(mut node) = replacement {
+            node.next = tail;
+            tail = Box::into_raw(Box::new(node));
+        }
+        let mut prefix = Vec::new();
+        let mut node = bucket.load(Ordering::Relaxed);
+        while node != target {
+            prefix.push(node);
+            // SAFETY: nodes before `target` are linked and alive.
+            node = unsafe { (*node).next };
+        }
+        for &old in prefix.iter().rev() {
+            // SAFETY: as above.
+            let old = unsafe { &*old };
+            tail = Box::into_raw(Box::new(Node {
+                hash: old.hash,
+                key: old.key.clone(),
+                value: old.value.clone(),
+                next: tail,
+            }));
+        }
+        bucket.store(tail, Ordering::Release);
+        // SAFETY: the old prefix and target are unreachable for later pins.
+        unsafe {
+            for old in prefix {
+                guard.defer_destroy(old);
+            }
+            guard.defer_destroy(target);
+        }
+    }
+
+    /// Rehashes the shard into a table with twice the buckets.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the shard's write lock.
+    unsafe fn grow(&self, shard: &Shard<K, V>, guard: &Guard) {
+        let old = shard.table.load(Ordering::Acquire);
+        // SAFETY: the lock keeps the table current.
+        let old_ref = unsafe { &*old };
+        let table = Table::new(old_ref.buckets.len() * 2);
+        for bucket in old_ref.buckets.iter() {
+            let mut node = bucket.load(Ordering::Relaxed);
+            while !node.is_null() {
+                // SAFETY: linked nodes are alive.
+                let n = unsafe { &*node };
+                let slot = table.bucket(n.hash);
+                let copy = Box::into_raw(Box::new(Node {
+                    hash: n.hash,
+                    key: n.key.clone(),
+                    value: n.value.clone(),
+                    next: slot.load(Ordering::Relaxed),
+                }));
+                slot.store(copy, Ordering::Relaxed);
+                node = n.next;
+            }
+        }
+        shard
+            .table
+            .store(Box::into_raw(Box::new(table)), Ordering::Release);
+        // SAFETY: the old table is unreachable for later pins.
+        unsafe { retire_table(guard, old) };
+    }
+}
+
+/// Retires a table and every node linked from it.
+///
+/// # Safety
+///
+/// The table must be unreachable for threads that pin from now on.
+unsafe fn retire_table<K: Send, V: Send>(guard: &Guard, table: *mut Table<K, V>) {
+    // SAFETY: still alive until the retirements below take effect.
+    let buckets = unsafe { &(*table).buckets };
+    for bucket in buckets.iter() {
+        let mut node = bucket.load(Ordering::Relaxed);
+        while !node.is_null() {
+            // SAFETY: as above; each node is in one bucket, retired once.
+            unsafe {
+                let next = (*node).next;
+                guard.defer_destroy(node);
+                node = next;
+            }
+        }
+    }
+    // SAFETY: as above.
+    unsafe { guard.defer_destroy(table) };
+}
+
+impl<K, V> Drop for ConcurrentHashMap<K, V> {
+    fn drop(&mut self) {
+        for shard in self.shards.iter_mut() {
+            // SAFETY: `&mut self` rules out readers, and the current table
+            // owns every node linked from it.
+            let table = unsafe { Box::from_raw(*shard.table.get_mut()) };
+            for bucket in table.buckets.iter() {
+                let mut node = bucket.load(Ordering::Relaxed);
+                while !node.is_null() {
+                    // SAFETY: as above.
+                    let boxed = unsafe { Box::from_raw(node) };
+                    node = boxed.next;
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for ConcurrentHashMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConcurrentHashMap")
+            .field("shards", &self.shards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Locks a shard for writing, ignoring poisoning: a panic in an update
+/// closure leaves the shard unchanged.
+fn lock(mutex: &Mutex<()>) -> MutexGuard<'_, ()> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,298 @@
+//! A count-min sketch: approximate frequencies of a stream.
+//!
+//! A count-min sketch (Cormode and Muthukrishnan, 2005) is a `d × w` array
+//! of counters with one hash function per row. Adding an item increments
+//! one counter in every row; its estimated count is the smallest of those
+//! counters. Collisions only ever add, so the estimate never falls short
+//! of the true count, and with `w = ⌈e / ε⌉` and `d = ⌈ln(1 / δ)⌉` it
+//! exceeds it by more than `ε N`, for a stream of total count `N`, with
+//! probability at most `δ`. The space depends on `ε` and `δ` only, not on
+//! the number of distinct items.
+//!
+//! Conservative update (Estan and Varghese, 2002) raises each of the item's
+//! counters only as far as the new estimate, `min + count`, instead of
+//! adding to all of them. Estimates keep the same guarantee and are often
+//! several times tighter on skewed streams.
+//!
+//! The sketch cannot list the items it has seen. [`HeavyHitters`] keeps the
+//! `k` items with the largest estimates alongside it, in an
+//! [`IndexedHeap`] keyed by slot so the least frequent candidate is evicted
+//! in `O(log k)`: any item with more than `N / k` occurrences, plus the
+//! sketch's error, is sure to be among them.
+//!
+//! Rows are probed by double hashing one [`StableHasher`] hash, so sketches
+//! built in different processes with the same seed can be merged.
+//!
+//! [`IndexedHeap`]: crate::indexed_heap::IndexedHeap
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::indexed_heap::IndexedHeap;
+use crate::probabilistic::{self, Incompatible, DEFAULT_SEED};
+
+/// A count-min sketch over items of any `Hash` type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountMinSketch {
+    /// `depth` rows of `width` counters.
+    counters: Vec<u64>,
+    width: usize,
+    depth: u32,
+    seed: u64,
+    total: u64,
+    conservative: bool,
+}
+
+impl CountMinSketch {
+    /// Creates a sketch whose estimates exceed the true count by more than
+    /// `epsilon` times the total count with probability at most `delta`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `epsilon` and `delta` are strictly between 0 and 1.
+    pub fn new(epsilon: f64, delta: f64) -> Self {
+        assert!(
+            epsilon > 0.0 && epsilon < 1.0,
+            "epsilon must be strictly between 0 and 1"
+        );
+        assert!(
+            delta > 0.0 && delta < 1.0,
+            "delta must be strictly between 0 and 1"
+        );
+        let width = (std::f64::consts::E / epsilon).ceil() as usize;
+        let depth = (1.0 / delta).ln().ceil().max(1.0) as u32;
+        Self::with_size(width, depth, DEFAULT_SEED)
+    }
+
+    /// Creates a sketch of `depth` rows of `width` counters, seeded with
+    /// `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `depth` is zero.
+    pub fn with_size(width: usize, depth: u32, seed: u64) -> Self {
+        assert!(width > 0, "count-min sketch needs at least one column");
+        assert!(depth > 0, "count-min sketch needs at least one row");
+        CountMinSketch {
+            counters: vec![0; width * depth as usize],
+            width,
+            depth,
+            seed,
+            total: 0,
+            conservative: false,
+        }
+    }
+
+    /// Switches the sketch to conservative update.
+    pub fn with_conservative_update(mut self) -> Self {
+        self.conservative = true;
+        self
+    }
+
+    /// Whether the sketch uses conservative update.
+    pub fn is_conservative(&self) -> bool {
+        self.conservative
+    }
+
+    /// Number of counters per row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The hash seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The sum of all counts added.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Whether nothing has been added.
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Resets every counter.
+    pub fn clear(&mut self) {
+        self.counters.fill(0);
+        self.total = 0;
+    }
+
+    /// Adds one occurrence of an item.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        self.add(item, 1);
+    }
+
+    /// Adds `count` occurrences of an item. Returns its new estimate.
+    pub fn add<T: Hash + ?Sized>(&mut self, item: &T, count: u64) -> u64 {
+        self.total = self.total.saturating_add(count);
+        let hash = probabilistic::stable_hash(self.seed, item);
+        if self.conservative {
+            let estimate = self.row_cells(hash).map(|c| self.counters[c]).min();
+            let estimate = estimate.expect("at least one row").saturating_add(count);
+            for c in self.row_cells(hash) {
+                self.counters[c] = self.counters[c].max(estimate);
+            }
+            estimate
+        } else {
+            let mut estimate = u64::MAX;
+            for c in self.row_cells(hash) {
+                self.counters[c] = self.counters[c].saturating_add(count);
+                estimate = estimate.min(self.counters[c]);
+            }
+            estimate
+        }
+    }
+
+    /// An upper bound on the item's count, usually close to it.
+    pub fn estimate<T: Hash + ?Sized>(&self, item: &T) -> u64 {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        self.row_cells(hash)
+            .map(|c| self.counters[c])
+            .min()
+            .expect("at least one row")
+    }
+
+    /// Whether `other` has the same size and seed, so the two can be
+    /// merged.
+    pub fn is_compatible(&self, other: &CountMinSketch) -> bool {
+        self.width == other.width && self.depth == other.depth && self.seed == other.seed
+    }
+
+    /// Adds every count of `other` to this sketch. Merging conservative
+    /// sketches keeps estimates upper bounds, though looser than one
+    /// conservative sketch of the combined stream.
+    pub fn merge(&mut self, other: &CountMinSketch) -> Result<(), Incompatible> {
+        if !self.is_compatible(other) {
+            return Err(Incompatible);
+        }
+        for (a, &b) in self.counters.iter_mut().zip(&other.counters) {
+            *a = a.saturating_add(b);
+        }
+        self.total = self.total.saturating_add(other.total);
+        Ok(())
+    }
+
+    /// The counter in each row for an item with the given hash.
+    fn row_cells(&self, hash: u64) -> impl Iterator<Item = usize> + use<> {
+        let width = self.width;
+        probabilistic::probes(hash, self.depth, width as u64)
+            .enumerate()
+            .map(move |(row, column)| row * width + column as usize)
+    }
+}
+
+/// The `k` most frequent items of a stream, by count-min estimate.
+#[derive(Clone, Debug)]
+pub struct HeavyHitters<T> {
+    sketch: CountMinSketch,
+    capacity: usize,
+    /// Candidate items, by slot.
+    items: Vec<T>,
+    slots: HashMap<T, usize>,
+    /// Candidate slots by estimate, least first.
+    heap: IndexedHeap<u64>,
+}
+
+impl<T: Hash + Eq + Clone> HeavyHitters<T> {
+    /// Tracks the `k` most frequent items, estimated by a conservative
+    /// sketch with the given error bounds; see [`CountMinSketch::new`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero, or unless `epsilon` and `delta` are strictly
+    /// between 0 and 1.
+    pub fn new(k: usize, epsilon: f64, delta: f64) -> Self {
+        Self::with_sketch(
+            k,
+            CountMinSketch::new(epsilon, delta).with_conservative_update(),
+        )
+    }
+
+    /// Tracks the `k` most frequent items, estimated by `sketch`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero.
+    pub fn with_sketch(k: usize, sketch: CountMinSketch) -> Self {
+        assert!(k > 0, "must track at least one item");
+        HeavyHitters {
+            sketch,
+            capacity: k,
+            items: Vec::with_capacity(k),
+            slots: HashMap::with_capacity(k),
+            heap: IndexedHeap::new(),
+        }
+    }
+
+    /// The underlying sketch.
+    pub fn sketch(&self) -> &CountMinSketch {
+        &self.sketch
+    }
+
+    /// Number of items tracked at most.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Adds one occurrence of an item.
+    pub fn insert(&mut self, item: T) {
+        self.add(item, 1);
+    }
+
+    /// Adds `count` occurrences of an item.
+    pub fn add(&mut self, item: T, count: u64) {
+        let estimate = self.sketch.add(&item, count);
+        if let Some(&slot) = self.slots.get(&item) {
+            self.heap.change_priority(slot, estimate);
+        } else if self.items.len() < self.capacity {
+            let slot = self.items.len();
+            self.items.push(item.clone());
+            self.slots.insert(item, slot);
+            self.heap.insert(slot, estimate);
+        } else {
+            let (slot, &least) = self.heap.peek().expect("full");
+            if estimate > least {
+                let evicted = std::mem::replace(&mut self.items[slot], item.clone());
+                self.slots.remove(&evicted);
+                self.slots.insert(item, slot);
+                self.heap.change_priority(slot, estimate);
+            }
+        }
+    }
+
+    /// An upper bound on the item's count, from the sketch.
+    pub fn estimate(&self, item: &T) -> u64 {
+        self.sketch.estimate(item)
+    }
+
+    /// The tracked items with their estimates, most frequent first.
+    pub fn top(&self) -> Vec<(&T, u64)> {
+        let mut top: Vec<(&T, u64)> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(slot, item)| (item, *self.heap.priority(slot).expect("tracked")))
+            .collect();
+        top.sort_by_key(|&(_, estimate)| Reverse(estimate));
+        top
+    }
+
+    /// The tracked items estimated at `fraction` of the total count or
+    /// more, most frequent first.
+    pub fn above(&self, fraction: f64) -> Vec<(&T, u64)> {
+        let threshold = fraction * self.sketch.total() as f64;
+        let mut top = self.top();
+        top.retain(|&(_, estimate)| estimate as f64 >= threshold);
+        top
+    }
+}
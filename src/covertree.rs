@@ -0,0 +1,330 @@
+//! A dynamic cover tree for nearest-neighbor search under any [`Metric`].
+//!
+//! Every node is a data point with an integer level `l`. A node's children
+//! sit one level below it and lie within its *cover distance* `2^l`, and the
+//! children of one node are kept more than `2^(l - 1)` apart. For data with
+//! bounded expansion constant these invariants bound the depth and fan-out
+//! of the tree, which is what gives cover trees their `O(log n)` insertion
+//! and query guarantees independent of the ambient dimension.
+//!
+//! This is the simplified cover tree of Izbicki and Shelton. Each node also
+//! tracks an upper bound on the distance to its farthest descendant, which
+//! is what queries prune with. Removal detaches a node and reinserts its
+//! descendants, so it costs time proportional to the size of the subtree.
+//!
+//! [`Metric`]: crate::metric::Metric
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::metric::Metric;
+use crate::util::Total;
+
+/// Handle to a point stored in a [`CoverTree`].
+///
+/// Handles of removed points may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CoverId(u32);
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<P, T> {
+    point: P,
+    data: T,
+    level: i32,
+    parent: u32,
+    children: Vec<u32>,
+    /// Upper bound on the distance from this point to any descendant.
+    max_dist: f64,
+}
+
+/// A cover tree mapping points of type `P` to data of type `T` under the
+/// metric `M`.
+#[derive(Clone, Debug)]
+pub struct CoverTree<P, T, M> {
+    nodes: Vec<Option<Node<P, T>>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+    metric: M,
+}
+
+fn cover_distance(level: i32) -> f64 {
+    2f64.powi(level)
+}
+
+impl<P, T, M: Metric<P>> CoverTree<P, T, M> {
+    /// Creates an empty tree using `metric`.
+    pub fn new(metric: M) -> Self {
+        CoverTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+            metric,
+        }
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The metric the tree was built with.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// Returns the point and data behind a handle.
+    pub fn get(&self, id: CoverId) -> Option<(&P, &T)> {
+        let n = self.nodes.get(id.0 as usize)?.as_ref()?;
+        Some((&n.point, &n.data))
+    }
+
+    /// Returns the point and mutable data behind a handle.
+    pub fn get_mut(&mut self, id: CoverId) -> Option<(&P, &mut T)> {
+        let n = self.nodes.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&n.point, &mut n.data))
+    }
+
+    /// Iterates over every stored point in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (CoverId, &P, &T)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, n)| n.as_ref().map(|n| (CoverId(i as u32), &n.point, &n.data)))
+    }
+
+    /// Inserts a point, returning its handle.
+    pub fn insert(&mut self, point: P, data: T) -> CoverId {
+        let node = Node {
+            point,
+            data,
+            level: 0,
+            parent: NONE,
+            children: Vec::new(),
+            max_dist: 0.0,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id as usize] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.attach(id);
+        self.len += 1;
+        CoverId(id)
+    }
+
+    /// Removes a point, returning it and its data.
+    ///
+    /// The point's descendants are reinserted, keeping their handles.
+    pub fn remove(&mut self, id: CoverId) -> Option<(P, T)> {
+        self.nodes.get(id.0 as usize)?.as_ref()?;
+        let node = self.nodes[id.0 as usize].take()?;
+        self.free.push(id.0);
+        self.len -= 1;
+        if node.parent == NONE {
+            self.root = NONE;
+        } else {
+            let siblings = &mut self.node_mut(node.parent).children;
+            let pos = siblings
+                .iter()
+                .position(|&c| c == id.0)
+                .expect("child of its parent");
+            siblings.swap_remove(pos);
+        }
+        // Reinsert top-down so upper levels are rebuilt first.
+        let mut queue = std::collections::VecDeque::from(node.children);
+        while let Some(n) = queue.pop_front() {
+            let node = self.node_mut(n);
+            queue.extend(std::mem::take(&mut node.children));
+            node.parent = NONE;
+            node.max_dist = 0.0;
+            self.attach(n);
+        }
+        Some((node.point, node.data))
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// distances.
+    pub fn nearest(&self, query: &P, k: usize) -> Vec<(CoverId, f64)> {
+        if k == 0 || self.root == NONE {
+            return Vec::new();
+        }
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        let d = self.metric.distance(query, &self.node(self.root).point);
+        frontier.push(Reverse((
+            Total(self.lower_bound(self.root, d)),
+            self.root,
+            Total(d),
+        )));
+        while let Some(Reverse((Total(bound), n, Total(d)))) = frontier.pop() {
+            let worst = |best: &BinaryHeap<(Total, u32)>| {
+                if best.len() < k {
+                    f64::INFINITY
+                } else {
+                    best.peek().map_or(f64::INFINITY, |b| b.0 .0)
+                }
+            };
+            if bound >= worst(&best) {
+                break;
+            }
+            if d < worst(&best) {
+                if best.len() == k {
+                    best.pop();
+                }
+                best.push((Total(d), n));
+            }
+            for &c in &self.node(n).children {
+                let dc = self.metric.distance(query, &self.node(c).point);
+                let bound = self.lower_bound(c, dc);
+                if bound < worst(&best) {
+                    frontier.push(Reverse((Total(bound), c, Total(dc))));
+                }
+            }
+        }
+        let mut out: Vec<_> = best.into_iter().map(|(d, i)| (CoverId(i), d.0)).collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one(&self, query: &P) -> Option<(CoverId, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Returns every point within `radius` of `query` with its distance, in
+    /// unspecified order.
+    pub fn within_radius(&self, query: &P, radius: f64) -> Vec<(CoverId, f64)> {
+        let mut out = Vec::new();
+        let mut stack = if self.root == NONE {
+            Vec::new()
+        } else {
+            vec![self.root]
+        };
+        while let Some(n) = stack.pop() {
+            let node = self.node(n);
+            let d = self.metric.distance(query, &node.point);
+            if d - node.max_dist > radius {
+                continue;
+            }
+            if d <= radius {
+                out.push((CoverId(n), d));
+            }
+            stack.extend(&node.children);
+        }
+        out
+    }
+
+    fn node(&self, n: u32) -> &Node<P, T> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<P, T> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+
+    fn lower_bound(&self, n: u32, d: f64) -> f64 {
+        (d - self.node(n).max_dist).max(0.0)
+    }
+
+    fn dist(&self, a: u32, b: u32) -> f64 {
+        self.metric
+            .distance(&self.node(a).point, &self.node(b).point)
+    }
+
+    /// Links a detached, childless node `x` into the tree.
+    fn attach(&mut self, x: u32) {
+        if self.root == NONE {
+            self.root = x;
+            self.node_mut(x).level = 0;
+            return;
+        }
+        let mut d = self.dist(self.root, x);
+        if d > cover_distance(self.node(self.root).level) {
+            if self.node(self.root).children.is_empty() {
+                // A lone root can simply widen its cover.
+                let root = self.root;
+                let node = self.node_mut(root);
+                while cover_distance(node.level) < d {
+                    node.level += 1;
+                }
+            } else {
+                // Promote leaves to new roots until `x` is within twice the
+                // root's cover, then put `x` on top.
+                while d > 2.0 * cover_distance(self.node(self.root).level) {
+                    let leaf = self.detach_leaf();
+                    self.adopt_root(leaf);
+                    d = self.dist(self.root, x);
+                }
+                self.adopt_root(x);
+                return;
+            }
+        }
+        let mut p = self.root;
+        'descend: loop {
+            let dp = self.dist(p, x);
+            let node = self.node_mut(p);
+            node.max_dist = node.max_dist.max(dp);
+            // Duplicates hang directly off their twin rather than chaining
+            // ever deeper.
+            let children = if dp > 0.0 {
+                self.node(p).children.len()
+            } else {
+                0
+            };
+            for i in 0..children {
+                let c = self.node(p).children[i];
+                if self.dist(c, x) <= cover_distance(self.node(c).level) {
+                    p = c;
+                    continue 'descend;
+                }
+            }
+            let level = self.node(p).level - 1;
+            let node = self.node_mut(x);
+            node.level = level;
+            node.parent = p;
+            self.node_mut(p).children.push(x);
+            return;
+        }
+    }
+
+    /// Makes the detached node `q` the root, one level above the old root.
+    fn adopt_root(&mut self, q: u32) {
+        let old = self.root;
+        let d = self.dist(q, old);
+        let level = self.node(old).level + 1;
+        let bound = d + self.node(old).max_dist;
+        self.node_mut(old).parent = q;
+        let node = self.node_mut(q);
+        node.level = level;
+        node.parent = NONE;
+        node.children.push(old);
+        node.max_dist = bound;
+        self.root = q;
+    }
+
+    /// Unlinks and returns some leaf below the root.
+    fn detach_leaf(&mut self) -> u32 {
+        let mut n = self.root;
+        while let Some(&c) = self.node(n).children.last() {
+            n = c;
+        }
+        let parent = self.node(n).parent;
+        self.node_mut(parent).children.pop();
+        self.node_mut(n).parent = NONE;
+        n
+    }
+}
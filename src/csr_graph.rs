@@ -0,0 +1,280 @@
+//! A static directed graph in compressed sparse row form.
+//!
+//! Compressed sparse row (CSR) storage lays the out-edges of every node
+//! end to end in one array, in node order, and keeps a second array of
+//! offsets: the edges leaving node `u` are positions `offsets[u]` to
+//! `offsets[u + 1]`. Neighbor iteration is then a scan of a contiguous
+//! slice, with no pointer chasing and one 4-byte target per edge, which is
+//! why graph libraries and sparse linear algebra alike use it for graphs
+//! that do not change.
+//!
+//! Nodes are numbered `0..node_count`, and edges by their position in the
+//! edge array, which is the order the graph iterates them: grouped by
+//! source, and within a source sorted by target, with parallel edges in
+//! input order. Sorted rows let [`CsrGraph::find_edge`] binary search.
+//! Nodes and edges carry payloads of types `N` and `E`; use `()` for
+//! either when there is nothing to store.
+//!
+//! The graph is built once from an edge list and cannot be modified after,
+//! apart from the payloads. [`CsrGraph::transpose`] builds the graph with
+//! every edge reversed in `O(n + m)`, for algorithms that need in-edges.
+
+use std::ops::Range;
+
+/// A static directed graph with node payloads `N` and edge payloads `E`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsrGraph<N, E> {
+    nodes: Vec<N>,
+    /// The edges leaving node `u` are `offsets[u]..offsets[u + 1]`.
+    offsets: Vec<usize>,
+    targets: Vec<u32>,
+    edges: Vec<E>,
+}
+
+impl<N, E> Default for CsrGraph<N, E> {
+    fn default() -> Self {
+        CsrGraph {
+            nodes: Vec::new(),
+            offsets: vec![0],
+            targets: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+impl CsrGraph<(), ()> {
+    /// Builds a graph of `node_count` nodes from `(source, target)` pairs,
+    /// without payloads.
+    ///
+    /// # Panics
+    ///
+    /// Panics as [`CsrGraph::build`] does.
+    pub fn from_edges(node_count: usize, edges: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        Self::build(
+            vec![(); node_count],
+            edges.into_iter().map(|(u, v)| (u, v, ())).collect(),
+        )
+    }
+}
+
+impl<N, E> CsrGraph<N, E> {
+    /// Builds a graph whose node `u` has payload `nodes[u]`, from
+    /// `(source, target, payload)` edges in any order, in `O(n + m log m)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge names a node out of range, or if there are more
+    /// than `u32::MAX` nodes.
+    pub fn build(nodes: Vec<N>, mut edges: Vec<(usize, usize, E)>) -> Self {
+        let n = nodes.len();
+        assert!(n <= u32::MAX as usize, "too many nodes");
+        assert!(
+            edges.iter().all(|&(u, v, _)| u < n && v < n),
+            "edge endpoint out of range"
+        );
+        edges.sort_by_key(|&(u, v, _)| (u, v));
+        let mut offsets = vec![0; n + 1];
+        for &(u, _, _) in &edges {
+            offsets[u + 1] += 1;
+        }
+        for u in 0..n {
+            offsets[u + 1] += offsets[u];
+        }
+        let (targets, edges) = edges.into_iter().map(|(_, v, e)| (v as u32, e)).unzip();
+        CsrGraph {
+            nodes,
+            offsets,
+            targets,
+            edges,
+        }
+    }
+
+    /// Builds an undirected graph, stored with each edge in both
+    /// directions; a self-loop is stored once.
+    ///
+    /// # Panics
+    ///
+    /// Panics as [`CsrGraph::build`] does.
+    pub fn build_undirected(nodes: Vec<N>, edges: Vec<(usize, usize, E)>) -> Self
+    where
+        E: Clone,
+    {
+        let mut both = Vec::with_capacity(2 * edges.len());
+        for (u, v, e) in edges {
+            if u != v {
+                both.push((v, u, e.clone()));
+            }
+            both.push((u, v, e));
+        }
+        Self::build(nodes, both)
+    }
+
+    /// Number of nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    /// Whether the graph has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The payload of node `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn node(&self, u: usize) -> &N {
+        &self.nodes[u]
+    }
+
+    /// The mutable payload of node `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn node_mut(&mut self, u: usize) -> &mut N {
+        &mut self.nodes[u]
+    }
+
+    /// The node payloads, indexed by node.
+    pub fn nodes(&self) -> &[N] {
+        &self.nodes
+    }
+
+    /// Number of edges leaving `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn degree(&self, u: usize) -> usize {
+        self.offsets[u + 1] - self.offsets[u]
+    }
+
+    /// The ids of the edges leaving `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn edge_range(&self, u: usize) -> Range<usize> {
+        self.offsets[u]..self.offsets[u + 1]
+    }
+
+    /// The targets of the edges leaving `u`, in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn neighbors(&self, u: usize) -> impl ExactSizeIterator<Item = usize> + '_ {
+        self.targets[self.edge_range(u)].iter().map(|&v| v as usize)
+    }
+
+    /// The edges leaving `u` as `(target, payload)` pairs, by target.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn out_edges(&self, u: usize) -> impl ExactSizeIterator<Item = (usize, &E)> + '_ {
+        let range = self.edge_range(u);
+        self.targets[range.clone()]
+            .iter()
+            .zip(&self.edges[range])
+            .map(|(&v, e)| (v as usize, e))
+    }
+
+    /// The target of edge `e`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `e` is out of range.
+    pub fn target(&self, e: usize) -> usize {
+        self.targets[e] as usize
+    }
+
+    /// The source of edge `e`, found by binary search in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `e` is out of range.
+    pub fn source(&self, e: usize) -> usize {
+        assert!(e < self.edge_count(), "edge out of range");
+        self.offsets.partition_point(|&o| o <= e) - 1
+    }
+
+    /// The payload of edge `e`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `e` is out of range.
+    pub fn edge(&self, e: usize) -> &E {
+        &self.edges[e]
+    }
+
+    /// The mutable payload of edge `e`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `e` is out of range.
+    pub fn edge_mut(&mut self, e: usize) -> &mut E {
+        &mut self.edges[e]
+    }
+
+    /// The id of the first edge from `u` to `v`, in `O(log degree(u))`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` is out of range.
+    pub fn find_edge(&self, u: usize, v: usize) -> Option<usize> {
+        let range = self.edge_range(u);
+        let row = &self.targets[range.clone()];
+        let i = row.partition_point(|&t| (t as usize) < v);
+        (i < row.len() && row[i] as usize == v).then_some(range.start + i)
+    }
+
+    /// Iterates over every edge as `(source, target, payload)`, in id
+    /// order.
+    pub fn iter_edges(&self) -> impl Iterator<Item = (usize, usize, &E)> + '_ {
+        (0..self.node_count()).flat_map(move |u| self.out_edges(u).map(move |(v, e)| (u, v, e)))
+    }
+
+    /// The graph with every edge reversed, in `O(n + m)`. An edge's
+    /// reverse keeps its payload.
+    pub fn transpose(&self) -> Self
+    where
+        N: Clone,
+        E: Clone,
+    {
+        let n = self.node_count();
+        let mut offsets = vec![0; n + 1];
+        for &v in &self.targets {
+            offsets[v as usize + 1] += 1;
+        }
+        for v in 0..n {
+            offsets[v + 1] += offsets[v];
+        }
+        // Visiting sources in order leaves every reversed row sorted.
+        let mut next = offsets.clone();
+        let mut targets = vec![0; self.edge_count()];
+        // The original id of the edge at each reversed position.
+        let mut ids = vec![0; self.edge_count()];
+        for u in 0..n {
+            for e in self.edge_range(u) {
+                let at = &mut next[self.targets[e] as usize];
+                targets[*at] = u as u32;
+                ids[*at] = e;
+                *at += 1;
+            }
+        }
+        CsrGraph {
+            nodes: self.nodes.clone(),
+            offsets,
+            targets,
+            edges: ids.into_iter().map(|e| self.edges[e].clone()).collect(),
+        }
+    }
+}
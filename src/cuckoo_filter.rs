@@ -0,0 +1,358 @@
+//! A cuckoo filter: approximate set membership with deletion.
+//!
+//! A cuckoo filter (Fan, Andersen, Kaminsky and Mitzenmacher, 2014) stores
+//! a short fingerprint of each item in a cuckoo hash table of buckets with
+//! four slots each. An item may live in either of two buckets, the second
+//! found from the first and the fingerprint alone by partial-key cuckoo
+//! hashing: `i₂ = i₁ ⊕ hash(fingerprint)`. That lets an insertion into two
+//! full buckets evict a resident fingerprint to its other bucket without
+//! knowing the item it came from, and lets a removal find and delete one
+//! copy of a fingerprint exactly, which a Bloom filter cannot do.
+//!
+//! A lookup compares the fingerprints in two buckets, so with `f`-bit
+//! fingerprints and the table at load factor `α` it reports a false
+//! positive with probability about `8α / 2^f`. The slots are packed at `f`
+//! bits each; with the table filled to 95%, that is
+//! `(log₂(1/ε) + 3) / 0.95` bits per item against a Bloom filter's
+//! `1.44 log₂(1/ε)`: the cuckoo filter is the smaller of the two below a
+//! false positive rate of about 3%, and reads only two cache lines per
+//! lookup however low the rate. [`CuckooFilter::new`] rounds the table up
+//! to a power of two buckets and picks the fewest fingerprint bits that
+//! meet the target at the load the expected items will reach.
+//!
+//! Insertion fails once a chain of 500 evictions finds no free slot. The
+//! fingerprint left over is kept aside, so nothing inserted is ever lost,
+//! and later insertions report [`Full`] until removals make room for it.
+//! Removing an item that was never inserted may delete another item's
+//! fingerprint, so only remove what was inserted.
+//!
+//! [`to_bytes`](CuckooFilter::to_bytes) packs the buckets at `f` bits a
+//! slot, with fingerprints from the shared [`StableHasher`], so a filter
+//! can be built in one service and queried in another.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::probabilistic::{self, DecodeError, Reader, DEFAULT_SEED};
+use crate::rng::SplitMix64;
+
+const SLOTS: usize = 4;
+const MAX_KICKS: usize = 500;
+const MAX_FINGERPRINT_BITS: u32 = 32;
+const TAG: &[u8; 4] = b"CKF1";
+
+/// Returned when an item cannot be inserted because the filter is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Full;
+
+impl fmt::Display for Full {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cuckoo filter is full")
+    }
+}
+
+impl Error for Full {}
+
+/// A cuckoo filter over items of any `Hash` type.
+#[derive(Clone, Debug)]
+pub struct CuckooFilter {
+    /// `SLOTS` fingerprints per bucket, packed at `fingerprint_bits` bits
+    /// each from the low bits up; zero marks an empty slot.
+    words: Vec<u64>,
+    buckets: usize,
+    /// The fingerprint an insertion could not place, and one of its buckets.
+    victim: Option<(usize, u32)>,
+    fingerprint_bits: u32,
+    seed: u64,
+    len: usize,
+    rng: SplitMix64,
+}
+
+impl CuckooFilter {
+    /// Creates a filter with room for `expected_items` items at a false
+    /// positive rate of about `false_positive_rate`.
+    ///
+    /// Rates below what 32-bit fingerprints reach, about `2·10⁻⁹` with the
+    /// table nearly full, get 32-bit fingerprints.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `false_positive_rate` is strictly between 0 and 1.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be strictly between 0 and 1"
+        );
+        let items = expected_items.max(1) as f64;
+        let buckets = ((items / (0.95 * SLOTS as f64)).ceil() as usize).next_power_of_two();
+        let load = items / (buckets * SLOTS) as f64;
+        let bits = (2.0 * SLOTS as f64 * load / false_positive_rate)
+            .log2()
+            .ceil()
+            .clamp(1.0, f64::from(MAX_FINGERPRINT_BITS));
+        Self::with_size(buckets, bits as u32, DEFAULT_SEED)
+    }
+
+    /// Creates a filter of at least `buckets` buckets, rounded up to a power
+    /// of two, holding `fingerprint_bits`-bit fingerprints, seeded with
+    /// `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `fingerprint_bits` is between 1 and 32.
+    pub fn with_size(buckets: usize, fingerprint_bits: u32, seed: u64) -> Self {
+        assert!(
+            (1..=MAX_FINGERPRINT_BITS).contains(&fingerprint_bits),
+            "fingerprints must have 1 to 32 bits"
+        );
+        let buckets = buckets.max(1).next_power_of_two();
+        let bits = buckets * SLOTS * fingerprint_bits as usize;
+        CuckooFilter {
+            words: vec![0; bits.div_ceil(64)],
+            buckets,
+            victim: None,
+            fingerprint_bits,
+            seed,
+            len: 0,
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the filter is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of fingerprint slots.
+    pub fn capacity(&self) -> usize {
+        self.buckets * SLOTS
+    }
+
+    /// The share of slots in use.
+    pub fn load_factor(&self) -> f64 {
+        self.len as f64 / self.capacity() as f64
+    }
+
+    /// Number of bits in a fingerprint.
+    pub fn fingerprint_bits(&self) -> u32 {
+        self.fingerprint_bits
+    }
+
+    /// The hash seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+        self.victim = None;
+        self.len = 0;
+    }
+
+    /// Inserts an item. Inserting an item twice stores it twice, so it takes
+    /// two removals to delete.
+    ///
+    /// Returns [`Full`], changing nothing, if an earlier insertion failed to
+    /// find room and removals have not made room since.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> Result<(), Full> {
+        if self.victim.is_some() {
+            return Err(Full);
+        }
+        let (bucket, fp) = self.locate(item);
+        self.victim = self.place(bucket, fp);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Whether the item may have been inserted and not removed. Never
+    /// `false` for an item present.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        let (bucket, fp) = self.locate(item);
+        let alt = self.alternate(bucket, fp);
+        self.find(bucket, fp).is_some()
+            || self.find(alt, fp).is_some()
+            || self
+                .victim
+                .is_some_and(|(b, f)| f == fp && (b == bucket || b == alt))
+    }
+
+    /// Removes one copy of an item. Returns `false` if it is certainly
+    /// absent.
+    pub fn remove<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+        let (bucket, fp) = self.locate(item);
+        let alt = self.alternate(bucket, fp);
+        if let Some((b, f)) = self.victim {
+            if f == fp && (b == bucket || b == alt) {
+                self.victim = None;
+                self.len -= 1;
+                return true;
+            }
+        }
+        let Some(slot) = self.find(bucket, fp).or_else(|| self.find(alt, fp)) else {
+            return false;
+        };
+        self.set_slot(slot, 0);
+        self.len -= 1;
+        // The freed slot may make room for the fingerprint set aside.
+        if let Some((b, f)) = self.victim {
+            self.victim = self.place(b, f);
+        }
+        true
+    }
+
+    /// Serializes the filter: a tag, the fingerprint width, seed, bucket
+    /// count and item count, the fingerprint set aside if any, then every
+    /// slot packed at the fingerprint width, little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let packed = self.packed_len();
+        let mut bytes = Vec::with_capacity(44 + packed);
+        bytes.extend_from_slice(TAG);
+        bytes.extend_from_slice(&self.fingerprint_bits.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.buckets as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.len as u64).to_le_bytes());
+        let (bucket, fp) = self.victim.map_or((0, 0), |(b, f)| (b as u64, f));
+        bytes.extend_from_slice(&bucket.to_le_bytes());
+        bytes.extend_from_slice(&fp.to_le_bytes());
+        // The slots are already packed in order from the low bits up.
+        bytes.extend(self.words.iter().flat_map(|w| w.to_le_bytes()).take(packed));
+        bytes
+    }
+
+    /// Reads back a filter written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, TAG)?;
+        let fingerprint_bits = reader.u32()?;
+        let seed = reader.u64()?;
+        let buckets = reader.u64()?;
+        let len = reader.u64()?;
+        let victim_bucket = reader.u64()?;
+        let victim_fp = reader.u32()?;
+        if !(1..=MAX_FINGERPRINT_BITS).contains(&fingerprint_bits) || !buckets.is_power_of_two() {
+            return Err(DecodeError::Invalid);
+        }
+        let bits = usize::try_from(buckets)
+            .ok()
+            .and_then(|b| b.checked_mul(SLOTS * fingerprint_bits as usize))
+            .ok_or(DecodeError::Invalid)?;
+        let data = reader.take(bits.div_ceil(8))?;
+        reader.finish()?;
+        let mut filter = CuckooFilter::with_size(buckets as usize, fingerprint_bits, seed);
+        for (word, chunk) in filter.words.iter_mut().zip(data.chunks(8)) {
+            let mut buf = [0; 8];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            *word = u64::from_le_bytes(buf);
+        }
+        // Bits past the last slot are never set.
+        if bits % 64 != 0 && filter.words[bits / 64] >> (bits % 64) != 0 {
+            return Err(DecodeError::Invalid);
+        }
+        if victim_fp != 0 {
+            if u64::from(victim_fp) > filter.fingerprint_mask() || victim_bucket >= buckets {
+                return Err(DecodeError::Invalid);
+            }
+            filter.victim = Some((victim_bucket as usize, victim_fp));
+        }
+        let stored = (0..filter.capacity())
+            .filter(|&i| filter.slot(i) != 0)
+            .count()
+            + usize::from(victim_fp != 0);
+        if len != stored as u64 {
+            return Err(DecodeError::Invalid);
+        }
+        filter.len = stored;
+        Ok(filter)
+    }
+
+    /// Stores a fingerprint in one of its buckets, evicting others to their
+    /// alternate buckets as needed. Returns the fingerprint left without a
+    /// slot, and its bucket, if the evictions give up.
+    fn place(&mut self, mut bucket: usize, mut fp: u32) -> Option<(usize, u32)> {
+        let alt = self.alternate(bucket, fp);
+        if self.put(bucket, fp) || self.put(alt, fp) {
+            return None;
+        }
+        if self.rng.next_u64() & 1 == 1 {
+            bucket = alt;
+        }
+        for _ in 0..MAX_KICKS {
+            let slot = bucket * SLOTS + (self.rng.next_u64() % SLOTS as u64) as usize;
+            let evicted = self.slot(slot);
+            self.set_slot(slot, fp);
+            fp = evicted;
+            bucket = self.alternate(bucket, fp);
+            if self.put(bucket, fp) {
+                return None;
+            }
+        }
+        Some((bucket, fp))
+    }
+
+    /// The item's first bucket and its nonzero fingerprint.
+    fn locate<T: Hash + ?Sized>(&self, item: &T) -> (usize, u32) {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        let fp = ((hash >> 32) % self.fingerprint_mask() + 1) as u32;
+        ((hash as usize) & (self.buckets - 1), fp)
+    }
+
+    /// The other bucket a fingerprint in `bucket` may live in.
+    fn alternate(&self, bucket: usize, fp: u32) -> usize {
+        bucket ^ (probabilistic::mix(u64::from(fp)) as usize & (self.buckets - 1))
+    }
+
+    fn find(&self, bucket: usize, fp: u32) -> Option<usize> {
+        (bucket * SLOTS..(bucket + 1) * SLOTS).find(|&s| self.slot(s) == fp)
+    }
+
+    /// Puts the fingerprint in a free slot of the bucket, if there is one.
+    fn put(&mut self, bucket: usize, fp: u32) -> bool {
+        match self.find(bucket, 0) {
+            Some(slot) => {
+                self.set_slot(slot, fp);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The largest fingerprint, all `fingerprint_bits` bits set.
+    fn fingerprint_mask(&self) -> u64 {
+        (1 << self.fingerprint_bits) - 1
+    }
+
+    /// Bytes the packed slots take, rounded up to a whole byte.
+    fn packed_len(&self) -> usize {
+        (self.capacity() * self.fingerprint_bits as usize).div_ceil(8)
+    }
+
+    /// The fingerprint in slot `i`, which may straddle two words.
+    fn slot(&self, i: usize) -> u32 {
+        let bit = i * self.fingerprint_bits as usize;
+        let (word, offset) = (bit / 64, bit % 64);
+        let mut value = self.words[word] >> offset;
+        if offset + self.fingerprint_bits as usize > 64 {
+            value |= self.words[word + 1] << (64 - offset);
+        }
+        (value & self.fingerprint_mask()) as u32
+    }
+
+    fn set_slot(&mut self, i: usize, fp: u32) {
+        let bit = i * self.fingerprint_bits as usize;
+        let (word, offset) = (bit / 64, bit % 64);
+        let (mask, fp) = (self.fingerprint_mask(), u64::from(fp));
+        self.words[word] = (self.words[word] & !(mask << offset)) | (fp << offset);
+        if offset + self.fingerprint_bits as usize > 64 {
+            let shift = 64 - offset;
+            self.words[word + 1] = (self.words[word + 1] & !(mask >> shift)) | (fp >> shift);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Exact cover by dancing links.
+//!
+//! An exact cover problem gives a set of columns and a collection of rows,
+//! each row a subset of the columns, and asks for rows that together hold
+//! every column exactly once. Sudoku, polyomino tiling and the n-queens
+//! problem all reduce to it. Knuth's Algorithm X solves it by
+//! backtracking: pick an uncovered column, try each row that holds it, and
+//! remove every row that clashes with the choice before recursing.
+//!
+//! Dancing links (Knuth, 2000) makes the removals cheap. The matrix is
+//! stored sparsely as a toroidal grid of nodes, one per one-entry, linked
+//! to its neighbors in four directions. Unlinking a node leaves its own
+//! links intact, so the same links put it back in `O(1)`, and a whole
+//! column and the rows through it are covered and uncovered by walking
+//! only the rows concerned. The search always branches on the column with
+//! the fewest remaining rows, which keeps the tree small.
+//!
+//! Secondary columns may be covered at most once rather than exactly once,
+//! which expresses constraints such as the diagonals of n-queens. Every
+//! row chosen covers some primary column, so a row made only of secondary
+//! columns never appears in a solution. The search is an iterator,
+//! [`ExactCover::solutions`], that yields each solution as it is found and
+//! restores the matrix as it goes, so the problem can be solved again, or
+//! have rows added, afterwards.
+
+use std::iter::FusedIterator;
+
+/// Index of the root header.
+const ROOT: usize = 0;
+
+#[derive(Clone, Debug)]
+struct Node {
+    left: u32,
+    right: u32,
+    up: u32,
+    down: u32,
+    /// The column header, or the node itself for a header.
+    col: u32,
+    /// The row the node belongs to; unused for headers.
+    row: u32,
+}
+
+/// A sparse 0/1 matrix of rows over columns, for exact cover search.
+#[derive(Clone, Debug)]
+pub struct ExactCover {
+    /// The root, then one header per column, then the row nodes.
+    nodes: Vec<Node>,
+    /// Rows remaining in each column, indexed by header.
+    sizes: Vec<u32>,
+    primary: usize,
+    columns: usize,
+    /// The first node of each row.
+    rows: Vec<u32>,
+}
+
+impl ExactCover {
+    /// Creates a problem over `columns` primary columns and no rows.
+    pub fn new(columns: usize) -> Self {
+        Self::with_secondary(columns, 0)
+    }
+
+    /// Creates a problem over `primary` columns that must each be covered
+    /// exactly once, numbered first, and `secondary` columns that may be
+    /// covered at most once, numbered after them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than `u32::MAX` columns in all.
+    pub fn with_secondary(primary: usize, secondary: usize) -> Self {
+        let columns = primary + secondary;
+        assert!(columns < u32::MAX as usize, "too many columns");
+        let mut nodes = Vec::with_capacity(columns + 1);
+        for h in 0..=columns {
+            // Primary headers form a ring through the root; secondary ones
+            // link only to themselves, so the search never selects them.
+            let (left, right) = if h <= primary {
+                ((h + primary) % (primary + 1), (h + 1) % (primary + 1))
+            } else {
+                (h, h)
+            };
+            nodes.push(Node {
+                left: left as u32,
+                right: right as u32,
+                up: h as u32,
+                down: h as u32,
+                col: h as u32,
+                row: u32::MAX,
+            });
+        }
+        ExactCover {
+            nodes,
+            sizes: vec![0; columns + 1],
+            primary,
+            columns,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Number of columns, primary and secondary.
+    pub fn column_count(&self) -> usize {
+        self.columns
+    }
+
+    /// Number of primary columns.
+    pub fn primary_count(&self) -> usize {
+        self.primary
+    }
+
+    /// Number of rows.
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Adds a row holding the given columns, returning its index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a column is out of range or repeated, or if the row is
+    /// empty.
+    pub fn add_row(&mut self, columns: &[usize]) -> usize {
+        assert!(!columns.is_empty(), "rows must hold at least one column");
+        assert!(
+            columns.iter().all(|&c| c < self.columns),
+            "column out of range"
+        );
+        let mut sorted = columns.to_vec();
+        sorted.sort_unstable();
+        assert!(
+            sorted.windows(2).all(|w| w[0] != w[1]),
+            "column repeated in a row"
+        );
+        let row = self.rows.len() as u32;
+        let first = self.nodes.len() as u32;
+        let last = first + columns.len() as u32 - 1;
+        for (k, &c) in columns.iter().enumerate() {
+            let n = first + k as u32;
+            let header = c as u32 + 1;
+            let up = self.nodes[header as usize].up;
+            self.nodes.push(Node {
+                left: if n == first { last } else { n - 1 },
+                right: if n == last { first } else { n + 1 },
+                up,
+                down: header,
+                col: header,
+                row,
+            });
+            self.nodes[up as usize].down = n;
+            self.nodes[header as usize].up = n;
+            self.sizes[header as usize] += 1;
+        }
+        self.rows.push(first);
+        row as usize
+    }
+
+    /// The columns of row `row`, in the order they were given.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row` is out of range.
+    pub fn row(&self, row: usize) -> impl Iterator<Item = usize> + '_ {
+        let first = self.rows[row];
+        let mut n = Some(first);
+        std::iter::from_fn(move || {
+            let cur = n?;
+            let next = self.nodes[cur as usize].right;
+            n = (next != first).then_some(next);
+            Some(self.nodes[cur as usize].col as usize - 1)
+        })
+    }
+
+    /// Iterates over every exact cover, each as the ascending indices of
+    /// its rows. Dropping the iterator early restores the matrix.
+    pub fn solutions(&mut self) -> Solutions<'_> {
+        Solutions {
+            problem: self,
+            chosen: Vec::new(),
+            started: false,
+            done: false,
+        }
+    }
+
+    /// The first exact cover found, if there is one.
+    pub fn solve(&mut self) -> Option<Vec<usize>> {
+        self.solutions().next()
+    }
+
+    /// Number of exact covers.
+    pub fn count_solutions(&mut self) -> usize {
+        self.solutions().count()
+    }
+
+    /// Removes column header `c` from the header ring and every row
+    /// through it from the other columns.
+    fn cover(&mut self, c: u32) {
+        let Node { left, right, .. } = self.nodes[c as usize];
+        self.nodes[left as usize].right = right;
+        self.nodes[right as usize].left = left;
+        let mut i = self.nodes[c as usize].down;
+        while i != c {
+            let mut j = self.nodes[i as usize].right;
+            while j != i {
+                let Node { up, down, col, .. } = self.nodes[j as usize];
+                self.nodes[up as usize].down = down;
+                self.nodes[down as usize].up = up;
+                self.sizes[col as usize] -= 1;
+                j = self.nodes[j as usize].right;
+            }
+            i = self.nodes[i as usize].down;
+        }
+    }
+
+    /// Undoes [`cover`](Self::cover), in exactly the reverse order.
+    fn uncover(&mut self, c: u32) {
+        let mut i = self.nodes[c as usize].up;
+        while i != c {
+            let mut j = self.nodes[i as usize].left;
+            while j != i {
+                let Node { up, down, col, .. } = self.nodes[j as usize];
+                self.sizes[col as usize] += 1;
+                self.nodes[up as usize].down = j;
+                self.nodes[down as usize].up = j;
+                j = self.nodes[j as usize].left;
+            }
+            i = self.nodes[i as usize].up;
+        }
+        let Node { left, right, .. } = self.nodes[c as usize];
+        self.nodes[left as usize].right = c;
+        self.nodes[right as usize].left = c;
+    }
+
+    /// Covers the columns of the row through `r` other than `r`'s own.
+    fn choose(&mut self, r: u32) {
+        let mut j = self.nodes[r as usize].right;
+        while j != r {
+            self.cover(self.nodes[j as usize].col);
+            j = self.nodes[j as usize].right;
+        }
+    }
+
+    /// Undoes [`choose`](Self::choose).
+    fn unchoose(&mut self, r: u32) {
+        let mut j = self.nodes[r as usize].left;
+        while j != r {
+            self.uncover(self.nodes[j as usize].col);
+            j = self.nodes[j as usize].left;
+        }
+    }
+
+    /// The uncovered primary column with the fewest rows, or `None` when
+    /// every primary column is covered.
+    fn pick_column(&self) -> Option<u32> {
+        let mut best = None;
+        let mut c = self.nodes[ROOT].right;
+        while c as usize != ROOT {
+            if best.is_none_or(|b: u32| self.sizes[c as usize] < self.sizes[b as usize]) {
+                best = Some(c);
+            }
+            c = self.nodes[c as usize].right;
+        }
+        best
+    }
+}
+
+/// Iterator over the solutions of an [`ExactCover`] problem.
+pub struct Solutions<'a> {
+    problem: &'a mut ExactCover,
+    /// The row node chosen at each level of the search.
+    chosen: Vec<u32>,
+    started: bool,
+    done: bool,
+}
+
+impl Solutions<'_> {
+    /// Moves to the next untried row at the deepest level, popping levels
+    /// whose rows are exhausted. Returns `false` once the search is over.
+    fn advance(&mut self) -> bool {
+        let p = &mut *self.problem;
+        while let Some(r) = self.chosen.pop() {
+            p.unchoose(r);
+            let next = p.nodes[r as usize].down;
+            let c = p.nodes[r as usize].col;
+            if next != c {
+                p.choose(next);
+                self.chosen.push(next);
+                return true;
+            }
+            p.uncover(c);
+        }
+        false
+    }
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done {
+            return None;
+        }
+        if self.started && !self.advance() {
+            self.done = true;
+            return None;
+        }
+        self.started = true;
+        loop {
+            let p = &mut *self.problem;
+            match p.pick_column() {
+                None => {
+                    let mut rows: Vec<usize> = self
+                        .chosen
+                        .iter()
+                        .map(|&r| p.nodes[r as usize].row as usize)
+                        .collect();
+                    rows.sort_unstable();
+                    return Some(rows);
+                }
+                Some(c) => {
+                    let r = p.nodes[c as usize].down;
+                    if r == c {
+                        // A column no row can cover: this branch is dead.
+                        if !self.advance() {
+                            self.done = true;
+                            return None;
+                        }
+                    } else {
+                        p.cover(c);
+                        p.choose(r);
+                        self.chosen.push(r);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for Solutions<'_> {}
+
+impl Drop for Solutions<'_> {
+    fn drop(&mut self) {
+        while let Some(r) = self.chosen.pop() {
+            self.problem.unchoose(r);
+            let c = self.problem.nodes[r as usize].col;
+            self.problem.uncover(c);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Elias–Fano coding of a monotone sequence, with random access and
+//! successor queries.
+//!
+//! The Elias–Fano representation (Elias, 1974; Fano, 1971) of `n` sorted
+//! integers below `u` splits each value into its low `l = ⌊log₂(u / n)⌋`
+//! bits, stored verbatim in a packed array, and its high bits, stored in
+//! unary: value `i` sets bit `high(i) + i` of a bit vector of about `2n`
+//! bits. That totals `n (2 + ⌈log₂(u / n)⌉)` bits, within two bits per
+//! item of the information-theoretic minimum, however the values are
+//! spread.
+//!
+//! Position `i`'s high bits are `select1(i) - i` on the [`BitVector`], so
+//! access takes one select. Finding the first value at least `x`, the
+//! `next_geq` of posting-list intersection, takes one `select0` to reach
+//! the bucket of values sharing `x`'s high bits, then a scan of that
+//! bucket, which holds about `2^l ≈ u / n` values at most and usually very
+//! few.
+//!
+//! Besides posting lists, a sorted list of offsets compresses well this
+//! way: [`ZOrderIndex::cell_starts`] returns the start of every occupied
+//! cell at some depth of the implicit octree, a child index for the
+//! pointerless octree in a few bits per cell.
+//!
+//! [`BitVector`]: crate::bit_vector::BitVector
+//! [`ZOrderIndex::cell_starts`]: crate::zorder::ZOrderIndex::cell_starts
+
+use crate::bit_vector::BitVector;
+
+/// A non-decreasing sequence of `u64`s in Elias–Fano coding.
+#[derive(Clone, Debug)]
+pub struct EliasFano {
+    /// Unary-coded high bits, one set bit per value.
+    highs: BitVector,
+    /// Low bits, `low_bits` per value, packed least significant first.
+    lows: Vec<u64>,
+    low_bits: u32,
+    len: usize,
+}
+
+impl EliasFano {
+    /// Encodes `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is not sorted in non-decreasing order.
+    pub fn new(values: &[u64]) -> Self {
+        assert!(
+            values.windows(2).all(|w| w[0] <= w[1]),
+            "Elias–Fano values must be non-decreasing"
+        );
+        let len = values.len();
+        let universe = values.last().map_or(0, |&v| v);
+        let low_bits = if len == 0 || universe / len as u64 == 0 {
+            0
+        } else {
+            63 - (universe / len as u64).leading_zeros()
+        };
+        let mut lows = vec![0u64; (len * low_bits as usize).div_ceil(64)];
+        let high_len = len + (universe >> low_bits) as usize + 1;
+        let mut highs = vec![0u64; high_len.div_ceil(64)];
+        for (i, &v) in values.iter().enumerate() {
+            let h = (v >> low_bits) as usize + i;
+            highs[h / 64] |= 1 << (h % 64);
+            if low_bits > 0 {
+                let low = v & ((1 << low_bits) - 1);
+                let at = i * low_bits as usize;
+                lows[at / 64] |= low << (at % 64);
+                if at % 64 + low_bits as usize > 64 {
+                    lows[at / 64 + 1] |= low >> (64 - at % 64);
+                }
+            }
+        }
+        EliasFano {
+            highs: BitVector::from_words(highs, high_len),
+            lows,
+            low_bits,
+            len,
+        }
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bits spent per value, not counting the bit vector's directories.
+    pub fn bits_per_value(&self) -> f64 {
+        (self.highs.len() + self.lows.len() * 64) as f64 / self.len.max(1) as f64
+    }
+
+    /// The value at position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> u64 {
+        assert!(
+            i < self.len,
+            "index {i} out of bounds for length {}",
+            self.len
+        );
+        let high = self.highs.select1(i).expect("one bit per value") - i;
+        (high as u64) << self.low_bits | self.low(i)
+    }
+
+    /// The first position whose value is at least `x`, and that value, or
+    /// `None` if every value is smaller.
+    pub fn next_geq(&self, x: u64) -> Option<(usize, u64)> {
+        let high = x >> self.low_bits;
+        if high >= (self.highs.len() - self.len) as u64 {
+            return None;
+        }
+        // Values with smaller high bits end at the `high`th zero.
+        let (mut i, mut position) = if high == 0 {
+            (0, 0)
+        } else {
+            let zero = self.highs.select0(high as usize - 1)?;
+            (zero + 1 - high as usize, zero + 1)
+        };
+        while i < self.len {
+            // Skip zeros: each moves to the next high value.
+            while !self.highs.get(position) {
+                position += 1;
+            }
+            let value = ((position - i) as u64) << self.low_bits | self.low(i);
+            if value >= x {
+                return Some((i, value));
+            }
+            i += 1;
+            position += 1;
+        }
+        None
+    }
+
+    /// Iterates over the values.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut position = 0;
+        (0..self.len).map(move |i| {
+            while !self.highs.get(position) {
+                position += 1;
+            }
+            position += 1;
+            ((position - 1 - i) as u64) << self.low_bits | self.low(i)
+        })
+    }
+
+    fn low(&self, i: usize) -> u64 {
+        let bits = self.low_bits as usize;
+        if bits == 0 {
+            return 0;
+        }
+        let at = i * bits;
+        let mut low = self.lows[at / 64] >> (at % 64);
+        if at % 64 + bits > 64 {
+            low |= self.lows[at / 64 + 1] << (64 - at % 64);
+        }
+        low & ((1 << bits) - 1)
+    }
+}
+
+impl FromIterator<u64> for EliasFano {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let values: Vec<u64> = iter.into_iter().collect();
+        EliasFano::new(&values)
+    }
+}
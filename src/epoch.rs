@@ -0,0 +1,260 @@
+//! Epoch-based memory reclamation for the lock-free structures.
+//!
+//! A lock-free structure cannot free a node the moment it unlinks it:
+//! another thread may have read a pointer to it just before and still be
+//! about to dereference it. Epoch-based reclamation (Fraser, 2004) delays
+//! the free until no thread can hold such a pointer. A global epoch
+//! counter advances over time, and each thread announces the epoch it saw
+//! whenever it is *pinned*, inside an operation that may dereference
+//! shared pointers. An unlinked node is retired with the current epoch
+//! and freed once the global epoch is two past it: the epoch only
+//! advances when every pinned thread has announced the current one, so
+//! two advances mean every thread pinned at the time of the unlink has
+//! since unpinned, and no thread pinned later can reach the node.
+//!
+//! Retired nodes gather in a per-thread bag and are freed in batches, when
+//! the bag fills and the epoch can be advanced; a thread's leftover bag
+//! passes to the others when it exits. Pinning is a thread-local counter
+//! and one fence, so operations pay almost nothing for reclamation, at the
+//! cost of unbounded garbage if some thread stays pinned indefinitely.
+//!
+//! The state is global, one epoch shared by every structure in the crate,
+//! and uses `std` atomics even under loom, which cannot model statics.
+
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Retired objects a thread holds before it tries to free some.
+const BAG_LIMIT: usize = 64;
+
+/// The global epoch.
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+/// Every participant record ever registered, linked through `next`. The
+/// list only grows; records are reused by later threads.
+static PARTICIPANTS: AtomicPtr<Participant> = AtomicPtr::new(ptr::null_mut());
+
+/// Garbage left by exited threads.
+static ORPHANS: Mutex<Vec<Deferred>> = Mutex::new(Vec::new());
+
+/// A thread's announcement of the epoch it is pinned in.
+struct Participant {
+    /// The pinned epoch shifted left one, with the low bit set while
+    /// pinned.
+    state: AtomicUsize,
+    in_use: AtomicBool,
+    next: *mut Participant,
+}
+
+// SAFETY: `next` is written once, before the record is published, and
+// records are never freed.
+unsafe impl Sync for Participant {}
+
+/// An object to free once the global epoch passes `epoch + 1`.
+struct Deferred {
+    epoch: usize,
+    ptr: *mut (),
+    free: unsafe fn(*mut ()),
+}
+
+// SAFETY: `Guard::defer_destroy` only accepts `Send` objects.
+unsafe impl Send for Deferred {}
+
+impl Deferred {
+    fn is_expired(&self, epoch: usize) -> bool {
+        epoch.wrapping_sub(self.epoch) >= 2
+    }
+}
+
+/// A thread's participant record and garbage bag.
+struct Local {
+    participant: &'static Participant,
+    /// Number of live guards on this thread.
+    pins: Cell<usize>,
+    bag: RefCell<Vec<Deferred>>,
+}
+
+thread_local! {
+    static LOCAL: Local = Local::register();
+}
+
+impl Local {
+    /// Claims an unused participant record, or adds one.
+    fn register() -> Local {
+        let mut record = PARTICIPANTS.load(Ordering::Acquire);
+        while !record.is_null() {
+            // SAFETY: records are never freed.
+            let participant = unsafe { &*record };
+            if participant
+                .in_use
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return Local::new(participant);
+            }
+            record = participant.next;
+        }
+        let record = Box::into_raw(Box::new(Participant {
+            state: AtomicUsize::new(0),
+            in_use: AtomicBool::new(true),
+            next: ptr::null_mut(),
+        }));
+        let mut head = PARTICIPANTS.load(Ordering::Relaxed);
+        loop {
+            // SAFETY: the record is not yet shared.
+            unsafe { (*record).next = head };
+            match PARTICIPANTS.compare_exchange_weak(
+                head,
+                record,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+        // SAFETY: leaked above and never freed.
+        Local::new(unsafe { &*record })
+    }
+
+    fn new(participant: &'static Participant) -> Local {
+        Local {
+            participant,
+            pins: Cell::new(0),
+            bag: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+impl Drop for Local {
+    fn drop(&mut self) {
+        let bag = std::mem::take(self.bag.get_mut());
+        ORPHANS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extend(bag);
+        self.participant.state.store(0, Ordering::Release);
+        self.participant.in_use.store(false, Ordering::Release);
+    }
+}
+
+/// Proof that the current thread is pinned. Shared pointers loaded while
+/// a guard lives stay valid until it drops.
+pub(crate) struct Guard {
+    /// Guards are tied to the thread that pinned.
+    _thread: PhantomData<*const ()>,
+}
+
+/// Pins the current thread until the returned guard drops. Guards nest.
+pub(crate) fn pin() -> Guard {
+    LOCAL.with(|local| {
+        let pins = local.pins.get();
+        local.pins.set(pins + 1);
+        if pins == 0 {
+            let epoch = EPOCH.load(Ordering::Relaxed);
+            local
+                .participant
+                .state
+                .store(epoch << 1 | 1, Ordering::Relaxed);
+            // Order the announcement before every load of a shared pointer.
+            fence(Ordering::SeqCst);
+        }
+    });
+    Guard {
+        _thread: PhantomData,
+    }
+}
+
+impl Guard {
+    /// Frees `ptr`, a `Box` leaked with `Box::into_raw`, once no pinned
+    /// thread can still reach it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must already be unreachable for threads that pin from now on,
+    /// must not be retired twice, and may be freed on any thread. The free
+    /// may also run after every structure that used `ptr` is gone, so
+    /// dropping the `T` must not reach any borrowed data: the `T` must
+    /// be `'static`, or its drop must not touch what it borrows.
+    pub(crate) unsafe fn defer_destroy<T: Send>(&self, ptr: *mut T) {
+        unsafe fn free<T>(ptr: *mut ()) {
+            // SAFETY: `defer_destroy`'s contract.
+            drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+        }
+        let expired = LOCAL.with(|local| {
+            let mut bag = local.bag.borrow_mut();
+            bag.push(Deferred {
+                epoch: EPOCH.load(Ordering::SeqCst),
+                ptr: ptr.cast(),
+                free: free::<T>,
+            });
+            if bag.len() < BAG_LIMIT {
+                return Vec::new();
+            }
+            let epoch = try_advance();
+            let mut expired = take_expired(&mut bag, epoch);
+            if let Ok(mut orphans) = ORPHANS.try_lock() {
+                expired.extend(take_expired(&mut orphans, epoch));
+            }
+            expired
+        });
+        // Freed outside the bag's borrow, in case a destructor retires
+        // more.
+        for deferred in expired {
+            // SAFETY: two epoch advances since retirement; no thread can
+            // still hold the pointer.
+            unsafe { (deferred.free)(deferred.ptr) };
+        }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        LOCAL.with(|local| {
+            let pins = local.pins.get() - 1;
+            local.pins.set(pins);
+            if pins == 0 {
+                local.participant.state.store(0, Ordering::Release);
+            }
+        });
+    }
+}
+
+/// Advances the global epoch if every pinned thread has seen the current
+/// one, and returns the epoch afterwards.
+fn try_advance() -> usize {
+    let epoch = EPOCH.load(Ordering::Relaxed);
+    fence(Ordering::SeqCst);
+    let mut record = PARTICIPANTS.load(Ordering::Acquire);
+    while !record.is_null() {
+        // SAFETY: records are never freed.
+        let participant = unsafe { &*record };
+        let state = participant.state.load(Ordering::Relaxed);
+        if state & 1 == 1 && state >> 1 != epoch {
+            return epoch;
+        }
+        record = participant.next;
+    }
+    let next = epoch.wrapping_add(1);
+    match EPOCH.compare_exchange(epoch, next, Ordering::Release, Ordering::Relaxed) {
+        Ok(_) => next,
+        Err(current) => current,
+    }
+}
+
+/// Removes and returns the objects in `bag` that have expired by `epoch`.
+fn take_expired(bag: &mut Vec<Deferred>, epoch: usize) -> Vec<Deferred> {
+    let mut expired = Vec::new();
+    let mut i = 0;
+    while i < bag.len() {
+        if bag[i].is_expired(epoch) {
+            expired.push(bag.swap_remove(i));
+        } else {
+            i += 1;
+        }
+    }
+    expired
+}
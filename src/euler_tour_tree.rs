@@ -0,0 +1,496 @@
+//! Euler tour trees over a dynamic forest, with subtree and component
+//! aggregates.
+//!
+//! An Euler tour tree (Henzinger and King, 1995) stores each tree of a
+//! forest as the sequence of its Euler tour: walking around the tree,
+//! every vertex appears once and every edge twice, once in each direction.
+//! The tour is kept in a balanced sequence structure, here a treap whose
+//! nodes know their parents, so the sequence can be split before or after
+//! any of its elements and two sequences concatenated, each in `O(log n)`
+//! expected. A tour is cyclic, so it can be rotated to start at any
+//! vertex; linking two trees rotates each to start at an endpoint and
+//! joins them with the two directions of the new edge between, and cutting
+//! an edge splits out the stretch between its two directions, which is
+//! the tour of the part that falls off.
+//!
+//! Connectivity is then the question of whether two vertices sit in the
+//! same sequence, answered by walking up to its root. Every treap node
+//! keeps the [`Monoid`] combination of its subtree and its number of
+//! vertices, so the aggregate over a whole tree, or over the subtree below
+//! an edge, is read off at most two sequence roots.
+//!
+//! Where the [`LinkCutTree`] aggregates along paths, the Euler tour tree
+//! aggregates over subtrees and whole components, which paths cannot
+//! express; it is also the building block of Holm, de Lichtenberg and
+//! Thorup's fully dynamic connectivity for general graphs. The tour order
+//! depends on the history of links and cuts, so aggregates combine their
+//! values in no particular order and the monoid should be commutative.
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+//! [`LinkCutTree`]: crate::link_cut::LinkCutTree
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use crate::algebra::Monoid;
+use crate::probabilistic::StableHasher;
+use crate::rng::SplitMix64;
+
+const NONE: u32 = u32::MAX;
+
+/// The treap node of each direction of each edge.
+type Arcs = HashMap<(u32, u32), u32, BuildHasherDefault<StableHasher>>;
+
+#[derive(Clone, Debug)]
+struct Node<V> {
+    left: u32,
+    right: u32,
+    parent: u32,
+    priority: u64,
+    /// Number of tour elements in this subtree.
+    size: u32,
+    /// Number of vertices among them.
+    vertices: u32,
+    /// The vertex this element stands for, or `NONE` for an edge.
+    vertex: u32,
+    value: V,
+    /// The combination of the subtree's vertex values, in tour order.
+    sum: V,
+}
+
+/// A forest over vertices `0..n` carrying values of `M::Value`.
+#[derive(Clone, Debug)]
+pub struct EulerTourTree<M: Monoid> {
+    monoid: M,
+    nodes: Vec<Node<M::Value>>,
+    /// Freed edge nodes, for reuse.
+    free: Vec<u32>,
+    /// The tour element of each vertex.
+    vertex: Vec<u32>,
+    arcs: Arcs,
+    rng: SplitMix64,
+}
+
+impl<M: Monoid> EulerTourTree<M> {
+    /// Creates a forest of `n` isolated vertices, each holding the identity.
+    pub fn new(monoid: M, n: usize) -> Self {
+        let values = vec![monoid.identity(); n];
+        Self::with_values(monoid, values)
+    }
+
+    /// Creates a forest of isolated vertices holding `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `u32::MAX` values or more.
+    pub fn with_values(monoid: M, values: Vec<M::Value>) -> Self {
+        assert!(values.len() < NONE as usize, "too many vertices");
+        let mut tree = EulerTourTree {
+            monoid,
+            nodes: Vec::with_capacity(values.len()),
+            free: Vec::new(),
+            vertex: Vec::with_capacity(values.len()),
+            arcs: Arcs::default(),
+            rng: SplitMix64::new(0x5eed),
+        };
+        for value in values {
+            tree.add_vertex(value);
+        }
+        tree
+    }
+
+    /// Number of vertices.
+    pub fn len(&self) -> usize {
+        self.vertex.len()
+    }
+
+    /// Whether the forest has no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.vertex.is_empty()
+    }
+
+    /// Number of edges.
+    pub fn edge_count(&self) -> usize {
+        self.arcs.len() / 2
+    }
+
+    /// The monoid the aggregates combine with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// Adds an isolated vertex holding `value`, returning its number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the forest already holds `u32::MAX - 1` vertices.
+    pub fn add_vertex(&mut self, value: M::Value) -> usize {
+        assert!(self.vertex.len() < NONE as usize - 1, "too many vertices");
+        let v = self.vertex.len() as u32;
+        let x = self.alloc(v, value);
+        self.vertex.push(x);
+        v as usize
+    }
+
+    /// The value at vertex `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn get(&self, v: usize) -> &M::Value {
+        &self.nodes[self.vertex[v] as usize].value
+    }
+
+    /// Replaces the value at vertex `v`, in `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn set(&mut self, v: usize, value: M::Value) {
+        let mut x = self.vertex[v];
+        self.nodes[x as usize].value = value;
+        while x != NONE {
+            self.pull(x);
+            x = self.nodes[x as usize].parent;
+        }
+    }
+
+    /// Whether the edge `u`–`v` is in the forest, in `O(1)`.
+    pub fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.arcs.contains_key(&(u as u32, v as u32))
+    }
+
+    /// Whether `u` and `v` are in the same tree, in `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn connected(&self, u: usize, v: usize) -> bool {
+        self.root(self.vertex[u]) == self.root(self.vertex[v])
+    }
+
+    /// Adds the edge `u`–`v`, returning `false` and changing nothing if
+    /// they are already connected, in `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn link(&mut self, u: usize, v: usize) -> bool {
+        if self.connected(u, v) {
+            return false;
+        }
+        let tu = self.reroot(self.vertex[u]);
+        let tv = self.reroot(self.vertex[v]);
+        let identity = self.monoid.identity();
+        let uv = self.alloc(NONE, identity.clone());
+        let vu = self.alloc(NONE, identity);
+        let (u, v) = (u as u32, v as u32);
+        self.arcs.insert((u, v), uv);
+        self.arcs.insert((v, u), vu);
+        let tour = self.merge(tu, uv);
+        let tour = self.merge(tour, tv);
+        self.merge(tour, vu);
+        true
+    }
+
+    /// Removes the edge `u`–`v`, returning `false` if there is none, in
+    /// `O(log n)` expected.
+    pub fn cut(&mut self, u: usize, v: usize) -> bool {
+        let (u, v) = (u as u32, v as u32);
+        let Some(uv) = self.arcs.remove(&(u, v)) else {
+            return false;
+        };
+        let vu = self
+            .arcs
+            .remove(&(v, u))
+            .expect("edges are stored both ways");
+        let (first, second) = if self.position(uv) < self.position(vu) {
+            (uv, vu)
+        } else {
+            (vu, uv)
+        };
+        // The tour is `a first b second c`; `b` falls off, and `a c` is
+        // what stays.
+        let (a, _) = self.split_before(first);
+        self.split_after(first);
+        self.split_before(second);
+        let (_, c) = self.split_after(second);
+        self.merge(a, c);
+        self.release(first);
+        self.release(second);
+        true
+    }
+
+    /// Number of vertices in the tree holding `v`, in `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn component_len(&self, v: usize) -> usize {
+        self.nodes[self.root(self.vertex[v]) as usize].vertices as usize
+    }
+
+    /// The combination of the values in the tree holding `v`, in
+    /// `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn component(&self, v: usize) -> M::Value {
+        self.nodes[self.root(self.vertex[v]) as usize].sum.clone()
+    }
+
+    /// The vertices of the tree holding `v`, in tour order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn component_vertices(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut stack = Vec::new();
+        let mut x = self.root(self.vertex[v]);
+        std::iter::from_fn(move || loop {
+            while x != NONE {
+                stack.push(x);
+                x = self.nodes[x as usize].left;
+            }
+            let y = stack.pop()?;
+            let node = &self.nodes[y as usize];
+            x = node.right;
+            if node.vertex != NONE {
+                return Some(node.vertex as usize);
+            }
+        })
+    }
+
+    /// Number of vertices in the subtree of `v` when the tree is rooted on
+    /// the side of its neighbour `parent`, or `None` if there is no edge
+    /// `v`–`parent`, in `O(log n)` expected.
+    pub fn subtree_len(&mut self, v: usize, parent: usize) -> Option<usize> {
+        self.subtree_with(v, parent, |tree, parts| {
+            parts
+                .iter()
+                .filter(|&&p| p != NONE)
+                .map(|&p| tree.nodes[p as usize].vertices as usize)
+                .sum()
+        })
+    }
+
+    /// The combination of the values in the subtree of `v` when the tree is
+    /// rooted on the side of its neighbour `parent`, or `None` if there is
+    /// no edge `v`–`parent`, in `O(log n)` expected.
+    pub fn subtree(&mut self, v: usize, parent: usize) -> Option<M::Value> {
+        self.subtree_with(v, parent, |tree, parts| {
+            parts
+                .iter()
+                .filter(|&&p| p != NONE)
+                .fold(tree.monoid.identity(), |acc, &p| {
+                    tree.monoid.combine(&acc, &tree.nodes[p as usize].sum)
+                })
+        })
+    }
+
+    /// Splits out the sequences that make up the tour of the subtree of
+    /// `v` below `parent`, hands their roots to `f`, and puts the tour back
+    /// together.
+    fn subtree_with<R>(
+        &mut self,
+        v: usize,
+        parent: usize,
+        f: impl FnOnce(&Self, [u32; 2]) -> R,
+    ) -> Option<R> {
+        let (v, parent) = (v as u32, parent as u32);
+        let down = *self.arcs.get(&(parent, v))?;
+        let up = self.arcs[&(v, parent)];
+        // The subtree's tour runs from just after `down` to just before
+        // `up`, going round the end of the sequence if `up` comes first.
+        let (first, second) = if self.position(down) < self.position(up) {
+            (down, up)
+        } else {
+            (up, down)
+        };
+        let (a, _) = self.split_before(first);
+        self.split_after(first);
+        let (b, _) = self.split_before(second);
+        let (_, c) = self.split_after(second);
+        let result = if first == down {
+            f(self, [b, NONE])
+        } else {
+            f(self, [c, a])
+        };
+        let tour = self.merge(a, first);
+        let tour = self.merge(tour, b);
+        let tour = self.merge(tour, second);
+        self.merge(tour, c);
+        Some(result)
+    }
+
+    /// Rotates the tour holding `x` to start at `x`, returning its root.
+    fn reroot(&mut self, x: u32) -> u32 {
+        let (a, b) = self.split_before(x);
+        self.merge(b, a)
+    }
+
+    fn alloc(&mut self, vertex: u32, value: M::Value) -> u32 {
+        let node = Node {
+            left: NONE,
+            right: NONE,
+            parent: NONE,
+            priority: self.rng.next_u64(),
+            size: 1,
+            vertices: u32::from(vertex != NONE),
+            vertex,
+            sum: value.clone(),
+            value,
+        };
+        match self.free.pop() {
+            Some(x) => {
+                self.nodes[x as usize] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn release(&mut self, x: u32) {
+        self.free.push(x);
+    }
+
+    /// The root of the sequence holding `x`.
+    fn root(&self, mut x: u32) -> u32 {
+        while self.nodes[x as usize].parent != NONE {
+            x = self.nodes[x as usize].parent;
+        }
+        x
+    }
+
+    /// The index of `x` within its sequence.
+    fn position(&self, mut x: u32) -> usize {
+        let mut pos = self.size(self.nodes[x as usize].left);
+        loop {
+            let p = self.nodes[x as usize].parent;
+            if p == NONE {
+                return pos;
+            }
+            if self.nodes[p as usize].right == x {
+                pos += self.size(self.nodes[p as usize].left) + 1;
+            }
+            x = p;
+        }
+    }
+
+    fn size(&self, x: u32) -> usize {
+        if x == NONE {
+            0
+        } else {
+            self.nodes[x as usize].size as usize
+        }
+    }
+
+    /// Recomputes the counts and combination at `x` from its children.
+    fn pull(&mut self, x: u32) {
+        let Node { left, right, .. } = self.nodes[x as usize];
+        let n = &self.nodes[x as usize];
+        let (mut size, mut vertices) = (1, u32::from(n.vertex != NONE));
+        let mut sum = n.value.clone();
+        if left != NONE {
+            let l = &self.nodes[left as usize];
+            size += l.size;
+            vertices += l.vertices;
+            sum = self.monoid.combine(&l.sum, &sum);
+        }
+        if right != NONE {
+            let r = &self.nodes[right as usize];
+            size += r.size;
+            vertices += r.vertices;
+            sum = self.monoid.combine(&sum, &r.sum);
+        }
+        let n = &mut self.nodes[x as usize];
+        n.size = size;
+        n.vertices = vertices;
+        n.sum = sum;
+    }
+
+    /// Concatenates the sequences rooted at `a` and `b`, returning the root.
+    fn merge(&mut self, a: u32, b: u32) -> u32 {
+        let root = self.merge_inner(a, b);
+        if root != NONE {
+            self.nodes[root as usize].parent = NONE;
+        }
+        root
+    }
+
+    fn merge_inner(&mut self, a: u32, b: u32) -> u32 {
+        if a == NONE {
+            return b;
+        }
+        if b == NONE {
+            return a;
+        }
+        if self.nodes[a as usize].priority > self.nodes[b as usize].priority {
+            let m = self.merge_inner(self.nodes[a as usize].right, b);
+            self.nodes[a as usize].right = m;
+            self.nodes[m as usize].parent = a;
+            self.pull(a);
+            a
+        } else {
+            let m = self.merge_inner(a, self.nodes[b as usize].left);
+            self.nodes[b as usize].left = m;
+            self.nodes[m as usize].parent = b;
+            self.pull(b);
+            b
+        }
+    }
+
+    /// Splits the sequence holding `x` into the elements before `x` and
+    /// the rest, returning both roots.
+    fn split_before(&mut self, x: u32) -> (u32, u32) {
+        let l = std::mem::replace(&mut self.nodes[x as usize].left, NONE);
+        self.split_up(x, l, x)
+    }
+
+    /// Splits the sequence holding `x` into the elements up to `x` and the
+    /// rest, returning both roots.
+    fn split_after(&mut self, x: u32) -> (u32, u32) {
+        let r = std::mem::replace(&mut self.nodes[x as usize].right, NONE);
+        self.split_up(x, x, r)
+    }
+
+    /// Finishes a split at `x`, whose subtree has already been divided into
+    /// the sequences rooted at `l` and `r`, by walking up and handing each
+    /// ancestor to the side it belongs on.
+    fn split_up(&mut self, x: u32, mut l: u32, mut r: u32) -> (u32, u32) {
+        let mut p = self.nodes[x as usize].parent;
+        for part in [l, r] {
+            if part != NONE {
+                self.nodes[part as usize].parent = NONE;
+            }
+        }
+        self.pull(x);
+        let mut child = x;
+        while p != NONE {
+            let next = std::mem::replace(&mut self.nodes[p as usize].parent, NONE);
+            if self.nodes[p as usize].right == child {
+                // `p` and its left subtree come before the split.
+                self.nodes[p as usize].right = l;
+                if l != NONE {
+                    self.nodes[l as usize].parent = p;
+                }
+                self.pull(p);
+                l = p;
+            } else {
+                self.nodes[p as usize].left = r;
+                if r != NONE {
+                    self.nodes[r as usize].parent = p;
+                }
+                self.pull(p);
+                r = p;
+            }
+            child = p;
+            p = next;
+        }
+        (l, r)
+    }
+}
@@ -0,0 +1,587 @@
+//! Fenwick trees (binary indexed trees) over an abelian group.
+//!
+//! A Fenwick tree stores, at position `i`, the combination of the elements
+//! in the range ending at `i` whose length is the lowest set bit of `i`.
+//! Any prefix is the combination of at most `log n` of those ranges, and
+//! any element lies in at most `log n` of them. So point updates and
+//! prefix queries both cost `O(log n)`, with one array of `n` values and
+//! none of a segment tree's bookkeeping.
+//!
+//! The price is that the operation must be an [`AbelianGroup`]. Prefixes
+//! get assembled out of order, and a range query is the difference of the
+//! prefixes at its two ends. For minimum or maximum, which have no
+//! inverse, use a [`SegmentTree`].
+//!
+//! Two variants trade the point operations for range ones.
+//! [`RangeAddFenwick`] stores differences between neighbouring elements,
+//! so adding to a range touches its two ends and reading an element is a
+//! prefix query. [`RangeSumFenwick`] keeps a second tree of index-weighted
+//! differences on top, from which it recovers range sums under range
+//! additions.
+//!
+//! [`Fenwick2d`] nests the same scheme in two dimensions: each row range
+//! of the outer tree holds an inner tree over the columns. Point updates
+//! and rectangle queries take `O(log r · log c)` on an `r × c` grid.
+//!
+//! [`AbelianGroup`]: crate::algebra::AbelianGroup
+//! [`SegmentTree`]: crate::segment_tree::SegmentTree
+
+use std::fmt;
+use std::ops::RangeBounds;
+
+use crate::algebra::AbelianGroup;
+use crate::util::bounds;
+
+/// A Fenwick tree with point updates and range queries.
+#[derive(Clone, Debug)]
+pub struct Fenwick<G: AbelianGroup> {
+    group: G,
+    /// `tree[i]` combines the elements `i - lowbit(i)..i`; `tree[0]` is
+    /// unused.
+    tree: Vec<G::Value>,
+}
+
+impl<G: AbelianGroup> Fenwick<G> {
+    /// Builds a tree over `values` in `O(n)`.
+    pub fn new(group: G, values: Vec<G::Value>) -> Self {
+        let mut tree = Vec::with_capacity(values.len() + 1);
+        tree.push(group.identity());
+        tree.extend(values);
+        let n = tree.len() - 1;
+        for i in 1..=n {
+            let j = i + lowbit(i);
+            if j <= n {
+                tree[j] = group.combine(&tree[j], &tree[i]);
+            }
+        }
+        Fenwick { group, tree }
+    }
+
+    /// Builds a tree of `len` copies of the identity.
+    pub fn with_len(group: G, len: usize) -> Self {
+        Fenwick {
+            tree: vec![group.identity(); len + 1],
+            group,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The group the tree combines with.
+    pub fn group(&self) -> &G {
+        &self.group
+    }
+
+    /// Combines `delta` into the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn add(&mut self, index: usize, delta: &G::Value) {
+        let n = self.len();
+        assert!(index < n, "index {index} out of bounds for length {n}");
+        let mut i = index + 1;
+        while i <= n {
+            self.tree[i] = self.group.combine(&self.tree[i], delta);
+            i += lowbit(i);
+        }
+    }
+
+    /// The combination of the first `end` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end > len`.
+    pub fn prefix(&self, end: usize) -> G::Value {
+        let n = self.len();
+        assert!(end <= n, "end {end} out of bounds for length {n}");
+        let mut acc = self.group.identity();
+        let mut i = end;
+        while i > 0 {
+            acc = self.group.combine(&acc, &self.tree[i]);
+            i -= lowbit(i);
+        }
+        acc
+    }
+
+    /// The combination of the elements in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> G::Value {
+        let (lo, hi) = bounds(range, self.len());
+        self.group.difference(&self.prefix(hi), &self.prefix(lo))
+    }
+
+    /// The element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> G::Value {
+        self.query(index..=index)
+    }
+
+    /// Replaces the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: &G::Value) {
+        let delta = self.group.difference(value, &self.get(index));
+        self.add(index, &delta);
+    }
+
+    /// Finds the largest `end` such that `pred` holds for the prefix of
+    /// length `end`, assuming `pred` is monotone: true for the empty prefix
+    /// and, once false, false for every longer one. Takes `O(log n)`.
+    pub fn max_prefix(&self, pred: impl Fn(&G::Value) -> bool) -> usize {
+        let n = self.len();
+        let (mut pos, mut acc) = (0, self.group.identity());
+        let mut step = if n == 0 { 0 } else { 1 << n.ilog2() };
+        // Descend by binary lifting: each tree entry past `pos` covers
+        // exactly the next `step` elements.
+        while step > 0 {
+            if pos + step <= n {
+                let extended = self.group.combine(&acc, &self.tree[pos + step]);
+                if pred(&extended) {
+                    pos += step;
+                    acc = extended;
+                }
+            }
+            step >>= 1;
+        }
+        pos
+    }
+
+    /// Collects the current elements.
+    pub fn to_vec(&self) -> Vec<G::Value> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+
+impl<G: AbelianGroup> Fenwick<G>
+where
+    G::Value: PartialOrd,
+{
+    /// The index of the element holding the `k`-th unit, counting from
+    /// zero, when the elements are non-negative counts. That is the least
+    /// `i` whose prefix through `i` exceeds `k`, or `None` if the total
+    /// does not.
+    pub fn kth(&self, k: &G::Value) -> Option<usize> {
+        let i = self.max_prefix(|s| s <= k);
+        (i < self.len()).then_some(i)
+    }
+}
+
+impl<G: AbelianGroup + Default> FromIterator<G::Value> for Fenwick<G> {
+    fn from_iter<I: IntoIterator<Item = G::Value>>(iter: I) -> Self {
+        Fenwick::new(G::default(), iter.into_iter().collect())
+    }
+}
+
+/// A Fenwick tree with range updates and point queries.
+#[derive(Clone)]
+pub struct RangeAddFenwick<G: AbelianGroup> {
+    /// Element `i` is the prefix of `diff` through `i`.
+    diff: Fenwick<G>,
+}
+
+impl<G: AbelianGroup> RangeAddFenwick<G> {
+    /// Builds a tree over `values` in `O(n)`.
+    pub fn new(group: G, values: Vec<G::Value>) -> Self {
+        let diff = differences(&group, &values);
+        RangeAddFenwick {
+            diff: Fenwick::new(group, diff),
+        }
+    }
+
+    /// Builds a tree of `len` copies of the identity.
+    pub fn with_len(group: G, len: usize) -> Self {
+        RangeAddFenwick {
+            diff: Fenwick::with_len(group, len),
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.diff.len()
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+    }
+
+    /// Combines `delta` into every element in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn add(&mut self, range: impl RangeBounds<usize>, delta: &G::Value) {
+        let (lo, hi) = bounds(range, self.len());
+        if lo == hi {
+            return;
+        }
+        self.diff.add(lo, delta);
+        if hi < self.len() {
+            let inverse = self.diff.group.inverse(delta);
+            self.diff.add(hi, &inverse);
+        }
+    }
+
+    /// The element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> G::Value {
+        let n = self.len();
+        assert!(index < n, "index {index} out of bounds for length {n}");
+        self.diff.prefix(index + 1)
+    }
+
+    /// Collects the current elements.
+    pub fn to_vec(&self) -> Vec<G::Value> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+
+/// A Fenwick tree with range updates and range queries.
+///
+/// With `d` the differences between neighbouring elements, the prefix of
+/// length `p` is `p · Σ d[i] − Σ i · d[i]` over `i < p`. The tree keeps both
+/// sums, so an update touches two entries in each.
+#[derive(Clone)]
+pub struct RangeSumFenwick<G: AbelianGroup> {
+    diff: Fenwick<G>,
+    /// `weighted[i]` is `i` copies of `diff[i]`.
+    weighted: Fenwick<G>,
+}
+
+impl<G: AbelianGroup + Clone> RangeSumFenwick<G> {
+    /// Builds a tree over `values` in `O(n log n)`.
+    pub fn new(group: G, values: Vec<G::Value>) -> Self {
+        let diff = differences(&group, &values);
+        let weighted = diff
+            .iter()
+            .enumerate()
+            .map(|(i, d)| times(&group, d, i))
+            .collect();
+        RangeSumFenwick {
+            diff: Fenwick::new(group.clone(), diff),
+            weighted: Fenwick::new(group, weighted),
+        }
+    }
+
+    /// Builds a tree of `len` copies of the identity.
+    pub fn with_len(group: G, len: usize) -> Self {
+        RangeSumFenwick {
+            diff: Fenwick::with_len(group.clone(), len),
+            weighted: Fenwick::with_len(group, len),
+        }
+    }
+}
+
+impl<G: AbelianGroup> RangeSumFenwick<G> {
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.diff.len()
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty()
+    }
+
+    /// Combines `delta` into every element in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn add(&mut self, range: impl RangeBounds<usize>, delta: &G::Value) {
+        let (lo, hi) = bounds(range, self.len());
+        if lo == hi {
+            return;
+        }
+        let group = &self.diff.group;
+        let at_lo = times(group, delta, lo);
+        self.diff.add(lo, delta);
+        self.weighted.add(lo, &at_lo);
+        if hi < self.len() {
+            let group = &self.diff.group;
+            let inverse = group.inverse(delta);
+            let at_hi = group.inverse(&times(group, delta, hi));
+            self.diff.add(hi, &inverse);
+            self.weighted.add(hi, &at_hi);
+        }
+    }
+
+    /// The combination of the first `end` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `end > len`.
+    pub fn prefix(&self, end: usize) -> G::Value {
+        let group = &self.diff.group;
+        let scaled = times(group, &self.diff.prefix(end), end);
+        group.difference(&scaled, &self.weighted.prefix(end))
+    }
+
+    /// The combination of the elements in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> G::Value {
+        let (lo, hi) = bounds(range, self.len());
+        self.diff
+            .group
+            .difference(&self.prefix(hi), &self.prefix(lo))
+    }
+
+    /// The element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> G::Value {
+        let n = self.len();
+        assert!(index < n, "index {index} out of bounds for length {n}");
+        self.diff.prefix(index + 1)
+    }
+
+    /// Collects the current elements.
+    pub fn to_vec(&self) -> Vec<G::Value> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+
+/// A two-dimensional Fenwick tree with point updates and rectangle queries.
+#[derive(Clone, Debug)]
+pub struct Fenwick2d<G: AbelianGroup> {
+    group: G,
+    rows: usize,
+    cols: usize,
+    /// Row-major `(rows + 1) × (cols + 1)` entries, each combining the block
+    /// of cells that ends at it and spans the lowest set bit of its row and
+    /// of its column; row and column zero are unused.
+    tree: Vec<G::Value>,
+}
+
+impl<G: AbelianGroup> Fenwick2d<G> {
+    /// Builds a tree over a `rows × cols` grid of `values`, given in
+    /// row-major order, in `O(rows · cols)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` does not hold `rows · cols` cells.
+    pub fn new(group: G, rows: usize, cols: usize, values: Vec<G::Value>) -> Self {
+        assert_eq!(values.len(), rows * cols, "grid is not {rows} × {cols}");
+        let width = cols + 1;
+        let mut tree = vec![group.identity(); (rows + 1) * width];
+        for (k, v) in values.into_iter().enumerate() {
+            tree[(k / cols + 1) * width + k % cols + 1] = v;
+        }
+        // Build along the columns of every row, then along the rows.
+        for i in 1..=rows {
+            for j in 1..=cols {
+                let up = j + lowbit(j);
+                if up <= cols {
+                    tree[i * width + up] =
+                        group.combine(&tree[i * width + up], &tree[i * width + j]);
+                }
+            }
+        }
+        for i in 1..=rows {
+            let up = i + lowbit(i);
+            if up <= rows {
+                for j in 1..=cols {
+                    tree[up * width + j] =
+                        group.combine(&tree[up * width + j], &tree[i * width + j]);
+                }
+            }
+        }
+        Fenwick2d {
+            group,
+            rows,
+            cols,
+            tree,
+        }
+    }
+
+    /// Builds a `rows × cols` grid of the identity.
+    pub fn with_size(group: G, rows: usize, cols: usize) -> Self {
+        Fenwick2d {
+            tree: vec![group.identity(); (rows + 1) * (cols + 1)],
+            group,
+            rows,
+            cols,
+        }
+    }
+
+    /// Number of rows.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Number of columns.
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The group the tree combines with.
+    pub fn group(&self) -> &G {
+        &self.group
+    }
+
+    /// Combines `delta` into the cell at `row`, `col`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is out of bounds.
+    pub fn add(&mut self, row: usize, col: usize, delta: &G::Value) {
+        assert!(
+            row < self.rows && col < self.cols,
+            "cell ({row}, {col}) out of bounds for {} × {}",
+            self.rows,
+            self.cols
+        );
+        let width = self.cols + 1;
+        let mut i = row + 1;
+        while i <= self.rows {
+            let mut j = col + 1;
+            while j <= self.cols {
+                let t = &mut self.tree[i * width + j];
+                *t = self.group.combine(t, delta);
+                j += lowbit(j);
+            }
+            i += lowbit(i);
+        }
+    }
+
+    /// The combination of the cells in the first `row_end` rows and
+    /// `col_end` columns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either end is past the grid.
+    pub fn prefix(&self, row_end: usize, col_end: usize) -> G::Value {
+        assert!(
+            row_end <= self.rows && col_end <= self.cols,
+            "prefix ({row_end}, {col_end}) out of bounds for {} × {}",
+            self.rows,
+            self.cols
+        );
+        let width = self.cols + 1;
+        let mut acc = self.group.identity();
+        let mut i = row_end;
+        while i > 0 {
+            let mut j = col_end;
+            while j > 0 {
+                acc = self.group.combine(&acc, &self.tree[i * width + j]);
+                j -= lowbit(j);
+            }
+            i -= lowbit(i);
+        }
+        acc
+    }
+
+    /// The combination of the cells in the rectangle `rows × cols`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either range is out of bounds.
+    pub fn query(&self, rows: impl RangeBounds<usize>, cols: impl RangeBounds<usize>) -> G::Value {
+        let (r0, r1) = bounds(rows, self.rows);
+        let (c0, c1) = bounds(cols, self.cols);
+        // Inclusion–exclusion over the four corner prefixes.
+        let g = &self.group;
+        let outer = g.combine(&self.prefix(r1, c1), &self.prefix(r0, c0));
+        let sides = g.combine(&self.prefix(r0, c1), &self.prefix(r1, c0));
+        g.difference(&outer, &sides)
+    }
+
+    /// The cell at `row`, `col`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is out of bounds.
+    pub fn get(&self, row: usize, col: usize) -> G::Value {
+        self.query(row..=row, col..=col)
+    }
+
+    /// Replaces the cell at `row`, `col`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is out of bounds.
+    pub fn set(&mut self, row: usize, col: usize, value: &G::Value) {
+        let delta = self.group.difference(value, &self.get(row, col));
+        self.add(row, col, &delta);
+    }
+}
+
+// Written out because the derive cannot see that the inner trees need
+// `G::Value: Debug`.
+impl<G: AbelianGroup + fmt::Debug> fmt::Debug for RangeAddFenwick<G>
+where
+    G::Value: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeAddFenwick")
+            .field("diff", &self.diff)
+            .finish()
+    }
+}
+
+impl<G: AbelianGroup + fmt::Debug> fmt::Debug for RangeSumFenwick<G>
+where
+    G::Value: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeSumFenwick")
+            .field("diff", &self.diff)
+            .field("weighted", &self.weighted)
+            .finish()
+    }
+}
+
+fn lowbit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+/// The differences between neighbouring values, the first against the
+/// identity.
+fn differences<G: AbelianGroup>(group: &G, values: &[G::Value]) -> Vec<G::Value> {
+    let first = values.first().cloned();
+    first
+        .into_iter()
+        .chain(values.windows(2).map(|w| group.difference(&w[1], &w[0])))
+        .collect()
+}
+
+/// `x` combined with itself `n` times, by doubling.
+fn times<G: AbelianGroup>(group: &G, x: &G::Value, mut n: usize) -> G::Value {
+    let mut acc = group.identity();
+    let mut pow = x.clone();
+    while n > 0 {
+        if n & 1 == 1 {
+            acc = group.combine(&acc, &pow);
+        }
+        n >>= 1;
+        if n > 0 {
+            pow = group.combine(&pow, &pow);
+        }
+    }
+    acc
+}
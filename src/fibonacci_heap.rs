@@ -0,0 +1,398 @@
+//! A Fibonacci heap with handles, decrease-key and meld.
+//!
+//! Fredman and Tarjan's Fibonacci heap is a list of heap-ordered trees
+//! that puts off all structural work until a pop. Pushing adds a one-node
+//! tree to the root list in `O(1)`, and melding joins two root lists in
+//! `O(1)` plus the cost of moving the smaller heap's nodes into the larger
+//! heap's arena: a node only moves into an arena at least twice the size,
+//! so over any run of melds each node moves at most `log2 n` times.
+//! Popping the minimum moves its children to the root list and then
+//! consolidates, linking roots of equal degree until no two are alike. That
+//! costs `O(log n)` amortized.
+//!
+//! Decreasing a key cuts the item from its parent if it now undercuts it,
+//! and makes it a root. A parent that loses a second child is cut in turn,
+//! a cascade that keeps every tree of degree `d` at least as large as the
+//! `d + 2`nd Fibonacci number and so keeps degrees logarithmic. Decrease-key
+//! is then `O(1)` amortized, which gives Dijkstra's algorithm its
+//! `O(m + n log n)` bound. The constant factors are large, though: measure
+//! against a [`PairingHeap`] before choosing. Both implement
+//! [`MeldableHeap`], and the crate's `heaps` benchmark compares them.
+//!
+//! Roots and siblings form circular doubly linked lists, by index into an
+//! arena. The heap puts the smallest item first; wrap items in
+//! [`Reverse`](std::cmp::Reverse) for the largest.
+//!
+//! [`PairingHeap`]: crate::pairing_heap::PairingHeap
+//! [`MeldableHeap`]: crate::heap::MeldableHeap
+
+use crate::heap_arena::{shift, Arena, Key, Links, NONE};
+
+/// Handle to an item stored in a [`FibonacciHeap`].
+///
+/// A handle stays valid until its item is removed, including across
+/// melds into or from the heap. After that it is stale: lookups through it
+/// return `None` even once the slot holds another item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle(Key);
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    item: T,
+    parent: u32,
+    /// Any one child; the rest follow around the ring.
+    child: u32,
+    left: u32,
+    right: u32,
+    degree: u32,
+    /// Whether the node has lost a child since it last became a child.
+    marked: bool,
+}
+
+impl<T> Links for Node<T> {
+    fn shift(&mut self, offset: u32) {
+        shift(&mut self.parent, offset);
+        shift(&mut self.child, offset);
+        shift(&mut self.left, offset);
+        shift(&mut self.right, offset);
+    }
+}
+
+/// A min-heap of items of type `T` with handles to them.
+///
+/// A clone holds the same items under new handles: handles into the
+/// original do not reach the clone's items.
+#[derive(Clone, Debug)]
+pub struct FibonacciHeap<T> {
+    nodes: Arena<Node<T>>,
+    /// The root holding the smallest item.
+    min: u32,
+}
+
+impl<T> Default for FibonacciHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FibonacciHeap<T> {
+    /// Creates an empty heap.
+    pub fn new() -> Self {
+        FibonacciHeap {
+            nodes: Arena::new(),
+            min: NONE,
+        }
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 0
+    }
+
+    /// Removes every item, invalidating all handles.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// The smallest item.
+    pub fn peek(&self) -> Option<&T> {
+        (self.min != NONE).then(|| &self.nodes[self.min].item)
+    }
+
+    /// The handle of the smallest item.
+    pub fn peek_handle(&self) -> Option<Handle> {
+        (self.min != NONE).then(|| Handle(self.nodes.key(self.min)))
+    }
+
+    /// The item behind `handle`, or `None` if it has been removed.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let n = self.nodes.find(handle.0)?;
+        Some(&self.nodes[n].item)
+    }
+
+    /// Whether the item behind `handle` is still in the heap.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Iterates over the items in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.nodes.iter().map(|n| &n.item)
+    }
+
+    fn node(&mut self, n: u32) -> &mut Node<T> {
+        &mut self.nodes[n]
+    }
+
+    /// Adds `x` to the ring after `a`.
+    fn insert_after(&mut self, a: u32, x: u32) {
+        let right = self.nodes[a].right;
+        let node = self.node(x);
+        node.left = a;
+        node.right = right;
+        self.node(right).left = x;
+        self.node(a).right = x;
+    }
+
+    /// Takes `x` out of its ring, leaving it a ring of one.
+    fn unlink(&mut self, x: u32) {
+        let Node { left, right, .. } = self.nodes[x];
+        self.node(left).right = right;
+        self.node(right).left = left;
+        let node = self.node(x);
+        node.left = x;
+        node.right = x;
+    }
+
+    /// The nodes of the ring containing `start`.
+    fn ring(&self, start: u32) -> Vec<u32> {
+        let mut out = Vec::new();
+        if start == NONE {
+            return out;
+        }
+        let mut n = start;
+        loop {
+            out.push(n);
+            n = self.nodes[n].right;
+            if n == start {
+                return out;
+            }
+        }
+    }
+
+    /// The slot of the item behind `handle`.
+    fn find(&self, handle: Handle) -> u32 {
+        let n = self.nodes.find(handle.0);
+        n.expect("handle refers to a removed item")
+    }
+}
+
+impl<T: Ord> FibonacciHeap<T> {
+    /// Adds an item in `O(1)`, returning its handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the heap already holds `u32::MAX - 1` nodes.
+    pub fn push(&mut self, item: T) -> Handle {
+        let (n, key) = self.nodes.insert(Node {
+            item,
+            parent: NONE,
+            child: NONE,
+            left: NONE,
+            right: NONE,
+            degree: 0,
+            marked: false,
+        });
+        let node = self.node(n);
+        node.left = n;
+        node.right = n;
+        self.add_root(n);
+        Handle(key)
+    }
+
+    /// Removes and returns the smallest item, in `O(log n)` amortized.
+    pub fn pop(&mut self) -> Option<T> {
+        let z = self.min;
+        if z == NONE {
+            return None;
+        }
+        for c in self.ring(self.nodes[z].child) {
+            let node = self.node(c);
+            node.parent = NONE;
+            node.marked = false;
+            self.unlink(c);
+            self.insert_after(z, c);
+        }
+        let next = self.nodes[z].right;
+        self.unlink(z);
+        let item = self.nodes.remove(z).item;
+        if next == z {
+            self.min = NONE;
+        } else {
+            self.min = next;
+            self.consolidate();
+        }
+        Some(item)
+    }
+
+    /// Replaces the item behind `handle` with a smaller or equal one, in
+    /// `O(1)` amortized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed or `item` is greater than it.
+    pub fn decrease_key(&mut self, handle: Handle, item: T) {
+        let x = self.find(handle);
+        let slot = &mut self.node(x).item;
+        assert!(item <= *slot, "new item is greater than the current one");
+        *slot = item;
+        let parent = self.nodes[x].parent;
+        if parent != NONE && self.nodes[x].item < self.nodes[parent].item {
+            self.cut(x);
+            self.cascade(parent);
+        }
+        if self.nodes[x].item < self.nodes[self.min].item {
+            self.min = x;
+        }
+    }
+
+    /// Removes and returns the item behind `handle`, in `O(log n)`
+    /// amortized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed.
+    pub fn remove(&mut self, handle: Handle) -> T {
+        let x = self.find(handle);
+        // Treat the item as smaller than everything: make it a root and the
+        // minimum, then pop it.
+        let parent = self.nodes[x].parent;
+        if parent != NONE {
+            self.cut(x);
+            self.cascade(parent);
+        }
+        self.min = x;
+        self.pop().expect("heap holds the item")
+    }
+
+    /// Moves every item of `other` into this heap, in `O(1)` plus moving
+    /// the smaller heap's nodes into the larger heap's arena.
+    ///
+    /// Handles into both heaps stay valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arenas together hold `u32::MAX - 1` nodes or more.
+    pub fn meld(&mut self, other: Self) {
+        let (mut ours, mut theirs) = (self.min, other.min);
+        let (a, b) = self.nodes.meld(other.nodes);
+        shift(&mut ours, a);
+        shift(&mut theirs, b);
+        self.min = ours;
+        if theirs == NONE {
+            return;
+        }
+        if self.min == NONE {
+            self.min = theirs;
+            return;
+        }
+        // Splice the two root rings together after our minimum.
+        let (ours_right, theirs_left) = (self.nodes[self.min].right, self.nodes[theirs].left);
+        self.node(self.min).right = theirs;
+        self.node(theirs).left = self.min;
+        self.node(theirs_left).right = ours_right;
+        self.node(ours_right).left = theirs_left;
+        if self.nodes[theirs].item < self.nodes[self.min].item {
+            self.min = theirs;
+        }
+    }
+
+    /// Adds a lone node to the root ring.
+    fn add_root(&mut self, x: u32) {
+        if self.min == NONE {
+            self.min = x;
+            return;
+        }
+        self.insert_after(self.min, x);
+        if self.nodes[x].item < self.nodes[self.min].item {
+            self.min = x;
+        }
+    }
+
+    /// Links roots until no two have the same degree, then finds the new
+    /// minimum.
+    fn consolidate(&mut self) {
+        let mut by_degree: Vec<u32> = Vec::new();
+        for mut x in self.ring(self.min) {
+            let mut d = self.nodes[x].degree as usize;
+            while let Some(&y) = by_degree.get(d).filter(|&&y| y != NONE) {
+                let y = if self.nodes[y].item < self.nodes[x].item {
+                    std::mem::replace(&mut x, y)
+                } else {
+                    y
+                };
+                self.link(y, x);
+                by_degree[d] = NONE;
+                d += 1;
+            }
+            if d >= by_degree.len() {
+                by_degree.resize(d + 1, NONE);
+            }
+            by_degree[d] = x;
+        }
+        self.min = NONE;
+        for x in by_degree.into_iter().filter(|&x| x != NONE) {
+            if self.min == NONE || self.nodes[x].item < self.nodes[self.min].item {
+                self.min = x;
+            }
+        }
+    }
+
+    /// Makes root `y` a child of root `x`.
+    fn link(&mut self, y: u32, x: u32) {
+        self.unlink(y);
+        let child = self.nodes[x].child;
+        if child == NONE {
+            self.node(x).child = y;
+        } else {
+            self.insert_after(child, y);
+        }
+        let node = self.node(y);
+        node.parent = x;
+        node.marked = false;
+        self.node(x).degree += 1;
+    }
+
+    /// Moves `x` from its parent's children to the root ring.
+    fn cut(&mut self, x: u32) {
+        let parent = self.nodes[x].parent;
+        if self.nodes[parent].child == x {
+            let right = self.nodes[x].right;
+            self.node(parent).child = if right == x { NONE } else { right };
+        }
+        self.unlink(x);
+        self.node(parent).degree -= 1;
+        let node = self.node(x);
+        node.parent = NONE;
+        node.marked = false;
+        self.add_root(x);
+    }
+
+    /// Marks `x` for the child it just lost, cutting it too if it had
+    /// already lost one, and so on up.
+    fn cascade(&mut self, mut x: u32) {
+        loop {
+            let parent = self.nodes[x].parent;
+            if parent == NONE {
+                return;
+            }
+            if !self.nodes[x].marked {
+                self.node(x).marked = true;
+                return;
+            }
+            self.cut(x);
+            x = parent;
+        }
+    }
+}
+
+impl<T: Ord> Extend<T> for FibonacciHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for FibonacciHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = FibonacciHeap::new();
+        heap.extend(iter);
+        heap
+    }
+}
@@ -0,0 +1,1126 @@
+//! A persistent finger tree over a measurement monoid.
+//!
+//! A 2-3 finger tree (Hinze and Paterson, 2006) keeps one to four elements
+//! at each end of the sequence, the *digits*, and the rest in a tree of the
+//! same shape one level down whose elements are 2-3 nodes of elements.
+//! Pushing or popping at either end touches only the digits except when one
+//! overflows or runs dry, which happens rarely enough that both are `O(1)`
+//! amortized. Two trees concatenate in `O(log min(n, m))` by grouping the
+//! digits that meet in the middle into nodes.
+//!
+//! Every node caches the [`Monoid`] combination of its elements' measures,
+//! supplied by a [`Measure`]. A monotone predicate on the measure of a
+//! prefix then locates the point where it first holds in `O(log n)`, and
+//! splitting the tree there is the single operation from which the derived
+//! structures follow: with a size measure it is a sequence indexed by
+//! position ([`Deque`]), with a running maximum a priority queue taking the
+//! greatest element ([`PriorityQueue`]), and with the last key of a sorted
+//! sequence an ordered set with duplicates ([`OrderedSequence`]).
+//!
+//! The tree is persistent: subtrees are shared between versions through
+//! `Arc`, so clones are `O(1)` and every operation leaves other versions
+//! intact, copying only the parts it rebuilds.
+//!
+//! ```text
+//! FingerTree<T, Size>  sequence with positional split
+//! Deque<T>             push and pop at both ends, index, split, append
+//! PriorityQueue<T>     push, pop the greatest, meld
+//! OrderedSequence<T>   insert, remove, rank, split at a key
+//! ```
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Arc;
+
+use crate::algebra::Monoid;
+
+/// A monoid together with the map from elements into it.
+pub trait Measure<T>: Monoid {
+    /// The measure of a single element.
+    fn measure(&self, item: &T) -> Self::Value;
+}
+
+/// Measures every element as one, so a prefix measures its length.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Size;
+
+impl Monoid for Size {
+    type Value = usize;
+
+    fn identity(&self) -> usize {
+        0
+    }
+
+    fn combine(&self, a: &usize, b: &usize) -> usize {
+        a + b
+    }
+}
+
+impl<T> Measure<T> for Size {
+    fn measure(&self, _: &T) -> usize {
+        1
+    }
+}
+
+/// An element of the tree at some level: an element of the sequence at
+/// the top, and a 2-3 node below.
+enum Piece<T, V> {
+    Leaf(T),
+    Node(Arc<Node<T, V>>),
+}
+
+impl<T: Clone, V> Clone for Piece<T, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Piece::Leaf(item) => Piece::Leaf(item.clone()),
+            Piece::Node(node) => Piece::Node(Arc::clone(node)),
+        }
+    }
+}
+
+struct Node<T, V> {
+    measure: V,
+    /// Two or three pieces.
+    children: Vec<Piece<T, V>>,
+}
+
+enum Tree<T, V> {
+    Empty,
+    Single(Piece<T, V>),
+    Deep(Arc<Deep<T, V>>),
+}
+
+impl<T: Clone, V> Clone for Tree<T, V> {
+    fn clone(&self) -> Self {
+        match self {
+            Tree::Empty => Tree::Empty,
+            Tree::Single(piece) => Tree::Single(piece.clone()),
+            Tree::Deep(deep) => Tree::Deep(Arc::clone(deep)),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Deep<T, V> {
+    measure: V,
+    /// One to four pieces.
+    prefix: Vec<Piece<T, V>>,
+    middle: Tree<T, V>,
+    /// One to four pieces.
+    suffix: Vec<Piece<T, V>>,
+}
+
+fn piece_measure<T, M: Measure<T>>(m: &M, piece: &Piece<T, M::Value>) -> M::Value {
+    match piece {
+        Piece::Leaf(item) => m.measure(item),
+        Piece::Node(node) => node.measure.clone(),
+    }
+}
+
+fn pieces_measure<T, M: Measure<T>>(m: &M, pieces: &[Piece<T, M::Value>]) -> M::Value {
+    pieces
+        .iter()
+        .fold(m.identity(), |acc, p| m.combine(&acc, &piece_measure(m, p)))
+}
+
+fn tree_measure<T, M: Measure<T>>(m: &M, tree: &Tree<T, M::Value>) -> M::Value {
+    match tree {
+        Tree::Empty => m.identity(),
+        Tree::Single(piece) => piece_measure(m, piece),
+        Tree::Deep(deep) => deep.measure.clone(),
+    }
+}
+
+fn node<T, M: Measure<T>>(m: &M, children: Vec<Piece<T, M::Value>>) -> Piece<T, M::Value> {
+    Piece::Node(Arc::new(Node {
+        measure: pieces_measure(m, &children),
+        children,
+    }))
+}
+
+/// The children of a piece from a lower level, which is always a node.
+fn children<T: Clone, V: Clone>(piece: Piece<T, V>) -> Vec<Piece<T, V>> {
+    match piece {
+        Piece::Node(node) => match Arc::try_unwrap(node) {
+            Ok(node) => node.children,
+            Err(node) => node.children.clone(),
+        },
+        Piece::Leaf(_) => unreachable!("lower levels hold nodes"),
+    }
+}
+
+fn deep<T, M: Measure<T>>(
+    m: &M,
+    prefix: Vec<Piece<T, M::Value>>,
+    middle: Tree<T, M::Value>,
+    suffix: Vec<Piece<T, M::Value>>,
+) -> Tree<T, M::Value> {
+    let measure = m.combine(
+        &m.combine(&pieces_measure(m, &prefix), &tree_measure(m, &middle)),
+        &pieces_measure(m, &suffix),
+    );
+    Tree::Deep(Arc::new(Deep {
+        measure,
+        prefix,
+        middle,
+        suffix,
+    }))
+}
+
+fn unwrap_deep<T: Clone, V: Clone>(deep: Arc<Deep<T, V>>) -> Deep<T, V> {
+    Arc::unwrap_or_clone(deep)
+}
+
+fn push_front<T: Clone, M: Measure<T>>(
+    m: &M,
+    tree: Tree<T, M::Value>,
+    piece: Piece<T, M::Value>,
+) -> Tree<T, M::Value> {
+    match tree {
+        Tree::Empty => Tree::Single(piece),
+        Tree::Single(other) => deep(m, vec![piece], Tree::Empty, vec![other]),
+        Tree::Deep(d) => {
+            let mut d = unwrap_deep(d);
+            d.measure = m.combine(&piece_measure(m, &piece), &d.measure);
+            if d.prefix.len() == 4 {
+                let rest = d.prefix.split_off(1);
+                let middle = mem::replace(&mut d.middle, Tree::Empty);
+                d.middle = push_front(m, middle, node(m, rest));
+            }
+            d.prefix.insert(0, piece);
+            Tree::Deep(Arc::new(d))
+        }
+    }
+}
+
+fn push_back<T: Clone, M: Measure<T>>(
+    m: &M,
+    tree: Tree<T, M::Value>,
+    piece: Piece<T, M::Value>,
+) -> Tree<T, M::Value> {
+    match tree {
+        Tree::Empty => Tree::Single(piece),
+        Tree::Single(other) => deep(m, vec![other], Tree::Empty, vec![piece]),
+        Tree::Deep(d) => {
+            let mut d = unwrap_deep(d);
+            d.measure = m.combine(&d.measure, &piece_measure(m, &piece));
+            if d.suffix.len() == 4 {
+                let last = d.suffix.split_off(3);
+                let full = mem::replace(&mut d.suffix, last);
+                let middle = mem::replace(&mut d.middle, Tree::Empty);
+                d.middle = push_back(m, middle, node(m, full));
+            }
+            d.suffix.push(piece);
+            Tree::Deep(Arc::new(d))
+        }
+    }
+}
+
+/// A tree from at most a few pieces.
+fn from_pieces<T: Clone, M: Measure<T>>(
+    m: &M,
+    pieces: Vec<Piece<T, M::Value>>,
+) -> Tree<T, M::Value> {
+    pieces
+        .into_iter()
+        .fold(Tree::Empty, |tree, p| push_back(m, tree, p))
+}
+
+/// A deep tree whose prefix may be empty, refilled from the middle.
+fn deep_left<T: Clone, M: Measure<T>>(
+    m: &M,
+    prefix: Vec<Piece<T, M::Value>>,
+    middle: Tree<T, M::Value>,
+    suffix: Vec<Piece<T, M::Value>>,
+) -> Tree<T, M::Value> {
+    if !prefix.is_empty() {
+        return deep(m, prefix, middle, suffix);
+    }
+    match pop_front(m, middle) {
+        None => from_pieces(m, suffix),
+        Some((first, middle)) => deep(m, children(first), middle, suffix),
+    }
+}
+
+/// A deep tree whose suffix may be empty, refilled from the middle.
+fn deep_right<T: Clone, M: Measure<T>>(
+    m: &M,
+    prefix: Vec<Piece<T, M::Value>>,
+    middle: Tree<T, M::Value>,
+    suffix: Vec<Piece<T, M::Value>>,
+) -> Tree<T, M::Value> {
+    if !suffix.is_empty() {
+        return deep(m, prefix, middle, suffix);
+    }
+    match pop_back(m, middle) {
+        None => from_pieces(m, prefix),
+        Some((last, middle)) => deep(m, prefix, middle, children(last)),
+    }
+}
+
+type Popped<T, V> = Option<(Piece<T, V>, Tree<T, V>)>;
+
+fn pop_front<T: Clone, M: Measure<T>>(m: &M, tree: Tree<T, M::Value>) -> Popped<T, M::Value> {
+    match tree {
+        Tree::Empty => None,
+        Tree::Single(piece) => Some((piece, Tree::Empty)),
+        Tree::Deep(d) => {
+            let mut d = unwrap_deep(d);
+            let first = d.prefix.remove(0);
+            Some((first, deep_left(m, d.prefix, d.middle, d.suffix)))
+        }
+    }
+}
+
+fn pop_back<T: Clone, M: Measure<T>>(m: &M, tree: Tree<T, M::Value>) -> Popped<T, M::Value> {
+    match tree {
+        Tree::Empty => None,
+        Tree::Single(piece) => Some((piece, Tree::Empty)),
+        Tree::Deep(d) => {
+            let mut d = unwrap_deep(d);
+            let last = d.suffix.pop().expect("digits are never empty");
+            Some((last, deep_right(m, d.prefix, d.middle, d.suffix)))
+        }
+    }
+}
+
+/// Groups two or more pieces into 2-3 nodes.
+fn nodes<T, M: Measure<T>>(m: &M, mut pieces: Vec<Piece<T, M::Value>>) -> Vec<Piece<T, M::Value>> {
+    let mut grouped = Vec::with_capacity(pieces.len() / 2);
+    let mut rest = pieces.drain(..);
+    let mut left = rest.len();
+    while left > 0 {
+        let take = match left {
+            2 | 4 => 2,
+            _ => 3,
+        };
+        grouped.push(node(m, rest.by_ref().take(take).collect()));
+        left -= take;
+    }
+    grouped
+}
+
+/// Concatenates `left`, `between` and `right`.
+fn concat<T: Clone, M: Measure<T>>(
+    m: &M,
+    left: Tree<T, M::Value>,
+    mut between: Vec<Piece<T, M::Value>>,
+    right: Tree<T, M::Value>,
+) -> Tree<T, M::Value> {
+    match (left, right) {
+        (Tree::Empty, right) => between
+            .into_iter()
+            .rev()
+            .fold(right, |tree, p| push_front(m, tree, p)),
+        (left, Tree::Empty) => between
+            .into_iter()
+            .fold(left, |tree, p| push_back(m, tree, p)),
+        (Tree::Single(piece), right) => {
+            let tree = concat(m, Tree::Empty, between, right);
+            push_front(m, tree, piece)
+        }
+        (left, Tree::Single(piece)) => {
+            let tree = concat(m, left, between, Tree::Empty);
+            push_back(m, tree, piece)
+        }
+        (Tree::Deep(a), Tree::Deep(b)) => {
+            let (a, b) = (unwrap_deep(a), unwrap_deep(b));
+            let mut seam = a.suffix;
+            seam.append(&mut between);
+            seam.extend(b.prefix);
+            let middle = concat(m, a.middle, nodes(m, seam), b.middle);
+            deep(m, a.prefix, middle, b.suffix)
+        }
+    }
+}
+
+type Split<T, V> = (Vec<Piece<T, V>>, Piece<T, V>, Vec<Piece<T, V>>);
+
+/// Splits a digit at the first piece whose inclusion makes `pred` hold,
+/// or at the last piece.
+fn split_pieces<T, M: Measure<T>>(
+    m: &M,
+    pred: &mut impl FnMut(&M::Value) -> bool,
+    mut acc: M::Value,
+    mut pieces: Vec<Piece<T, M::Value>>,
+) -> Split<T, M::Value> {
+    let mut at = pieces.len() - 1;
+    for (i, piece) in pieces.iter().enumerate().take(at) {
+        acc = m.combine(&acc, &piece_measure(m, piece));
+        if pred(&acc) {
+            at = i;
+            break;
+        }
+    }
+    let right = pieces.split_off(at + 1);
+    let piece = pieces.pop().expect("split point is in range");
+    (pieces, piece, right)
+}
+
+type TreeSplit<T, V> = (Tree<T, V>, Piece<T, V>, Tree<T, V>);
+
+/// Splits a non-empty tree around the piece at which `pred`, applied to
+/// `acc` combined with the prefix measure, first holds. `pred` must hold
+/// for the whole tree.
+fn split_tree<T: Clone, M: Measure<T>>(
+    m: &M,
+    pred: &mut impl FnMut(&M::Value) -> bool,
+    acc: M::Value,
+    tree: Tree<T, M::Value>,
+) -> TreeSplit<T, M::Value> {
+    match tree {
+        Tree::Empty => unreachable!("split of an empty tree"),
+        Tree::Single(piece) => (Tree::Empty, piece, Tree::Empty),
+        Tree::Deep(d) => {
+            let d = unwrap_deep(d);
+            let with_prefix = m.combine(&acc, &pieces_measure(m, &d.prefix));
+            if pred(&with_prefix) {
+                let (left, piece, right) = split_pieces(m, pred, acc, d.prefix);
+                return (
+                    from_pieces(m, left),
+                    piece,
+                    deep_left(m, right, d.middle, d.suffix),
+                );
+            }
+            let with_middle = m.combine(&with_prefix, &tree_measure(m, &d.middle));
+            if pred(&with_middle) {
+                let (below, node, above) = split_tree(m, pred, with_prefix.clone(), d.middle);
+                let acc = m.combine(&with_prefix, &tree_measure(m, &below));
+                let (left, piece, right) = split_pieces(m, pred, acc, children(node));
+                return (
+                    deep_right(m, d.prefix, below, left),
+                    piece,
+                    deep_left(m, right, above, d.suffix),
+                );
+            }
+            let (left, piece, right) = split_pieces(m, pred, with_middle, d.suffix);
+            (
+                deep_right(m, d.prefix, d.middle, left),
+                piece,
+                from_pieces(m, right),
+            )
+        }
+    }
+}
+
+/// The first piece whose inclusion makes `pred` hold, or the last one,
+/// with the measure before it.
+fn find_piece<'a, T, M: Measure<T>>(
+    m: &M,
+    pred: &mut impl FnMut(&M::Value) -> bool,
+    mut acc: M::Value,
+    pieces: &'a [Piece<T, M::Value>],
+) -> (&'a Piece<T, M::Value>, M::Value) {
+    let (last, init) = pieces.split_last().expect("digits are never empty");
+    for piece in init {
+        let next = m.combine(&acc, &piece_measure(m, piece));
+        if pred(&next) {
+            return (piece, acc);
+        }
+        acc = next;
+    }
+    (last, acc)
+}
+
+/// The element at which `pred` first holds, below a piece.
+fn find_in_piece<'a, T, M: Measure<T>>(
+    m: &M,
+    pred: &mut impl FnMut(&M::Value) -> bool,
+    mut acc: M::Value,
+    mut piece: &'a Piece<T, M::Value>,
+) -> &'a T {
+    loop {
+        match piece {
+            Piece::Leaf(item) => return item,
+            Piece::Node(node) => (piece, acc) = find_piece(m, pred, acc, &node.children),
+        }
+    }
+}
+
+/// The element at which `pred` first holds, in a non-empty tree for which
+/// it holds.
+fn find_in_tree<'a, T, M: Measure<T>>(
+    m: &M,
+    pred: &mut impl FnMut(&M::Value) -> bool,
+    acc: M::Value,
+    tree: &'a Tree<T, M::Value>,
+) -> &'a T {
+    match tree {
+        Tree::Empty => unreachable!("search of an empty tree"),
+        Tree::Single(piece) => find_in_piece(m, pred, acc, piece),
+        Tree::Deep(d) => {
+            let with_prefix = m.combine(&acc, &pieces_measure(m, &d.prefix));
+            if pred(&with_prefix) {
+                let (piece, acc) = find_piece(m, pred, acc, &d.prefix);
+                return find_in_piece(m, pred, acc, piece);
+            }
+            let with_middle = m.combine(&with_prefix, &tree_measure(m, &d.middle));
+            if pred(&with_middle) {
+                return find_in_tree(m, pred, with_prefix, &d.middle);
+            }
+            let (piece, acc) = find_piece(m, pred, with_middle, &d.suffix);
+            find_in_piece(m, pred, acc, piece)
+        }
+    }
+}
+
+fn leaf<T, V>(piece: Piece<T, V>) -> T {
+    match piece {
+        Piece::Leaf(item) => item,
+        Piece::Node(_) => unreachable!("the top level holds leaves"),
+    }
+}
+
+/// A persistent sequence of `T` annotated with measures in `M`.
+pub struct FingerTree<T, M: Measure<T>> {
+    monoid: M,
+    tree: Tree<T, M::Value>,
+}
+
+impl<T: Clone, M: Measure<T> + Clone> Clone for FingerTree<T, M> {
+    fn clone(&self) -> Self {
+        FingerTree {
+            monoid: self.monoid.clone(),
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Clone, M: Measure<T> + Default> Default for FingerTree<T, M> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<T: Clone, M: Measure<T>> FingerTree<T, M> {
+    /// Creates an empty tree measured by `monoid`.
+    pub fn new(monoid: M) -> Self {
+        FingerTree {
+            monoid,
+            tree: Tree::Empty,
+        }
+    }
+
+    /// Whether the tree holds no elements.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.tree, Tree::Empty)
+    }
+
+    /// The combined measure of every element, in `O(1)`.
+    pub fn measure(&self) -> M::Value {
+        tree_measure(&self.monoid, &self.tree)
+    }
+
+    /// Adds `item` at the front.
+    pub fn push_front(&mut self, item: T) {
+        let tree = mem::replace(&mut self.tree, Tree::Empty);
+        self.tree = push_front(&self.monoid, tree, Piece::Leaf(item));
+    }
+
+    /// Adds `item` at the back.
+    pub fn push_back(&mut self, item: T) {
+        let tree = mem::replace(&mut self.tree, Tree::Empty);
+        self.tree = push_back(&self.monoid, tree, Piece::Leaf(item));
+    }
+
+    /// Removes and returns the front element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        let tree = mem::replace(&mut self.tree, Tree::Empty);
+        let (piece, rest) = pop_front(&self.monoid, tree)?;
+        self.tree = rest;
+        Some(leaf(piece))
+    }
+
+    /// Removes and returns the back element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tree = mem::replace(&mut self.tree, Tree::Empty);
+        let (piece, rest) = pop_back(&self.monoid, tree)?;
+        self.tree = rest;
+        Some(leaf(piece))
+    }
+
+    /// The front element.
+    pub fn front(&self) -> Option<&T> {
+        let mut piece = match &self.tree {
+            Tree::Empty => return None,
+            Tree::Single(piece) => piece,
+            Tree::Deep(d) => &d.prefix[0],
+        };
+        loop {
+            match piece {
+                Piece::Leaf(item) => return Some(item),
+                Piece::Node(node) => piece = &node.children[0],
+            }
+        }
+    }
+
+    /// The back element.
+    pub fn back(&self) -> Option<&T> {
+        let mut piece = match &self.tree {
+            Tree::Empty => return None,
+            Tree::Single(piece) => piece,
+            Tree::Deep(d) => d.suffix.last().expect("digits are never empty"),
+        };
+        loop {
+            match piece {
+                Piece::Leaf(item) => return Some(item),
+                Piece::Node(node) => piece = node.children.last().expect("nodes are never empty"),
+            }
+        }
+    }
+
+    /// Moves the elements of `other` to the back of this tree, in
+    /// `O(log min(n, m))`. `other` must use the same measure.
+    pub fn append(&mut self, other: Self) {
+        let tree = mem::replace(&mut self.tree, Tree::Empty);
+        self.tree = concat(&self.monoid, tree, Vec::new(), other.tree);
+    }
+
+    /// The first element at which `pred`, applied to the measure of the
+    /// prefix up to and including it, holds. `pred` must be monotone: once
+    /// true for a prefix, true for every longer one.
+    pub fn find(&self, mut pred: impl FnMut(&M::Value) -> bool) -> Option<&T> {
+        if self.is_empty() || !pred(&self.measure()) {
+            return None;
+        }
+        Some(find_in_tree(
+            &self.monoid,
+            &mut pred,
+            self.monoid.identity(),
+            &self.tree,
+        ))
+    }
+
+    /// Splits the tree before the element [`find`](Self::find) would
+    /// return: the first part is the longest prefix for which `pred` does
+    /// not hold, the second the rest.
+    pub fn split(&self, mut pred: impl FnMut(&M::Value) -> bool) -> (Self, Self)
+    where
+        M: Clone,
+    {
+        if self.is_empty() || !pred(&self.measure()) {
+            return (self.clone(), Self::new(self.monoid.clone()));
+        }
+        let m = &self.monoid;
+        let (left, piece, right) = split_tree(m, &mut pred, m.identity(), self.tree.clone());
+        let left = FingerTree {
+            monoid: m.clone(),
+            tree: left,
+        };
+        let right = FingerTree {
+            monoid: m.clone(),
+            tree: push_front(m, right, piece),
+        };
+        (left, right)
+    }
+
+    /// Iterates over the elements, front to back.
+    pub fn iter(&self) -> Iter<'_, T, M::Value> {
+        Iter {
+            stack: vec![Frame::Tree(&self.tree)],
+        }
+    }
+}
+
+impl<T: Clone + fmt::Debug, M: Measure<T>> fmt::Debug for FingerTree<T, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone, M: Measure<T>> Extend<T> for FingerTree<T, M> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+impl<T: Clone, M: Measure<T> + Default> FromIterator<T> for FingerTree<T, M> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::default();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<'a, T: Clone, M: Measure<T>> IntoIterator for &'a FingerTree<T, M> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, M::Value>;
+
+    fn into_iter(self) -> Iter<'a, T, M::Value> {
+        self.iter()
+    }
+}
+
+enum Frame<'a, T, V> {
+    Tree(&'a Tree<T, V>),
+    Pieces(&'a [Piece<T, V>]),
+}
+
+/// An iterator over the elements of a finger tree, front to back.
+pub struct Iter<'a, T, V> {
+    stack: Vec<Frame<'a, T, V>>,
+}
+
+impl<'a, T, V> Iterator for Iter<'a, T, V> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            match self.stack.pop()? {
+                Frame::Tree(Tree::Empty) => {}
+                Frame::Tree(Tree::Single(piece)) => {
+                    self.stack.push(Frame::Pieces(std::slice::from_ref(piece)))
+                }
+                Frame::Tree(Tree::Deep(d)) => {
+                    self.stack.push(Frame::Pieces(&d.suffix));
+                    self.stack.push(Frame::Tree(&d.middle));
+                    self.stack.push(Frame::Pieces(&d.prefix));
+                }
+                Frame::Pieces(pieces) => {
+                    let Some((piece, rest)) = pieces.split_first() else {
+                        continue;
+                    };
+                    self.stack.push(Frame::Pieces(rest));
+                    match piece {
+                        Piece::Leaf(item) => return Some(item),
+                        Piece::Node(node) => self.stack.push(Frame::Pieces(&node.children)),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A persistent double-ended queue with indexing, splitting and
+/// concatenation in `O(log n)`.
+pub struct Deque<T> {
+    tree: FingerTree<T, Size>,
+}
+
+impl<T: Clone> Clone for Deque<T> {
+    fn clone(&self) -> Self {
+        Deque {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Clone> Default for Deque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Deque<T> {
+    /// Creates an empty deque.
+    pub fn new() -> Self {
+        Deque {
+            tree: FingerTree::new(Size),
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.tree.measure()
+    }
+
+    /// Whether the deque holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Adds `item` at the front.
+    pub fn push_front(&mut self, item: T) {
+        self.tree.push_front(item);
+    }
+
+    /// Adds `item` at the back.
+    pub fn push_back(&mut self, item: T) {
+        self.tree.push_back(item);
+    }
+
+    /// Removes and returns the front element.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.tree.pop_front()
+    }
+
+    /// Removes and returns the back element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tree.pop_back()
+    }
+
+    /// The front element.
+    pub fn front(&self) -> Option<&T> {
+        self.tree.front()
+    }
+
+    /// The back element.
+    pub fn back(&self) -> Option<&T> {
+        self.tree.back()
+    }
+
+    /// The element at `index`.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.tree.find(|&len| len > index)
+    }
+
+    /// Moves the elements of `other` to the back.
+    pub fn append(&mut self, other: Self) {
+        self.tree.append(other.tree);
+    }
+
+    /// Splits the deque at `at`, keeping `[0, at)` and returning the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(at <= self.len(), "split index out of bounds");
+        let (left, right) = self.tree.split(|&len| len > at);
+        self.tree = left;
+        Deque { tree: right }
+    }
+
+    /// Iterates over the elements, front to back.
+    pub fn iter(&self) -> Iter<'_, T, usize> {
+        self.tree.iter()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for Deque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for Deque<T> {}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for Deque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> Extend<T> for Deque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.tree.extend(iter);
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Deque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Deque {
+            tree: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a Deque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, usize>;
+
+    fn into_iter(self) -> Iter<'a, T, usize> {
+        self.iter()
+    }
+}
+
+/// The greatest element, or `None` for the identity.
+struct Greatest<T>(PhantomData<fn() -> T>);
+
+impl<T> Clone for Greatest<T> {
+    fn clone(&self) -> Self {
+        Greatest(PhantomData)
+    }
+}
+
+impl<T: Ord + Clone> Monoid for Greatest<T> {
+    type Value = Option<T>;
+
+    fn identity(&self) -> Option<T> {
+        None
+    }
+
+    fn combine(&self, a: &Option<T>, b: &Option<T>) -> Option<T> {
+        // Ties keep the left, so pops take the earliest of equal elements.
+        if b > a {
+            b.clone()
+        } else {
+            a.clone()
+        }
+    }
+}
+
+impl<T: Ord + Clone> Measure<T> for Greatest<T> {
+    fn measure(&self, item: &T) -> Option<T> {
+        Some(item.clone())
+    }
+}
+
+/// A persistent max-priority queue that melds in `O(log n)`. Equal
+/// elements pop in the order they were pushed.
+pub struct PriorityQueue<T: Ord + Clone> {
+    tree: FingerTree<T, Greatest<T>>,
+    len: usize,
+}
+
+impl<T: Ord + Clone> Clone for PriorityQueue<T> {
+    fn clone(&self) -> Self {
+        PriorityQueue {
+            tree: self.tree.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<T: Ord + Clone> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone> PriorityQueue<T> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        PriorityQueue {
+            tree: FingerTree::new(Greatest(PhantomData)),
+            len: 0,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the queue holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds `item`, in `O(1)` amortized.
+    pub fn push(&mut self, item: T) {
+        self.tree.push_back(item);
+        self.len += 1;
+    }
+
+    /// The greatest element.
+    pub fn peek(&self) -> Option<&T> {
+        let max = self.tree.measure()?;
+        self.tree.find(|m| m.as_ref() >= Some(&max))
+    }
+
+    /// Removes and returns the greatest element.
+    pub fn pop(&mut self) -> Option<T> {
+        let max = self.tree.measure()?;
+        let (mut left, mut right) = self.tree.split(|m| m.as_ref() >= Some(&max));
+        let item = right.pop_front();
+        left.append(right);
+        self.tree = left;
+        self.len -= 1;
+        item
+    }
+
+    /// Moves the elements of `other` into this queue.
+    pub fn append(&mut self, other: Self) {
+        self.tree.append(other.tree);
+        self.len += other.len;
+    }
+
+    /// Iterates over the elements in the order they were pushed.
+    pub fn iter(&self) -> Iter<'_, T, Option<T>> {
+        self.tree.iter()
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> fmt::Debug for PriorityQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord + Clone> Extend<T> for PriorityQueue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for PriorityQueue<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+/// The number of elements and the last one, which in a sorted sequence
+/// is the greatest.
+struct Keyed<T>(PhantomData<fn() -> T>);
+
+impl<T> Clone for Keyed<T> {
+    fn clone(&self) -> Self {
+        Keyed(PhantomData)
+    }
+}
+
+impl<T: Clone> Monoid for Keyed<T> {
+    type Value = (usize, Option<T>);
+
+    fn identity(&self) -> (usize, Option<T>) {
+        (0, None)
+    }
+
+    fn combine(&self, a: &(usize, Option<T>), b: &(usize, Option<T>)) -> (usize, Option<T>) {
+        (a.0 + b.0, b.1.as_ref().or(a.1.as_ref()).cloned())
+    }
+}
+
+impl<T: Clone> Measure<T> for Keyed<T> {
+    fn measure(&self, item: &T) -> (usize, Option<T>) {
+        (1, Some(item.clone()))
+    }
+}
+
+/// A persistent sorted sequence, duplicates allowed, with insertion,
+/// removal, rank queries and splits at a key in `O(log n)`.
+pub struct OrderedSequence<T: Ord + Clone> {
+    tree: FingerTree<T, Keyed<T>>,
+}
+
+impl<T: Ord + Clone> Clone for OrderedSequence<T> {
+    fn clone(&self) -> Self {
+        OrderedSequence {
+            tree: self.tree.clone(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> Default for OrderedSequence<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone> OrderedSequence<T> {
+    /// Creates an empty sequence.
+    pub fn new() -> Self {
+        OrderedSequence {
+            tree: FingerTree::new(Keyed(PhantomData)),
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.tree.measure().0
+    }
+
+    /// Whether the sequence holds no elements.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Inserts `item` after any elements equal to it.
+    pub fn insert(&mut self, item: T) {
+        let (mut left, right) = self.tree.split(|(_, k)| k.as_ref() > Some(&item));
+        left.push_back(item);
+        left.append(right);
+        self.tree = left;
+    }
+
+    /// Removes one element equal to `item`; returns whether there was one.
+    pub fn remove(&mut self, item: &T) -> bool {
+        if !self.contains(item) {
+            return false;
+        }
+        let (mut left, mut right) = self.tree.split(|(_, k)| k.as_ref() >= Some(item));
+        right.pop_front();
+        left.append(right);
+        self.tree = left;
+        true
+    }
+
+    /// Whether an element equal to `item` is present.
+    pub fn contains(&self, item: &T) -> bool {
+        self.tree
+            .find(|(_, k)| k.as_ref() >= Some(item))
+            .is_some_and(|found| found == item)
+    }
+
+    /// Number of elements less than `item`.
+    pub fn rank(&self, item: &T) -> usize {
+        self.tree
+            .split(|(_, k)| k.as_ref() >= Some(item))
+            .0
+            .measure()
+            .0
+    }
+
+    /// The element at sorted position `index`.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.tree.find(|&(len, _)| len > index)
+    }
+
+    /// The least element.
+    pub fn first(&self) -> Option<&T> {
+        self.tree.front()
+    }
+
+    /// The greatest element.
+    pub fn last(&self) -> Option<&T> {
+        self.tree.back()
+    }
+
+    /// Removes and returns the least element.
+    pub fn pop_first(&mut self) -> Option<T> {
+        self.tree.pop_front()
+    }
+
+    /// Removes and returns the greatest element.
+    pub fn pop_last(&mut self) -> Option<T> {
+        self.tree.pop_back()
+    }
+
+    /// Splits the sequence into the elements less than `key` and the rest.
+    pub fn split(&self, key: &T) -> (Self, Self) {
+        let (left, right) = self.tree.split(|(_, k)| k.as_ref() >= Some(key));
+        (
+            OrderedSequence { tree: left },
+            OrderedSequence { tree: right },
+        )
+    }
+
+    /// Iterates over the elements in sorted order.
+    pub fn iter(&self) -> Iter<'_, T, (usize, Option<T>)> {
+        self.tree.iter()
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> fmt::Debug for OrderedSequence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord + Clone> Extend<T> for OrderedSequence<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<T> for OrderedSequence<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut items: Vec<T> = iter.into_iter().collect();
+        items.sort();
+        let mut sequence = Self::new();
+        sequence.tree.extend(items);
+        sequence
+    }
+}
@@ -0,0 +1,178 @@
+//! An FM-index: substring counting and location over a compressed text.
+//!
+//! The FM-index (Ferragina and Manzini, 2000) searches a text through its
+//! Burrows–Wheeler transform: the character before each suffix, listed in
+//! suffix-array order. A pattern's occurrences are a range of suffixes,
+//! and backward search narrows that range one pattern character at a
+//! time, right to left. Prepending `c` to the suffixes of rows `lo..hi`
+//! gives the rows `C[c] + rank_c(lo)..C[c] + rank_c(hi)`, where `C[c]`
+//! counts the characters smaller than `c` and `rank_c(i)` the `c`s among
+//! the first `i` BWT characters. Counting a pattern of length `m` takes
+//! `m` pairs of rank queries, however long the text.
+//!
+//! Ranks come from a [`WaveletTree`] over the BWT, built on the succinct
+//! [`BitVector`]: up to nine bits per text byte, for 256 byte values
+//! and the end-of-text sentinel. Locating an occurrence needs its text
+//! position, which is only sampled: the suffix array entries that are
+//! multiples of the sample rate are kept, rows holding one are marked in a
+//! bit vector, and from any other row LF-mapping, the same step as
+//! backward search, walks the text backwards until it meets a sample.
+//! That is `O(s)` steps per occurrence for `4/s` bytes per text byte.
+//!
+//! The suffix array is built by [`SuffixArray`] and then dropped; neither
+//! it nor the text is kept.
+//!
+//! [`BitVector`]: crate::bit_vector::BitVector
+//! [`SuffixArray`]: crate::suffix_array::SuffixArray
+//! [`WaveletTree`]: crate::wavelet_tree::WaveletTree
+
+use crate::bit_vector::BitVector;
+use crate::suffix_array::SuffixArray;
+use crate::wavelet_tree::WaveletTree;
+
+/// The suffix array sample rate [`FmIndex::new`] uses.
+pub const DEFAULT_SAMPLE_RATE: usize = 32;
+
+/// The BWT symbol ending the text; byte `b` is symbol `b + 1`.
+const SENTINEL: u64 = 0;
+
+/// An FM-index of a byte string.
+#[derive(Clone, Debug)]
+pub struct FmIndex {
+    /// The BWT of the text and sentinel, with one row per suffix including
+    /// the empty one.
+    bwt: WaveletTree,
+    /// Number of symbols smaller than each symbol.
+    counts: Vec<usize>,
+    /// Rows whose text position is sampled.
+    sampled: BitVector,
+    /// The sampled positions, in row order.
+    samples: Vec<u32>,
+    sample_rate: usize,
+}
+
+impl FmIndex {
+    /// Indexes `text`, sampling every 32nd text position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `text` is `u32::MAX` bytes or longer.
+    pub fn new(text: &[u8]) -> Self {
+        Self::with_sample_rate(text, DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Indexes `text`, sampling every `rate`th text position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero or `text` is `u32::MAX` bytes or longer.
+    pub fn with_sample_rate(text: &[u8], rate: usize) -> Self {
+        Self::from_suffix_array(&SuffixArray::build(text), rate)
+    }
+
+    /// Indexes the text of an already built suffix array, sampling every
+    /// `rate`th text position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub fn from_suffix_array(sa: &SuffixArray, rate: usize) -> Self {
+        assert!(rate > 0, "sample rate must be positive");
+        let text = sa.text();
+        // Row 0 is the empty suffix, which sorts first.
+        let rows = std::iter::once(text.len() as u32).chain(sa.suffixes().iter().copied());
+        let mut bwt = Vec::with_capacity(text.len() + 1);
+        let mut marks = Vec::with_capacity(text.len() + 1);
+        let mut samples = Vec::new();
+        for position in rows {
+            bwt.push(match position {
+                0 => SENTINEL,
+                p => u64::from(text[p as usize - 1]) + 1,
+            });
+            let sampled = (position as usize).is_multiple_of(rate);
+            marks.push(sampled);
+            if sampled {
+                samples.push(position);
+            }
+        }
+        let mut counts = vec![0; 258];
+        for &symbol in &bwt {
+            counts[symbol as usize + 1] += 1;
+        }
+        for c in 1..counts.len() {
+            counts[c] += counts[c - 1];
+        }
+        FmIndex {
+            bwt: WaveletTree::new(&bwt),
+            counts,
+            sampled: marks.into_iter().collect(),
+            samples,
+            sample_rate: rate,
+        }
+    }
+
+    /// Length of the indexed text.
+    pub fn len(&self) -> usize {
+        self.bwt.len() - 1
+    }
+
+    /// Whether the indexed text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The suffix array sample rate.
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// The number of occurrences of `pattern`, overlapping ones included,
+    /// in `O(m)` rank queries. The empty pattern occurs once at every text
+    /// position, as in [`SuffixArray::count`].
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        self.rows(pattern).len()
+    }
+
+    /// Whether `pattern` occurs in the text.
+    pub fn contains(&self, pattern: &[u8]) -> bool {
+        !self.rows(pattern).is_empty()
+    }
+
+    /// The positions where `pattern` occurs, in ascending order.
+    pub fn locate(&self, pattern: &[u8]) -> Vec<usize> {
+        let mut positions: Vec<usize> = self.rows(pattern).map(|row| self.position(row)).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// The rows of the suffixes starting with `pattern`, by backward search.
+    fn rows(&self, pattern: &[u8]) -> std::ops::Range<usize> {
+        if pattern.is_empty() {
+            // Every row but the empty suffix's, which is no occurrence.
+            return 1..self.bwt.len();
+        }
+        let (mut lo, mut hi) = (0, self.bwt.len());
+        for &byte in pattern.iter().rev() {
+            let symbol = u64::from(byte) + 1;
+            let base = self.counts[symbol as usize];
+            lo = base + self.bwt.rank(symbol, lo);
+            hi = base + self.bwt.rank(symbol, hi);
+            if lo >= hi {
+                return 0..0;
+            }
+        }
+        lo..hi
+    }
+
+    /// The text position of the suffix in `row`.
+    fn position(&self, mut row: usize) -> usize {
+        let mut steps = 0;
+        while !self.sampled.get(row) {
+            // The row of the suffix one character longer.
+            let symbol = self.bwt.get(row);
+            row = self.counts[symbol as usize] + self.bwt.rank(symbol, row);
+            steps += 1;
+        }
+        self.samples[self.sampled.rank1(row)] as usize + steps
+    }
+}
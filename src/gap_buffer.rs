@@ -0,0 +1,254 @@
+//! A gap buffer: text in one array with a movable hole at the cursor.
+//!
+//! The bytes before the cursor sit at the front of the array and the bytes
+//! after it at the back, with unused space between them. Typing writes into
+//! the gap and deleting next to the cursor widens it, so both are `O(1)`.
+//! When the gap is used up, the array doubles, which keeps insertion
+//! amortized `O(1)`. Moving the cursor moves the bytes between the old and
+//! new position across the gap, so it costs the distance moved. That suits
+//! the way people edit: many small changes close together.
+//!
+//! Edits far apart cost a full copy of everything between them, and so do
+//! views of the whole text. Once a document grows large enough for that to
+//! matter, move it into a [`Rope`]: `Rope::from(&buffer)` and
+//! `GapBuffer::from(&rope)` convert between the two.
+//!
+//! [`Rope`]: crate::rope::Rope
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+/// Smallest capacity an array is grown to.
+const MIN_CAPACITY: usize = 64;
+
+/// A UTF-8 text buffer with cheap edits at a cursor.
+#[derive(Clone, Debug, Default)]
+pub struct GapBuffer {
+    /// The text before the gap, the gap, then the text after it.
+    buf: Vec<u8>,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl GapBuffer {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty buffer with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        GapBuffer {
+            buf: vec![0; capacity],
+            gap_start: 0,
+            gap_end: capacity,
+        }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.buf.len() - (self.gap_end - self.gap_start)
+    }
+
+    /// Whether the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of bytes the buffer can hold without growing.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Byte offset of the cursor.
+    pub fn cursor(&self) -> usize {
+        self.gap_start
+    }
+
+    /// Moves the cursor to byte offset `at`, in `O(distance moved)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end or not on a char boundary.
+    pub fn set_cursor(&mut self, at: usize) {
+        self.check_boundary(at);
+        if at < self.gap_start {
+            let n = self.gap_start - at;
+            self.buf.copy_within(at..self.gap_start, self.gap_end - n);
+            self.gap_start = at;
+            self.gap_end -= n;
+        } else if at > self.gap_start {
+            let n = at - self.gap_start;
+            self.buf
+                .copy_within(self.gap_end..self.gap_end + n, self.gap_start);
+            self.gap_start += n;
+            self.gap_end += n;
+        }
+    }
+
+    /// Inserts `text` at the cursor, leaving the cursor after it.
+    pub fn insert(&mut self, text: &str) {
+        self.reserve(text.len());
+        self.buf[self.gap_start..self.gap_start + text.len()].copy_from_slice(text.as_bytes());
+        self.gap_start += text.len();
+    }
+
+    /// Inserts a char at the cursor, leaving the cursor after it.
+    pub fn insert_char(&mut self, c: char) {
+        self.insert(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Removes and returns the char before the cursor, if any.
+    pub fn delete_backward(&mut self) -> Option<char> {
+        let before = &self.buf[self.gap_start.saturating_sub(4)..self.gap_start];
+        let start = (0..before.len())
+            .rev()
+            .find(|&i| !is_continuation(before[i]))?;
+        let c = decode(&before[start..]);
+        self.gap_start -= c.len_utf8();
+        Some(c)
+    }
+
+    /// Removes and returns the char after the cursor, if any.
+    pub fn delete_forward(&mut self) -> Option<char> {
+        let after = &self.buf[self.gap_end..];
+        let &first = after.first()?;
+        let width = utf8_width(first);
+        let c = decode(&after[..width]);
+        self.gap_end += width;
+        Some(c)
+    }
+
+    /// Removes the bytes in `range`, leaving the cursor where they were.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn remove(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.end);
+        self.set_cursor(range.start);
+        self.gap_end += range.len();
+    }
+
+    /// Copies out the bytes in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn slice(&self, range: Range<usize>) -> String {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        let (before, after) = self.as_slices();
+        let split = before.len();
+        let mut out = String::with_capacity(range.len());
+        if range.start < split {
+            out.push_str(&before[range.start..range.end.min(split)]);
+        }
+        if range.end > split {
+            out.push_str(&after[range.start.max(split) - split..range.end - split]);
+        }
+        out
+    }
+
+    /// The text before and after the cursor.
+    ///
+    /// This validates both halves as UTF-8, so it is `O(n)`.
+    pub fn as_slices(&self) -> (&str, &str) {
+        let text = |bytes| std::str::from_utf8(bytes).expect("gap buffer holds UTF-8");
+        (
+            text(&self.buf[..self.gap_start]),
+            text(&self.buf[self.gap_end..]),
+        )
+    }
+
+    /// Makes room for at least `additional` more bytes in the gap.
+    fn reserve(&mut self, additional: usize) {
+        if self.gap_end - self.gap_start >= additional {
+            return;
+        }
+        let old = self.buf.len();
+        let tail = old - self.gap_end;
+        let size = (2 * (self.len() + additional)).max(MIN_CAPACITY);
+        self.buf.resize(size, 0);
+        self.buf.copy_within(self.gap_end..old, size - tail);
+        self.gap_end = size - tail;
+    }
+
+    /// The byte at text offset `at`, skipping the gap.
+    fn byte(&self, at: usize) -> u8 {
+        if at < self.gap_start {
+            self.buf[at]
+        } else {
+            self.buf[at + self.gap_end - self.gap_start]
+        }
+    }
+
+    fn check_boundary(&self, at: usize) {
+        let len = self.len();
+        assert!(at <= len, "byte offset is past the end");
+        assert!(
+            at == len || !is_continuation(self.byte(at)),
+            "byte offset is not on a char boundary"
+        );
+    }
+}
+
+fn is_continuation(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+/// Length of the UTF-8 sequence starting with `b`.
+fn utf8_width(b: u8) -> usize {
+    match b.leading_ones() {
+        0 => 1,
+        n => n as usize,
+    }
+}
+
+/// The single char encoded by `bytes`.
+fn decode(bytes: &[u8]) -> char {
+    let s = std::str::from_utf8(bytes).expect("gap buffer holds UTF-8");
+    s.chars().next().expect("sequence is not empty")
+}
+
+impl From<&str> for GapBuffer {
+    /// Creates a buffer holding `text`, with the cursor at the end.
+    fn from(text: &str) -> Self {
+        let mut buffer = Self::with_capacity(text.len().max(MIN_CAPACITY));
+        buffer.insert(text);
+        buffer
+    }
+}
+
+impl From<&Rope> for GapBuffer {
+    fn from(rope: &Rope) -> Self {
+        let mut buffer = Self::with_capacity((2 * rope.len_bytes()).max(MIN_CAPACITY));
+        for chunk in rope.chunks() {
+            buffer.insert(chunk);
+        }
+        buffer
+    }
+}
+
+impl From<&GapBuffer> for Rope {
+    fn from(buffer: &GapBuffer) -> Self {
+        let (before, after) = buffer.as_slices();
+        let mut rope = Rope::from(before);
+        rope.insert(before.len(), after);
+        rope
+    }
+}
+
+impl fmt::Display for GapBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (before, after) = self.as_slices();
+        f.write_str(before)?;
+        f.write_str(after)
+    }
+}
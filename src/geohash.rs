@@ -0,0 +1,358 @@
+//! Geohash cells over latitude and longitude.
+//!
+//! A geohash is the Morton code of a point on the longitude/latitude
+//! rectangle, written five bits per character in a base-32 alphabet. Bits
+//! alternate between the axes starting with longitude, and each bit halves
+//! the cell along its axis, so every prefix of a geohash names an enclosing
+//! cell and hashes that share a long prefix are close together. That makes
+//! geohashes a convenient key for sorted key-value stores: a region query
+//! becomes a handful of prefix scans, produced here by [`covering`].
+//!
+//! The cells are rectangles in degrees, so they shrink in width toward the
+//! poles and a cell touching the antimeridian has its neighbor on the other
+//! side of the map. Longitude wraps around in [`Geohash::neighbor`];
+//! latitude does not.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::morton;
+
+/// Longest supported geohash, in characters: 60 bits.
+pub const MAX_PRECISION: u8 = 12;
+
+const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+/// Bits per axis at [`MAX_PRECISION`].
+const AXIS_BITS: u32 = 30;
+
+/// Errors from building or parsing a geohash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeohashError {
+    /// A latitude outside `[-90, 90]` or longitude outside `[-180, 180]`.
+    InvalidCoordinate,
+    /// A precision outside `1..=MAX_PRECISION`.
+    InvalidPrecision,
+    /// A character outside the geohash alphabet.
+    InvalidCharacter,
+}
+
+impl fmt::Display for GeohashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GeohashError::InvalidCoordinate => "latitude or longitude out of range",
+            GeohashError::InvalidPrecision => "geohash precision out of range",
+            GeohashError::InvalidCharacter => "invalid geohash character",
+        })
+    }
+}
+
+impl Error for GeohashError {}
+
+/// A closed latitude/longitude rectangle, in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatLonBox {
+    /// Southern edge.
+    pub min_lat: f64,
+    /// Western edge.
+    pub min_lon: f64,
+    /// Northern edge.
+    pub max_lat: f64,
+    /// Eastern edge.
+    pub max_lon: f64,
+}
+
+impl LatLonBox {
+    /// Creates a box from its south-west and north-east corners.
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        LatLonBox {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        }
+    }
+
+    /// The center of the box as `(lat, lon)`.
+    pub fn center(&self) -> (f64, f64) {
+        (
+            (self.min_lat + self.max_lat) / 2.0,
+            (self.min_lon + self.max_lon) / 2.0,
+        )
+    }
+
+    /// Whether `(lat, lon)` lies inside or on the boundary of the box.
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        lat >= self.min_lat && lat <= self.max_lat && lon >= self.min_lon && lon <= self.max_lon
+    }
+
+    /// Whether `other` lies entirely inside the box.
+    pub fn contains_box(&self, other: &LatLonBox) -> bool {
+        other.min_lat >= self.min_lat
+            && other.max_lat <= self.max_lat
+            && other.min_lon >= self.min_lon
+            && other.max_lon <= self.max_lon
+    }
+
+    /// Whether the two boxes share at least one point.
+    pub fn intersects(&self, other: &LatLonBox) -> bool {
+        self.min_lat <= other.max_lat
+            && other.min_lat <= self.max_lat
+            && self.min_lon <= other.max_lon
+            && other.min_lon <= self.max_lon
+    }
+}
+
+/// A compass direction, for [`Geohash::neighbor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// All eight directions, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The `(lat, lon)` cell offsets of the direction.
+    fn offset(self) -> (i64, i64) {
+        match self {
+            Direction::North => (1, 0),
+            Direction::NorthEast => (1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (-1, 1),
+            Direction::South => (-1, 0),
+            Direction::SouthWest => (-1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (1, -1),
+        }
+    }
+}
+
+/// A geohash cell of 1 to [`MAX_PRECISION`] characters.
+///
+/// Geohashes order like their strings, so a cell sorts directly before all
+/// of its descendants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Geohash {
+    /// The `5 * len` code bits, right-aligned.
+    bits: u64,
+    len: u8,
+}
+
+impl Geohash {
+    /// The cell of `precision` characters containing `(lat, lon)`.
+    pub fn encode(lat: f64, lon: f64, precision: u8) -> Result<Self, GeohashError> {
+        if !(1..=MAX_PRECISION).contains(&precision) {
+            return Err(GeohashError::InvalidPrecision);
+        }
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(GeohashError::InvalidCoordinate);
+        }
+        let lat = quantize((lat + 90.0) / 180.0);
+        let lon = quantize((lon + 180.0) / 360.0);
+        // Longitude takes the odd bits so that it leads from the top.
+        let code = morton::encode2(lat, lon);
+        Ok(Geohash {
+            bits: code >> (2 * AXIS_BITS - 5 * precision as u32),
+            len: precision,
+        })
+    }
+
+    /// Number of characters.
+    pub fn precision(&self) -> u8 {
+        self.len
+    }
+
+    /// The code bits, `5 * precision` of them, right-aligned.
+    pub fn bits(&self) -> u64 {
+        self.bits
+    }
+
+    /// The cell's rectangle.
+    pub fn bounds(&self) -> LatLonBox {
+        let (lat_bits, lon_bits) = self.axis_bits();
+        let (lat, lon) = self.indices();
+        let lat_step = 180.0 / (1u64 << lat_bits) as f64;
+        let lon_step = 360.0 / (1u64 << lon_bits) as f64;
+        LatLonBox {
+            min_lat: -90.0 + lat as f64 * lat_step,
+            min_lon: -180.0 + lon as f64 * lon_step,
+            max_lat: -90.0 + (lat + 1) as f64 * lat_step,
+            max_lon: -180.0 + (lon + 1) as f64 * lon_step,
+        }
+    }
+
+    /// The center of the cell as `(lat, lon)`.
+    pub fn center(&self) -> (f64, f64) {
+        self.bounds().center()
+    }
+
+    /// The enclosing cell one character shorter, if any.
+    pub fn parent(&self) -> Option<Geohash> {
+        (self.len > 1).then(|| Geohash {
+            bits: self.bits >> 5,
+            len: self.len - 1,
+        })
+    }
+
+    /// The 32 cells one character longer, in order; empty at
+    /// [`MAX_PRECISION`].
+    pub fn children(&self) -> impl Iterator<Item = Geohash> {
+        let this = *self;
+        let n = if self.len < MAX_PRECISION { 32 } else { 0 };
+        (0..n).map(move |c| Geohash {
+            bits: this.bits << 5 | c,
+            len: this.len + 1,
+        })
+    }
+
+    /// Whether `other` is this cell or one of its descendants, i.e. whether
+    /// this hash is a prefix of `other`.
+    pub fn contains(&self, other: &Geohash) -> bool {
+        other.len >= self.len && other.bits >> (5 * (other.len - self.len) as u32) == self.bits
+    }
+
+    /// The adjacent cell of the same precision in `direction`.
+    ///
+    /// Longitude wraps across the antimeridian; returns `None` past a pole.
+    pub fn neighbor(&self, direction: Direction) -> Option<Geohash> {
+        let (lat_bits, lon_bits) = self.axis_bits();
+        let (lat, lon) = self.indices();
+        let (dlat, dlon) = direction.offset();
+        let lat = lat as i64 + dlat;
+        if lat < 0 || lat >= 1i64 << lat_bits {
+            return None;
+        }
+        let lon = (lon as i64 + dlon).rem_euclid(1i64 << lon_bits);
+        Some(self.with_indices(lat as u32, lon as u32))
+    }
+
+    /// The neighbors in [`Direction::ALL`] order.
+    pub fn neighbors(&self) -> [Option<Geohash>; 8] {
+        Direction::ALL.map(|d| self.neighbor(d))
+    }
+
+    /// Bits of the code spent on latitude and on longitude.
+    fn axis_bits(&self) -> (u32, u32) {
+        let n = 5 * self.len as u32;
+        (n / 2, n - n / 2)
+    }
+
+    /// The cell's row and column on its precision's grid.
+    fn indices(&self) -> (u32, u32) {
+        let (lat_bits, lon_bits) = self.axis_bits();
+        let (lat, lon) = morton::decode2(self.bits << (2 * AXIS_BITS - 5 * self.len as u32));
+        (lat >> (AXIS_BITS - lat_bits), lon >> (AXIS_BITS - lon_bits))
+    }
+
+    fn with_indices(&self, lat: u32, lon: u32) -> Geohash {
+        let (lat_bits, lon_bits) = self.axis_bits();
+        let code = morton::encode2(lat << (AXIS_BITS - lat_bits), lon << (AXIS_BITS - lon_bits));
+        Geohash {
+            bits: code >> (2 * AXIS_BITS - 5 * self.len as u32),
+            len: self.len,
+        }
+    }
+}
+
+impl PartialOrd for Geohash {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Geohash {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let align = |g: &Geohash| g.bits << (5 * (MAX_PRECISION - g.len) as u32);
+        (align(self), self.len).cmp(&(align(other), other.len))
+    }
+}
+
+impl fmt::Display for Geohash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in (0..self.len as u32).rev() {
+            let c = ALPHABET[(self.bits >> (5 * i) & 31) as usize];
+            fmt::Write::write_char(f, c as char)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Geohash {
+    type Err = GeohashError;
+
+    /// Parses a geohash, accepting upper- or lowercase characters.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || s.len() > MAX_PRECISION as usize {
+            return Err(GeohashError::InvalidPrecision);
+        }
+        let mut bits = 0u64;
+        for c in s.bytes() {
+            let c = c.to_ascii_lowercase();
+            let v = ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(GeohashError::InvalidCharacter)?;
+            bits = bits << 5 | v as u64;
+        }
+        Ok(Geohash {
+            bits,
+            len: s.len() as u8,
+        })
+    }
+}
+
+/// Covers `region` with geohash prefixes, none longer than `precision`.
+///
+/// Cells lying entirely inside `region` are kept as short as possible, and
+/// cells on its boundary are refined down to `precision`, so the result is
+/// a small set of prefixes whose union contains `region`. The cells are
+/// disjoint and returned in order.
+///
+/// # Panics
+///
+/// Panics if `precision` is not in `1..=MAX_PRECISION`.
+pub fn covering(region: &LatLonBox, precision: u8) -> Vec<Geohash> {
+    assert!(
+        (1..=MAX_PRECISION).contains(&precision),
+        "precision must be between 1 and {MAX_PRECISION}"
+    );
+    let mut out = Vec::new();
+    let mut stack: Vec<Geohash> = (0..32).rev().map(|c| Geohash { bits: c, len: 1 }).collect();
+    while let Some(cell) = stack.pop() {
+        let bounds = cell.bounds();
+        if !region.intersects(&bounds) {
+            continue;
+        }
+        if cell.len == precision || region.contains_box(&bounds) {
+            out.push(cell);
+            continue;
+        }
+        let start = stack.len();
+        stack.extend(cell.children());
+        stack[start..].reverse();
+    }
+    out
+}
+
+/// Maps `t` in `[0, 1]` to a `AXIS_BITS`-bit cell index.
+fn quantize(t: f64) -> u32 {
+    let cells = (1u64 << AXIS_BITS) as f64;
+    ((t * cells) as u64).min((1 << AXIS_BITS) - 1) as u32
+}
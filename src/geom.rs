@@ -0,0 +1,608 @@
+//! Geometric primitives shared by the spatial structures.
+//!
+//! Every index in the crate speaks in terms of these types: [`Aabb`] for
+//! node bounds and box queries, [`Ray`] for ray casts, and [`Sphere`],
+//! [`Plane`], [`Frustum`] and [`Obb`] for the bounding volumes and query
+//! shapes the more specialized structures need. Keeping them in one place
+//! means one set of intersection and containment predicates, with one set
+//! of edge-case conventions: boxes and spheres are closed, and ray hits are
+//! reported as the parameter `t` at which the ray enters the shape, zero if
+//! it starts inside.
+//!
+//! Boxes, rays, spheres and planes work in any dimension. Frustums and
+//! oriented boxes are three-dimensional.
+
+/// Which side of a plane a shape lies on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Entirely on the side the normal points to.
+    Front,
+    /// Entirely on the other side.
+    Back,
+    /// Touching or crossing the plane.
+    Straddling,
+}
+
+/// An axis-aligned box, closed on all sides.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb<const N: usize> {
+    /// The minimum corner.
+    pub min: [f64; N],
+    /// The maximum corner.
+    pub max: [f64; N],
+}
+
+impl<const N: usize> Aabb<N> {
+    /// Creates a box from its corners.
+    pub fn new(min: [f64; N], max: [f64; N]) -> Self {
+        Aabb { min, max }
+    }
+
+    /// A degenerate box containing exactly one point.
+    pub fn point(p: [f64; N]) -> Self {
+        Aabb { min: p, max: p }
+    }
+
+    /// The box containing nothing, the identity for [`Aabb::union`].
+    pub fn empty() -> Self {
+        Aabb {
+            min: [f64::INFINITY; N],
+            max: [f64::NEG_INFINITY; N],
+        }
+    }
+
+    /// Whether the box contains no points.
+    pub fn is_empty(&self) -> bool {
+        (0..N).any(|a| self.min[a] > self.max[a])
+    }
+
+    /// The center point.
+    pub fn center(&self) -> [f64; N] {
+        std::array::from_fn(|a| 0.5 * (self.min[a] + self.max[a]))
+    }
+
+    /// The `N`-dimensional volume.
+    pub fn area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        (0..N).map(|a| self.max[a] - self.min[a]).product()
+    }
+
+    /// The sum of the edge lengths, used by R* splits as a squareness measure.
+    pub fn margin(&self) -> f64 {
+        (0..N).map(|a| (self.max[a] - self.min[a]).max(0.0)).sum()
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Aabb {
+            min: std::array::from_fn(|a| self.min[a].min(other.min[a])),
+            max: std::array::from_fn(|a| self.max[a].max(other.max[a])),
+        }
+    }
+
+    /// The overlapping part of both boxes, which may be empty.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Aabb {
+            min: std::array::from_fn(|a| self.min[a].max(other.min[a])),
+            max: std::array::from_fn(|a| self.max[a].min(other.max[a])),
+        }
+    }
+
+    /// Whether the two boxes share at least one point.
+    pub fn intersects(&self, other: &Self) -> bool {
+        (0..N).all(|a| self.min[a] <= other.max[a] && other.min[a] <= self.max[a])
+    }
+
+    /// Whether `other` lies entirely inside this box.
+    pub fn contains(&self, other: &Self) -> bool {
+        (0..N).all(|a| self.min[a] <= other.min[a] && other.max[a] <= self.max[a])
+    }
+
+    /// Whether `p` lies inside or on the boundary of the box.
+    pub fn contains_point(&self, p: &[f64; N]) -> bool {
+        (0..N).all(|a| self.min[a] <= p[a] && p[a] <= self.max[a])
+    }
+
+    /// Squared distance from `p` to the nearest point of the box.
+    pub fn distance_squared(&self, p: &[f64; N]) -> f64 {
+        (0..N)
+            .map(|a| {
+                let d = (self.min[a] - p[a]).max(p[a] - self.max[a]).max(0.0);
+                d * d
+            })
+            .sum()
+    }
+
+    /// Whether the box and `sphere` share at least one point.
+    pub fn intersects_sphere(&self, sphere: &Sphere<N>) -> bool {
+        self.distance_squared(&sphere.center) <= sphere.radius * sphere.radius
+    }
+
+    /// Which side of `plane` the box lies on.
+    pub fn side_of(&self, plane: &Plane<N>) -> Side {
+        let center = plane.height(&self.center());
+        let reach: f64 = (0..N)
+            .map(|a| 0.5 * (self.max[a] - self.min[a]) * plane.normal[a].abs())
+            .sum();
+        classify(center, reach)
+    }
+}
+
+/// A half-line `origin + t * direction` for `t >= 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray<const N: usize> {
+    /// The starting point.
+    pub origin: [f64; N],
+    /// The direction; it need not be normalized, and hit distances are
+    /// measured in multiples of it.
+    pub direction: [f64; N],
+}
+
+impl<const N: usize> Ray<N> {
+    /// Creates a ray from its origin and direction.
+    pub fn new(origin: [f64; N], direction: [f64; N]) -> Self {
+        Ray { origin, direction }
+    }
+
+    /// The point at parameter `t` along the ray.
+    pub fn at(&self, t: f64) -> [f64; N] {
+        std::array::from_fn(|a| self.origin[a] + t * self.direction[a])
+    }
+
+    /// The parameter at which the ray enters `bbox`, if it does so before
+    /// `max_t`.
+    pub fn hit_aabb(&self, bbox: &Aabb<N>, max_t: f64) -> Option<f64> {
+        slab(bbox, self, &inverse(self), max_t)
+    }
+
+    /// The parameter at which the ray enters `sphere`, if it does so before
+    /// `max_t`.
+    pub fn hit_sphere(&self, sphere: &Sphere<N>, max_t: f64) -> Option<f64> {
+        let m = sub(&self.origin, &sphere.center);
+        let a = dot(&self.direction, &self.direction);
+        let b = dot(&m, &self.direction);
+        let c = dot(&m, &m) - sphere.radius * sphere.radius;
+        if c <= 0.0 {
+            return Some(0.0);
+        }
+        let disc = b * b - a * c;
+        if a == 0.0 || b > 0.0 || disc < 0.0 {
+            return None;
+        }
+        let t = (-b - disc.sqrt()) / a;
+        (t <= max_t).then_some(t)
+    }
+
+    /// The parameter at which the ray meets `plane`, if it does so before
+    /// `max_t`. A ray lying in the plane hits it at zero.
+    pub fn hit_plane(&self, plane: &Plane<N>, max_t: f64) -> Option<f64> {
+        let dist = plane.height(&self.origin);
+        let rate = dot(&plane.normal, &self.direction);
+        if dist == 0.0 {
+            return Some(0.0);
+        }
+        if rate == 0.0 {
+            return None;
+        }
+        let t = -dist / rate;
+        (t >= 0.0 && t <= max_t).then_some(t)
+    }
+}
+
+impl Ray<3> {
+    /// The parameter at which the ray enters `obb`, if it does so before
+    /// `max_t`.
+    pub fn hit_obb(&self, obb: &Obb, max_t: f64) -> Option<f64> {
+        // Slab test in the box's own frame.
+        let local = Ray {
+            origin: obb.local(&self.origin),
+            direction: std::array::from_fn(|i| dot(&obb.axes[i], &self.direction)),
+        };
+        let half = obb.half_extents;
+        local.hit_aabb(&Aabb::new(half.map(|h| -h), half), max_t)
+    }
+}
+
+/// A closed ball.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere<const N: usize> {
+    /// The center.
+    pub center: [f64; N],
+    /// The radius.
+    pub radius: f64,
+}
+
+impl<const N: usize> Sphere<N> {
+    /// Creates a sphere from its center and radius.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radius` is negative or NaN.
+    pub fn new(center: [f64; N], radius: f64) -> Self {
+        assert!(radius >= 0.0, "radius must be non-negative");
+        Sphere { center, radius }
+    }
+
+    /// The smallest box containing the sphere.
+    pub fn bounds(&self) -> Aabb<N> {
+        Aabb::new(
+            self.center.map(|c| c - self.radius),
+            self.center.map(|c| c + self.radius),
+        )
+    }
+
+    /// Whether `p` lies inside or on the sphere.
+    pub fn contains_point(&self, p: &[f64; N]) -> bool {
+        distance_squared(&self.center, p) <= self.radius * self.radius
+    }
+
+    /// Whether the two spheres share at least one point.
+    pub fn intersects(&self, other: &Self) -> bool {
+        let r = self.radius + other.radius;
+        distance_squared(&self.center, &other.center) <= r * r
+    }
+
+    /// Whether `other` lies entirely inside this sphere.
+    pub fn contains(&self, other: &Self) -> bool {
+        let gap = self.radius - other.radius;
+        gap >= 0.0 && distance_squared(&self.center, &other.center) <= gap * gap
+    }
+
+    /// Whether the sphere and `bbox` share at least one point.
+    pub fn intersects_aabb(&self, bbox: &Aabb<N>) -> bool {
+        bbox.intersects_sphere(self)
+    }
+
+    /// Whether `bbox` lies entirely inside the sphere.
+    pub fn contains_aabb(&self, bbox: &Aabb<N>) -> bool {
+        let far: f64 = (0..N)
+            .map(|a| {
+                let d = (self.center[a] - bbox.min[a])
+                    .abs()
+                    .max((bbox.max[a] - self.center[a]).abs());
+                d * d
+            })
+            .sum();
+        far <= self.radius * self.radius
+    }
+
+    /// Which side of `plane` the sphere lies on.
+    pub fn side_of(&self, plane: &Plane<N>) -> Side {
+        let reach = self.radius * dot(&plane.normal, &plane.normal).sqrt();
+        classify(plane.height(&self.center), reach)
+    }
+}
+
+/// A hyperplane, the points `p` with `normal · p == offset`.
+///
+/// The normal need not be unit length; [`Plane::signed_distance`] accounts
+/// for its length, and [`Plane::normalized`] rescales it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane<const N: usize> {
+    /// The normal, pointing to the front side.
+    pub normal: [f64; N],
+    /// The plane's offset along the normal.
+    pub offset: f64,
+}
+
+impl<const N: usize> Plane<N> {
+    /// Creates a plane from its normal and offset.
+    pub fn new(normal: [f64; N], offset: f64) -> Self {
+        Plane { normal, offset }
+    }
+
+    /// The plane through `point` with the given normal.
+    pub fn from_point_normal(point: &[f64; N], normal: [f64; N]) -> Self {
+        Plane {
+            normal,
+            offset: dot(&normal, point),
+        }
+    }
+
+    /// The same plane with a unit normal.
+    pub fn normalized(&self) -> Self {
+        let len = dot(&self.normal, &self.normal).sqrt();
+        Plane {
+            normal: self.normal.map(|n| n / len),
+            offset: self.offset / len,
+        }
+    }
+
+    /// The distance from the plane to `p`, positive on the front side.
+    pub fn signed_distance(&self, p: &[f64; N]) -> f64 {
+        self.height(p) / dot(&self.normal, &self.normal).sqrt()
+    }
+
+    /// Which side of the plane `p` lies on.
+    pub fn side_of_point(&self, p: &[f64; N]) -> Side {
+        classify(self.height(p), 0.0)
+    }
+
+    /// `normal · p - offset`: the signed distance scaled by the normal's
+    /// length.
+    fn height(&self, p: &[f64; N]) -> f64 {
+        dot(&self.normal, p) - self.offset
+    }
+}
+
+/// A convex region bounded by six planes, such as a camera's view volume.
+///
+/// The planes' normals point inward, so the frustum is the intersection of
+/// their front sides. The culling tests are conservative in the usual way:
+/// a box that misses the frustum only near one of its corners may still be
+/// reported as intersecting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// The bounding planes: left, right, bottom, top, near and far.
+    pub planes: [Plane<3>; 6],
+}
+
+impl Frustum {
+    /// Creates a frustum from inward-facing planes.
+    pub fn new(planes: [Plane<3>; 6]) -> Self {
+        Frustum { planes }
+    }
+
+    /// Extracts the frustum of a row-major view-projection matrix that maps
+    /// the view volume to the clip cube `-w <= x, y, z <= w`, by Gribb and
+    /// Hartmann's method.
+    pub fn from_matrix(m: &[[f64; 4]; 4]) -> Self {
+        let plane = |row: usize, sign: f64| {
+            let c: [f64; 4] = std::array::from_fn(|j| m[3][j] + sign * m[row][j]);
+            Plane::new([c[0], c[1], c[2]], -c[3]).normalized()
+        };
+        Frustum {
+            planes: [
+                plane(0, 1.0),
+                plane(0, -1.0),
+                plane(1, 1.0),
+                plane(1, -1.0),
+                plane(2, 1.0),
+                plane(2, -1.0),
+            ],
+        }
+    }
+
+    /// Whether `p` lies inside or on the frustum.
+    pub fn contains_point(&self, p: &[f64; 3]) -> bool {
+        self.planes.iter().all(|pl| pl.height(p) >= 0.0)
+    }
+
+    /// Whether `bbox` may meet the frustum: false only if it lies wholly
+    /// behind one plane.
+    pub fn intersects_aabb(&self, bbox: &Aabb<3>) -> bool {
+        self.planes.iter().all(|pl| bbox.side_of(pl) != Side::Back)
+    }
+
+    /// Whether `bbox` lies entirely inside the frustum, clear of its planes:
+    /// a box touching a plane from inside straddles it and is not counted.
+    pub fn contains_aabb(&self, bbox: &Aabb<3>) -> bool {
+        self.planes.iter().all(|pl| bbox.side_of(pl) == Side::Front)
+    }
+
+    /// Whether `sphere` may meet the frustum: false only if it lies wholly
+    /// behind one plane.
+    pub fn intersects_sphere(&self, sphere: &Sphere<3>) -> bool {
+        self.planes
+            .iter()
+            .all(|pl| sphere.side_of(pl) != Side::Back)
+    }
+
+    /// Whether `sphere` lies entirely inside the frustum, clear of its
+    /// planes.
+    pub fn contains_sphere(&self, sphere: &Sphere<3>) -> bool {
+        self.planes
+            .iter()
+            .all(|pl| sphere.side_of(pl) == Side::Front)
+    }
+}
+
+/// An oriented bounding box in three dimensions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    /// The center.
+    pub center: [f64; 3],
+    /// The box's local axes, which must be orthonormal.
+    pub axes: [[f64; 3]; 3],
+    /// Half the box's extent along each local axis.
+    pub half_extents: [f64; 3],
+}
+
+impl Obb {
+    /// Creates a box from its center, orthonormal axes and half extents.
+    pub fn new(center: [f64; 3], axes: [[f64; 3]; 3], half_extents: [f64; 3]) -> Self {
+        Obb {
+            center,
+            axes,
+            half_extents,
+        }
+    }
+
+    /// The axis-aligned box as an oriented one.
+    pub fn from_aabb(bbox: &Aabb<3>) -> Self {
+        Obb {
+            center: bbox.center(),
+            axes: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            half_extents: std::array::from_fn(|a| 0.5 * (bbox.max[a] - bbox.min[a])),
+        }
+    }
+
+    /// The eight corners.
+    pub fn corners(&self) -> [[f64; 3]; 8] {
+        std::array::from_fn(|i| {
+            std::array::from_fn(|a| {
+                (0..3).fold(self.center[a], |acc, j| {
+                    let sign = if i >> j & 1 == 1 { 1.0 } else { -1.0 };
+                    acc + sign * self.half_extents[j] * self.axes[j][a]
+                })
+            })
+        })
+    }
+
+    /// The smallest axis-aligned box containing this one.
+    pub fn bounds(&self) -> Aabb<3> {
+        let reach: [f64; 3] = std::array::from_fn(|a| {
+            (0..3)
+                .map(|j| self.half_extents[j] * self.axes[j][a].abs())
+                .sum()
+        });
+        Aabb::new(
+            std::array::from_fn(|a| self.center[a] - reach[a]),
+            std::array::from_fn(|a| self.center[a] + reach[a]),
+        )
+    }
+
+    /// Whether `p` lies inside or on the box.
+    pub fn contains_point(&self, p: &[f64; 3]) -> bool {
+        let local = self.local(p);
+        (0..3).all(|j| local[j].abs() <= self.half_extents[j])
+    }
+
+    /// The point of the box nearest to `p`.
+    pub fn closest_point(&self, p: &[f64; 3]) -> [f64; 3] {
+        let local = self.local(p);
+        std::array::from_fn(|a| {
+            (0..3).fold(self.center[a], |acc, j| {
+                let h = self.half_extents[j];
+                acc + local[j].clamp(-h, h) * self.axes[j][a]
+            })
+        })
+    }
+
+    /// Squared distance from `p` to the nearest point of the box.
+    pub fn distance_squared(&self, p: &[f64; 3]) -> f64 {
+        let local = self.local(p);
+        (0..3)
+            .map(|j| {
+                let d = (local[j].abs() - self.half_extents[j]).max(0.0);
+                d * d
+            })
+            .sum()
+    }
+
+    /// Whether the two boxes share at least one point, by the separating
+    /// axis test over the fifteen candidate axes.
+    pub fn intersects(&self, other: &Obb) -> bool {
+        // Rotation and translation expressing `other` in this box's frame.
+        let r: [[f64; 3]; 3] =
+            std::array::from_fn(|i| std::array::from_fn(|j| dot(&self.axes[i], &other.axes[j])));
+        let t = self.local(&other.center);
+        // Padding keeps near-parallel edge pairs, whose cross products are
+        // nearly zero, from reporting a spurious separation.
+        let abs_r: [[f64; 3]; 3] =
+            std::array::from_fn(|i| std::array::from_fn(|j| r[i][j].abs() + 1e-12));
+        let (a, b) = (&self.half_extents, &other.half_extents);
+        for i in 0..3 {
+            let rb = b[0] * abs_r[i][0] + b[1] * abs_r[i][1] + b[2] * abs_r[i][2];
+            if t[i].abs() > a[i] + rb {
+                return false;
+            }
+        }
+        for j in 0..3 {
+            let ra = a[0] * abs_r[0][j] + a[1] * abs_r[1][j] + a[2] * abs_r[2][j];
+            let dist = t[0] * r[0][j] + t[1] * r[1][j] + t[2] * r[2][j];
+            if dist.abs() > ra + b[j] {
+                return false;
+            }
+        }
+        for i in 0..3 {
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            for j in 0..3 {
+                let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+                let ra = a[i1] * abs_r[i2][j] + a[i2] * abs_r[i1][j];
+                let rb = b[j1] * abs_r[i][j2] + b[j2] * abs_r[i][j1];
+                let dist = t[i2] * r[i1][j] - t[i1] * r[i2][j];
+                if dist.abs() > ra + rb {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Whether the box and `bbox` share at least one point.
+    pub fn intersects_aabb(&self, bbox: &Aabb<3>) -> bool {
+        self.intersects(&Obb::from_aabb(bbox))
+    }
+
+    /// Whether the box and `sphere` share at least one point.
+    pub fn intersects_sphere(&self, sphere: &Sphere<3>) -> bool {
+        self.distance_squared(&sphere.center) <= sphere.radius * sphere.radius
+    }
+
+    /// Which side of `plane` the box lies on.
+    pub fn side_of(&self, plane: &Plane<3>) -> Side {
+        let reach: f64 = (0..3)
+            .map(|j| self.half_extents[j] * dot(&plane.normal, &self.axes[j]).abs())
+            .sum();
+        classify(plane.height(&self.center), reach)
+    }
+
+    /// `p` in the box's frame, relative to its center.
+    fn local(&self, p: &[f64; 3]) -> [f64; 3] {
+        let d = sub(p, &self.center);
+        std::array::from_fn(|j| dot(&self.axes[j], &d))
+    }
+}
+
+/// Componentwise reciprocal of the ray direction, hoisted out of slab tests.
+pub(crate) fn inverse<const N: usize>(ray: &Ray<N>) -> [f64; N] {
+    ray.direction.map(|d| 1.0 / d)
+}
+
+/// Slab test: the parameter at which `ray` enters `bbox`, if it does so
+/// before `max_t`.
+#[allow(clippy::needless_range_loop)]
+pub(crate) fn slab<const N: usize>(
+    bbox: &Aabb<N>,
+    ray: &Ray<N>,
+    inv: &[f64; N],
+    max_t: f64,
+) -> Option<f64> {
+    let (mut t0, mut t1) = (0.0f64, max_t);
+    for a in 0..N {
+        // A ray parallel to the slab is inside it everywhere or nowhere.
+        // Multiplying by the infinite inverse would give NaN for an origin
+        // on the slab's boundary.
+        if ray.direction[a] == 0.0 {
+            if ray.origin[a] < bbox.min[a] || bbox.max[a] < ray.origin[a] {
+                return None;
+            }
+            continue;
+        }
+        let near = (bbox.min[a] - ray.origin[a]) * inv[a];
+        let far = (bbox.max[a] - ray.origin[a]) * inv[a];
+        t0 = t0.max(near.min(far));
+        t1 = t1.min(near.max(far));
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some(t0)
+}
+
+/// Sorts a shape whose center sits at `height` above a plane and which
+/// reaches `reach` either side of it along the normal.
+fn classify(height: f64, reach: f64) -> Side {
+    if height > reach {
+        Side::Front
+    } else if height < -reach {
+        Side::Back
+    } else {
+        Side::Straddling
+    }
+}
+
+fn dot<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn sub<const N: usize>(a: &[f64; N], b: &[f64; N]) -> [f64; N] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
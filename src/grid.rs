@@ -0,0 +1,352 @@
+//! A uniform spatial hash grid.
+//!
+//! Space is cut into cubic cells of one fixed size and each point is filed
+//! under the integer coordinates of its cell in a hash map, so the grid is
+//! unbounded and only occupied cells cost memory. Insertion, removal and
+//! relocation are `O(1)`; a neighborhood query visits just the cells the
+//! query overlaps. For dense, roughly uniform, constantly moving points this
+//! usually beats a tree, and it's never hard to reason about.
+
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::geom::Aabb;
+use crate::util::Total;
+
+/// Handle to a point stored in a [`SpatialHashGrid`].
+///
+/// Handles of removed points may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemId(u32);
+
+#[derive(Clone, Debug)]
+struct Entry<T, const N: usize> {
+    point: [f64; N],
+    data: T,
+    cell: [i64; N],
+    /// Position within the cell's item list.
+    slot: u32,
+}
+
+/// A hash grid mapping points in `N` dimensions to data of type `T`.
+#[derive(Clone, Debug)]
+pub struct SpatialHashGrid<T, const N: usize> {
+    cell_size: f64,
+    cells: HashMap<[i64; N], Vec<u32>>,
+    entries: Vec<Option<Entry<T, N>>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<T, const N: usize> SpatialHashGrid<T, N> {
+    /// Creates an empty grid of cubic cells with edge length `cell_size`.
+    ///
+    /// Queries are fastest when the cell size is close to the typical query
+    /// radius.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `cell_size` is positive and finite.
+    pub fn new(cell_size: f64) -> Self {
+        assert!(
+            cell_size > 0.0 && cell_size.is_finite(),
+            "cell size must be positive and finite"
+        );
+        SpatialHashGrid {
+            cell_size,
+            cells: HashMap::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// The edge length of each cell.
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the grid holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of occupied cells.
+    pub fn occupied_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Removes every point, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entries.clear();
+        self.free.clear();
+        self.len = 0;
+    }
+
+    /// The cell containing `p`.
+    pub fn cell_of(&self, p: &[f64; N]) -> [i64; N] {
+        p.map(|c| (c / self.cell_size).floor() as i64)
+    }
+
+    /// Inserts a point, returning its handle.
+    pub fn insert(&mut self, point: [f64; N], data: T) -> ItemId {
+        let cell = self.cell_of(&point);
+        let entry = Entry {
+            point,
+            data,
+            cell,
+            slot: 0,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.link(id, cell);
+        self.len += 1;
+        ItemId(id)
+    }
+
+    /// Removes a point, returning its position and data.
+    pub fn remove(&mut self, id: ItemId) -> Option<([f64; N], T)> {
+        self.entries.get(id.0 as usize)?.as_ref()?;
+        self.unlink(id.0);
+        let entry = self.entries[id.0 as usize].take()?;
+        self.free.push(id.0);
+        self.len -= 1;
+        Some((entry.point, entry.data))
+    }
+
+    /// Moves a point, returning its previous position, or `None` if `id` is
+    /// not in the grid.
+    pub fn relocate(&mut self, id: ItemId, to: [f64; N]) -> Option<[f64; N]> {
+        let cell = self.cell_of(&to);
+        let entry = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        let old = std::mem::replace(&mut entry.point, to);
+        if entry.cell != cell {
+            self.unlink(id.0);
+            self.link(id.0, cell);
+        }
+        Some(old)
+    }
+
+    /// Returns the position and data of a point.
+    pub fn get(&self, id: ItemId) -> Option<(&[f64; N], &T)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((&e.point, &e.data))
+    }
+
+    /// Returns the position and mutable data of a point.
+    pub fn get_mut(&mut self, id: ItemId) -> Option<(&[f64; N], &mut T)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&e.point, &mut e.data))
+    }
+
+    /// Iterates over every stored point in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (ItemId, &[f64; N], &T)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.as_ref().map(|e| (ItemId(i as u32), &e.point, &e.data)))
+    }
+
+    /// Iterates over the points stored in one cell.
+    pub fn cell_items(&self, cell: [i64; N]) -> impl Iterator<Item = (ItemId, &[f64; N], &T)> + '_ {
+        self.cells.get(&cell).into_iter().flatten().map(move |&i| {
+            let e = self.entry(i);
+            (ItemId(i), &e.point, &e.data)
+        })
+    }
+
+    /// Iterates over the points inside `query`.
+    pub fn query_aabb(&self, query: Aabb<N>) -> impl Iterator<Item = (ItemId, &[f64; N], &T)> + '_ {
+        self.scan(self.cell_of(&query.min), self.cell_of(&query.max))
+            .filter(move |(_, p, _)| query.contains_point(p))
+    }
+
+    /// Iterates over the points within `radius` of `center`.
+    pub fn within_radius(
+        &self,
+        center: [f64; N],
+        radius: f64,
+    ) -> impl Iterator<Item = (ItemId, &[f64; N], &T)> + '_ {
+        let r2 = radius * radius;
+        let lo = self.cell_of(&center.map(|c| c - radius));
+        let hi = self.cell_of(&center.map(|c| c + radius));
+        self.scan(lo, hi)
+            .filter(move |(_, p, _)| distance_squared(p, &center) <= r2)
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// Euclidean distances.
+    ///
+    /// Searches shells of cells outward from the query's cell, so the cost
+    /// grows with the distance to the `k`-th neighbor in cells.
+    pub fn nearest(&self, query: &[f64; N], k: usize) -> Vec<(ItemId, f64)> {
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let center = self.cell_of(query);
+        // Every point lies within this many shells of the query cell.
+        let max_ring = self
+            .cells
+            .keys()
+            .map(|c| (0..N).map(|a| c[a].abs_diff(center[a])).max().unwrap_or(0))
+            .max()
+            .unwrap_or(0);
+        for ring in 0..=max_ring {
+            // Points in shell `ring` are at least `ring - 1` cells away.
+            let reach = (ring.saturating_sub(1)) as f64 * self.cell_size;
+            if best.len() == k && reach * reach > best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                break;
+            }
+            self.for_each_in_shell(center, ring, |i| {
+                let d2 = distance_squared(&self.entry(i).point, query);
+                if best.len() < k {
+                    best.push((Total(d2), i));
+                } else if d2 < best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                    best.pop();
+                    best.push((Total(d2), i));
+                }
+            });
+        }
+        let mut out: Vec<_> = best
+            .into_iter()
+            .map(|(d2, i)| (ItemId(i), d2.0.sqrt()))
+            .collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Calls `f` for every item in cells at Chebyshev distance exactly
+    /// `ring` from `center`.
+    fn for_each_in_shell(&self, center: [i64; N], ring: u64, mut f: impl FnMut(u32)) {
+        let r = ring as i64;
+        let lo = center.map(|c| c - r);
+        let hi = center.map(|c| c + r);
+        // With few occupied cells it's cheaper to filter them than to probe
+        // every cell of the shell.
+        let shell_cells = (2 * ring + 1).checked_pow(N as u32).unwrap_or(u64::MAX);
+        if shell_cells > self.cells.len() as u64 {
+            for (cell, items) in &self.cells {
+                let d = (0..N)
+                    .map(|a| cell[a].abs_diff(center[a]))
+                    .max()
+                    .unwrap_or(0);
+                if d == ring {
+                    items.iter().for_each(|&i| f(i));
+                }
+            }
+            return;
+        }
+        for_each_cell(lo, hi, |cell| {
+            let on_shell = (0..N).any(|a| cell[a] == lo[a] || cell[a] == hi[a]) || ring == 0;
+            if on_shell {
+                if let Some(items) = self.cells.get(&cell) {
+                    items.iter().for_each(|&i| f(i));
+                }
+            }
+        });
+    }
+
+    /// Iterates over every point in the cells from `lo` to `hi` inclusive.
+    fn scan(
+        &self,
+        lo: [i64; N],
+        hi: [i64; N],
+    ) -> impl Iterator<Item = (ItemId, &[f64; N], &T)> + '_ {
+        let span = (0..N)
+            .map(|a| (hi[a] as i128 - lo[a] as i128 + 1).max(0) as u128)
+            .fold(1u128, u128::saturating_mul);
+        let mut cells = Vec::new();
+        if span > self.cells.len() as u128 {
+            cells.extend(
+                self.cells
+                    .iter()
+                    .filter(|(c, _)| (0..N).all(|a| lo[a] <= c[a] && c[a] <= hi[a]))
+                    .map(|(_, items)| items.as_slice()),
+            );
+        } else {
+            for_each_cell(lo, hi, |cell| {
+                if let Some(items) = self.cells.get(&cell) {
+                    cells.push(items.as_slice());
+                }
+            });
+        }
+        cells.into_iter().flatten().map(move |&i| {
+            let e = self.entry(i);
+            (ItemId(i), &e.point, &e.data)
+        })
+    }
+
+    fn entry(&self, i: u32) -> &Entry<T, N> {
+        self.entries[i as usize].as_ref().expect("live entry")
+    }
+
+    fn link(&mut self, id: u32, cell: [i64; N]) {
+        let items = self.cells.entry(cell).or_default();
+        items.push(id);
+        let entry = self.entries[id as usize].as_mut().expect("live entry");
+        entry.cell = cell;
+        entry.slot = (items.len() - 1) as u32;
+    }
+
+    fn unlink(&mut self, id: u32) {
+        let (cell, slot) = {
+            let e = self.entry(id);
+            (e.cell, e.slot)
+        };
+        let items = self.cells.get_mut(&cell).expect("entry's cell exists");
+        items.swap_remove(slot as usize);
+        if let Some(&moved) = items.get(slot as usize) {
+            self.entries[moved as usize]
+                .as_mut()
+                .expect("live entry")
+                .slot = slot;
+        } else if items.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+}
+
+/// Calls `f` for every cell in the box from `lo` to `hi` inclusive.
+pub(crate) fn for_each_cell<const N: usize>(
+    lo: [i64; N],
+    hi: [i64; N],
+    mut f: impl FnMut([i64; N]),
+) {
+    if (0..N).any(|a| lo[a] > hi[a]) {
+        return;
+    }
+    let mut cell = lo;
+    loop {
+        f(cell);
+        let mut a = 0;
+        loop {
+            if a == N {
+                return;
+            }
+            if cell[a] < hi[a] {
+                cell[a] += 1;
+                break;
+            }
+            cell[a] = lo[a];
+            a += 1;
+        }
+    }
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
@@ -0,0 +1,528 @@
+//! A persistent hash map stored as a hash array mapped trie.
+//!
+//! A hash array mapped trie (Bagwell, 2001) indexes a trie by the bits of
+//! each key's hash, five at a time, so a branch has up to 32 children and
+//! a map of a million keys is about four levels deep. A branch stores only
+//! the children that exist, packed in order, plus a 32-bit map of which
+//! ones they are: the child for a 5-bit fragment `f` sits at the number of
+//! set bits below bit `f`. That keeps sparse branches small without giving
+//! up constant-time child lookup. Keys whose full 64-bit hashes are equal
+//! share a collision node, searched linearly.
+//!
+//! The map is persistent: nodes are shared between clones through `Arc`,
+//! so a clone is `O(1)`, and a write copies only the path from the root to
+//! its key, leaving every other version intact. A node that only one map
+//! holds is updated in place instead of copied, so a map that is not
+//! shared costs little more than a mutable one. Removals fold a branch
+//! left holding a single key back into its parent, so the trie never
+//! keeps chains of one-child branches.
+//!
+//! A [`Transient`] makes a batch of writes explicit: it cannot be cloned,
+//! so every node it copies stays its own, and after the first write along
+//! a path the rest happen in place. [`persistent`](Transient::persistent)
+//! turns it back into a map in `O(1)`.
+//!
+//! Keys are hashed with the crate's [`StableHasher`], so a map's layout,
+//! and its iteration order, are the same from run to run.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+use std::ops::Index;
+use std::sync::Arc;
+
+use crate::probabilistic::{stable_hash, DEFAULT_SEED};
+
+/// Hash bits consumed per level.
+const BITS: u32 = 5;
+const MASK: u64 = (1 << BITS) - 1;
+
+#[derive(Clone, Debug)]
+enum Entry<K, V> {
+    Leaf { hash: u64, key: K, value: V },
+    Node(Arc<Node<K, V>>),
+}
+
+#[derive(Clone, Debug)]
+enum Node<K, V> {
+    Branch {
+        /// Which of the 32 fragments have a child.
+        bitmap: u32,
+        /// The children, in fragment order.
+        children: Vec<Entry<K, V>>,
+    },
+    /// Keys with one full hash.
+    Collision { hash: u64, entries: Vec<(K, V)> },
+}
+
+impl<K, V> Node<K, V> {
+    fn empty() -> Self {
+        Node::Branch {
+            bitmap: 0,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// The bit for the fragment of `hash` at `shift`.
+fn bit(hash: u64, shift: u32) -> u32 {
+    1 << ((hash >> shift) & MASK)
+}
+
+/// A persistent map from `K` to `V`.
+#[derive(Clone)]
+pub struct HamtMap<K, V> {
+    root: Arc<Node<K, V>>,
+    len: usize,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for HamtMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> HamtMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        HamtMap {
+            root: Arc::new(Node::empty()),
+            len: 0,
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = stable_hash(DEFAULT_SEED, key);
+        let (mut node, mut shift) = (&*self.root, 0);
+        loop {
+            match node {
+                Node::Branch { bitmap, children } => {
+                    let bit = bit(hash, shift);
+                    if bitmap & bit == 0 {
+                        return None;
+                    }
+                    match &children[(bitmap & (bit - 1)).count_ones() as usize] {
+                        Entry::Leaf {
+                            hash: h,
+                            key: k,
+                            value,
+                        } => {
+                            return (*h == hash && k.borrow() == key).then_some(value);
+                        }
+                        Entry::Node(child) => {
+                            node = child;
+                            shift += BITS;
+                        }
+                    }
+                }
+                Node::Collision { hash: h, entries } => {
+                    if *h != hash {
+                        return None;
+                    }
+                    return entries
+                        .iter()
+                        .find(|(k, _)| k.borrow() == key)
+                        .map(|(_, v)| v);
+                }
+            }
+        }
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts `value` under `key`, returning the value previously stored
+    /// under it. Copies the nodes on the key's path that are shared with
+    /// other maps.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let hash = stable_hash(DEFAULT_SEED, &key);
+        let old = insert(&mut self.root, 0, hash, key, value);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Removes the entry stored under `key` and returns its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // Check first, so a miss copies nothing.
+        if !self.contains_key(key) {
+            return None;
+        }
+        let hash = stable_hash(DEFAULT_SEED, key);
+        let value = remove(&mut self.root, 0, hash, key);
+        self.len -= 1;
+        value
+    }
+
+    /// A copy of the map with `value` inserted under `key`, sharing all
+    /// but one path with this one.
+    pub fn inserted(&self, key: K, value: V) -> Self {
+        let mut map = self.clone();
+        map.insert(key, value);
+        map
+    }
+
+    /// A copy of the map without `key`.
+    pub fn removed<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut map = self.clone();
+        map.remove(key);
+        map
+    }
+
+    /// Iterates over the entries, in hash order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            stack: vec![self.root.children()],
+            collision: [].iter(),
+            remaining: self.len,
+        }
+    }
+
+    /// Iterates over the keys, in hash order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Iterates over the values, in the hash order of their keys.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Converts the map into a [`Transient`] for a batch of writes.
+    pub fn transient(self) -> Transient<K, V> {
+        Transient { map: self }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn children(&self) -> &[Entry<K, V>] {
+        match self {
+            Node::Branch { children, .. } => children,
+            Node::Collision { .. } => &[],
+        }
+    }
+}
+
+fn insert<K: Eq + Clone, V: Clone>(
+    node: &mut Arc<Node<K, V>>,
+    shift: u32,
+    hash: u64,
+    key: K,
+    value: V,
+) -> Option<V> {
+    // A key that reaches a collision node without its hash pushes the
+    // node down one level, under a branch with room for both.
+    if let Node::Collision { hash: h, .. } = **node {
+        if h != hash {
+            let collision = Entry::Node(Arc::clone(node));
+            *node = Arc::new(Node::Branch {
+                bitmap: bit(h, shift),
+                children: vec![collision],
+            });
+        }
+    }
+    match Arc::make_mut(node) {
+        Node::Branch { bitmap, children } => {
+            let bit = bit(hash, shift);
+            let index = (*bitmap & (bit - 1)).count_ones() as usize;
+            if *bitmap & bit == 0 {
+                *bitmap |= bit;
+                children.insert(index, Entry::Leaf { hash, key, value });
+                return None;
+            }
+            match &mut children[index] {
+                Entry::Node(child) => insert(child, shift + BITS, hash, key, value),
+                Entry::Leaf {
+                    hash: h,
+                    key: k,
+                    value: v,
+                } if *h == hash && *k == key => Some(mem::replace(v, value)),
+                slot => {
+                    let Entry::Leaf {
+                        hash: h,
+                        key: k,
+                        value: v,
+                    } = mem::replace(slot, Entry::Node(Arc::new(Node::empty())))
+                    else {
+                        unreachable!("matched a leaf")
+                    };
+                    *slot =
+                        Entry::Node(Arc::new(pair(shift + BITS, (h, k, v), (hash, key, value))));
+                    None
+                }
+            }
+        }
+        Node::Collision { entries, .. } => {
+            if let Some((_, v)) = entries.iter_mut().find(|(k, _)| *k == key) {
+                return Some(mem::replace(v, value));
+            }
+            entries.push((key, value));
+            None
+        }
+    }
+}
+
+/// A node at `shift` holding two distinct keys.
+fn pair<K, V>(shift: u32, a: (u64, K, V), b: (u64, K, V)) -> Node<K, V> {
+    if a.0 == b.0 {
+        return Node::Collision {
+            hash: a.0,
+            entries: vec![(a.1, a.2), (b.1, b.2)],
+        };
+    }
+    let (bit_a, bit_b) = (bit(a.0, shift), bit(b.0, shift));
+    if bit_a == bit_b {
+        return Node::Branch {
+            bitmap: bit_a,
+            children: vec![Entry::Node(Arc::new(pair(shift + BITS, a, b)))],
+        };
+    }
+    let leaf = |(hash, key, value)| Entry::Leaf { hash, key, value };
+    let children = if bit_a < bit_b {
+        vec![leaf(a), leaf(b)]
+    } else {
+        vec![leaf(b), leaf(a)]
+    };
+    Node::Branch {
+        bitmap: bit_a | bit_b,
+        children,
+    }
+}
+
+/// Removes `key`, which must be present below `node`.
+fn remove<K, V, Q>(node: &mut Arc<Node<K, V>>, shift: u32, hash: u64, key: &Q) -> Option<V>
+where
+    K: Borrow<Q> + Clone,
+    V: Clone,
+    Q: Eq + ?Sized,
+{
+    match Arc::make_mut(node) {
+        Node::Branch { bitmap, children } => {
+            let bit = bit(hash, shift);
+            let index = (*bitmap & (bit - 1)).count_ones() as usize;
+            let Entry::Node(child) = &mut children[index] else {
+                *bitmap &= !bit;
+                let Entry::Leaf { value, .. } = children.remove(index) else {
+                    unreachable!("matched a leaf")
+                };
+                return Some(value);
+            };
+            let value = remove(child, shift + BITS, hash, key);
+            // Fold a child left with one key back into this branch.
+            if let Some(entry) = lone_entry(child) {
+                children[index] = entry;
+            }
+            value
+        }
+        Node::Collision { entries, .. } => {
+            let i = entries.iter().position(|(k, _)| k.borrow() == key)?;
+            Some(entries.swap_remove(i).1)
+        }
+    }
+}
+
+/// The single key `node` holds as a leaf, if it holds only one.
+fn lone_entry<K: Clone, V: Clone>(node: &Arc<Node<K, V>>) -> Option<Entry<K, V>> {
+    match &**node {
+        Node::Branch { children, .. } => match children.as_slice() {
+            [leaf @ Entry::Leaf { .. }] => Some(leaf.clone()),
+            _ => None,
+        },
+        Node::Collision { hash, entries } => match entries.as_slice() {
+            [(key, value)] => Some(Entry::Leaf {
+                hash: *hash,
+                key: key.clone(),
+                value: value.clone(),
+            }),
+            _ => None,
+        },
+    }
+}
+
+impl<K, Q, V> Index<&Q> for HamtMap<K, V>
+where
+    K: Hash + Eq + Clone + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    V: Clone,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry for key")
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone + PartialEq> PartialEq for HamtMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone + Eq> Eq for HamtMap<K, V> {}
+
+impl<K: Hash + Eq + Clone + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for HamtMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Extend<(K, V)> for HamtMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> FromIterator<(K, V)> for HamtMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K: Hash + Eq + Clone, V: Clone> IntoIterator for &'a HamtMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of a [`HamtMap`].
+pub struct Iter<'a, K, V> {
+    /// Entries still to visit at each level.
+    stack: Vec<&'a [Entry<K, V>]>,
+    collision: std::slice::Iter<'a, (K, V)>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((k, v)) = self.collision.next() {
+                self.remaining -= 1;
+                return Some((k, v));
+            }
+            let entries = self.stack.last_mut()?;
+            let Some((entry, rest)) = entries.split_first() else {
+                self.stack.pop();
+                continue;
+            };
+            *entries = rest;
+            match entry {
+                Entry::Leaf { key, value, .. } => {
+                    self.remaining -= 1;
+                    return Some((key, value));
+                }
+                Entry::Node(node) => match &**node {
+                    Node::Branch { children, .. } => self.stack.push(children),
+                    Node::Collision { entries, .. } => self.collision = entries.iter(),
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+/// A [`HamtMap`] being changed by a batch of writes.
+///
+/// A transient cannot be cloned, so the nodes it copies are never shared
+/// and every write after the first along a path happens in place.
+pub struct Transient<K, V> {
+    map: HamtMap<K, V>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Transient<K, V> {
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Inserts `value` under `key`, returning the value previously stored
+    /// under it.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    /// Removes the entry stored under `key` and returns its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(key)
+    }
+
+    /// Finishes the batch, returning the persistent map.
+    pub fn persistent(self) -> HamtMap<K, V> {
+        self.map
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Extend<(K, V)> for Transient<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.map.extend(iter);
+    }
+}
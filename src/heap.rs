@@ -0,0 +1,220 @@
+//! A common interface over the addressable, meldable heaps.
+//!
+//! [`MeldableHeap`] covers what graph algorithms ask of a priority queue
+//! beyond [`BinaryHeap`](std::collections::BinaryHeap): a handle for every
+//! pushed item, decrease-key through that handle, and melding two heaps
+//! into one. It is implemented by [`PairingHeap`], [`FibonacciHeap`],
+//! [`LeftistHeap`] and [`SkewHeap`], so code written against the trait
+//! can switch between them. The Fibonacci heap has the better amortized
+//! bounds, `O(1)` decrease-key, while the pairing heap usually wins on
+//! real inputs through smaller nodes and less pointer chasing. Melding
+//! either links the roots in `O(1)` and moves the smaller heap's nodes
+//! into the larger heap's arena, so a node moves at most `log2 n` times
+//! over any run of melds.
+//! The leftist and skew heaps do everything through an `O(log n)` meld of
+//! two right spines, which makes them the simplest of the four; the
+//! `heaps` benchmark measures them all.
+//!
+//! All four heaps put the smallest item first.
+//!
+//! [`PairingHeap`]: crate::pairing_heap::PairingHeap
+//! [`FibonacciHeap`]: crate::fibonacci_heap::FibonacciHeap
+//! [`LeftistHeap`]: crate::leftist_heap::LeftistHeap
+//! [`SkewHeap`]: crate::leftist_heap::SkewHeap
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::fibonacci_heap::{self, FibonacciHeap};
+use crate::leftist_heap::{self, LeftistHeap, SkewHeap};
+use crate::pairing_heap::{self, PairingHeap};
+
+/// A min-heap of items of type `T` with handles, decrease-key and meld.
+pub trait MeldableHeap<T: Ord>: Default {
+    /// Handle to a pushed item, valid until the item is removed, across
+    /// melds. A stale handle never reaches another item, even one pushed
+    /// where the removed item was.
+    type Handle: Copy + Eq + Hash + Debug;
+
+    /// Number of items.
+    fn len(&self) -> usize;
+
+    /// Whether the heap is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds an item, returning its handle.
+    fn push(&mut self, item: T) -> Self::Handle;
+
+    /// The smallest item.
+    fn peek(&self) -> Option<&T>;
+
+    /// Removes and returns the smallest item.
+    fn pop(&mut self) -> Option<T>;
+
+    /// The item behind `handle`, or `None` if it has been removed.
+    fn get(&self, handle: Self::Handle) -> Option<&T>;
+
+    /// Replaces the item behind `handle` with a smaller or equal one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed or `item` is greater than it.
+    fn decrease_key(&mut self, handle: Self::Handle, item: T);
+
+    /// Removes and returns the item behind `handle`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed.
+    fn remove(&mut self, handle: Self::Handle) -> T;
+
+    /// Moves every item of `other` into this heap. Handles into both stay
+    /// valid.
+    fn meld(&mut self, other: Self);
+}
+
+impl<T: Ord> MeldableHeap<T> for PairingHeap<T> {
+    type Handle = pairing_heap::Handle;
+
+    fn len(&self) -> usize {
+        PairingHeap::len(self)
+    }
+
+    fn push(&mut self, item: T) -> pairing_heap::Handle {
+        PairingHeap::push(self, item)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        PairingHeap::peek(self)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        PairingHeap::pop(self)
+    }
+
+    fn get(&self, handle: pairing_heap::Handle) -> Option<&T> {
+        PairingHeap::get(self, handle)
+    }
+
+    fn decrease_key(&mut self, handle: pairing_heap::Handle, item: T) {
+        PairingHeap::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: pairing_heap::Handle) -> T {
+        PairingHeap::remove(self, handle)
+    }
+
+    fn meld(&mut self, other: Self) {
+        PairingHeap::meld(self, other)
+    }
+}
+
+impl<T: Ord> MeldableHeap<T> for FibonacciHeap<T> {
+    type Handle = fibonacci_heap::Handle;
+
+    fn len(&self) -> usize {
+        FibonacciHeap::len(self)
+    }
+
+    fn push(&mut self, item: T) -> fibonacci_heap::Handle {
+        FibonacciHeap::push(self, item)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        FibonacciHeap::peek(self)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        FibonacciHeap::pop(self)
+    }
+
+    fn get(&self, handle: fibonacci_heap::Handle) -> Option<&T> {
+        FibonacciHeap::get(self, handle)
+    }
+
+    fn decrease_key(&mut self, handle: fibonacci_heap::Handle, item: T) {
+        FibonacciHeap::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: fibonacci_heap::Handle) -> T {
+        FibonacciHeap::remove(self, handle)
+    }
+
+    fn meld(&mut self, other: Self) {
+        FibonacciHeap::meld(self, other)
+    }
+}
+
+impl<T: Ord> MeldableHeap<T> for LeftistHeap<T> {
+    type Handle = leftist_heap::Handle;
+
+    fn len(&self) -> usize {
+        LeftistHeap::len(self)
+    }
+
+    fn push(&mut self, item: T) -> leftist_heap::Handle {
+        LeftistHeap::push(self, item)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        LeftistHeap::peek(self)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        LeftistHeap::pop(self)
+    }
+
+    fn get(&self, handle: leftist_heap::Handle) -> Option<&T> {
+        LeftistHeap::get(self, handle)
+    }
+
+    fn decrease_key(&mut self, handle: leftist_heap::Handle, item: T) {
+        LeftistHeap::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: leftist_heap::Handle) -> T {
+        LeftistHeap::remove(self, handle)
+    }
+
+    fn meld(&mut self, other: Self) {
+        LeftistHeap::meld(self, other)
+    }
+}
+
+impl<T: Ord> MeldableHeap<T> for SkewHeap<T> {
+    type Handle = leftist_heap::Handle;
+
+    fn len(&self) -> usize {
+        SkewHeap::len(self)
+    }
+
+    fn push(&mut self, item: T) -> leftist_heap::Handle {
+        SkewHeap::push(self, item)
+    }
+
+    fn peek(&self) -> Option<&T> {
+        SkewHeap::peek(self)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        SkewHeap::pop(self)
+    }
+
+    fn get(&self, handle: leftist_heap::Handle) -> Option<&T> {
+        SkewHeap::get(self, handle)
+    }
+
+    fn decrease_key(&mut self, handle: leftist_heap::Handle, item: T) {
+        SkewHeap::decrease_key(self, handle, item)
+    }
+
+    fn remove(&mut self, handle: leftist_heap::Handle) -> T {
+        SkewHeap::remove(self, handle)
+    }
+
+    fn meld(&mut self, other: Self) {
+        SkewHeap::meld(self, other)
+    }
+}
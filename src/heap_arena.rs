@@ -0,0 +1,223 @@
+//! The node arena behind the addressable heaps, with generational keys
+//! that survive melding.
+//!
+//! Nodes live in slots linked by index, as in a [`SlotMap`]: removing a
+//! node frees its slot for reuse and bumps the slot's generation, so a key
+//! to a removed node stops matching instead of silently reaching whatever
+//! is pushed there next. Every arena also has a tag, unique across the
+//! process, and a key names its node by tag, index and generation.
+//!
+//! Melding two heaps moves the nodes of the smaller arena to the end of
+//! the larger one, shifting their links, and records where the moved
+//! arena's slots now start under its tag, along with the tags it had
+//! itself absorbed. Keys into either heap keep resolving, and since a node
+//! only ever moves into an arena at least twice the size of the one it
+//! left, it moves at most `log2 n` times: a run of melds costs `O(log n)`
+//! amortized per node on top of linking the roots.
+//!
+//! [`SlotMap`]: crate::slot_map::SlotMap
+
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The missing link.
+pub(crate) const NONE: u32 = u32::MAX;
+
+/// The source of arena tags.
+static NEXT_TAG: AtomicU64 = AtomicU64::new(0);
+
+/// A node's arena tag, slot index and slot generation when it was pushed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Key {
+    tag: u64,
+    index: u32,
+    generation: u32,
+}
+
+/// A node whose links are slot indices, so that it can be moved to
+/// another arena.
+pub(crate) trait Links {
+    /// Adds `offset` to every link but [`NONE`].
+    fn shift(&mut self, offset: u32);
+}
+
+/// Adds `offset` to `link` unless it is [`NONE`].
+pub(crate) fn shift(link: &mut u32, offset: u32) {
+    if *link != NONE {
+        *link += offset;
+    }
+}
+
+#[derive(Debug)]
+struct Slot<N> {
+    generation: u32,
+    node: Option<N>,
+}
+
+/// Nodes of type `N` in slots, with the tags of the arenas moved into it.
+#[derive(Debug)]
+pub(crate) struct Arena<N> {
+    tag: u64,
+    slots: Vec<Slot<N>>,
+    free: Vec<u32>,
+    /// The tags of arenas moved into this one, and the index their slots
+    /// now start at.
+    moved: HashMap<u64, u32>,
+    len: usize,
+}
+
+/// A clone is a new arena under a new tag: keys into the original do not
+/// reach its nodes, so a clone melded back into the original cannot make
+/// one key name two nodes.
+impl<N: Clone> Clone for Arena<N> {
+    fn clone(&self) -> Self {
+        let slots = self.slots.iter().map(|s| Slot {
+            generation: s.generation,
+            node: s.node.clone(),
+        });
+        Arena {
+            slots: slots.collect(),
+            free: self.free.clone(),
+            len: self.len,
+            ..Arena::new()
+        }
+    }
+}
+
+impl<N> Arena<N> {
+    pub(crate) fn new() -> Self {
+        Arena {
+            tag: NEXT_TAG.fetch_add(1, Ordering::Relaxed),
+            slots: Vec::new(),
+            free: Vec::new(),
+            moved: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of live nodes.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Stores `node`, returning its index and key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already holds `u32::MAX - 1` slots.
+    pub(crate) fn insert(&mut self, node: N) -> (u32, Key) {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                assert!(self.slots.len() < NONE as usize - 1, "heap is full");
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.slots[index as usize].node = Some(node);
+        self.len += 1;
+        (index, self.key(index))
+    }
+
+    /// Takes the node out of slot `n`, making its key stale.
+    pub(crate) fn remove(&mut self, n: u32) -> N {
+        let slot = &mut self.slots[n as usize];
+        let node = slot.node.take().expect("node is live");
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(n);
+        self.len -= 1;
+        node
+    }
+
+    /// The key of the live node in slot `n`.
+    pub(crate) fn key(&self, n: u32) -> Key {
+        Key {
+            tag: self.tag,
+            index: n,
+            generation: self.slots[n as usize].generation,
+        }
+    }
+
+    /// The slot holding the node `key` names, or `None` if the node has
+    /// been removed or was never in this arena.
+    pub(crate) fn find(&self, key: Key) -> Option<u32> {
+        let start = if key.tag == self.tag {
+            0
+        } else {
+            *self.moved.get(&key.tag)?
+        };
+        let n = start.checked_add(key.index)?;
+        let slot = self.slots.get(n as usize)?;
+        (slot.generation == key.generation && slot.node.is_some()).then_some(n)
+    }
+
+    /// The live nodes, in slot order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &N> + '_ {
+        self.slots.iter().filter_map(|s| s.node.as_ref())
+    }
+}
+
+impl<N: Links> Arena<N> {
+    /// Moves the nodes of `other` into this arena, or of this arena into
+    /// `other`'s slots if that is the larger, keeping every key of both
+    /// valid. Returns the offsets to add to this arena's old indices and to
+    /// `other`'s; one of them is zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arenas together hold `u32::MAX - 1` slots or more.
+    pub(crate) fn meld(&mut self, mut other: Self) -> (u32, u32) {
+        // An arena without live nodes has no valid keys to keep.
+        if other.len == 0 {
+            return (0, 0);
+        }
+        if self.len == 0 {
+            *self = other;
+            return (0, 0);
+        }
+        let swapped = self.slots.len() < other.slots.len();
+        if swapped {
+            std::mem::swap(self, &mut other);
+        }
+        assert!(
+            self.slots.len() + other.slots.len() < NONE as usize - 1,
+            "heap is full"
+        );
+        let offset = self.slots.len() as u32;
+        self.slots.extend(other.slots.into_iter().map(|mut s| {
+            if let Some(node) = &mut s.node {
+                node.shift(offset);
+            }
+            s
+        }));
+        self.free.extend(other.free.into_iter().map(|n| n + offset));
+        self.moved.insert(other.tag, offset);
+        for (tag, start) in other.moved {
+            self.moved.insert(tag, start + offset);
+        }
+        self.len += other.len;
+        if swapped {
+            (offset, 0)
+        } else {
+            (0, offset)
+        }
+    }
+}
+
+impl<N> Index<u32> for Arena<N> {
+    type Output = N;
+
+    fn index(&self, n: u32) -> &N {
+        self.slots[n as usize].node.as_ref().expect("node is live")
+    }
+}
+
+impl<N> IndexMut<u32> for Arena<N> {
+    fn index_mut(&mut self, n: u32) -> &mut N {
+        self.slots[n as usize].node.as_mut().expect("node is live")
+    }
+}
@@ -0,0 +1,439 @@
+//! Heavy-light decomposition of a rooted tree, mapping paths to ranges.
+//!
+//! Every node of the tree picks its child with the largest subtree as its
+//! heavy child, and the edges to heavy children link up into heavy paths
+//! that partition the nodes (Sleator and Tarjan, 1983). Walking up from a
+//! node, each light edge crossed leads into a subtree at least twice as
+//! large, so any root path meets `O(log n)` heavy paths. Numbering the
+//! nodes in a depth-first order that always visits the heavy child first
+//! gives every heavy path, and every subtree, a contiguous range of
+//! positions.
+//!
+//! A path between two nodes therefore splits into `O(log n)` ranges, and
+//! a structure over sequences answers queries on tree paths range by
+//! range. Lay the node values out with [`arrange`](HeavyLight::arrange)
+//! and build a [`SegmentTree`] over them: [`query_path`] and
+//! [`update_path`] then aggregate and update along a path in `O(log² n)`,
+//! and [`query_subtree`] and [`update_subtree`] need a single range.
+//! Those helpers combine the ranges in no particular order, so the monoid
+//! should be commutative; [`path`](HeavyLight::path) gives the ranges in
+//! order from one end to the other, each with its direction, for
+//! aggregates where order matters. For a weight on each edge, store it at
+//! the deeper endpoint and use [`path_edges`](HeavyLight::path_edges),
+//! which leaves out the lowest common ancestor.
+//!
+//! The tree is static; for paths in a forest that changes, see the
+//! [`LinkCutTree`].
+//!
+//! [`SegmentTree`]: crate::segment_tree::SegmentTree
+//! [`query_path`]: HeavyLight::query_path
+//! [`update_path`]: HeavyLight::update_path
+//! [`query_subtree`]: HeavyLight::query_subtree
+//! [`update_subtree`]: HeavyLight::update_subtree
+//! [`LinkCutTree`]: crate::link_cut::LinkCutTree
+
+use std::ops::Range;
+
+use crate::algebra::{Action, Monoid};
+use crate::csr_graph::CsrGraph;
+use crate::segment_tree::SegmentTree;
+
+const NONE: u32 = u32::MAX;
+
+/// A stretch of a path that lies on one heavy path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    /// The positions of the nodes.
+    pub range: Range<usize>,
+    /// Whether the path walks the range from its last position to its
+    /// first.
+    pub reversed: bool,
+}
+
+/// The heavy-light decomposition of a tree over nodes `0..n`.
+#[derive(Clone, Debug)]
+pub struct HeavyLight {
+    root: u32,
+    parent: Vec<u32>,
+    depth: Vec<u32>,
+    size: Vec<u32>,
+    /// The top of each node's heavy path.
+    head: Vec<u32>,
+    /// Each node's position in the heavy-first order.
+    position: Vec<u32>,
+    /// The node at each position.
+    order: Vec<u32>,
+}
+
+impl HeavyLight {
+    /// Decomposes the tree with `node_count` nodes and the undirected
+    /// `edges`, rooted at `root`, in `O(n)` plus the sort of the edges.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge names a node out of range, if `root` is out of
+    /// range, or if the edges do not form a tree.
+    pub fn from_edges(
+        node_count: usize,
+        edges: impl IntoIterator<Item = (usize, usize)>,
+        root: usize,
+    ) -> Self {
+        let edges = edges.into_iter().map(|(u, v)| (u, v, ())).collect();
+        Self::from_graph(
+            &CsrGraph::build_undirected(vec![(); node_count], edges),
+            root,
+        )
+    }
+
+    /// Decomposes the tree stored in `graph` with each edge in both
+    /// directions, as [`CsrGraph::build_undirected`] stores it, rooted at
+    /// `root`, in `O(n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `root` is out of range or if the graph is not a tree.
+    pub fn from_graph<N, E>(graph: &CsrGraph<N, E>, root: usize) -> Self {
+        let n = graph.node_count();
+        assert!(root < n, "root {root} out of range for {n} nodes");
+        assert_eq!(graph.edge_count(), 2 * (n - 1), "not a tree");
+        let mut parent = vec![NONE; n];
+        let mut depth = vec![0; n];
+        // Nodes in the order a search from the root first reaches them,
+        // so every parent comes before its children.
+        let mut reached = Vec::with_capacity(n);
+        reached.push(root as u32);
+        parent[root] = root as u32;
+        let mut i = 0;
+        while i < reached.len() {
+            let u = reached[i] as usize;
+            for v in graph.neighbors(u) {
+                if v as u32 == parent[u] && u != root {
+                    continue;
+                }
+                assert_eq!(parent[v], NONE, "not a tree");
+                parent[v] = u as u32;
+                depth[v] = depth[u] + 1;
+                reached.push(v as u32);
+            }
+            i += 1;
+        }
+        assert_eq!(reached.len(), n, "not a tree");
+        parent[root] = NONE;
+
+        let mut size = vec![1; n];
+        let mut heavy = vec![NONE; n];
+        for &v in reached.iter().rev() {
+            let p = parent[v as usize];
+            if p != NONE {
+                size[p as usize] += size[v as usize];
+                let h = heavy[p as usize];
+                if h == NONE || size[h as usize] < size[v as usize] {
+                    heavy[p as usize] = v;
+                }
+            }
+        }
+
+        let mut head = vec![NONE; n];
+        let mut position = vec![0; n];
+        let mut order = Vec::with_capacity(n);
+        head[root] = root as u32;
+        let mut stack = vec![root as u32];
+        while let Some(u) = stack.pop() {
+            position[u as usize] = order.len() as u32;
+            order.push(u);
+            let h = heavy[u as usize];
+            for v in graph.neighbors(u as usize) {
+                let v = v as u32;
+                if v != parent[u as usize] && v != h {
+                    head[v as usize] = v;
+                    stack.push(v);
+                }
+            }
+            // Pushed last, so visited next.
+            if h != NONE {
+                head[h as usize] = head[u as usize];
+                stack.push(h);
+            }
+        }
+
+        HeavyLight {
+            root: root as u32,
+            parent,
+            depth,
+            size,
+            head,
+            position,
+            order,
+        }
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Whether the tree is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// The root.
+    pub fn root(&self) -> usize {
+        self.root as usize
+    }
+
+    /// The parent of `v`, or `None` for the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn parent(&self, v: usize) -> Option<usize> {
+        let p = self.parent[v];
+        (p != NONE).then_some(p as usize)
+    }
+
+    /// The number of ancestors of `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn depth(&self, v: usize) -> usize {
+        self.depth[v] as usize
+    }
+
+    /// The number of nodes in the subtree of `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn subtree_size(&self, v: usize) -> usize {
+        self.size[v] as usize
+    }
+
+    /// The top of the heavy path through `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn head(&self, v: usize) -> usize {
+        self.head[v] as usize
+    }
+
+    /// The position of `v` in the heavy-first order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn position(&self, v: usize) -> usize {
+        self.position[v] as usize
+    }
+
+    /// The node at position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn node(&self, i: usize) -> usize {
+        self.order[i] as usize
+    }
+
+    /// Lays out `values`, one per node, in position order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not one value per node.
+    pub fn arrange<T: Clone>(&self, values: &[T]) -> Vec<T> {
+        assert_eq!(values.len(), self.len(), "one value per node");
+        self.order
+            .iter()
+            .map(|&v| values[v as usize].clone())
+            .collect()
+    }
+
+    /// The positions of the subtree of `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn subtree(&self, v: usize) -> Range<usize> {
+        let start = self.position[v] as usize;
+        start..start + self.size[v] as usize
+    }
+
+    /// Whether `u` is an ancestor of `v`, or `v` itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn is_ancestor(&self, u: usize, v: usize) -> bool {
+        self.subtree(u).contains(&(self.position[v] as usize))
+    }
+
+    /// The lowest common ancestor of `u` and `v`, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn lca(&self, u: usize, v: usize) -> usize {
+        let (mut u, mut v) = (u as u32, v as u32);
+        while self.head[u as usize] != self.head[v as usize] {
+            if self.depth[self.head[u as usize] as usize]
+                > self.depth[self.head[v as usize] as usize]
+            {
+                u = self.parent[self.head[u as usize] as usize];
+            } else {
+                v = self.parent[self.head[v as usize] as usize];
+            }
+        }
+        if self.depth[u as usize] < self.depth[v as usize] {
+            u as usize
+        } else {
+            v as usize
+        }
+    }
+
+    /// The number of edges on the path between `u` and `v`, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn distance(&self, u: usize, v: usize) -> usize {
+        let w = self.lca(u, v);
+        self.depth(u) + self.depth(v) - 2 * self.depth(w)
+    }
+
+    /// The `O(log n)` segments covering the nodes on the path from `u` to
+    /// `v`, in order from `u`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn path(&self, u: usize, v: usize) -> Vec<Segment> {
+        self.segments(u, v, true)
+    }
+
+    /// The segments covering the nodes on the path from `u` to `v` other
+    /// than their lowest common ancestor, in order from `u`: with each
+    /// edge's weight stored at its deeper endpoint, the edges of the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn path_edges(&self, u: usize, v: usize) -> Vec<Segment> {
+        self.segments(u, v, false)
+    }
+
+    fn segments(&self, u: usize, v: usize, with_lca: bool) -> Vec<Segment> {
+        let at = |x: u32| self.position[x as usize] as usize;
+        let (mut u, mut v) = (u as u32, v as u32);
+        let (mut up, mut down) = (Vec::new(), Vec::new());
+        while self.head[u as usize] != self.head[v as usize] {
+            let (hu, hv) = (self.head[u as usize], self.head[v as usize]);
+            if self.depth[hu as usize] > self.depth[hv as usize] {
+                up.push(Segment {
+                    range: at(hu)..at(u) + 1,
+                    reversed: true,
+                });
+                u = self.parent[hu as usize];
+            } else {
+                down.push(Segment {
+                    range: at(hv)..at(v) + 1,
+                    reversed: false,
+                });
+                v = self.parent[hv as usize];
+            }
+        }
+        // One of `u` and `v` is now the lowest common ancestor.
+        let skip = usize::from(!with_lca);
+        if at(u) >= at(v) {
+            up.push(Segment {
+                range: at(v) + skip..at(u) + 1,
+                reversed: true,
+            });
+        } else {
+            down.push(Segment {
+                range: at(u) + skip..at(v) + 1,
+                reversed: false,
+            });
+        }
+        up.extend(down.into_iter().rev());
+        up.retain(|s| !s.range.is_empty());
+        up
+    }
+
+    /// The combination of the values at the nodes on the path between `u`
+    /// and `v`, read from `tree` over the values in position order, in
+    /// `O(log² n)`. The monoid should be commutative.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds, or if `tree` does not have
+    /// one element per node.
+    pub fn query_path<M: Monoid, U: Action<M>>(
+        &self,
+        tree: &SegmentTree<M, U>,
+        u: usize,
+        v: usize,
+    ) -> M::Value {
+        assert_eq!(tree.len(), self.len(), "one element per node");
+        self.path(u, v)
+            .into_iter()
+            .fold(tree.monoid().identity(), |acc, s| {
+                tree.monoid().combine(&acc, &tree.query(s.range))
+            })
+    }
+
+    /// Applies `update` to the values at the nodes on the path between `u`
+    /// and `v`, in `tree` over the values in position order, in
+    /// `O(log² n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds, or if `tree` does not have
+    /// one element per node.
+    pub fn update_path<M: Monoid, U: Action<M>>(
+        &self,
+        tree: &mut SegmentTree<M, U>,
+        u: usize,
+        v: usize,
+        update: U,
+    ) {
+        assert_eq!(tree.len(), self.len(), "one element per node");
+        for s in self.path(u, v) {
+            tree.update(s.range, update.clone());
+        }
+    }
+
+    /// The combination of the values in the subtree of `v`, read from
+    /// `tree` over the values in position order, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds, or if `tree` does not have one
+    /// element per node.
+    pub fn query_subtree<M: Monoid, U: Action<M>>(
+        &self,
+        tree: &SegmentTree<M, U>,
+        v: usize,
+    ) -> M::Value {
+        assert_eq!(tree.len(), self.len(), "one element per node");
+        tree.query(self.subtree(v))
+    }
+
+    /// Applies `update` to the values in the subtree of `v`, in `tree` over
+    /// the values in position order, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds, or if `tree` does not have one
+    /// element per node.
+    pub fn update_subtree<M: Monoid, U: Action<M>>(
+        &self,
+        tree: &mut SegmentTree<M, U>,
+        v: usize,
+        update: U,
+    ) {
+        assert_eq!(tree.len(), self.len(), "one element per node");
+        tree.update(self.subtree(v), update);
+    }
+}
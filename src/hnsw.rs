@@ -0,0 +1,316 @@
+//! Approximate nearest-neighbor search with a hierarchical navigable small
+//! world (HNSW) graph.
+//!
+//! Malkov and Yashunin's HNSW links every point to a handful of near
+//! neighbors on a stack of proximity graphs. Each point is assigned a random
+//! top layer with exponentially decaying probability, so upper layers are
+//! sparse and their links long. A search descends greedily through the
+//! upper layers to land near the query, then runs a best-first beam search
+//! of width `ef` on the bottom layer, which holds every point.
+//!
+//! The structure works under any [`Metric`] and its cost grows roughly
+//! logarithmically with the number of points, independent of the
+//! dimension, which makes it the usual choice for embedding search. Results
+//! are approximate: widening `ef` trades speed for recall. Points cannot be
+//! removed; handles are insertion indices.
+//!
+//! [`Metric`]: crate::metric::Metric
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::metric::Metric;
+use crate::rng::SplitMix64;
+use crate::util::Total;
+
+/// Tuning parameters for an [`Hnsw`] graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HnswConfig {
+    /// Links per point on the upper layers; the bottom layer allows twice
+    /// as many. Larger values raise recall and memory use.
+    pub m: usize,
+    /// Beam width used while inserting. Larger values build a better graph
+    /// more slowly.
+    pub ef_construction: usize,
+    /// Default beam width for [`Hnsw::nearest`].
+    pub ef_search: usize,
+    /// Seed for the layer assignment, making builds reproducible.
+    pub seed: u64,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        HnswConfig {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 50,
+            seed: 0x5eed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node<P, T> {
+    point: P,
+    data: T,
+    /// Neighbor lists, one per layer from the bottom up.
+    links: Vec<Vec<u32>>,
+}
+
+/// An HNSW graph over points of type `P` carrying data of type `T`, under
+/// the metric `M`.
+#[derive(Clone, Debug)]
+pub struct Hnsw<P, T, M> {
+    nodes: Vec<Node<P, T>>,
+    entry: Option<u32>,
+    config: HnswConfig,
+    /// Normalization of the layer distribution, `1 / ln(m)`.
+    level_scale: f64,
+    rng: SplitMix64,
+    metric: M,
+}
+
+impl<P, T, M: Metric<P>> Hnsw<P, T, M> {
+    /// Creates an empty graph with the default configuration.
+    pub fn new(metric: M) -> Self {
+        Self::with_config(metric, HnswConfig::default())
+    }
+
+    /// Creates an empty graph with explicit tuning parameters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config.m` is less than 2 or either beam width is zero.
+    pub fn with_config(metric: M, config: HnswConfig) -> Self {
+        assert!(config.m >= 2, "m must be at least 2");
+        assert!(
+            config.ef_construction > 0 && config.ef_search > 0,
+            "beam widths must be positive"
+        );
+        Hnsw {
+            nodes: Vec::new(),
+            entry: None,
+            config,
+            level_scale: 1.0 / (config.m as f64).ln(),
+            rng: SplitMix64::new(config.seed),
+            metric,
+        }
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the graph holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The metric the graph was built with.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// The tuning parameters.
+    pub fn config(&self) -> &HnswConfig {
+        &self.config
+    }
+
+    /// Sets the default beam width used by [`Hnsw::nearest`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ef` is zero.
+    pub fn set_ef_search(&mut self, ef: usize) {
+        assert!(ef > 0, "beam width must be positive");
+        self.config.ef_search = ef;
+    }
+
+    /// Number of layers above the bottom one.
+    pub fn top_layer(&self) -> usize {
+        self.entry
+            .map_or(0, |e| self.nodes[e as usize].links.len() - 1)
+    }
+
+    /// The point and data at an insertion index.
+    pub fn get(&self, index: usize) -> Option<(&P, &T)> {
+        self.nodes.get(index).map(|n| (&n.point, &n.data))
+    }
+
+    /// Iterates over the points in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &P, &T)> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (i, &n.point, &n.data))
+    }
+
+    /// The bottom-layer neighbors of a point.
+    pub fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[index].links[0].iter().map(|&n| n as usize)
+    }
+
+    /// Inserts a point, returning its insertion index.
+    pub fn insert(&mut self, point: P, data: T) -> usize {
+        let level = (-(1.0 - self.rng.next_f64()).ln() * self.level_scale) as usize;
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            point,
+            data,
+            links: vec![Vec::new(); level + 1],
+        });
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            return id as usize;
+        };
+        let top = self.top_layer();
+        let q = &self.nodes[id as usize].point;
+        let mut nearest = vec![(
+            self.metric.distance(q, &self.nodes[entry as usize].point),
+            entry,
+        )];
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(q, nearest, 1, layer);
+        }
+        for layer in (0..=level.min(top)).rev() {
+            let q = &self.nodes[id as usize].point;
+            let found = self.search_layer(q, nearest, self.config.ef_construction, layer);
+            let chosen = self.select(&found, self.config.m);
+            self.nodes[id as usize].links[layer] = chosen.clone();
+            for &n in &chosen {
+                self.nodes[n as usize].links[layer].push(id);
+                self.shrink(n, layer);
+            }
+            nearest = found;
+        }
+        if level > top {
+            self.entry = Some(id);
+        }
+        id as usize
+    }
+
+    /// Returns approximately the `k` points nearest to `query`, closest
+    /// first, searching with the configured beam width (at least `k`).
+    pub fn nearest(&self, query: &P, k: usize) -> Vec<(usize, f64)> {
+        self.search(query, k, self.config.ef_search)
+    }
+
+    /// Returns the approximately nearest point and its distance.
+    pub fn nearest_one(&self, query: &P) -> Option<(usize, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Returns approximately the `k` points nearest to `query`, closest
+    /// first, with a beam of width `ef` (raised to `k` if smaller).
+    pub fn search(&self, query: &P, k: usize, ef: usize) -> Vec<(usize, f64)> {
+        let Some(entry) = self.entry.filter(|_| k > 0) else {
+            return Vec::new();
+        };
+        let mut nearest = vec![(
+            self.metric
+                .distance(query, &self.nodes[entry as usize].point),
+            entry,
+        )];
+        for layer in (1..=self.top_layer()).rev() {
+            nearest = self.search_layer(query, nearest, 1, layer);
+        }
+        let mut found = self.search_layer(query, nearest, ef.max(k), 0);
+        found.truncate(k);
+        found.into_iter().map(|(d, n)| (n as usize, d)).collect()
+    }
+
+    /// Beam search on one layer from `entries`, returning up to `ef`
+    /// `(distance, node)` pairs sorted closest first.
+    fn search_layer(
+        &self,
+        q: &P,
+        entries: Vec<(f64, u32)>,
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(f64, u32)> {
+        let mut visited: HashSet<u32> = entries.iter().map(|e| e.1).collect();
+        let mut frontier: BinaryHeap<Reverse<(Total, u32)>> = entries
+            .iter()
+            .map(|&(d, n)| Reverse((Total(d), n)))
+            .collect();
+        let mut best: BinaryHeap<(Total, u32)> =
+            entries.iter().map(|&(d, n)| (Total(d), n)).collect();
+        while best.len() > ef {
+            best.pop();
+        }
+        while let Some(Reverse((Total(d), n))) = frontier.pop() {
+            let worst = best.peek().map_or(f64::INFINITY, |b| b.0 .0);
+            if d > worst && best.len() >= ef {
+                break;
+            }
+            for &m in &self.nodes[n as usize].links[layer] {
+                if !visited.insert(m) {
+                    continue;
+                }
+                let dm = self.metric.distance(q, &self.nodes[m as usize].point);
+                let worst = best.peek().map_or(f64::INFINITY, |b| b.0 .0);
+                if best.len() < ef || dm < worst {
+                    frontier.push(Reverse((Total(dm), m)));
+                    best.push((Total(dm), m));
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+        let mut out: Vec<_> = best.into_iter().map(|(d, n)| (d.0, n)).collect();
+        out.sort_by(|a, b| a.0.total_cmp(&b.0));
+        out
+    }
+
+    /// The neighbor-selection heuristic: takes candidates closest first,
+    /// skipping any that is nearer to an already chosen neighbor than to
+    /// the base point, so links spread out in different directions. Skipped
+    /// candidates fill any remaining slots.
+    fn select(&self, candidates: &[(f64, u32)], m: usize) -> Vec<u32> {
+        let mut chosen: Vec<u32> = Vec::with_capacity(m);
+        let mut skipped = Vec::new();
+        for &(d, c) in candidates {
+            if chosen.len() == m {
+                break;
+            }
+            let point = &self.nodes[c as usize].point;
+            let diverse = chosen
+                .iter()
+                .all(|&r| self.metric.distance(point, &self.nodes[r as usize].point) > d);
+            if diverse {
+                chosen.push(c);
+            } else {
+                skipped.push(c);
+            }
+        }
+        let room = m - chosen.len();
+        chosen.extend(skipped.into_iter().take(room));
+        chosen
+    }
+
+    /// Prunes the links of `n` on `layer` back to the layer's limit.
+    fn shrink(&mut self, n: u32, layer: usize) {
+        let limit = if layer == 0 {
+            2 * self.config.m
+        } else {
+            self.config.m
+        };
+        if self.nodes[n as usize].links[layer].len() <= limit {
+            return;
+        }
+        let point = &self.nodes[n as usize].point;
+        let mut candidates: Vec<(f64, u32)> = self.nodes[n as usize].links[layer]
+            .iter()
+            .map(|&c| {
+                (
+                    self.metric.distance(point, &self.nodes[c as usize].point),
+                    c,
+                )
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.nodes[n as usize].links[layer] = self.select(&candidates, limit);
+    }
+}
@@ -0,0 +1,381 @@
+//! A HyperLogLog sketch: distinct counts in a few kilobytes.
+//!
+//! HyperLogLog (Flajolet, Fusy, Gandouet and Meunier, 2007) hashes every
+//! item, sends it to one of `m = 2^p` registers by the hash's top `p` bits,
+//! and keeps in each register the largest rank seen, the position of the
+//! first one bit among the rest. A register that has seen `n` items holds
+//! about `log₂ n`, so the registers together estimate the number of
+//! distinct items with a relative standard error of `1.04 / √m`: 1.6% at
+//! the default precision of 12, in 4 KiB. Inserting an item twice changes
+//! nothing, and two sketches merge by taking register-wise maxima, so
+//! sketches of shards combine into the sketch of their union.
+//!
+//! This follows HyperLogLog++ (Heule, Nunkesser and Hall, 2013) in hashing
+//! to 64 bits, so the estimate does not degrade at billions of items, and
+//! in starting out sparse: a small sketch keeps only its nonzero registers,
+//! as a sorted list, until that list would outgrow the dense array. Where
+//! HyperLogLog++ corrects the raw estimate's bias at small cardinalities
+//! with empirical tables, this uses Ertl's improved estimator (2017), which
+//! is unbiased across the whole range from the register histogram alone.
+//!
+//! [`to_bytes`](HyperLogLog::to_bytes) writes the sparse list as varint
+//! deltas or the dense registers at six bits each. Items are hashed with
+//! the shared [`StableHasher`], so sketches from different processes merge.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::hash::Hash;
+
+use crate::probabilistic::{self, DecodeError, Incompatible, Reader, DEFAULT_SEED};
+
+/// The smallest supported precision.
+pub const MIN_PRECISION: u8 = 4;
+/// The largest supported precision.
+pub const MAX_PRECISION: u8 = 18;
+/// The precision [`HyperLogLog::default`] uses.
+pub const DEFAULT_PRECISION: u8 = 12;
+
+const TAG: &[u8; 4] = b"HLL1";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Registers {
+    /// The nonzero registers as `index << 6 | rank`, sorted by index.
+    Sparse(Vec<u32>),
+    Dense(Vec<u8>),
+}
+
+/// A HyperLogLog sketch over items of any `Hash` type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Registers,
+    precision: u8,
+    seed: u64,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_PRECISION)
+    }
+}
+
+impl HyperLogLog {
+    /// Creates an empty sketch with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `precision` is between [`MIN_PRECISION`] and
+    /// [`MAX_PRECISION`].
+    pub fn new(precision: u8) -> Self {
+        Self::with_seed(precision, DEFAULT_SEED)
+    }
+
+    /// Creates an empty sketch with `2^precision` registers, hashing with
+    /// `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `precision` is between [`MIN_PRECISION`] and
+    /// [`MAX_PRECISION`].
+    pub fn with_seed(precision: u8, seed: u64) -> Self {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "precision must be between {MIN_PRECISION} and {MAX_PRECISION}"
+        );
+        HyperLogLog {
+            registers: Registers::Sparse(Vec::new()),
+            precision,
+            seed,
+        }
+    }
+
+    /// The precision: the sketch has `2^precision` registers.
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    /// The hash seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The relative standard error of the estimate, `1.04 / √m`.
+    pub fn relative_error(&self) -> f64 {
+        1.04 / (self.registers() as f64).sqrt()
+    }
+
+    /// Whether nothing has been inserted.
+    pub fn is_empty(&self) -> bool {
+        match &self.registers {
+            Registers::Sparse(entries) => entries.is_empty(),
+            Registers::Dense(registers) => registers.iter().all(|&r| r == 0),
+        }
+    }
+
+    /// Whether the sketch still keeps only its nonzero registers.
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.registers, Registers::Sparse(_))
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.registers = Registers::Sparse(Vec::new());
+    }
+
+    /// Adds an item.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let hash = probabilistic::stable_hash(self.seed, item);
+        let p = u32::from(self.precision);
+        let index = (hash >> (64 - p)) as u32;
+        let rest = hash << p;
+        let rank = if rest == 0 {
+            64 - p + 1
+        } else {
+            rest.leading_zeros() + 1
+        };
+        self.raise(index, rank as u8);
+    }
+
+    /// Estimates the number of distinct items inserted.
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers() as f64;
+        let q = 64 - usize::from(self.precision);
+        // How many registers hold each rank, from 0 to q + 1.
+        let mut histogram = vec![0.0; q + 2];
+        match &self.registers {
+            Registers::Sparse(entries) => {
+                histogram[0] = m - entries.len() as f64;
+                for &e in entries {
+                    histogram[(e & 0x3f) as usize] += 1.0;
+                }
+            }
+            Registers::Dense(registers) => {
+                for &r in registers {
+                    histogram[usize::from(r)] += 1.0;
+                }
+            }
+        }
+        if histogram[0] == m {
+            return 0.0;
+        }
+        let mut z = m * tau(1.0 - histogram[q + 1] / m);
+        for k in (1..=q).rev() {
+            z = 0.5 * (z + histogram[k]);
+        }
+        z += m * sigma(histogram[0] / m);
+        m * m / (2.0 * std::f64::consts::LN_2 * z)
+    }
+
+    /// Whether `other` has the same precision and seed, so the two can be
+    /// merged.
+    pub fn is_compatible(&self, other: &HyperLogLog) -> bool {
+        self.precision == other.precision && self.seed == other.seed
+    }
+
+    /// Adds every item of `other` to this sketch.
+    pub fn merge(&mut self, other: &HyperLogLog) -> Result<(), Incompatible> {
+        if !self.is_compatible(other) {
+            return Err(Incompatible);
+        }
+        match &other.registers {
+            Registers::Sparse(entries) => {
+                for &e in entries {
+                    self.raise(e >> 6, (e & 0x3f) as u8);
+                }
+            }
+            Registers::Dense(theirs) => {
+                self.densify();
+                let Registers::Dense(ours) = &mut self.registers else {
+                    unreachable!("densified");
+                };
+                for (a, &b) in ours.iter_mut().zip(theirs) {
+                    *a = (*a).max(b);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes the sketch: a tag, the precision, seed and a format byte,
+    /// then for a sparse sketch its entry count and the entries as varint
+    /// deltas, or for a dense one the registers packed at six bits each.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(TAG);
+        bytes.push(self.precision);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        match &self.registers {
+            Registers::Sparse(entries) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+                let mut previous = 0;
+                for &e in entries {
+                    let mut delta = e - previous;
+                    previous = e;
+                    while delta >= 0x80 {
+                        bytes.push(delta as u8 | 0x80);
+                        delta >>= 7;
+                    }
+                    bytes.push(delta as u8);
+                }
+            }
+            Registers::Dense(registers) => {
+                bytes.push(1);
+                for chunk in registers.chunks(4) {
+                    let packed = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |acc, (i, &r)| acc | u32::from(r) << (6 * i));
+                    bytes.extend_from_slice(&packed.to_le_bytes()[..3]);
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Reads back a sketch written by [`to_bytes`](Self::to_bytes).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(bytes, TAG)?;
+        let precision = reader.u8()?;
+        let seed = reader.u64()?;
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            return Err(DecodeError::Invalid);
+        }
+        let mut sketch = HyperLogLog::with_seed(precision, seed);
+        let max_rank = 64 - u32::from(precision) + 1;
+        let m = sketch.registers();
+        match reader.u8()? {
+            0 => {
+                let count = reader.u32()? as usize;
+                if count > m {
+                    return Err(DecodeError::Invalid);
+                }
+                let mut entries = Vec::with_capacity(count);
+                let mut previous: Option<u32> = None;
+                for _ in 0..count {
+                    let mut delta = 0u32;
+                    let mut shift = 0;
+                    loop {
+                        let byte = reader.u8()?;
+                        if shift > 28 {
+                            return Err(DecodeError::Invalid);
+                        }
+                        delta |= u32::from(byte & 0x7f) << shift;
+                        shift += 7;
+                        if byte & 0x80 == 0 {
+                            break;
+                        }
+                    }
+                    let e = previous
+                        .unwrap_or(0)
+                        .checked_add(delta)
+                        .ok_or(DecodeError::Invalid)?;
+                    let index_increases = previous.is_none_or(|p| e >> 6 > p >> 6);
+                    let rank = e & 0x3f;
+                    if !index_increases || e >> 6 >= m as u32 || rank == 0 || rank > max_rank {
+                        return Err(DecodeError::Invalid);
+                    }
+                    entries.push(e);
+                    previous = Some(e);
+                }
+                sketch.registers = Registers::Sparse(entries);
+            }
+            1 => {
+                let data = reader.take(m / 4 * 3)?;
+                let mut registers = Vec::with_capacity(m);
+                for chunk in data.chunks_exact(3) {
+                    let packed = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], 0]);
+                    registers.extend((0..4).map(|i| (packed >> (6 * i) & 0x3f) as u8));
+                }
+                if registers.iter().any(|&r| u32::from(r) > max_rank) {
+                    return Err(DecodeError::Invalid);
+                }
+                sketch.registers = Registers::Dense(registers);
+            }
+            _ => return Err(DecodeError::Invalid),
+        }
+        reader.finish()?;
+        Ok(sketch)
+    }
+
+    fn registers(&self) -> usize {
+        1 << self.precision
+    }
+
+    /// Raises register `index` to at least `rank`.
+    fn raise(&mut self, index: u32, rank: u8) {
+        match &mut self.registers {
+            Registers::Sparse(entries) => {
+                match entries.binary_search_by_key(&index, |&e| e >> 6) {
+                    Ok(i) => {
+                        if u32::from(rank) > entries[i] & 0x3f {
+                            entries[i] = index << 6 | u32::from(rank);
+                        }
+                        return;
+                    }
+                    Err(i) => entries.insert(i, index << 6 | u32::from(rank)),
+                }
+                // Four bytes an entry against one a register.
+                if entries.len() * 4 > self.registers() {
+                    self.densify();
+                }
+            }
+            Registers::Dense(registers) => {
+                let r = &mut registers[index as usize];
+                *r = (*r).max(rank);
+            }
+        }
+    }
+
+    fn densify(&mut self) {
+        if let Registers::Sparse(entries) = &self.registers {
+            let mut registers = vec![0; self.registers()];
+            for &e in entries {
+                registers[(e >> 6) as usize] = (e & 0x3f) as u8;
+            }
+            self.registers = Registers::Dense(registers);
+        }
+    }
+}
+
+impl<T: Hash> Extend<T> for HyperLogLog {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(&item);
+        }
+    }
+}
+
+/// Ertl's `σ(x) = x + Σ_{k≥1} x^(2^k) 2^(k-1)`, for the empty registers.
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y *= 2.0;
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+/// Ertl's `τ(x) = (1 - x - Σ_{k≥1} (1 - x^(2^-k))² 2^-k) / 3`, for the
+/// saturated registers.
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! An indexed d-ary heap keyed by dense integer IDs.
+//!
+//! A d-ary heap is a binary heap with `d` children per node. A wider node
+//! makes the tree shallower, so moving an item up costs `log_d n` steps,
+//! while moving one down compares `d` children per level. Graph searches
+//! change priorities upward far more often than they pop, and four-way
+//! heaps also keep each node's children in one cache line, so `d = 4` is
+//! the default.
+//!
+//! Items are IDs from `0..n`, such as vertex numbers, each with a
+//! priority. Alongside the heap an array records where each ID sits, so
+//! [`contains`](IndexedHeap::contains) is `O(1)`, and
+//! [`change_priority`](IndexedHeap::change_priority) and
+//! [`remove`](IndexedHeap::remove) locate their item in `O(1)` and then
+//! restore heap order in `O(d log_d n)`. The position array is as long as
+//! the largest ID used, which suits dense ID spaces; for sparse ones, use a
+//! [`PairingHeap`] and keep its handles in a map.
+//!
+//! The smallest priority comes first; wrap priorities in
+//! [`Reverse`](std::cmp::Reverse) for the largest.
+//!
+//! [`PairingHeap`]: crate::pairing_heap::PairingHeap
+
+const NONE: u32 = u32::MAX;
+
+/// Default number of children per node.
+const ARITY: usize = 4;
+
+/// A min-heap of IDs with priorities of type `P`.
+#[derive(Clone, Debug)]
+pub struct IndexedHeap<P> {
+    arity: usize,
+    /// IDs and their priorities, in heap order.
+    heap: Vec<(u32, P)>,
+    /// Each ID's position in `heap`, or `NONE` if absent.
+    positions: Vec<u32>,
+}
+
+impl<P: Ord> Default for IndexedHeap<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Ord> IndexedHeap<P> {
+    /// Creates an empty four-way heap.
+    pub fn new() -> Self {
+        Self::with_arity(ARITY)
+    }
+
+    /// Creates an empty heap with `arity` children per node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `arity < 2`.
+    pub fn with_arity(arity: usize) -> Self {
+        assert!(arity >= 2, "arity must be at least 2");
+        IndexedHeap {
+            arity,
+            heap: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+
+    /// Number of children per node.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    /// Number of IDs in the heap.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Whether the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Removes every ID.
+    pub fn clear(&mut self) {
+        for &(id, _) in &self.heap {
+            self.positions[id as usize] = NONE;
+        }
+        self.heap.clear();
+    }
+
+    /// Whether `id` is in the heap.
+    pub fn contains(&self, id: usize) -> bool {
+        self.position(id).is_some()
+    }
+
+    /// The priority of `id`, if it is in the heap.
+    pub fn priority(&self, id: usize) -> Option<&P> {
+        Some(&self.heap[self.position(id)?].1)
+    }
+
+    /// The ID with the smallest priority, and that priority.
+    pub fn peek(&self) -> Option<(usize, &P)> {
+        self.heap.first().map(|(id, p)| (*id as usize, p))
+    }
+
+    /// Adds `id` with `priority`, or changes its priority if it is already
+    /// in the heap, returning the old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is `u32::MAX` or more.
+    pub fn insert(&mut self, id: usize, priority: P) -> Option<P> {
+        assert!(id < NONE as usize, "id {id} is too large");
+        if let Some(i) = self.position(id) {
+            return Some(self.replace(i, priority));
+        }
+        if id >= self.positions.len() {
+            self.positions.resize(id + 1, NONE);
+        }
+        let i = self.heap.len();
+        self.heap.push((id as u32, priority));
+        self.positions[id] = i as u32;
+        self.sift_up(i);
+        None
+    }
+
+    /// Changes the priority of `id`, in either direction, returning the old
+    /// one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is not in the heap.
+    pub fn change_priority(&mut self, id: usize, priority: P) -> P {
+        let i = self.position(id).expect("id is not in the heap");
+        self.replace(i, priority)
+    }
+
+    /// Removes and returns the ID with the smallest priority, and that
+    /// priority.
+    pub fn pop(&mut self) -> Option<(usize, P)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.take(0))
+    }
+
+    /// Removes `id`, returning its priority, if it is in the heap.
+    pub fn remove(&mut self, id: usize) -> Option<P> {
+        let i = self.position(id)?;
+        Some(self.take(i).1)
+    }
+
+    fn position(&self, id: usize) -> Option<usize> {
+        match self.positions.get(id) {
+            Some(&i) if i != NONE => Some(i as usize),
+            _ => None,
+        }
+    }
+
+    fn replace(&mut self, i: usize, priority: P) -> P {
+        let old = std::mem::replace(&mut self.heap[i].1, priority);
+        if self.heap[i].1 < old {
+            self.sift_up(i);
+        } else {
+            self.sift_down(i);
+        }
+        old
+    }
+
+    /// Removes the entry at position `i`, filling the hole with the last
+    /// entry.
+    fn take(&mut self, i: usize) -> (usize, P) {
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let (id, priority) = self.heap.pop().expect("heap is not empty");
+        self.positions[id as usize] = NONE;
+        if i < last {
+            // The moved entry may belong above or below its new spot.
+            self.sift_up(i);
+            self.sift_down(i);
+        }
+        (id as usize, priority)
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        self.positions[self.heap[a].0 as usize] = a as u32;
+        self.positions[self.heap[b].0 as usize] = b as u32;
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / self.arity;
+            if self.heap[i].1 >= self.heap[parent].1 {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let first = self.arity * i + 1;
+            let end = (first + self.arity).min(self.heap.len());
+            let Some(child) = (first..end).min_by(|&a, &b| self.heap[a].1.cmp(&self.heap[b].1))
+            else {
+                break;
+            };
+            if self.heap[child].1 >= self.heap[i].1 {
+                break;
+            }
+            self.swap(i, child);
+            i = child;
+        }
+    }
+}
+
+impl<P: Ord> Extend<(usize, P)> for IndexedHeap<P> {
+    fn extend<I: IntoIterator<Item = (usize, P)>>(&mut self, iter: I) {
+        for (id, priority) in iter {
+            self.insert(id, priority);
+        }
+    }
+}
+
+impl<P: Ord> FromIterator<(usize, P)> for IndexedHeap<P> {
+    fn from_iter<I: IntoIterator<Item = (usize, P)>>(iter: I) -> Self {
+        let mut heap = IndexedHeap::new();
+        heap.extend(iter);
+        heap
+    }
+}
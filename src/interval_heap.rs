@@ -0,0 +1,214 @@
+//! An interval heap: a double-ended priority queue.
+//!
+//! An interval heap is a complete binary tree whose nodes each hold two
+//! items, a low and a high one, so a node stands for an interval. Every
+//! node's interval lies within its parent's. The low ends then form a
+//! min-heap and the high ends a max-heap, and the root holds both the
+//! smallest and the largest item. Pushing and popping from either end
+//! restore order along one root-to-leaf path, in `O(log n)`, and the whole
+//! structure is a single array with no overhead per item.
+//!
+//! That suits queues consumed from both ends: a scheduler taking the most
+//! urgent job while evicting the least valuable one when over budget, or
+//! a bounded buffer that keeps the best `k` items of a stream.
+//!
+//! Node `k` keeps its low item at index `2k` and its high one at `2k + 1`.
+//! Only the last node can be missing its high item.
+
+/// A double-ended priority queue of items of type `T`.
+#[derive(Clone, Debug)]
+pub struct IntervalHeap<T> {
+    items: Vec<T>,
+}
+
+impl<T: Ord> Default for IntervalHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord> IntervalHeap<T> {
+    /// Creates an empty heap.
+    pub fn new() -> Self {
+        IntervalHeap { items: Vec::new() }
+    }
+
+    /// Creates an empty heap with room for `capacity` items.
+    pub fn with_capacity(capacity: usize) -> Self {
+        IntervalHeap {
+            items: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// The smallest item.
+    pub fn peek_min(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    /// The largest item.
+    pub fn peek_max(&self) -> Option<&T> {
+        self.items.get(1).or(self.items.first())
+    }
+
+    /// Iterates over the items in no particular order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    /// Adds an item in `O(log n)`.
+    pub fn push(&mut self, item: T) {
+        let i = self.items.len();
+        self.items.push(item);
+        let v = &mut self.items;
+        if i % 2 == 1 {
+            // The item completes its node as the high end, unless it is
+            // smaller than the low one.
+            if v[i] < v[i - 1] {
+                v.swap(i, i - 1);
+                self.sift_up_min(i - 1);
+            } else {
+                self.sift_up_max(i);
+            }
+        } else if i > 0 {
+            // A lone item in a new node: it can only break the bounds of
+            // its parent's interval on one side.
+            let parent = (i / 2 - 1) / 2;
+            if v[i] < v[2 * parent] {
+                self.sift_up_min(i);
+            } else if v[i] > v[2 * parent + 1] {
+                self.sift_up_max(i);
+            }
+        }
+    }
+
+    /// Removes and returns the smallest item, in `O(log n)`.
+    pub fn pop_min(&mut self) -> Option<T> {
+        if self.items.len() <= 2 {
+            return (!self.items.is_empty()).then(|| self.items.remove(0));
+        }
+        let min = self.items.swap_remove(0);
+        let v = &mut self.items;
+        let len = v.len();
+        let mut k = 0;
+        loop {
+            let (lo, hi) = (2 * k, 2 * k + 1);
+            if hi < len && v[lo] > v[hi] {
+                v.swap(lo, hi);
+            }
+            let Some(c) = [2 * k + 1, 2 * k + 2]
+                .into_iter()
+                .filter(|&c| 2 * c < len)
+                .min_by(|&a, &b| v[2 * a].cmp(&v[2 * b]))
+            else {
+                break;
+            };
+            if v[2 * c] >= v[lo] {
+                break;
+            }
+            v.swap(2 * c, lo);
+            k = c;
+        }
+        Some(min)
+    }
+
+    /// Removes and returns the largest item, in `O(log n)`.
+    pub fn pop_max(&mut self) -> Option<T> {
+        if self.items.len() <= 2 {
+            return self.items.pop();
+        }
+        let max = self.items.swap_remove(1);
+        let v = &mut self.items;
+        let len = v.len();
+        let mut k = 0;
+        loop {
+            let (lo, hi) = (2 * k, 2 * k + 1);
+            if v[lo] > v[hi] {
+                v.swap(lo, hi);
+            }
+            // A child's high end, or its only item if it has one.
+            let high = |c: usize| if 2 * c + 1 < len { 2 * c + 1 } else { 2 * c };
+            let Some(c) = [2 * k + 1, 2 * k + 2]
+                .into_iter()
+                .filter(|&c| 2 * c < len)
+                .max_by(|&a, &b| v[high(a)].cmp(&v[high(b)]))
+            else {
+                break;
+            };
+            let top = high(c);
+            if v[top] <= v[hi] {
+                break;
+            }
+            v.swap(top, hi);
+            if top % 2 == 0 {
+                // A lone item has no children.
+                break;
+            }
+            k = c;
+        }
+        Some(max)
+    }
+
+    /// Consumes the heap, returning the items in ascending order.
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut sorted = Vec::with_capacity(self.len());
+        while let Some(item) = self.pop_min() {
+            sorted.push(item);
+        }
+        sorted
+    }
+
+    /// Moves the item at `i`, a low end or a lone item, up the min-heap.
+    fn sift_up_min(&mut self, mut i: usize) {
+        while i >= 2 {
+            let parent = 2 * ((i / 2 - 1) / 2);
+            if self.items[i] >= self.items[parent] {
+                break;
+            }
+            self.items.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    /// Moves the item at `i`, a high end or a lone item, up the max-heap.
+    fn sift_up_max(&mut self, mut i: usize) {
+        while i >= 2 {
+            let parent = 2 * ((i / 2 - 1) / 2) + 1;
+            if self.items[i] <= self.items[parent] {
+                break;
+            }
+            self.items.swap(i, parent);
+            i = parent;
+        }
+    }
+}
+
+impl<T: Ord> Extend<T> for IntervalHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for IntervalHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = IntervalHeap::new();
+        heap.extend(iter);
+        heap
+    }
+}
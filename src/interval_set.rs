@@ -0,0 +1,213 @@
+//! An ordered set of disjoint half-open ranges.
+//!
+//! The set stores a union of ranges as the fewest disjoint ranges that
+//! cover it: inserting a range absorbs every stored range it overlaps or
+//! touches, and removing one trims the ranges it overlaps, splitting a
+//! range it falls strictly inside. Stored ranges never overlap and never
+//! touch, so the representation of a set is unique. They are kept in a
+//! `BTreeMap` from start to end, and every operation costs `O(log n)` plus
+//! the number of ranges it merges or removes.
+//!
+//! [`IntervalSet::gaps`] walks the uncovered ranges inside a window, which
+//! is what a free-space allocator searches, and
+//! [`IntervalSet::overlapping`] the stored ranges meeting a query. Unlike
+//! an [`IntervalTree`], the set keeps no values and no individual
+//! intervals: it answers only which points are covered.
+//!
+//! [`IntervalTree`]: crate::interval_tree::IntervalTree
+
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// A set of points of `K` stored as disjoint half-open ranges.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IntervalSet<K> {
+    /// Start to end of each stored range.
+    ranges: BTreeMap<K, K>,
+}
+
+impl<K> Default for IntervalSet<K> {
+    fn default() -> Self {
+        IntervalSet {
+            ranges: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord + Copy> IntervalSet<K> {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored disjoint ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether the set covers no points.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Removes every range.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Adds the points of `range`, merging it with the stored ranges it
+    /// overlaps or touches. Empty ranges are ignored.
+    pub fn insert(&mut self, range: Range<K>) {
+        let Range { mut start, mut end } = range;
+        if start >= end {
+            return;
+        }
+        if let Some((&s, &e)) = self.ranges.range(..=start).next_back() {
+            if e >= start {
+                start = s;
+                end = end.max(e);
+                self.ranges.remove(&s);
+            }
+        }
+        while let Some((&s, &e)) = self.ranges.range(start..=end).next() {
+            end = end.max(e);
+            self.ranges.remove(&s);
+        }
+        self.ranges.insert(start, end);
+    }
+
+    /// Removes the points of `range`, trimming or splitting the stored
+    /// ranges it overlaps. Empty ranges are ignored.
+    pub fn remove(&mut self, range: Range<K>) {
+        let Range { start, end } = range;
+        if start >= end {
+            return;
+        }
+        if let Some((&s, &e)) = self.ranges.range(..start).next_back() {
+            if e > start {
+                self.ranges.insert(s, start);
+                if e > end {
+                    self.ranges.insert(end, e);
+                    return;
+                }
+            }
+        }
+        while let Some((&s, &e)) = self.ranges.range(start..end).next() {
+            self.ranges.remove(&s);
+            if e > end {
+                self.ranges.insert(end, e);
+                break;
+            }
+        }
+    }
+
+    /// Whether `point` is covered.
+    pub fn contains(&self, point: K) -> bool {
+        self.get(point).is_some()
+    }
+
+    /// The stored range covering `point`.
+    pub fn get(&self, point: K) -> Option<Range<K>> {
+        let (&s, &e) = self.ranges.range(..=point).next_back()?;
+        (point < e).then_some(s..e)
+    }
+
+    /// Whether every point of `range` is covered. Empty ranges are covered.
+    pub fn covers(&self, range: Range<K>) -> bool {
+        range.start >= range.end || self.get(range.start).is_some_and(|r| r.end >= range.end)
+    }
+
+    /// Whether any point of `range` is covered.
+    pub fn overlaps(&self, range: Range<K>) -> bool {
+        self.overlapping(range).next().is_some()
+    }
+
+    /// The stored ranges that share a point with `range`, in order.
+    pub fn overlapping(&self, range: Range<K>) -> impl Iterator<Item = Range<K>> + '_ {
+        let Range { start, end } = range;
+        let first = if start < end {
+            match self.ranges.range(..=start).next_back() {
+                Some((&s, &e)) if e > start => s,
+                _ => start,
+            }
+        } else {
+            end
+        };
+        let ranges = (first < end).then(|| self.ranges.range(first..end));
+        ranges.into_iter().flatten().map(|(&s, &e)| s..e)
+    }
+
+    /// The maximal uncovered ranges within `window`, in order.
+    pub fn gaps(&self, window: Range<K>) -> Gaps<'_, K> {
+        let Range { start, end } = window;
+        let cursor = self.get(start).map_or(start, |r| r.end);
+        Gaps {
+            ranges: (cursor < end).then(|| self.ranges.range(cursor..end)),
+            cursor,
+            end,
+        }
+    }
+
+    /// The least covered point.
+    pub fn first(&self) -> Option<K> {
+        self.ranges.keys().next().copied()
+    }
+
+    /// The end of the last stored range, one past the greatest covered
+    /// point.
+    pub fn end(&self) -> Option<K> {
+        self.ranges.values().next_back().copied()
+    }
+
+    /// Iterates over the stored ranges in order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = Range<K>> + ExactSizeIterator + '_ {
+        self.ranges.iter().map(|(&s, &e)| s..e)
+    }
+}
+
+impl<K: Ord + Copy> Extend<Range<K>> for IntervalSet<K> {
+    fn extend<I: IntoIterator<Item = Range<K>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+impl<K: Ord + Copy> FromIterator<Range<K>> for IntervalSet<K> {
+    fn from_iter<I: IntoIterator<Item = Range<K>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+/// An iterator over the gaps of an [`IntervalSet`] within a window.
+pub struct Gaps<'a, K> {
+    /// Stored ranges starting after `cursor` and before the window's end.
+    ranges: Option<btree_map::Range<'a, K, K>>,
+    /// Start of the next gap.
+    cursor: K,
+    end: K,
+}
+
+impl<K: Ord + Copy> Iterator for Gaps<'_, K> {
+    type Item = Range<K>;
+
+    fn next(&mut self) -> Option<Range<K>> {
+        if self.cursor >= self.end {
+            return None;
+        }
+        let start = self.cursor;
+        match self.ranges.as_mut().and_then(Iterator::next) {
+            Some((&s, &e)) => {
+                self.cursor = e;
+                Some(start..s)
+            }
+            None => {
+                self.cursor = self.end;
+                Some(start..self.end)
+            }
+        }
+    }
+}
@@ -0,0 +1,469 @@
+//! An interval skip list: a skip list of end points with intervals marked
+//! on its edges.
+//!
+//! Hanson's interval skip list (1991) keeps the distinct end points of the
+//! stored intervals in a skip list and marks each interval on a chain of
+//! skip list edges that covers it exactly, from its start to its end,
+//! taking the highest edge it can at every node on the way, so the chain
+//! has `O(log n)` edges in expectation. A node on the chain is marked too,
+//! when the interval contains its key. A stabbing query is then a single
+//! skip list search for the point: at each lane the search crosses one
+//! edge that spans the point, and collects the intervals marked there,
+//! and if the point is itself an end point it collects the marks on that
+//! node. Each interval containing the point is met exactly once, so a
+//! query costs `O(log n + m)` expected for `m` results.
+//!
+//! Adding or removing an end point splits or joins the edges around it,
+//! and the intervals marked on those edges are marked again from scratch;
+//! an interval whose end points are already present costs `O(log n)` to
+//! insert and to remove. There are no rotations and no subtree maxima to
+//! repair, which suits churn-heavy workloads such as sweep lines, where
+//! intervals come and go in the order of their end points.
+//!
+//! The same queries are answered by the [`IntervalTree`], an augmented
+//! balanced tree, and for a set of intervals that does not change by the
+//! more compact [`NcList`]. Intervals are half-open; empty ones are stored
+//! but contain no points.
+//!
+//! [`IntervalTree`]: crate::interval_tree::IntervalTree
+//! [`NcList`]: crate::nclist::NcList
+
+use std::ops::Range;
+
+use crate::rng::SplitMix64;
+
+const NONE: u32 = u32::MAX;
+
+/// The number of lanes; enough for about `4^MAX_LEVEL` end points.
+const MAX_LEVEL: usize = 24;
+
+/// Handle to an interval stored in an [`IntervalSkipList`].
+///
+/// Handles of removed intervals may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IntervalId(u32);
+
+#[derive(Clone, Debug)]
+struct Node<K> {
+    key: K,
+    /// The next node in each lane this node joins, bottom lane first.
+    next: Vec<u32>,
+    /// The intervals marked on the edge leaving this node in each lane.
+    marks: Vec<Vec<u32>>,
+    /// The intervals marked on this node.
+    eq: Vec<u32>,
+    /// The nonempty intervals starting at this key.
+    starts: Vec<u32>,
+    /// Number of nonempty intervals ending at this key.
+    ends: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<K, V> {
+    range: Range<K>,
+    value: V,
+    /// The edges, as a node and a lane, this interval is marked on.
+    edges: Vec<(u32, usize)>,
+    /// The nodes this interval is marked on.
+    nodes: Vec<u32>,
+}
+
+/// An interval skip list mapping half-open ranges of `K` to values of type
+/// `V`.
+///
+/// Several intervals may share the same bounds; each insertion gets its own
+/// handle.
+#[derive(Clone, Debug)]
+pub struct IntervalSkipList<K, V> {
+    nodes: Vec<Option<Node<K>>>,
+    free_nodes: Vec<u32>,
+    entries: Vec<Option<Entry<K, V>>>,
+    free: Vec<u32>,
+    /// The first node in each lane.
+    head: [u32; MAX_LEVEL],
+    /// Number of lanes in use.
+    level: usize,
+    len: usize,
+    rng: SplitMix64,
+}
+
+impl<K: Ord + Copy, V> Default for IntervalSkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Copy, V> IntervalSkipList<K, V> {
+    /// Creates an empty list with a fixed default seed.
+    pub fn new() -> Self {
+        Self::with_seed(0x5eed)
+    }
+
+    /// Creates an empty list whose node levels are drawn from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        IntervalSkipList {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            head: [NONE; MAX_LEVEL],
+            level: 0,
+            len: 0,
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Number of stored intervals.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no intervals are stored.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every interval.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free_nodes.clear();
+        self.entries.clear();
+        self.free.clear();
+        self.head = [NONE; MAX_LEVEL];
+        self.level = 0;
+        self.len = 0;
+    }
+
+    /// Inserts an interval, returning its handle, in `O(log n)` expected
+    /// when its end points are already present.
+    pub fn insert(&mut self, range: Range<K>, value: V) -> IntervalId {
+        let entry = Entry {
+            range: range.clone(),
+            value,
+            edges: Vec::new(),
+            nodes: Vec::new(),
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.len += 1;
+        if range.start < range.end {
+            let a = self.endpoint(range.start);
+            self.node_mut(a).starts.push(id);
+            let b = self.endpoint(range.end);
+            self.node_mut(b).ends += 1;
+            self.mark(id);
+        }
+        IntervalId(id)
+    }
+
+    /// Removes an interval, returning its bounds and value, or `None` if
+    /// the handle is stale.
+    pub fn remove(&mut self, id: IntervalId) -> Option<(Range<K>, V)> {
+        self.entries.get(id.0 as usize)?.as_ref()?;
+        let range = self.entry(id.0).range.clone();
+        if range.start < range.end {
+            self.unmark(id.0);
+            let a = self.find(&range.start);
+            forget(&mut self.node_mut(a).starts, id.0);
+            self.release(a);
+            let b = self.find(&range.end);
+            self.node_mut(b).ends -= 1;
+            self.release(b);
+        }
+        let entry = self.entries[id.0 as usize].take().expect("live entry");
+        self.free.push(id.0);
+        self.len -= 1;
+        Some((entry.range, entry.value))
+    }
+
+    /// Returns the bounds and value of an interval.
+    pub fn get(&self, id: IntervalId) -> Option<(&Range<K>, &V)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((&e.range, &e.value))
+    }
+
+    /// Returns the bounds and a mutable reference to the value of an
+    /// interval.
+    pub fn get_mut(&mut self, id: IntervalId) -> Option<(&Range<K>, &mut V)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&e.range, &mut e.value))
+    }
+
+    /// Iterates over every interval, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        self.entries.iter().enumerate().filter_map(|(i, e)| {
+            let e = e.as_ref()?;
+            Some((IntervalId(i as u32), &e.range, &e.value))
+        })
+    }
+
+    /// Iterates over the intervals containing `point`, in `O(log n + m)`
+    /// expected.
+    pub fn stabbing(&self, point: K) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        let mut found = Vec::new();
+        self.stab(&point, &mut found);
+        self.resolve(found)
+    }
+
+    /// Iterates over the intervals sharing at least one point with `range`.
+    ///
+    /// An empty `range` overlaps nothing.
+    pub fn overlapping(
+        &self,
+        range: Range<K>,
+    ) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        let mut found = Vec::new();
+        if range.start < range.end {
+            // Those containing the start, and those starting after it.
+            self.stab(&range.start, &mut found);
+            let preds = self.predecessors(&range.start);
+            let mut n = self.after(preds[0], 0);
+            if n != NONE && self.node(n).key == range.start {
+                n = self.after(n, 0);
+            }
+            while n != NONE && self.node(n).key < range.end {
+                found.extend_from_slice(&self.node(n).starts);
+                n = self.after(n, 0);
+            }
+        }
+        self.resolve(found)
+    }
+
+    /// Whether any stored interval contains `point`.
+    pub fn contains_point(&self, point: K) -> bool {
+        self.stabbing(point).next().is_some()
+    }
+
+    fn resolve(&self, ids: Vec<u32>) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        ids.into_iter().map(|id| {
+            let e = self.entry(id);
+            (IntervalId(id), &e.range, &e.value)
+        })
+    }
+
+    /// Collects the intervals containing `point` into `found`.
+    fn stab(&self, point: &K, found: &mut Vec<u32>) {
+        let mut n = NONE;
+        for l in (0..self.level).rev() {
+            let mut next = self.after(n, l);
+            while next != NONE && self.node(next).key < *point {
+                n = next;
+                next = self.after(n, l);
+            }
+            // The edge from `n` spans `point` unless it ends there; edges
+            // from the head are never marked.
+            if n != NONE && next != NONE && self.node(next).key != *point {
+                found.extend_from_slice(&self.node(n).marks[l]);
+            }
+        }
+        let next = self.after(n, 0);
+        if next != NONE && self.node(next).key == *point {
+            found.extend_from_slice(&self.node(next).eq);
+        }
+    }
+
+    /// Marks a nonempty interval on its chain of edges, from the node at
+    /// its start to the node at its end, taking the highest edge that
+    /// stays within it at each node.
+    fn mark(&mut self, id: u32) {
+        let Range { start, end } = self.entry(id).range.clone();
+        let mut n = self.find(&start);
+        let (mut edges, mut nodes) = (Vec::new(), Vec::new());
+        while self.node(n).key != end {
+            nodes.push(n);
+            self.node_mut(n).eq.push(id);
+            let l = (0..self.node(n).next.len())
+                .rev()
+                .find(|&l| {
+                    let next = self.after(n, l);
+                    next != NONE && self.node(next).key <= end
+                })
+                .expect("the end point follows the start");
+            edges.push((n, l));
+            self.node_mut(n).marks[l].push(id);
+            n = self.after(n, l);
+        }
+        let e = self.entry_mut(id);
+        e.edges = edges;
+        e.nodes = nodes;
+    }
+
+    /// Takes every mark of an interval off the list.
+    fn unmark(&mut self, id: u32) {
+        let e = self.entry_mut(id);
+        let (edges, nodes) = (std::mem::take(&mut e.edges), std::mem::take(&mut e.nodes));
+        for (n, l) in edges {
+            forget(&mut self.node_mut(n).marks[l], id);
+        }
+        for n in nodes {
+            forget(&mut self.node_mut(n).eq, id);
+        }
+    }
+
+    /// The node for the end point `key`, adding it if need be.
+    fn endpoint(&mut self, key: K) -> u32 {
+        let preds = self.predecessors(&key);
+        let at = self.after(preds[0], 0);
+        if at != NONE && self.node(at).key == key {
+            return at;
+        }
+        let height = self.random_level();
+        // The edges the new node splits lose their marks, which are put
+        // back once it is in place.
+        let mut moved = Vec::new();
+        for (l, &p) in preds.iter().enumerate().take(height) {
+            if p != NONE {
+                moved.extend_from_slice(&self.node(p).marks[l]);
+            }
+        }
+        moved.sort_unstable();
+        moved.dedup();
+        for &id in &moved {
+            self.unmark(id);
+        }
+        let node = Node {
+            key,
+            next: (0..height).map(|l| self.after(preds[l], l)).collect(),
+            marks: vec![Vec::new(); height],
+            eq: Vec::new(),
+            starts: Vec::new(),
+            ends: 0,
+        };
+        let n = match self.free_nodes.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = Some(node);
+                n
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        for (l, &p) in preds.iter().enumerate().take(height) {
+            self.set_after(p, l, n);
+        }
+        self.level = self.level.max(height);
+        for id in moved {
+            self.mark(id);
+        }
+        n
+    }
+
+    /// Removes the node `n` if no interval starts or ends at it any more,
+    /// marking again the intervals on the edges around it.
+    fn release(&mut self, n: u32) {
+        let node = self.node(n);
+        if !node.starts.is_empty() || node.ends > 0 {
+            return;
+        }
+        let preds = self.predecessors(&node.key);
+        let height = node.next.len();
+        let mut moved = node.eq.clone();
+        for (l, &p) in preds.iter().enumerate().take(height) {
+            if p != NONE {
+                moved.extend_from_slice(&self.node(p).marks[l]);
+            }
+            moved.extend_from_slice(&self.node(n).marks[l]);
+        }
+        moved.sort_unstable();
+        moved.dedup();
+        for &id in &moved {
+            self.unmark(id);
+        }
+        for (l, &p) in preds.iter().enumerate().take(height) {
+            let next = self.node(n).next[l];
+            self.set_after(p, l, next);
+        }
+        self.nodes[n as usize] = None;
+        self.free_nodes.push(n);
+        while self.level > 0 && self.head[self.level - 1] == NONE {
+            self.level -= 1;
+        }
+        for id in moved {
+            self.mark(id);
+        }
+    }
+
+    /// The node holding the end point `key`, which must be present.
+    fn find(&self, key: &K) -> u32 {
+        let preds = self.predecessors(key);
+        let n = self.after(preds[0], 0);
+        debug_assert!(n != NONE && self.node(n).key == *key);
+        n
+    }
+
+    /// The last node before `key` in each lane, or `NONE` for the head.
+    fn predecessors(&self, key: &K) -> [u32; MAX_LEVEL] {
+        let mut preds = [NONE; MAX_LEVEL];
+        let mut n = NONE;
+        for l in (0..self.level).rev() {
+            let mut next = self.after(n, l);
+            while next != NONE && self.node(next).key < *key {
+                n = next;
+                next = self.after(n, l);
+            }
+            preds[l] = n;
+        }
+        preds
+    }
+
+    fn after(&self, n: u32, l: usize) -> u32 {
+        match n {
+            NONE => self.head[l],
+            n => self.node(n).next[l],
+        }
+    }
+
+    fn set_after(&mut self, n: u32, l: usize, to: u32) {
+        match n {
+            NONE => self.head[l] = to,
+            n => self.node_mut(n).next[l] = to,
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        let bits = self.rng.next_u64();
+        (1 + bits.trailing_zeros() as usize / 2).min(MAX_LEVEL)
+    }
+
+    fn node(&self, n: u32) -> &Node<K> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<K> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+
+    fn entry(&self, id: u32) -> &Entry<K, V> {
+        self.entries[id as usize].as_ref().expect("live entry")
+    }
+
+    fn entry_mut(&mut self, id: u32) -> &mut Entry<K, V> {
+        self.entries[id as usize].as_mut().expect("live entry")
+    }
+}
+
+impl<K: Ord + Copy, V> FromIterator<(Range<K>, V)> for IntervalSkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        let mut list = IntervalSkipList::new();
+        for (range, value) in iter {
+            list.insert(range, value);
+        }
+        list
+    }
+}
+
+/// Removes one occurrence of `id` from `ids`.
+fn forget(ids: &mut Vec<u32>, id: u32) {
+    if let Some(i) = ids.iter().position(|&x| x == id) {
+        ids.swap_remove(i);
+    }
+}
@@ -0,0 +1,344 @@
+//! An interval tree: an AVL tree of half-open intervals ordered by start,
+//! with each node augmented by the largest end point in its subtree.
+//!
+//! The augmentation lets stabbing and overlap queries skip every subtree
+//! whose intervals all end before the query begins, so a query costs
+//! `O(log n + m)` for `m` results. Insertion and removal are `O(log n)`.
+
+use std::ops::Range;
+
+/// Handle to an interval stored in an [`IntervalTree`].
+///
+/// Handles of removed intervals may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IntervalId(u32);
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    range: Range<K>,
+    value: V,
+    left: u32,
+    right: u32,
+    height: u8,
+    /// Largest `range.end` in this subtree.
+    max_end: K,
+}
+
+/// An interval tree mapping half-open ranges of `K` to values of type `V`.
+///
+/// Several intervals may share the same bounds; each insertion gets its own
+/// handle.
+#[derive(Clone, Debug)]
+pub struct IntervalTree<K, V> {
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+}
+
+impl<K, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        IntervalTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+        }
+    }
+}
+
+impl<K: Ord + Copy, V> IntervalTree<K, V> {
+    /// Creates an empty tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of stored intervals.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every interval, invalidating all handles.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Inserts an interval, returning its handle.
+    ///
+    /// Empty intervals (`start >= end`) are stored but never match a query.
+    pub fn insert(&mut self, range: Range<K>, value: V) -> IntervalId {
+        let node = Node {
+            max_end: range.end,
+            range,
+            value,
+            left: NONE,
+            right: NONE,
+            height: 1,
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id as usize] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.root = self.insert_at(self.root, id);
+        self.len += 1;
+        IntervalId(id)
+    }
+
+    /// Removes an interval, returning its range and value.
+    pub fn remove(&mut self, id: IntervalId) -> Option<(Range<K>, V)> {
+        self.nodes.get(id.0 as usize)?.as_ref()?;
+        self.root = self.remove_at(self.root, id.0);
+        let node = self.nodes[id.0 as usize].take()?;
+        self.free.push(id.0);
+        self.len -= 1;
+        Some((node.range, node.value))
+    }
+
+    /// Returns the range and value of an interval.
+    pub fn get(&self, id: IntervalId) -> Option<(&Range<K>, &V)> {
+        let n = self.nodes.get(id.0 as usize)?.as_ref()?;
+        Some((&n.range, &n.value))
+    }
+
+    /// Returns the range and mutable value of an interval.
+    pub fn get_mut(&mut self, id: IntervalId) -> Option<(&Range<K>, &mut V)> {
+        let n = self.nodes.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&n.range, &mut n.value))
+    }
+
+    /// Iterates over all intervals in order of their start points.
+    pub fn iter(&self) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        let mut stack = Vec::new();
+        let mut cur = self.root;
+        std::iter::from_fn(move || {
+            while cur != NONE {
+                stack.push(cur);
+                cur = self.node(cur).left;
+            }
+            let n = stack.pop()?;
+            let node = self.node(n);
+            cur = node.right;
+            Some((IntervalId(n), &node.range, &node.value))
+        })
+    }
+
+    /// Iterates over the intervals containing `point`.
+    pub fn stabbing(&self, point: K) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        self.query(
+            move |r| r.start <= point && point < r.end,
+            move |start| start <= point,
+            point,
+        )
+    }
+
+    /// Iterates over the intervals sharing at least one point with `range`.
+    ///
+    /// An empty `range` overlaps nothing.
+    pub fn overlapping(
+        &self,
+        range: Range<K>,
+    ) -> impl Iterator<Item = (IntervalId, &Range<K>, &V)> + '_ {
+        let (start, end) = (range.start, range.end);
+        self.query(
+            move |r| r.start < end && start < r.end && r.start < r.end && start < end,
+            move |s| s < end,
+            start,
+        )
+    }
+
+    /// Whether any stored interval contains `point`.
+    pub fn contains_point(&self, point: K) -> bool {
+        self.stabbing(point).next().is_some()
+    }
+
+    /// Preorder traversal that skips subtrees ending at or before `from`, and
+    /// right subtrees whose starts fail `right_ok`.
+    fn query<'a>(
+        &'a self,
+        keep: impl Fn(&Range<K>) -> bool + 'a,
+        right_ok: impl Fn(K) -> bool + 'a,
+        from: K,
+    ) -> impl Iterator<Item = (IntervalId, &'a Range<K>, &'a V)> + 'a {
+        let mut stack = if self.root == NONE {
+            Vec::new()
+        } else {
+            vec![self.root]
+        };
+        std::iter::from_fn(move || {
+            while let Some(n) = stack.pop() {
+                let node = self.node(n);
+                if node.max_end <= from {
+                    continue;
+                }
+                if node.right != NONE && right_ok(node.range.start) {
+                    stack.push(node.right);
+                }
+                if node.left != NONE {
+                    stack.push(node.left);
+                }
+                if keep(&node.range) {
+                    return Some((IntervalId(n), &node.range, &node.value));
+                }
+            }
+            None
+        })
+    }
+
+    fn node(&self, n: u32) -> &Node<K, V> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<K, V> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+
+    fn height(&self, n: u32) -> u8 {
+        if n == NONE {
+            0
+        } else {
+            self.node(n).height
+        }
+    }
+
+    /// Orders nodes by start point, breaking ties by handle.
+    fn less(&self, a: u32, b: u32) -> bool {
+        (self.node(a).range.start, a) < (self.node(b).range.start, b)
+    }
+
+    /// Recomputes the height and augmentation of `n` from its children.
+    fn update(&mut self, n: u32) {
+        let (left, right) = (self.node(n).left, self.node(n).right);
+        let mut max_end = self.node(n).range.end;
+        for c in [left, right] {
+            if c != NONE {
+                max_end = max_end.max(self.node(c).max_end);
+            }
+        }
+        let height = 1 + self.height(left).max(self.height(right));
+        let node = self.node_mut(n);
+        node.max_end = max_end;
+        node.height = height;
+    }
+
+    fn rotate_left(&mut self, n: u32) -> u32 {
+        let r = self.node(n).right;
+        self.node_mut(n).right = self.node(r).left;
+        self.node_mut(r).left = n;
+        self.update(n);
+        self.update(r);
+        r
+    }
+
+    fn rotate_right(&mut self, n: u32) -> u32 {
+        let l = self.node(n).left;
+        self.node_mut(n).left = self.node(l).right;
+        self.node_mut(l).right = n;
+        self.update(n);
+        self.update(l);
+        l
+    }
+
+    /// Restores the AVL balance at `n`, returning the subtree's new root.
+    fn rebalance(&mut self, n: u32) -> u32 {
+        self.update(n);
+        let (left, right) = (self.node(n).left, self.node(n).right);
+        let balance = self.height(left) as i32 - self.height(right) as i32;
+        if balance > 1 {
+            let l = self.node(left);
+            if self.height(l.left) < self.height(l.right) {
+                let new_left = self.rotate_left(left);
+                self.node_mut(n).left = new_left;
+            }
+            return self.rotate_right(n);
+        }
+        if balance < -1 {
+            let r = self.node(right);
+            if self.height(r.right) < self.height(r.left) {
+                let new_right = self.rotate_right(right);
+                self.node_mut(n).right = new_right;
+            }
+            return self.rotate_left(n);
+        }
+        n
+    }
+
+    fn insert_at(&mut self, root: u32, n: u32) -> u32 {
+        if root == NONE {
+            return n;
+        }
+        if self.less(n, root) {
+            let left = self.insert_at(self.node(root).left, n);
+            self.node_mut(root).left = left;
+        } else {
+            let right = self.insert_at(self.node(root).right, n);
+            self.node_mut(root).right = right;
+        }
+        self.rebalance(root)
+    }
+
+    /// Unlinks node `n` from the subtree at `root`, returning the new root.
+    /// Nodes are relinked rather than having their contents swapped, so
+    /// handles stay attached to their intervals.
+    fn remove_at(&mut self, root: u32, n: u32) -> u32 {
+        if root == NONE {
+            return NONE;
+        }
+        if root == n {
+            let (left, right) = (self.node(n).left, self.node(n).right);
+            if left == NONE {
+                return right;
+            }
+            if right == NONE {
+                return left;
+            }
+            let (rest, successor) = self.remove_min(right);
+            let node = self.node_mut(successor);
+            node.left = left;
+            node.right = rest;
+            return self.rebalance(successor);
+        }
+        if self.less(n, root) {
+            let left = self.remove_at(self.node(root).left, n);
+            self.node_mut(root).left = left;
+        } else {
+            let right = self.remove_at(self.node(root).right, n);
+            self.node_mut(root).right = right;
+        }
+        self.rebalance(root)
+    }
+
+    /// Unlinks the leftmost node below `root`, returning the remaining
+    /// subtree and the unlinked node.
+    fn remove_min(&mut self, root: u32) -> (u32, u32) {
+        let left = self.node(root).left;
+        if left == NONE {
+            return (self.node(root).right, root);
+        }
+        let (rest, min) = self.remove_min(left);
+        self.node_mut(root).left = rest;
+        (self.rebalance(root), min)
+    }
+}
+
+impl<K: Ord + Copy, V> FromIterator<(Range<K>, V)> for IntervalTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        let mut tree = IntervalTree::new();
+        for (range, value) in iter {
+            tree.insert(range, value);
+        }
+        tree
+    }
+}
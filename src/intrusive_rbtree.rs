@@ -0,0 +1,508 @@
+//! An intrusive red-black tree over items the caller stores.
+//!
+//! The tree owns nothing but a root index and a count. Each item carries an
+//! [`RbLink`] member holding its parent, children and color, and the items
+//! live wherever the caller keeps them, in a slice passed to every
+//! operation. So inserting, removing and walking never allocate, and an item
+//! can join several trees at once by carrying one link per tree. Each tree
+//! finds its link through an [`Adapter`].
+//!
+//! Links name other items by their index in the slice rather than by
+//! pointer. That keeps the whole thing in safe code, and a slice of items
+//! with links stays valid when moved, copied or written to disk. The price is
+//! that the caller must pass the same slice to every call on a tree, and
+//! must not move linked items within it.
+//!
+//! The rebalancing is the classic one: at most two rotations per insertion
+//! and three per removal, for a height of at most `2 log n`. Items with
+//! equal keys are allowed and keep their insertion order.
+
+use std::borrow::Borrow;
+use std::marker::PhantomData;
+
+const NONE: u32 = u32::MAX;
+
+/// The tree links embedded in an item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RbLink {
+    parent: u32,
+    left: u32,
+    right: u32,
+    red: bool,
+    linked: bool,
+}
+
+impl Default for RbLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RbLink {
+    /// An unlinked link.
+    pub const fn new() -> Self {
+        RbLink {
+            parent: NONE,
+            left: NONE,
+            right: NONE,
+            red: false,
+            linked: false,
+        }
+    }
+
+    /// Whether the item is currently in a tree.
+    pub fn is_linked(&self) -> bool {
+        self.linked
+    }
+}
+
+/// How a tree finds the key and link of its items.
+pub trait Adapter {
+    /// The caller's item type.
+    type Item;
+    /// The key the items are ordered by.
+    type Key: Ord + ?Sized;
+
+    /// The item's key.
+    fn key(item: &Self::Item) -> &Self::Key;
+
+    /// The item's link for this tree.
+    fn link(item: &Self::Item) -> &RbLink;
+
+    /// The item's link for this tree, mutably.
+    fn link_mut(item: &mut Self::Item) -> &mut RbLink;
+}
+
+/// A red-black tree over a caller's slice of items, ordered by the key
+/// its [`Adapter`] reports.
+#[derive(Debug)]
+pub struct RbTree<A> {
+    root: u32,
+    len: usize,
+    _adapter: PhantomData<fn() -> A>,
+}
+
+impl<A: Adapter> Default for RbTree<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Adapter> RbTree<A> {
+    /// Creates an empty tree.
+    pub const fn new() -> Self {
+        RbTree {
+            root: NONE,
+            len: 0,
+            _adapter: PhantomData,
+        }
+    }
+
+    /// Number of linked items.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Unlinks every item.
+    pub fn clear(&mut self, items: &mut [A::Item]) {
+        // Peel off leaves, detaching each from its parent, so the walk never
+        // reads a link it has already reset.
+        let mut n = self.root;
+        while n != NONE {
+            let link = *self.link(items, n);
+            if link.left != NONE {
+                n = link.left;
+            } else if link.right != NONE {
+                n = link.right;
+            } else {
+                *self.link_mut(items, n) = RbLink::new();
+                if link.parent != NONE {
+                    let parent = self.link_mut(items, link.parent);
+                    if parent.left == n {
+                        parent.left = NONE;
+                    } else {
+                        parent.right = NONE;
+                    }
+                }
+                n = link.parent;
+            }
+        }
+        self.root = NONE;
+        self.len = 0;
+    }
+
+    /// Links `items[index]` into the tree by its key, after any items with
+    /// an equal key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item is already linked, or `index` is out of bounds or
+    /// not below `u32::MAX`.
+    pub fn insert(&mut self, items: &mut [A::Item], index: usize) {
+        assert!(!A::link(&items[index]).linked, "item is already in a tree");
+        let z = u32::try_from(index).ok().filter(|&z| z != NONE);
+        let z = z.expect("item index must be below u32::MAX");
+        let (mut parent, mut n, mut left) = (NONE, self.root, false);
+        while n != NONE {
+            parent = n;
+            left = A::key(&items[z as usize]) < A::key(&items[n as usize]);
+            n = if left {
+                self.link(items, n).left
+            } else {
+                self.link(items, n).right
+            };
+        }
+        *A::link_mut(&mut items[index]) = RbLink {
+            parent,
+            left: NONE,
+            right: NONE,
+            red: true,
+            linked: true,
+        };
+        match parent {
+            NONE => self.root = z,
+            p if left => self.link_mut(items, p).left = z,
+            p => self.link_mut(items, p).right = z,
+        }
+        self.len += 1;
+        self.insert_fixup(items, z);
+    }
+
+    /// Unlinks `items[index]` from the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item is not linked. It must be linked into this tree.
+    pub fn remove(&mut self, items: &mut [A::Item], index: usize) {
+        assert!(A::link(&items[index]).linked, "item is not in a tree");
+        let z = index as u32;
+        let zl = *self.link(items, z);
+        let (x, x_parent, removed_red);
+        if zl.left == NONE || zl.right == NONE {
+            x = if zl.left == NONE { zl.right } else { zl.left };
+            x_parent = zl.parent;
+            removed_red = zl.red;
+            self.replace_child(items, z, x);
+        } else {
+            // The successor has no left child; it takes z's place and color.
+            let y = self.extreme(items, zl.right, false);
+            let yl = *self.link(items, y);
+            removed_red = yl.red;
+            x = yl.right;
+            if yl.parent == z {
+                x_parent = y;
+            } else {
+                x_parent = yl.parent;
+                self.replace_child(items, y, x);
+                self.link_mut(items, y).right = zl.right;
+                self.link_mut(items, zl.right).parent = y;
+            }
+            self.replace_child(items, z, y);
+            let y_link = self.link_mut(items, y);
+            y_link.left = zl.left;
+            y_link.red = zl.red;
+            self.link_mut(items, zl.left).parent = y;
+        }
+        *A::link_mut(&mut items[index]) = RbLink::new();
+        self.len -= 1;
+        if !removed_red {
+            self.remove_fixup(items, x, x_parent);
+        }
+    }
+
+    /// The index of the first item whose key equals `key`.
+    pub fn find<Q>(&self, items: &[A::Item], key: &Q) -> Option<usize>
+    where
+        A::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.lower_bound(items, key)
+            .filter(|&i| A::key(&items[i]).borrow() == key)
+    }
+
+    /// The index of the first item whose key is at least `key`.
+    pub fn lower_bound<Q>(&self, items: &[A::Item], key: &Q) -> Option<usize>
+    where
+        A::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.bound(items, |k| k.borrow() >= key)
+    }
+
+    /// The index of the first item whose key is above `key`.
+    pub fn upper_bound<Q>(&self, items: &[A::Item], key: &Q) -> Option<usize>
+    where
+        A::Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.bound(items, |k| k.borrow() > key)
+    }
+
+    /// The index of the item with the smallest key.
+    pub fn first(&self, items: &[A::Item]) -> Option<usize> {
+        index(self.first_index(items))
+    }
+
+    /// The index of the item with the largest key.
+    pub fn last(&self, items: &[A::Item]) -> Option<usize> {
+        index(self.extreme(items, self.root, true))
+    }
+
+    /// The index of the item after `items[index]` in key order.
+    pub fn next(&self, items: &[A::Item], index: usize) -> Option<usize> {
+        self::index(self.successor(items, index as u32))
+    }
+
+    /// The index of the item before `items[index]` in key order.
+    pub fn prev(&self, items: &[A::Item], index: usize) -> Option<usize> {
+        let n = index as u32;
+        let left = self.link(items, n).left;
+        if left != NONE {
+            return self::index(self.extreme(items, left, true));
+        }
+        let mut n = n;
+        loop {
+            let p = self.link(items, n).parent;
+            if p == NONE || self.link(items, p).right == n {
+                return self::index(p);
+            }
+            n = p;
+        }
+    }
+
+    /// Iterates over the linked items in key order, with their indices.
+    pub fn iter<'a>(
+        &'a self,
+        items: &'a [A::Item],
+    ) -> impl Iterator<Item = (usize, &'a A::Item)> + 'a {
+        let mut n = self.first_index(items);
+        std::iter::from_fn(move || {
+            if n == NONE {
+                return None;
+            }
+            let i = n as usize;
+            n = self.successor(items, n);
+            Some((i, &items[i]))
+        })
+    }
+
+    /// The first item, in key order, whose key satisfies `above`, a
+    /// predicate false for a prefix of the keys and true for the rest.
+    fn bound(&self, items: &[A::Item], above: impl Fn(&A::Key) -> bool) -> Option<usize> {
+        let (mut n, mut best) = (self.root, NONE);
+        while n != NONE {
+            if above(A::key(&items[n as usize])) {
+                best = n;
+                n = self.link(items, n).left;
+            } else {
+                n = self.link(items, n).right;
+            }
+        }
+        index(best)
+    }
+
+    fn first_index(&self, items: &[A::Item]) -> u32 {
+        self.extreme(items, self.root, false)
+    }
+
+    fn successor(&self, items: &[A::Item], mut n: u32) -> u32 {
+        let right = self.link(items, n).right;
+        if right != NONE {
+            return self.extreme(items, right, false);
+        }
+        loop {
+            let p = self.link(items, n).parent;
+            if p == NONE || self.link(items, p).left == n {
+                return p;
+            }
+            n = p;
+        }
+    }
+
+    /// The leftmost item under `n`, or with `rightmost` set, the rightmost.
+    fn extreme(&self, items: &[A::Item], mut n: u32, rightmost: bool) -> u32 {
+        if n == NONE {
+            return NONE;
+        }
+        loop {
+            let link = self.link(items, n);
+            let next = if rightmost { link.right } else { link.left };
+            if next == NONE {
+                return n;
+            }
+            n = next;
+        }
+    }
+
+    fn insert_fixup(&mut self, items: &mut [A::Item], mut z: u32) {
+        loop {
+            let p = self.link(items, z).parent;
+            if !self.is_red(items, p) {
+                break;
+            }
+            // A red parent is never the root, so the grandparent exists.
+            let g = self.link(items, p).parent;
+            let parent_is_left = self.link(items, g).left == p;
+            let uncle = if parent_is_left {
+                self.link(items, g).right
+            } else {
+                self.link(items, g).left
+            };
+            if self.is_red(items, uncle) {
+                self.link_mut(items, p).red = false;
+                self.link_mut(items, uncle).red = false;
+                self.link_mut(items, g).red = true;
+                z = g;
+                continue;
+            }
+            let mut p = p;
+            if parent_is_left {
+                if self.link(items, p).right == z {
+                    self.rotate_left(items, p);
+                    p = z;
+                }
+                self.rotate_right(items, g);
+            } else {
+                if self.link(items, p).left == z {
+                    self.rotate_right(items, p);
+                    p = z;
+                }
+                self.rotate_left(items, g);
+            }
+            self.link_mut(items, p).red = false;
+            self.link_mut(items, g).red = true;
+            break;
+        }
+        let root = self.root;
+        self.link_mut(items, root).red = false;
+    }
+
+    /// Restores the black heights after a black node was removed above `x`,
+    /// a child of `parent`; either may be `NONE`.
+    fn remove_fixup(&mut self, items: &mut [A::Item], mut x: u32, mut parent: u32) {
+        while x != self.root && !self.is_red(items, x) {
+            let is_left = self.link(items, parent).left == x;
+            let sibling = move |tree: &Self, items: &[A::Item]| {
+                let p = tree.link(items, parent);
+                if is_left {
+                    p.right
+                } else {
+                    p.left
+                }
+            };
+            let mut w = sibling(self, items);
+            if self.is_red(items, w) {
+                self.link_mut(items, w).red = false;
+                self.link_mut(items, parent).red = true;
+                if is_left {
+                    self.rotate_left(items, parent);
+                } else {
+                    self.rotate_right(items, parent);
+                }
+                w = sibling(self, items);
+            }
+            let (near, far) = {
+                let wl = self.link(items, w);
+                if is_left {
+                    (wl.left, wl.right)
+                } else {
+                    (wl.right, wl.left)
+                }
+            };
+            if !self.is_red(items, near) && !self.is_red(items, far) {
+                self.link_mut(items, w).red = true;
+                x = parent;
+                parent = self.link(items, x).parent;
+                continue;
+            }
+            let mut far = far;
+            if !self.is_red(items, far) {
+                self.link_mut(items, near).red = false;
+                self.link_mut(items, w).red = true;
+                if is_left {
+                    self.rotate_right(items, w);
+                } else {
+                    self.rotate_left(items, w);
+                }
+                far = w;
+                w = sibling(self, items);
+            }
+            let parent_red = self.link(items, parent).red;
+            self.link_mut(items, w).red = parent_red;
+            self.link_mut(items, parent).red = false;
+            self.link_mut(items, far).red = false;
+            if is_left {
+                self.rotate_left(items, parent);
+            } else {
+                self.rotate_right(items, parent);
+            }
+            x = self.root;
+        }
+        if x != NONE {
+            self.link_mut(items, x).red = false;
+        }
+    }
+
+    /// Rotates the right child of `x` above it.
+    fn rotate_left(&mut self, items: &mut [A::Item], x: u32) {
+        let y = self.link(items, x).right;
+        let moved = self.link(items, y).left;
+        self.link_mut(items, x).right = moved;
+        if moved != NONE {
+            self.link_mut(items, moved).parent = x;
+        }
+        self.replace_child(items, x, y);
+        self.link_mut(items, y).left = x;
+        self.link_mut(items, x).parent = y;
+    }
+
+    /// Rotates the left child of `x` above it.
+    fn rotate_right(&mut self, items: &mut [A::Item], x: u32) {
+        let y = self.link(items, x).left;
+        let moved = self.link(items, y).right;
+        self.link_mut(items, x).left = moved;
+        if moved != NONE {
+            self.link_mut(items, moved).parent = x;
+        }
+        self.replace_child(items, x, y);
+        self.link_mut(items, y).right = x;
+        self.link_mut(items, x).parent = y;
+    }
+
+    /// Points `u`'s parent, or the root, at `v` instead, and gives `v` that
+    /// parent.
+    fn replace_child(&mut self, items: &mut [A::Item], u: u32, v: u32) {
+        let p = self.link(items, u).parent;
+        if p == NONE {
+            self.root = v;
+        } else if self.link(items, p).left == u {
+            self.link_mut(items, p).left = v;
+        } else {
+            self.link_mut(items, p).right = v;
+        }
+        if v != NONE {
+            self.link_mut(items, v).parent = p;
+        }
+    }
+
+    fn is_red(&self, items: &[A::Item], n: u32) -> bool {
+        n != NONE && self.link(items, n).red
+    }
+
+    fn link<'a>(&self, items: &'a [A::Item], n: u32) -> &'a RbLink {
+        A::link(&items[n as usize])
+    }
+
+    fn link_mut<'a>(&self, items: &'a mut [A::Item], n: u32) -> &'a mut RbLink {
+        A::link_mut(&mut items[n as usize])
+    }
+}
+
+fn index(n: u32) -> Option<usize> {
+    (n != NONE).then_some(n as usize)
+}
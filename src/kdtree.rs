@@ -0,0 +1,467 @@
+//! A k-d tree over `N`-dimensional points.
+//!
+//! The tree is built once from a batch of points by recursive median
+//! splitting along the axis of greatest spread, and is stored implicitly: the
+//! points are permuted in place so that every subrange `lo..hi` is a subtree
+//! whose splitting point sits at `(lo + hi) / 2`. There are no child pointers
+//! and no per-node allocation beyond one byte recording the split axis.
+//!
+//! The tree is at its best when built once and queried many times, but it
+//! also takes occasional updates. Insertions collect in a small buffer and
+//! removals only mark their point, until a rebuild folds both in. Queries
+//! return each point's index, which stays fixed across rebuilds; use
+//! [`KdTree::get`] to recover the point and its data.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::spatial_index::ApproxNearest;
+use crate::util::Total;
+
+const NONE: u32 = u32::MAX;
+
+/// A k-d tree mapping points in `N` dimensions to data of type `T`.
+#[derive(Clone, Debug)]
+pub struct KdTree<T, const N: usize> {
+    /// Points in storage order: the implicit tree, then the unsorted tail.
+    points: Vec<[f64; N]>,
+    /// Data in storage order; `None` marks a removed point that still
+    /// splits its subtree.
+    data: Vec<Option<T>>,
+    /// Split axis of the node whose median is at each index.
+    axes: Vec<u8>,
+    /// Index of the point at each storage position.
+    ids: Vec<u32>,
+    /// Storage position of each index, `NONE` if free.
+    slots: Vec<u32>,
+    free: Vec<u32>,
+    /// Length of the implicit tree prefix.
+    built: usize,
+    /// Removed points still inside the implicit tree.
+    dead: usize,
+}
+
+impl<T, const N: usize> Default for KdTree<T, N> {
+    fn default() -> Self {
+        KdTree {
+            points: Vec::new(),
+            data: Vec::new(),
+            axes: Vec::new(),
+            ids: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            built: 0,
+            dead: 0,
+        }
+    }
+}
+
+impl<T, const N: usize> KdTree<T, N> {
+    /// Builds a tree from `(point, data)` pairs in `O(n log n)`. Each point's
+    /// index is its position in `items`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero or exceeds 255, or if any coordinate is NaN.
+    pub fn build(items: Vec<([f64; N], T)>) -> Self {
+        assert!(
+            N > 0 && N <= u8::MAX as usize,
+            "dimension must be in 1..=255"
+        );
+        assert!(
+            items.iter().all(|(p, _)| p.iter().all(|c| !c.is_nan())),
+            "k-d tree coordinates must not be NaN"
+        );
+        let mut tree = KdTree {
+            slots: vec![NONE; items.len()],
+            ..Self::default()
+        };
+        tree.arrange(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, (p, t))| (p, (i as u32, t)))
+                .collect(),
+        );
+        tree
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.points.len() - self.dead
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The point and data stored at `index`.
+    pub fn get(&self, index: usize) -> Option<(&[f64; N], &T)> {
+        let pos = self.position(index)?;
+        Some((&self.points[pos], self.data[pos].as_ref()?))
+    }
+
+    /// Mutable access to the data stored at `index`.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let pos = self.position(index)?;
+        self.data[pos].as_mut()
+    }
+
+    /// Iterates over all points in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[f64; N], &T)> + '_ {
+        (0..self.points.len()).filter_map(move |pos| self.entry(pos))
+    }
+
+    /// Inserts a point, returning its index. Indices of removed points may
+    /// be reused by later insertions.
+    ///
+    /// New points wait in an unsorted buffer that queries scan linearly; the
+    /// tree is rebuilt once the buffer outgrows the square root of the
+    /// tree's size, so the tree suits occasional updates rather than
+    /// constant churn.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any coordinate is NaN.
+    pub fn insert(&mut self, point: [f64; N], data: T) -> usize {
+        assert!(
+            point.iter().all(|c| !c.is_nan()),
+            "k-d tree coordinates must not be NaN"
+        );
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.slots.push(NONE);
+                (self.slots.len() - 1) as u32
+            }
+        };
+        self.slots[id as usize] = self.points.len() as u32;
+        self.points.push(point);
+        self.data.push(Some(data));
+        self.axes.push(0);
+        self.ids.push(id);
+        let tail = self.points.len() - self.built;
+        if tail > 32 && tail * tail > self.len() {
+            self.rebuild();
+        }
+        id as usize
+    }
+
+    /// Removes the point at `index`, returning it and its data.
+    ///
+    /// Points removed from the tree proper are only marked, and the tree is
+    /// rebuilt once half of it is marked.
+    pub fn remove(&mut self, index: usize) -> Option<([f64; N], T)> {
+        let pos = self.position(index)?;
+        self.slots[index] = NONE;
+        self.free.push(index as u32);
+        if pos >= self.built {
+            let point = self.points.swap_remove(pos);
+            let data = self.data.swap_remove(pos).expect("tail points are live");
+            self.axes.swap_remove(pos);
+            self.ids.swap_remove(pos);
+            if let Some(&moved) = self.ids.get(pos) {
+                self.slots[moved as usize] = pos as u32;
+            }
+            return Some((point, data));
+        }
+        let data = self.data[pos].take().expect("positions hold live points");
+        let point = self.points[pos];
+        self.dead += 1;
+        if 2 * self.dead > self.built {
+            self.rebuild();
+        }
+        Some((point, data))
+    }
+
+    /// Rebuilds the tree from its live points, folding in buffered
+    /// insertions and dropping removed points. Indices are unaffected.
+    pub fn rebuild(&mut self) {
+        let points = std::mem::take(&mut self.points);
+        let data = std::mem::take(&mut self.data);
+        let ids = std::mem::take(&mut self.ids);
+        let items = points
+            .into_iter()
+            .zip(data)
+            .zip(ids)
+            .filter_map(|((p, t), id)| Some((p, (id, t?))))
+            .collect();
+        self.arrange(items);
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// Euclidean distances.
+    pub fn nearest(&self, query: &[f64; N], k: usize) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        self.nearest_in(0, self.built, query, k, &mut best);
+        for pos in self.built..self.points.len() {
+            offer(
+                &mut best,
+                k,
+                distance_squared(&self.points[pos], query),
+                pos,
+            );
+        }
+        let mut out: Vec<_> = best
+            .into_iter()
+            .map(|(d2, pos): (Total, usize)| (self.ids[pos] as usize, d2.0.sqrt()))
+            .collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one(&self, query: &[f64; N]) -> Option<(usize, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Best-first search for the `k` points nearest to `query` that visits
+    /// at most `max_visits` tree nodes, in order of their distance bound
+    /// from `query`. Points still in the insertion buffer are always
+    /// examined. The result reports how far the search got and whether it
+    /// is exact, trading a small error rate for large speedups in higher
+    /// dimensions, where the exact search visits much of the tree.
+    pub fn nearest_approx(
+        &self,
+        query: &[f64; N],
+        k: usize,
+        max_visits: usize,
+    ) -> ApproxNearest<usize> {
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        if k > 0 {
+            if self.built > 0 {
+                frontier.push(Reverse((Total(0.0), 0, self.built)));
+            }
+            for pos in self.built..self.points.len() {
+                offer(
+                    &mut best,
+                    k,
+                    distance_squared(&self.points[pos], query),
+                    pos,
+                );
+            }
+        }
+        let mut visited = 0;
+        while let Some(&Reverse((Total(bound), lo, hi))) = frontier.peek() {
+            if (best.len() == k && bound > worst(&best)) || visited == max_visits {
+                break;
+            }
+            frontier.pop();
+            visited += 1;
+            let mid = (lo + hi) / 2;
+            let p = &self.points[mid];
+            if self.data[mid].is_some() {
+                offer(&mut best, k, distance_squared(p, query), mid);
+            }
+            // The far side is at least as far as the splitting plane.
+            let axis = self.axes[mid] as usize;
+            let diff = query[axis] - p[axis];
+            let (near, far) = if diff < 0.0 {
+                ((lo, mid), (mid + 1, hi))
+            } else {
+                ((mid + 1, hi), (lo, mid))
+            };
+            if near.0 < near.1 {
+                frontier.push(Reverse((Total(bound), near.0, near.1)));
+            }
+            if far.0 < far.1 {
+                frontier.push(Reverse((Total(bound.max(diff * diff)), far.0, far.1)));
+            }
+        }
+        let bound = frontier.peek().map_or(f64::INFINITY, |r| r.0 .0 .0);
+        let exact = frontier.is_empty() || (best.len() == k && bound >= worst(&best));
+        let mut neighbors: Vec<_> = best
+            .into_iter()
+            .map(|(d2, pos): (Total, usize)| (self.ids[pos] as usize, d2.0.sqrt()))
+            .collect();
+        neighbors.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ApproxNearest {
+            neighbors,
+            bound: bound.sqrt(),
+            exact,
+            visited,
+        }
+    }
+
+    fn nearest_in(
+        &self,
+        lo: usize,
+        hi: usize,
+        q: &[f64; N],
+        k: usize,
+        best: &mut BinaryHeap<(Total, usize)>,
+    ) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let p = &self.points[mid];
+        if self.data[mid].is_some() {
+            offer(best, k, distance_squared(p, q), mid);
+        }
+        let axis = self.axes[mid] as usize;
+        let diff = q[axis] - p[axis];
+        let (near, far) = if diff < 0.0 {
+            ((lo, mid), (mid + 1, hi))
+        } else {
+            ((mid + 1, hi), (lo, mid))
+        };
+        self.nearest_in(near.0, near.1, q, k, best);
+        if best.len() < k || diff * diff < worst(best) {
+            self.nearest_in(far.0, far.1, q, k, best);
+        }
+    }
+
+    /// Iterates over the points within `radius` of `center`.
+    pub fn within_radius<'a>(
+        &'a self,
+        center: &'a [f64; N],
+        radius: f64,
+    ) -> impl Iterator<Item = (usize, &'a [f64; N], &'a T)> + 'a {
+        let r2 = radius * radius;
+        self.query(
+            move |axis, split| {
+                (
+                    center[axis] - radius <= split,
+                    center[axis] + radius >= split,
+                )
+            },
+            move |p| distance_squared(p, center) <= r2,
+        )
+    }
+
+    /// Iterates over the points inside the closed box `min..=max`.
+    pub fn within_box<'a>(
+        &'a self,
+        min: &'a [f64; N],
+        max: &'a [f64; N],
+    ) -> impl Iterator<Item = (usize, &'a [f64; N], &'a T)> + 'a {
+        self.query(
+            move |axis, split| (min[axis] <= split, max[axis] >= split),
+            move |p| (0..N).all(|a| min[a] <= p[a] && p[a] <= max[a]),
+        )
+    }
+
+    /// Depth-first traversal of the tree, then a scan of the buffer.
+    /// `sides` reports, for a node's split axis and value, whether the
+    /// query may reach below and above the split.
+    fn query<'a>(
+        &'a self,
+        sides: impl Fn(usize, f64) -> (bool, bool) + 'a,
+        keep: impl Fn(&[f64; N]) -> bool + 'a,
+    ) -> impl Iterator<Item = (usize, &'a [f64; N], &'a T)> + 'a {
+        let mut stack = vec![(0, self.built)];
+        let mut tail = self.built..self.points.len();
+        std::iter::from_fn(move || {
+            while let Some((lo, hi)) = stack.pop() {
+                if lo >= hi {
+                    continue;
+                }
+                let mid = (lo + hi) / 2;
+                let p = &self.points[mid];
+                let axis = self.axes[mid] as usize;
+                let (below, above) = sides(axis, p[axis]);
+                if above {
+                    stack.push((mid + 1, hi));
+                }
+                if below {
+                    stack.push((lo, mid));
+                }
+                if keep(p) {
+                    if let Some(entry) = self.entry(mid) {
+                        return Some(entry);
+                    }
+                }
+            }
+            tail.by_ref()
+                .find(|&pos| keep(&self.points[pos]))
+                .and_then(|pos| self.entry(pos))
+        })
+    }
+
+    /// Lays out `(point, (index, data))` items as a fresh implicit tree.
+    fn arrange(&mut self, mut items: Vec<([f64; N], (u32, T))>) {
+        let mut axes = vec![0u8; items.len()];
+        split(&mut items, &mut axes);
+        self.axes = axes;
+        self.points = Vec::with_capacity(items.len());
+        self.data = Vec::with_capacity(items.len());
+        self.ids = Vec::with_capacity(items.len());
+        for (pos, (p, (id, t))) in items.into_iter().enumerate() {
+            self.slots[id as usize] = pos as u32;
+            self.points.push(p);
+            self.data.push(Some(t));
+            self.ids.push(id);
+        }
+        self.built = self.points.len();
+        self.dead = 0;
+    }
+
+    fn position(&self, index: usize) -> Option<usize> {
+        let pos = *self.slots.get(index)?;
+        (pos != NONE).then_some(pos as usize)
+    }
+
+    fn entry(&self, pos: usize) -> Option<(usize, &[f64; N], &T)> {
+        let data = self.data[pos].as_ref()?;
+        Some((self.ids[pos] as usize, &self.points[pos], data))
+    }
+}
+
+impl<T, const N: usize> FromIterator<([f64; N], T)> for KdTree<T, N> {
+    fn from_iter<I: IntoIterator<Item = ([f64; N], T)>>(iter: I) -> Self {
+        KdTree::build(iter.into_iter().collect())
+    }
+}
+
+/// Arranges `items` into implicit k-d order, recording split axes.
+fn split<T, const N: usize>(items: &mut [([f64; N], T)], axes: &mut [u8]) {
+    if items.len() <= 1 {
+        return;
+    }
+    let axis = widest_axis(items);
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+    axes[mid] = axis as u8;
+    let (left, rest) = items.split_at_mut(mid);
+    let (left_axes, rest_axes) = axes.split_at_mut(mid);
+    split(left, left_axes);
+    split(&mut rest[1..], &mut rest_axes[1..]);
+}
+
+fn widest_axis<T, const N: usize>(items: &[([f64; N], T)]) -> usize {
+    let mut lo = [f64::INFINITY; N];
+    let mut hi = [f64::NEG_INFINITY; N];
+    for (p, _) in items {
+        for a in 0..N {
+            lo[a] = lo[a].min(p[a]);
+            hi[a] = hi[a].max(p[a]);
+        }
+    }
+    (0..N)
+        .max_by(|&a, &b| (hi[a] - lo[a]).total_cmp(&(hi[b] - lo[b])))
+        .unwrap_or(0)
+}
+
+/// Keeps `pos` among the `k` best candidates if it is close enough.
+fn offer(best: &mut BinaryHeap<(Total, usize)>, k: usize, d2: f64, pos: usize) {
+    if best.len() < k {
+        best.push((Total(d2), pos));
+    } else if d2 < worst(best) {
+        best.pop();
+        best.push((Total(d2), pos));
+    }
+}
+
+fn worst(best: &BinaryHeap<(Total, usize)>) -> f64 {
+    best.peek().map_or(f64::INFINITY, |b| b.0 .0)
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
@@ -0,0 +1,409 @@
+//! Leftist and skew heaps, with handles and decrease-key.
+//!
+//! Both are binary trees in heap order whose only interesting operation
+//! is meld: walk down the right spines of the two trees, merging them as
+//! sorted lists, so that each node on the merged spine takes the smaller
+//! of the two candidates as its right child. Push melds with a single
+//! node and pop melds the root's two children, so everything costs what
+//! meld costs.
+//!
+//! A leftist heap (Crane, 1972) stores in every node the length of its
+//! right spine, its rank, and keeps the lower-ranked child on the right.
+//! The right spine is then at most `log2(n + 1)` nodes long, and meld walks
+//! two of them, so it is `O(log n)` in the worst case. A skew heap
+//! (Sleator and Tarjan, 1986) keeps no rank and instead swaps the children
+//! of every node on the merged spine, which makes meld `O(log n)`
+//! amortized with one less field and no bookkeeping on the way back up.
+//!
+//! Neither has the pairing heap's `O(1)` push, but their melds are both
+//! short and simple, which suits workloads dominated by meld, such as
+//! merging the queues of a divide-and-conquer or of a leftist-style
+//! event simulation. Like the [`PairingHeap`] they hand out a [`Handle`]
+//! for every item and implement [`MeldableHeap`]. Decrease-key and remove
+//! cut the item's subtree out and meld it back, restoring ranks up the
+//! path in a leftist heap. The skew heap's amortized analysis does not
+//! cover these cuts, though with random keys they cost the same.
+//!
+//! Nodes live in an arena linked by index, each with a parent link so a
+//! handle can find its place in the tree. Melding moves the smaller heap's
+//! nodes into the larger heap's arena before melding the trees; a node
+//! only moves into an arena at least twice the size, so over any run of
+//! melds each node moves at most `log2 n` times.
+//!
+//! [`PairingHeap`]: crate::pairing_heap::PairingHeap
+//! [`MeldableHeap`]: crate::heap::MeldableHeap
+
+use crate::heap_arena::{shift, Arena, Key, Links, NONE};
+
+/// Handle to an item stored in a [`LeftistHeap`] or [`SkewHeap`].
+///
+/// A handle stays valid until its item is removed, including across
+/// melds into or from the heap. After that it is stale: lookups through it
+/// return `None` even once the slot holds another item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle(Key);
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    item: T,
+    left: u32,
+    right: u32,
+    parent: u32,
+    /// Length of the right spine, for a leftist heap; unused in a skew
+    /// heap.
+    rank: u32,
+}
+
+impl<T> Links for Node<T> {
+    fn shift(&mut self, offset: u32) {
+        shift(&mut self.left, offset);
+        shift(&mut self.right, offset);
+        shift(&mut self.parent, offset);
+    }
+}
+
+/// The arena and tree shared by both heaps; `SKEW` picks the meld.
+#[derive(Clone, Debug)]
+struct Forest<T, const SKEW: bool> {
+    nodes: Arena<Node<T>>,
+    root: u32,
+}
+
+impl<T, const SKEW: bool> Forest<T, SKEW> {
+    fn new() -> Self {
+        Forest {
+            nodes: Arena::new(),
+            root: NONE,
+        }
+    }
+
+    /// The item behind `handle`, if it is still in the heap.
+    fn get(&self, handle: Handle) -> Option<&T> {
+        let n = self.nodes.find(handle.0)?;
+        Some(&self.nodes[n].item)
+    }
+
+    fn node(&mut self, n: u32) -> &mut Node<T> {
+        &mut self.nodes[n]
+    }
+
+    fn rank(&self, n: u32) -> u32 {
+        if n == NONE {
+            0
+        } else {
+            self.nodes[n].rank
+        }
+    }
+
+    fn set_parent(&mut self, n: u32, parent: u32) {
+        if n != NONE {
+            self.node(n).parent = parent;
+        }
+    }
+
+    /// Puts `child` in whichever slot of `parent` held `old`.
+    fn replace_child(&mut self, parent: u32, old: u32, child: u32) {
+        let p = self.node(parent);
+        if p.left == old {
+            p.left = child;
+        } else {
+            p.right = child;
+        }
+        self.set_parent(child, parent);
+    }
+
+    /// Restores ranks from `n` up, after one of its subtrees changed. Stops
+    /// at the first node whose rank is unchanged, above which nothing is
+    /// affected.
+    fn fix_ranks(&mut self, mut n: u32) {
+        if SKEW {
+            return;
+        }
+        while n != NONE {
+            let Node { left, right, .. } = self.nodes[n];
+            let (rl, rr) = (self.rank(left), self.rank(right));
+            let node = self.node(n);
+            if rl < rr {
+                node.left = right;
+                node.right = left;
+            }
+            let rank = rl.min(rr) + 1;
+            if node.rank == rank {
+                return;
+            }
+            node.rank = rank;
+            n = node.parent;
+        }
+    }
+
+    /// The slot of the item behind `handle`.
+    fn find(&self, handle: Handle) -> u32 {
+        let n = self.nodes.find(handle.0);
+        n.expect("handle refers to a removed item")
+    }
+}
+
+impl<T: Ord, const SKEW: bool> Forest<T, SKEW> {
+    fn less(&self, a: u32, b: u32) -> bool {
+        self.nodes[a].item < self.nodes[b].item
+    }
+
+    /// Melds two detached trees, returning the new root.
+    fn meld_trees(&mut self, a: u32, b: u32) -> u32 {
+        if a == NONE {
+            return b;
+        }
+        if b == NONE {
+            return a;
+        }
+        let (root, mut other) = if self.less(b, a) { (b, a) } else { (a, b) };
+        // Merge the right spines, `tail` being the last node placed.
+        let mut tail = root;
+        loop {
+            let right = self.nodes[tail].right;
+            if right == NONE {
+                self.node(tail).right = other;
+                self.set_parent(other, tail);
+                break;
+            }
+            let next = if self.less(other, right) {
+                std::mem::replace(&mut other, right)
+            } else {
+                right
+            };
+            self.node(tail).right = next;
+            self.set_parent(next, tail);
+            tail = next;
+        }
+        // Walk back up the merged spine.
+        let mut n = tail;
+        loop {
+            let Node { left, right, .. } = self.nodes[n];
+            let (rl, rr) = (self.rank(left), self.rank(right));
+            let node = self.node(n);
+            if SKEW || rl < rr {
+                node.left = right;
+                node.right = left;
+            }
+            node.rank = rl.min(rr) + 1;
+            if n == root {
+                return root;
+            }
+            n = self.nodes[n].parent;
+        }
+    }
+
+    fn push(&mut self, item: T) -> Handle {
+        let (n, key) = self.nodes.insert(Node {
+            item,
+            left: NONE,
+            right: NONE,
+            parent: NONE,
+            rank: 1,
+        });
+        self.root = self.meld_trees(self.root, n);
+        Handle(key)
+    }
+
+    /// Unlinks `n` from the tree, putting the meld of its children in its
+    /// place, and returns its item.
+    fn unlink(&mut self, n: u32) -> T {
+        let Node {
+            left,
+            right,
+            parent,
+            ..
+        } = self.nodes[n];
+        self.set_parent(left, NONE);
+        self.set_parent(right, NONE);
+        let sub = self.meld_trees(left, right);
+        if parent == NONE {
+            self.root = sub;
+            self.set_parent(sub, NONE);
+        } else {
+            self.replace_child(parent, n, sub);
+            self.fix_ranks(parent);
+        }
+        self.nodes.remove(n).item
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        (self.root != NONE).then(|| self.unlink(self.root))
+    }
+
+    fn decrease_key(&mut self, handle: Handle, item: T) {
+        let n = self.find(handle);
+        let slot = &mut self.node(n).item;
+        assert!(item <= *slot, "decrease_key must not increase the item");
+        *slot = item;
+        let parent = self.nodes[n].parent;
+        if parent == NONE || !self.less(n, parent) {
+            return;
+        }
+        // Cut the subtree out and meld it back in at the root.
+        self.replace_child(parent, n, NONE);
+        self.fix_ranks(parent);
+        self.node(n).parent = NONE;
+        self.root = self.meld_trees(self.root, n);
+    }
+
+    fn remove(&mut self, handle: Handle) -> T {
+        let n = self.find(handle);
+        self.unlink(n)
+    }
+
+    fn meld(&mut self, other: Self) {
+        let (mut ours, mut theirs) = (self.root, other.root);
+        let (a, b) = self.nodes.meld(other.nodes);
+        shift(&mut ours, a);
+        shift(&mut theirs, b);
+        self.root = self.meld_trees(ours, theirs);
+    }
+}
+
+macro_rules! meldable_heap {
+    ($(#[$doc:meta])* $name:ident, $skew:literal) => {
+        $(#[$doc])*
+        ///
+        /// A clone holds the same items under new handles: handles into
+        /// the original do not reach the clone's items.
+        #[derive(Clone, Debug)]
+        pub struct $name<T> {
+            forest: Forest<T, $skew>,
+        }
+
+        impl<T> Default for $name<T> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<T> $name<T> {
+            /// Creates an empty heap.
+            pub fn new() -> Self {
+                $name {
+                    forest: Forest::new(),
+                }
+            }
+
+            /// Number of items.
+            pub fn len(&self) -> usize {
+                self.forest.nodes.len()
+            }
+
+            /// Whether the heap is empty.
+            pub fn is_empty(&self) -> bool {
+                self.forest.nodes.len() == 0
+            }
+
+            /// Removes every item, invalidating all handles.
+            pub fn clear(&mut self) {
+                *self = Self::new();
+            }
+
+            /// The smallest item.
+            pub fn peek(&self) -> Option<&T> {
+                let root = self.forest.root;
+                (root != NONE).then(|| &self.forest.nodes[root].item)
+            }
+
+            /// The handle of the smallest item.
+            pub fn peek_handle(&self) -> Option<Handle> {
+                let root = self.forest.root;
+                (root != NONE).then(|| Handle(self.forest.nodes.key(root)))
+            }
+
+            /// The item behind `handle`, or `None` if it has been removed.
+            pub fn get(&self, handle: Handle) -> Option<&T> {
+                self.forest.get(handle)
+            }
+
+            /// Whether the item behind `handle` is still in the heap.
+            pub fn contains(&self, handle: Handle) -> bool {
+                self.get(handle).is_some()
+            }
+
+            /// Iterates over the items in no particular order.
+            pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+                self.forest.nodes.iter().map(|n| &n.item)
+            }
+        }
+
+        impl<T: Ord> $name<T> {
+            /// Adds an item in `O(log n)`, returning its handle.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the heap already holds `u32::MAX - 1` nodes.
+            pub fn push(&mut self, item: T) -> Handle {
+                self.forest.push(item)
+            }
+
+            /// Removes and returns the smallest item, in `O(log n)`.
+            pub fn pop(&mut self) -> Option<T> {
+                self.forest.pop()
+            }
+
+            /// Replaces the item behind `handle` with a smaller or equal
+            /// one.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the item has been removed or `item` is greater
+            /// than it.
+            pub fn decrease_key(&mut self, handle: Handle, item: T) {
+                self.forest.decrease_key(handle, item)
+            }
+
+            /// Removes and returns the item behind `handle`.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the item has been removed.
+            pub fn remove(&mut self, handle: Handle) -> T {
+                self.forest.remove(handle)
+            }
+
+            /// Moves every item of `other` into this heap, in `O(log n)`
+            /// plus moving the smaller heap's nodes into the larger heap's
+            /// arena.
+            ///
+            /// Handles into both heaps stay valid.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the arenas together hold `u32::MAX - 1` nodes or
+            /// more.
+            pub fn meld(&mut self, other: Self) {
+                self.forest.meld(other.forest)
+            }
+        }
+
+        impl<T: Ord> Extend<T> for $name<T> {
+            fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+                for item in iter {
+                    self.push(item);
+                }
+            }
+        }
+
+        impl<T: Ord> FromIterator<T> for $name<T> {
+            fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+                let mut heap = $name::new();
+                heap.extend(iter);
+                heap
+            }
+        }
+    };
+}
+
+meldable_heap! {
+    /// A min-heap of items of type `T` with handles, kept leftist by rank.
+    LeftistHeap, false
+}
+
+meldable_heap! {
+    /// A min-heap of items of type `T` with handles, kept shallow by
+    /// swapping children on every meld.
+    SkewHeap, true
+}
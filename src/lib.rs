@@ -0,0 +1,104 @@
+//! A collection of data structures, with an emphasis on spatial indexes.
+
+mod epoch;
+mod heap_arena;
+mod rng;
+mod sync;
+mod util;
+
+pub mod aabb_tree;
+pub mod adjacency_graph;
+pub mod algebra;
+pub mod avl;
+pub mod balltree;
+pub mod bih;
+pub mod bin_lattice;
+pub mod bit_vector;
+pub mod bitset;
+pub mod blocking_queue;
+pub mod bloom;
+pub mod bplus_tree;
+pub mod btree;
+pub mod bvh;
+pub mod cartesian_tree;
+pub mod catenable_deque;
+pub mod compressed_orthtree;
+pub mod concurrent_map;
+pub mod count_min;
+pub mod covertree;
+pub mod csr_graph;
+pub mod cuckoo_filter;
+pub mod dancing_links;
+pub mod elias_fano;
+pub mod euler_tour_tree;
+pub mod fenwick;
+pub mod fibonacci_heap;
+pub mod finger_tree;
+pub mod fm_index;
+pub mod gap_buffer;
+pub mod geohash;
+pub mod geom;
+pub mod grid;
+pub mod hamt;
+pub mod heap;
+pub mod heavy_light;
+pub mod hnsw;
+pub mod hyperloglog;
+pub mod indexed_heap;
+pub mod interval_heap;
+pub mod interval_set;
+pub mod interval_skip_list;
+pub mod interval_tree;
+pub mod intrusive_rbtree;
+pub mod kdtree;
+pub mod leftist_heap;
+pub mod link_cut;
+pub mod loose_octree;
+pub mod lru;
+pub mod lsh;
+pub mod merkle_tree;
+pub mod metric;
+pub mod morton;
+pub mod mpmc;
+pub mod mtree;
+pub mod naive;
+pub mod nclist;
+pub mod octree;
+pub mod orthtree;
+pub mod pairing_heap;
+pub mod persistent_segment_tree;
+pub mod phtree;
+pub mod piece_table;
+pub mod probabilistic;
+pub mod quadtree;
+pub mod radix_trie;
+pub mod ring_buffer;
+pub mod roaring;
+pub mod rope;
+pub mod rrb_vector;
+pub mod rtree;
+pub mod scapegoat;
+pub mod segment_tree;
+pub mod sharded_cache;
+pub mod skip_list;
+pub mod slab;
+pub mod sliding_window;
+pub mod slot_map;
+pub mod sparse_set;
+pub mod sparse_table;
+pub mod spatial_index;
+pub mod sphere_cell;
+pub mod splay;
+pub mod sqrt_decomposition;
+pub mod suffix_array;
+pub mod suffix_automaton;
+pub mod tdigest;
+pub mod tiled_octree;
+pub mod treap;
+pub mod treiber_stack;
+pub mod union_find;
+pub mod veb;
+pub mod wavelet_tree;
+pub mod weight_balanced;
+pub mod yfast_trie;
+pub mod zorder;
@@ -0,0 +1,375 @@
+//! Link-cut trees over a dynamic forest, with path aggregates.
+//!
+//! A link-cut tree (Sleator and Tarjan, 1983) maintains a forest of
+//! unrooted trees on a fixed set of nodes under adding and removing edges,
+//! and answers connectivity and path queries as it changes, each in
+//! `O(log n)` amortized. Every tree is split into preferred paths, each
+//! stored as a splay tree keyed by depth, and an access makes the path
+//! from a node to its root preferred, splaying it to the top. A path query
+//! is then read off a single splay tree: every splay node keeps the
+//! [`Monoid`] combination of its subtree, so the combination of the values
+//! along the path is at the root.
+//!
+//! Linking and cutting arbitrary pairs needs a choice of root, which
+//! [`evert`](LinkCutTree::evert) moves to any node by reversing the path
+//! to it. Reversal is lazy, a flag on a splay node, and each node keeps
+//! its combination in both directions, so path aggregates stay correct for
+//! monoids that are not commutative.
+//!
+//! Dynamic trees are what the crate's static structures cannot offer:
+//! connectivity in a forest that gains and loses edges, the minimum edge
+//! weight on the path of a changing spanning tree, or the bottleneck of an
+//! augmenting path in Dinic's or Goldberg-Tarjan's maximum-flow
+//! algorithms, with no recomputation after each change. Nodes are numbered
+//! from zero and kept in flat arrays.
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+
+use crate::algebra::Monoid;
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<V> {
+    /// The splay children: shallower on the left, deeper on the right.
+    child: [u32; 2],
+    /// The splay parent, or for a splay root the path parent.
+    parent: u32,
+    /// Whether the subtree's children are yet to be swapped.
+    flip: bool,
+    value: V,
+    /// The combination of the subtree's values, shallowest first.
+    sum: V,
+    /// The same combination, deepest first.
+    rev: V,
+}
+
+/// A forest over nodes `0..n` carrying values of `M::Value`.
+#[derive(Clone, Debug)]
+pub struct LinkCutTree<M: Monoid> {
+    monoid: M,
+    nodes: Vec<Node<M::Value>>,
+}
+
+impl<M: Monoid> LinkCutTree<M> {
+    /// Creates a forest of `n` isolated nodes, each holding the identity.
+    pub fn new(monoid: M, n: usize) -> Self {
+        let values = vec![monoid.identity(); n];
+        Self::with_values(monoid, values)
+    }
+
+    /// Creates a forest of isolated nodes holding `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `u32::MAX` values or more.
+    pub fn with_values(monoid: M, values: Vec<M::Value>) -> Self {
+        assert!(values.len() < NONE as usize, "too many nodes");
+        let mut tree = LinkCutTree {
+            monoid,
+            nodes: Vec::with_capacity(values.len()),
+        };
+        for value in values {
+            tree.add_node(value);
+        }
+        tree
+    }
+
+    /// Number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the forest has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The monoid the paths combine with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// Adds an isolated node holding `value`, returning its number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the forest already holds `u32::MAX - 1` nodes.
+    pub fn add_node(&mut self, value: M::Value) -> usize {
+        assert!(self.nodes.len() < NONE as usize - 1, "too many nodes");
+        self.nodes.push(Node {
+            child: [NONE; 2],
+            parent: NONE,
+            flip: false,
+            sum: value.clone(),
+            rev: value.clone(),
+            value,
+        });
+        self.nodes.len() - 1
+    }
+
+    /// The value at node `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn get(&self, v: usize) -> &M::Value {
+        &self.nodes[v].value
+    }
+
+    /// Replaces the value at node `v`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn set(&mut self, v: usize, value: M::Value) {
+        let v = self.check(v);
+        // Once splayed, `v` is the root of its splay tree, so only its own
+        // combination depends on the value.
+        self.splay(v);
+        self.nodes[v as usize].value = value;
+        self.pull(v);
+    }
+
+    /// Makes `v` the root of its tree, in `O(log n)` amortized.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn evert(&mut self, v: usize) {
+        let v = self.check(v);
+        self.access(v);
+        self.toggle(v);
+    }
+
+    /// The root of the tree holding `v`, in `O(log n)` amortized. Roots
+    /// move only by everting, which [`link`](Self::link),
+    /// [`cut`](Self::cut) and [`path`](Self::path) do to their first
+    /// argument.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `v` is out of bounds.
+    pub fn find_root(&mut self, v: usize) -> usize {
+        let v = self.check(v);
+        self.access(v);
+        let mut r = v;
+        loop {
+            self.push(r);
+            match self.nodes[r as usize].child[0] {
+                NONE => break,
+                l => r = l,
+            }
+        }
+        // Splaying the root keeps repeated queries cheap.
+        self.splay(r);
+        r as usize
+    }
+
+    /// Whether `u` and `v` are in the same tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn connected(&mut self, u: usize, v: usize) -> bool {
+        self.find_root(u) == self.find_root(v)
+    }
+
+    /// Adds the edge `u`–`v`, returning `false` and changing nothing if
+    /// they are already connected. `v`'s tree keeps its root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn link(&mut self, u: usize, v: usize) -> bool {
+        if self.connected(u, v) {
+            return false;
+        }
+        self.evert(u);
+        self.nodes[u].parent = v as u32;
+        true
+    }
+
+    /// Removes the edge `u`–`v`, returning `false` if there is none.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn cut(&mut self, u: usize, v: usize) -> bool {
+        let (u, v) = (self.check(u), self.check(v));
+        if u == v {
+            return false;
+        }
+        self.evert(u as usize);
+        self.access(v);
+        // The path from `u` to `v` is now `v`'s splay tree, with `v` at the
+        // root; they are adjacent when `u` is all that lies above it.
+        let above = self.nodes[v as usize].child[0];
+        if above != u {
+            return false;
+        }
+        self.push(u);
+        if self.nodes[u as usize].child != [NONE; 2] {
+            return false;
+        }
+        self.nodes[v as usize].child[0] = NONE;
+        self.nodes[u as usize].parent = NONE;
+        self.pull(v);
+        true
+    }
+
+    /// The combination of the values on the path from `u` to `v`, both
+    /// included and in that order, or `None` if they are not connected.
+    /// Makes `u` the root of its tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn path(&mut self, u: usize, v: usize) -> Option<M::Value> {
+        if !self.connected(u, v) {
+            return None;
+        }
+        self.evert(u);
+        let v = v as u32;
+        self.access(v);
+        Some(self.nodes[v as usize].sum.clone())
+    }
+
+    /// The lowest common ancestor of `u` and `v` under the current root, or
+    /// `None` if they are not connected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `u` or `v` is out of bounds.
+    pub fn lca(&mut self, u: usize, v: usize) -> Option<usize> {
+        if !self.connected(u, v) {
+            return None;
+        }
+        let (u, v) = (self.check(u), self.check(v));
+        self.access(u);
+        Some(self.access(v) as usize)
+    }
+
+    fn check(&self, v: usize) -> u32 {
+        assert!(
+            v < self.nodes.len(),
+            "node {v} out of bounds for {} nodes",
+            self.nodes.len()
+        );
+        v as u32
+    }
+
+    /// Whether `x` is the root of its splay tree.
+    fn is_splay_root(&self, x: u32) -> bool {
+        let p = self.nodes[x as usize].parent;
+        p == NONE || !self.nodes[p as usize].child.contains(&x)
+    }
+
+    /// Reverses the path stored in `x`'s subtree.
+    fn toggle(&mut self, x: u32) {
+        let n = &mut self.nodes[x as usize];
+        n.child.swap(0, 1);
+        std::mem::swap(&mut n.sum, &mut n.rev);
+        n.flip = !n.flip;
+    }
+
+    /// Hands a pending reversal at `x` down to its children.
+    fn push(&mut self, x: u32) {
+        if std::mem::take(&mut self.nodes[x as usize].flip) {
+            for c in self.nodes[x as usize].child {
+                if c != NONE {
+                    self.toggle(c);
+                }
+            }
+        }
+    }
+
+    /// Recomputes the combinations at `x` from its children.
+    fn pull(&mut self, x: u32) {
+        let [l, r] = self.nodes[x as usize].child;
+        let id = self.monoid.identity();
+        let side = |c: u32, rev: bool| {
+            if c == NONE {
+                &id
+            } else if rev {
+                &self.nodes[c as usize].rev
+            } else {
+                &self.nodes[c as usize].sum
+            }
+        };
+        let value = &self.nodes[x as usize].value;
+        let sum = self
+            .monoid
+            .combine(&self.monoid.combine(side(l, false), value), side(r, false));
+        let rev = self
+            .monoid
+            .combine(&self.monoid.combine(side(r, true), value), side(l, true));
+        let n = &mut self.nodes[x as usize];
+        n.sum = sum;
+        n.rev = rev;
+    }
+
+    /// Rotates `x` above its splay parent.
+    fn rotate(&mut self, x: u32) {
+        let p = self.nodes[x as usize].parent;
+        let g = self.nodes[p as usize].parent;
+        let side = usize::from(self.nodes[p as usize].child[1] == x);
+        let inner = self.nodes[x as usize].child[1 - side];
+        if !self.is_splay_root(p) {
+            let gs = usize::from(self.nodes[g as usize].child[1] == p);
+            self.nodes[g as usize].child[gs] = x;
+        }
+        self.nodes[x as usize].parent = g;
+        self.nodes[x as usize].child[1 - side] = p;
+        self.nodes[p as usize].parent = x;
+        self.nodes[p as usize].child[side] = inner;
+        if inner != NONE {
+            self.nodes[inner as usize].parent = p;
+        }
+        self.pull(p);
+        self.pull(x);
+    }
+
+    /// Moves `x` to the root of its splay tree.
+    fn splay(&mut self, x: u32) {
+        // Push reversals down from the splay root first, so the shape seen
+        // while rotating is the real one.
+        let mut path = vec![x];
+        let mut y = x;
+        while !self.is_splay_root(y) {
+            y = self.nodes[y as usize].parent;
+            path.push(y);
+        }
+        for &y in path.iter().rev() {
+            self.push(y);
+        }
+        while !self.is_splay_root(x) {
+            let p = self.nodes[x as usize].parent;
+            if !self.is_splay_root(p) {
+                let g = self.nodes[p as usize].parent;
+                let zig_zig = (self.nodes[g as usize].child[1] == p)
+                    == (self.nodes[p as usize].child[1] == x);
+                self.rotate(if zig_zig { p } else { x });
+            }
+            self.rotate(x);
+        }
+    }
+
+    /// Makes the path from the root to `x` preferred, ending at `x`, with
+    /// `x` at the root of its splay tree. Returns the last node at which
+    /// the walk joined a path, which after a previous `access(u)` is the
+    /// lowest common ancestor of `u` and `x`.
+    fn access(&mut self, x: u32) -> u32 {
+        let mut last = NONE;
+        let mut y = x;
+        while y != NONE {
+            self.splay(y);
+            self.nodes[y as usize].child[1] = last;
+            self.pull(y);
+            last = y;
+            y = self.nodes[y as usize].parent;
+        }
+        self.splay(x);
+        last
+    }
+}
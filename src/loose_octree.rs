@@ -0,0 +1,485 @@
+//! A loose octree over oriented bounding boxes.
+//!
+//! Game objects are mostly rotated boxes rather than points, and a plain
+//! octree stores boxes badly. A box that straddles a cell boundary has to
+//! sit high in the tree or be duplicated across cells. A loose octree
+//! (Ulrich) avoids both by letting every cell hold objects that stick out
+//! of it. Each node's *loose box* is its cell scaled by the looseness
+//! factor about the cell center. An object goes in the deepest cell that
+//! contains its center and whose loose box still contains the whole object.
+//! That cell depends only on the object's center and size, so placement
+//! walks straight down with no search, and a moving object usually stays
+//! put.
+//!
+//! Objects are [`Obb`]s. Queries prune nodes by their loose boxes and then
+//! test each candidate against its exact oriented box: the separating axis
+//! test for boxes, closest-point distance for spheres, and a slab test in
+//! the box's frame for rays. [`LooseOctree::intersecting_pairs`] provides a
+//! broad phase for collision detection.
+//!
+//! The tree covers a fixed box chosen at construction. An object whose
+//! center lies inside it is accepted, however large; objects too big for
+//! any child are kept at the root, which every query visits.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::geom::{Aabb, Obb, Ray, Sphere};
+use crate::orthtree::OutOfBounds;
+use crate::util::Total;
+
+/// Handle to a box stored in a [`LooseOctree`].
+///
+/// Handles of removed boxes may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObbId(u32);
+
+const NONE: u32 = u32::MAX;
+
+/// The deepest level a [`LooseOctree`] may be built with.
+pub const MAX_LOOSE_DEPTH: u8 = 20;
+
+#[derive(Clone, Debug)]
+struct Node {
+    center: [f64; 3],
+    /// Half the edge lengths of the node's cell, before loosening.
+    half: [f64; 3],
+    depth: u8,
+    parent: u32,
+    children: [u32; 8],
+    items: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T> {
+    obb: Obb,
+    data: T,
+    node: u32,
+    /// Position within the node's item list.
+    slot: u32,
+}
+
+/// A loose octree mapping oriented boxes to data of type `T`.
+#[derive(Clone, Debug)]
+pub struct LooseOctree<T> {
+    bounds: Aabb<3>,
+    looseness: f64,
+    max_depth: u8,
+    nodes: Vec<Node>,
+    free_nodes: Vec<u32>,
+    entries: Vec<Option<Entry<T>>>,
+    free_entries: Vec<u32>,
+    len: usize,
+}
+
+impl<T> LooseOctree<T> {
+    /// Creates an empty tree over `bounds` with looseness 2 and depth at
+    /// most 10.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite.
+    pub fn new(bounds: Aabb<3>) -> Self {
+        Self::with_limits(bounds, 2.0, 10)
+    }
+
+    /// Creates an empty tree whose loose boxes are `looseness` times the
+    /// size of their cells, with depth at most `max_depth`.
+    ///
+    /// Larger looseness pushes objects deeper at the cost of looser
+    /// pruning; 2 is the usual choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite, if `looseness` is not
+    /// greater than 1 and finite, or if `max_depth` exceeds
+    /// [`MAX_LOOSE_DEPTH`].
+    pub fn with_limits(bounds: Aabb<3>, looseness: f64, max_depth: u8) -> Self {
+        assert!(
+            (0..3).all(|a| bounds.min[a].is_finite()
+                && bounds.max[a].is_finite()
+                && bounds.min[a] < bounds.max[a]),
+            "octree bounds must be finite and non-empty"
+        );
+        assert!(
+            looseness > 1.0 && looseness.is_finite(),
+            "looseness must be greater than 1 and finite"
+        );
+        assert!(
+            max_depth <= MAX_LOOSE_DEPTH,
+            "max depth must be at most {MAX_LOOSE_DEPTH}"
+        );
+        LooseOctree {
+            bounds,
+            looseness,
+            max_depth,
+            nodes: vec![Self::root_node(&bounds)],
+            free_nodes: Vec::new(),
+            entries: Vec::new(),
+            free_entries: Vec::new(),
+            len: 0,
+        }
+    }
+
+    fn root_node(bounds: &Aabb<3>) -> Node {
+        Node {
+            center: bounds.center(),
+            half: std::array::from_fn(|a| 0.5 * (bounds.max[a] - bounds.min[a])),
+            depth: 0,
+            parent: NONE,
+            children: [NONE; 8],
+            items: Vec::new(),
+        }
+    }
+
+    /// The box covered by the tree's cells.
+    pub fn bounds(&self) -> Aabb<3> {
+        self.bounds
+    }
+
+    /// The ratio of a loose box to its cell.
+    pub fn looseness(&self) -> f64 {
+        self.looseness
+    }
+
+    /// Number of stored boxes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no boxes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every box, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.nodes.push(Self::root_node(&self.bounds));
+        self.free_nodes.clear();
+        self.entries.clear();
+        self.free_entries.clear();
+        self.len = 0;
+    }
+
+    /// Inserts a box, returning its handle, or an error if its center lies
+    /// outside the tree's bounds.
+    pub fn insert(&mut self, obb: Obb, data: T) -> Result<ObbId, OutOfBounds> {
+        if !self.bounds.contains_point(&obb.center) {
+            return Err(OutOfBounds);
+        }
+        let entry = Entry {
+            obb,
+            data,
+            node: NONE,
+            slot: 0,
+        };
+        let id = match self.free_entries.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.place(id);
+        self.len += 1;
+        Ok(ObbId(id))
+    }
+
+    /// Removes a box, returning it and its data.
+    pub fn remove(&mut self, id: ObbId) -> Option<(Obb, T)> {
+        self.entries.get(id.0 as usize)?.as_ref()?;
+        self.detach(id.0);
+        let entry = self.entries[id.0 as usize].take()?;
+        self.free_entries.push(id.0);
+        self.len -= 1;
+        Some((entry.obb, entry.data))
+    }
+
+    /// Replaces a stored box, keeping its handle and data.
+    ///
+    /// Returns the previous box, or `None` if `id` is not in the tree.
+    /// Fails without modifying the tree if the new center is out of bounds.
+    pub fn relocate(&mut self, id: ObbId, to: Obb) -> Result<Option<Obb>, OutOfBounds> {
+        if !self.bounds.contains_point(&to.center) {
+            return Err(OutOfBounds);
+        }
+        let Some(entry) = self.entries.get(id.0 as usize).and_then(Option::as_ref) else {
+            return Ok(None);
+        };
+        let same_node = self.target(&to) == entry.node;
+        if !same_node {
+            self.detach(id.0);
+        }
+        let entry = self.entries[id.0 as usize]
+            .as_mut()
+            .expect("entry checked above");
+        let old = std::mem::replace(&mut entry.obb, to);
+        if !same_node {
+            self.place(id.0);
+        }
+        Ok(Some(old))
+    }
+
+    /// Returns a stored box and its data.
+    pub fn get(&self, id: ObbId) -> Option<(&Obb, &T)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((&e.obb, &e.data))
+    }
+
+    /// Returns a stored box and its mutable data.
+    pub fn get_mut(&mut self, id: ObbId) -> Option<(&Obb, &mut T)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&e.obb, &mut e.data))
+    }
+
+    /// Iterates over every box in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (ObbId, &Obb, &T)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.as_ref().map(|e| (ObbId(i as u32), &e.obb, &e.data)))
+    }
+
+    /// Iterates over the boxes intersecting `query`.
+    pub fn intersecting(&self, query: Obb) -> impl Iterator<Item = (ObbId, &Obb, &T)> + '_ {
+        let reach = query.bounds();
+        self.query(
+            move |b| reach.intersects(b) && query.intersects_aabb(b),
+            move |o| query.intersects(o),
+        )
+    }
+
+    /// Iterates over the boxes intersecting the axis-aligned box `query`.
+    pub fn intersecting_aabb(
+        &self,
+        query: Aabb<3>,
+    ) -> impl Iterator<Item = (ObbId, &Obb, &T)> + '_ {
+        self.query(
+            move |b| query.intersects(b),
+            move |o| o.intersects_aabb(&query),
+        )
+    }
+
+    /// Iterates over the boxes intersecting `sphere`.
+    pub fn intersecting_sphere(
+        &self,
+        sphere: Sphere<3>,
+    ) -> impl Iterator<Item = (ObbId, &Obb, &T)> + '_ {
+        self.query(
+            move |b| b.intersects_sphere(&sphere),
+            move |o| o.intersects_sphere(&sphere),
+        )
+    }
+
+    /// Iterates over the boxes containing `p`.
+    pub fn containing_point(&self, p: [f64; 3]) -> impl Iterator<Item = (ObbId, &Obb, &T)> + '_ {
+        self.query(move |b| b.contains_point(&p), move |o| o.contains_point(&p))
+    }
+
+    /// The first box `ray` enters before `max_t`, with the parameter at
+    /// which it enters.
+    pub fn raycast(&self, ray: &Ray<3>, max_t: f64) -> Option<(ObbId, f64)> {
+        let mut best: Option<(ObbId, f64)> = None;
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((Total(0.0), 0u32)));
+        while let Some(Reverse((Total(t), n))) = frontier.pop() {
+            let limit = best.map_or(max_t, |b| b.1);
+            if t > limit {
+                break;
+            }
+            let node = &self.nodes[n as usize];
+            for &i in &node.items {
+                let e = self.entry(i);
+                if let Some(t) = ray.hit_obb(&e.obb, best.map_or(max_t, |b| b.1)) {
+                    if best.is_none_or(|b| t < b.1) {
+                        best = Some((ObbId(i), t));
+                    }
+                }
+            }
+            for &c in node.children.iter().filter(|&&c| c != NONE) {
+                let limit = best.map_or(max_t, |b| b.1);
+                if let Some(t) = ray.hit_aabb(&self.loose_box(c), limit) {
+                    frontier.push(Reverse((Total(t), c)));
+                }
+            }
+        }
+        best
+    }
+
+    /// Every pair of stored boxes that intersect, each pair once, in
+    /// unspecified order.
+    pub fn intersecting_pairs(&self) -> Vec<(ObbId, ObbId)> {
+        let mut out = Vec::new();
+        for (a, obb, _) in self.iter() {
+            out.extend(
+                self.intersecting(*obb)
+                    .filter(|&(b, _, _)| a < b)
+                    .map(|(b, _, _)| (a, b)),
+            );
+        }
+        out
+    }
+
+    /// Depth-first traversal entering nodes whose loose boxes pass `enter`
+    /// and yielding boxes that pass `keep`. The root is always entered,
+    /// since it may hold boxes larger than its loose box.
+    fn query<'a>(
+        &'a self,
+        enter: impl Fn(&Aabb<3>) -> bool + 'a,
+        keep: impl Fn(&Obb) -> bool + 'a,
+    ) -> impl Iterator<Item = (ObbId, &'a Obb, &'a T)> + 'a {
+        let mut stack = if self.is_empty() { Vec::new() } else { vec![0] };
+        let mut items: std::slice::Iter<'a, u32> = [].iter();
+        std::iter::from_fn(move || loop {
+            for &i in items.by_ref() {
+                let e = self.entry(i);
+                if keep(&e.obb) {
+                    return Some((ObbId(i), &e.obb, &e.data));
+                }
+            }
+            let n = stack.pop()?;
+            if n != 0 && !enter(&self.loose_box(n)) {
+                continue;
+            }
+            let node = &self.nodes[n as usize];
+            items = node.items.iter();
+            stack.extend(node.children.iter().filter(|&&c| c != NONE));
+        })
+    }
+
+    fn entry(&self, i: u32) -> &Entry<T> {
+        self.entries[i as usize].as_ref().expect("live entry")
+    }
+
+    fn loose_box(&self, n: u32) -> Aabb<3> {
+        let node = &self.nodes[n as usize];
+        let reach = node.half.map(|h| h * self.looseness);
+        Aabb::new(
+            std::array::from_fn(|a| node.center[a] - reach[a]),
+            std::array::from_fn(|a| node.center[a] + reach[a]),
+        )
+    }
+
+    /// The octant of node `n` holding `p`, and whether a box reaching
+    /// `reach` from `p` fits that octant's loose box.
+    fn step(&self, n: u32, p: &[f64; 3], reach: &[f64; 3]) -> (usize, bool) {
+        let node = &self.nodes[n as usize];
+        let octant = (0..3).fold(0, |o, a| o | ((p[a] >= node.center[a]) as usize) << a);
+        let slack = self.looseness - 1.0;
+        let fits =
+            node.depth < self.max_depth && (0..3).all(|a| reach[a] <= slack * 0.5 * node.half[a]);
+        (octant, fits)
+    }
+
+    /// The existing node `obb` would be placed in, or `NONE` if placing it
+    /// would create a node.
+    fn target(&self, obb: &Obb) -> u32 {
+        let reach = half_sizes(obb);
+        let mut n = 0;
+        loop {
+            let (octant, fits) = self.step(n, &obb.center, &reach);
+            if !fits {
+                return n;
+            }
+            n = self.nodes[n as usize].children[octant];
+            if n == NONE {
+                return NONE;
+            }
+        }
+    }
+
+    /// Files entry `id` in the deepest node whose loose box holds it,
+    /// creating nodes along the way.
+    fn place(&mut self, id: u32) {
+        let obb = self.entry(id).obb;
+        let reach = half_sizes(&obb);
+        let mut n = 0;
+        loop {
+            let (octant, fits) = self.step(n, &obb.center, &reach);
+            if !fits {
+                break;
+            }
+            let child = self.nodes[n as usize].children[octant];
+            n = if child == NONE {
+                let c = self.alloc_node(n, octant);
+                self.nodes[n as usize].children[octant] = c;
+                c
+            } else {
+                child
+            };
+        }
+        let items = &mut self.nodes[n as usize].items;
+        let slot = items.len() as u32;
+        items.push(id);
+        let entry = self.entries[id as usize].as_mut().expect("live entry");
+        entry.node = n;
+        entry.slot = slot;
+    }
+
+    fn alloc_node(&mut self, parent: u32, octant: usize) -> u32 {
+        let p = &self.nodes[parent as usize];
+        let half = p.half.map(|h| 0.5 * h);
+        let node = Node {
+            center: std::array::from_fn(|a| {
+                if octant >> a & 1 == 1 {
+                    p.center[a] + half[a]
+                } else {
+                    p.center[a] - half[a]
+                }
+            }),
+            half,
+            depth: p.depth + 1,
+            parent,
+            children: [NONE; 8],
+            items: Vec::new(),
+        };
+        match self.free_nodes.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Unlinks entry `id` from its node, freeing nodes left empty.
+    fn detach(&mut self, id: u32) {
+        let entry = self.entry(id);
+        let (mut n, slot) = (entry.node, entry.slot as usize);
+        let items = &mut self.nodes[n as usize].items;
+        items.swap_remove(slot);
+        if let Some(&moved) = items.get(slot) {
+            self.entries[moved as usize]
+                .as_mut()
+                .expect("live entry")
+                .slot = slot as u32;
+        }
+        while n != 0 {
+            let node = &self.nodes[n as usize];
+            if !node.items.is_empty() || node.children.iter().any(|&c| c != NONE) {
+                break;
+            }
+            let parent = node.parent;
+            for c in &mut self.nodes[parent as usize].children {
+                if *c == n {
+                    *c = NONE;
+                }
+            }
+            self.free_nodes.push(n);
+            n = parent;
+        }
+    }
+}
+
+/// Half the edge lengths of the axis-aligned bounds of `obb`.
+fn half_sizes(obb: &Obb) -> [f64; 3] {
+    let b = obb.bounds();
+    std::array::from_fn(|a| 0.5 * (b.max[a] - b.min[a]))
+}
@@ -0,0 +1,399 @@
+//! A least-recently-used cache with weighted entries.
+//!
+//! An LRU cache keeps the entries used most recently and, when full,
+//! evicts the one used longest ago. Each entry here also has a weight,
+//! such as its size in bytes, and the capacity bounds the total weight
+//! rather than the number of entries: inserting one large entry may evict
+//! several small ones. Entries inserted without a weight weigh one, which
+//! makes the capacity an entry count.
+//!
+//! Entries live in an arena, doubly linked by index from the most to the
+//! least recently used, and a hash map finds each key's slot. A lookup
+//! that counts as a use unlinks its entry and relinks it at the front, so
+//! every operation is `O(1)` expected. [`peek`](LruCache::peek) reads an
+//! entry without that promotion, for inspection that should not keep an
+//! entry alive.
+//!
+//! Evictions can be observed with a callback, which receives each entry
+//! evicted to make room, for write-back or metrics. Entries removed
+//! explicitly or replaced by a new value for the same key are returned to
+//! the caller instead.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+const NONE: u32 = u32::MAX;
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    weight: u64,
+    /// The next more recently used entry.
+    prev: u32,
+    /// The next less recently used entry.
+    next: u32,
+}
+
+/// A map from `K` to `V` that evicts its least recently used entries to
+/// keep their total weight within a capacity.
+pub struct LruCache<K, V> {
+    entries: Vec<Option<Entry<K, V>>>,
+    free: Vec<u32>,
+    slots: HashMap<K, u32>,
+    /// The most recently used entry.
+    head: u32,
+    /// The least recently used entry.
+    tail: u32,
+    weight: u64,
+    capacity: u64,
+    on_evict: Option<Box<dyn FnMut(K, V) + Send>>,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Creates an empty cache holding entries of total weight at most
+    /// `capacity`.
+    pub fn new(capacity: u64) -> Self {
+        LruCache {
+            entries: Vec::new(),
+            free: Vec::new(),
+            slots: HashMap::new(),
+            head: NONE,
+            tail: NONE,
+            weight: 0,
+            capacity,
+            on_evict: None,
+        }
+    }
+
+    /// Calls `f` with every entry evicted to make room from now on.
+    pub fn with_eviction_callback(mut self, f: impl FnMut(K, V) + Send + 'static) -> Self {
+        self.on_evict = Some(Box::new(f));
+        self
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether the cache holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Total weight of the entries.
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
+
+    /// The largest total weight the cache holds.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Changes the capacity, evicting least recently used entries until
+    /// the cache fits.
+    pub fn set_capacity(&mut self, capacity: u64) {
+        self.capacity = capacity;
+        self.evict_to(capacity);
+    }
+
+    /// Removes every entry, without calling the eviction callback.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.free.clear();
+        self.slots.clear();
+        self.head = NONE;
+        self.tail = NONE;
+        self.weight = 0;
+    }
+
+    /// Whether an entry is stored under `key`. Does not count as a use.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.slots.contains_key(key)
+    }
+
+    /// Returns the value stored under `key`, marking it most recently
+    /// used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.slots.get(key)?;
+        self.promote(slot);
+        Some(&self.entry(slot).value)
+    }
+
+    /// Returns the value stored under `key` mutably, marking it most
+    /// recently used.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.slots.get(key)?;
+        self.promote(slot);
+        Some(&mut self.entry_mut(slot).value)
+    }
+
+    /// Returns the value stored under `key` without marking it used.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.slots.get(key)?;
+        Some(&self.entry(slot).value)
+    }
+
+    /// The weight of the entry stored under `key`.
+    pub fn weight_of<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = *self.slots.get(key)?;
+        Some(self.entry(slot).weight)
+    }
+
+    /// Inserts `value` under `key` with weight one; see
+    /// [`insert_weighted`](Self::insert_weighted).
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_weighted(key, value, 1)
+    }
+
+    /// Inserts `value` under `key` with the given weight as the most
+    /// recently used entry, evicting least recently used entries until the
+    /// total weight fits the capacity. Returns the value previously stored
+    /// under `key`.
+    ///
+    /// An entry heavier than the whole capacity is rejected: it goes
+    /// straight to the eviction callback, if there is one, and evicts
+    /// nothing else. The value it replaces is still removed and returned.
+    pub fn insert_weighted(&mut self, key: K, value: V, weight: u64) -> Option<V> {
+        let old = self.remove(&key);
+        if weight > self.capacity {
+            if let Some(f) = &mut self.on_evict {
+                f(key, value);
+            }
+            return old;
+        }
+        // Make room first, so the total never exceeds the capacity.
+        self.evict_to(self.capacity - weight);
+        let entry = Entry {
+            key: key.clone(),
+            value,
+            weight,
+            prev: NONE,
+            next: NONE,
+        };
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.entries[slot as usize] = Some(entry);
+                slot
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.slots.insert(key, slot);
+        self.link_front(slot);
+        self.weight = self
+            .weight
+            .checked_add(weight)
+            .expect("weight fits the capacity");
+        old
+    }
+
+    /// Removes the entry stored under `key` and returns its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let slot = self.slots.remove(key)?;
+        Some(self.take(slot).1)
+    }
+
+    /// The least recently used entry, the next to be evicted, without
+    /// marking it used.
+    pub fn peek_lru(&self) -> Option<(&K, &V)> {
+        if self.tail == NONE {
+            return None;
+        }
+        let entry = self.entry(self.tail);
+        Some((&entry.key, &entry.value))
+    }
+
+    /// Removes and returns the least recently used entry, without calling
+    /// the eviction callback.
+    pub fn pop_lru(&mut self) -> Option<(K, V)> {
+        if self.tail == NONE {
+            return None;
+        }
+        let (key, value) = self.take(self.tail);
+        self.slots.remove(&key);
+        Some((key, value))
+    }
+
+    /// Iterates over the entries from the most to the least recently used,
+    /// without marking any used.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            cache: self,
+            front: self.head,
+            back: self.tail,
+            remaining: self.len(),
+        }
+    }
+
+    /// Evicts least recently used entries until the total weight is at
+    /// most `limit`.
+    fn evict_to(&mut self, limit: u64) {
+        while self.weight > limit {
+            let Some((key, value)) = self.pop_lru() else {
+                break;
+            };
+            if let Some(f) = &mut self.on_evict {
+                f(key, value);
+            }
+        }
+    }
+
+    /// Unlinks and frees `slot`, leaving its key in the map.
+    fn take(&mut self, slot: u32) -> (K, V) {
+        self.unlink(slot);
+        let entry = self.entries[slot as usize].take().expect("live entry");
+        self.free.push(slot);
+        self.weight -= entry.weight;
+        (entry.key, entry.value)
+    }
+
+    fn promote(&mut self, slot: u32) {
+        if self.head != slot {
+            self.unlink(slot);
+            self.link_front(slot);
+        }
+    }
+
+    fn link_front(&mut self, slot: u32) {
+        let head = self.head;
+        let entry = self.entry_mut(slot);
+        entry.prev = NONE;
+        entry.next = head;
+        if head == NONE {
+            self.tail = slot;
+        } else {
+            self.entry_mut(head).prev = slot;
+        }
+        self.head = slot;
+    }
+
+    fn unlink(&mut self, slot: u32) {
+        let (prev, next) = {
+            let entry = self.entry(slot);
+            (entry.prev, entry.next)
+        };
+        if prev == NONE {
+            self.head = next;
+        } else {
+            self.entry_mut(prev).next = next;
+        }
+        if next == NONE {
+            self.tail = prev;
+        } else {
+            self.entry_mut(next).prev = prev;
+        }
+    }
+
+    fn entry(&self, slot: u32) -> &Entry<K, V> {
+        self.entries[slot as usize].as_ref().expect("live entry")
+    }
+
+    fn entry_mut(&mut self, slot: u32) -> &mut Entry<K, V> {
+        self.entries[slot as usize].as_mut().expect("live entry")
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for LruCache<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        let mut slot = self.head;
+        while slot != NONE {
+            let entry = self.entries[slot as usize].as_ref().expect("live entry");
+            map.entry(&entry.key, &entry.value);
+            slot = entry.next;
+        }
+        map.finish()
+    }
+}
+
+impl<K: Hash + Eq + Clone, V> Extend<(K, V)> for LruCache<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// An iterator over a cache's entries in recency order, returned by
+/// [`LruCache::iter`].
+pub struct Iter<'a, K, V> {
+    cache: &'a LruCache<K, V>,
+    front: u32,
+    back: u32,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let entry = self.cache.entries[self.front as usize]
+            .as_ref()
+            .expect("live entry");
+        self.front = entry.next;
+        Some((&entry.key, &entry.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let entry = self.cache.entries[self.back as usize]
+            .as_ref()
+            .expect("live entry");
+        self.back = entry.prev;
+        Some((&entry.key, &entry.value))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K: Hash + Eq + Clone, V> IntoIterator for &'a LruCache<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
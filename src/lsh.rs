@@ -0,0 +1,348 @@
+//! Approximate nearest-neighbor search by locality-sensitive hashing.
+//!
+//! A locality-sensitive hash sends nearby points to the same bucket more
+//! often than distant ones. [`Lsh`] keeps several independent hash tables,
+//! each keyed by a concatenation of `hashes` elementary hashes, and answers
+//! a query by gathering the points that share a bucket with it in any table
+//! and ranking those candidates by their true distance.
+//!
+//! Two families are provided. Random hyperplanes (Charikar) take the sign
+//! of a projection onto a Gaussian direction and suit angular distance.
+//! P-stable hashes (Datar et al.) cut a Gaussian projection into buckets of
+//! a fixed width and suit Euclidean distance.
+//!
+//! Multi-probe querying (Lv et al.) also visits the buckets the query only
+//! just missed. Each elementary hash is nudged across its nearest bucket
+//! boundary, and combinations of nudges are tried in order of how close the
+//! query lies to those boundaries. A few probes per table recover much of
+//! the recall that more tables would buy, at no extra memory.
+//!
+//! The index is cheap to build and update compared with a graph index such
+//! as [`Hnsw`](crate::hnsw::Hnsw), at the cost of more distance evaluations
+//! per query. Points cannot be removed; handles are insertion indices.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::metric::Metric;
+use crate::rng::SplitMix64;
+use crate::util::Total;
+
+/// The elementary hash family of an [`Lsh`] index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LshFamily {
+    /// The sign of a projection onto a random direction, for angular
+    /// distance.
+    Hyperplane,
+    /// A random projection cut into buckets of the given width, for
+    /// Euclidean distance. The width should be on the order of the
+    /// distances being searched for.
+    PStable {
+        /// Bucket width along each projection.
+        width: f64,
+    },
+}
+
+/// Tuning parameters for an [`Lsh`] index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LshConfig {
+    /// The hash family.
+    pub family: LshFamily,
+    /// Number of independent hash tables. More tables raise recall and
+    /// memory use.
+    pub tables: usize,
+    /// Elementary hashes concatenated per table. More hashes make buckets
+    /// smaller and more selective.
+    pub hashes: usize,
+    /// Extra buckets probed per table by [`Lsh::nearest`].
+    pub probes: usize,
+    /// Seed for the random projections.
+    pub seed: u64,
+}
+
+impl Default for LshConfig {
+    fn default() -> Self {
+        LshConfig {
+            family: LshFamily::Hyperplane,
+            tables: 8,
+            hashes: 12,
+            probes: 8,
+            seed: 0x5eed,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Table {
+    /// `hashes` projection directions of `dim` components each.
+    directions: Vec<f64>,
+    /// Per-hash offsets in `[0, width)`; unused by hyperplanes.
+    offsets: Vec<f64>,
+    buckets: HashMap<u64, Vec<u32>>,
+}
+
+/// A bucket boundary the query could be nudged across.
+#[derive(Clone, Copy, Debug)]
+struct Nudge {
+    cost: f64,
+    hash: usize,
+    delta: i64,
+}
+
+/// A locality-sensitive hashing index over `dim`-dimensional points of type
+/// `P` carrying data of type `T`, ranking candidates under the metric `M`.
+#[derive(Clone, Debug)]
+pub struct Lsh<P, T, M> {
+    dim: usize,
+    config: LshConfig,
+    tables: Vec<Table>,
+    points: Vec<(P, T)>,
+    metric: M,
+}
+
+impl<P: AsRef<[f64]>, T, M: Metric<P>> Lsh<P, T, M> {
+    /// Creates an empty index for points of dimension `dim`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dim` or the table count is zero, if `hashes` is not
+    /// between 1 and 64, or if a p-stable width is not positive and finite.
+    pub fn new(metric: M, dim: usize, config: LshConfig) -> Self {
+        assert!(dim > 0, "dimension must be positive");
+        assert!(config.tables > 0, "table count must be positive");
+        assert!(
+            (1..=64).contains(&config.hashes),
+            "hashes per table must be between 1 and 64"
+        );
+        if let LshFamily::PStable { width } = config.family {
+            assert!(
+                width > 0.0 && width.is_finite(),
+                "bucket width must be positive and finite"
+            );
+        }
+        let mut rng = SplitMix64::new(config.seed);
+        let tables = (0..config.tables)
+            .map(|_| Table {
+                directions: (0..config.hashes * dim)
+                    .map(|_| rng.next_gaussian())
+                    .collect(),
+                offsets: (0..config.hashes)
+                    .map(|_| match config.family {
+                        LshFamily::Hyperplane => 0.0,
+                        LshFamily::PStable { width } => rng.next_f64() * width,
+                    })
+                    .collect(),
+                buckets: HashMap::new(),
+            })
+            .collect();
+        Lsh {
+            dim,
+            config,
+            tables,
+            points: Vec::new(),
+            metric,
+        }
+    }
+
+    /// The dimension of the indexed points.
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the index holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The metric used to rank candidates.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// The tuning parameters.
+    pub fn config(&self) -> &LshConfig {
+        &self.config
+    }
+
+    /// The point and data at an insertion index.
+    pub fn get(&self, index: usize) -> Option<(&P, &T)> {
+        self.points.get(index).map(|(p, t)| (p, t))
+    }
+
+    /// Iterates over the points in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &P, &T)> + '_ {
+        self.points.iter().enumerate().map(|(i, (p, t))| (i, p, t))
+    }
+
+    /// Inserts a point, returning its insertion index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the point's dimension differs from the index's.
+    pub fn insert(&mut self, point: P, data: T) -> usize {
+        assert_eq!(point.as_ref().len(), self.dim, "point dimension mismatch");
+        let id = self.points.len() as u32;
+        for t in 0..self.tables.len() {
+            let (values, _) = self.hash(t, point.as_ref());
+            self.tables[t]
+                .buckets
+                .entry(bucket_key(&values))
+                .or_default()
+                .push(id);
+        }
+        self.points.push((point, data));
+        id as usize
+    }
+
+    /// Returns approximately the `k` points nearest to `query`, closest
+    /// first, probing the configured number of extra buckets per table.
+    pub fn nearest(&self, query: &P, k: usize) -> Vec<(usize, f64)> {
+        self.search(query, k, self.config.probes)
+    }
+
+    /// Returns the approximately nearest point and its distance.
+    pub fn nearest_one(&self, query: &P) -> Option<(usize, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Returns approximately the `k` points nearest to `query`, closest
+    /// first, probing `probes` buckets per table beyond the query's own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query's dimension differs from the index's.
+    pub fn search(&self, query: &P, k: usize, probes: usize) -> Vec<(usize, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::new();
+        for id in self.candidates(query, probes) {
+            let d = self.metric.distance(query, &self.points[id].0);
+            if best.len() < k {
+                best.push((Total(d), id as u32));
+            } else if d < best.peek().expect("k > 0").0 .0 {
+                best.pop();
+                best.push((Total(d), id as u32));
+            }
+        }
+        let mut out: Vec<_> = best.into_iter().map(|(d, id)| (id as usize, d.0)).collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// The distinct insertion indices sharing a probed bucket with `query`,
+    /// in no particular order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the query's dimension differs from the index's.
+    pub fn candidates(&self, query: &P, probes: usize) -> Vec<usize> {
+        let query = query.as_ref();
+        assert_eq!(query.len(), self.dim, "query dimension mismatch");
+        let mut seen = HashSet::new();
+        let mut out = Vec::new();
+        for (t, table) in self.tables.iter().enumerate() {
+            let (values, nudges) = self.hash(t, query);
+            let mut probe = values.clone();
+            for set in probe_sequence(&nudges, probes + 1) {
+                probe.copy_from_slice(&values);
+                for &j in &set {
+                    probe[nudges[j].hash] += nudges[j].delta;
+                }
+                let ids = table.buckets.get(&bucket_key(&probe));
+                for &id in ids.into_iter().flatten() {
+                    if seen.insert(id) {
+                        out.push(id as usize);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// The elementary hash values of `x` in table `t`, and the nudges
+    /// across their nearest boundaries sorted cheapest first.
+    fn hash(&self, t: usize, x: &[f64]) -> (Vec<i64>, Vec<Nudge>) {
+        let table = &self.tables[t];
+        let mut values = Vec::with_capacity(self.config.hashes);
+        let mut nudges = Vec::with_capacity(2 * self.config.hashes);
+        for hash in 0..self.config.hashes {
+            let dir = &table.directions[hash * self.dim..(hash + 1) * self.dim];
+            let dot: f64 = dir.iter().zip(x).map(|(a, b)| a * b).sum();
+            match self.config.family {
+                LshFamily::Hyperplane => {
+                    let v = (dot >= 0.0) as i64;
+                    values.push(v);
+                    nudges.push(Nudge {
+                        cost: dot.abs(),
+                        hash,
+                        delta: 1 - 2 * v,
+                    });
+                }
+                LshFamily::PStable { width } => {
+                    let s = (dot + table.offsets[hash]) / width;
+                    let v = s.floor();
+                    values.push(v as i64);
+                    nudges.push(Nudge {
+                        cost: s - v,
+                        hash,
+                        delta: -1,
+                    });
+                    nudges.push(Nudge {
+                        cost: 1.0 - (s - v),
+                        hash,
+                        delta: 1,
+                    });
+                }
+            }
+        }
+        nudges.sort_by(|a, b| a.cost.total_cmp(&b.cost));
+        (values, nudges)
+    }
+}
+
+/// Up to `count` sets of nudge indices in increasing order of their summed
+/// squared cost, starting with the empty set, generated by Lv et al.'s
+/// shift and expand steps. Sets nudging one hash twice are skipped.
+fn probe_sequence(nudges: &[Nudge], count: usize) -> Vec<Vec<usize>> {
+    let score = |set: &[usize]| set.iter().map(|&j| nudges[j].cost.powi(2)).sum::<f64>();
+    let mut out = vec![Vec::new()];
+    let mut heap = BinaryHeap::new();
+    if !nudges.is_empty() {
+        heap.push(Reverse((Total(score(&[0])), vec![0])));
+    }
+    while out.len() < count {
+        let Some(Reverse((_, set))) = heap.pop() else {
+            break;
+        };
+        let last = *set.last().expect("sets are non-empty");
+        if last + 1 < nudges.len() {
+            let mut shifted = set.clone();
+            *shifted.last_mut().expect("non-empty") = last + 1;
+            heap.push(Reverse((Total(score(&shifted)), shifted)));
+            let mut expanded = set.clone();
+            expanded.push(last + 1);
+            heap.push(Reverse((Total(score(&expanded)), expanded)));
+        }
+        let mut hashes: Vec<usize> = set.iter().map(|&j| nudges[j].hash).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        if hashes.len() == set.len() {
+            out.push(set);
+        }
+    }
+    out
+}
+
+/// Folds a bucket's hash values into a table key. Distinct buckets that
+/// collide only add candidates, which ranking filters out.
+fn bucket_key(values: &[i64]) -> u64 {
+    values.iter().fold(0xcbf2_9ce4_8422_2325, |acc, &v| {
+        (acc ^ v as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
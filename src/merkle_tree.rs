@@ -0,0 +1,289 @@
+//! An append-only Merkle tree with inclusion proofs.
+//!
+//! A Merkle tree (Merkle, 1987) hashes a sequence of leaves pairwise, level
+//! by level, into a single root, so a root commits to every leaf and its
+//! position. A party holding only the root can check that a leaf is in the
+//! sequence from an inclusion proof: the `O(log n)` sibling hashes on the
+//! path from the leaf to the root. Two replicas compare roots to learn
+//! whether they agree, and can compare subtree hashes to find where they
+//! differ.
+//!
+//! The shape is the one Certificate Transparency uses (RFC 6962): a tree
+//! of `n` leaves splits into a perfect left subtree of the largest power
+//! of two below `n` leaves and a right subtree of the rest. Every leaf
+//! keeps its position as the tree grows, and the root of any prefix of
+//! the sequence can still be recomputed. The tree stores the hash of every
+//! perfect subtree, about `2n` digests, so an append combines at most
+//! `log n` pairs and `O(1)` amortized, and a proof or root of any prefix
+//! is built in `O(log n)`.
+//!
+//! Leaves are supplied already hashed, and pairs are combined by a
+//! [`MerkleHasher`], so the digest and its strength are the caller's
+//! choice. [`StableMerkleHasher`] combines with the crate's 64-bit
+//! [`StableHasher`], which is enough to detect accidental divergence but is
+//! not cryptographic; protocols that must resist tampering plug in a
+//! cryptographic hash instead.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::fmt;
+
+use crate::probabilistic::{stable_hash, DEFAULT_SEED};
+
+/// How a Merkle tree combines two child digests into their parent's.
+pub trait MerkleHasher {
+    /// The digest type.
+    type Digest: Clone + Eq;
+
+    /// The digest of a node with children `left` and `right`.
+    fn combine(&self, left: &Self::Digest, right: &Self::Digest) -> Self::Digest;
+}
+
+/// Combines `u64` digests with a seeded [`StableHasher`].
+///
+/// [`StableHasher`]: crate::probabilistic::StableHasher
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StableMerkleHasher {
+    seed: u64,
+}
+
+impl StableMerkleHasher {
+    /// Creates a hasher with the given seed.
+    pub fn new(seed: u64) -> Self {
+        StableMerkleHasher { seed }
+    }
+}
+
+impl Default for StableMerkleHasher {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl MerkleHasher for StableMerkleHasher {
+    type Digest = u64;
+
+    fn combine(&self, left: &u64, right: &u64) -> u64 {
+        stable_hash(self.seed, &(left, right))
+    }
+}
+
+/// The largest power of two strictly below `n`, for `n >= 2`.
+fn split_point(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+/// An append-only Merkle tree over leaf digests.
+#[derive(Clone)]
+pub struct MerkleTree<H: MerkleHasher> {
+    hasher: H,
+    /// `levels[h][i]` is the digest of the perfect subtree over leaves
+    /// `[i << h, (i + 1) << h)`; `levels[0]` holds the leaves.
+    levels: Vec<Vec<H::Digest>>,
+}
+
+impl<H: MerkleHasher + Default> Default for MerkleTree<H> {
+    fn default() -> Self {
+        Self::new(H::default())
+    }
+}
+
+impl<H: MerkleHasher> MerkleTree<H> {
+    /// Creates an empty tree combining digests with `hasher`.
+    pub fn new(hasher: H) -> Self {
+        MerkleTree {
+            hasher,
+            levels: vec![Vec::new()],
+        }
+    }
+
+    /// Builds a tree over `leaves`.
+    pub fn from_leaves(hasher: H, leaves: impl IntoIterator<Item = H::Digest>) -> Self {
+        let mut tree = Self::new(hasher);
+        tree.extend(leaves);
+        tree
+    }
+
+    /// The hasher.
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Whether the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The digest of leaf `index`.
+    pub fn leaf(&self, index: usize) -> Option<&H::Digest> {
+        self.levels[0].get(index)
+    }
+
+    /// The leaf digests, in order.
+    pub fn leaves(&self) -> &[H::Digest] {
+        &self.levels[0]
+    }
+
+    /// Appends a leaf, returning its index.
+    pub fn push(&mut self, leaf: H::Digest) -> usize {
+        let index = self.len();
+        self.levels[0].push(leaf);
+        // Every level whose length became even completed a pair.
+        let mut level = 0;
+        while self.levels[level].len().is_multiple_of(2) {
+            let pair = &self.levels[level][self.levels[level].len() - 2..];
+            let parent = self.hasher.combine(&pair[0], &pair[1]);
+            if level + 1 == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            self.levels[level + 1].push(parent);
+            level += 1;
+        }
+        index
+    }
+
+    /// The root digest, or `None` while the tree is empty.
+    pub fn root(&self) -> Option<H::Digest> {
+        self.root_at(self.len())
+    }
+
+    /// The root the tree had when it held its first `len` leaves, or `None`
+    /// if `len` is zero or more than the number of leaves.
+    pub fn root_at(&self, len: usize) -> Option<H::Digest> {
+        (len > 0 && len <= self.len()).then(|| self.range(0, len))
+    }
+
+    /// A proof that leaf `index` is in the tree, checked against
+    /// [`root`](Self::root).
+    pub fn prove(&self, index: usize) -> Option<MerkleProof<H::Digest>> {
+        self.prove_at(index, self.len())
+    }
+
+    /// A proof that leaf `index` was in the tree when it held its first
+    /// `len` leaves, checked against [`root_at(len)`](Self::root_at).
+    pub fn prove_at(&self, index: usize, len: usize) -> Option<MerkleProof<H::Digest>> {
+        if index >= len || len > self.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let (mut start, mut end) = (0, len);
+        // Walk down from the root, collecting the sibling at each split.
+        while end - start > 1 {
+            let mid = start + split_point(end - start);
+            if index < mid {
+                path.push(self.range(mid, end));
+                end = mid;
+            } else {
+                path.push(self.range(start, mid));
+                start = mid;
+            }
+        }
+        path.reverse();
+        Some(MerkleProof { index, len, path })
+    }
+
+    /// The digest of the subtree over leaves `[start, end)`, where `start`
+    /// is a multiple of the largest power of two at most `end - start`.
+    fn range(&self, start: usize, end: usize) -> H::Digest {
+        let n = end - start;
+        if n.is_power_of_two() {
+            let level = n.trailing_zeros() as usize;
+            return self.levels[level][start >> level].clone();
+        }
+        let mid = start + split_point(n);
+        self.hasher
+            .combine(&self.range(start, mid), &self.range(mid, end))
+    }
+}
+
+impl<H: MerkleHasher> fmt::Debug for MerkleTree<H>
+where
+    H::Digest: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MerkleTree")
+            .field("len", &self.len())
+            .field("root", &self.root())
+            .finish()
+    }
+}
+
+impl<H: MerkleHasher> Extend<H::Digest> for MerkleTree<H> {
+    fn extend<I: IntoIterator<Item = H::Digest>>(&mut self, iter: I) {
+        for leaf in iter {
+            self.push(leaf);
+        }
+    }
+}
+
+impl<H: MerkleHasher + Default> FromIterator<H::Digest> for MerkleTree<H> {
+    fn from_iter<I: IntoIterator<Item = H::Digest>>(iter: I) -> Self {
+        Self::from_leaves(H::default(), iter)
+    }
+}
+
+/// Proof that a leaf sits at a given index in a tree of a given size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<D> {
+    index: usize,
+    len: usize,
+    /// Sibling digests from the leaf up.
+    path: Vec<D>,
+}
+
+impl<D: Clone + Eq> MerkleProof<D> {
+    /// Reassembles a proof, for example after it crossed the network.
+    pub fn from_parts(index: usize, len: usize, path: Vec<D>) -> Self {
+        MerkleProof { index, len, path }
+    }
+
+    /// The index of the proven leaf.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The number of leaves in the tree the proof is for.
+    pub fn tree_len(&self) -> usize {
+        self.len
+    }
+
+    /// The sibling digests, from the leaf up.
+    pub fn path(&self) -> &[D] {
+        &self.path
+    }
+
+    /// Whether `leaf` at this proof's index, combined with the path by
+    /// `hasher`, gives `root`.
+    pub fn verify<H: MerkleHasher<Digest = D>>(&self, hasher: &H, leaf: &D, root: &D) -> bool {
+        if self.index >= self.len {
+            return false;
+        }
+        // The verification of RFC 9162, section 2.1.3.2: `index` and
+        // `last` track the node's position and the last position on its
+        // level, and a node with no right sibling is carried up.
+        let (mut index, mut last) = (self.index, self.len - 1);
+        let mut digest = leaf.clone();
+        for sibling in &self.path {
+            if last == 0 {
+                return false;
+            }
+            if index & 1 == 1 || index == last {
+                digest = hasher.combine(sibling, &digest);
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                digest = hasher.combine(&digest, sibling);
+            }
+            index >>= 1;
+            last >>= 1;
+        }
+        last == 0 && digest == *root
+    }
+}
@@ -0,0 +1,96 @@
+//! Distance functions for the metric-space indexes.
+//!
+//! Metric trees prune by the triangle inequality, so every [`Metric`] must
+//! be a true metric: non-negative, symmetric, zero only between identical
+//! points, and satisfying `d(a, c) <= d(a, b) + d(b, c)`. Implementations are
+//! provided for the common vector norms over anything that derefs to a
+//! slice of `f64`; other point types such as strings only need their own
+//! `Metric` impl.
+
+/// A distance function over points of type `P`.
+pub trait Metric<P: ?Sized> {
+    /// The distance between `a` and `b`.
+    fn distance(&self, a: &P, b: &P) -> f64;
+}
+
+impl<P: ?Sized, F: Fn(&P, &P) -> f64> Metric<P> for F {
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        self(a, b)
+    }
+}
+
+/// The Euclidean (L2) distance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Euclidean;
+
+impl<P: AsRef<[f64]> + ?Sized> Metric<P> for Euclidean {
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// The Manhattan (L1) distance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Manhattan;
+
+impl<P: AsRef<[f64]> + ?Sized> Metric<P> for Manhattan {
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+    }
+}
+
+/// The Chebyshev (L-infinity) distance.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Chebyshev;
+
+impl<P: AsRef<[f64]> + ?Sized> Metric<P> for Chebyshev {
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// Cosine dissimilarity, measured as the angle between the vectors in
+/// radians.
+///
+/// The familiar `1 - cos θ` violates the triangle inequality and would make
+/// tree pruning unsound; the angle itself is a metric on directions and
+/// ranks neighbors identically. It is computed from the unit vectors as
+/// `2 atan2(|â - b̂|, |â + b̂|)`, which is exactly zero between equal vectors
+/// and stays accurate near zero and π, where `acos` of the dot product
+/// does not.
+///
+/// The zero vector has no direction. It is at distance zero from itself
+/// and a right angle, π/2, from every other vector, which keeps
+/// `d(x, x) = 0` and the triangle inequality.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cosine;
+
+impl<P: AsRef<[f64]> + ?Sized> Metric<P> for Cosine {
+    fn distance(&self, a: &P, b: &P) -> f64 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        let na = a.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let nb = b.iter().map(|x| x * x).sum::<f64>().sqrt();
+        match (na == 0.0, nb == 0.0) {
+            (true, true) => return 0.0,
+            (true, false) | (false, true) => return std::f64::consts::FRAC_PI_2,
+            (false, false) => {}
+        }
+        let (mut diff, mut sum) = (0.0, 0.0);
+        for (x, y) in a.iter().zip(b) {
+            let (u, v) = (x / na, y / nb);
+            diff += (u - v) * (u - v);
+            sum += (u + v) * (u + v);
+        }
+        2.0 * diff.sqrt().atan2(sum.sqrt())
+    }
+}
@@ -0,0 +1,110 @@
+//! Morton (Z-order) codes.
+//!
+//! A Morton code interleaves the bits of each coordinate so that points which
+//! are close in space tend to share long code prefixes. The spatial trees in
+//! this crate use the code of a quantized point as its path from the root:
+//! each level of the tree consumes one bit per axis.
+
+/// Spreads the bits of `x` so that bit `i` lands on bit `2i`.
+#[inline]
+pub fn part1by1(x: u32) -> u64 {
+    let mut x = x as u64;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// Inverse of [`part1by1`]: gathers every second bit of `x`.
+#[inline]
+pub fn compact1by1(x: u64) -> u32 {
+    let mut x = x & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff;
+    x as u32
+}
+
+/// Spreads the low 21 bits of `x` so that bit `i` lands on bit `3i`.
+#[inline]
+pub fn part1by2(x: u32) -> u64 {
+    let mut x = (x as u64) & 0x1f_ffff;
+    x = (x | (x << 32)) & 0x001f_0000_0000_ffff;
+    x = (x | (x << 16)) & 0x001f_0000_ff00_00ff;
+    x = (x | (x << 8)) & 0x100f_00f0_0f00_f00f;
+    x = (x | (x << 4)) & 0x10c3_0c30_c30c_30c3;
+    x = (x | (x << 2)) & 0x1249_2492_4924_9249;
+    x
+}
+
+/// Inverse of [`part1by2`]: gathers every third bit of `x`.
+#[inline]
+pub fn compact1by2(x: u64) -> u32 {
+    let mut x = x & 0x1249_2492_4924_9249;
+    x = (x | (x >> 2)) & 0x10c3_0c30_c30c_30c3;
+    x = (x | (x >> 4)) & 0x100f_00f0_0f00_f00f;
+    x = (x | (x >> 8)) & 0x001f_0000_ff00_00ff;
+    x = (x | (x >> 16)) & 0x001f_0000_0000_ffff;
+    x = (x | (x >> 32)) & 0x1f_ffff;
+    x as u32
+}
+
+/// Interleaves two 32-bit coordinates; `x` occupies the even bits.
+#[inline]
+pub fn encode2(x: u32, y: u32) -> u64 {
+    part1by1(x) | (part1by1(y) << 1)
+}
+
+/// Splits a 2D code back into its coordinates.
+#[inline]
+pub fn decode2(code: u64) -> (u32, u32) {
+    (compact1by1(code), compact1by1(code >> 1))
+}
+
+/// Interleaves three 21-bit coordinates; `x` occupies bits `0, 3, 6, ...`.
+///
+/// Bits above the 21st are ignored.
+#[inline]
+pub fn encode3(x: u32, y: u32, z: u32) -> u64 {
+    part1by2(x) | (part1by2(y) << 1) | (part1by2(z) << 2)
+}
+
+/// Splits a 3D code back into its coordinates.
+#[inline]
+pub fn decode3(code: u64) -> (u32, u32, u32) {
+    (
+        compact1by2(code),
+        compact1by2(code >> 1),
+        compact1by2(code >> 2),
+    )
+}
+
+/// Interleaves up to four 32-bit coordinates into a 128-bit code; bit `i`
+/// of `coords[a]` lands on bit `i * N + a`.
+///
+/// # Panics
+///
+/// Panics if `N` exceeds 4.
+pub fn encode<const N: usize>(coords: &[u32; N]) -> u128 {
+    assert!(N <= 4, "at most four coordinates fit in 128 bits");
+    let mut code = 0u128;
+    for bit in 0..32 {
+        for (a, &c) in coords.iter().enumerate() {
+            code |= (((c >> bit) & 1) as u128) << (bit * N + a);
+        }
+    }
+    code
+}
+
+/// Splits an `N`-dimensional code back into its coordinates.
+pub fn decode<const N: usize>(code: u128) -> [u32; N] {
+    std::array::from_fn(|a| {
+        (0..32).fold(0, |acc, bit| {
+            acc | (((code >> (bit * N + a)) & 1) as u32) << bit
+        })
+    })
+}
@@ -0,0 +1,185 @@
+//! A bounded lock-free multi-producer multi-consumer queue.
+//!
+//! This is Vyukov's bounded MPMC queue (2010). The queue is a ring of
+//! slots, each pairing a value with a sequence number, plus two counters:
+//! the next position to push and the next to pop. A slot's sequence says
+//! whose turn it is. A producer that reads its position's slot holding
+//! sequence `pos` knows the slot is empty and its turn; it claims the
+//! position by advancing the push counter with a compare-and-swap, writes
+//! the value, and publishes it by storing `pos + 1`. A consumer waits for
+//! `pos + 1` in the same way, takes the value, and hands the slot to the
+//! producer one lap later by storing `pos + capacity`.
+//!
+//! Producers contend only on the push counter and consumers only on the
+//! pop counter, each with one compare-and-swap per operation, and the
+//! counters sit on separate cache lines. No operation blocks: a full
+//! queue fails a push and an empty one fails a pop, and the caller picks
+//! how to wait. A thread stalled between claiming a slot and publishing or
+//! freeing it leaves the slot looking empty or full to the others until it
+//! resumes, so progress is not guaranteed in the strict lock-free sense,
+//! but no thread ever waits on a lock.
+//!
+//! The capacity is rounded up to a power of two, and to at least two: with
+//! a single slot, a full slot's sequence would read as empty to the next
+//! lap's producer.
+//!
+//! The concurrency is checked with loom; run the model tests with
+//! `RUSTFLAGS="--cfg loom" cargo test --release --test loom_mpmc`.
+
+use std::fmt;
+use std::mem::MaybeUninit;
+
+use crate::sync::{AtomicUsize, Ordering, UnsafeCell};
+
+/// A value aligned to its own cache line, so that counters written by
+/// different threads do not share one.
+#[repr(align(128))]
+struct CachePadded<T>(T);
+
+struct Slot<T> {
+    /// The position this slot is ready for: `pos` when empty for the push
+    /// at `pos`, `pos + 1` when full for the pop at `pos`.
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A fixed-capacity queue that any number of threads can push to and pop
+/// from through a shared reference.
+pub struct MpmcQueue<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    push: CachePadded<AtomicUsize>,
+    pop: CachePadded<AtomicUsize>,
+}
+
+// SAFETY: values move between threads through the queue, which is sound
+// for `T: Send`; each slot's value is only accessed by the one thread
+// whose claimed position matches the slot's sequence.
+unsafe impl<T: Send> Send for MpmcQueue<T> {}
+unsafe impl<T: Send> Sync for MpmcQueue<T> {}
+
+impl<T> MpmcQueue<T> {
+    /// Creates an empty queue holding at least `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        MpmcQueue {
+            slots: (0..capacity)
+                .map(|i| Slot {
+                    sequence: AtomicUsize::new(i),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+            mask: capacity - 1,
+            push: CachePadded(AtomicUsize::new(0)),
+            pop: CachePadded(AtomicUsize::new(0)),
+        }
+    }
+
+    /// The number of values the queue holds when full.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Number of values, which other threads may change at any moment.
+    pub fn len(&self) -> usize {
+        loop {
+            let push = self.push.0.load(Ordering::SeqCst);
+            let pop = self.pop.0.load(Ordering::SeqCst);
+            // Retry if the push counter moved while the pop was read.
+            if self.push.0.load(Ordering::SeqCst) == push {
+                return push.wrapping_sub(pop).min(self.capacity());
+            }
+        }
+    }
+
+    /// Whether the queue held no values when checked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the queue was full when checked.
+    pub fn is_full(&self) -> bool {
+        self.len() == self.capacity()
+    }
+
+    /// Appends `value`, or returns it if the queue is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut pos = self.push.0.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos) as isize {
+                0 => match self.push.0.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: the compare-and-swap made this thread the
+                        // slot's only producer for this lap, and its
+                        // sequence says the consumer of the last lap is
+                        // done with it.
+                        slot.value.with_mut(|v| unsafe { (*v).write(value) });
+                        slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                },
+                // The slot still holds last lap's value: full.
+                d if d < 0 => return Err(value),
+                // Another producer took this position; catch up.
+                _ => pos = self.push.0.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Removes and returns the oldest value, or `None` if the queue is
+    /// empty.
+    pub fn pop(&self) -> Option<T> {
+        let mut pos = self.pop.0.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match sequence.wrapping_sub(pos.wrapping_add(1)) as isize {
+                0 => match self.pop.0.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: the sequence, read with acquire, says the
+                        // producer published a value here, and the
+                        // compare-and-swap made this thread its only
+                        // consumer.
+                        let value = slot.value.with(|v| unsafe { (*v).assume_init_read() });
+                        slot.sequence
+                            .store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                },
+                // Not yet pushed: empty.
+                d if d < 0 => return None,
+                // Another consumer took this position; catch up.
+                _ => pos = self.pop.0.load(Ordering::Relaxed),
+            }
+        }
+    }
+}
+
+impl<T> Drop for MpmcQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+    }
+}
+
+impl<T> fmt::Debug for MpmcQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpmcQueue")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
@@ -0,0 +1,411 @@
+//! A dynamic M-tree for similarity search under any [`Metric`].
+//!
+//! The M-tree of Ciaccia, Patella and Zezula is a balanced, paged tree in
+//! the mould of a B-tree. Each node holds up to a fixed number of entries.
+//! A leaf entry is a data point. A routing entry names a point as the
+//! center of a ball, together with its covering radius and the child node
+//! holding everything in the ball. Every entry also stores its distance to
+//! the routing point of its own node. With those stored distances a query
+//! that already knows its distance to the parent can rule an entry out with
+//! the triangle inequality, without evaluating the metric at all. That
+//! matters when the metric is the expensive part: edit distances, alignment
+//! scores, distances between images.
+//!
+//! Insertion descends to the leaf whose ball needs the least enlargement.
+//! A node that overflows is split in two by promoting the pair of entries
+//! whose generalized-hyperplane partition minimizes the larger covering
+//! radius (the mM_RAD policy), and splits propagate toward the root. All
+//! leaves stay at the same depth. The upper levels are small and bounded
+//! in fan-out, so the layout maps directly onto disk pages. That is the
+//! M-tree's advantage over the static [`BallTree`], which must be rebuilt
+//! to grow, and over the [`CoverTree`], whose fan-out is unbounded.
+//!
+//! Points cannot be removed; handles are insertion indices.
+//!
+//! [`Metric`]: crate::metric::Metric
+//! [`BallTree`]: crate::balltree::BallTree
+//! [`CoverTree`]: crate::covertree::CoverTree
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::metric::Metric;
+use crate::util::Total;
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    /// Storage index of the entry's point: the data point of a leaf entry,
+    /// the center of a routing entry.
+    object: u32,
+    /// Covering radius of a routing entry; zero in leaves.
+    radius: f64,
+    /// Distance to the routing point of the node holding this entry; zero
+    /// in the root.
+    to_parent: f64,
+    /// The subtree of a routing entry; `NONE` in leaves.
+    child: u32,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    leaf: bool,
+    parent: u32,
+    entries: Vec<Entry>,
+}
+
+/// An M-tree mapping points of type `P` to data of type `T` under the
+/// metric `M`.
+#[derive(Clone, Debug)]
+pub struct MTree<P, T, M> {
+    points: Vec<(P, T)>,
+    nodes: Vec<Node>,
+    root: u32,
+    capacity: usize,
+    metric: M,
+}
+
+impl<P, T, M: Metric<P>> MTree<P, T, M> {
+    /// Creates an empty tree with nodes of up to 16 entries.
+    pub fn new(metric: M) -> Self {
+        Self::with_node_capacity(metric, 16)
+    }
+
+    /// Creates an empty tree with nodes of up to `capacity` entries.
+    ///
+    /// Splitting costs time cubic in the capacity, so capacities much
+    /// beyond a hundred make insertion slow.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is less than 4.
+    pub fn with_node_capacity(metric: M, capacity: usize) -> Self {
+        assert!(
+            capacity >= 4,
+            "M-tree nodes need room for at least 4 entries"
+        );
+        MTree {
+            points: Vec::new(),
+            nodes: Vec::new(),
+            root: NONE,
+            capacity,
+            metric,
+        }
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// The metric the tree was built with.
+    pub fn metric(&self) -> &M {
+        &self.metric
+    }
+
+    /// The largest number of entries a node holds.
+    pub fn node_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of node levels; zero for an empty tree.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut n = self.root;
+        while n != NONE {
+            height += 1;
+            let node = &self.nodes[n as usize];
+            n = if node.leaf {
+                NONE
+            } else {
+                node.entries[0].child
+            };
+        }
+        height
+    }
+
+    /// The point and data at an insertion index.
+    pub fn get(&self, index: usize) -> Option<(&P, &T)> {
+        self.points.get(index).map(|(p, t)| (p, t))
+    }
+
+    /// Returns the point and mutable data at an insertion index.
+    pub fn get_mut(&mut self, index: usize) -> Option<(&P, &mut T)> {
+        self.points.get_mut(index).map(|(p, t)| (&*p, t))
+    }
+
+    /// Iterates over the points in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &P, &T)> + '_ {
+        self.points.iter().enumerate().map(|(i, (p, t))| (i, p, t))
+    }
+
+    /// Inserts a point, returning its insertion index.
+    pub fn insert(&mut self, point: P, data: T) -> usize {
+        let id = self.points.len() as u32;
+        self.points.push((point, data));
+        if self.root == NONE {
+            self.nodes.push(Node {
+                leaf: true,
+                parent: NONE,
+                entries: Vec::new(),
+            });
+            self.root = (self.nodes.len() - 1) as u32;
+        }
+        let mut n = self.root;
+        let mut to_parent = 0.0;
+        while !self.nodes[n as usize].leaf {
+            // Prefer a ball that already covers the point, the closest one;
+            // otherwise the one that needs the least enlargement.
+            let mut best = (false, f64::INFINITY, 0, 0.0);
+            for (i, e) in self.nodes[n as usize].entries.iter().enumerate() {
+                let d = self.distance(id, e.object);
+                let key = if d <= e.radius {
+                    (true, d)
+                } else {
+                    (false, d - e.radius)
+                };
+                if (key.0 && !best.0) || (key.0 == best.0 && key.1 < best.1) {
+                    best = (key.0, key.1, i, d);
+                }
+            }
+            let entry = &mut self.nodes[n as usize].entries[best.2];
+            entry.radius = entry.radius.max(best.3);
+            to_parent = best.3;
+            n = entry.child;
+        }
+        self.nodes[n as usize].entries.push(Entry {
+            object: id,
+            radius: 0.0,
+            to_parent,
+            child: NONE,
+        });
+        self.split_if_full(n);
+        id as usize
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// distances.
+    pub fn nearest(&self, query: &P, k: usize) -> Vec<(usize, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((Total(0.0), self.root, None)));
+        let kth = |best: &BinaryHeap<(Total, u32)>| {
+            if best.len() == k {
+                best.peek().map_or(f64::INFINITY, |b| b.0 .0)
+            } else {
+                f64::INFINITY
+            }
+        };
+        while let Some(Reverse((Total(bound), n, to_routing))) = frontier.pop() {
+            if bound >= kth(&best) {
+                break;
+            }
+            let node = &self.nodes[n as usize];
+            for e in &node.entries {
+                if let Some(dp) = to_routing {
+                    if lower_bound(dp, e) >= kth(&best) {
+                        continue;
+                    }
+                }
+                let d = self
+                    .metric
+                    .distance(query, &self.points[e.object as usize].0);
+                if node.leaf {
+                    if best.len() < k {
+                        best.push((Total(d), e.object));
+                    } else if d < kth(&best) {
+                        best.pop();
+                        best.push((Total(d), e.object));
+                    }
+                } else {
+                    let lower = (d - e.radius).max(0.0);
+                    if lower < kth(&best) {
+                        frontier.push(Reverse((Total(lower), e.child, Some(Total(d)))));
+                    }
+                }
+            }
+        }
+        let mut out: Vec<_> = best.into_iter().map(|(d, i)| (i as usize, d.0)).collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one(&self, query: &P) -> Option<(usize, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// Returns every point within `radius` of `query` with its distance, in
+    /// unspecified order.
+    pub fn within_radius(&self, query: &P, radius: f64) -> Vec<(usize, f64)> {
+        let mut out = Vec::new();
+        let mut stack = if self.is_empty() {
+            Vec::new()
+        } else {
+            vec![(self.root, None)]
+        };
+        while let Some((n, to_routing)) = stack.pop() {
+            let node = &self.nodes[n as usize];
+            for e in &node.entries {
+                if let Some(dp) = to_routing {
+                    if lower_bound(dp, e) > radius {
+                        continue;
+                    }
+                }
+                let d = self
+                    .metric
+                    .distance(query, &self.points[e.object as usize].0);
+                if node.leaf {
+                    if d <= radius {
+                        out.push((e.object as usize, d));
+                    }
+                } else if d - e.radius <= radius {
+                    stack.push((e.child, Some(Total(d))));
+                }
+            }
+        }
+        out
+    }
+
+    fn distance(&self, a: u32, b: u32) -> f64 {
+        self.metric
+            .distance(&self.points[a as usize].0, &self.points[b as usize].0)
+    }
+
+    /// The routing point of node `n`, if it is not the root.
+    fn routing_object(&self, n: u32) -> Option<u32> {
+        let parent = self.nodes[n as usize].parent;
+        if parent == NONE {
+            return None;
+        }
+        let entries = &self.nodes[parent as usize].entries;
+        entries.iter().find(|e| e.child == n).map(|e| e.object)
+    }
+
+    /// Splits node `n` if it holds more entries than the capacity, and its
+    /// ancestors in turn.
+    fn split_if_full(&mut self, n: u32) {
+        if self.nodes[n as usize].entries.len() <= self.capacity {
+            return;
+        }
+        let entries = std::mem::take(&mut self.nodes[n as usize].entries);
+        let k = entries.len();
+        let mut dist = vec![0.0; k * k];
+        for i in 0..k {
+            for j in i + 1..k {
+                let d = self.distance(entries[i].object, entries[j].object);
+                dist[i * k + j] = d;
+                dist[j * k + i] = d;
+            }
+        }
+        // Promote the pair whose partition has the smallest larger radius.
+        let mut promoted = (f64::INFINITY, 0, 1);
+        for a in 0..k {
+            for b in a + 1..k {
+                let (mut ra, mut rb) = (0.0f64, 0.0f64);
+                for (i, e) in entries.iter().enumerate() {
+                    let (da, db) = (dist[a * k + i], dist[b * k + i]);
+                    if i == a || (i != b && da <= db) {
+                        ra = ra.max(da + e.radius);
+                    } else {
+                        rb = rb.max(db + e.radius);
+                    }
+                }
+                if ra.max(rb) < promoted.0 {
+                    promoted = (ra.max(rb), a, b);
+                }
+            }
+        }
+        let (_, a, b) = promoted;
+        let (oa, ob) = (entries[a].object, entries[b].object);
+        let leaf = self.nodes[n as usize].leaf;
+        let sibling = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            leaf,
+            parent: NONE,
+            entries: Vec::new(),
+        });
+        let (mut ra, mut rb) = (0.0f64, 0.0f64);
+        for (i, mut e) in entries.into_iter().enumerate() {
+            let (da, db) = (dist[a * k + i], dist[b * k + i]);
+            let target = if i == a || (i != b && da <= db) {
+                e.to_parent = da;
+                ra = ra.max(da + e.radius);
+                n
+            } else {
+                e.to_parent = db;
+                rb = rb.max(db + e.radius);
+                sibling
+            };
+            if !leaf {
+                self.nodes[e.child as usize].parent = target;
+            }
+            self.nodes[target as usize].entries.push(e);
+        }
+        let parent = self.nodes[n as usize].parent;
+        if parent == NONE {
+            let root = self.nodes.len() as u32;
+            self.nodes.push(Node {
+                leaf: false,
+                parent: NONE,
+                entries: vec![
+                    Entry {
+                        object: oa,
+                        radius: ra,
+                        to_parent: 0.0,
+                        child: n,
+                    },
+                    Entry {
+                        object: ob,
+                        radius: rb,
+                        to_parent: 0.0,
+                        child: sibling,
+                    },
+                ],
+            });
+            self.nodes[n as usize].parent = root;
+            self.nodes[sibling as usize].parent = root;
+            self.root = root;
+            return;
+        }
+        let grand = self.routing_object(parent);
+        let (pa, pb) = match grand {
+            Some(g) => (self.distance(g, oa), self.distance(g, ob)),
+            None => (0.0, 0.0),
+        };
+        let entries = &mut self.nodes[parent as usize].entries;
+        let slot = entries
+            .iter_mut()
+            .find(|e| e.child == n)
+            .expect("node in its parent");
+        *slot = Entry {
+            object: oa,
+            radius: ra,
+            to_parent: pa,
+            child: n,
+        };
+        entries.push(Entry {
+            object: ob,
+            radius: rb,
+            to_parent: pb,
+            child: sibling,
+        });
+        self.nodes[sibling as usize].parent = parent;
+        self.split_if_full(parent);
+    }
+}
+
+/// The triangle-inequality lower bound on the distance from a query to the
+/// points under `entry`, given the query's distance to the entry's parent.
+fn lower_bound(to_parent: Total, entry: &Entry) -> f64 {
+    ((to_parent.0 - entry.to_parent).abs() - entry.radius).max(0.0)
+}
@@ -0,0 +1,196 @@
+//! Brute-force reference implementations of the spatial queries.
+//!
+//! Everything here answers queries by scanning every item, so it is slow
+//! but obviously correct. The functions and [`NaiveIndex`] exist to be
+//! compared against: feeding the same random inputs to an index and to its
+//! naive counterpart catches the off-by-one prunings and boundary slips that
+//! hand-written cases miss. Conventions match the indexes: boxes and balls
+//! are closed, distances are Euclidean, and nearest-neighbor results are
+//! sorted closest first.
+
+use crate::geom::{Aabb, Ray};
+use crate::orthtree::{OutOfBounds, Point};
+use crate::spatial_index::SpatialIndex;
+
+/// Up to `k` of `points` nearest to `query`, closest first, as indices with
+/// their distances. Ties are broken by index.
+pub fn nearest<const N: usize>(
+    points: &[[f64; N]],
+    query: &[f64; N],
+    k: usize,
+) -> Vec<(usize, f64)> {
+    let mut all: Vec<(usize, f64)> = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i, distance(p, query)))
+        .collect();
+    all.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    all.truncate(k);
+    all
+}
+
+/// The indices of the `points` within `radius` of `center`, ascending.
+pub fn within_radius<const N: usize>(
+    points: &[[f64; N]],
+    center: &[f64; N],
+    radius: f64,
+) -> Vec<usize> {
+    (0..points.len())
+        .filter(|&i| distance_squared(&points[i], center) <= radius * radius)
+        .collect()
+}
+
+/// The indices of the `points` inside `query`, ascending.
+pub fn within_box<const N: usize>(points: &[[f64; N]], query: &Aabb<N>) -> Vec<usize> {
+    (0..points.len())
+        .filter(|&i| query.contains_point(&points[i]))
+        .collect()
+}
+
+/// The indices of the `boxes` intersecting `query`, ascending.
+pub fn intersecting<const N: usize>(boxes: &[Aabb<N>], query: &Aabb<N>) -> Vec<usize> {
+    (0..boxes.len())
+        .filter(|&i| boxes[i].intersects(query))
+        .collect()
+}
+
+/// The nearest of `items` hit by `ray` before `max_t`, with its hit
+/// parameter, where `hit` reports where the ray meets an item. Ties go to
+/// the lowest index.
+pub fn closest_hit<T, const N: usize>(
+    items: &[T],
+    ray: &Ray<N>,
+    max_t: f64,
+    mut hit: impl FnMut(&T, &Ray<N>) -> Option<f64>,
+) -> Option<(usize, f64)> {
+    let mut best: Option<(usize, f64)> = None;
+    for (i, item) in items.iter().enumerate() {
+        if let Some(t) = hit(item, ray).filter(|&t| t <= max_t) {
+            if best.is_none_or(|(_, b)| t < b) {
+                best = Some((i, t));
+            }
+        }
+    }
+    best
+}
+
+/// The nearest of `boxes` entered by `ray` before `max_t`, with the
+/// parameter at which the ray enters it.
+pub fn raycast<const N: usize>(
+    boxes: &[Aabb<N>],
+    ray: &Ray<N>,
+    max_t: f64,
+) -> Option<(usize, f64)> {
+    closest_hit(boxes, ray, max_t, |b, r| r.hit_aabb(b, max_t))
+}
+
+/// An unbounded [`SpatialIndex`] that keeps its points in a list and scans
+/// all of them for every query.
+#[derive(Clone, Debug)]
+pub struct NaiveIndex<P, T, const N: usize> {
+    items: Vec<Option<(P, T)>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+impl<P, T, const N: usize> Default for NaiveIndex<P, T, N> {
+    fn default() -> Self {
+        NaiveIndex {
+            items: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<P: Point<N>, T, const N: usize> NaiveIndex<P, T, N> {
+    /// Creates an empty index.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over the stored points in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &P, &T)> + '_ {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.as_ref().map(|(p, t)| (i, p, t)))
+    }
+
+    /// Moves a point, returning its previous position.
+    pub fn relocate(&mut self, id: usize, to: P) -> Option<P> {
+        let (p, _) = self.items.get_mut(id)?.as_mut()?;
+        Some(std::mem::replace(p, to))
+    }
+
+    fn select(&self, keep: impl Fn(&[f64; N]) -> bool) -> Vec<usize> {
+        self.iter()
+            .filter(|(_, p, _)| keep(&p.coords()))
+            .map(|(i, _, _)| i)
+            .collect()
+    }
+}
+
+impl<P: Point<N>, T, const N: usize> SpatialIndex<P> for NaiveIndex<P, T, N> {
+    type Id = usize;
+    type Data = T;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, point: P, data: T) -> Result<usize, OutOfBounds> {
+        self.len += 1;
+        match self.free.pop() {
+            Some(i) => {
+                self.items[i] = Some((point, data));
+                Ok(i)
+            }
+            None => {
+                self.items.push(Some((point, data)));
+                Ok(self.items.len() - 1)
+            }
+        }
+    }
+
+    fn remove(&mut self, id: usize) -> Option<(P, T)> {
+        let item = self.items.get_mut(id)?.take()?;
+        self.free.push(id);
+        self.len -= 1;
+        Some(item)
+    }
+
+    fn get(&self, id: usize) -> Option<(P, &T)> {
+        let (p, t) = self.items.get(id)?.as_ref()?;
+        Some((*p, t))
+    }
+
+    fn nearest(&self, query: &P, k: usize) -> Vec<(usize, f64)> {
+        let q = query.coords();
+        let mut all: Vec<(usize, f64)> = self
+            .iter()
+            .map(|(i, p, _)| (i, distance(&p.coords(), &q)))
+            .collect();
+        all.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        all.truncate(k);
+        all
+    }
+
+    fn within_radius(&self, center: &P, radius: f64) -> Vec<usize> {
+        let c = center.coords();
+        self.select(|p| distance_squared(p, &c) <= radius * radius)
+    }
+
+    fn query_aabb(&self, min: &P, max: &P) -> Vec<usize> {
+        let query = Aabb::new(min.coords(), max.coords());
+        self.select(|p| query.contains_point(p))
+    }
+}
+
+fn distance<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    distance_squared(a, b).sqrt()
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
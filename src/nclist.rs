@@ -0,0 +1,239 @@
+//! A nested containment list: a static index of half-open intervals.
+//!
+//! The intervals are sorted by start, and every interval that lies inside
+//! another is moved into the sublist of the innermost interval containing
+//! it. No interval in a list contains another, so the intervals of a list
+//! ascend in both start and end. An overlap query can then binary-search
+//! each list for the first interval ending after the query begins and
+//! scan forward until the starts pass the query's end. Sublists are only
+//! entered below an interval that overlaps the query, and every interval
+//! scanned is a match.
+//!
+//! All lists are packed into flat arrays, one after another. On large
+//! read-only data, such as genomic annotations, this beats a pointer-based
+//! [`IntervalTree`] by a wide margin. The list cannot be modified after
+//! it is built. Queries report each interval's index in the input, and
+//! [`NcList::get`] recovers the interval and its value.
+//!
+//! [`IntervalTree`]: crate::interval_tree::IntervalTree
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+const NONE: u32 = u32::MAX;
+
+/// A nested containment list mapping half-open ranges of `K` to values of
+/// type `V`.
+#[derive(Clone, Debug)]
+pub struct NcList<K, V> {
+    /// Intervals in list order; each list is a contiguous run.
+    ranges: Vec<Range<K>>,
+    values: Vec<V>,
+    /// Input index of the interval at each position.
+    ids: Vec<u32>,
+    /// List nested under the interval at each position, or `NONE`.
+    sublists: Vec<u32>,
+    /// List `i` covers positions `lists[i]..lists[i + 1]`; list 0 is the
+    /// top level.
+    lists: Vec<u32>,
+    /// Position of each input index.
+    slots: Vec<u32>,
+}
+
+impl<K, V> Default for NcList<K, V> {
+    fn default() -> Self {
+        NcList {
+            ranges: Vec::new(),
+            values: Vec::new(),
+            ids: Vec::new(),
+            sublists: Vec::new(),
+            lists: vec![0, 0],
+            slots: Vec::new(),
+        }
+    }
+}
+
+impl<K: Ord + Copy, V> NcList<K, V> {
+    /// Builds a list from `(range, value)` pairs in any order, in
+    /// `O(n log n)`. Each interval's index is its position in `items`.
+    ///
+    /// Empty intervals (`start >= end`) are stored but never match a query.
+    pub fn build(items: Vec<(Range<K>, V)>) -> Self {
+        let mut order: Vec<u32> = (0..items.len() as u32).collect();
+        order.sort_by_key(|&i| {
+            let r = &items[i as usize].0;
+            (r.start, Reverse(r.end))
+        });
+        Self::nest(items, order)
+    }
+
+    /// Builds a list from intervals already sorted by start, in `O(n)`
+    /// unless many intervals share a start. Each interval's index is its
+    /// position in `items`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `items` is not sorted by start.
+    pub fn from_sorted(items: &[(Range<K>, V)]) -> Self
+    where
+        V: Clone,
+    {
+        assert!(
+            items.windows(2).all(|w| w[0].0.start <= w[1].0.start),
+            "intervals must be sorted by start"
+        );
+        // Within a run of equal starts, the longest interval goes first so
+        // that it can contain the rest.
+        let mut order: Vec<u32> = (0..items.len() as u32).collect();
+        for run in
+            order.chunk_by_mut(|&a, &b| items[a as usize].0.start == items[b as usize].0.start)
+        {
+            if run.len() > 1 {
+                run.sort_by_key(|&i| Reverse(items[i as usize].0.end));
+            }
+        }
+        Self::nest(items.to_vec(), order)
+    }
+
+    /// Lays out `items` visited in `order`, which sorts them by start and,
+    /// among equal starts, by descending end.
+    fn nest(items: Vec<(Range<K>, V)>, order: Vec<u32>) -> Self {
+        let n = items.len();
+        // Children of each interval in start order, with the top level last.
+        let mut children: Vec<Vec<u32>> = vec![Vec::new(); n + 1];
+        let mut open: Vec<u32> = Vec::new();
+        for &i in &order {
+            let end = items[i as usize].0.end;
+            while let Some(&top) = open.last() {
+                if end <= items[top as usize].0.end {
+                    break;
+                }
+                open.pop();
+            }
+            let parent = open.last().map_or(n, |&p| p as usize);
+            children[parent].push(i);
+            open.push(i);
+        }
+        // Lay the lists out breadth first, numbering them as they are met.
+        let mut placed: Vec<Option<(Range<K>, V)>> = items.into_iter().map(Some).collect();
+        let mut list = NcList {
+            ranges: Vec::with_capacity(n),
+            values: Vec::with_capacity(n),
+            ids: Vec::with_capacity(n),
+            sublists: Vec::with_capacity(n),
+            lists: vec![0],
+            slots: vec![NONE; n],
+        };
+        let mut pending = vec![n];
+        let mut next = 0;
+        while next < pending.len() {
+            let members = std::mem::take(&mut children[pending[next]]);
+            next += 1;
+            for i in members {
+                let (range, value) = placed[i as usize].take().expect("placed once");
+                list.slots[i as usize] = list.ranges.len() as u32;
+                list.ranges.push(range);
+                list.values.push(value);
+                list.ids.push(i);
+                if children[i as usize].is_empty() {
+                    list.sublists.push(NONE);
+                } else {
+                    list.sublists.push(pending.len() as u32);
+                    pending.push(i as usize);
+                }
+            }
+            list.lists.push(list.ranges.len() as u32);
+        }
+        list
+    }
+
+    /// Number of stored intervals.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether the list holds no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the interval and value at an input index.
+    pub fn get(&self, index: usize) -> Option<(&Range<K>, &V)> {
+        let pos = *self.slots.get(index)? as usize;
+        Some((&self.ranges[pos], &self.values[pos]))
+    }
+
+    /// Iterates over every interval, with its input index, in unspecified
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Range<K>, &V)> + '_ {
+        (0..self.len()).map(move |pos| self.entry(pos))
+    }
+
+    /// Iterates over the intervals containing `point`.
+    pub fn stabbing(&self, point: K) -> impl Iterator<Item = (usize, &Range<K>, &V)> + '_ {
+        self.query(point, move |start| start <= point)
+    }
+
+    /// Iterates over the intervals sharing at least one point with `range`.
+    ///
+    /// An empty `range` overlaps nothing.
+    pub fn overlapping(
+        &self,
+        range: Range<K>,
+    ) -> impl Iterator<Item = (usize, &Range<K>, &V)> + '_ {
+        let (start, end) = (range.start, range.end);
+        self.query(start, move |s| s < end && start < end)
+    }
+
+    /// Whether any stored interval contains `point`.
+    pub fn contains_point(&self, point: K) -> bool {
+        self.stabbing(point).next().is_some()
+    }
+
+    /// Scans, in each list entered, the intervals ending after `from` whose
+    /// starts pass `starts_in`, descending into the sublists of those that
+    /// match.
+    fn query<'a>(
+        &'a self,
+        from: K,
+        starts_in: impl Fn(K) -> bool + 'a,
+    ) -> impl Iterator<Item = (usize, &'a Range<K>, &'a V)> + 'a {
+        let mut stack = vec![self.cursor(0, from)];
+        std::iter::from_fn(move || loop {
+            let (pos, end) = stack.last_mut()?;
+            if *pos >= *end || !starts_in(self.ranges[*pos].start) {
+                stack.pop();
+                continue;
+            }
+            let p = *pos;
+            *pos += 1;
+            let r = &self.ranges[p];
+            if r.start >= r.end {
+                continue;
+            }
+            if self.sublists[p] != NONE {
+                stack.push(self.cursor(self.sublists[p], from));
+            }
+            return Some(self.entry(p));
+        })
+    }
+
+    /// The positions of list `list` from its first interval ending after
+    /// `from` to its end.
+    fn cursor(&self, list: u32, from: K) -> (usize, usize) {
+        let lo = self.lists[list as usize] as usize;
+        let hi = self.lists[list as usize + 1] as usize;
+        let skip = self.ranges[lo..hi].partition_point(|r| r.end <= from);
+        (lo + skip, hi)
+    }
+
+    fn entry(&self, pos: usize) -> (usize, &Range<K>, &V) {
+        (self.ids[pos] as usize, &self.ranges[pos], &self.values[pos])
+    }
+}
+
+impl<K: Ord + Copy, V> FromIterator<(Range<K>, V)> for NcList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Range<K>, V)>>(iter: I) -> Self {
+        NcList::build(iter.into_iter().collect())
+    }
+}
@@ -0,0 +1,11 @@
+//! A bucketed region octree over 3D points.
+//!
+//! The octree is the three-dimensional [`Orthtree`]: every node splits into
+//! eight octants, and points are routed by their Morton order. See the
+//! [`orthtree`](crate::orthtree) module for the shared implementation.
+
+use crate::orthtree::Orthtree;
+pub use crate::orthtree::{Orthant, OutOfBounds, Point, PointId, MAX_DEPTH};
+
+/// An octree mapping 3D points of type `P` to data of type `D`.
+pub type Octree<P, D> = Orthtree<P, D, 3>;
@@ -0,0 +1,959 @@
+//! A bucketed region orthtree over `N`-dimensional points.
+//!
+//! An orthtree splits every node into `2^N` equal children, one per orthant:
+//! a quadtree for `N = 2`, an octree for `N = 3`, and the same machinery for
+//! space-time or colour-space data in higher dimensions. The tree covers a
+//! fixed box chosen at construction. Every point is quantized onto a
+//! `2^max_depth` grid along each axis and routed by the bits of its cell
+//! coordinates: the bit each axis contributes at a level selects the child,
+//! which is the Morton order of the cells. Leaves hold up to
+//! `bucket_capacity` points before splitting, and a subtree collapses back
+//! into a leaf once removals bring it under that capacity.
+//!
+//! Inserting returns a [`PointId`] that stays valid until the point is
+//! removed, and which [`Orthtree::relocate`] uses to move points in place.
+//! [`Quadtree`] and [`Octree`] are the two- and three-dimensional aliases.
+//!
+//! When the data is hashable, every node can carry a content hash over its
+//! subtree, as in a Merkle tree, so two replicas can find where they
+//! differ without exchanging the points. A node's hash combines its
+//! children's, and a leaf's its points', in an order that does not depend
+//! on insertion history, and any [`Orthant`] of the grid has a hash even
+//! where the tree has not split that far. Replicas built with the same
+//! bounds and limits then hash the same contents alike: comparing the hash
+//! of an orthant and descending only into the children that differ narrows
+//! a divergence down to a few small orthants, which
+//! [`Orthtree::orthant_points`] lists for syncing. Hashes are computed on
+//! first request and cached; a change to a point only marks the nodes
+//! above it stale, so trees that never ask pay nothing more.
+//!
+//! [`Quadtree`]: crate::quadtree::Quadtree
+//! [`Octree`]: crate::octree::Octree
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::bitset::BitSet;
+use crate::geom::Aabb;
+use crate::probabilistic::{StableHasher, DEFAULT_SEED};
+use crate::slot_map::{Key, SlotMap};
+use crate::spatial_index::ApproxNearest;
+use crate::util::Total;
+
+/// Types usable as `N`-dimensional point coordinates.
+pub trait Point<const N: usize>: Copy {
+    /// The coordinates, one per axis.
+    fn coords(&self) -> [f64; N];
+}
+
+impl<const N: usize> Point<N> for [f64; N] {
+    fn coords(&self) -> [f64; N] {
+        *self
+    }
+}
+
+impl<const N: usize> Point<N> for [f32; N] {
+    fn coords(&self) -> [f64; N] {
+        self.map(f64::from)
+    }
+}
+
+impl Point<2> for (f64, f64) {
+    fn coords(&self) -> [f64; 2] {
+        [self.0, self.1]
+    }
+}
+
+impl Point<2> for (f32, f32) {
+    fn coords(&self) -> [f64; 2] {
+        [self.0, self.1].map(f64::from)
+    }
+}
+
+impl Point<3> for (f64, f64, f64) {
+    fn coords(&self) -> [f64; 3] {
+        [self.0, self.1, self.2]
+    }
+}
+
+impl Point<3> for (f32, f32, f32) {
+    fn coords(&self) -> [f64; 3] {
+        [self.0, self.1, self.2].map(f64::from)
+    }
+}
+
+/// Handle to a point stored in an [`Orthtree`].
+///
+/// Handles are generational keys of a [`SlotMap`]: a removed point's
+/// handle stops resolving, even after a later insertion reuses its slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PointId(Key);
+
+impl From<Key> for PointId {
+    fn from(key: Key) -> Self {
+        PointId(key)
+    }
+}
+
+impl From<PointId> for Key {
+    fn from(id: PointId) -> Self {
+        id.0
+    }
+}
+
+/// Returned when a point falls outside the tree's bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfBounds;
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("point lies outside the tree bounds")
+    }
+}
+
+impl Error for OutOfBounds {}
+
+const NONE: u32 = u32::MAX;
+
+/// Deepest supported tree; each axis is quantized to `u32`.
+pub const MAX_DEPTH: u8 = 32;
+
+/// Highest supported dimension, keeping a node's `2^N` children reasonable.
+pub const MAX_DIMENSION: usize = 8;
+
+#[derive(Clone, Debug)]
+struct Node {
+    parent: u32,
+    /// Index of the first of `2^N` consecutive children, or `NONE` for a
+    /// leaf.
+    children: u32,
+    depth: u8,
+    /// Number of points in this subtree.
+    count: u32,
+    /// Entry indices; only populated on leaves.
+    items: Vec<u32>,
+    /// Cached content hash of the subtree, `None` when stale.
+    hash: Option<u64>,
+}
+
+impl Node {
+    fn leaf(parent: u32, depth: u8) -> Self {
+        Node {
+            parent,
+            children: NONE,
+            depth,
+            count: 0,
+            items: Vec::new(),
+            hash: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry<P, D, const N: usize> {
+    point: P,
+    data: D,
+    cell: [u32; N],
+    leaf: u32,
+}
+
+/// A cell of the grid at some depth: the region a node at that depth
+/// covers, whether or not the tree has split that far.
+///
+/// Along each axis the orthant is the `index`-th of the `2^depth` equal
+/// slices of the tree's bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Orthant<const N: usize> {
+    /// Depth below the root, which is the orthant at depth zero.
+    pub depth: u8,
+    /// Position along each axis among the orthants of this depth.
+    pub index: [u32; N],
+}
+
+impl<const N: usize> Orthant<N> {
+    /// The orthant covering the whole tree.
+    pub const ROOT: Self = Orthant {
+        depth: 0,
+        index: [0; N],
+    };
+
+    /// The child of this orthant in the direction `digit`, whose bit `a` is
+    /// set for the upper half along axis `a`.
+    pub fn child(self, digit: u32) -> Self {
+        Orthant {
+            depth: self.depth + 1,
+            index: std::array::from_fn(|a| self.index[a] << 1 | (digit >> a) & 1),
+        }
+    }
+
+    /// The orthant containing this one, or `None` for the root.
+    pub fn parent(self) -> Option<Self> {
+        (self.depth > 0).then(|| Orthant {
+            depth: self.depth - 1,
+            index: self.index.map(|i| i >> 1),
+        })
+    }
+}
+
+/// The cell range covered by a node during traversal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Region<const N: usize> {
+    node: u32,
+    origin: [u64; N],
+    side: u64,
+}
+
+/// An orthtree mapping `N`-dimensional points of type `P` to data of type
+/// `D`.
+#[derive(Clone, Debug)]
+pub struct Orthtree<P, D, const N: usize> {
+    bounds: Aabb<N>,
+    max_depth: u8,
+    bucket: usize,
+    /// Cell size along each axis at the maximum depth.
+    cell: [f64; N],
+    /// Rounding allowance applied when turning cells back into coordinates.
+    slack: [f64; N],
+    nodes: Vec<Node>,
+    free_blocks: Vec<u32>,
+    entries: SlotMap<PointId, Entry<P, D, N>>,
+}
+
+impl<P: Point<N>, D, const N: usize> Orthtree<P, D, N> {
+    const FANOUT: u32 = 1 << N;
+
+    /// Creates an empty tree covering `bounds`, with a bucket capacity of 8
+    /// and a maximum depth of 16.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite, or if `N` is zero or
+    /// exceeds [`MAX_DIMENSION`].
+    pub fn new(bounds: Aabb<N>) -> Self {
+        Self::with_limits(bounds, 8, 16)
+    }
+
+    /// Creates an empty tree with explicit leaf capacity and depth limit.
+    ///
+    /// Leaves at `max_depth` never split and may exceed `bucket_capacity`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bounds` is empty or not finite, if `N` is zero or exceeds
+    /// [`MAX_DIMENSION`], if `bucket_capacity` is zero, or if `max_depth`
+    /// exceeds [`MAX_DEPTH`].
+    pub fn with_limits(bounds: Aabb<N>, bucket_capacity: usize, max_depth: u8) -> Self {
+        assert!(
+            (1..=MAX_DIMENSION).contains(&N),
+            "dimension must be between 1 and {MAX_DIMENSION}"
+        );
+        assert!(
+            (0..N).all(|a| bounds.min[a].is_finite()
+                && bounds.max[a].is_finite()
+                && bounds.min[a] < bounds.max[a]),
+            "orthtree bounds must be finite and non-empty"
+        );
+        assert!(bucket_capacity > 0, "bucket capacity must be positive");
+        assert!(
+            max_depth <= MAX_DEPTH,
+            "max depth must be at most {MAX_DEPTH}"
+        );
+        let cells = (1u64 << max_depth) as f64;
+        let cell = std::array::from_fn(|a| (bounds.max[a] - bounds.min[a]) / cells);
+        let slack = std::array::from_fn(|a| {
+            8.0 * f64::EPSILON * (bounds.min[a].abs() + bounds.max[a].abs())
+        });
+        Orthtree {
+            bounds,
+            max_depth,
+            bucket: bucket_capacity,
+            cell,
+            slack,
+            nodes: vec![Node::leaf(NONE, 0)],
+            free_blocks: Vec::new(),
+            entries: SlotMap::new(),
+        }
+    }
+
+    /// The box covered by the tree.
+    pub fn bounds(&self) -> Aabb<N> {
+        self.bounds
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the tree holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes every point, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.nodes.push(Node::leaf(NONE, 0));
+        self.free_blocks.clear();
+        self.entries.clear();
+    }
+
+    /// Inserts a point, returning its handle.
+    pub fn insert(&mut self, point: P, data: D) -> Result<PointId, OutOfBounds> {
+        let cell = self.cell_of(&point).ok_or(OutOfBounds)?;
+        let entry = Entry {
+            point,
+            data,
+            cell,
+            leaf: NONE,
+        };
+        let id = self.entries.insert(entry);
+        self.place(id.0.index());
+        Ok(id)
+    }
+
+    /// Removes a point, returning its coordinates and data.
+    pub fn remove(&mut self, id: PointId) -> Option<(P, D)> {
+        self.entries.get(id)?;
+        self.detach(id.0.index());
+        let entry = self.entries.remove(id)?;
+        Some((entry.point, entry.data))
+    }
+
+    /// Moves a point to a new position, keeping its handle and data.
+    ///
+    /// Returns the previous position, or `None` if `id` is not in the tree.
+    /// Fails without modifying the tree if `to` is out of bounds.
+    pub fn relocate(&mut self, id: PointId, to: P) -> Result<Option<P>, OutOfBounds> {
+        let cell = self.cell_of(&to).ok_or(OutOfBounds)?;
+        let Some(entry) = self.entries.get(id) else {
+            return Ok(None);
+        };
+        let shift = (self.max_depth - self.nodes[entry.leaf as usize].depth) as u32;
+        let same_leaf = (0..N).all(|a| {
+            (entry.cell[a] as u64).checked_shr(shift) == (cell[a] as u64).checked_shr(shift)
+        });
+        if same_leaf {
+            self.invalidate(entry.leaf);
+        } else {
+            self.detach(id.0.index());
+        }
+        let entry = self.entries.get_mut(id).expect("entry checked above");
+        let old = std::mem::replace(&mut entry.point, to);
+        entry.cell = cell;
+        if !same_leaf {
+            self.place(id.0.index());
+        }
+        Ok(Some(old))
+    }
+
+    /// Returns the position and data of a point.
+    pub fn get(&self, id: PointId) -> Option<(&P, &D)> {
+        let e = self.entries.get(id)?;
+        Some((&e.point, &e.data))
+    }
+
+    /// Returns the position and mutable data of a point.
+    pub fn get_mut(&mut self, id: PointId) -> Option<(&P, &mut D)> {
+        let leaf = self.entries.get(id)?.leaf;
+        self.invalidate(leaf);
+        let e = self.entries.get_mut(id)?;
+        Some((&e.point, &mut e.data))
+    }
+
+    /// Whether `id` refers to a stored point.
+    pub fn contains(&self, id: PointId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Iterates over every stored point in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (PointId, &P, &D)> + '_ {
+        self.entries.iter().map(|(id, e)| (id, &e.point, &e.data))
+    }
+
+    /// Iterates over the points inside the closed box `query`.
+    pub fn within_box(&self, query: Aabb<N>) -> impl Iterator<Item = (PointId, &P, &D)> + '_ {
+        let cells = self
+            .bounds
+            .intersects(&query)
+            .then(|| (self.quantize(query.min), self.quantize(query.max)));
+        self.query(
+            move |r| match cells {
+                Some((lo, hi)) => {
+                    (0..N).all(|a| r.origin[a] <= hi[a] && lo[a] < r.origin[a] + r.side)
+                }
+                None => false,
+            },
+            move |p| query.contains_point(&p.coords()),
+        )
+    }
+
+    /// Iterates over the points within `radius` of `center`.
+    pub fn within_radius<Q: Point<N>>(
+        &self,
+        center: Q,
+        radius: f64,
+    ) -> impl Iterator<Item = (PointId, &P, &D)> + '_ {
+        let c = center.coords();
+        let r2 = radius * radius;
+        self.query(
+            move |r| self.region_box(r).distance_squared(&c) <= r2,
+            move |p| distance_squared(&c, &p.coords()) <= r2,
+        )
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// Euclidean distances.
+    pub fn nearest<Q: Point<N>>(&self, query: Q, k: usize) -> Vec<(PointId, f64)> {
+        self.nearest_approx(query, k, usize::MAX).neighbors
+    }
+
+    /// Best-first search for the `k` points nearest to `query` that visits
+    /// at most `max_visits` nodes, nearest cell first. The result reports
+    /// how far the search got and whether it is exact; with a budget of a
+    /// few leaves it is usually both exact and much cheaper than
+    /// [`Orthtree::nearest`] on large trees.
+    pub fn nearest_approx<Q: Point<N>>(
+        &self,
+        query: Q,
+        k: usize,
+        max_visits: usize,
+    ) -> ApproxNearest<PointId> {
+        let q = query.coords();
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        let mut frontier = BinaryHeap::new();
+        if k > 0 {
+            frontier.push(Reverse((Total(0.0), self.root())));
+        }
+        let mut visited = 0;
+        while let Some(&Reverse((Total(bound), region))) = frontier.peek() {
+            if best.len() == k && bound > best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                break;
+            }
+            if visited == max_visits {
+                break;
+            }
+            frontier.pop();
+            visited += 1;
+            let node = &self.nodes[region.node as usize];
+            if node.children == NONE {
+                for &i in &node.items {
+                    let d2 = distance_squared(&q, &self.entry(i).point.coords());
+                    if best.len() < k {
+                        best.push((Total(d2), i));
+                    } else if d2 < best.peek().map_or(f64::INFINITY, |b| b.0 .0) {
+                        best.pop();
+                        best.push((Total(d2), i));
+                    }
+                }
+            } else {
+                for child in self.children(region) {
+                    if self.nodes[child.node as usize].count == 0 {
+                        continue;
+                    }
+                    let d2 = self.region_box(child).distance_squared(&q);
+                    frontier.push(Reverse((Total(d2), child)));
+                }
+            }
+        }
+        let bound = frontier.peek().map_or(f64::INFINITY, |r| r.0 .0 .0);
+        let exact = frontier.is_empty()
+            || (best.len() == k && bound >= best.peek().map_or(f64::INFINITY, |b| b.0 .0));
+        let mut neighbors: Vec<_> = best
+            .into_iter()
+            .map(|(d2, i)| (self.entries.key_of(i), d2.0.sqrt()))
+            .collect();
+        neighbors.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ApproxNearest {
+            neighbors,
+            bound: bound.sqrt(),
+            exact,
+            visited,
+        }
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one<Q: Point<N>>(&self, query: Q) -> Option<(PointId, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// The box an orthant covers, or `None` if it is deeper than the tree's
+    /// depth limit or its index is out of range.
+    pub fn orthant_bounds(&self, orthant: Orthant<N>) -> Option<Aabb<N>> {
+        let (origin, side) = self.orthant_cells(orthant)?;
+        Some(Aabb::new(
+            std::array::from_fn(|a| self.bounds.min[a] + origin[a] as f64 * self.cell[a]),
+            std::array::from_fn(|a| self.bounds.min[a] + (origin[a] + side) as f64 * self.cell[a]),
+        ))
+    }
+
+    /// Iterates over the points inside `orthant`. Every point lies in
+    /// exactly one orthant of each depth.
+    pub fn orthant_points(
+        &self,
+        orthant: Orthant<N>,
+    ) -> impl Iterator<Item = (PointId, &P, &D)> + '_ {
+        let cells = self.orthant_cells(orthant);
+        self.query(
+            move |r| match cells {
+                Some((origin, side)) => (0..N)
+                    .all(|a| r.origin[a] < origin[a] + side && origin[a] < r.origin[a] + r.side),
+                None => false,
+            },
+            move |p| {
+                self.cell_of(p)
+                    .is_some_and(|cell| self.in_orthant(&cell, orthant))
+            },
+        )
+    }
+
+    /// Which children of `orthant` hold at least one point, as a set of
+    /// child digits: `d` is a member when [`orthant.child(d)`](Orthant::child)
+    /// is nonempty. Returns `None` if `orthant` is invalid or at the depth
+    /// limit, where it has no children.
+    pub fn occupancy(&self, orthant: Orthant<N>) -> Option<BitSet> {
+        if orthant.depth >= self.max_depth {
+            return None;
+        }
+        let n = self.locate(orthant)?;
+        let node = &self.nodes[n as usize];
+        let mut mask = BitSet::with_capacity(Self::FANOUT as usize);
+        if node.depth == orthant.depth && node.children != NONE {
+            for d in 0..Self::FANOUT {
+                if self.nodes[(node.children + d) as usize].count > 0 {
+                    mask.insert(d as usize);
+                }
+            }
+        } else {
+            // The orthant lies within a leaf; sort its points by child.
+            for &i in &node.items {
+                let cell = &self.entry(i).cell;
+                if self.in_orthant(cell, orthant) {
+                    mask.insert(self.digit(cell, orthant.depth) as usize);
+                }
+            }
+        }
+        Some(mask)
+    }
+
+    /// Walks the whole tree checking its structural invariants, for tests
+    /// and fuzzing: subtree counts add up, every stored point sits in
+    /// exactly one leaf whose region contains its cell, and no subtree is
+    /// small enough that it should have collapsed.
+    ///
+    /// Hidden from the docs: it is a testing hook, not part of the API.
+    ///
+    /// # Panics
+    ///
+    /// Panics with a description of the first violation found.
+    #[doc(hidden)]
+    pub fn check_invariants(&self) {
+        let mut seen = vec![false; self.entries.slot_count()];
+        let mut stack = vec![self.root()];
+        while let Some(r) = stack.pop() {
+            let node = &self.nodes[r.node as usize];
+            if node.children == NONE {
+                assert_eq!(node.count as usize, node.items.len(), "leaf count");
+                assert!(
+                    node.items.len() <= self.bucket || node.depth == self.max_depth,
+                    "overfull leaf above the depth limit"
+                );
+                for &i in &node.items {
+                    let e = self.entries.slot(i).expect("leaf item is live");
+                    assert!(
+                        !std::mem::replace(&mut seen[i as usize], true),
+                        "point in two leaves"
+                    );
+                    assert_eq!(e.leaf, r.node, "entry's leaf link");
+                    assert_eq!(Some(e.cell), self.cell_of(&e.point), "entry's cell");
+                    assert!(
+                        (0..N).all(|a| {
+                            let c = e.cell[a] as u64;
+                            r.origin[a] <= c && c < r.origin[a] + r.side
+                        }),
+                        "point outside its leaf's region"
+                    );
+                }
+                continue;
+            }
+            assert!(node.items.is_empty(), "items on an interior node");
+            assert!(node.count as usize > self.bucket, "uncollapsed subtree");
+            let mut total = 0;
+            for child in self.children(r) {
+                let c = &self.nodes[child.node as usize];
+                assert_eq!(c.parent, r.node, "child's parent link");
+                assert_eq!(c.depth, node.depth + 1, "child's depth");
+                total += c.count;
+                stack.push(child);
+            }
+            assert_eq!(node.count, total, "interior count");
+        }
+        assert_eq!(self.nodes[0].count as usize, self.len(), "root count");
+        for (id, _) in self.entries.iter() {
+            assert!(
+                seen[id.0.index() as usize],
+                "live entry missing from the tree"
+            );
+        }
+    }
+
+    /// The first cell and side length in cells of a valid orthant.
+    fn orthant_cells(&self, orthant: Orthant<N>) -> Option<([u64; N], u64)> {
+        if orthant.depth > self.max_depth
+            || orthant
+                .index
+                .iter()
+                .any(|&i| u64::from(i) >> orthant.depth != 0)
+        {
+            return None;
+        }
+        let shift = self.max_depth - orthant.depth;
+        Some((orthant.index.map(|i| u64::from(i) << shift), 1 << shift))
+    }
+
+    fn in_orthant(&self, cell: &[u32; N], orthant: Orthant<N>) -> bool {
+        let shift = self.max_depth - orthant.depth;
+        (0..N).all(|a| u64::from(cell[a]) >> shift == u64::from(orthant.index[a]))
+    }
+
+    /// The deepest node whose region contains a valid orthant: the node
+    /// covering exactly that orthant, or a shallower leaf.
+    fn locate(&self, orthant: Orthant<N>) -> Option<u32> {
+        self.orthant_cells(orthant)?;
+        let mut n = 0;
+        loop {
+            let node = &self.nodes[n as usize];
+            if node.depth == orthant.depth || node.children == NONE {
+                return Some(n);
+            }
+            let shift = orthant.depth - node.depth - 1;
+            let digit = (0..N).fold(0, |acc, a| acc | ((orthant.index[a] >> shift) & 1) << a);
+            n = node.children + digit;
+        }
+    }
+
+    /// Whether the tree splits `orthant` into children.
+    fn splits(&self, orthant: Orthant<N>) -> bool {
+        self.locate(orthant).is_some_and(|n| {
+            let node = &self.nodes[n as usize];
+            node.depth == orthant.depth && node.children != NONE
+        })
+    }
+
+    fn root(&self) -> Region<N> {
+        Region {
+            node: 0,
+            origin: [0; N],
+            side: 1u64 << self.max_depth,
+        }
+    }
+
+    fn entry(&self, i: u32) -> &Entry<P, D, N> {
+        self.entries.slot(i).expect("live entry")
+    }
+
+    /// Quantizes coordinates onto the grid, clamping to its edges.
+    fn quantize(&self, p: [f64; N]) -> [u64; N] {
+        let cells = 1u64 << self.max_depth;
+        std::array::from_fn(|a| {
+            let t = (p[a] - self.bounds.min[a]) / self.cell[a];
+            if t <= 0.0 {
+                0
+            } else {
+                (t as u64).min(cells - 1)
+            }
+        })
+    }
+
+    fn cell_of(&self, p: &P) -> Option<[u32; N]> {
+        let c = p.coords();
+        if !self.bounds.contains_point(&c) {
+            return None;
+        }
+        Some(self.quantize(c).map(|v| v as u32))
+    }
+
+    /// The coordinate box of a region, padded to absorb rounding.
+    fn region_box(&self, r: Region<N>) -> Aabb<N> {
+        Aabb::new(
+            std::array::from_fn(|a| {
+                self.bounds.min[a] + r.origin[a] as f64 * self.cell[a] - self.slack[a]
+            }),
+            std::array::from_fn(|a| {
+                self.bounds.min[a] + (r.origin[a] + r.side) as f64 * self.cell[a] + self.slack[a]
+            }),
+        )
+    }
+
+    fn children(&self, r: Region<N>) -> impl Iterator<Item = Region<N>> {
+        let first = self.nodes[r.node as usize].children;
+        let half = r.side / 2;
+        (0..Self::FANOUT).map(move |c| Region {
+            node: first + c,
+            origin: std::array::from_fn(|a| r.origin[a] + ((c >> a) & 1) as u64 * half),
+            side: half,
+        })
+    }
+
+    /// Which child of a node at `depth` a cell descends into: bit `a` of the
+    /// child index is the next bit of the cell's coordinate on axis `a`.
+    fn digit(&self, cell: &[u32; N], depth: u8) -> u32 {
+        let shift = self.max_depth - depth - 1;
+        (0..N).fold(0, |acc, a| acc | ((cell[a] >> shift) & 1) << a)
+    }
+
+    /// Routes a detached entry down to its leaf, splitting it if it overflows.
+    fn place(&mut self, id: u32) {
+        let cell = self.entry(id).cell;
+        let mut n = 0u32;
+        loop {
+            let node = &mut self.nodes[n as usize];
+            node.count += 1;
+            node.hash = None;
+            if node.children == NONE {
+                break;
+            }
+            let (first, depth) = (node.children, node.depth);
+            n = first + self.digit(&cell, depth);
+        }
+        self.nodes[n as usize].items.push(id);
+        self.entries.slot_mut(id).expect("live entry").leaf = n;
+        self.split_if_full(n);
+    }
+
+    fn split_if_full(&mut self, leaf: u32) {
+        let node = &self.nodes[leaf as usize];
+        if node.items.len() <= self.bucket || node.depth >= self.max_depth {
+            return;
+        }
+        let depth = node.depth;
+        let first = self.alloc_block(leaf, depth + 1);
+        self.nodes[leaf as usize].children = first;
+        let items = std::mem::take(&mut self.nodes[leaf as usize].items);
+        for id in items {
+            let child = first + self.digit(&self.entry(id).cell, depth);
+            let node = &mut self.nodes[child as usize];
+            node.items.push(id);
+            node.count += 1;
+            self.entries.slot_mut(id).expect("live entry").leaf = child;
+        }
+        for c in first..first + Self::FANOUT {
+            self.split_if_full(c);
+        }
+    }
+
+    fn alloc_block(&mut self, parent: u32, depth: u8) -> u32 {
+        match self.free_blocks.pop() {
+            Some(first) => {
+                for c in first..first + Self::FANOUT {
+                    self.nodes[c as usize] = Node::leaf(parent, depth);
+                }
+                first
+            }
+            None => {
+                let first = self.nodes.len() as u32;
+                self.nodes
+                    .extend((0..Self::FANOUT).map(|_| Node::leaf(parent, depth)));
+                first
+            }
+        }
+    }
+
+    /// Unlinks an entry from its leaf and collapses any subtree left with
+    /// few enough points to fit in a single leaf.
+    fn detach(&mut self, id: u32) {
+        let leaf = self.entry(id).leaf;
+        let items = &mut self.nodes[leaf as usize].items;
+        let pos = items
+            .iter()
+            .position(|&i| i == id)
+            .expect("entry in its leaf");
+        items.swap_remove(pos);
+        let mut n = leaf;
+        let mut collapse = NONE;
+        while n != NONE {
+            let node = &mut self.nodes[n as usize];
+            node.count -= 1;
+            node.hash = None;
+            if node.children != NONE && node.count as usize <= self.bucket {
+                collapse = n;
+            }
+            n = node.parent;
+        }
+        if collapse != NONE {
+            self.collapse(collapse);
+        }
+    }
+
+    /// Marks the hashes of `n` and its ancestors stale.
+    fn invalidate(&mut self, mut n: u32) {
+        while n != NONE {
+            let node = &mut self.nodes[n as usize];
+            node.hash = None;
+            n = node.parent;
+        }
+    }
+
+    fn collapse(&mut self, n: u32) {
+        let mut items = Vec::with_capacity(self.nodes[n as usize].count as usize);
+        let mut stack = vec![self.nodes[n as usize].children];
+        while let Some(first) = stack.pop() {
+            for c in first..first + Self::FANOUT {
+                let node = &mut self.nodes[c as usize];
+                items.append(&mut node.items);
+                if node.children != NONE {
+                    stack.push(node.children);
+                }
+            }
+            self.free_blocks.push(first);
+        }
+        for &id in &items {
+            self.entries.slot_mut(id).expect("live entry").leaf = n;
+        }
+        let node = &mut self.nodes[n as usize];
+        node.children = NONE;
+        node.items = items;
+    }
+
+    /// Depth-first traversal visiting regions accepted by `enter` and
+    /// yielding points accepted by `keep`.
+    fn query<'a>(
+        &'a self,
+        enter: impl Fn(Region<N>) -> bool + 'a,
+        keep: impl Fn(&P) -> bool + 'a,
+    ) -> impl Iterator<Item = (PointId, &'a P, &'a D)> + 'a {
+        let mut stack = vec![self.root()];
+        let mut items: std::slice::Iter<'a, u32> = [].iter();
+        std::iter::from_fn(move || loop {
+            for &i in items.by_ref() {
+                let e = self.entry(i);
+                if keep(&e.point) {
+                    return Some((self.entries.key_of(i), &e.point, &e.data));
+                }
+            }
+            let region = stack.pop()?;
+            let node = &self.nodes[region.node as usize];
+            if node.count == 0 || !enter(region) {
+                continue;
+            }
+            if node.children == NONE {
+                items = node.items.iter();
+            } else {
+                stack.extend(self.children(region));
+            }
+        })
+    }
+}
+
+impl<P: Point<N>, D: Hash, const N: usize> Orthtree<P, D, N> {
+    /// The content hash of the whole tree.
+    pub fn content_hash(&mut self) -> u64 {
+        self.node_hash(0)
+    }
+
+    /// The content hash of the points inside `orthant`, or `None` if the
+    /// orthant is not valid for this tree. Empty orthants hash to zero.
+    ///
+    /// The hash depends only on the points in the orthant and their data,
+    /// for trees with the same bounds, bucket capacity and depth limit.
+    pub fn orthant_hash(&mut self, orthant: Orthant<N>) -> Option<u64> {
+        let n = self.locate(orthant)?;
+        if self.nodes[n as usize].depth == orthant.depth {
+            return Some(self.node_hash(n));
+        }
+        // The orthant lies inside a leaf; hash its share of the points.
+        let hashes = self.nodes[n as usize]
+            .items
+            .iter()
+            .filter(|&&i| self.in_orthant(&self.entry(i).cell, orthant))
+            .map(|&i| self.entry_hash(i))
+            .collect();
+        Some(leaf_hash(hashes))
+    }
+
+    /// The orthants in which this tree and `other` hold different points,
+    /// found by comparing hashes from the root down and descending only
+    /// where they differ. Each returned orthant is one that at least one of
+    /// the trees keeps as a leaf, so it holds few points on that side.
+    /// Orthants come out in sorted order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trees have different bounds, bucket capacities or
+    /// depth limits.
+    pub fn diff(&mut self, other: &mut Self) -> Vec<Orthant<N>> {
+        assert!(
+            self.bounds == other.bounds
+                && self.bucket == other.bucket
+                && self.max_depth == other.max_depth,
+            "trees must share bounds and limits to be compared"
+        );
+        let mut diverging = Vec::new();
+        let mut stack = vec![Orthant::ROOT];
+        while let Some(orthant) = stack.pop() {
+            if self.orthant_hash(orthant) == other.orthant_hash(orthant) {
+                continue;
+            }
+            if self.splits(orthant) && other.splits(orthant) {
+                stack.extend((0..Self::FANOUT).map(|digit| orthant.child(digit)));
+            } else {
+                diverging.push(orthant);
+            }
+        }
+        diverging.sort_unstable();
+        diverging
+    }
+
+    fn node_hash(&mut self, n: u32) -> u64 {
+        let node = &self.nodes[n as usize];
+        if let Some(hash) = node.hash {
+            return hash;
+        }
+        let hash = if node.count == 0 {
+            0
+        } else if node.children == NONE {
+            leaf_hash(node.items.iter().map(|&i| self.entry_hash(i)).collect())
+        } else {
+            let first = node.children;
+            let children: Vec<u64> = (first..first + Self::FANOUT)
+                .map(|c| self.node_hash(c))
+                .collect();
+            let mut hasher = StableHasher::new(DEFAULT_SEED);
+            hasher.write_u8(1);
+            children.hash(&mut hasher);
+            hasher.finish()
+        };
+        self.nodes[n as usize].hash = Some(hash);
+        hash
+    }
+
+    fn entry_hash(&self, i: u32) -> u64 {
+        let entry = self.entry(i);
+        let mut hasher = StableHasher::new(DEFAULT_SEED);
+        for c in entry.point.coords() {
+            hasher.write_u64(c.to_bits());
+        }
+        entry.data.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The hash of a leaf holding points with the given hashes, whatever their
+/// order.
+fn leaf_hash(mut hashes: Vec<u64>) -> u64 {
+    if hashes.is_empty() {
+        return 0;
+    }
+    hashes.sort_unstable();
+    let mut hasher = StableHasher::new(DEFAULT_SEED);
+    hasher.write_u8(0);
+    hashes.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn distance_squared<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
@@ -0,0 +1,298 @@
+//! A pairing heap with handles and decrease-key.
+//!
+//! A pairing heap is a single heap-ordered tree of any shape. Pushing
+//! links the new item with the root, the larger of the two becoming the
+//! other's first child, so it is `O(1)`. Popping removes the root and
+//! rebuilds from its children in two passes: link them in pairs left to
+//! right, then fold the pairs into one tree right to left. Fredman,
+//! Sedgewick, Sleator and Tarjan showed the pop takes `O(log n)` amortized
+//! time. Decreasing a key cuts the item's subtree out and links it back
+//! with the root, which is fast in practice: `O(log n)` amortized as
+//! proven, and thought to be much less.
+//!
+//! Unlike [`BinaryHeap`](std::collections::BinaryHeap), every push returns
+//! a [`Handle`] to the item, through which it can later be inspected,
+//! decreased or removed. That is the operation Dijkstra's, Prim's and A*
+//! need to improve a queued distance in place instead of pushing a
+//! duplicate. The heap puts the smallest item first; wrap items in
+//! [`Reverse`](std::cmp::Reverse) for the largest. It shares the
+//! [`MeldableHeap`] interface with the [`FibonacciHeap`].
+//!
+//! Nodes live in an arena linked by index, as first child, next sibling,
+//! and a back link that is the parent for a first child and the previous
+//! sibling otherwise. Melding links the two roots in `O(1)` and moves the
+//! smaller heap's nodes into the larger heap's arena. A node only moves
+//! into an arena at least twice the size, so over any run of melds each
+//! node moves at most `log2 n` times.
+//!
+//! [`MeldableHeap`]: crate::heap::MeldableHeap
+//! [`FibonacciHeap`]: crate::fibonacci_heap::FibonacciHeap
+
+use crate::heap_arena::{shift, Arena, Key, Links, NONE};
+
+/// Handle to an item stored in a [`PairingHeap`].
+///
+/// A handle stays valid until its item is removed, including across
+/// melds into or from the heap. After that it is stale: lookups through it
+/// return `None` even once the slot holds another item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle(Key);
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    item: T,
+    child: u32,
+    sibling: u32,
+    /// The parent of a first child, the previous sibling of any other.
+    back: u32,
+}
+
+impl<T> Links for Node<T> {
+    fn shift(&mut self, offset: u32) {
+        shift(&mut self.child, offset);
+        shift(&mut self.sibling, offset);
+        shift(&mut self.back, offset);
+    }
+}
+
+/// A min-heap of items of type `T` with handles to them.
+///
+/// A clone holds the same items under new handles: handles into the
+/// original do not reach the clone's items.
+#[derive(Clone, Debug)]
+pub struct PairingHeap<T> {
+    nodes: Arena<Node<T>>,
+    root: u32,
+}
+
+impl<T> Default for PairingHeap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PairingHeap<T> {
+    /// Creates an empty heap.
+    pub fn new() -> Self {
+        PairingHeap {
+            nodes: Arena::new(),
+            root: NONE,
+        }
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the heap is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 0
+    }
+
+    /// Removes every item, invalidating all handles.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// The smallest item.
+    pub fn peek(&self) -> Option<&T> {
+        (self.root != NONE).then(|| &self.nodes[self.root].item)
+    }
+
+    /// The handle of the smallest item.
+    pub fn peek_handle(&self) -> Option<Handle> {
+        (self.root != NONE).then(|| Handle(self.nodes.key(self.root)))
+    }
+
+    /// The item behind `handle`, or `None` if it has been removed.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let n = self.nodes.find(handle.0)?;
+        Some(&self.nodes[n].item)
+    }
+
+    /// Whether the item behind `handle` is still in the heap.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Iterates over the items in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.nodes.iter().map(|n| &n.item)
+    }
+
+    fn node(&mut self, n: u32) -> &mut Node<T> {
+        &mut self.nodes[n]
+    }
+
+    /// Detaches `n` and its subtree from its parent and siblings.
+    fn cut(&mut self, n: u32) {
+        let Node { sibling, back, .. } = self.nodes[n];
+        if self.nodes[back].child == n {
+            self.node(back).child = sibling;
+        } else {
+            self.node(back).sibling = sibling;
+        }
+        if sibling != NONE {
+            self.node(sibling).back = back;
+        }
+        let node = self.node(n);
+        node.sibling = NONE;
+        node.back = NONE;
+    }
+
+    fn release(&mut self, n: u32) -> T {
+        self.nodes.remove(n).item
+    }
+
+    /// The slot of the item behind `handle`.
+    fn find(&self, handle: Handle) -> u32 {
+        let n = self.nodes.find(handle.0);
+        n.expect("handle refers to a removed item")
+    }
+}
+
+impl<T: Ord> PairingHeap<T> {
+    /// Adds an item in `O(1)`, returning its handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the heap already holds `u32::MAX - 1` nodes.
+    pub fn push(&mut self, item: T) -> Handle {
+        let (n, key) = self.nodes.insert(Node {
+            item,
+            child: NONE,
+            sibling: NONE,
+            back: NONE,
+        });
+        self.root = self.link(self.root, n);
+        Handle(key)
+    }
+
+    /// Removes and returns the smallest item, in `O(log n)` amortized.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.root == NONE {
+            return None;
+        }
+        let root = self.root;
+        self.root = self.combine(self.nodes[root].child);
+        Some(self.release(root))
+    }
+
+    /// Replaces the item behind `handle` with a smaller or equal one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed or `item` is greater than it.
+    pub fn decrease_key(&mut self, handle: Handle, item: T) {
+        let n = self.find(handle);
+        let slot = &mut self.node(n).item;
+        assert!(item <= *slot, "new item is greater than the current one");
+        *slot = item;
+        if n != self.root {
+            self.cut(n);
+            self.root = self.link(self.root, n);
+        }
+    }
+
+    /// Removes and returns the item behind `handle`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the item has been removed.
+    pub fn remove(&mut self, handle: Handle) -> T {
+        let n = self.find(handle);
+        if n == self.root {
+            return self.pop().expect("heap holds the item");
+        }
+        self.cut(n);
+        let children = self.combine(self.nodes[n].child);
+        self.root = self.link(self.root, children);
+        self.release(n)
+    }
+
+    /// Moves every item of `other` into this heap, in `O(1)` plus moving
+    /// the smaller heap's nodes into the larger heap's arena.
+    ///
+    /// Handles into both heaps stay valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arenas together hold `u32::MAX - 1` nodes or more.
+    pub fn meld(&mut self, other: Self) {
+        let (mut ours, mut theirs) = (self.root, other.root);
+        let (a, b) = self.nodes.meld(other.nodes);
+        shift(&mut ours, a);
+        shift(&mut theirs, b);
+        self.root = self.link(ours, theirs);
+    }
+
+    /// Links two roots, the larger becoming the first child of the smaller.
+    fn link(&mut self, a: u32, b: u32) -> u32 {
+        if a == NONE {
+            return b;
+        }
+        if b == NONE {
+            return a;
+        }
+        let (parent, child) = if self.nodes[b].item < self.nodes[a].item {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let first = self.nodes[parent].child;
+        if first != NONE {
+            self.node(first).back = child;
+        }
+        let c = self.node(child);
+        c.sibling = first;
+        c.back = parent;
+        self.node(parent).child = child;
+        parent
+    }
+
+    /// Combines the sibling list starting at `first` into one tree, by the
+    /// two-pass pairing.
+    fn combine(&mut self, first: u32) -> u32 {
+        let mut pairs = Vec::new();
+        let mut a = first;
+        while a != NONE {
+            let b = self.nodes[a].sibling;
+            let next = if b == NONE {
+                NONE
+            } else {
+                self.nodes[b].sibling
+            };
+            for n in [a, b] {
+                if n != NONE {
+                    let node = self.node(n);
+                    node.sibling = NONE;
+                    node.back = NONE;
+                }
+            }
+            pairs.push(self.link(a, b));
+            a = next;
+        }
+        let mut root = pairs.pop().unwrap_or(NONE);
+        while let Some(tree) = pairs.pop() {
+            root = self.link(tree, root);
+        }
+        root
+    }
+}
+
+impl<T: Ord> Extend<T> for PairingHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for PairingHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut heap = PairingHeap::new();
+        heap.extend(iter);
+        heap
+    }
+}
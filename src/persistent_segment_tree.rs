@@ -0,0 +1,368 @@
+//! A persistent segment tree, keeping every version of a sequence.
+//!
+//! The tree stores the [`Monoid`] combination of every aligned range of a
+//! sequence, as the [`SegmentTree`] does, but a point update never changes
+//! a node. It copies the `O(log n)` nodes on the path from the root to the
+//! element instead, and the copies share every other subtree with the
+//! version they were made from (Driscoll, Sarnak, Sleator and Tarjan,
+//! 1989). Each update therefore makes a new version for `O(log n)` time
+//! and space, and every earlier version stays queryable, in `O(log n)`
+//! like the latest. Updates can start from any version, so the history
+//! may branch.
+//!
+//! Versions are numbered in the order they were made, with the initial
+//! sequence as version `0`. Nodes live in one arena and are never freed,
+//! so `u` updates take `O(n + u log n)` space in all.
+//!
+//! Two versions of the same tree line up node for node, which lets one
+//! descent compare them. [`RangeQuantiles`] builds one version per prefix
+//! of a sequence, each counting how often every value occurs in it, so
+//! subtracting the counts of two prefixes describes the values in any
+//! range: the `k`th smallest of them is found in one walk down both trees,
+//! in `O(log n)`, for values of any ordered type. The [`WaveletTree`]
+//! answers the same queries over integers in far less space.
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+//! [`SegmentTree`]: crate::segment_tree::SegmentTree
+//! [`WaveletTree`]: crate::wavelet_tree::WaveletTree
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::algebra::{Monoid, Sum};
+use crate::util::bounds;
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<V> {
+    left: u32,
+    right: u32,
+    sum: V,
+}
+
+/// Every version of a sequence of `M::Value`, with point updates.
+#[derive(Clone, Debug)]
+pub struct PersistentSegmentTree<M: Monoid> {
+    monoid: M,
+    len: usize,
+    nodes: Vec<Node<M::Value>>,
+    /// The root of each version, or `NONE` when the sequence is empty.
+    roots: Vec<u32>,
+}
+
+impl<M: Monoid> PersistentSegmentTree<M> {
+    /// Builds version `0` over `values` in `O(n)`.
+    pub fn new(monoid: M, values: Vec<M::Value>) -> Self {
+        let len = values.len();
+        let mut tree = PersistentSegmentTree {
+            monoid,
+            len,
+            nodes: Vec::with_capacity(2 * len),
+            roots: Vec::new(),
+        };
+        let root = if len == 0 {
+            NONE
+        } else {
+            tree.build(0, len, &values)
+        };
+        tree.roots.push(root);
+        tree
+    }
+
+    /// Builds version `0` as `len` copies of the identity.
+    pub fn with_len(monoid: M, len: usize) -> Self {
+        let values = vec![monoid.identity(); len];
+        Self::new(monoid, values)
+    }
+
+    fn build(&mut self, lo: usize, hi: usize, values: &[M::Value]) -> u32 {
+        if hi - lo == 1 {
+            return self.alloc(NONE, NONE, values[lo].clone());
+        }
+        let mid = (lo + hi) / 2;
+        let left = self.build(lo, mid, values);
+        let right = self.build(mid, hi, values);
+        self.join(left, right)
+    }
+
+    /// Number of elements, the same in every version.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The monoid the tree combines with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// Number of versions, including the initial one.
+    pub fn versions(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// The newest version.
+    pub fn latest(&self) -> usize {
+        self.roots.len() - 1
+    }
+
+    /// Makes a new version from `version` with the element at `index`
+    /// replaced, in `O(log n)`, and returns its number.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` does not exist or `index` is out of bounds.
+    pub fn set(&mut self, version: usize, index: usize, value: M::Value) -> usize {
+        let root = self.root(version);
+        assert!(
+            index < self.len,
+            "index {index} out of bounds for length {}",
+            self.len
+        );
+        let root = self.set_in(root, 0, self.len, index, value);
+        self.roots.push(root);
+        self.latest()
+    }
+
+    fn set_in(&mut self, node: u32, lo: usize, hi: usize, index: usize, value: M::Value) -> u32 {
+        if hi - lo == 1 {
+            return self.alloc(NONE, NONE, value);
+        }
+        let mid = (lo + hi) / 2;
+        let Node { left, right, .. } = self.nodes[node as usize];
+        if index < mid {
+            let left = self.set_in(left, lo, mid, index, value);
+            self.join(left, right)
+        } else {
+            let right = self.set_in(right, mid, hi, index, value);
+            self.join(left, right)
+        }
+    }
+
+    /// The combination of the elements in `range` as of `version`, in
+    /// `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` does not exist or `range` is out of bounds.
+    pub fn query(&self, version: usize, range: impl RangeBounds<usize>) -> M::Value {
+        let root = self.root(version);
+        let (lo, hi) = bounds(range, self.len);
+        if lo == hi {
+            return self.monoid.identity();
+        }
+        self.query_in(root, 0, self.len, lo, hi)
+    }
+
+    fn query_in(&self, node: u32, lo: usize, hi: usize, qlo: usize, qhi: usize) -> M::Value {
+        let n = &self.nodes[node as usize];
+        if qlo <= lo && hi <= qhi {
+            return n.sum.clone();
+        }
+        let mid = (lo + hi) / 2;
+        if qhi <= mid {
+            self.query_in(n.left, lo, mid, qlo, qhi)
+        } else if mid <= qlo {
+            self.query_in(n.right, mid, hi, qlo, qhi)
+        } else {
+            let l = self.query_in(n.left, lo, mid, qlo, qhi);
+            let r = self.query_in(n.right, mid, hi, qlo, qhi);
+            self.monoid.combine(&l, &r)
+        }
+    }
+
+    /// The combination of every element as of `version`, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` does not exist.
+    pub fn total(&self, version: usize) -> M::Value {
+        match self.root(version) {
+            NONE => self.monoid.identity(),
+            root => self.nodes[root as usize].sum.clone(),
+        }
+    }
+
+    /// The element at `index` as of `version`, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` does not exist or `index` is out of bounds.
+    pub fn get(&self, version: usize, index: usize) -> M::Value {
+        self.query(version, index..=index)
+    }
+
+    /// Collects the elements as of `version`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` does not exist.
+    pub fn to_vec(&self, version: usize) -> Vec<M::Value> {
+        let mut out = Vec::with_capacity(self.len);
+        match self.root(version) {
+            NONE => {}
+            root => self.collect(root, &mut out),
+        }
+        out
+    }
+
+    fn collect(&self, node: u32, out: &mut Vec<M::Value>) {
+        let n = &self.nodes[node as usize];
+        if n.left == NONE {
+            out.push(n.sum.clone());
+        } else {
+            self.collect(n.left, out);
+            self.collect(n.right, out);
+        }
+    }
+
+    fn root(&self, version: usize) -> u32 {
+        assert!(
+            version < self.roots.len(),
+            "version {version} out of bounds for {} versions",
+            self.roots.len()
+        );
+        self.roots[version]
+    }
+
+    fn alloc(&mut self, left: u32, right: u32, sum: M::Value) -> u32 {
+        assert!(self.nodes.len() < NONE as usize, "too many nodes");
+        self.nodes.push(Node { left, right, sum });
+        (self.nodes.len() - 1) as u32
+    }
+
+    /// A new node over the subtrees `left` and `right`.
+    fn join(&mut self, left: u32, right: u32) -> u32 {
+        let sum = self.monoid.combine(
+            &self.nodes[left as usize].sum,
+            &self.nodes[right as usize].sum,
+        );
+        self.alloc(left, right, sum)
+    }
+}
+
+impl<M: Monoid + Default> FromIterator<M::Value> for PersistentSegmentTree<M> {
+    fn from_iter<I: IntoIterator<Item = M::Value>>(iter: I) -> Self {
+        PersistentSegmentTree::new(M::default(), iter.into_iter().collect())
+    }
+}
+
+/// Order statistics over ranges of a static sequence, by one persistent
+/// tree of value counts per prefix.
+#[derive(Clone, Debug)]
+pub struct RangeQuantiles<T> {
+    /// The distinct values, in order.
+    keys: Vec<T>,
+    /// Version `i` counts the occurrences of each key among the first `i`
+    /// values.
+    counts: PersistentSegmentTree<Sum<u32>>,
+}
+
+impl<T: Ord + Clone> RangeQuantiles<T> {
+    /// Builds the prefix versions of `values` in `O(n log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `u32::MAX` values or more.
+    pub fn new(values: &[T]) -> Self {
+        assert!(values.len() < NONE as usize, "too many values");
+        let mut keys = values.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        let mut counts = PersistentSegmentTree::with_len(Sum::new(), keys.len());
+        for (i, v) in values.iter().enumerate() {
+            let k = keys.binary_search(v).expect("every value is a key");
+            let count = counts.get(i, k);
+            counts.set(i, k, count + 1);
+        }
+        RangeQuantiles { keys, counts }
+    }
+
+    /// Number of values in the sequence.
+    pub fn len(&self) -> usize {
+        self.counts.versions() - 1
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `k`th smallest value in `range`, counting from zero, or `None`
+    /// if the range holds `k` values or fewer, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn quantile(&self, range: impl RangeBounds<usize>, k: usize) -> Option<&T> {
+        let (lo, hi) = bounds(range, self.len());
+        if k >= hi - lo {
+            return None;
+        }
+        let nodes = &self.counts.nodes;
+        let (mut a, mut b) = (self.counts.roots[lo], self.counts.roots[hi]);
+        let (mut from, mut to) = (0, self.keys.len());
+        let mut k = k as u32;
+        while to - from > 1 {
+            let (na, nb) = (&nodes[a as usize], &nodes[b as usize]);
+            let below = nodes[nb.left as usize].sum - nodes[na.left as usize].sum;
+            let mid = (from + to) / 2;
+            if k < below {
+                (a, b, to) = (na.left, nb.left, mid);
+            } else {
+                k -= below;
+                (a, b, from) = (na.right, nb.right, mid);
+            }
+        }
+        Some(&self.keys[from])
+    }
+
+    /// The smallest value in `range`, or `None` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn min(&self, range: impl RangeBounds<usize>) -> Option<&T> {
+        self.quantile(range, 0)
+    }
+
+    /// The median of `range`, the lower of the two middle values when it
+    /// holds an even number of them, or `None` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn median(&self, range: impl RangeBounds<usize>) -> Option<&T> {
+        let (lo, hi) = bounds(range, self.len());
+        self.quantile(lo..hi, (hi - lo).saturating_sub(1) / 2)
+    }
+
+    /// Number of values in `range` that fall within `values`, in
+    /// `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn count(&self, range: impl RangeBounds<usize>, values: impl RangeBounds<T>) -> usize {
+        let (lo, hi) = bounds(range, self.len());
+        let from = match values.start_bound() {
+            Bound::Included(v) => self.keys.partition_point(|k| k < v),
+            Bound::Excluded(v) => self.keys.partition_point(|k| k <= v),
+            Bound::Unbounded => 0,
+        };
+        let to = match values.end_bound() {
+            Bound::Included(v) => self.keys.partition_point(|k| k <= v),
+            Bound::Excluded(v) => self.keys.partition_point(|k| k < v),
+            Bound::Unbounded => self.keys.len(),
+        };
+        if from >= to {
+            return 0;
+        }
+        let count = |version| self.counts.query(version, from..to) as usize;
+        count(hi) - count(lo)
+    }
+}
@@ -0,0 +1,369 @@
+//! A PH-tree: a prefix-sharing hypercube tree over integer keys.
+//!
+//! The PH-tree of Zäschke, Zimmerli and Norrie stores `N`-dimensional keys
+//! of `u64` coordinates as a map. Like an orthtree it descends one bit per
+//! axis at a time, taking the `N` bits at one bit position as the child's
+//! *hypercube address*, so keys are visited in Morton order. Unlike an
+//! orthtree it has no fixed depth: a node exists only where keys actually
+//! diverge, and the run of bits shared by everything below it (its infix)
+//! is skipped in one step. The tree's shape is therefore independent of
+//! insertion order, its depth is at most 64, and each entry costs little
+//! more than its key and value.
+//!
+//! Children are kept in a sorted list of `(address, child)` pairs, the
+//! sparse representation that suits high dimensions. Window queries turn
+//! the query box into two bit masks per node, which rule out every child
+//! address whose orthant misses the box without comparing coordinates.
+//! Floating-point coordinates can be stored through [`f64_to_key`], which
+//! preserves their order.
+
+const NONE: u32 = u32::MAX;
+
+/// Maps an `f64` to a `u64` with the same order, so floating-point points
+/// can be stored as keys. NaNs sort beyond the infinities.
+pub fn f64_to_key(x: f64) -> u64 {
+    let bits = x.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    }
+}
+
+/// Inverse of [`f64_to_key`].
+pub fn key_to_f64(key: u64) -> f64 {
+    if key >> 63 == 1 {
+        f64::from_bits(key & !(1 << 63))
+    } else {
+        f64::from_bits(!key)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Child<V, const N: usize> {
+    Node(u32),
+    Leaf([u64; N], V),
+}
+
+#[derive(Clone, Debug)]
+struct Node<V, const N: usize> {
+    /// Some key below the node; bits above `level` are shared by all of
+    /// them.
+    prefix: [u64; N],
+    /// The bit position whose `N` bits address the children.
+    level: u8,
+    /// Children sorted by hypercube address.
+    children: Vec<(u64, Child<V, N>)>,
+}
+
+/// A PH-tree mapping `N`-dimensional `u64` keys to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct PhTree<V, const N: usize> {
+    nodes: Vec<Option<Node<V, N>>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+}
+
+impl<V, const N: usize> Default for PhTree<V, N> {
+    fn default() -> Self {
+        PhTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+        }
+    }
+}
+
+impl<V, const N: usize> PhTree<V, N> {
+    /// Creates an empty tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero or exceeds 64, the width of an address.
+    pub fn new() -> Self {
+        assert!((1..=64).contains(&N), "dimension must be between 1 and 64");
+        Self::default()
+    }
+
+    /// Number of stored keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Inserts a key, returning the value it previously held.
+    pub fn insert(&mut self, key: [u64; N], value: V) -> Option<V> {
+        if self.root == NONE {
+            self.root = self.alloc(Node {
+                prefix: key,
+                level: 63,
+                children: Vec::new(),
+            });
+        }
+        let mut n = self.root;
+        let (pos, d, other) = loop {
+            let node = self.node(n);
+            let addr = address(&key, node.level);
+            let pos = match node.children.binary_search_by_key(&addr, |c| c.0) {
+                Ok(pos) => pos,
+                Err(pos) => {
+                    self.node_mut(n)
+                        .children
+                        .insert(pos, (addr, Child::Leaf(key, value)));
+                    self.len += 1;
+                    return None;
+                }
+            };
+            let below = node.level as u32;
+            match &node.children[pos].1 {
+                Child::Leaf(k, _) if *k == key => {
+                    let Child::Leaf(_, v) = &mut self.node_mut(n).children[pos].1 else {
+                        unreachable!("checked to be a leaf")
+                    };
+                    return Some(std::mem::replace(v, value));
+                }
+                Child::Leaf(k, _) => {
+                    break (pos, diverge(k, &key, below).expect("distinct keys"), *k);
+                }
+                &Child::Node(m) => {
+                    let child = self.node(m);
+                    match diverge(&child.prefix, &key, below) {
+                        Some(d) if d > child.level => break (pos, d, child.prefix),
+                        _ => n = m,
+                    }
+                }
+            }
+        };
+        // The key parts ways with the existing child at bit `d`: put a new
+        // node there holding both.
+        let mid = self.alloc(Node {
+            prefix: key,
+            level: d,
+            children: Vec::new(),
+        });
+        let old = std::mem::replace(&mut self.node_mut(n).children[pos].1, Child::Node(mid));
+        let mut pair = [
+            (address(&other, d), old),
+            (address(&key, d), Child::Leaf(key, value)),
+        ];
+        pair.sort_by_key(|c| c.0);
+        self.node_mut(mid).children.extend(pair);
+        self.len += 1;
+        None
+    }
+
+    /// Removes a key, returning its value.
+    pub fn remove(&mut self, key: &[u64; N]) -> Option<V> {
+        let (parent, n, pos) = self.locate(key)?;
+        let (_, child) = self.node_mut(n).children.remove(pos);
+        self.len -= 1;
+        let Child::Leaf(_, value) = child else {
+            unreachable!("located child is a leaf")
+        };
+        // A node left with a single child is spliced out; the root survives
+        // until it is empty.
+        let node = self.node(n);
+        if n == self.root {
+            if node.children.is_empty() {
+                self.release(n);
+                self.root = NONE;
+            }
+        } else if node.children.len() == 1 {
+            let (_, only) = self.node_mut(n).children.pop().expect("one child");
+            let slot = self
+                .node_mut(parent)
+                .children
+                .iter_mut()
+                .find(|c| matches!(c.1, Child::Node(m) if m == n))
+                .expect("child of its parent");
+            slot.1 = only;
+            self.release(n);
+        }
+        Some(value)
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get(&self, key: &[u64; N]) -> Option<&V> {
+        let (_, n, pos) = self.locate(key)?;
+        match &self.node(n).children[pos].1 {
+            Child::Leaf(_, v) => Some(v),
+            Child::Node(_) => None,
+        }
+    }
+
+    /// Returns the value stored under `key`, mutably.
+    pub fn get_mut(&mut self, key: &[u64; N]) -> Option<&mut V> {
+        let (_, n, pos) = self.locate(key)?;
+        match &mut self.node_mut(n).children[pos].1 {
+            Child::Leaf(_, v) => Some(v),
+            Child::Node(_) => None,
+        }
+    }
+
+    /// Whether `key` is stored.
+    pub fn contains_key(&self, key: &[u64; N]) -> bool {
+        self.locate(key).is_some()
+    }
+
+    /// Iterates over every entry in Morton order of the keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u64; N], &V)> + '_ {
+        self.window([0; N], [u64::MAX; N])
+    }
+
+    /// Iterates over the entries whose keys lie in the closed box
+    /// `min..=max`, in Morton order.
+    pub fn window(
+        &self,
+        min: [u64; N],
+        max: [u64; N],
+    ) -> impl Iterator<Item = (&[u64; N], &V)> + '_ {
+        let mut stack: Vec<&Child<V, N>> = Vec::new();
+        let mut pending = if self.root == NONE || (0..N).any(|a| min[a] > max[a]) {
+            None
+        } else {
+            Some(self.root)
+        };
+        std::iter::from_fn(move || loop {
+            if let Some(n) = pending.take() {
+                let node = self.node(n);
+                let (lower, upper) = masks(node, &min, &max);
+                stack.extend(
+                    node.children
+                        .iter()
+                        .rev()
+                        .filter(|(addr, _)| addr | lower == *addr && addr & upper == *addr)
+                        .map(|(_, c)| c),
+                );
+            }
+            match stack.pop()? {
+                Child::Leaf(k, v) => {
+                    if (0..N).all(|a| min[a] <= k[a] && k[a] <= max[a]) {
+                        return Some((k, v));
+                    }
+                }
+                &Child::Node(m) => {
+                    if self.overlaps(m, &min, &max) {
+                        pending = Some(m);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Finds `key`'s leaf, returning the parent of its node (`NONE` at the
+    /// root), the node, and the leaf's position in it.
+    fn locate(&self, key: &[u64; N]) -> Option<(u32, u32, usize)> {
+        if self.root == NONE {
+            return None;
+        }
+        let (mut parent, mut n) = (NONE, self.root);
+        loop {
+            let node = self.node(n);
+            let pos = node
+                .children
+                .binary_search_by_key(&address(key, node.level), |c| c.0)
+                .ok()?;
+            match &node.children[pos].1 {
+                Child::Leaf(k, _) => return (k == key).then_some((parent, n, pos)),
+                &Child::Node(m) => (parent, n) = (n, m),
+            }
+        }
+    }
+
+    /// Whether the region of node `n` meets the box.
+    fn overlaps(&self, n: u32, min: &[u64; N], max: &[u64; N]) -> bool {
+        let node = self.node(n);
+        let below = low_mask(node.level as u32 + 1);
+        (0..N).all(|a| {
+            let lo = node.prefix[a] & !below;
+            min[a] <= lo | below && lo <= max[a]
+        })
+    }
+
+    fn node(&self, n: u32) -> &Node<V, N> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<V, N> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+
+    fn alloc(&mut self, node: Node<V, N>) -> u32 {
+        match self.free.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = Some(node);
+                n
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn release(&mut self, n: u32) {
+        self.nodes[n as usize] = None;
+        self.free.push(n);
+    }
+}
+
+impl<V, const N: usize> FromIterator<([u64; N], V)> for PhTree<V, N> {
+    fn from_iter<I: IntoIterator<Item = ([u64; N], V)>>(iter: I) -> Self {
+        let mut tree = PhTree::new();
+        for (key, value) in iter {
+            tree.insert(key, value);
+        }
+        tree
+    }
+}
+
+/// The hypercube address of `key` at bit `level`: bit `a` of the address
+/// is bit `level` of coordinate `a`.
+fn address<const N: usize>(key: &[u64; N], level: u8) -> u64 {
+    (0..N).fold(0, |acc, a| acc | ((key[a] >> level) & 1) << a)
+}
+
+/// The highest bit below `limit` at which two keys differ.
+fn diverge<const N: usize>(a: &[u64; N], b: &[u64; N], limit: u32) -> Option<u8> {
+    let diff = (0..N).fold(0, |acc, i| acc | (a[i] ^ b[i])) & low_mask(limit);
+    (diff != 0).then(|| 63 - diff.leading_zeros() as u8)
+}
+
+/// A mask of the `bits` lowest bits.
+fn low_mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Hypercube masks of a node for a query box: addresses must contain every
+/// bit of `lower` (axes where only the upper half meets the box) and no
+/// bit outside `upper` (axes where the upper half does).
+fn masks<V, const N: usize>(node: &Node<V, N>, min: &[u64; N], max: &[u64; N]) -> (u64, u64) {
+    let below = low_mask(node.level as u32);
+    let (mut lower, mut upper) = (0, 0);
+    for a in 0..N {
+        // The first coordinate with bit `level` set within the node.
+        let mid = (node.prefix[a] & !below) | 1 << node.level;
+        if min[a] >= mid {
+            lower |= 1 << a;
+        }
+        if max[a] >= mid {
+            upper |= 1 << a;
+        }
+    }
+    (lower, upper)
+}
@@ -0,0 +1,288 @@
+//! A piece table: text as a list of spans over two append-only buffers.
+//!
+//! The original text is kept unchanged in one buffer, and everything ever
+//! inserted is appended to a second. The document itself is a list of
+//! pieces, each naming a span of one buffer. An edit never moves text: it
+//! replaces a few pieces around the edit point, splitting at most one.
+//! Neither buffer ever shrinks, so every piece ever created stays valid.
+//!
+//! That is what makes undo nearly free. Each edit is logged as the splice
+//! it made to the piece list: where, the pieces it took out, and the pieces
+//! it put in. [`undo`](PieceTable::undo) and [`redo`](PieceTable::redo)
+//! replay splices backwards and forwards without copying any text.
+//! Typing at the end of the last insertion extends its piece rather than
+//! adding one, so a run of keystrokes stays a single piece.
+//!
+//! Locating a position scans the piece list, so edits cost `O(pieces)`:
+//! fine for the thousands of pieces a typical editing session makes. For
+//! documents edited far more heavily, convert to a [`Rope`].
+//!
+//! [`Rope`]: crate::rope::Rope
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Piece {
+    /// Whether the span is in the add buffer rather than the original.
+    added: bool,
+    start: usize,
+    len: usize,
+}
+
+/// One edit, as the splice it made to the piece list.
+#[derive(Clone, Debug)]
+struct Splice {
+    at: usize,
+    removed: Vec<Piece>,
+    inserted: Vec<Piece>,
+}
+
+/// A text buffer with cheap edits and an unbounded undo history.
+#[derive(Clone, Debug, Default)]
+pub struct PieceTable {
+    original: String,
+    added: String,
+    pieces: Vec<Piece>,
+    len: usize,
+    undo: Vec<Splice>,
+    redo: Vec<Splice>,
+}
+
+impl PieceTable {
+    /// Creates an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of pieces the text is made of.
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Inserts `text` at byte offset `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end or not on a char boundary.
+    pub fn insert(&mut self, at: usize, text: &str) {
+        self.check_boundary(at);
+        if text.is_empty() {
+            return;
+        }
+        let new = Piece {
+            added: true,
+            start: self.added.len(),
+            len: text.len(),
+        };
+        self.added.push_str(text);
+        let (i, offset) = self.locate(at);
+        if offset == 0 {
+            // Extend the previous piece if it ends where the new text begins.
+            if let Some(&prev) = i.checked_sub(1).and_then(|p| self.pieces.get(p)) {
+                if prev.added && prev.start + prev.len == new.start {
+                    let grown = Piece {
+                        len: prev.len + new.len,
+                        ..prev
+                    };
+                    self.apply(i - 1, 1, vec![grown]);
+                    return;
+                }
+            }
+            self.apply(i, 0, vec![new]);
+        } else {
+            let (head, tail) = cut(self.pieces[i], offset);
+            self.apply(i, 1, vec![head, new, tail]);
+        }
+    }
+
+    /// Removes the bytes in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn remove(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        if range.is_empty() {
+            return;
+        }
+        let (first, start_offset) = self.locate(range.start);
+        let (last, end_offset) = self.locate(range.end);
+        // Keep the part of the first piece before the range and the part of
+        // the last piece after it.
+        let mut kept = Vec::new();
+        if start_offset > 0 {
+            kept.push(cut(self.pieces[first], start_offset).0);
+        }
+        let mut count = last - first;
+        if end_offset > 0 {
+            kept.push(cut(self.pieces[last], end_offset).1);
+            count += 1;
+        }
+        self.apply(first, count, kept);
+    }
+
+    /// Reverts the most recent edit not yet undone. Returns whether there
+    /// was one.
+    pub fn undo(&mut self) -> bool {
+        let Some(splice) = self.undo.pop() else {
+            return false;
+        };
+        self.splice(splice.at, splice.inserted.len(), &splice.removed);
+        self.redo.push(splice);
+        true
+    }
+
+    /// Reapplies the most recently undone edit. Returns whether there was
+    /// one.
+    pub fn redo(&mut self) -> bool {
+        let Some(splice) = self.redo.pop() else {
+            return false;
+        };
+        self.splice(splice.at, splice.removed.len(), &splice.inserted);
+        self.undo.push(splice);
+        true
+    }
+
+    /// Copies out the bytes in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn slice(&self, range: Range<usize>) -> String {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        let mut out = String::with_capacity(range.len());
+        let mut offset = 0;
+        for chunk in self.chunks() {
+            let end = offset + chunk.len();
+            if end > range.start && offset < range.end {
+                let from = range.start.saturating_sub(offset);
+                let to = (range.end - offset).min(chunk.len());
+                out.push_str(&chunk[from..to]);
+            }
+            offset = end;
+        }
+        out
+    }
+
+    /// Iterates over the spans of the text in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> + '_ {
+        self.pieces.iter().map(|&p| self.text(p))
+    }
+
+    fn text(&self, p: Piece) -> &str {
+        let buffer = if p.added { &self.added } else { &self.original };
+        &buffer[p.start..p.start + p.len]
+    }
+
+    /// The piece holding byte `at` and the offset into it, or the piece
+    /// count and zero for the end.
+    fn locate(&self, at: usize) -> (usize, usize) {
+        let mut offset = 0;
+        for (i, p) in self.pieces.iter().enumerate() {
+            if at < offset + p.len {
+                return (i, at - offset);
+            }
+            offset += p.len;
+        }
+        (self.pieces.len(), 0)
+    }
+
+    fn check_boundary(&self, at: usize) {
+        assert!(at <= self.len, "byte offset is past the end");
+        let (i, offset) = self.locate(at);
+        if let Some(&p) = self.pieces.get(i) {
+            assert!(
+                self.text(p).is_char_boundary(offset),
+                "byte offset is not on a char boundary"
+            );
+        }
+    }
+
+    /// Makes a splice as a new edit, discarding the redo history.
+    fn apply(&mut self, at: usize, count: usize, inserted: Vec<Piece>) {
+        let removed = self.pieces[at..at + count].to_vec();
+        self.splice(at, count, &inserted);
+        self.undo.push(Splice {
+            at,
+            removed,
+            inserted,
+        });
+        self.redo.clear();
+    }
+
+    fn splice(&mut self, at: usize, count: usize, with: &[Piece]) {
+        let removed: usize = self.pieces[at..at + count].iter().map(|p| p.len).sum();
+        let added: usize = with.iter().map(|p| p.len).sum();
+        self.pieces.splice(at..at + count, with.iter().copied());
+        self.len = self.len - removed + added;
+    }
+}
+
+/// Splits a piece `offset` bytes in.
+fn cut(p: Piece, offset: usize) -> (Piece, Piece) {
+    (
+        Piece { len: offset, ..p },
+        Piece {
+            start: p.start + offset,
+            len: p.len - offset,
+            ..p
+        },
+    )
+}
+
+impl From<&str> for PieceTable {
+    fn from(text: &str) -> Self {
+        let pieces = if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![Piece {
+                added: false,
+                start: 0,
+                len: text.len(),
+            }]
+        };
+        PieceTable {
+            original: text.to_string(),
+            pieces,
+            len: text.len(),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&Rope> for PieceTable {
+    fn from(rope: &Rope) -> Self {
+        PieceTable::from(rope.to_string().as_str())
+    }
+}
+
+impl From<&PieceTable> for Rope {
+    fn from(table: &PieceTable) -> Self {
+        Rope::from(table.to_string().as_str())
+    }
+}
+
+impl fmt::Display for PieceTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|c| f.write_str(c))
+    }
+}
@@ -0,0 +1,236 @@
+//! Shared scaffolding for the probabilistic filters and sketches.
+//!
+//! Filters and sketches only work across processes if every process hashes
+//! an item to the same bits. The standard library's hashers promise no such
+//! thing: `RandomState` is seeded per process, and `DefaultHasher`'s
+//! algorithm may change between releases. [`StableHasher`] is a seeded
+//! 64-bit hash whose output is fixed: integers are hashed as little-endian
+//! `u64`s whatever their width and the platform's byte order, so a filter
+//! built on one machine answers the same way on another.
+//!
+//! It mixes eight bytes at a time with a folded 64×64→128-bit multiply, as
+//! in wyhash, and finishes with SplitMix64's finalizer. That is not
+//! cryptographic: an adversary who knows the seed can choose colliding
+//! items.
+//!
+//! The filters derive all their probe positions from one hash by double
+//! hashing (Kirsch and Mitzenmacher), which keeps their false positive
+//! rates as good as with independent hashes. Their serialized forms report
+//! problems as a [`DecodeError`], and merging filters built with different
+//! parameters as [`Incompatible`].
+
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The seed the filters use unless given another.
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+const K0: u64 = 0xa076_1d64_78bd_642f;
+const K1: u64 = 0xe703_7ed1_a0b4_28db;
+const K2: u64 = 0x8ebc_6af0_9c88_c6e3;
+
+/// A seeded hasher whose output is the same on every run and platform.
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+    len: u64,
+}
+
+impl StableHasher {
+    /// Creates a hasher with the given seed.
+    pub fn new(seed: u64) -> Self {
+        StableHasher {
+            state: seed ^ K0,
+            len: 0,
+        }
+    }
+
+    fn word(&mut self, w: u64) {
+        self.state = fold(self.state ^ w, K1);
+        self.len += 8;
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.word(u64::from_le_bytes(chunk.try_into().expect("chunk of 8")));
+        }
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut buf = [0; 8];
+            buf[..rest.len()].copy_from_slice(rest);
+            // Tag the padding with the length so `[1]` and `[1, 0]` differ.
+            self.state = fold(self.state ^ u64::from_le_bytes(buf), K2 ^ rest.len() as u64);
+            self.len += rest.len() as u64;
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.word(u64::from(i));
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.word(u64::from(i));
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.word(u64::from(i));
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.word(i);
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.word(i as u64);
+        self.word((i >> 64) as u64);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.word(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        mix(fold(self.state, K0 ^ self.len))
+    }
+}
+
+/// Hashes one item with a [`StableHasher`] seeded with `seed`.
+pub fn stable_hash<T: Hash + ?Sized>(seed: u64, item: &T) -> u64 {
+    let mut hasher = StableHasher::new(seed);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The low and high halves of the 128-bit product, combined.
+fn fold(a: u64, b: u64) -> u64 {
+    let product = u128::from(a).wrapping_mul(u128::from(b));
+    product as u64 ^ (product >> 64) as u64
+}
+
+/// SplitMix64's finalizer.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// `count` positions below `range` derived from one hash by enhanced
+/// double hashing (Dillinger and Manolios), `h1 + i·h2 + (i³ - i)/6`.
+///
+/// Plain double hashing repeats a position whenever `h2` shares a factor
+/// with `range`, in the worst case probing one position `count` times; the
+/// cubic term keeps the positions apart.
+pub(crate) fn probes(hash: u64, count: u32, range: u64) -> impl Iterator<Item = u64> {
+    let mut x = hash % range;
+    let mut y = mix(hash) % range;
+    (0..u64::from(count)).map(move |i| {
+        let position = x;
+        x = add_mod(x, y, range);
+        y = add_mod(y, i % range, range);
+        position
+    })
+}
+
+/// `(a + b) % m` for `a, b < m`, without overflow.
+fn add_mod(a: u64, b: u64, m: u64) -> u64 {
+    if a >= m - b {
+        a - (m - b)
+    } else {
+        a + b
+    }
+}
+
+/// Errors from decoding a serialized filter or sketch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The bytes do not start with the expected tag and version.
+    WrongFormat,
+    /// The bytes end before the encoded contents do.
+    Truncated,
+    /// A parameter or the contents are out of range.
+    Invalid,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DecodeError::WrongFormat => "bytes are not in the expected format",
+            DecodeError::Truncated => "bytes end unexpectedly",
+            DecodeError::Invalid => "encoded parameters are invalid",
+        })
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Returned when combining filters or sketches built with different
+/// parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Incompatible;
+
+impl fmt::Display for Incompatible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("filters have different parameters")
+    }
+}
+
+impl Error for Incompatible {}
+
+/// Reads the little-endian fields of a serialized form in order.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Checks the tag at the start of `bytes` and reads on from after it.
+    pub(crate) fn new(bytes: &'a [u8], tag: &[u8; 4]) -> Result<Self, DecodeError> {
+        match bytes.strip_prefix(tag) {
+            Some(rest) => Ok(Reader { bytes: rest }),
+            None => Err(DecodeError::WrongFormat),
+        }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.bytes.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("took 4"),
+        ))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(
+            self.take(8)?.try_into().expect("took 8"),
+        ))
+    }
+
+    /// Fails unless every byte has been read.
+    pub(crate) fn finish(self) -> Result<(), DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::Invalid)
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! A bucketed region quadtree over 2D points.
+//!
+//! The quadtree is the two-dimensional [`Orthtree`]: every node splits into
+//! four quadrants, and points are routed by their Morton order. See the
+//! [`orthtree`](crate::orthtree) module for the shared implementation.
+
+use crate::geom::Aabb;
+use crate::orthtree::Orthtree;
+pub use crate::orthtree::{Orthant, OutOfBounds, Point, PointId, MAX_DEPTH};
+
+/// An axis-aligned rectangle, closed on all sides.
+pub type Rect = Aabb<2>;
+
+/// A quadtree mapping 2D points of type `P` to data of type `D`.
+pub type Quadtree<P, D> = Orthtree<P, D, 2>;
@@ -0,0 +1,371 @@
+//! A compressed radix trie (Patricia trie) keyed by byte strings.
+//!
+//! A plain trie spends one node per key byte. A radix trie collapses every
+//! chain of single-child nodes into one edge labelled with the whole byte
+//! run, so the node count is at most twice the number of keys, whatever
+//! their length. A lookup follows one edge per branching point and compares
+//! the label bytes on the way. It costs `O(key length)` and never depends on
+//! how many keys are stored.
+//!
+//! The shape is what makes prefix queries cheap. The keys starting with
+//! some prefix are exactly one subtree, so [`iter_prefix`](RadixTrie::iter_prefix)
+//! descends once and then walks that subtree in lexicographic order. The
+//! longest stored key that prefixes a query, the routing-table lookup, is
+//! the last valued node on that query's path
+//! ([`longest_prefix_match`](RadixTrie::longest_prefix_match)).
+//!
+//! [`search_fuzzy`](RadixTrie::search_fuzzy) finds the keys within an edit
+//! distance of a query, for autocompletion that tolerates typos. It shares
+//! the work for common prefixes the same way.
+//!
+//! Each node's children are kept sorted by the first byte of their
+//! labels, which is unique among siblings. Removal merges a node left with a
+//! single child back into it, so the tree stays compressed.
+
+const ROOT: u32 = 0;
+
+#[derive(Clone, Debug)]
+struct Node<V> {
+    /// The bytes on the edge into this node.
+    label: Vec<u8>,
+    value: Option<V>,
+    /// Sorted by the first byte of each child's label.
+    children: Vec<u32>,
+}
+
+/// A map from byte strings to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct RadixTrie<V> {
+    /// Node 0 is the root, with an empty label.
+    nodes: Vec<Node<V>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<V> Default for RadixTrie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> RadixTrie<V> {
+    /// Creates an empty trie.
+    pub fn new() -> Self {
+        RadixTrie {
+            nodes: vec![Node {
+                label: Vec::new(),
+                value: None,
+                children: Vec::new(),
+            }],
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the trie holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of live nodes, the root included.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&V> {
+        self.nodes[self.find(key)? as usize].value.as_ref()
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut(&mut self, key: &[u8]) -> Option<&mut V> {
+        let n = self.find(key)?;
+        self.nodes[n as usize].value.as_mut()
+    }
+
+    /// Whether a value is stored under `key`.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value under `key`, returning the one it replaced, if any.
+    pub fn insert(&mut self, key: &[u8], value: V) -> Option<V> {
+        let (mut n, mut i) = (ROOT, 0);
+        loop {
+            if i == key.len() {
+                let old = self.nodes[n as usize].value.replace(value);
+                if old.is_none() {
+                    self.len += 1;
+                }
+                return old;
+            }
+            let rest = &key[i..];
+            let slot = match self.child_slot(n, rest[0]) {
+                Ok(slot) => slot,
+                Err(slot) => {
+                    let leaf = self.alloc(Node {
+                        label: rest.to_vec(),
+                        value: Some(value),
+                        children: Vec::new(),
+                    });
+                    self.nodes[n as usize].children.insert(slot, leaf);
+                    self.len += 1;
+                    return None;
+                }
+            };
+            let c = self.nodes[n as usize].children[slot];
+            let label = &self.nodes[c as usize].label;
+            let common = common_prefix(label, rest);
+            if common < label.len() {
+                // The key leaves the edge partway: split it there.
+                let tail = self.nodes[c as usize].label.split_off(common);
+                let head = std::mem::replace(&mut self.nodes[c as usize].label, tail);
+                let mid = self.alloc(Node {
+                    label: head,
+                    value: None,
+                    children: vec![c],
+                });
+                self.nodes[n as usize].children[slot] = mid;
+                n = mid;
+            } else {
+                n = c;
+            }
+            i += common;
+        }
+    }
+
+    /// Removes the value stored under `key`, returning it.
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        let mut path = Vec::new();
+        let (mut n, mut i) = (ROOT, 0);
+        while i < key.len() {
+            let slot = self.child_slot(n, key[i]).ok()?;
+            let c = self.nodes[n as usize].children[slot];
+            if !key[i..].starts_with(&self.nodes[c as usize].label) {
+                return None;
+            }
+            path.push((n, slot));
+            i += self.nodes[c as usize].label.len();
+            n = c;
+        }
+        let value = self.nodes[n as usize].value.take()?;
+        self.len -= 1;
+        // Drop the node if it is now an empty leaf, then fold whichever
+        // node is left with no value and one child into that child.
+        if n != ROOT && self.nodes[n as usize].children.is_empty() {
+            let (parent, slot) = path.pop().expect("non-root node has a parent");
+            self.nodes[parent as usize].children.remove(slot);
+            self.release(n);
+            n = parent;
+        }
+        let node = &self.nodes[n as usize];
+        if n != ROOT && node.value.is_none() && node.children.len() == 1 {
+            let c = node.children[0];
+            let child = std::mem::replace(
+                &mut self.nodes[c as usize],
+                Node {
+                    label: Vec::new(),
+                    value: None,
+                    children: Vec::new(),
+                },
+            );
+            let node = &mut self.nodes[n as usize];
+            node.label.extend_from_slice(&child.label);
+            node.value = child.value;
+            node.children = child.children;
+            self.release(c);
+        }
+        Some(value)
+    }
+
+    /// The longest stored key that is a prefix of `query`, as a slice of
+    /// `query`, with its value.
+    pub fn longest_prefix_match<'q>(&self, query: &'q [u8]) -> Option<(&'q [u8], &V)> {
+        let mut best = self.nodes[ROOT as usize].value.as_ref().map(|v| (0, v));
+        let (mut n, mut i) = (ROOT, 0);
+        while i < query.len() {
+            let Ok(slot) = self.child_slot(n, query[i]) else {
+                break;
+            };
+            let c = self.nodes[n as usize].children[slot];
+            let label = &self.nodes[c as usize].label;
+            if !query[i..].starts_with(label) {
+                break;
+            }
+            i += label.len();
+            n = c;
+            if let Some(v) = &self.nodes[n as usize].value {
+                best = Some((i, v));
+            }
+        }
+        best.map(|(len, v)| (&query[..len], v))
+    }
+
+    /// Iterates over every key and value in lexicographic key order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, &V)> + '_ {
+        self.iter_prefix(&[])
+    }
+
+    /// Iterates over the keys starting with `prefix`, and their values, in
+    /// lexicographic key order.
+    pub fn iter_prefix(&self, prefix: &[u8]) -> impl Iterator<Item = (Vec<u8>, &V)> + '_ {
+        // Find the highest node whose key extends the prefix, and the length
+        // of its key above its own label.
+        let (mut n, mut i) = (ROOT, 0);
+        let start = loop {
+            if i == prefix.len() {
+                break Some((n, i - self.nodes[n as usize].label.len()));
+            }
+            let Ok(slot) = self.child_slot(n, prefix[i]) else {
+                break None;
+            };
+            let c = self.nodes[n as usize].children[slot];
+            let label = &self.nodes[c as usize].label;
+            let rest = &prefix[i..];
+            if label.starts_with(rest) {
+                break Some((c, i));
+            }
+            if !rest.starts_with(label) {
+                break None;
+            }
+            i += label.len();
+            n = c;
+        };
+        let mut key = Vec::new();
+        let mut stack = Vec::new();
+        if let Some((n, depth)) = start {
+            key.extend_from_slice(&prefix[..depth]);
+            stack.push((n, depth));
+        }
+        std::iter::from_fn(move || loop {
+            let (n, depth) = stack.pop()?;
+            let node = &self.nodes[n as usize];
+            key.truncate(depth);
+            key.extend_from_slice(&node.label);
+            let depth = key.len();
+            stack.extend(node.children.iter().rev().map(|&c| (c, depth)));
+            if let Some(v) = &node.value {
+                return Some((key.clone(), v));
+            }
+        })
+    }
+
+    /// Iterates over the keys within `max_edits` byte insertions, deletions
+    /// or substitutions of `query`, in lexicographic key order, with their
+    /// values and edit distances.
+    ///
+    /// The walk carries one row of the Levenshtein table down each edge, a
+    /// column per query byte, and abandons a subtree as soon as every entry
+    /// of its row exceeds `max_edits`. So the cost depends on how many nodes
+    /// lie near the query, not on the size of the trie.
+    pub fn search_fuzzy<'a>(
+        &'a self,
+        query: &'a [u8],
+        max_edits: usize,
+    ) -> impl Iterator<Item = (Vec<u8>, &'a V, usize)> + 'a {
+        let mut key = Vec::new();
+        // Each entry is a node, the key length above its label, and the row
+        // for that key.
+        let first: Vec<usize> = (0..=query.len()).collect();
+        let mut stack = vec![(ROOT, 0, first)];
+        std::iter::from_fn(move || loop {
+            let (n, depth, mut row) = stack.pop()?;
+            let node = &self.nodes[n as usize];
+            key.truncate(depth);
+            let mut alive = true;
+            for &b in &node.label {
+                key.push(b);
+                let mut next = Vec::with_capacity(row.len());
+                next.push(row[0] + 1);
+                for (j, &q) in query.iter().enumerate() {
+                    let substitute = row[j] + usize::from(q != b);
+                    next.push(substitute.min(row[j + 1] + 1).min(next[j] + 1));
+                }
+                row = next;
+                if row.iter().all(|&d| d > max_edits) {
+                    alive = false;
+                    break;
+                }
+            }
+            if !alive {
+                continue;
+            }
+            let depth = key.len();
+            stack.extend(node.children.iter().rev().map(|&c| (c, depth, row.clone())));
+            let distance = row[query.len()];
+            if let Some(v) = node.value.as_ref().filter(|_| distance <= max_edits) {
+                return Some((key.clone(), v, distance));
+            }
+        })
+    }
+
+    /// The node whose key is exactly `key`.
+    fn find(&self, key: &[u8]) -> Option<u32> {
+        let (mut n, mut i) = (ROOT, 0);
+        while i < key.len() {
+            let slot = self.child_slot(n, key[i]).ok()?;
+            let c = self.nodes[n as usize].children[slot];
+            let label = &self.nodes[c as usize].label;
+            if !key[i..].starts_with(label) {
+                return None;
+            }
+            i += label.len();
+            n = c;
+        }
+        Some(n)
+    }
+
+    /// The position among `n`'s children of the one whose label starts
+    /// with `byte`, or where it would go.
+    fn child_slot(&self, n: u32, byte: u8) -> Result<usize, usize> {
+        self.nodes[n as usize]
+            .children
+            .binary_search_by_key(&byte, |&c| self.nodes[c as usize].label[0])
+    }
+
+    fn alloc(&mut self, node: Node<V>) -> u32 {
+        match self.free.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    fn release(&mut self, n: u32) {
+        self.nodes[n as usize] = Node {
+            label: Vec::new(),
+            value: None,
+            children: Vec::new(),
+        };
+        self.free.push(n);
+    }
+}
+
+impl<K: AsRef<[u8]>, V> FromIterator<(K, V)> for RadixTrie<V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut trie = RadixTrie::new();
+        for (k, v) in iter {
+            trie.insert(k.as_ref(), v);
+        }
+        trie
+    }
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
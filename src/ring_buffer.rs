@@ -0,0 +1,317 @@
+//! A fixed-capacity ring buffer with its capacity in the type.
+//!
+//! A ring buffer stores up to `N` items in an inline array, treating it as
+//! a circle: the oldest item sits at `head`, the rest follow it around the
+//! end of the array and back to the start, and pushing and popping move
+//! the ends without shifting anything. The buffer never allocates, so it
+//! suits embedded and `no_std` use, such as keeping the last `N` samples
+//! of a telemetry stream; this module uses only `core`.
+//!
+//! When the buffer is full, [`push_overwrite`](RingBuffer::push_overwrite)
+//! drops the oldest item to make room, the right choice for "most recent
+//! `N`" windows, while [`try_push`](RingBuffer::try_push) refuses and
+//! hands the item back, for queues that must not lose data.
+//!
+//! Items wrap around the array, so the contents are in general two slices:
+//! [`as_slices`](RingBuffer::as_slices) returns both, oldest first, and
+//! [`make_contiguous`](RingBuffer::make_contiguous) rotates the array so
+//! they form one. Empty slots are left uninitialised, so `T` needs no
+//! placeholder value; the `len` slots from `head` on are the only ones
+//! holding items, and the only ones dropped with the buffer.
+
+use core::fmt;
+use core::mem::MaybeUninit;
+
+/// A ring buffer holding at most `N` items.
+pub struct RingBuffer<T, const N: usize> {
+    /// The slots, initialised exactly for the `len` from `head` on,
+    /// wrapping around.
+    items: [MaybeUninit<T>; N],
+    /// Index of the oldest item.
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// Creates an empty buffer.
+    pub const fn new() -> Self {
+        RingBuffer {
+            items: [const { MaybeUninit::uninit() }; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// The number of items the buffer holds when full, `N`.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the buffer holds `N` items.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Removes every item.
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+        self.head = 0;
+    }
+
+    /// Appends `value` as the newest item, or returns it if the buffer is
+    /// full.
+    pub fn try_push(&mut self, value: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(value);
+        }
+        let slot = self.slot(self.len);
+        self.items[slot].write(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends `value` as the newest item, removing and returning the
+    /// oldest if the buffer is full.
+    pub fn push_overwrite(&mut self, value: T) -> Option<T> {
+        if N == 0 {
+            return Some(value);
+        }
+        if !self.is_full() {
+            let slot = self.slot(self.len);
+            self.items[slot].write(value);
+            self.len += 1;
+            return None;
+        }
+        let oldest = core::mem::replace(&mut self.items[self.head], MaybeUninit::new(value));
+        self.head = self.slot(1);
+        // SAFETY: the buffer is full, so its head slot was initialised.
+        Some(unsafe { oldest.assume_init() })
+    }
+
+    /// Removes and returns the oldest item.
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        // SAFETY: the head slot of a nonempty buffer is initialised, and
+        // moving `head` past it leaves it uninitialised again.
+        let value = unsafe { self.items[self.head].assume_init_read() };
+        self.head = self.slot(1);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Removes and returns the newest item.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        let slot = self.slot(self.len);
+        // SAFETY: the slot held the newest item, and is now past the end.
+        Some(unsafe { self.items[slot].assume_init_read() })
+    }
+
+    /// The oldest item.
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// The newest item.
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// The item `i` places after the oldest.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        // SAFETY: the first `len` slots from `head` are initialised.
+        (i < self.len).then(|| unsafe { self.items[self.slot(i)].assume_init_ref() })
+    }
+
+    /// The item `i` places after the oldest, mutably.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        if i >= self.len {
+            return None;
+        }
+        let slot = self.slot(i);
+        // SAFETY: the first `len` slots from `head` are initialised.
+        Some(unsafe { self.items[slot].assume_init_mut() })
+    }
+
+    /// The items as two slices, oldest first; the second is empty unless
+    /// the items wrap around the end of the array.
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let end = self.head + self.len;
+        let (a, b) = if end <= N {
+            (&self.items[self.head..end], &[][..])
+        } else {
+            (&self.items[self.head..], &self.items[..end - N])
+        };
+        // SAFETY: both slices cover only the live slots.
+        unsafe { (assume_init(a), assume_init(b)) }
+    }
+
+    /// The items as two mutable slices, oldest first.
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let end = self.head + self.len;
+        let (a, b) = if end <= N {
+            (&mut self.items[self.head..end], &mut [][..])
+        } else {
+            let (wrapped, rest) = self.items.split_at_mut(self.head);
+            (rest, &mut wrapped[..end - N])
+        };
+        // SAFETY: both slices cover only the live slots.
+        unsafe { (assume_init_mut(a), assume_init_mut(b)) }
+    }
+
+    /// Rotates the array so the items are contiguous and returns them,
+    /// oldest first, in `O(N)` if they wrapped.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if self.head + self.len > N {
+            self.items.rotate_left(self.head);
+            self.head = 0;
+        }
+        let live = &mut self.items[self.head..self.head + self.len];
+        // SAFETY: a rotation moves the live slots to the front, contents
+        // and all, so these are still exactly the live ones.
+        unsafe { assume_init_mut(live) }
+    }
+
+    /// Iterates over the items, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator + '_ {
+        let (a, b) = self.as_slices();
+        Chain { a, b }
+    }
+
+    /// The array index of the item `i` places after the oldest.
+    fn slot(&self, i: usize) -> usize {
+        let slot = self.head + i;
+        if slot >= N {
+            slot - N
+        } else {
+            slot
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for RingBuffer<T, N> {
+    fn drop(&mut self) {
+        let (a, b) = self.as_mut_slices();
+        // SAFETY: these are the live items, and nothing reads them after.
+        unsafe {
+            core::ptr::drop_in_place(a);
+            core::ptr::drop_in_place(b);
+        }
+    }
+}
+
+/// A clone holds clones of the items; its empty slots stay uninitialised.
+impl<T: Clone, const N: usize> Clone for RingBuffer<T, N> {
+    fn clone(&self) -> Self {
+        let mut copy = Self::new();
+        for item in self.iter() {
+            // The copy has room for every item, and is dropped cleanly if
+            // a clone panics.
+            let _ = copy.try_push(item.clone());
+        }
+        copy
+    }
+}
+
+/// The items of slots known to be initialised.
+///
+/// # Safety
+///
+/// Every slot in `slots` must be initialised.
+unsafe fn assume_init<T>(slots: &[MaybeUninit<T>]) -> &[T] {
+    // SAFETY: `MaybeUninit<T>` has the layout of `T`, and the caller
+    // guarantees the slots are initialised.
+    unsafe { &*(slots as *const [MaybeUninit<T>] as *const [T]) }
+}
+
+/// The items of slots known to be initialised, mutably.
+///
+/// # Safety
+///
+/// Every slot in `slots` must be initialised.
+unsafe fn assume_init_mut<T>(slots: &mut [MaybeUninit<T>]) -> &mut [T] {
+    // SAFETY: as for `assume_init`.
+    unsafe { &mut *(slots as *mut [MaybeUninit<T>] as *mut [T]) }
+}
+
+/// The two slices of a buffer, as one exactly sized iterator.
+struct Chain<'a, T> {
+    a: &'a [T],
+    b: &'a [T],
+}
+
+impl<'a, T> Iterator for Chain<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.a.is_empty() {
+            core::mem::swap(&mut self.a, &mut self.b);
+        }
+        let (first, rest) = self.a.split_first()?;
+        self.a = rest;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.a.len() + self.b.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Chain<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.b.is_empty() {
+            core::mem::swap(&mut self.a, &mut self.b);
+        }
+        let (last, rest) = self.b.split_last()?;
+        self.b = rest;
+        Some(last)
+    }
+}
+
+impl<T> ExactSizeIterator for Chain<'_, T> {}
+
+impl<T, const N: usize> Extend<T> for RingBuffer<T, N> {
+    /// Pushes each item with [`push_overwrite`](RingBuffer::push_overwrite),
+    /// so the buffer ends up with the last `N`.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_overwrite(value);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<T> for RingBuffer<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut buffer = Self::new();
+        buffer.extend(iter);
+        buffer
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for RingBuffer<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
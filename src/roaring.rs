@@ -0,0 +1,600 @@
+//! A roaring bitmap: a compressed set of `u32`s.
+//!
+//! A roaring bitmap (Chambi, Lemire, Kaser and Godin, 2016) splits the
+//! key space into chunks of 2^16 by the keys' high 16 bits, and stores
+//! each nonempty chunk in whichever of two containers suits its density: a
+//! sorted array of the low 16 bits while the chunk holds at most 4096
+//! keys, or a 2^16-bit bitmap, 8 KiB, once it holds more. Either way a
+//! key costs at most 16 bits, sparse sets stay small and dense ones are as
+//! compact as a plain bitmap.
+//!
+//! Set operations work chunk by chunk, on the matching containers only,
+//! with a specialized routine for each pair of container kinds: bitmaps
+//! combine a word at a time, arrays by merging, and an array against a
+//! bitmap by probing each array key. That makes union, intersection and
+//! difference of posting lists or ID sets fast whatever their densities,
+//! which is why roaring bitmaps back most inverted indexes.
+//!
+//! Every chunk also records how many keys the chunks before it hold, so
+//! rank and select find their container by binary search, in
+//! `O(log k)` for `k` chunks, then count bits or index the array within
+//! it. Inserting or removing a key updates the counts of the chunks after
+//! its own, which is linear in their number but only a pass over a
+//! `Vec<u64>`. The run-length containers of later roaring versions are
+//! not implemented.
+
+use std::fmt;
+
+/// Above this many keys a chunk is stored as a bitmap.
+const ARRAY_MAX: usize = 4096;
+const BITMAP_WORDS: usize = 1024;
+
+#[derive(Clone, PartialEq, Eq)]
+enum Container {
+    /// Sorted low bits.
+    Array(Vec<u16>),
+    Bitmap {
+        words: Box<[u64; BITMAP_WORDS]>,
+        len: u32,
+    },
+}
+
+impl Container {
+    fn len(&self) -> u32 {
+        match self {
+            Container::Array(keys) => keys.len() as u32,
+            Container::Bitmap { len, .. } => *len,
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(keys) => keys.binary_search(&low).is_ok(),
+            Container::Bitmap { words, .. } => words[usize::from(low) / 64] >> (low % 64) & 1 == 1,
+        }
+    }
+
+    fn insert(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(keys) => match keys.binary_search(&low) {
+                Ok(_) => false,
+                Err(i) => {
+                    keys.insert(i, low);
+                    if keys.len() > ARRAY_MAX {
+                        *self = Container::bitmap_of(keys);
+                    }
+                    true
+                }
+            },
+            Container::Bitmap { words, len } => {
+                let (word, bit) = (usize::from(low) / 64, 1 << (low % 64));
+                let added = words[word] & bit == 0;
+                words[word] |= bit;
+                *len += u32::from(added);
+                added
+            }
+        }
+    }
+
+    fn remove(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(keys) => match keys.binary_search(&low) {
+                Ok(i) => {
+                    keys.remove(i);
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap { words, len } => {
+                let (word, bit) = (usize::from(low) / 64, 1 << (low % 64));
+                let removed = words[word] & bit != 0;
+                words[word] &= !bit;
+                *len -= u32::from(removed);
+                if *len as usize <= ARRAY_MAX {
+                    *self = Container::Array(self.iter().collect());
+                }
+                removed
+            }
+        }
+    }
+
+    fn bitmap_of(keys: &[u16]) -> Container {
+        let mut words = Box::new([0u64; BITMAP_WORDS]);
+        for &k in keys {
+            words[usize::from(k) / 64] |= 1 << (k % 64);
+        }
+        Container::Bitmap {
+            words,
+            len: keys.len() as u32,
+        }
+    }
+
+    /// A bitmap container from its words, or an array if sparse enough.
+    fn from_words(words: Box<[u64; BITMAP_WORDS]>) -> Container {
+        let len: u32 = words.iter().map(|w| w.count_ones()).sum();
+        let container = Container::Bitmap { words, len };
+        if len as usize <= ARRAY_MAX {
+            Container::Array(container.iter().collect())
+        } else {
+            container
+        }
+    }
+
+    /// An array container, or a bitmap if too dense.
+    fn from_keys(keys: Vec<u16>) -> Container {
+        if keys.len() > ARRAY_MAX {
+            Container::bitmap_of(&keys)
+        } else {
+            Container::Array(keys)
+        }
+    }
+
+    fn iter(&self) -> ContainerIter<'_> {
+        match self {
+            Container::Array(keys) => ContainerIter::Array(keys.iter()),
+            Container::Bitmap { words, .. } => ContainerIter::Bitmap {
+                words,
+                index: 0,
+                current: words[0],
+            },
+        }
+    }
+
+    /// Number of keys below `low`.
+    fn rank(&self, low: u16) -> u32 {
+        match self {
+            Container::Array(keys) => keys.partition_point(|&k| k < low) as u32,
+            Container::Bitmap { words, .. } => {
+                let word = usize::from(low) / 64;
+                let full: u32 = words[..word].iter().map(|w| w.count_ones()).sum();
+                let mask = (1 << (low % 64)) - 1;
+                full + (words[word] & mask).count_ones()
+            }
+        }
+    }
+
+    /// The low bits of the key with `n` keys before it.
+    fn select(&self, mut n: u32) -> u16 {
+        match self {
+            Container::Array(keys) => keys[n as usize],
+            Container::Bitmap { words, .. } => {
+                for (i, &w) in words.iter().enumerate() {
+                    let ones = w.count_ones();
+                    if n < ones {
+                        let mut w = w;
+                        for _ in 0..n {
+                            w &= w - 1;
+                        }
+                        return (i * 64) as u16 + w.trailing_zeros() as u16;
+                    }
+                    n -= ones;
+                }
+                unreachable!("select past the container's length")
+            }
+        }
+    }
+
+    fn union(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Array(a), Container::Array(b)) => {
+                let mut keys = Vec::with_capacity(a.len() + b.len());
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() && j < b.len() {
+                    match a[i].cmp(&b[j]) {
+                        std::cmp::Ordering::Less => {
+                            keys.push(a[i]);
+                            i += 1;
+                        }
+                        std::cmp::Ordering::Greater => {
+                            keys.push(b[j]);
+                            j += 1;
+                        }
+                        std::cmp::Ordering::Equal => {
+                            keys.push(a[i]);
+                            i += 1;
+                            j += 1;
+                        }
+                    }
+                }
+                keys.extend_from_slice(&a[i..]);
+                keys.extend_from_slice(&b[j..]);
+                Container::from_keys(keys)
+            }
+            (Container::Bitmap { words, .. }, Container::Array(keys))
+            | (Container::Array(keys), Container::Bitmap { words, .. }) => {
+                let mut words = words.clone();
+                for &k in keys {
+                    words[usize::from(k) / 64] |= 1 << (k % 64);
+                }
+                Container::from_words(words)
+            }
+            (Container::Bitmap { words: a, .. }, Container::Bitmap { words: b, .. }) => {
+                let mut words = a.clone();
+                for (w, &v) in words.iter_mut().zip(b.iter()) {
+                    *w |= v;
+                }
+                Container::from_words(words)
+            }
+        }
+    }
+
+    fn intersection(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Array(a), Container::Array(b)) => {
+                let mut keys = Vec::with_capacity(a.len().min(b.len()));
+                let (mut i, mut j) = (0, 0);
+                while i < a.len() && j < b.len() {
+                    match a[i].cmp(&b[j]) {
+                        std::cmp::Ordering::Less => i += 1,
+                        std::cmp::Ordering::Greater => j += 1,
+                        std::cmp::Ordering::Equal => {
+                            keys.push(a[i]);
+                            i += 1;
+                            j += 1;
+                        }
+                    }
+                }
+                Container::Array(keys)
+            }
+            (bitmap @ Container::Bitmap { .. }, Container::Array(keys))
+            | (Container::Array(keys), bitmap @ Container::Bitmap { .. }) => Container::Array(
+                keys.iter()
+                    .copied()
+                    .filter(|&k| bitmap.contains(k))
+                    .collect(),
+            ),
+            (Container::Bitmap { words: a, .. }, Container::Bitmap { words: b, .. }) => {
+                let mut words = a.clone();
+                for (w, &v) in words.iter_mut().zip(b.iter()) {
+                    *w &= v;
+                }
+                Container::from_words(words)
+            }
+        }
+    }
+
+    fn difference(&self, other: &Container) -> Container {
+        match (self, other) {
+            (Container::Array(a), _) => {
+                Container::Array(a.iter().copied().filter(|&k| !other.contains(k)).collect())
+            }
+            (Container::Bitmap { words, .. }, Container::Array(keys)) => {
+                let mut words = words.clone();
+                for &k in keys {
+                    words[usize::from(k) / 64] &= !(1 << (k % 64));
+                }
+                Container::from_words(words)
+            }
+            (Container::Bitmap { words: a, .. }, Container::Bitmap { words: b, .. }) => {
+                let mut words = a.clone();
+                for (w, &v) in words.iter_mut().zip(b.iter()) {
+                    *w &= !v;
+                }
+                Container::from_words(words)
+            }
+        }
+    }
+}
+
+enum ContainerIter<'a> {
+    Array(std::slice::Iter<'a, u16>),
+    Bitmap {
+        words: &'a [u64; BITMAP_WORDS],
+        index: usize,
+        current: u64,
+    },
+}
+
+impl Iterator for ContainerIter<'_> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match self {
+            ContainerIter::Array(keys) => keys.next().copied(),
+            ContainerIter::Bitmap {
+                words,
+                index,
+                current,
+            } => {
+                while *current == 0 {
+                    *index += 1;
+                    if *index == BITMAP_WORDS {
+                        return None;
+                    }
+                    *current = words[*index];
+                }
+                let bit = current.trailing_zeros();
+                *current &= *current - 1;
+                Some((*index * 64) as u16 + bit as u16)
+            }
+        }
+    }
+}
+
+/// A compressed set of `u32` keys.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct RoaringBitmap {
+    /// High 16 bits of each chunk, sorted.
+    highs: Vec<u16>,
+    containers: Vec<Container>,
+    /// Number of keys in the chunks before each chunk.
+    starts: Vec<u64>,
+}
+
+impl RoaringBitmap {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> u64 {
+        match (self.starts.last(), self.containers.last()) {
+            (Some(&start), Some(c)) => start + u64::from(c.len()),
+            _ => 0,
+        }
+    }
+
+    /// Whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        self.highs.clear();
+        self.containers.clear();
+        self.starts.clear();
+    }
+
+    /// Whether `key` is in the set.
+    pub fn contains(&self, key: u32) -> bool {
+        let (high, low) = split(key);
+        self.highs
+            .binary_search(&high)
+            .is_ok_and(|i| self.containers[i].contains(low))
+    }
+
+    /// Adds `key`. Returns whether it was absent.
+    pub fn insert(&mut self, key: u32) -> bool {
+        let (high, low) = split(key);
+        let i = match self.highs.binary_search(&high) {
+            Ok(i) => {
+                if !self.containers[i].insert(low) {
+                    return false;
+                }
+                i
+            }
+            Err(i) => {
+                let start = self.starts.get(i).copied().unwrap_or(self.len());
+                self.highs.insert(i, high);
+                self.containers.insert(i, Container::Array(vec![low]));
+                self.starts.insert(i, start);
+                i
+            }
+        };
+        for start in &mut self.starts[i + 1..] {
+            *start += 1;
+        }
+        true
+    }
+
+    /// Removes `key`. Returns whether it was present.
+    pub fn remove(&mut self, key: u32) -> bool {
+        let (high, low) = split(key);
+        let Ok(i) = self.highs.binary_search(&high) else {
+            return false;
+        };
+        if !self.containers[i].remove(low) {
+            return false;
+        }
+        for start in &mut self.starts[i + 1..] {
+            *start -= 1;
+        }
+        if self.containers[i].len() == 0 {
+            self.highs.remove(i);
+            self.containers.remove(i);
+            self.starts.remove(i);
+        }
+        true
+    }
+
+    /// The smallest key.
+    pub fn min(&self) -> Option<u32> {
+        let c = self.containers.first()?;
+        Some(join(self.highs[0], c.iter().next().expect("nonempty")))
+    }
+
+    /// The largest key.
+    pub fn max(&self) -> Option<u32> {
+        let c = self.containers.last()?;
+        Some(join(*self.highs.last()?, c.select(c.len() - 1)))
+    }
+
+    /// Number of keys below `key`, in `O(log k)` for `k` chunks plus the
+    /// count within `key`'s chunk.
+    pub fn rank(&self, key: u32) -> u64 {
+        let (high, low) = split(key);
+        let i = self.highs.partition_point(|&h| h < high);
+        match self.highs.get(i) {
+            Some(&h) if h == high => self.starts[i] + u64::from(self.containers[i].rank(low)),
+            Some(_) => self.starts[i],
+            None => self.len(),
+        }
+    }
+
+    /// The key with `n` smaller keys in the set, or `None` if the set has
+    /// `n` keys or fewer.
+    pub fn select(&self, n: u64) -> Option<u32> {
+        if n >= self.len() {
+            return None;
+        }
+        // The last chunk starting at or before `n`, which is nonempty.
+        let i = self.starts.partition_point(|&start| start <= n) - 1;
+        let c = &self.containers[i];
+        Some(join(self.highs[i], c.select((n - self.starts[i]) as u32)))
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            highs: self.highs.iter(),
+            containers: self.containers.iter(),
+            current: None,
+        }
+    }
+
+    /// The keys in either set.
+    pub fn union(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        self.combine(other, true, true, Container::union)
+    }
+
+    /// The keys in both sets.
+    pub fn intersection(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        self.combine(other, false, false, Container::intersection)
+    }
+
+    /// The keys in this set but not in `other`.
+    pub fn difference(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        self.combine(other, true, false, Container::difference)
+    }
+
+    /// Number of keys in both sets, without building the intersection.
+    pub fn intersection_len(&self, other: &RoaringBitmap) -> u64 {
+        let (mut i, mut j) = (0, 0);
+        let mut count = 0;
+        while i < self.highs.len() && j < other.highs.len() {
+            match self.highs[i].cmp(&other.highs[j]) {
+                std::cmp::Ordering::Less => i += 1,
+                std::cmp::Ordering::Greater => j += 1,
+                std::cmp::Ordering::Equal => {
+                    let (a, b) = (&self.containers[i], &other.containers[j]);
+                    count += match (a, b) {
+                        (
+                            Container::Bitmap { words: x, .. },
+                            Container::Bitmap { words: y, .. },
+                        ) => x
+                            .iter()
+                            .zip(y.iter())
+                            .map(|(p, q)| u64::from((p & q).count_ones()))
+                            .sum(),
+                        (Container::Array(keys), other) | (other, Container::Array(keys)) => {
+                            keys.iter().filter(|&&k| other.contains(k)).count() as u64
+                        }
+                    };
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Merges the chunk lists, applying `op` where both sets have a chunk
+    /// and keeping this set's or the other's unmatched chunks as asked.
+    fn combine(
+        &self,
+        other: &RoaringBitmap,
+        keep_left: bool,
+        keep_right: bool,
+        op: fn(&Container, &Container) -> Container,
+    ) -> RoaringBitmap {
+        let mut result = RoaringBitmap::new();
+        let mut push = |high: u16, c: Container| {
+            if c.len() > 0 {
+                result.starts.push(result.len());
+                result.highs.push(high);
+                result.containers.push(c);
+            }
+        };
+        let (mut i, mut j) = (0, 0);
+        while i < self.highs.len() || j < other.highs.len() {
+            let left = self.highs.get(i);
+            let right = other.highs.get(j);
+            match (left, right) {
+                (Some(&a), Some(&b)) if a == b => {
+                    push(a, op(&self.containers[i], &other.containers[j]));
+                    i += 1;
+                    j += 1;
+                }
+                (Some(&a), b) if b.is_none_or(|&b| a < b) => {
+                    if keep_left {
+                        push(a, self.containers[i].clone());
+                    }
+                    i += 1;
+                }
+                (_, Some(&b)) => {
+                    if keep_right {
+                        push(b, other.containers[j].clone());
+                    }
+                    j += 1;
+                }
+                (_, None) => unreachable!("loop condition"),
+            }
+        }
+        result
+    }
+}
+
+fn split(key: u32) -> (u16, u16) {
+    ((key >> 16) as u16, key as u16)
+}
+
+fn join(high: u16, low: u16) -> u32 {
+    u32::from(high) << 16 | u32::from(low)
+}
+
+impl fmt::Debug for RoaringBitmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the keys of a [`RoaringBitmap`] in ascending order.
+pub struct Iter<'a> {
+    highs: std::slice::Iter<'a, u16>,
+    containers: std::slice::Iter<'a, Container>,
+    current: Option<(u16, ContainerIter<'a>)>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            if let Some((high, keys)) = &mut self.current {
+                if let Some(low) = keys.next() {
+                    return Some(join(*high, low));
+                }
+            }
+            let high = *self.highs.next()?;
+            let container = self.containers.next().expect("one per chunk");
+            self.current = Some((high, container.iter()));
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a RoaringBitmap {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl Extend<u32> for RoaringBitmap {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl FromIterator<u32> for RoaringBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut set = RoaringBitmap::new();
+        set.extend(iter);
+        set
+    }
+}
@@ -0,0 +1,572 @@
+//! A rope: text stored as a balanced tree of string chunks.
+//!
+//! Editing a large text held in one `String` copies everything after the
+//! edit point. A rope cuts the text into chunks of at most a kilobyte and
+//! keeps them in order at the leaves of a height-balanced binary tree.
+//! Each node records the byte, char and line-break totals of its subtree,
+//! so any byte, char or line position is found by one descent.
+//!
+//! An edit that fits inside one chunk just rewrites that chunk and the
+//! totals above it. Anything larger splits the tree at the edit points and
+//! joins the pieces back together, each in `O(log n)`. Joining two trees
+//! walks down the taller one's spine to the height of the shorter and
+//! rebalances on the way back up, as AVL trees do. Neighbouring leaves
+//! that meet in a join are merged if they fit in one chunk, which keeps
+//! repeated edits from fragmenting the text.
+//!
+//! Positions are byte offsets unless a method says otherwise, and must fall
+//! on char boundaries. Lines are separated by `\n`, so a text with `k` line
+//! breaks has `k + 1` lines.
+
+use std::fmt;
+use std::ops::Range;
+
+/// The most bytes a leaf holds.
+const MAX_LEAF: usize = 1024;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Metrics {
+    bytes: usize,
+    chars: usize,
+    breaks: usize,
+}
+
+impl Metrics {
+    fn of(s: &str) -> Self {
+        Metrics {
+            bytes: s.len(),
+            chars: s.chars().count(),
+            breaks: s.bytes().filter(|&b| b == b'\n').count(),
+        }
+    }
+
+    fn add(self, o: Self) -> Self {
+        Metrics {
+            bytes: self.bytes + o.bytes,
+            chars: self.chars + o.chars,
+            breaks: self.breaks + o.breaks,
+        }
+    }
+
+    fn sub(self, o: Self) -> Self {
+        Metrics {
+            bytes: self.bytes - o.bytes,
+            chars: self.chars - o.chars,
+            breaks: self.breaks - o.breaks,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Leaf(String),
+    Branch(Box<Node>, Box<Node>),
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    metrics: Metrics,
+    height: u8,
+    kind: Kind,
+}
+
+/// A text supporting `O(log n)` edits and position lookups anywhere in it.
+#[derive(Clone, Debug, Default)]
+pub struct Rope {
+    root: Option<Box<Node>>,
+}
+
+impl Rope {
+    /// Creates an empty rope.
+    pub fn new() -> Self {
+        Rope { root: None }
+    }
+
+    /// Length in bytes.
+    pub fn len_bytes(&self) -> usize {
+        self.metrics().bytes
+    }
+
+    /// Length in chars.
+    pub fn len_chars(&self) -> usize {
+        self.metrics().chars
+    }
+
+    /// Number of lines: one more than the number of line breaks.
+    pub fn len_lines(&self) -> usize {
+        self.metrics().breaks + 1
+    }
+
+    /// Whether the rope is empty.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Number of tree levels; zero for an empty rope.
+    pub fn height(&self) -> usize {
+        self.root.as_ref().map_or(0, |n| n.height as usize)
+    }
+
+    /// Inserts `text` at byte offset `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end or not on a char boundary.
+    pub fn insert(&mut self, at: usize, text: &str) {
+        self.check_boundary(at);
+        if text.is_empty() {
+            return;
+        }
+        let added = Metrics::of(text);
+        if let Some(root) = &mut self.root {
+            if insert_in_leaf(root, at, text, added) {
+                return;
+            }
+        }
+        let (left, right) = split(self.root.take(), at);
+        self.root = join(join(left, build(text)), right);
+    }
+
+    /// Inserts `text` at char index `at`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end.
+    pub fn insert_at_char(&mut self, at: usize, text: &str) {
+        let at = self.char_to_byte(at);
+        self.insert(at, text);
+    }
+
+    /// Removes the bytes in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn remove(&mut self, range: Range<usize>) {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        if range.is_empty() {
+            return;
+        }
+        if let Some(root) = &mut self.root {
+            if remove_in_leaf(root, range.clone()) {
+                return;
+            }
+        }
+        let (left, rest) = split(self.root.take(), range.start);
+        let (_, right) = split(rest, range.len());
+        self.root = join(left, right);
+    }
+
+    /// Removes the chars with indices in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or past the end.
+    pub fn remove_chars(&mut self, range: Range<usize>) {
+        let start = self.char_to_byte(range.start);
+        let end = self.char_to_byte(range.end);
+        self.remove(start..end);
+    }
+
+    /// Copies out the bytes in `range`, in `O(log n + range.len())`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing, past the end, or either end is not
+    /// on a char boundary.
+    pub fn slice(&self, range: Range<usize>) -> String {
+        assert!(range.start <= range.end, "range is decreasing");
+        self.check_boundary(range.start);
+        self.check_boundary(range.end);
+        let mut out = String::with_capacity(range.len());
+        if let Some(root) = &self.root {
+            collect(root, 0, &range, &mut out);
+        }
+        out
+    }
+
+    /// Copies out the chars with indices in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or past the end.
+    pub fn slice_chars(&self, range: Range<usize>) -> String {
+        self.slice(self.char_to_byte(range.start)..self.char_to_byte(range.end))
+    }
+
+    /// The char index of the char containing byte offset `at`, or the char
+    /// count for the end.
+    ///
+    /// Unlike the other byte offsets, `at` need not be on a char boundary:
+    /// an offset inside a multi-byte char maps to that char.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end.
+    pub fn byte_to_char(&self, at: usize) -> usize {
+        assert!(at <= self.len_bytes(), "byte offset is past the end");
+        let (leaf, offset, before) = self.leaf_at(at, |m| m.bytes);
+        let starts = |bytes: &[u8]| bytes.iter().filter(|&&b| b & 0xc0 != 0x80).count();
+        let bytes = leaf.as_bytes();
+        if offset == bytes.len() {
+            return before.chars + starts(bytes);
+        }
+        // Leaves start on char boundaries, so the char containing the
+        // offset is the last one starting at or before it.
+        before.chars + starts(&bytes[..=offset]) - 1
+    }
+
+    /// The byte offset of the char with index `at`, or the length for the
+    /// end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end.
+    pub fn char_to_byte(&self, at: usize) -> usize {
+        assert!(at <= self.len_chars(), "char index is past the end");
+        let (leaf, offset, before) = self.leaf_at(at, |m| m.chars);
+        let inner = leaf
+            .char_indices()
+            .nth(offset)
+            .map_or(leaf.len(), |(i, _)| i);
+        before.bytes + inner
+    }
+
+    /// The line containing byte offset `at`: the number of line breaks
+    /// before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is past the end.
+    pub fn byte_to_line(&self, at: usize) -> usize {
+        assert!(at <= self.len_bytes(), "byte offset is past the end");
+        let (leaf, offset, before) = self.leaf_at(at, |m| m.bytes);
+        before.breaks
+            + leaf.as_bytes()[..offset]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count()
+    }
+
+    /// The byte offset where line `line` starts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line` is not below [`len_lines`](Self::len_lines).
+    pub fn line_to_byte(&self, line: usize) -> usize {
+        assert!(line < self.len_lines(), "line is past the end");
+        if line == 0 {
+            return 0;
+        }
+        // Find the leaf holding the `line`-th break, counting from one.
+        let mut node = self.root.as_deref().expect("a break exists");
+        let (mut k, mut offset) = (line, 0);
+        loop {
+            match &node.kind {
+                Kind::Branch(l, r) => {
+                    if k <= l.metrics.breaks {
+                        node = l;
+                    } else {
+                        k -= l.metrics.breaks;
+                        offset += l.metrics.bytes;
+                        node = r;
+                    }
+                }
+                Kind::Leaf(s) => {
+                    let (i, _) = s
+                        .bytes()
+                        .enumerate()
+                        .filter(|&(_, b)| b == b'\n')
+                        .nth(k - 1)
+                        .expect("leaf holds the break");
+                    return offset + i + 1;
+                }
+            }
+        }
+    }
+
+    /// The text of line `line`, without its line break.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line` is not below [`len_lines`](Self::len_lines).
+    pub fn line(&self, line: usize) -> String {
+        let start = self.line_to_byte(line);
+        let end = if line + 1 < self.len_lines() {
+            self.line_to_byte(line + 1) - 1
+        } else {
+            self.len_bytes()
+        };
+        self.slice(start..end)
+    }
+
+    /// Iterates over the chunks of the text in order.
+    pub fn chunks(&self) -> impl Iterator<Item = &str> + '_ {
+        let mut stack: Vec<&Node> = self.root.as_deref().into_iter().collect();
+        std::iter::from_fn(move || loop {
+            match &stack.pop()?.kind {
+                Kind::Leaf(s) => return Some(s.as_str()),
+                Kind::Branch(l, r) => {
+                    stack.push(r);
+                    stack.push(l);
+                }
+            }
+        })
+    }
+
+    fn metrics(&self) -> Metrics {
+        self.root
+            .as_ref()
+            .map_or_else(Metrics::default, |n| n.metrics)
+    }
+
+    /// The leaf containing position `at` in the unit `unit` measures, the
+    /// offset within it in that unit, and the metrics of all text before the
+    /// leaf. The end of the text maps to the end of the last leaf.
+    fn leaf_at(&self, mut at: usize, unit: impl Fn(&Metrics) -> usize) -> (&str, usize, Metrics) {
+        let mut before = Metrics::default();
+        let Some(mut node) = self.root.as_deref() else {
+            return ("", 0, before);
+        };
+        loop {
+            match &node.kind {
+                Kind::Branch(l, r) => {
+                    if at < unit(&l.metrics) {
+                        node = l;
+                    } else {
+                        at -= unit(&l.metrics);
+                        before = before.add(l.metrics);
+                        node = r;
+                    }
+                }
+                Kind::Leaf(s) => return (s, at, before),
+            }
+        }
+    }
+
+    fn check_boundary(&self, at: usize) {
+        assert!(at <= self.len_bytes(), "byte offset is past the end");
+        let (leaf, offset, _) = self.leaf_at(at, |m| m.bytes);
+        assert!(
+            leaf.is_char_boundary(offset),
+            "byte offset is not on a char boundary"
+        );
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        Rope { root: build(text) }
+    }
+}
+
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|c| f.write_str(c))
+    }
+}
+
+fn leaf(text: String) -> Box<Node> {
+    Box::new(Node {
+        metrics: Metrics::of(&text),
+        height: 1,
+        kind: Kind::Leaf(text),
+    })
+}
+
+fn branch(l: Box<Node>, r: Box<Node>) -> Box<Node> {
+    Box::new(Node {
+        metrics: l.metrics.add(r.metrics),
+        height: 1 + l.height.max(r.height),
+        kind: Kind::Branch(l, r),
+    })
+}
+
+fn children(n: Node) -> (Box<Node>, Box<Node>) {
+    match n.kind {
+        Kind::Branch(l, r) => (l, r),
+        Kind::Leaf(_) => unreachable!("a taller node is a branch"),
+    }
+}
+
+/// A perfectly balanced tree over `text` cut into full chunks.
+fn build(text: &str) -> Option<Box<Node>> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut cut = rest.len().min(MAX_LEAF);
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        chunks.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+    fn pair_up(chunks: &[&str]) -> Box<Node> {
+        if let [chunk] = chunks {
+            return leaf(chunk.to_string());
+        }
+        let (l, r) = chunks.split_at(chunks.len() / 2);
+        branch(pair_up(l), pair_up(r))
+    }
+    (!chunks.is_empty()).then(|| pair_up(&chunks))
+}
+
+/// A branch over two trees whose heights differ by at most two, rotated
+/// back into balance.
+fn balanced(l: Box<Node>, r: Box<Node>) -> Box<Node> {
+    if l.height > r.height + 1 {
+        let (ll, lr) = children(*l);
+        if ll.height >= lr.height {
+            return branch(ll, branch(lr, r));
+        }
+        let (lrl, lrr) = children(*lr);
+        return branch(branch(ll, lrl), branch(lrr, r));
+    }
+    if r.height > l.height + 1 {
+        let (rl, rr) = children(*r);
+        if rr.height >= rl.height {
+            return branch(branch(l, rl), rr);
+        }
+        let (rll, rlr) = children(*rl);
+        return branch(branch(l, rll), branch(rlr, rr));
+    }
+    branch(l, r)
+}
+
+/// Concatenates two trees.
+fn join(a: Option<Box<Node>>, b: Option<Box<Node>>) -> Option<Box<Node>> {
+    let (a, b) = match (a, b) {
+        (None, b) => return b,
+        (a, None) => return a,
+        (Some(a), Some(b)) => (a, b),
+    };
+    Some(join_nodes(a, b))
+}
+
+fn join_nodes(a: Box<Node>, b: Box<Node>) -> Box<Node> {
+    if a.height > b.height + 1 {
+        let (al, ar) = children(*a);
+        return balanced(al, join_nodes(ar, b));
+    }
+    if b.height > a.height + 1 {
+        let (bl, br) = children(*b);
+        return balanced(join_nodes(a, bl), br);
+    }
+    if let (Kind::Leaf(x), Kind::Leaf(y)) = (&a.kind, &b.kind) {
+        if x.len() + y.len() <= MAX_LEAF {
+            return leaf(format!("{x}{y}"));
+        }
+    }
+    branch(a, b)
+}
+
+/// Splits a tree into the bytes before `at` and the rest.
+fn split(node: Option<Box<Node>>, at: usize) -> (Option<Box<Node>>, Option<Box<Node>>) {
+    let Some(node) = node else {
+        return (None, None);
+    };
+    if at == 0 {
+        return (None, Some(node));
+    }
+    if at >= node.metrics.bytes {
+        return (Some(node), None);
+    }
+    match node.kind {
+        Kind::Leaf(mut s) => {
+            let right = s.split_off(at);
+            (Some(leaf(s)), Some(leaf(right)))
+        }
+        Kind::Branch(l, r) => {
+            let lb = l.metrics.bytes;
+            if at <= lb {
+                let (a, b) = split(Some(l), at);
+                (a, join(b, Some(r)))
+            } else {
+                let (a, b) = split(Some(r), at - lb);
+                (join(Some(l), a), b)
+            }
+        }
+    }
+}
+
+/// Inserts into the leaf holding `at` if the text fits there, updating the
+/// totals on the way back up. Returns whether it did.
+fn insert_in_leaf(node: &mut Node, at: usize, text: &str, added: Metrics) -> bool {
+    let done = match &mut node.kind {
+        Kind::Leaf(s) => {
+            if s.len() + text.len() > MAX_LEAF {
+                return false;
+            }
+            s.insert_str(at, text);
+            true
+        }
+        Kind::Branch(l, r) => {
+            let lb = l.metrics.bytes;
+            if at <= lb {
+                insert_in_leaf(l, at, text, added)
+            } else {
+                insert_in_leaf(r, at - lb, text, added)
+            }
+        }
+    };
+    if done {
+        node.metrics = node.metrics.add(added);
+    }
+    done
+}
+
+/// Removes `range` from the leaf holding it if it lies within one leaf and
+/// leaves it non-empty, updating the totals. Returns whether it did.
+fn remove_in_leaf(node: &mut Node, range: Range<usize>) -> bool {
+    let removed = match &mut node.kind {
+        Kind::Leaf(s) => {
+            if range.len() == s.len() {
+                return false;
+            }
+            let removed = Metrics::of(&s[range.clone()]);
+            s.replace_range(range, "");
+            removed
+        }
+        Kind::Branch(l, r) => {
+            let lb = l.metrics.bytes;
+            let done = if range.end <= lb {
+                remove_in_leaf(l, range)
+            } else if range.start >= lb {
+                remove_in_leaf(r, range.start - lb..range.end - lb)
+            } else {
+                false
+            };
+            if !done {
+                return false;
+            }
+            // The child's totals already changed; recompute from them.
+            node.metrics = l.metrics.add(r.metrics);
+            return true;
+        }
+    };
+    node.metrics = node.metrics.sub(removed);
+    true
+}
+
+/// Appends the part of `node`, which starts at byte `offset`, inside
+/// `range` to `out`.
+fn collect(node: &Node, offset: usize, range: &Range<usize>, out: &mut String) {
+    let end = offset + node.metrics.bytes;
+    if end <= range.start || offset >= range.end {
+        return;
+    }
+    match &node.kind {
+        Kind::Leaf(s) => {
+            let from = range.start.saturating_sub(offset);
+            let to = (range.end - offset).min(s.len());
+            out.push_str(&s[from..to]);
+        }
+        Kind::Branch(l, r) => {
+            collect(l, offset, range, out);
+            collect(r, offset + l.metrics.bytes, range, out);
+        }
+    }
+}
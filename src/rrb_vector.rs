@@ -0,0 +1,721 @@
+//! A persistent vector stored as a relaxed radix-balanced tree.
+//!
+//! A persistent vector is immutable in effect: cloning one is `O(1)`, and
+//! changing a clone leaves the original untouched, because the two share
+//! every node the change does not touch. The elements sit in leaves of up
+//! to 32, under a tree of 32-way branches, so the tree is at most
+//! `log₃₂ n` deep: seven levels index four billion elements. An update
+//! copies only the path from the root to its leaf, and a node owned by a
+//! single vector is updated in place rather than copied at all. The last
+//! leaf is kept outside the tree as a tail, so most pushes and pops touch
+//! nothing else.
+//!
+//! In a plain radix tree every node but the rightmost is full, which is
+//! what lets an index pick its child by shifting, but also what makes
+//! concatenation and slicing `O(n)`: the pieces must be repacked. The RRB
+//! tree (Bagwell and Rompf, 2011) relaxes that. Nodes may be less than
+//! full, and each branch records the cumulative sizes of its children, so
+//! an index still guesses the child by shifting and then steps forward
+//! past any shortfall. Concatenation merges the two trees only along the
+//! seam between them, and repacks a level of that seam only when it holds
+//! more than two nodes beyond the minimum its elements need. That bound
+//! keeps the forward steps few, and makes concatenation, slicing and
+//! splitting `O(log n)`.
+//!
+//! For building a vector from many pushes, a [`Transient`] collects
+//! elements in a plain buffer and adds them to the tree a full leaf at a
+//! time when made persistent again.
+
+use std::fmt;
+use std::mem;
+use std::ops::{Index, RangeBounds};
+use std::sync::Arc;
+
+use crate::util::bounds;
+
+/// Bits of index consumed per level.
+const BITS: usize = 5;
+/// Children per branch and elements per leaf.
+const WIDTH: usize = 1 << BITS;
+/// Nodes a level of a concatenation seam may hold beyond the minimum.
+const EXTRA: usize = 2;
+
+#[derive(Clone, Debug)]
+enum Node<T> {
+    Leaf(Vec<T>),
+    Branch {
+        children: Vec<Arc<Node<T>>>,
+        /// Elements in the first `i + 1` children, for each `i`.
+        sizes: Vec<usize>,
+    },
+}
+
+impl<T: Clone> Node<T> {
+    fn branch(children: Vec<Arc<Node<T>>>) -> Self {
+        let mut total = 0;
+        let sizes = children
+            .iter()
+            .map(|child| {
+                total += child.len();
+                total
+            })
+            .collect();
+        Node::Branch { children, sizes }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Node::Leaf(items) => items.len(),
+            Node::Branch { sizes, .. } => sizes.last().copied().unwrap_or(0),
+        }
+    }
+
+    /// Number of elements or children.
+    fn slots(&self) -> usize {
+        match self {
+            Node::Leaf(items) => items.len(),
+            Node::Branch { children, .. } => children.len(),
+        }
+    }
+
+    fn children(&self) -> &[Arc<Node<T>>] {
+        match self {
+            Node::Branch { children, .. } => children,
+            Node::Leaf(_) => unreachable!("leaves have no children"),
+        }
+    }
+
+    fn items(&self) -> &[T] {
+        match self {
+            Node::Leaf(items) => items,
+            Node::Branch { .. } => unreachable!("branches have no items"),
+        }
+    }
+}
+
+/// The index of the child of a branch at `height` holding element `i`.
+fn child_index(sizes: &[usize], i: usize, height: usize) -> usize {
+    // Children hold at most `WIDTH^height` elements, so shifting gives a
+    // lower bound.
+    let mut index = i >> (BITS * height);
+    while sizes[index] <= i {
+        index += 1;
+    }
+    index
+}
+
+/// A persistent vector of `T`s.
+#[derive(Clone)]
+pub struct RrbVector<T> {
+    /// The tree, with leaves at depth `height`.
+    root: Option<Arc<Node<T>>>,
+    height: usize,
+    /// The elements after the tree.
+    tail: Vec<T>,
+    len: usize,
+}
+
+impl<T: Clone> Default for RrbVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> RrbVector<T> {
+    /// Creates an empty vector.
+    pub fn new() -> Self {
+        RrbVector {
+            root: None,
+            height: 0,
+            tail: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every element.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// The element at `index`, in `O(log n)`.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        let tree_len = self.len - self.tail.len();
+        if index >= tree_len {
+            return self.tail.get(index - tree_len);
+        }
+        let (mut node, mut height, mut i) = (self.root.as_deref()?, self.height, index);
+        loop {
+            match node {
+                Node::Leaf(items) => return Some(&items[i]),
+                Node::Branch { children, sizes } => {
+                    let c = child_index(sizes, i, height);
+                    if c > 0 {
+                        i -= sizes[c - 1];
+                    }
+                    node = &children[c];
+                    height -= 1;
+                }
+            }
+        }
+    }
+
+    /// The element at `index` mutably, copying the nodes on its path that
+    /// are shared with other vectors.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        let tree_len = self.len - self.tail.len();
+        if index >= tree_len {
+            return self.tail.get_mut(index - tree_len);
+        }
+        let (mut height, mut i) = (self.height, index);
+        let mut node = Arc::make_mut(self.root.as_mut()?);
+        loop {
+            match node {
+                Node::Leaf(items) => return Some(&mut items[i]),
+                Node::Branch { children, sizes } => {
+                    let c = child_index(sizes, i, height);
+                    if c > 0 {
+                        i -= sizes[c - 1];
+                    }
+                    node = Arc::make_mut(&mut children[c]);
+                    height -= 1;
+                }
+            }
+        }
+    }
+
+    /// Replaces the element at `index`, returning the old one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> T {
+        let len = self.len;
+        let slot = self
+            .get_mut(index)
+            .unwrap_or_else(|| panic!("index {index} out of bounds for length {len}"));
+        mem::replace(slot, value)
+    }
+
+    /// The first element.
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    /// The last element.
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    /// Appends `value`, in `O(1)` amortized: only every 32nd push adds a
+    /// leaf to the tree.
+    pub fn push_back(&mut self, value: T) {
+        if self.tail.len() == WIDTH {
+            let leaf = mem::replace(&mut self.tail, Vec::with_capacity(WIDTH));
+            self.push_leaf(leaf);
+        }
+        self.tail.push(value);
+        self.len += 1;
+    }
+
+    /// Removes and returns the last element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.tail.is_empty() {
+            self.tail = self.pop_leaf()?;
+        }
+        self.len -= 1;
+        self.tail.pop()
+    }
+
+    /// Appends the elements of `other`, sharing its nodes, in `O(log n)`.
+    pub fn append(&mut self, other: &Self) {
+        if other.root.is_none() {
+            for value in &other.tail {
+                self.push_back(value.clone());
+            }
+            return;
+        }
+        let left = self.take_tree();
+        let right = other.root.clone().map(|root| (root, other.height));
+        self.set_tree(join(left, right));
+        self.tail = other.tail.clone();
+        self.len += other.len;
+    }
+
+    /// The elements in `range`, sharing this vector's nodes, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let (lo, hi) = bounds(range, self.len);
+        let mut result = Self::new();
+        if lo == hi {
+            return result;
+        }
+        let Some((root, height)) = self.clone().take_tree() else {
+            unreachable!("a nonempty vector has a tree once its tail is added")
+        };
+        let root = drop_front(&take_front(&root, hi), lo);
+        result.set_tree(Some((root, height)));
+        result.len = hi - lo;
+        result
+    }
+
+    /// Splits the vector at `at`, keeping the elements before it and
+    /// returning the rest, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at` is greater than the length.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        assert!(
+            at <= self.len,
+            "split index {at} out of bounds for length {}",
+            self.len
+        );
+        let rest = self.slice(at..);
+        *self = self.slice(..at);
+        rest
+    }
+
+    /// Iterates over the elements.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: self.root.iter().map(std::slice::from_ref).collect(),
+            leaf: &[],
+            tail: &self.tail,
+            remaining: self.len,
+        }
+    }
+
+    /// Converts the vector into a [`Transient`] for a batch of pushes.
+    pub fn transient(self) -> Transient<T> {
+        Transient {
+            vector: self,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds a nonempty leaf to the right edge of the tree.
+    fn push_leaf(&mut self, leaf: Vec<T>) {
+        let leaf_len = leaf.len();
+        let leaf = Arc::new(Node::Leaf(leaf));
+        let root = match self.root.take() {
+            None => leaf,
+            Some(root) if self.height == 0 => {
+                self.height = 1;
+                Arc::new(Node::branch(vec![root, leaf]))
+            }
+            Some(mut root) => match push_leaf(&mut root, self.height, leaf, leaf_len) {
+                None => root,
+                Some(sibling) => {
+                    self.height += 1;
+                    Arc::new(Node::branch(vec![root, sibling]))
+                }
+            },
+        };
+        self.root = Some(root);
+    }
+
+    /// Removes the last leaf of the tree.
+    fn pop_leaf(&mut self) -> Option<Vec<T>> {
+        let mut root = self.root.take()?;
+        if self.height == 0 {
+            return Some(into_items(root));
+        }
+        let leaf = pop_leaf(&mut root, self.height);
+        self.set_tree(Some((root, self.height)));
+        Some(leaf)
+    }
+
+    /// Takes the tree with the tail joined onto it, leaving the vector's
+    /// tree empty.
+    fn take_tree(&mut self) -> Option<(Arc<Node<T>>, usize)> {
+        let tree = self.root.take().map(|root| (root, self.height));
+        if self.tail.is_empty() {
+            return tree;
+        }
+        let tail = Arc::new(Node::Leaf(mem::take(&mut self.tail)));
+        join(tree, Some((tail, 0)))
+    }
+
+    /// Installs `tree`, collapsing single-child roots.
+    fn set_tree(&mut self, mut tree: Option<(Arc<Node<T>>, usize)>) {
+        while let Some((root, height)) = &tree {
+            match &**root {
+                Node::Branch { children, .. } if children.is_empty() => tree = None,
+                Node::Branch { children, .. } if children.len() == 1 => {
+                    tree = Some((children[0].clone(), height - 1));
+                }
+                _ => break,
+            }
+        }
+        (self.root, self.height) = match tree {
+            Some((root, height)) => (Some(root), height),
+            None => (None, 0),
+        };
+    }
+}
+
+/// Adds `leaf` below the right edge of the branch `node` at `height`.
+/// Returns a new sibling for `node` if it had no room.
+fn push_leaf<T: Clone>(
+    node: &mut Arc<Node<T>>,
+    height: usize,
+    leaf: Arc<Node<T>>,
+    leaf_len: usize,
+) -> Option<Arc<Node<T>>> {
+    let Node::Branch { children, sizes } = Arc::make_mut(node) else {
+        unreachable!("leaves are at height 0")
+    };
+    let orphan = if height == 1 {
+        leaf
+    } else {
+        let last = children.last_mut().expect("branches are nonempty");
+        match push_leaf(last, height - 1, leaf, leaf_len) {
+            None => {
+                *sizes.last_mut().expect("branches are nonempty") += leaf_len;
+                return None;
+            }
+            Some(orphan) => orphan,
+        }
+    };
+    if children.len() < WIDTH {
+        let total = sizes.last().copied().unwrap_or(0) + leaf_len;
+        children.push(orphan);
+        sizes.push(total);
+        None
+    } else {
+        Some(Arc::new(Node::branch(vec![orphan])))
+    }
+}
+
+/// Removes the last leaf below the branch `node` at `height`, leaving
+/// `node` empty if it was its only leaf.
+fn pop_leaf<T: Clone>(node: &mut Arc<Node<T>>, height: usize) -> Vec<T> {
+    let Node::Branch { children, sizes } = Arc::make_mut(node) else {
+        unreachable!("leaves are at height 0")
+    };
+    if height == 1 {
+        let leaf = children.pop().expect("branches are nonempty");
+        sizes.pop();
+        return into_items(leaf);
+    }
+    let last = children.last_mut().expect("branches are nonempty");
+    let leaf = pop_leaf(last, height - 1);
+    if last.slots() == 0 {
+        children.pop();
+        sizes.pop();
+    } else {
+        *sizes.last_mut().expect("branches are nonempty") -= leaf.len();
+    }
+    leaf
+}
+
+fn into_items<T: Clone>(leaf: Arc<Node<T>>) -> Vec<T> {
+    match Arc::unwrap_or_clone(leaf) {
+        Node::Leaf(items) => items,
+        Node::Branch { .. } => unreachable!("leaves are at height 0"),
+    }
+}
+
+/// Concatenates two trees.
+fn join<T: Clone>(
+    left: Option<(Arc<Node<T>>, usize)>,
+    right: Option<(Arc<Node<T>>, usize)>,
+) -> Option<(Arc<Node<T>>, usize)> {
+    let (left, right) = match (left, right) {
+        (Some(left), Some(right)) => (left, right),
+        (left, right) => return left.or(right),
+    };
+    let height = left.1.max(right.1);
+    let mut nodes = concat(&left.0, left.1, &right.0, right.1);
+    if nodes.len() == 1 {
+        nodes.pop().map(|root| (root, height))
+    } else {
+        Some((Arc::new(Node::branch(nodes)), height + 1))
+    }
+}
+
+/// Concatenates the trees `left` and `right` of the given heights into
+/// one or two nodes at the greater height, merging along the seam.
+fn concat<T: Clone>(
+    left: &Arc<Node<T>>,
+    left_height: usize,
+    right: &Arc<Node<T>>,
+    right_height: usize,
+) -> Vec<Arc<Node<T>>> {
+    if left_height == 0 && right_height == 0 {
+        let (a, b) = (left.items(), right.items());
+        if a.len() + b.len() <= WIDTH {
+            return vec![Arc::new(Node::Leaf([a, b].concat()))];
+        }
+        return vec![left.clone(), right.clone()];
+    }
+    let height = left_height.max(right_height);
+    // The children of the result: everything but the seam, and the
+    // concatenation of the seam's two sides one level down.
+    let mut nodes = Vec::with_capacity(2 * WIDTH);
+    if left_height == height {
+        let (last, rest) = left.children().split_last().expect("branches are nonempty");
+        nodes.extend_from_slice(rest);
+        if right_height == height {
+            let (first, _) = right
+                .children()
+                .split_first()
+                .expect("branches are nonempty");
+            nodes.extend(concat(last, height - 1, first, height - 1));
+        } else {
+            nodes.extend(concat(last, height - 1, right, right_height));
+        }
+    } else {
+        let (first, _) = right
+            .children()
+            .split_first()
+            .expect("branches are nonempty");
+        nodes.extend(concat(left, left_height, first, height - 1));
+    }
+    if right_height == height {
+        nodes.extend_from_slice(&right.children()[1..]);
+    }
+    rebalance(&mut nodes, height - 1);
+    nodes
+        .chunks(WIDTH)
+        .map(|chunk| Arc::new(Node::branch(chunk.to_vec())))
+        .collect()
+}
+
+/// Repacks `nodes`, all at `height`, into as few full nodes as possible if
+/// they hold more than `EXTRA` beyond that minimum.
+fn rebalance<T: Clone>(nodes: &mut Vec<Arc<Node<T>>>, height: usize) {
+    let slots: usize = nodes.iter().map(|n| n.slots()).sum();
+    if nodes.len() <= slots.div_ceil(WIDTH) + EXTRA {
+        return;
+    }
+    *nodes = if height == 0 {
+        let items: Vec<T> = nodes.iter().flat_map(|n| n.items()).cloned().collect();
+        items
+            .chunks(WIDTH)
+            .map(|chunk| Arc::new(Node::Leaf(chunk.to_vec())))
+            .collect()
+    } else {
+        let children: Vec<_> = nodes.iter().flat_map(|n| n.children()).cloned().collect();
+        children
+            .chunks(WIDTH)
+            .map(|chunk| Arc::new(Node::branch(chunk.to_vec())))
+            .collect()
+    };
+}
+
+/// The first `n` elements of `node`, for `n` from 1 to its length.
+fn take_front<T: Clone>(node: &Arc<Node<T>>, n: usize) -> Arc<Node<T>> {
+    if n == node.len() {
+        return node.clone();
+    }
+    match &**node {
+        Node::Leaf(items) => Arc::new(Node::Leaf(items[..n].to_vec())),
+        Node::Branch { children, sizes } => {
+            let c = sizes.partition_point(|&s| s < n);
+            let before = if c == 0 { 0 } else { sizes[c - 1] };
+            let mut kept = children[..c].to_vec();
+            kept.push(take_front(&children[c], n - before));
+            Arc::new(Node::branch(kept))
+        }
+    }
+}
+
+/// `node` without its first `n` elements, for `n` below its length.
+fn drop_front<T: Clone>(node: &Arc<Node<T>>, n: usize) -> Arc<Node<T>> {
+    if n == 0 {
+        return node.clone();
+    }
+    match &**node {
+        Node::Leaf(items) => Arc::new(Node::Leaf(items[n..].to_vec())),
+        Node::Branch { children, sizes } => {
+            let c = sizes.partition_point(|&s| s <= n);
+            let before = if c == 0 { 0 } else { sizes[c - 1] };
+            let mut kept = vec![drop_front(&children[c], n - before)];
+            kept.extend_from_slice(&children[c + 1..]);
+            Arc::new(Node::branch(kept))
+        }
+    }
+}
+
+impl<T: Clone> Index<usize> for RrbVector<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        self.get(index)
+            .unwrap_or_else(|| panic!("index {index} out of bounds for length {}", self.len))
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for RrbVector<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Clone + Eq> Eq for RrbVector<T> {}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for RrbVector<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Clone> Extend<T> for RrbVector<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.push_back(value);
+        }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for RrbVector<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut transient = Self::new().transient();
+        transient.extend(iter);
+        transient.persistent()
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a RrbVector<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of an [`RrbVector`].
+pub struct Iter<'a, T> {
+    /// Siblings still to visit at each level above the current leaf.
+    stack: Vec<&'a [Arc<Node<T>>]>,
+    leaf: &'a [T],
+    tail: &'a [T],
+    remaining: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some((first, rest)) = self.leaf.split_first() {
+                self.leaf = rest;
+                self.remaining -= 1;
+                return Some(first);
+            }
+            let Some(siblings) = self.stack.last_mut() else {
+                self.leaf = mem::take(&mut self.tail);
+                if self.leaf.is_empty() {
+                    return None;
+                }
+                continue;
+            };
+            match siblings.split_first() {
+                None => {
+                    self.stack.pop();
+                }
+                Some((node, rest)) => {
+                    *siblings = rest;
+                    match &**node {
+                        Node::Leaf(items) => self.leaf = items,
+                        Node::Branch { children, .. } => self.stack.push(children),
+                    }
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// An [`RrbVector`] being built by a batch of pushes.
+///
+/// Pushes go to a plain buffer, with none of the tree's bookkeeping, and
+/// [`persistent`](Transient::persistent) adds them to the tree a full leaf
+/// at a time. Reads and writes see the buffer as the end of the vector.
+pub struct Transient<T> {
+    vector: RrbVector<T>,
+    pending: Vec<T>,
+}
+
+impl<T: Clone> Transient<T> {
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.vector.len() + self.pending.len()
+    }
+
+    /// Whether the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The element at `index`.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        match index.checked_sub(self.vector.len()) {
+            Some(i) => self.pending.get(i),
+            None => self.vector.get(index),
+        }
+    }
+
+    /// The element at `index` mutably.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        match index.checked_sub(self.vector.len()) {
+            Some(i) => self.pending.get_mut(i),
+            None => self.vector.get_mut(index),
+        }
+    }
+
+    /// Appends `value`.
+    pub fn push_back(&mut self, value: T) {
+        self.pending.push(value);
+    }
+
+    /// Removes and returns the last element.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.pending.pop().or_else(|| self.vector.pop_back())
+    }
+
+    /// Finishes the batch, returning the persistent vector.
+    pub fn persistent(self) -> RrbVector<T> {
+        let Transient {
+            mut vector,
+            pending,
+        } = self;
+        let mut pending = pending.into_iter();
+        // Top up the tail, then add whole leaves.
+        let room = WIDTH - vector.tail.len();
+        vector.tail.extend(pending.by_ref().take(room));
+        vector.len = vector.tail.len() + vector.root.as_ref().map_or(0, |r| r.len());
+        while pending.len() > 0 {
+            let leaf = mem::replace(&mut vector.tail, pending.by_ref().take(WIDTH).collect());
+            vector.len += vector.tail.len();
+            vector.push_leaf(leaf);
+        }
+        vector
+    }
+}
+
+impl<T: Clone> Extend<T> for Transient<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.pending.extend(iter);
+    }
+}
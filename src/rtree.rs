@@ -0,0 +1,965 @@
+//! An R*-tree over axis-aligned boxes in `N` dimensions.
+//!
+//! Unlike the point structures in this crate, an R-tree stores extents: each
+//! entry is an [`Aabb`] with attached data, and each node records the bounding
+//! box of everything beneath it. Insertion follows the R*-tree heuristics of
+//! Beckmann et al.: overlap-minimizing subtree choice near the leaves, forced
+//! reinsertion on the first overflow at each level, and margin-driven splits.
+//! [`RTree::bulk_load`] packs a static dataset with Sort-Tile-Recursive
+//! instead, which is faster and yields tighter nodes.
+//!
+//! The tree can also be kept in Hilbert order, after Kamel and Faloutsos.
+//! Each box is keyed by the position of its center along a Hilbert curve
+//! through a fixed frame, and every node keeps its children sorted by key
+//! and remembers the largest key beneath it. [`RTree::hilbert_load`] packs
+//! a sorted dataset into full nodes, and a tree from
+//! [`RTree::hilbert_ordered`] inserts each box beside its curve neighbors,
+//! splitting overflowing nodes into their siblings two-to-three and merging
+//! underflowing ones into a sibling. Because the curve keeps nearby centers
+//! together, this clusters geographic data well even when it arrives in an
+//! arbitrary order, and insertion is much cheaper than the R* heuristics.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+pub use crate::geom::Aabb;
+use crate::util::Total;
+
+/// Handle to an entry stored in an [`RTree`].
+///
+/// Handles of removed entries may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntryId(u32);
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<const N: usize> {
+    bbox: Aabb<N>,
+    /// Zero for leaves, whose children are entries; otherwise children are
+    /// nodes one level down.
+    level: u32,
+    parent: u32,
+    children: Vec<u32>,
+    /// Largest Hilbert key beneath the node; zero outside Hilbert order.
+    key: u128,
+}
+
+#[derive(Clone, Debug)]
+struct Entry<T, const N: usize> {
+    bbox: Aabb<N>,
+    data: T,
+    leaf: u32,
+    key: u128,
+}
+
+/// An R*-tree mapping boxes in `N` dimensions to data of type `T`.
+#[derive(Clone, Debug)]
+pub struct RTree<T, const N: usize> {
+    nodes: Vec<Node<N>>,
+    free_nodes: Vec<u32>,
+    entries: Vec<Option<Entry<T, N>>>,
+    free_entries: Vec<u32>,
+    root: u32,
+    len: usize,
+    max: usize,
+    min: usize,
+    /// The box quantized for Hilbert keys when the tree is kept in Hilbert
+    /// order.
+    frame: Option<Aabb<N>>,
+}
+
+impl<T, const N: usize> Default for RTree<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> RTree<T, N> {
+    /// Creates an empty tree with nodes of up to 16 children.
+    pub fn new() -> Self {
+        Self::with_max_entries(16)
+    }
+
+    /// Creates an empty tree with nodes of up to `max_entries` children.
+    ///
+    /// Nodes other than the root keep at least 40% of that many children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is less than 4.
+    pub fn with_max_entries(max_entries: usize) -> Self {
+        assert!(
+            max_entries >= 4,
+            "R-tree nodes need room for at least 4 children"
+        );
+        RTree {
+            nodes: vec![Node {
+                bbox: Aabb::empty(),
+                level: 0,
+                parent: NONE,
+                children: Vec::new(),
+                key: 0,
+            }],
+            free_nodes: Vec::new(),
+            entries: Vec::new(),
+            free_entries: Vec::new(),
+            root: 0,
+            len: 0,
+            max: max_entries,
+            min: (max_entries * 2 / 5).max(2),
+            frame: None,
+        }
+    }
+
+    /// Creates an empty tree kept in Hilbert order, with nodes of up to 16
+    /// children. Keys are taken along a Hilbert curve through `frame`;
+    /// boxes centered outside it are keyed as if clamped to its boundary,
+    /// which keeps queries exact but clusters them less well.
+    pub fn hilbert_ordered(frame: Aabb<N>) -> Self {
+        Self::hilbert_ordered_with(16, frame)
+    }
+
+    /// Like [`RTree::hilbert_ordered`], with nodes of up to `max_entries`
+    /// children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is less than 4.
+    pub fn hilbert_ordered_with(max_entries: usize, frame: Aabb<N>) -> Self {
+        let mut tree = Self::with_max_entries(max_entries);
+        tree.frame = Some(frame);
+        tree
+    }
+
+    /// Packs a static set of boxes with Sort-Tile-Recursive loading.
+    pub fn bulk_load(items: Vec<(Aabb<N>, T)>) -> Self {
+        Self::bulk_load_with(16, items)
+    }
+
+    /// Like [`RTree::bulk_load`], with nodes of up to `max_entries` children.
+    pub fn bulk_load_with(max_entries: usize, items: Vec<(Aabb<N>, T)>) -> Self {
+        let mut tree = Self::with_max_entries(max_entries);
+        if items.is_empty() {
+            return tree;
+        }
+        tree.nodes.clear();
+        tree.len = items.len();
+        let mut level_items: Vec<(Aabb<N>, u32)> = Vec::with_capacity(items.len());
+        for (i, (bbox, data)) in items.into_iter().enumerate() {
+            tree.entries.push(Some(Entry {
+                bbox,
+                data,
+                leaf: NONE,
+                key: 0,
+            }));
+            level_items.push((bbox, i as u32));
+        }
+        let mut level = 0;
+        loop {
+            let mut groups = Vec::new();
+            str_groups(&mut level_items, tree.max, 0, &mut groups);
+            let mut parents = Vec::with_capacity(groups.len());
+            for group in groups {
+                let n = tree.alloc_node(level);
+                for c in group {
+                    tree.adopt(n, c);
+                }
+                tree.refresh(n);
+                parents.push((tree.nodes[n as usize].bbox, n));
+            }
+            if parents.len() == 1 {
+                tree.root = parents[0].1;
+                return tree;
+            }
+            level_items = parents;
+            level += 1;
+        }
+    }
+
+    /// Packs a static set of boxes in Hilbert order of their centers,
+    /// returning a tree that keeps that order on later insertions. The
+    /// frame is the bounding box of the input.
+    pub fn hilbert_load(items: Vec<(Aabb<N>, T)>) -> Self {
+        Self::hilbert_load_with(16, items)
+    }
+
+    /// Like [`RTree::hilbert_load`], with nodes of up to `max_entries`
+    /// children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_entries` is less than 4.
+    pub fn hilbert_load_with(max_entries: usize, items: Vec<(Aabb<N>, T)>) -> Self {
+        let frame = items
+            .iter()
+            .fold(Aabb::empty(), |acc, (bbox, _)| acc.union(bbox));
+        let mut tree = Self::hilbert_ordered_with(max_entries, frame);
+        if items.is_empty() {
+            return tree;
+        }
+        tree.nodes.clear();
+        tree.len = items.len();
+        let mut order: Vec<(u128, u32)> = Vec::with_capacity(items.len());
+        for (i, (bbox, data)) in items.into_iter().enumerate() {
+            let key = hilbert_key(&frame, &bbox.center());
+            tree.entries.push(Some(Entry {
+                bbox,
+                data,
+                leaf: NONE,
+                key,
+            }));
+            order.push((key, i as u32));
+        }
+        order.sort_unstable();
+        let mut level_items: Vec<u32> = order.into_iter().map(|(_, i)| i).collect();
+        let mut level = 0;
+        loop {
+            // Spread each level evenly over as few nodes as will hold it.
+            let pages = level_items.len().div_ceil(tree.max);
+            let parents: Vec<u32> = (0..pages).map(|_| tree.alloc_node(level)).collect();
+            tree.spread(&parents, level_items);
+            if parents.len() == 1 {
+                tree.root = parents[0];
+                return tree;
+            }
+            level_items = parents;
+            level += 1;
+        }
+    }
+
+    /// The frame Hilbert keys are taken in, if the tree is kept in Hilbert
+    /// order.
+    pub fn hilbert_frame(&self) -> Option<&Aabb<N>> {
+        self.frame.as_ref()
+    }
+
+    /// Number of stored entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of node levels; an empty or single-leaf tree has height 1.
+    pub fn height(&self) -> usize {
+        self.nodes[self.root as usize].level as usize + 1
+    }
+
+    /// The bounding box of all entries.
+    pub fn bounds(&self) -> Aabb<N> {
+        self.nodes[self.root as usize].bbox
+    }
+
+    /// Removes every entry, invalidating all handles.
+    pub fn clear(&mut self) {
+        let frame = self.frame;
+        *self = Self::with_max_entries(self.max);
+        self.frame = frame;
+    }
+
+    /// Inserts a box, returning its handle.
+    pub fn insert(&mut self, bbox: Aabb<N>, data: T) -> EntryId {
+        let entry = Entry {
+            bbox,
+            data,
+            leaf: NONE,
+            key: self.frame.map_or(0, |f| hilbert_key(&f, &bbox.center())),
+        };
+        let id = match self.free_entries.pop() {
+            Some(id) => {
+                self.entries[id as usize] = Some(entry);
+                id
+            }
+            None => {
+                self.entries.push(Some(entry));
+                (self.entries.len() - 1) as u32
+            }
+        };
+        self.len += 1;
+        self.insert_at(id, 0, &mut 0);
+        EntryId(id)
+    }
+
+    /// Removes an entry, returning its box and data.
+    pub fn remove(&mut self, id: EntryId) -> Option<(Aabb<N>, T)> {
+        let entry = self.entries.get_mut(id.0 as usize)?.take()?;
+        self.free_entries.push(id.0);
+        self.len -= 1;
+        let leaf = entry.leaf;
+        let children = &mut self.nodes[leaf as usize].children;
+        let pos = children
+            .iter()
+            .position(|&c| c == id.0)
+            .expect("entry in its leaf");
+        children.remove(pos);
+        if self.frame.is_some() {
+            self.condense_ordered(leaf);
+        } else {
+            self.condense(leaf);
+        }
+        Some((entry.bbox, entry.data))
+    }
+
+    /// Returns the box and data of an entry.
+    pub fn get(&self, id: EntryId) -> Option<(&Aabb<N>, &T)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((&e.bbox, &e.data))
+    }
+
+    /// Returns the box and mutable data of an entry.
+    pub fn get_mut(&mut self, id: EntryId) -> Option<(&Aabb<N>, &mut T)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        Some((&e.bbox, &mut e.data))
+    }
+
+    /// Iterates over every entry in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (EntryId, &Aabb<N>, &T)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.as_ref().map(|e| (EntryId(i as u32), &e.bbox, &e.data)))
+    }
+
+    /// Iterates over the entries whose boxes intersect `query`.
+    pub fn intersecting(
+        &self,
+        query: Aabb<N>,
+    ) -> impl Iterator<Item = (EntryId, &Aabb<N>, &T)> + '_ {
+        self.query(move |b| b.intersects(&query), move |b| b.intersects(&query))
+    }
+
+    /// Iterates over the entries whose boxes lie entirely inside `query`.
+    pub fn contained_in(
+        &self,
+        query: Aabb<N>,
+    ) -> impl Iterator<Item = (EntryId, &Aabb<N>, &T)> + '_ {
+        self.query(move |b| b.intersects(&query), move |b| query.contains(b))
+    }
+
+    /// Iterates over the entries whose boxes entirely contain `query`.
+    pub fn containing(&self, query: Aabb<N>) -> impl Iterator<Item = (EntryId, &Aabb<N>, &T)> + '_ {
+        self.query(move |b| b.contains(&query), move |b| b.contains(&query))
+    }
+
+    /// Returns up to `k` entries nearest to `point`, closest first, with the
+    /// Euclidean distance from `point` to each box.
+    pub fn nearest(&self, point: &[f64; N], k: usize) -> Vec<(EntryId, f64)> {
+        let mut out = Vec::with_capacity(k);
+        if k == 0 || self.is_empty() {
+            return out;
+        }
+        // Nodes and entries share one frontier; entries carry exact distances
+        // and nodes lower bounds, so entries pop in nearest-first order.
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Reverse((Total(0.0), false, self.root)));
+        while let Some(Reverse((Total(d2), is_entry, i))) = frontier.pop() {
+            if is_entry {
+                out.push((EntryId(i), d2.sqrt()));
+                if out.len() == k {
+                    break;
+                }
+                continue;
+            }
+            let node = &self.nodes[i as usize];
+            for &c in &node.children {
+                let bbox = self.child_bbox(node.level, c);
+                frontier.push(Reverse((
+                    Total(bbox.distance_squared(point)),
+                    node.level == 0,
+                    c,
+                )));
+            }
+        }
+        out
+    }
+
+    /// Returns the entry nearest to `point` and its distance.
+    pub fn nearest_one(&self, point: &[f64; N]) -> Option<(EntryId, f64)> {
+        self.nearest(point, 1).pop()
+    }
+
+    /// Depth-first traversal entering nodes whose boxes pass `enter` and
+    /// yielding entries whose boxes pass `keep`.
+    fn query<'a>(
+        &'a self,
+        enter: impl Fn(&Aabb<N>) -> bool + 'a,
+        keep: impl Fn(&Aabb<N>) -> bool + 'a,
+    ) -> impl Iterator<Item = (EntryId, &'a Aabb<N>, &'a T)> + 'a {
+        let mut stack = if self.is_empty() {
+            Vec::new()
+        } else {
+            vec![self.root]
+        };
+        let mut items: std::slice::Iter<'a, u32> = [].iter();
+        std::iter::from_fn(move || loop {
+            for &i in items.by_ref() {
+                let e = self.entry(i);
+                if keep(&e.bbox) {
+                    return Some((EntryId(i), &e.bbox, &e.data));
+                }
+            }
+            let node = &self.nodes[stack.pop()? as usize];
+            if !enter(&node.bbox) {
+                continue;
+            }
+            if node.level == 0 {
+                items = node.children.iter();
+            } else {
+                stack.extend(&node.children);
+            }
+        })
+    }
+
+    fn entry(&self, i: u32) -> &Entry<T, N> {
+        self.entries[i as usize].as_ref().expect("live entry")
+    }
+
+    fn child_bbox(&self, level: u32, child: u32) -> Aabb<N> {
+        if level == 0 {
+            self.entry(child).bbox
+        } else {
+            self.nodes[child as usize].bbox
+        }
+    }
+
+    fn child_key(&self, level: u32, child: u32) -> u128 {
+        if level == 0 {
+            self.entry(child).key
+        } else {
+            self.nodes[child as usize].key
+        }
+    }
+
+    fn alloc_node(&mut self, level: u32) -> u32 {
+        let node = Node {
+            bbox: Aabb::empty(),
+            level,
+            parent: NONE,
+            children: Vec::new(),
+            key: 0,
+        };
+        match self.free_nodes.pop() {
+            Some(n) => {
+                self.nodes[n as usize] = node;
+                n
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+    /// Appends `child` to node `n` and points it back at `n`.
+    fn adopt(&mut self, n: u32, child: u32) {
+        let len = self.nodes[n as usize].children.len();
+        self.adopt_at(n, len, child);
+    }
+
+    /// Inserts `child` at position `index` of node `n` and points it back
+    /// at `n`.
+    fn adopt_at(&mut self, n: u32, index: usize, child: u32) {
+        let level = self.nodes[n as usize].level;
+        self.nodes[n as usize].children.insert(index, child);
+        if level == 0 {
+            self.entries[child as usize]
+                .as_mut()
+                .expect("live entry")
+                .leaf = n;
+        } else {
+            self.nodes[child as usize].parent = n;
+        }
+    }
+
+    /// Recomputes the box and key of node `n` from its children.
+    fn refresh(&mut self, n: u32) {
+        let node = &self.nodes[n as usize];
+        let bbox = node.children.iter().fold(Aabb::empty(), |acc, &c| {
+            acc.union(&self.child_bbox(node.level, c))
+        });
+        let key = node
+            .children
+            .iter()
+            .map(|&c| self.child_key(node.level, c))
+            .max()
+            .unwrap_or(0);
+        let node = &mut self.nodes[n as usize];
+        node.bbox = bbox;
+        node.key = key;
+    }
+
+    fn refresh_upward(&mut self, mut n: u32) {
+        while n != NONE {
+            self.refresh(n);
+            n = self.nodes[n as usize].parent;
+        }
+    }
+
+    /// Inserts an entry (`level == 0`) or a subtree root into a node at
+    /// `level`. `reinserted` has bit `l` set once level `l` has used its
+    /// forced reinsertion during the current top-level operation.
+    fn insert_at(&mut self, child: u32, level: u32, reinserted: &mut u64) {
+        if self.frame.is_some() {
+            self.insert_ordered(child, level);
+            return;
+        }
+        let bbox = if level == 0 {
+            self.entry(child).bbox
+        } else {
+            self.nodes[child as usize].bbox
+        };
+        let target = self.choose_subtree(&bbox, level);
+        self.adopt(target, child);
+        let mut n = target;
+        while n != NONE {
+            let node = &mut self.nodes[n as usize];
+            node.bbox = node.bbox.union(&bbox);
+            n = node.parent;
+        }
+        self.handle_overflow(target, reinserted);
+    }
+
+    fn choose_subtree(&self, bbox: &Aabb<N>, level: u32) -> u32 {
+        let mut n = self.root;
+        loop {
+            let node = &self.nodes[n as usize];
+            if node.level == level {
+                return n;
+            }
+            let boxes: Vec<Aabb<N>> = node
+                .children
+                .iter()
+                .map(|&c| self.nodes[c as usize].bbox)
+                .collect();
+            let enlargement = |b: &Aabb<N>| b.union(bbox).area() - b.area();
+            let best = if node.level == 1 {
+                // Children are leaves: minimize the overlap the insertion adds.
+                (0..boxes.len()).min_by(|&i, &j| {
+                    let key = |i: usize| {
+                        let grown = boxes[i].union(bbox);
+                        let overlap: f64 = boxes
+                            .iter()
+                            .enumerate()
+                            .filter(|&(o, _)| o != i)
+                            .map(|(_, b)| {
+                                grown.intersection(b).area() - boxes[i].intersection(b).area()
+                            })
+                            .sum();
+                        (overlap, enlargement(&boxes[i]), boxes[i].area())
+                    };
+                    cmp3(key(i), key(j))
+                })
+            } else {
+                (0..boxes.len()).min_by(|&i, &j| {
+                    let key = |i: usize| (enlargement(&boxes[i]), boxes[i].area(), 0.0);
+                    cmp3(key(i), key(j))
+                })
+            };
+            n = node.children[best.expect("internal nodes have children")];
+        }
+    }
+
+    fn handle_overflow(&mut self, n: u32, reinserted: &mut u64) {
+        if self.nodes[n as usize].children.len() <= self.max {
+            return;
+        }
+        let level = self.nodes[n as usize].level;
+        if n != self.root && *reinserted & (1 << level) == 0 {
+            *reinserted |= 1 << level;
+            self.reinsert(n, reinserted);
+            return;
+        }
+        let sibling = self.split(n);
+        if n == self.root {
+            let root = self.alloc_node(level + 1);
+            self.adopt(root, n);
+            self.adopt(root, sibling);
+            self.refresh(root);
+            self.root = root;
+        } else {
+            let parent = self.nodes[n as usize].parent;
+            self.adopt(parent, sibling);
+            self.handle_overflow(parent, reinserted);
+        }
+    }
+
+    /// Evicts the 30% of children farthest from the node's center and
+    /// inserts them again from the top.
+    fn reinsert(&mut self, n: u32, reinserted: &mut u64) {
+        let node = &self.nodes[n as usize];
+        let level = node.level;
+        let center = node.bbox.center();
+        let mut children = std::mem::take(&mut self.nodes[n as usize].children);
+        children.sort_by_cached_key(|&c| {
+            let cc = self.child_bbox(level, c).center();
+            Reverse(Total((0..N).map(|a| (cc[a] - center[a]).powi(2)).sum()))
+        });
+        let count = (self.max * 3 / 10).max(1);
+        let evicted: Vec<u32> = children.drain(..count).collect();
+        self.nodes[n as usize].children = children;
+        self.refresh_upward(n);
+        for &c in evicted.iter().rev() {
+            self.insert_at(c, level, reinserted);
+        }
+    }
+
+    /// Splits an overflowing node in two along the R* split axis, returning
+    /// the new sibling.
+    fn split(&mut self, n: u32) -> u32 {
+        let level = self.nodes[n as usize].level;
+        let mut children: Vec<(Aabb<N>, u32)> =
+            std::mem::take(&mut self.nodes[n as usize].children)
+                .into_iter()
+                .map(|c| (self.child_bbox(level, c), c))
+                .collect();
+        let total = children.len();
+        let distributions = self.min..=total - self.min;
+
+        let sort = |children: &mut [(Aabb<N>, u32)], axis: usize, by_max: bool| {
+            children.sort_by(|a, b| {
+                let (x, y) = if by_max {
+                    (a.0.max[axis], b.0.max[axis])
+                } else {
+                    (a.0.min[axis], b.0.min[axis])
+                };
+                x.total_cmp(&y)
+            })
+        };
+        let prefix_boxes = |children: &[(Aabb<N>, u32)]| {
+            let mut fwd = Vec::with_capacity(total);
+            let mut acc = Aabb::empty();
+            for c in children {
+                acc = acc.union(&c.0);
+                fwd.push(acc);
+            }
+            let mut bwd = vec![Aabb::empty(); total];
+            let mut acc = Aabb::empty();
+            for i in (0..total).rev() {
+                acc = acc.union(&children[i].0);
+                bwd[i] = acc;
+            }
+            (fwd, bwd)
+        };
+
+        // Choose the axis whose distributions have the least total margin.
+        let mut best_axis = (f64::INFINITY, 0);
+        for axis in 0..N {
+            let mut margin = 0.0;
+            for by_max in [false, true] {
+                sort(&mut children, axis, by_max);
+                let (fwd, bwd) = prefix_boxes(&children);
+                for k in distributions.clone() {
+                    margin += fwd[k - 1].margin() + bwd[k].margin();
+                }
+            }
+            if margin < best_axis.0 {
+                best_axis = (margin, axis);
+            }
+        }
+
+        // Along it, choose the distribution with least overlap, then area.
+        let axis = best_axis.1;
+        let mut best = ((f64::INFINITY, f64::INFINITY), false, self.min);
+        for by_max in [false, true] {
+            sort(&mut children, axis, by_max);
+            let (fwd, bwd) = prefix_boxes(&children);
+            for k in distributions.clone() {
+                let (a, b) = (fwd[k - 1], bwd[k]);
+                let key = (a.intersection(&b).area(), a.area() + b.area());
+                if key.0 < best.0 .0 || (key.0 == best.0 .0 && key.1 < best.0 .1) {
+                    best = (key, by_max, k);
+                }
+            }
+        }
+        sort(&mut children, axis, best.1);
+
+        let sibling = self.alloc_node(level);
+        for (i, &(_, c)) in children.iter().enumerate() {
+            self.adopt(if i < best.2 { n } else { sibling }, c);
+        }
+        self.refresh(n);
+        self.refresh(sibling);
+        sibling
+    }
+
+    /// Restores the minimum fill after a removal from leaf `n`, reinserting
+    /// the contents of any node that drops below it.
+    fn condense(&mut self, leaf: u32) {
+        let mut orphans = Vec::new();
+        let mut n = leaf;
+        while n != self.root {
+            let parent = self.nodes[n as usize].parent;
+            if self.nodes[n as usize].children.len() < self.min {
+                let siblings = &mut self.nodes[parent as usize].children;
+                let pos = siblings
+                    .iter()
+                    .position(|&c| c == n)
+                    .expect("node in its parent");
+                siblings.remove(pos);
+                let node = &mut self.nodes[n as usize];
+                let level = node.level;
+                orphans.extend(
+                    std::mem::take(&mut node.children)
+                        .into_iter()
+                        .map(|c| (c, level)),
+                );
+                self.free_nodes.push(n);
+            } else {
+                self.refresh(n);
+            }
+            n = parent;
+        }
+        self.refresh(self.root);
+        for (c, level) in orphans {
+            self.insert_at(c, level, &mut 0);
+        }
+        self.shrink_root();
+    }
+
+    /// Replaces a root with a single child by that child, repeatedly.
+    fn shrink_root(&mut self) {
+        loop {
+            let root = &self.nodes[self.root as usize];
+            if root.level == 0 || root.children.len() != 1 {
+                break;
+            }
+            let child = root.children[0];
+            self.free_nodes.push(self.root);
+            self.nodes[child as usize].parent = NONE;
+            self.root = child;
+        }
+    }
+
+    /// Inserts an entry or subtree in Hilbert order: down the path of the
+    /// first child whose largest key is at least the new one, and into its
+    /// sorted place at `level`.
+    fn insert_ordered(&mut self, child: u32, level: u32) {
+        let key = self.child_key(level, child);
+        let bbox = self.child_bbox(level, child);
+        let mut n = self.root;
+        while self.nodes[n as usize].level != level {
+            let children = &self.nodes[n as usize].children;
+            let i = children
+                .iter()
+                .position(|&c| self.nodes[c as usize].key >= key)
+                .unwrap_or(children.len() - 1);
+            n = children[i];
+        }
+        let index = self.nodes[n as usize]
+            .children
+            .partition_point(|&c| self.child_key(level, c) <= key);
+        self.adopt_at(n, index, child);
+        let mut up = n;
+        while up != NONE {
+            let node = &mut self.nodes[up as usize];
+            node.bbox = node.bbox.union(&bbox);
+            node.key = node.key.max(key);
+            up = node.parent;
+        }
+        self.overflow_ordered(n);
+    }
+
+    /// Resolves an overflow of node `n` in a Hilbert-ordered tree by
+    /// sharing its children with the adjacent sibling, or, if both are
+    /// full, by spreading them over a new third node.
+    fn overflow_ordered(&mut self, n: u32) {
+        if self.nodes[n as usize].children.len() <= self.max {
+            return;
+        }
+        let level = self.nodes[n as usize].level;
+        if n == self.root {
+            let sibling = self.alloc_node(level);
+            let children = std::mem::take(&mut self.nodes[n as usize].children);
+            self.spread(&[n, sibling], children);
+            let root = self.alloc_node(level + 1);
+            self.adopt(root, n);
+            self.adopt(root, sibling);
+            self.refresh(root);
+            self.root = root;
+            return;
+        }
+        let parent = self.nodes[n as usize].parent;
+        let mut group = self.cooperating(n);
+        let mut children = Vec::new();
+        for &g in &group {
+            children.append(&mut self.nodes[g as usize].children);
+        }
+        if children.len() > group.len() * self.max {
+            let last = *group.last().expect("group holds n");
+            let index = self.position(parent, last) + 1;
+            let fresh = self.alloc_node(level);
+            self.adopt_at(parent, index, fresh);
+            group.push(fresh);
+        }
+        self.spread(&group, children);
+        self.overflow_ordered(parent);
+    }
+
+    /// Restores the minimum fill after a removal from leaf `n` in a
+    /// Hilbert-ordered tree, borrowing from an adjacent sibling or merging
+    /// into it.
+    fn condense_ordered(&mut self, leaf: u32) {
+        let mut n = leaf;
+        while n != self.root {
+            let parent = self.nodes[n as usize].parent;
+            let group = self.cooperating(n);
+            if self.nodes[n as usize].children.len() < self.min && group.len() > 1 {
+                let mut children = Vec::new();
+                for &g in &group {
+                    children.append(&mut self.nodes[g as usize].children);
+                }
+                if children.len() >= 2 * self.min {
+                    self.spread(&group, children);
+                } else {
+                    let (keep, drop) = (group[0], group[1]);
+                    let index = self.position(parent, drop);
+                    self.nodes[parent as usize].children.remove(index);
+                    self.free_nodes.push(drop);
+                    self.spread(&[keep], children);
+                }
+            } else {
+                self.refresh(n);
+            }
+            n = parent;
+        }
+        self.refresh(self.root);
+        self.shrink_root();
+    }
+
+    /// Node `n` and, if it has one, its next sibling (or its previous one
+    /// if it is the last child), in order.
+    fn cooperating(&self, n: u32) -> Vec<u32> {
+        let parent = self.nodes[n as usize].parent;
+        let siblings = &self.nodes[parent as usize].children;
+        let i = self.position(parent, n);
+        if i + 1 < siblings.len() {
+            vec![n, siblings[i + 1]]
+        } else if i > 0 {
+            vec![siblings[i - 1], n]
+        } else {
+            vec![n]
+        }
+    }
+
+    fn position(&self, parent: u32, child: u32) -> usize {
+        self.nodes[parent as usize]
+            .children
+            .iter()
+            .position(|&c| c == child)
+            .expect("node in its parent")
+    }
+
+    /// Distributes `children`, in order, as evenly as possible over the
+    /// nodes of `group`, which must be empty and share a level.
+    fn spread(&mut self, group: &[u32], children: Vec<u32>) {
+        let total = children.len();
+        for (i, c) in children.into_iter().enumerate() {
+            self.adopt(group[i * group.len() / total], c);
+        }
+        for &g in group {
+            self.refresh(g);
+        }
+    }
+}
+
+impl<T, const N: usize> FromIterator<(Aabb<N>, T)> for RTree<T, N> {
+    fn from_iter<I: IntoIterator<Item = (Aabb<N>, T)>>(iter: I) -> Self {
+        RTree::bulk_load(iter.into_iter().collect())
+    }
+}
+
+fn cmp3(a: (f64, f64, f64), b: (f64, f64, f64)) -> std::cmp::Ordering {
+    a.0.total_cmp(&b.0)
+        .then(a.1.total_cmp(&b.1))
+        .then(a.2.total_cmp(&b.2))
+}
+
+/// The Hilbert key of `point` in `frame`, quantizing each axis to as many
+/// bits as fit `N` axes in 128.
+fn hilbert_key<const N: usize>(frame: &Aabb<N>, point: &[f64; N]) -> u128 {
+    let bits = (128 / N.max(1)).min(32) as u32;
+    if bits == 0 {
+        return 0;
+    }
+    let scale = ((1u64 << bits) - 1) as f64;
+    let coords: [u32; N] = std::array::from_fn(|a| {
+        let t = (point[a] - frame.min[a]) / (frame.max[a] - frame.min[a]);
+        // NaN, from a degenerate or empty frame, clamps to zero.
+        (t.clamp(0.0, 1.0) * scale).round() as u32
+    });
+    hilbert_index(coords, bits)
+}
+
+/// The distance along an `N`-dimensional Hilbert curve of the cell with the
+/// given `bits`-bit coordinates, by Skilling's transposition.
+fn hilbert_index<const N: usize>(mut x: [u32; N], bits: u32) -> u128 {
+    let top = 1u32 << (bits - 1);
+    let mut q = top;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..N {
+            if x[i] & q != 0 {
+                x[0] ^= p;
+            } else {
+                let t = (x[0] ^ x[i]) & p;
+                x[0] ^= t;
+                x[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+    for i in 1..N {
+        x[i] ^= x[i - 1];
+    }
+    let mut t = 0;
+    let mut q = top;
+    while q > 1 {
+        if x[N - 1] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    let mut key = 0u128;
+    for bit in (0..bits).rev() {
+        for c in &x {
+            key = (key << 1) | (((c ^ t) >> bit) & 1) as u128;
+        }
+    }
+    key
+}
+
+/// Partitions boxes into Sort-Tile-Recursive groups of at most `max`,
+/// slicing along `axis` and recursing through the remaining axes.
+fn str_groups<const N: usize>(
+    items: &mut [(Aabb<N>, u32)],
+    max: usize,
+    axis: usize,
+    out: &mut Vec<Vec<u32>>,
+) {
+    let by_center = |a: &(Aabb<N>, u32), b: &(Aabb<N>, u32)| {
+        (a.0.min[axis] + a.0.max[axis]).total_cmp(&(b.0.min[axis] + b.0.max[axis]))
+    };
+    if items.len() <= max {
+        out.push(items.iter().map(|&(_, c)| c).collect());
+        return;
+    }
+    items.sort_by(by_center);
+    if axis + 1 == N {
+        out.extend(
+            items
+                .chunks(max)
+                .map(|g| g.iter().map(|&(_, c)| c).collect()),
+        );
+        return;
+    }
+    let pages = items.len().div_ceil(max);
+    let slabs = (pages as f64).powf(1.0 / (N - axis) as f64).ceil() as usize;
+    let per_slab = pages.div_ceil(slabs) * max;
+    for slab in items.chunks_mut(per_slab) {
+        str_groups(slab, max, axis + 1, out);
+    }
+}
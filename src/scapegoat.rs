@@ -0,0 +1,412 @@
+//! An ordered map stored as a scapegoat tree, balanced by rebuilding.
+//!
+//! A scapegoat tree (Galperin and Rivest, 1993) is a plain binary search
+//! tree that keeps no balance data in its nodes, no colors, heights or
+//! priorities, and never rotates. It keeps only the number of entries and
+//! the most entries held since the last full rebuild. An insertion that
+//! lands deeper than `log_{1/α} n` has unbalanced some ancestor: walking
+//! back up, the first node one of whose children holds more than `α` of
+//! its entries is the scapegoat, and its subtree is rebuilt into a
+//! perfectly balanced one. When removals bring the map below `α` of its
+//! high-water mark, the whole tree is rebuilt.
+//!
+//! Height stays within `log_{1/α} n + 1`, so lookups cost `O(log n)` in
+//! the worst case, and updates cost `O(log n)` amortized, the rebuilt
+//! subtrees paying for themselves with the updates that unbalanced them.
+//! The balance parameter `α`, between 1/2 and 1, trades the two off:
+//! lower values keep the tree shallower and rebuild more often, higher
+//! values rebuild rarely and suit write-heavy loads. Lookups never modify
+//! the tree, unlike the [`SplayTree`]'s, and the code is much smaller than
+//! an [`AvlTree`]'s.
+//!
+//! Rebuilding relinks the existing boxed nodes in order, so it allocates
+//! only the list of nodes being rebuilt.
+//!
+//! [`SplayTree`]: crate::splay::SplayTree
+//! [`AvlTree`]: crate::avl::AvlTree
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+/// The balance parameter used by [`ScapegoatTree::new`].
+pub const DEFAULT_ALPHA: f64 = 2.0 / 3.0;
+
+/// What an insertion below a node did.
+enum Inserted<V> {
+    /// The key was present; its old value.
+    Replaced(V),
+    /// A new node went in, and the tree is balanced enough.
+    Done,
+    /// A new node went in too deep, and no scapegoat has been found yet
+    /// below this subtree, which holds this many entries.
+    Deep(usize),
+}
+
+/// An ordered map from keys of type `K` to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct ScapegoatTree<K, V> {
+    root: Link<K, V>,
+    alpha: f64,
+    len: usize,
+    /// The most entries held since the whole tree was last rebuilt.
+    max_len: usize,
+}
+
+impl<K: Ord, V> Default for ScapegoatTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> ScapegoatTree<K, V> {
+    /// Creates an empty map with the balance parameter [`DEFAULT_ALPHA`].
+    pub fn new() -> Self {
+        Self::with_alpha(DEFAULT_ALPHA)
+    }
+
+    /// Creates an empty map with balance parameter `alpha`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `alpha` is at least 1/2 and below 1.
+    pub fn with_alpha(alpha: f64) -> Self {
+        assert!((0.5..1.0).contains(&alpha), "alpha must be in [0.5, 1)");
+        ScapegoatTree {
+            root: None,
+            alpha,
+            len: 0,
+            max_len: 0,
+        }
+    }
+
+    /// The balance parameter.
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of nodes on the longest root-to-leaf path, in `O(n)`.
+    pub fn height(&self) -> usize {
+        fn height<K, V>(link: &Link<K, V>) -> usize {
+            link.as_ref()
+                .map_or(0, |n| 1 + height(&n.left).max(height(&n.right)))
+        }
+        height(&self.root)
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+        self.max_len = 0;
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Some(&n.value),
+            };
+        }
+        None
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &mut self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &mut n.left,
+                Ordering::Greater => &mut n.right,
+                Ordering::Equal => return Some(&mut n.value),
+            };
+        }
+        None
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_deref()?;
+        while let Some(l) = n.left.as_deref() {
+            n = l;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let mut n = self.root.as_deref()?;
+        while let Some(r) = n.right.as_deref() {
+            n = r;
+        }
+        Some((&n.key, &n.value))
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        // The depth bound for the map as it will be after the insertion.
+        let bound = self.depth_bound(self.len + 1);
+        match insert(&mut self.root, key, value, 0, bound, self.alpha) {
+            Inserted::Replaced(old) => Some(old),
+            Inserted::Done | Inserted::Deep(_) => {
+                self.len += 1;
+                self.max_len = self.max_len.max(self.len);
+                None
+            }
+        }
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let entry = remove(&mut self.root, key)?;
+        self.len -= 1;
+        if (self.len as f64) < self.alpha * self.max_len as f64 {
+            self.rebalance();
+        }
+        Some(entry)
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Rebuilds the whole tree into a perfectly balanced one, in `O(n)`.
+    pub fn rebalance(&mut self) {
+        rebuild(&mut self.root, self.len);
+        self.max_len = self.len;
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order.
+    pub fn range<'a, Q, R>(&'a self, range: R) -> impl Iterator<Item = (&'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<Q> + 'a,
+    {
+        // The stack holds the nodes at or above the start bound whose left
+        // subtrees have been dealt with.
+        let mut stack: Vec<&Node<K, V>> = Vec::new();
+        let mut link = self.root.as_deref();
+        while let Some(n) = link {
+            let above = match range.start_bound() {
+                Bound::Included(b) => n.key.borrow() >= b,
+                Bound::Excluded(b) => n.key.borrow() > b,
+                Bound::Unbounded => true,
+            };
+            if above {
+                stack.push(n);
+                link = n.left.as_deref();
+            } else {
+                link = n.right.as_deref();
+            }
+        }
+        std::iter::from_fn(move || {
+            let n = stack.pop()?;
+            let inside = match range.end_bound() {
+                Bound::Included(b) => n.key.borrow() <= b,
+                Bound::Excluded(b) => n.key.borrow() < b,
+                Bound::Unbounded => true,
+            };
+            if !inside {
+                stack.clear();
+                return None;
+            }
+            let mut link = n.right.as_deref();
+            while let Some(c) = link {
+                stack.push(c);
+                link = c.left.as_deref();
+            }
+            Some((&n.key, &n.value))
+        })
+    }
+
+    /// The deepest a node may sit, counting the root as depth zero, in a
+    /// tree of `len` entries: `log_{1/α} len`.
+    fn depth_bound(&self, len: usize) -> usize {
+        ((len as f64).ln() / (1.0 / self.alpha).ln()).floor() as usize
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for ScapegoatTree<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for ScapegoatTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = ScapegoatTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+fn count<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref()
+        .map_or(0, |n| 1 + count(&n.left) + count(&n.right))
+}
+
+/// Inserts below `link`, at depth `depth`, rebuilding at the scapegoat if
+/// the new node lands deeper than `bound`.
+fn insert<K: Ord, V>(
+    link: &mut Link<K, V>,
+    key: K,
+    value: V,
+    depth: usize,
+    bound: usize,
+    alpha: f64,
+) -> Inserted<V> {
+    let Some(n) = link else {
+        *link = Some(Box::new(Node {
+            key,
+            value,
+            left: None,
+            right: None,
+        }));
+        return if depth > bound {
+            Inserted::Deep(1)
+        } else {
+            Inserted::Done
+        };
+    };
+    let (child, sibling) = match key.cmp(&n.key) {
+        Ordering::Equal => return Inserted::Replaced(std::mem::replace(&mut n.value, value)),
+        Ordering::Less => (&mut n.left, &n.right),
+        Ordering::Greater => (&mut n.right, &n.left),
+    };
+    match insert(child, key, value, depth + 1, bound, alpha) {
+        Inserted::Deep(below) => {
+            // The sibling's size is counted only on the way back up from a
+            // deep insertion, which is what keeps its cost amortized.
+            let size = below + 1 + count(sibling);
+            if below as f64 > alpha * size as f64 {
+                rebuild(link, size);
+                Inserted::Done
+            } else {
+                Inserted::Deep(size)
+            }
+        }
+        done => done,
+    }
+}
+
+/// Removes `key` from below `link`.
+fn remove<K, V, Q>(link: &mut Link<K, V>, key: &Q) -> Option<(K, V)>
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let n = link.as_mut()?;
+    match key.cmp(n.key.borrow()) {
+        Ordering::Less => return remove(&mut n.left, key),
+        Ordering::Greater => return remove(&mut n.right, key),
+        Ordering::Equal => {}
+    }
+    let mut n = link.take().expect("matched node");
+    *link = match (n.left.take(), n.right.take()) {
+        (None, child) | (child, None) => child,
+        (left, mut right) => {
+            // Put the least key of the right subtree in the node's place.
+            let mut min = take_min(&mut right);
+            min.left = left;
+            min.right = right;
+            Some(min)
+        }
+    };
+    Some((n.key, n.value))
+}
+
+/// Unlinks the least node below a nonempty `link`.
+fn take_min<K, V>(link: &mut Link<K, V>) -> Box<Node<K, V>> {
+    if link.as_ref().expect("nonempty subtree").left.is_some() {
+        return take_min(&mut link.as_mut().expect("nonempty subtree").left);
+    }
+    let mut n = link.take().expect("nonempty subtree");
+    *link = n.right.take();
+    n
+}
+
+/// Relinks the `size` nodes below `link` into a perfectly balanced tree.
+fn rebuild<K, V>(link: &mut Link<K, V>, size: usize) {
+    let mut nodes = Vec::with_capacity(size);
+    flatten(link.take(), &mut nodes);
+    let mut nodes = nodes.into_iter();
+    *link = build(&mut nodes, size);
+}
+
+/// Pushes the nodes below `link` onto `out` in key order, unlinked.
+fn flatten<K, V>(mut link: Link<K, V>, out: &mut Vec<Box<Node<K, V>>>) {
+    while let Some(mut n) = link {
+        flatten(n.left.take(), out);
+        link = n.right.take();
+        out.push(n);
+    }
+}
+
+/// Builds a balanced tree from the next `size` nodes of `nodes`.
+fn build<K, V>(nodes: &mut impl Iterator<Item = Box<Node<K, V>>>, size: usize) -> Link<K, V> {
+    if size == 0 {
+        return None;
+    }
+    let left = build(nodes, size / 2);
+    let mut n = nodes.next().expect("enough nodes");
+    n.left = left;
+    n.right = build(nodes, size - size / 2 - 1);
+    Some(n)
+}
@@ -0,0 +1,328 @@
+//! A segment tree with lazy propagation.
+//!
+//! The tree stores the [`Monoid`] combination of every aligned range of a
+//! sequence, answering any range query by combining `O(log n)` of them. Range
+//! updates are described by an [`Action`]: an update covering a whole node
+//! is applied to its summary and parked there as a pending tag, only pushed
+//! down to the children when a later update needs to look inside. Queries
+//! never push; they compose the tags they pass on the way down, so reads
+//! take `&self`.
+//!
+//! ```text
+//! SegmentTree<Sum<i64>, Increment<i64>>  range add, range sum
+//! SegmentTree<Min<i64>, Assign<i64>>     range assign, range min
+//! SegmentTree<Max<f64>>                  point set, range max
+//! ```
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+//! [`Action`]: crate::algebra::Action
+
+use std::ops::{Bound, RangeBounds};
+
+use crate::algebra::{Action, Monoid};
+
+/// A segment tree over a sequence of `M::Value`, with range updates of type
+/// `U`.
+#[derive(Clone, Debug)]
+pub struct SegmentTree<M: Monoid, U = ()> {
+    monoid: M,
+    len: usize,
+    /// Node summaries in heap order: node `i` has children `2i` and `2i + 1`.
+    sums: Vec<M::Value>,
+    lazy: Vec<Option<U>>,
+}
+
+impl<M: Monoid, U: Action<M>> SegmentTree<M, U> {
+    /// Builds a tree over `values` in `O(n)`.
+    pub fn new(monoid: M, values: Vec<M::Value>) -> Self {
+        let len = values.len();
+        let size = 4 * len.max(1);
+        let identity = monoid.identity();
+        let mut tree = SegmentTree {
+            monoid,
+            len,
+            sums: vec![identity; size],
+            lazy: vec![None; size],
+        };
+        if len > 0 {
+            tree.build(1, 0, len, &values);
+        }
+        tree
+    }
+
+    /// Builds a tree of `len` copies of the identity.
+    pub fn with_len(monoid: M, len: usize) -> Self {
+        let values = vec![monoid.identity(); len];
+        Self::new(monoid, values)
+    }
+
+    fn build(&mut self, node: usize, lo: usize, hi: usize, values: &[M::Value]) {
+        if hi - lo == 1 {
+            self.sums[node] = values[lo].clone();
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        self.build(2 * node, lo, mid, values);
+        self.build(2 * node + 1, mid, hi, values);
+        self.pull(node);
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The monoid the tree combines with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// The combination of the elements in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> M::Value {
+        let (lo, hi) = self.bounds(range);
+        if lo == hi {
+            return self.monoid.identity();
+        }
+        self.query_in(1, 0, self.len, lo, hi, None)
+    }
+
+    /// The combination of every element.
+    pub fn total(&self) -> M::Value {
+        if self.len == 0 {
+            self.monoid.identity()
+        } else {
+            self.sums[1].clone()
+        }
+    }
+
+    /// The element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> M::Value {
+        self.query(index..=index)
+    }
+
+    /// Replaces the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: M::Value) {
+        assert!(
+            index < self.len,
+            "index {index} out of bounds for length {}",
+            self.len
+        );
+        self.set_in(1, 0, self.len, index, value);
+    }
+
+    /// Applies `update` to every element in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn update(&mut self, range: impl RangeBounds<usize>, update: U) {
+        let (lo, hi) = self.bounds(range);
+        if lo < hi {
+            self.update_in(1, 0, self.len, lo, hi, &update);
+        }
+    }
+
+    /// Finds the largest `end` such that `pred` holds for the combination
+    /// of `start..end`, assuming `pred` is monotone: true for the empty
+    /// range and, once false, false for every longer range.
+    ///
+    /// With a sum over non-negative values this finds where a running total
+    /// first exceeds a threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start > len`.
+    pub fn max_right(&self, start: usize, pred: impl Fn(&M::Value) -> bool) -> usize {
+        assert!(
+            start <= self.len,
+            "start {start} out of bounds for length {}",
+            self.len
+        );
+        if start == self.len {
+            return start;
+        }
+        let mut acc = self.monoid.identity();
+        self.max_right_in(1, 0, self.len, start, &pred, &mut acc, None)
+            .unwrap_or(self.len)
+    }
+
+    /// Collects the current elements.
+    pub fn to_vec(&self) -> Vec<M::Value> {
+        (0..self.len).map(|i| self.get(i)).collect()
+    }
+
+    fn bounds(&self, range: impl RangeBounds<usize>) -> (usize, usize) {
+        let lo = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let hi = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(
+            lo <= hi && hi <= self.len,
+            "range {lo}..{hi} out of bounds for length {}",
+            self.len
+        );
+        (lo, hi)
+    }
+
+    fn pull(&mut self, node: usize) {
+        self.sums[node] = self
+            .monoid
+            .combine(&self.sums[2 * node], &self.sums[2 * node + 1]);
+    }
+
+    /// Applies `update` to a whole node, deferring it for the children.
+    fn apply(&mut self, node: usize, len: usize, update: &U) {
+        self.sums[node] = update.apply(&self.monoid, &self.sums[node], len);
+        if len > 1 {
+            self.lazy[node] = Some(match &self.lazy[node] {
+                Some(pending) => update.compose(pending),
+                None => update.clone(),
+            });
+        }
+    }
+
+    fn push(&mut self, node: usize, lo: usize, hi: usize) {
+        if let Some(pending) = self.lazy[node].take() {
+            let mid = (lo + hi) / 2;
+            self.apply(2 * node, mid - lo, &pending);
+            self.apply(2 * node + 1, hi - mid, &pending);
+        }
+    }
+
+    /// The combination of `qlo..qhi` within `node`, with `outer` the
+    /// composition of the tags pending above it.
+    fn query_in(
+        &self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        qlo: usize,
+        qhi: usize,
+        outer: Option<&U>,
+    ) -> M::Value {
+        if qlo <= lo && hi <= qhi {
+            return self.effective(node, hi - lo, outer);
+        }
+        let inner = self.inner(node, outer);
+        let mid = (lo + hi) / 2;
+        if qhi <= mid {
+            return self.query_in(2 * node, lo, mid, qlo, qhi, inner.as_ref());
+        }
+        if qlo >= mid {
+            return self.query_in(2 * node + 1, mid, hi, qlo, qhi, inner.as_ref());
+        }
+        let left = self.query_in(2 * node, lo, mid, qlo, qhi, inner.as_ref());
+        let right = self.query_in(2 * node + 1, mid, hi, qlo, qhi, inner.as_ref());
+        self.monoid.combine(&left, &right)
+    }
+
+    /// The summary of `node` once `outer` is applied.
+    fn effective(&self, node: usize, len: usize, outer: Option<&U>) -> M::Value {
+        match outer {
+            Some(u) => u.apply(&self.monoid, &self.sums[node], len),
+            None => self.sums[node].clone(),
+        }
+    }
+
+    /// The tags pending over the children of `node`: its own, then `outer`.
+    fn inner(&self, node: usize, outer: Option<&U>) -> Option<U> {
+        match (outer, &self.lazy[node]) {
+            (Some(o), Some(own)) => Some(o.compose(own)),
+            (Some(o), None) => Some(o.clone()),
+            (None, own) => own.clone(),
+        }
+    }
+
+    fn set_in(&mut self, node: usize, lo: usize, hi: usize, index: usize, value: M::Value) {
+        if hi - lo == 1 {
+            self.sums[node] = value;
+            return;
+        }
+        self.push(node, lo, hi);
+        let mid = (lo + hi) / 2;
+        if index < mid {
+            self.set_in(2 * node, lo, mid, index, value);
+        } else {
+            self.set_in(2 * node + 1, mid, hi, index, value);
+        }
+        self.pull(node);
+    }
+
+    fn update_in(&mut self, node: usize, lo: usize, hi: usize, qlo: usize, qhi: usize, update: &U) {
+        if qhi <= lo || hi <= qlo {
+            return;
+        }
+        if qlo <= lo && hi <= qhi {
+            self.apply(node, hi - lo, update);
+            return;
+        }
+        self.push(node, lo, hi);
+        let mid = (lo + hi) / 2;
+        self.update_in(2 * node, lo, mid, qlo, qhi, update);
+        self.update_in(2 * node + 1, mid, hi, qlo, qhi, update);
+        self.pull(node);
+    }
+
+    /// Extends `acc` rightward through nodes at or after `start`, returning
+    /// the first position where `pred` fails.
+    #[allow(clippy::too_many_arguments)]
+    fn max_right_in(
+        &self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        start: usize,
+        pred: &impl Fn(&M::Value) -> bool,
+        acc: &mut M::Value,
+        outer: Option<&U>,
+    ) -> Option<usize> {
+        if hi <= start {
+            return None;
+        }
+        if start <= lo {
+            let extended = self
+                .monoid
+                .combine(acc, &self.effective(node, hi - lo, outer));
+            if pred(&extended) {
+                *acc = extended;
+                return None;
+            }
+            if hi - lo == 1 {
+                return Some(lo);
+            }
+        }
+        let inner = self.inner(node, outer);
+        let mid = (lo + hi) / 2;
+        self.max_right_in(2 * node, lo, mid, start, pred, acc, inner.as_ref())
+            .or_else(|| self.max_right_in(2 * node + 1, mid, hi, start, pred, acc, inner.as_ref()))
+    }
+}
+
+impl<M: Monoid + Default, U: Action<M>> FromIterator<M::Value> for SegmentTree<M, U> {
+    fn from_iter<I: IntoIterator<Item = M::Value>>(iter: I) -> Self {
+        SegmentTree::new(M::default(), iter.into_iter().collect())
+    }
+}
@@ -0,0 +1,220 @@
+//! A thread-safe LRU cache, sharded to spread lock contention.
+//!
+//! An LRU cache mutates on every hit, since a hit moves its entry to the
+//! front, so even reads need exclusive access and a single lock around
+//! one cache serialises every worker thread. Lock striping splits the
+//! cache into independent shards, each an [`LruCache`] behind its own
+//! mutex, and sends each key to the shard its hash picks. Threads touching
+//! different shards never wait for each other, and with several shards
+//! per core contention stays low even for skewed workloads.
+//!
+//! The capacity is split evenly, so each shard evicts its own least
+//! recently used entry: an approximation of a global LRU that drifts from
+//! it only when one shard runs much hotter than the rest. Hit and miss
+//! counts are kept in atomics outside the locks. Lookups return clones of
+//! the values, since a reference cannot outlive the shard's lock; store
+//! large values behind an [`Arc`].
+//!
+//! Keys are hashed with the crate's [`StableHasher`], so a key always
+//! lands in the same shard.
+//!
+//! [`LruCache`]: crate::lru::LruCache
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+
+use std::borrow::Borrow;
+use std::hash::Hash;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::lru::LruCache;
+use crate::probabilistic::{stable_hash, DEFAULT_SEED};
+
+/// Shards per available core, by default.
+const SHARDS_PER_CORE: usize = 4;
+
+/// An LRU cache from `K` to `V` that can be shared between threads.
+#[derive(Debug)]
+pub struct ShardedLruCache<K, V> {
+    shards: Box<[Mutex<LruCache<K, V>>]>,
+    capacity: u64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq + Clone, V> ShardedLruCache<K, V> {
+    /// Creates an empty cache holding entries of total weight at most
+    /// about `capacity`, with four shards per available core.
+    pub fn new(capacity: u64) -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_shards(capacity, cores * SHARDS_PER_CORE)
+    }
+
+    /// Creates an empty cache with `shards` shards, rounded up to a power
+    /// of two. Each shard holds a share of `capacity`, rounded up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_shards(capacity: u64, shards: usize) -> Self {
+        assert!(shards > 0, "a cache needs at least one shard");
+        let shards = shards.next_power_of_two();
+        let share = capacity.div_ceil(shards as u64);
+        ShardedLruCache {
+            shards: (0..shards)
+                .map(|_| Mutex::new(LruCache::new(share)))
+                .collect(),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Calls `f` with every entry evicted to make room from now on, from
+    /// whichever thread caused the eviction, while that shard is locked.
+    pub fn with_eviction_callback(mut self, f: impl Fn(K, V) + Send + Sync + 'static) -> Self {
+        let f = Arc::new(f);
+        for shard in self.shards.iter_mut() {
+            let cache = shard.get_mut().unwrap_or_else(PoisonError::into_inner);
+            let f = Arc::clone(&f);
+            *cache =
+                mem::replace(cache, LruCache::new(0)).with_eviction_callback(move |k, v| f(k, v));
+        }
+        self
+    }
+
+    /// Number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The capacity the cache was created with.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Number of entries, summed shard by shard, so only a snapshot while
+    /// other threads write.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| lock(s).len()).sum()
+    }
+
+    /// Whether the cache holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|s| lock(s).is_empty())
+    }
+
+    /// Total weight of the entries, summed shard by shard.
+    pub fn weight(&self) -> u64 {
+        self.shards.iter().map(|s| lock(s).weight()).sum()
+    }
+
+    /// Removes every entry, without calling the eviction callback.
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            lock(shard).clear();
+        }
+    }
+
+    /// Number of lookups that found their key.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that missed.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Whether an entry is stored under `key`. Does not count as a use.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).contains(key)
+    }
+
+    /// Returns a clone of the value stored under `key`, marking it most
+    /// recently used in its shard.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        let value = self.shard(key).get(key).cloned();
+        self.record(value.is_some());
+        value
+    }
+
+    /// Returns a clone of the value stored under `key` without marking it
+    /// used or counting a hit or miss.
+    pub fn peek<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard(key).peek(key).cloned()
+    }
+
+    /// Inserts `value` under `key` with weight one, returning the value
+    /// previously stored under it.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.insert_weighted(key, value, 1)
+    }
+
+    /// Inserts `value` under `key` with the given weight, evicting least
+    /// recently used entries of its shard until the shard fits its share of
+    /// the capacity. Returns the value previously stored under `key`.
+    pub fn insert_weighted(&self, key: K, value: V, weight: u64) -> Option<V> {
+        self.shard(&key).insert_weighted(key, value, weight)
+    }
+
+    /// Returns the value stored under `key`, first inserting the one `f`
+    /// makes, with weight one, if there is none.
+    ///
+    /// `f` runs with the key's shard locked, so concurrent callers for the
+    /// same key compute it once; it must not use the cache itself.
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V
+    where
+        V: Clone,
+    {
+        let mut shard = self.shard(&key);
+        if let Some(value) = shard.get(&key) {
+            self.record(true);
+            return value.clone();
+        }
+        self.record(false);
+        let value = f();
+        shard.insert(key, value.clone());
+        value
+    }
+
+    /// Removes the entry stored under `key` and returns its value.
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).remove(key)
+    }
+
+    /// Locks the shard `key` hashes to.
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> MutexGuard<'_, LruCache<K, V>> {
+        let hash = stable_hash(DEFAULT_SEED, key);
+        lock(&self.shards[hash as usize & (self.shards.len() - 1)])
+    }
+
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Locks a shard, ignoring poisoning: a panic in an eviction callback
+/// leaves the shard's links intact.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
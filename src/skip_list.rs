@@ -0,0 +1,401 @@
+//! An ordered map stored as a skip list.
+//!
+//! Pugh's skip list keeps its entries in a sorted linked list. Each node
+//! also joins a random number of express lanes above it, each lane skipping
+//! over roughly four times as many nodes as the one below. A search starts
+//! in the top lane and drops a lane whenever the next hop would overshoot.
+//! That makes lookups, insertions and removals `O(log n)` expected, with no
+//! rotations or rebalancing at all.
+//!
+//! An update only ever rewires the immediate predecessors of one node, so
+//! there is no restructuring that can touch the rest of the map. That
+//! locality is why skip lists are the usual basis for concurrent ordered
+//! maps. This one is single-threaded, but follows the same layout. Nodes
+//! live in an arena and link by index. The bottom lane is doubly linked,
+//! so iteration runs from either end.
+//!
+//! Levels come from a seeded generator, so a given sequence of operations
+//! always builds the same list.
+
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+use crate::rng::SplitMix64;
+
+const NONE: u32 = u32::MAX;
+
+/// The number of lanes; enough for about `4^MAX_LEVEL` entries.
+const MAX_LEVEL: usize = 24;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    /// The next node in each lane this node joins, bottom lane first.
+    next: Vec<u32>,
+    /// The previous node in the bottom lane.
+    prev: u32,
+}
+
+/// An ordered map from keys of type `K` to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct SkipList<K, V> {
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<u32>,
+    /// The first node in each lane.
+    head: [u32; MAX_LEVEL],
+    /// The last node in the bottom lane.
+    tail: u32,
+    /// Number of lanes in use.
+    level: usize,
+    len: usize,
+    rng: SplitMix64,
+}
+
+impl<K: Ord, V> Default for SkipList<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> SkipList<K, V> {
+    /// Creates an empty map with a fixed default seed.
+    pub fn new() -> Self {
+        Self::with_seed(0x5eed)
+    }
+
+    /// Creates an empty map whose node levels are drawn from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        SkipList {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: [NONE; MAX_LEVEL],
+            tail: NONE,
+            level: 0,
+            len: 0,
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.head = [NONE; MAX_LEVEL];
+        self.tail = NONE;
+        self.level = 0;
+        self.len = 0;
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let n = self.find(key)?;
+        Some(&self.node(n).value)
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let n = self.find(key)?;
+        Some(&mut self.node_mut(n).value)
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        if self.head[0] == NONE {
+            return None;
+        }
+        let n = self.node(self.head[0]);
+        Some((&n.key, &n.value))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        if self.tail == NONE {
+            return None;
+        }
+        let n = self.node(self.tail);
+        Some((&n.key, &n.value))
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let preds = self.predecessors(&key, false);
+        let at = self.after(preds[0], 0);
+        if at != NONE && self.node(at).key == key {
+            return Some(std::mem::replace(&mut self.node_mut(at).value, value));
+        }
+        let height = self.random_level();
+        let next: Vec<u32> = (0..height).map(|l| self.after(preds[l], l)).collect();
+        let node = Node {
+            key,
+            value,
+            next,
+            prev: preds[0],
+        };
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id as usize] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        for (l, &p) in preds.iter().enumerate().take(height) {
+            self.set_after(p, l, id);
+        }
+        match self.after(id, 0) {
+            NONE => self.tail = id,
+            n => self.node_mut(n).prev = id,
+        }
+        self.level = self.level.max(height);
+        self.len += 1;
+        None
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let preds = self.predecessors(key, false);
+        let at = self.after(preds[0], 0);
+        if at == NONE || self.node(at).key.borrow() != key {
+            return None;
+        }
+        Some(self.unlink(at, &preds))
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Removes and returns the entry with the smallest key.
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        let first = self.head[0];
+        if first == NONE {
+            return None;
+        }
+        // The first node is preceded by the head in every lane it joins.
+        Some(self.unlink(first, &[NONE; MAX_LEVEL]))
+    }
+
+    /// Removes and returns the entry with the largest key.
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        if self.tail == NONE {
+            return None;
+        }
+        let preds = self.predecessors(&self.node(self.tail).key, false);
+        Some(self.unlink(self.tail, &preds))
+    }
+
+    /// Iterates over the entries in key order, from either end.
+    pub fn iter(&self) -> Range<'_, K, V> {
+        Range {
+            list: self,
+            front: self.head[0],
+            back: self.tail,
+        }
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order,
+    /// from either end.
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let front = match range.start_bound() {
+            Bound::Included(b) => self.after(self.predecessors(b, false)[0], 0),
+            Bound::Excluded(b) => self.after(self.predecessors(b, true)[0], 0),
+            Bound::Unbounded => self.head[0],
+        };
+        let back = match range.end_bound() {
+            Bound::Included(b) => self.predecessors(b, true)[0],
+            Bound::Excluded(b) => self.predecessors(b, false)[0],
+            Bound::Unbounded => self.tail,
+        };
+        if front == NONE || back == NONE || self.node(front).key > self.node(back).key {
+            return Range {
+                list: self,
+                front: NONE,
+                back: NONE,
+            };
+        }
+        Range {
+            list: self,
+            front,
+            back,
+        }
+    }
+
+    /// The node holding `key`.
+    fn find<Q>(&self, key: &Q) -> Option<u32>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let n = self.after(self.predecessors(key, false)[0], 0);
+        (n != NONE && self.node(n).key.borrow() == key).then_some(n)
+    }
+
+    /// The last node in each lane whose key is below `key`, or, with
+    /// `inclusive` set, at most `key`; `NONE` stands for the head.
+    fn predecessors<Q>(&self, key: &Q, inclusive: bool) -> [u32; MAX_LEVEL]
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut preds = [NONE; MAX_LEVEL];
+        let mut n = NONE;
+        for l in (0..self.level).rev() {
+            loop {
+                let next = self.after(n, l);
+                if next == NONE {
+                    break;
+                }
+                let k = self.node(next).key.borrow();
+                if k < key || (inclusive && k == key) {
+                    n = next;
+                } else {
+                    break;
+                }
+            }
+            preds[l] = n;
+        }
+        preds
+    }
+
+    /// Unlinks node `n`, whose predecessor in lane `l` is `preds[l]`.
+    fn unlink(&mut self, n: u32, preds: &[u32; MAX_LEVEL]) -> (K, V) {
+        let node = self.nodes[n as usize].take().expect("live node");
+        for (l, &next) in node.next.iter().enumerate() {
+            self.set_after(preds[l], l, next);
+        }
+        match node.next[0] {
+            NONE => self.tail = node.prev,
+            next => self.node_mut(next).prev = node.prev,
+        }
+        while self.level > 0 && self.head[self.level - 1] == NONE {
+            self.level -= 1;
+        }
+        self.free.push(n);
+        self.len -= 1;
+        (node.key, node.value)
+    }
+
+    /// The node following `n` in lane `l`, where `NONE` is the head.
+    fn after(&self, n: u32, l: usize) -> u32 {
+        match n {
+            NONE => self.head[l],
+            n => self.node(n).next[l],
+        }
+    }
+
+    fn set_after(&mut self, n: u32, l: usize, to: u32) {
+        match n {
+            NONE => self.head[l] = to,
+            n => self.node_mut(n).next[l] = to,
+        }
+    }
+
+    /// A level of at least one, each further level with probability 1/4.
+    fn random_level(&mut self) -> usize {
+        let bits = self.rng.next_u64();
+        (1 + bits.trailing_zeros() as usize / 2).min(MAX_LEVEL)
+    }
+
+    fn node(&self, n: u32) -> &Node<K, V> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<K, V> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SkipList<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut list = SkipList::new();
+        for (k, v) in iter {
+            list.insert(k, v);
+        }
+        list
+    }
+}
+
+/// An iterator over a key range of a [`SkipList`], from either end.
+#[derive(Clone, Debug)]
+pub struct Range<'a, K, V> {
+    list: &'a SkipList<K, V>,
+    /// The next nodes to yield from each end, or `NONE` once exhausted.
+    front: u32,
+    back: u32,
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == NONE {
+            return None;
+        }
+        let node = self.list.node(self.front);
+        if self.front == self.back {
+            (self.front, self.back) = (NONE, NONE);
+        } else {
+            self.front = node.next[0];
+        }
+        Some((&node.key, &node.value))
+    }
+}
+
+impl<K: Ord, V> DoubleEndedIterator for Range<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.back == NONE {
+            return None;
+        }
+        let node = self.list.node(self.back);
+        if self.front == self.back {
+            (self.front, self.back) = (NONE, NONE);
+        } else {
+            self.back = node.prev;
+        }
+        Some((&node.key, &node.value))
+    }
+}
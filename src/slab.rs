@@ -0,0 +1,253 @@
+//! A slab: a vector of reusable slots addressed by small integer keys.
+//!
+//! Values are stored in a vector and named by their index. Removing one
+//! leaves a vacant entry that joins a free list threaded through the vacant
+//! entries themselves, and the next insertion takes the most recently freed
+//! slot, so the slab needs no memory beyond the entries and a list head.
+//! Insertion, removal and lookup are `O(1)`, and keys stay put until the
+//! value is removed, which makes the slab a backing store for arena-based
+//! trees and graphs that link nodes by index.
+//!
+//! Keys are bare indices and are reused, so a key held past its value's
+//! removal may later name an unrelated value; [`SlotMap`] adds generations
+//! for handles that outlive their values. [`Slab::compact`] moves values
+//! from the end into holes to reclaim memory after heavy removal, telling
+//! the caller about each move so that links to the old keys can be
+//! rewritten.
+//!
+//! [`SlotMap`]: crate::slot_map::SlotMap
+
+use std::ops::{Index, IndexMut};
+
+#[derive(Clone, Debug)]
+enum Entry<T> {
+    Occupied(T),
+    /// A free slot and the next one on the free list.
+    Vacant(usize),
+}
+
+/// A vector of values of type `T` with reusable `usize` keys.
+#[derive(Clone, Debug)]
+pub struct Slab<T> {
+    entries: Vec<Entry<T>>,
+    /// Head of the free list; `entries.len()` when it is empty.
+    next: usize,
+    len: usize,
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Slab {
+            entries: Vec::new(),
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> Slab<T> {
+    /// Creates an empty slab.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty slab with room for `capacity` values.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Slab {
+            entries: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Number of values the slab can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the slab holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every value.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next = 0;
+        self.len = 0;
+    }
+
+    /// The key the next insertion will return.
+    pub fn vacant_key(&self) -> usize {
+        self.next
+    }
+
+    /// Stores `value`, returning its key.
+    pub fn insert(&mut self, value: T) -> usize {
+        let key = self.next;
+        if key == self.entries.len() {
+            self.entries.push(Entry::Occupied(value));
+            self.next = self.entries.len();
+        } else {
+            match std::mem::replace(&mut self.entries[key], Entry::Occupied(value)) {
+                Entry::Vacant(next) => self.next = next,
+                Entry::Occupied(_) => unreachable!("free list names an occupied entry"),
+            }
+        }
+        self.len += 1;
+        key
+    }
+
+    /// Removes the value under `key`, returning it.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        let entry = self.entries.get_mut(key)?;
+        if matches!(entry, Entry::Vacant(_)) {
+            return None;
+        }
+        let Entry::Occupied(value) = std::mem::replace(entry, Entry::Vacant(self.next)) else {
+            unreachable!("entry checked above");
+        };
+        self.next = key;
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Whether `key` names a stored value.
+    pub fn contains(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value under `key`.
+    pub fn get(&self, key: usize) -> Option<&T> {
+        match self.entries.get(key)? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// The value under `key`, mutably.
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        match self.entries.get_mut(key)? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    /// Keeps only the values for which `keep` returns `true`.
+    pub fn retain(&mut self, mut keep: impl FnMut(usize, &mut T) -> bool) {
+        for key in 0..self.entries.len() {
+            if let Entry::Occupied(value) = &mut self.entries[key] {
+                if !keep(key, value) {
+                    self.entries[key] = Entry::Vacant(self.next);
+                    self.next = key;
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    /// Moves values from the end of the slab into vacant slots until the
+    /// values fill a prefix of the keys, then releases the spare memory.
+    ///
+    /// Before each move, `rekey(value, from, to)` is called so the caller
+    /// can update whatever refers to `from`; returning `false` leaves that
+    /// value in place and stops compacting.
+    pub fn compact(&mut self, mut rekey: impl FnMut(&mut T, usize, usize) -> bool) {
+        let mut hole = 0;
+        let mut end = self.entries.len();
+        loop {
+            while end > 0 && matches!(self.entries[end - 1], Entry::Vacant(_)) {
+                end -= 1;
+            }
+            while hole < end && matches!(self.entries[hole], Entry::Occupied(_)) {
+                hole += 1;
+            }
+            if hole >= end {
+                break;
+            }
+            let from = end - 1;
+            let Entry::Occupied(mut value) =
+                std::mem::replace(&mut self.entries[from], Entry::Vacant(0))
+            else {
+                unreachable!("trailing entry is occupied");
+            };
+            if !rekey(&mut value, from, hole) {
+                self.entries[from] = Entry::Occupied(value);
+                break;
+            }
+            self.entries[hole] = Entry::Occupied(value);
+            end = from;
+        }
+        self.entries.truncate(end);
+        self.entries.shrink_to_fit();
+        // Rebuild the free list over the holes left, lowest first.
+        self.next = self.entries.len();
+        for key in (0..self.entries.len()).rev() {
+            if let Entry::Vacant(next) = &mut self.entries[key] {
+                *next = self.next;
+                self.next = key;
+            }
+        }
+    }
+
+    /// Iterates over the keys and values, in key order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (usize, &T)> + '_ {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    /// Iterates over the keys and mutable values, in key order.
+    pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (usize, &mut T)> + '_ {
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(key, entry)| match entry {
+                Entry::Occupied(value) => Some((key, value)),
+                Entry::Vacant(_) => None,
+            })
+    }
+
+    /// Removes every value, yielding them in key order.
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.next = 0;
+        self.len = 0;
+        self.entries.drain(..).filter_map(|entry| match entry {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        })
+    }
+}
+
+impl<T> Index<usize> for Slab<T> {
+    type Output = T;
+
+    fn index(&self, key: usize) -> &T {
+        self.get(key).expect("vacant slab key")
+    }
+}
+
+impl<T> IndexMut<usize> for Slab<T> {
+    fn index_mut(&mut self, key: usize) -> &mut T {
+        self.get_mut(key).expect("vacant slab key")
+    }
+}
+
+impl<T> FromIterator<T> for Slab<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let entries: Vec<_> = iter.into_iter().map(Entry::Occupied).collect();
+        Slab {
+            next: entries.len(),
+            len: entries.len(),
+            entries,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Sliding-window aggregates over a stream.
+//!
+//! A sliding window is a queue: values enter at the back as they arrive and
+//! leave at the front as they age out, and at every step the caller wants
+//! the aggregate of what is inside, such as the minimum over the last
+//! minute of samples. Recomputing it costs the window's length per step;
+//! the two structures here cost `O(1)` amortized.
+//!
+//! [`MonotonicQueue`] answers minimum queries (and maximum ones, through
+//! [`Reverse`](std::cmp::Reverse)) with the classic monotonic deque: it
+//! keeps only the values that could still become the minimum, which form
+//! an increasing run, so the minimum is always at the front. Values that
+//! are discarded are never handed back, so popping reports only whether
+//! the window was non-empty.
+//!
+//! [`SlidingWindow`] aggregates under any [`Monoid`], with no inverse and
+//! no idempotence required, by the two-stack queue trick. New values go on
+//! a back stack with a running aggregate; when the front runs dry the back
+//! stack is reversed onto it, each entry storing the aggregate of itself
+//! and everything newer on the front stack. The window's aggregate is then
+//! one combination of the front top and the back total. Every value is
+//! moved once, so each operation is amortized `O(1)` combinations.
+
+use std::collections::VecDeque;
+
+use crate::algebra::Monoid;
+
+/// A FIFO window of `T` that tracks its minimum.
+#[derive(Clone, Debug)]
+pub struct MonotonicQueue<T> {
+    /// The values that can still become the minimum, as `(sequence
+    /// number, value)`, increasing in both.
+    candidates: VecDeque<(u64, T)>,
+    /// Sequence number of the oldest value in the window.
+    head: u64,
+    /// Sequence number the next pushed value receives.
+    tail: u64,
+}
+
+impl<T> Default for MonotonicQueue<T> {
+    fn default() -> Self {
+        MonotonicQueue {
+            candidates: VecDeque::new(),
+            head: 0,
+            tail: 0,
+        }
+    }
+}
+
+impl<T: Ord> MonotonicQueue<T> {
+    /// Creates an empty window.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of values in the window, including discarded ones.
+    pub fn len(&self) -> usize {
+        (self.tail - self.head) as usize
+    }
+
+    /// Whether the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.tail == self.head
+    }
+
+    /// Empties the window.
+    pub fn clear(&mut self) {
+        self.candidates.clear();
+        self.head = self.tail;
+    }
+
+    /// Adds `value` as the newest value, in amortized `O(1)`.
+    pub fn push(&mut self, value: T) {
+        // A value no smaller than a newer one will leave before it.
+        while self.candidates.back().is_some_and(|(_, v)| *v > value) {
+            self.candidates.pop_back();
+        }
+        self.candidates.push_back((self.tail, value));
+        self.tail += 1;
+    }
+
+    /// Removes the oldest value, returning whether there was one.
+    pub fn pop(&mut self) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if self
+            .candidates
+            .front()
+            .is_some_and(|&(seq, _)| seq == self.head)
+        {
+            self.candidates.pop_front();
+        }
+        self.head += 1;
+        true
+    }
+
+    /// The least value in the window; the oldest one among equals.
+    pub fn min(&self) -> Option<&T> {
+        self.candidates.front().map(|(_, v)| v)
+    }
+}
+
+/// A FIFO window of monoid values that tracks their combination in order.
+#[derive(Clone, Debug)]
+pub struct SlidingWindow<M: Monoid> {
+    monoid: M,
+    /// Oldest values on top, each paired with the combination of itself
+    /// and every value below it.
+    front: Vec<(M::Value, M::Value)>,
+    /// Newest values on top.
+    back: Vec<M::Value>,
+    /// The combination of `back`, oldest first.
+    back_total: M::Value,
+}
+
+impl<M: Monoid + Default> Default for SlidingWindow<M> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<M: Monoid> SlidingWindow<M> {
+    /// Creates an empty window combining values with `monoid`.
+    pub fn new(monoid: M) -> Self {
+        let back_total = monoid.identity();
+        SlidingWindow {
+            monoid,
+            front: Vec::new(),
+            back: Vec::new(),
+            back_total,
+        }
+    }
+
+    /// The monoid.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// Number of values in the window.
+    pub fn len(&self) -> usize {
+        self.front.len() + self.back.len()
+    }
+
+    /// Whether the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Empties the window.
+    pub fn clear(&mut self) {
+        self.front.clear();
+        self.back.clear();
+        self.back_total = self.monoid.identity();
+    }
+
+    /// Adds `value` as the newest value, in `O(1)`.
+    pub fn push(&mut self, value: M::Value) {
+        self.back_total = self.monoid.combine(&self.back_total, &value);
+        self.back.push(value);
+    }
+
+    /// Removes and returns the oldest value, in amortized `O(1)`.
+    pub fn pop(&mut self) -> Option<M::Value> {
+        if self.front.is_empty() {
+            let mut total = self.monoid.identity();
+            while let Some(value) = self.back.pop() {
+                total = self.monoid.combine(&value, &total);
+                self.front.push((value, total.clone()));
+            }
+            self.back_total = self.monoid.identity();
+        }
+        self.front.pop().map(|(value, _)| value)
+    }
+
+    /// The oldest value.
+    pub fn front(&self) -> Option<&M::Value> {
+        self.front
+            .last()
+            .map(|(value, _)| value)
+            .or_else(|| self.back.first())
+    }
+
+    /// Adds `value` and, if the window then holds more than `width`
+    /// values, removes the oldest, returning it.
+    pub fn push_evict(&mut self, value: M::Value, width: usize) -> Option<M::Value> {
+        self.push(value);
+        if self.len() > width {
+            self.pop()
+        } else {
+            None
+        }
+    }
+
+    /// The combination of every value in the window, oldest first; the
+    /// identity when the window is empty.
+    pub fn aggregate(&self) -> M::Value {
+        match self.front.last() {
+            Some((_, total)) => self.monoid.combine(total, &self.back_total),
+            None => self.back_total.clone(),
+        }
+    }
+
+    /// Iterates over the values in the window, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &M::Value> + '_ {
+        self.front
+            .iter()
+            .rev()
+            .map(|(value, _)| value)
+            .chain(&self.back)
+    }
+}
+
+impl<M: Monoid> Extend<M::Value> for SlidingWindow<M> {
+    fn extend<I: IntoIterator<Item = M::Value>>(&mut self, iter: I) {
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! A slot map: an arena of values named by generational keys.
+//!
+//! Values live in a vector of slots, and a key holds a slot index and a
+//! generation. Removing a value frees its slot for the next insertion and
+//! bumps the slot's generation, so the removed value's key stops matching:
+//! a lookup through a stale key fails instead of silently reaching whatever
+//! was stored there next. Insertion, removal and lookup are `O(1)`, values
+//! never move, and every other key stays valid across removals, which
+//! makes keys safe to hand out as stable handles.
+//!
+//! Keys can be given distinct types, so handles into different maps cannot
+//! be mixed up: any `Copy` type convertible to and from [`Key`] is a
+//! [`SlotKey`]. A [`SecondaryMap`] attaches further data to the keys of a
+//! slot map without touching it, indexed by the same slots.
+//!
+//! Generations are 32 bits and wrap, so a key is only guaranteed stale
+//! while its slot has been reused fewer than `2^32` times.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+/// A slot index and generation: the key type of a [`SlotMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    index: u32,
+    generation: u32,
+}
+
+impl Key {
+    /// The slot the key names.
+    pub fn index(self) -> u32 {
+        self.index
+    }
+
+    /// The generation of the slot when the key was issued.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// A key type for a [`SlotMap`]: a handle that wraps a [`Key`].
+pub trait SlotKey: Copy + From<Key> + Into<Key> {}
+
+impl<K: Copy + From<Key> + Into<Key>> SlotKey for K {}
+
+#[derive(Clone, Debug)]
+struct Slot<V> {
+    generation: u32,
+    value: Option<V>,
+}
+
+/// An arena of values of type `V`, named by keys of type `K`.
+pub struct SlotMap<K, V> {
+    slots: Vec<Slot<V>>,
+    free: Vec<u32>,
+    len: usize,
+    key: PhantomData<fn() -> K>,
+}
+
+impl<K, V: Clone> Clone for SlotMap<K, V> {
+    fn clone(&self) -> Self {
+        SlotMap {
+            slots: self.slots.clone(),
+            free: self.free.clone(),
+            len: self.len,
+            key: PhantomData,
+        }
+    }
+}
+
+impl<K: SlotKey + fmt::Debug, V: fmt::Debug> fmt::Debug for SlotMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Default for SlotMap<K, V> {
+    fn default() -> Self {
+        SlotMap {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            key: PhantomData,
+        }
+    }
+}
+
+impl<K: SlotKey, V> SlotMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map with room for `capacity` values.
+    pub fn with_capacity(capacity: usize) -> Self {
+        SlotMap {
+            slots: Vec::with_capacity(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every value, invalidating every key.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Stores `value`, returning its key.
+    pub fn insert(&mut self, value: V) -> K {
+        self.insert_with_key(|_| value)
+    }
+
+    /// Stores the value `f` makes from its own key, returning the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map would need more than `u32::MAX` slots.
+    pub fn insert_with_key(&mut self, f: impl FnOnce(K) -> V) -> K {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                assert!(self.slots.len() < u32::MAX as usize, "slot map is full");
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        let key = K::from(Key {
+            index,
+            generation: slot.generation,
+        });
+        slot.value = Some(f(key));
+        self.len += 1;
+        key
+    }
+
+    /// Removes the value under `key`, returning it.
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let Key { index, generation } = key.into();
+        let slot = self.slots.get_mut(index as usize)?;
+        if slot.generation != generation {
+            return None;
+        }
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Whether `key` names a stored value.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value under `key`.
+    pub fn get(&self, key: K) -> Option<&V> {
+        let Key { index, generation } = key.into();
+        let slot = self.slots.get(index as usize)?;
+        (slot.generation == generation)
+            .then_some(slot.value.as_ref())
+            .flatten()
+    }
+
+    /// The value under `key`, mutably.
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        let Key { index, generation } = key.into();
+        let slot = self.slots.get_mut(index as usize)?;
+        (slot.generation == generation)
+            .then_some(slot.value.as_mut())
+            .flatten()
+    }
+
+    /// Keeps only the values for which `keep` returns `true`.
+    pub fn retain(&mut self, mut keep: impl FnMut(K, &mut V) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let key = K::from(Key {
+                index: index as u32,
+                generation: slot.generation,
+            });
+            if slot.value.as_mut().is_some_and(|v| !keep(key, v)) {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(index as u32);
+                self.len -= 1;
+            }
+        }
+    }
+
+    /// Iterates over the keys and values, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &V)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let key = K::from(Key {
+                index: index as u32,
+                generation: slot.generation,
+            });
+            slot.value.as_ref().map(|v| (key, v))
+        })
+    }
+
+    /// Iterates over the keys and mutable values, in slot order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (K, &mut V)> + '_ {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let key = K::from(Key {
+                    index: index as u32,
+                    generation: slot.generation,
+                });
+                slot.value.as_mut().map(|v| (key, v))
+            })
+    }
+
+    /// Iterates over the keys, in slot order.
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Iterates over the values, in slot order.
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    /// Iterates over the values mutably, in slot order.
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> + '_ {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    /// Number of slots, occupied or free.
+    pub(crate) fn slot_count(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The value in slot `index`, whatever its generation.
+    pub(crate) fn slot(&self, index: u32) -> Option<&V> {
+        self.slots.get(index as usize)?.value.as_ref()
+    }
+
+    /// The value in slot `index` mutably, whatever its generation.
+    pub(crate) fn slot_mut(&mut self, index: u32) -> Option<&mut V> {
+        self.slots.get_mut(index as usize)?.value.as_mut()
+    }
+
+    /// The current key of slot `index`.
+    pub(crate) fn key_of(&self, index: u32) -> K {
+        K::from(Key {
+            index,
+            generation: self.slots[index as usize].generation,
+        })
+    }
+}
+
+impl<K: SlotKey, V> Index<K> for SlotMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: K) -> &V {
+        self.get(key).expect("stale or invalid slot map key")
+    }
+}
+
+impl<K: SlotKey, V> IndexMut<K> for SlotMap<K, V> {
+    fn index_mut(&mut self, key: K) -> &mut V {
+        self.get_mut(key).expect("stale or invalid slot map key")
+    }
+}
+
+/// Extra values attached to the keys of a [`SlotMap`], stored by slot.
+///
+/// The map does not see removals from the slot map; an entry whose key
+/// went stale stays until it is overwritten by a newer key of the same
+/// slot, removed, or dropped by [`retain`](Self::retain).
+pub struct SecondaryMap<K, V> {
+    /// Each slot's key generation and value.
+    slots: Vec<Option<(u32, V)>>,
+    len: usize,
+    key: PhantomData<fn() -> K>,
+}
+
+impl<K, V: Clone> Clone for SecondaryMap<K, V> {
+    fn clone(&self) -> Self {
+        SecondaryMap {
+            slots: self.slots.clone(),
+            len: self.len,
+            key: PhantomData,
+        }
+    }
+}
+
+impl<K: SlotKey + fmt::Debug, V: fmt::Debug> fmt::Debug for SecondaryMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> Default for SecondaryMap<K, V> {
+    fn default() -> Self {
+        SecondaryMap {
+            slots: Vec::new(),
+            len: 0,
+            key: PhantomData,
+        }
+    }
+}
+
+impl<K: SlotKey, V> SecondaryMap<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.len = 0;
+    }
+
+    /// Stores `value` under `key`, returning the value stored under the
+    /// same key before. An entry for an older key of the slot is replaced
+    /// and not returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let Key { index, generation } = key.into();
+        let index = index as usize;
+        if index >= self.slots.len() {
+            self.slots.resize_with(index + 1, || None);
+        }
+        match self.slots[index].replace((generation, value)) {
+            Some((g, old)) if g == generation => Some(old),
+            Some(_) => None,
+            None => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    /// Removes the entry under `key`, returning its value.
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let Key { index, generation } = key.into();
+        let slot = self.slots.get_mut(index as usize)?;
+        if slot.as_ref()?.0 != generation {
+            return None;
+        }
+        self.len -= 1;
+        slot.take().map(|(_, v)| v)
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The value under `key`.
+    pub fn get(&self, key: K) -> Option<&V> {
+        let Key { index, generation } = key.into();
+        match self.slots.get(index as usize)? {
+            Some((g, v)) if *g == generation => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The value under `key`, mutably.
+    pub fn get_mut(&mut self, key: K) -> Option<&mut V> {
+        let Key { index, generation } = key.into();
+        match self.slots.get_mut(index as usize)? {
+            Some((g, v)) if *g == generation => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Keeps only the entries for which `keep` returns `true`; passing
+    /// `|k, _| primary.contains_key(k)` drops the stale ones.
+    pub fn retain(&mut self, mut keep: impl FnMut(K, &mut V) -> bool) {
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if let Some((generation, v)) = slot {
+                let key = K::from(Key {
+                    index: index as u32,
+                    generation: *generation,
+                });
+                if !keep(key, v) {
+                    *slot = None;
+                    self.len -= 1;
+                }
+            }
+        }
+    }
+
+    /// Iterates over the keys and values, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &V)> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let (generation, v) = slot.as_ref()?;
+            let key = K::from(Key {
+                index: index as u32,
+                generation: *generation,
+            });
+            Some((key, v))
+        })
+    }
+
+    /// Iterates over the keys and mutable values, in slot order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (K, &mut V)> + '_ {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let (generation, v) = slot.as_mut()?;
+                let key = K::from(Key {
+                    index: index as u32,
+                    generation: *generation,
+                });
+                Some((key, v))
+            })
+    }
+}
+
+impl<K: SlotKey, V> Index<K> for SecondaryMap<K, V> {
+    type Output = V;
+
+    fn index(&self, key: K) -> &V {
+        self.get(key).expect("no entry for key")
+    }
+}
+
+impl<K: SlotKey, V> IndexMut<K> for SecondaryMap<K, V> {
+    fn index_mut(&mut self, key: K) -> &mut V {
+        self.get_mut(key).expect("no entry for key")
+    }
+}
@@ -0,0 +1,266 @@
+//! A sparse set of small integers, and a map keyed by them.
+//!
+//! The sparse set of Briggs and Torczon (1993) stores its members packed in
+//! a dense array, in no particular order, and keeps a sparse array indexed
+//! by key that records where in the dense array each key sits. A key is a
+//! member exactly when its sparse entry points at a dense slot holding that
+//! key, so stale sparse entries need no cleanup: insertion and membership
+//! are one probe each, removal swaps the last member into the hole, and
+//! clearing only truncates the dense array. Iteration walks the dense array
+//! and touches nothing but members.
+//!
+//! The sparse array grows to the largest key inserted, so keys should come
+//! from a dense range such as entity ids or node numbers. [`SparseMap`]
+//! keeps a value beside each dense key, which is how entity-component
+//! systems store components: systems iterate the packed values directly,
+//! while lookups by entity stay `O(1)`.
+
+/// A set of `usize` keys with `O(1)` insert, remove, lookup and clear.
+#[derive(Clone, Debug, Default)]
+pub struct SparseSet {
+    /// The dense position of each key, valid only if `dense` agrees.
+    sparse: Vec<usize>,
+    dense: Vec<usize>,
+}
+
+impl SparseSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set whose keys below `universe` need no growth.
+    pub fn with_universe(universe: usize) -> Self {
+        SparseSet {
+            sparse: vec![0; universe],
+            dense: Vec::new(),
+        }
+    }
+
+    /// Number of members.
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Whether the set has no members.
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Removes every member, in `O(1)`.
+    pub fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    /// Whether `key` is a member.
+    pub fn contains(&self, key: usize) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    /// The position of `key` in [`as_slice`](Self::as_slice).
+    pub fn index_of(&self, key: usize) -> Option<usize> {
+        let &i = self.sparse.get(key)?;
+        (self.dense.get(i) == Some(&key)).then_some(i)
+    }
+
+    /// Adds `key`, returning whether it was absent.
+    pub fn insert(&mut self, key: usize) -> bool {
+        if self.contains(key) {
+            return false;
+        }
+        if key >= self.sparse.len() {
+            self.sparse.resize(key + 1, 0);
+        }
+        self.sparse[key] = self.dense.len();
+        self.dense.push(key);
+        true
+    }
+
+    /// Removes `key`, returning whether it was present. The last member
+    /// takes its place in the dense order.
+    pub fn remove(&mut self, key: usize) -> bool {
+        let Some(i) = self.index_of(key) else {
+            return false;
+        };
+        self.dense.swap_remove(i);
+        if let Some(&moved) = self.dense.get(i) {
+            self.sparse[moved] = i;
+        }
+        true
+    }
+
+    /// The members, packed, in dense order.
+    pub fn as_slice(&self) -> &[usize] {
+        &self.dense
+    }
+
+    /// Iterates over the members in dense order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = usize> + DoubleEndedIterator + '_ {
+        self.dense.iter().copied()
+    }
+}
+
+impl PartialEq for SparseSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|k| other.contains(k))
+    }
+}
+
+impl Eq for SparseSet {}
+
+impl Extend<usize> for SparseSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl FromIterator<usize> for SparseSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+/// A map from `usize` keys to values of type `T`, stored packed.
+#[derive(Clone, Debug)]
+pub struct SparseMap<T> {
+    /// The dense position of each key, valid only if `keys` agrees.
+    sparse: Vec<usize>,
+    keys: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> Default for SparseMap<T> {
+    fn default() -> Self {
+        SparseMap {
+            sparse: Vec::new(),
+            keys: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseMap<T> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map whose keys below `universe` need no growth.
+    pub fn with_universe(universe: usize) -> Self {
+        SparseMap {
+            sparse: vec![0; universe],
+            ..Self::default()
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Removes every entry, in time proportional to dropping the values.
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key(&self, key: usize) -> bool {
+        self.index_of(key).is_some()
+    }
+
+    /// The position of `key` in [`keys`](Self::keys) and
+    /// [`values`](Self::values).
+    pub fn index_of(&self, key: usize) -> Option<usize> {
+        let &i = self.sparse.get(key)?;
+        (self.keys.get(i) == Some(&key)).then_some(i)
+    }
+
+    /// The value stored under `key`.
+    pub fn get(&self, key: usize) -> Option<&T> {
+        self.index_of(key).map(|i| &self.values[i])
+    }
+
+    /// The value stored under `key`, mutably.
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        self.index_of(key).map(|i| &mut self.values[i])
+    }
+
+    /// Stores `value` under `key`, returning the value it replaced.
+    pub fn insert(&mut self, key: usize, value: T) -> Option<T> {
+        if let Some(i) = self.index_of(key) {
+            return Some(std::mem::replace(&mut self.values[i], value));
+        }
+        if key >= self.sparse.len() {
+            self.sparse.resize(key + 1, 0);
+        }
+        self.sparse[key] = self.keys.len();
+        self.keys.push(key);
+        self.values.push(value);
+        None
+    }
+
+    /// Removes the entry under `key`, returning its value. The last entry
+    /// takes its place in the dense order.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        let i = self.index_of(key)?;
+        self.keys.swap_remove(i);
+        let value = self.values.swap_remove(i);
+        if let Some(&moved) = self.keys.get(i) {
+            self.sparse[moved] = i;
+        }
+        Some(value)
+    }
+
+    /// The keys, packed, in dense order.
+    pub fn keys(&self) -> &[usize] {
+        &self.keys
+    }
+
+    /// The values, packed, in the same order as [`keys`](Self::keys).
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The values, mutably, in the same order as [`keys`](Self::keys).
+    pub fn values_mut(&mut self) -> &mut [T] {
+        &mut self.values
+    }
+
+    /// Iterates over the entries in dense order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (usize, &T)> + DoubleEndedIterator + '_ {
+        self.keys.iter().copied().zip(&self.values)
+    }
+
+    /// Iterates over the entries in dense order, with values mutable.
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator<Item = (usize, &mut T)> + DoubleEndedIterator + '_ {
+        self.keys.iter().copied().zip(&mut self.values)
+    }
+}
+
+impl<T> Extend<(usize, T)> for SparseMap<T> {
+    fn extend<I: IntoIterator<Item = (usize, T)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<T> FromIterator<(usize, T)> for SparseMap<T> {
+    fn from_iter<I: IntoIterator<Item = (usize, T)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
@@ -0,0 +1,259 @@
+//! Sparse tables for static range queries.
+//!
+//! A [`SparseTable`] stores, for every position and every power of two,
+//! the combination of the run of that length starting there: `n log n`
+//! values in all. Any range is covered by two such runs, one from each
+//! end, which overlap in the middle. When the monoid is [`Idempotent`],
+//! like minimum, maximum or gcd, counting the overlap twice does no harm,
+//! so a query combines exactly two stored values in `O(1)`. The sequence
+//! cannot change once built.
+//!
+//! [`FischerHeun`] answers range-minimum queries in `O(1)` with `O(n)`
+//! space. It follows Fischer and Heun's scheme: split the sequence into
+//! blocks, keep a sparse table over the block minima only, and answer
+//! queries inside a block some other way. With 64-element blocks the
+//! table over the minima has fewer entries than the sequence for any
+//! length that fits in memory. Here each position stores the
+//! shape of the block's Cartesian tree up to it, as a bitmask of the
+//! positions on the rightmost path. The minimum of a range inside a block
+//! is then the lowest bit of one mask at or after the range's start.
+//!
+//! The [`CartesianTree`] reaches the same bounds the other classic way,
+//! building the whole tree explicitly and reducing range minima to lowest
+//! common ancestors, and those to a `FischerHeun` over the depths of an
+//! Euler tour. [`FischerHeun::cartesian_tree`] builds it from the same
+//! values.
+//!
+//! [`Idempotent`]: crate::algebra::Idempotent
+//! [`CartesianTree`]: crate::cartesian_tree::CartesianTree
+
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+use crate::algebra::Idempotent;
+use crate::cartesian_tree::CartesianTree;
+use crate::util::bounds;
+
+/// A static sequence with `O(1)` range queries under an idempotent monoid.
+#[derive(Clone, Debug)]
+pub struct SparseTable<M: Idempotent> {
+    monoid: M,
+    /// `levels[k][i]` combines the elements `i..i + 2^k`.
+    levels: Vec<Vec<M::Value>>,
+}
+
+impl<M: Idempotent> SparseTable<M> {
+    /// Builds a table over `values` in `O(n log n)`.
+    pub fn new(monoid: M, values: Vec<M::Value>) -> Self {
+        let mut levels = vec![values];
+        let mut width = 1;
+        while 2 * width <= levels[0].len() {
+            let prev = levels.last().expect("level zero exists");
+            let next = (0..prev.len() - width)
+                .map(|i| monoid.combine(&prev[i], &prev[i + width]))
+                .collect();
+            levels.push(next);
+            width *= 2;
+        }
+        SparseTable { monoid, levels }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The monoid the table combines with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// The element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> &M::Value {
+        &self.levels[0][index]
+    }
+
+    /// The combination of the elements in `range`, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> M::Value {
+        let (lo, hi) = bounds(range, self.len());
+        if lo == hi {
+            return self.monoid.identity();
+        }
+        let k = (hi - lo).ilog2() as usize;
+        let level = &self.levels[k];
+        self.monoid.combine(&level[lo], &level[hi - (1 << k)])
+    }
+}
+
+impl<M: Idempotent + Default> FromIterator<M::Value> for SparseTable<M> {
+    fn from_iter<I: IntoIterator<Item = M::Value>>(iter: I) -> Self {
+        SparseTable::new(M::default(), iter.into_iter().collect())
+    }
+}
+
+/// Elements per block, one per bit of a mask.
+const BLOCK: usize = 64;
+
+/// A static sequence with `O(1)` range-minimum queries in `O(n)` space.
+///
+/// Ties go to the leftmost position. For maxima, store
+/// [`Reverse`](std::cmp::Reverse) values.
+#[derive(Clone, Debug)]
+pub struct FischerHeun<T> {
+    values: Vec<T>,
+    /// For each position, the positions in its block up to it that are the
+    /// minimum of everything between them and it, as bits from the block's
+    /// start.
+    masks: Vec<u64>,
+    /// `blocks[k][b]` is the position of the minimum of blocks
+    /// `b..b + 2^k`.
+    blocks: Vec<Vec<u32>>,
+}
+
+impl<T: Ord> FischerHeun<T> {
+    /// Builds the structure over `values` in `O(n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `u32::MAX` values or more.
+    pub fn new(values: Vec<T>) -> Self {
+        assert!(values.len() < u32::MAX as usize, "too many values");
+        let mut masks = Vec::with_capacity(values.len());
+        let mut stack: Vec<usize> = Vec::with_capacity(BLOCK);
+        for (i, v) in values.iter().enumerate() {
+            let offset = i % BLOCK;
+            if offset == 0 {
+                stack.clear();
+            }
+            // Pop the positions that `v` undercuts, keeping ties so the
+            // leftmost minimum stays on the path.
+            let mut mask = if offset == 0 { 0 } else { masks[i - 1] };
+            while let Some(&top) = stack.last() {
+                if values[top] <= *v {
+                    break;
+                }
+                mask &= !(1 << (top % BLOCK));
+                stack.pop();
+            }
+            stack.push(i);
+            masks.push(mask | 1 << offset);
+        }
+        let mut table = FischerHeun {
+            values,
+            masks,
+            blocks: Vec::new(),
+        };
+        let count = table.values.len().div_ceil(BLOCK);
+        let first: Vec<u32> = (0..count)
+            .map(|b| {
+                let end = ((b + 1) * BLOCK).min(table.values.len()) - 1;
+                table.in_block(b * BLOCK, end) as u32
+            })
+            .collect();
+        table.blocks.push(first);
+        let mut width = 1;
+        while 2 * width <= count {
+            let prev = table.blocks.last().expect("level zero exists");
+            let next = (0..prev.len() - width)
+                .map(|b| table.better(prev[b] as usize, prev[b + width] as usize) as u32)
+                .collect();
+            table.blocks.push(next);
+            width *= 2;
+        }
+        table
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The elements.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// The position of the leftmost minimum in `range`, or `None` if it is
+    /// empty, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn argmin(&self, range: impl RangeBounds<usize>) -> Option<usize> {
+        let (lo, hi) = bounds(range, self.len());
+        if lo == hi {
+            return None;
+        }
+        let last = hi - 1;
+        let (first_block, last_block) = (lo / BLOCK, last / BLOCK);
+        if first_block == last_block {
+            return Some(self.in_block(lo, last));
+        }
+        let head = self.in_block(lo, first_block * BLOCK + BLOCK - 1);
+        let tail = self.in_block(last_block * BLOCK, last);
+        let mut best = self.better(head, tail);
+        if first_block + 1 < last_block {
+            let (from, to) = (first_block + 1, last_block);
+            let k = (to - from).ilog2() as usize;
+            let level = &self.blocks[k];
+            let middle = self.better(level[from] as usize, level[to - (1 << k)] as usize);
+            best = self.better(best, middle);
+        }
+        Some(best)
+    }
+
+    /// The Cartesian tree of the elements, whose lowest common ancestors
+    /// are the minima this structure finds, in `O(n)`.
+    pub fn cartesian_tree(&self) -> CartesianTree {
+        CartesianTree::new(&self.values)
+    }
+
+    /// The leftmost minimum in `range`, or `None` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn min(&self, range: impl RangeBounds<usize>) -> Option<&T> {
+        self.argmin(range).map(|i| &self.values[i])
+    }
+
+    /// The position of the minimum of `lo..=hi`, which lie in one block.
+    fn in_block(&self, lo: usize, hi: usize) -> usize {
+        let mask = self.masks[hi] & (!0 << (lo % BLOCK));
+        hi - hi % BLOCK + mask.trailing_zeros() as usize
+    }
+
+    /// Whichever of two positions holds the smaller value, the earlier one
+    /// on a tie.
+    fn better(&self, a: usize, b: usize) -> usize {
+        match self.values[a].cmp(&self.values[b]) {
+            Ordering::Less => a,
+            Ordering::Greater => b,
+            Ordering::Equal => a.min(b),
+        }
+    }
+}
+
+impl<T: Ord> FromIterator<T> for FischerHeun<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        FischerHeun::new(iter.into_iter().collect())
+    }
+}
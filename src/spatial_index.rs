@@ -0,0 +1,278 @@
+//! A common interface over the dynamic point indexes.
+//!
+//! [`SpatialIndex`] covers what most applications ask of a point index:
+//! insert and remove points, find nearest neighbors, and gather the points
+//! in a ball or a box. It is implemented by the orthtrees (and so by
+//! [`Quadtree`] and [`Octree`]), [`CompressedOrthtree`], [`KdTree`],
+//! [`RTree`] and [`SpatialHashGrid`], so code written against the trait can swap one
+//! structure for another as the data demands, and algorithms built on top
+//! of it work with any of them.
+//!
+//! Distances are Euclidean throughout. The trait trades the per-structure
+//! iterators for collected `Vec`s of handles so that it stays object safe;
+//! the inherent methods remain the faster path when the concrete type is
+//! known.
+//!
+//! The k-d tree and the orthtrees also offer a best-first approximate
+//! search that stops after a fixed number of node visits, reporting the
+//! result as an [`ApproxNearest`].
+//!
+//! [`Quadtree`]: crate::quadtree::Quadtree
+//! [`Octree`]: crate::octree::Octree
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::compressed_orthtree::{CompressedId, CompressedOrthtree};
+use crate::geom::Aabb;
+use crate::grid::{ItemId, SpatialHashGrid};
+use crate::kdtree::KdTree;
+use crate::orthtree::{Orthtree, OutOfBounds, Point, PointId};
+use crate::rtree::{EntryId, RTree};
+
+/// A dynamic index over points of type `P`.
+pub trait SpatialIndex<P> {
+    /// Handle to a stored point, valid until the point is removed.
+    type Id: Copy + Eq + Hash + Debug;
+    /// Data attached to each point.
+    type Data;
+
+    /// Number of stored points.
+    fn len(&self) -> usize;
+
+    /// Whether the index holds no points.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts a point, returning its handle, or an error if the index has
+    /// fixed bounds that exclude the point.
+    fn insert(&mut self, point: P, data: Self::Data) -> Result<Self::Id, OutOfBounds>;
+
+    /// Removes a point, returning it and its data.
+    fn remove(&mut self, id: Self::Id) -> Option<(P, Self::Data)>;
+
+    /// The point and data behind a handle.
+    fn get(&self, id: Self::Id) -> Option<(P, &Self::Data)>;
+
+    /// Up to `k` points nearest to `query`, closest first, with their
+    /// distances.
+    fn nearest(&self, query: &P, k: usize) -> Vec<(Self::Id, f64)>;
+
+    /// The point nearest to `query` and its distance.
+    fn nearest_one(&self, query: &P) -> Option<(Self::Id, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// The points within `radius` of `center`, in unspecified order.
+    fn within_radius(&self, center: &P, radius: f64) -> Vec<Self::Id>;
+
+    /// The points inside the closed box with corners `min` and `max`, in
+    /// unspecified order.
+    fn query_aabb(&self, min: &P, max: &P) -> Vec<Self::Id>;
+}
+
+/// The outcome of a best-first nearest-neighbor search with a visit budget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApproxNearest<I> {
+    /// The best points found, closest first, with their distances.
+    pub neighbors: Vec<(I, f64)>,
+    /// Every point closer than this was examined, so any neighbor found
+    /// within it is a true neighbor. Infinite when the whole index was
+    /// searched.
+    pub bound: f64,
+    /// Whether the neighbor distances match an exhaustive search: the
+    /// budget ran out, if at all, only after the bound passed the farthest
+    /// neighbor.
+    pub exact: bool,
+    /// Number of nodes visited.
+    pub visited: usize,
+}
+
+impl<P: Point<N>, D, const N: usize> SpatialIndex<P> for Orthtree<P, D, N> {
+    type Id = PointId;
+    type Data = D;
+
+    fn len(&self) -> usize {
+        Orthtree::len(self)
+    }
+
+    fn insert(&mut self, point: P, data: D) -> Result<PointId, OutOfBounds> {
+        Orthtree::insert(self, point, data)
+    }
+
+    fn remove(&mut self, id: PointId) -> Option<(P, D)> {
+        Orthtree::remove(self, id)
+    }
+
+    fn get(&self, id: PointId) -> Option<(P, &D)> {
+        Orthtree::get(self, id).map(|(p, d)| (*p, d))
+    }
+
+    fn nearest(&self, query: &P, k: usize) -> Vec<(PointId, f64)> {
+        Orthtree::nearest(self, *query, k)
+    }
+
+    fn within_radius(&self, center: &P, radius: f64) -> Vec<PointId> {
+        Orthtree::within_radius(self, *center, radius)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    fn query_aabb(&self, min: &P, max: &P) -> Vec<PointId> {
+        self.within_box(Aabb::new(min.coords(), max.coords()))
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+}
+
+impl<P: Point<N>, D, const N: usize> SpatialIndex<P> for CompressedOrthtree<P, D, N> {
+    type Id = CompressedId;
+    type Data = D;
+
+    fn len(&self) -> usize {
+        CompressedOrthtree::len(self)
+    }
+
+    fn insert(&mut self, point: P, data: D) -> Result<CompressedId, OutOfBounds> {
+        CompressedOrthtree::insert(self, point, data)
+    }
+
+    fn remove(&mut self, id: CompressedId) -> Option<(P, D)> {
+        CompressedOrthtree::remove(self, id)
+    }
+
+    fn get(&self, id: CompressedId) -> Option<(P, &D)> {
+        CompressedOrthtree::get(self, id).map(|(p, d)| (*p, d))
+    }
+
+    fn nearest(&self, query: &P, k: usize) -> Vec<(CompressedId, f64)> {
+        CompressedOrthtree::nearest(self, *query, k)
+    }
+
+    fn within_radius(&self, center: &P, radius: f64) -> Vec<CompressedId> {
+        CompressedOrthtree::within_radius(self, *center, radius)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    fn query_aabb(&self, min: &P, max: &P) -> Vec<CompressedId> {
+        self.within_box(Aabb::new(min.coords(), max.coords()))
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+}
+
+impl<T, const N: usize> SpatialIndex<[f64; N]> for KdTree<T, N> {
+    type Id = usize;
+    type Data = T;
+
+    fn len(&self) -> usize {
+        KdTree::len(self)
+    }
+
+    fn insert(&mut self, point: [f64; N], data: T) -> Result<usize, OutOfBounds> {
+        Ok(KdTree::insert(self, point, data))
+    }
+
+    fn remove(&mut self, id: usize) -> Option<([f64; N], T)> {
+        KdTree::remove(self, id)
+    }
+
+    fn get(&self, id: usize) -> Option<([f64; N], &T)> {
+        KdTree::get(self, id).map(|(p, d)| (*p, d))
+    }
+
+    fn nearest(&self, query: &[f64; N], k: usize) -> Vec<(usize, f64)> {
+        KdTree::nearest(self, query, k)
+    }
+
+    fn within_radius(&self, center: &[f64; N], radius: f64) -> Vec<usize> {
+        KdTree::within_radius(self, center, radius)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    fn query_aabb(&self, min: &[f64; N], max: &[f64; N]) -> Vec<usize> {
+        self.within_box(min, max).map(|(id, _, _)| id).collect()
+    }
+}
+
+/// Points are stored as degenerate boxes. Entries inserted as larger boxes
+/// through [`RTree::insert`] take part too: they report their center as
+/// their point, and distances are measured to the box.
+impl<T, const N: usize> SpatialIndex<[f64; N]> for RTree<T, N> {
+    type Id = EntryId;
+    type Data = T;
+
+    fn len(&self) -> usize {
+        RTree::len(self)
+    }
+
+    fn insert(&mut self, point: [f64; N], data: T) -> Result<EntryId, OutOfBounds> {
+        Ok(RTree::insert(self, Aabb::point(point), data))
+    }
+
+    fn remove(&mut self, id: EntryId) -> Option<([f64; N], T)> {
+        RTree::remove(self, id).map(|(b, d)| (b.center(), d))
+    }
+
+    fn get(&self, id: EntryId) -> Option<([f64; N], &T)> {
+        RTree::get(self, id).map(|(b, d)| (b.center(), d))
+    }
+
+    fn nearest(&self, query: &[f64; N], k: usize) -> Vec<(EntryId, f64)> {
+        RTree::nearest(self, query, k)
+    }
+
+    fn within_radius(&self, center: &[f64; N], radius: f64) -> Vec<EntryId> {
+        let reach = Aabb::new(center.map(|c| c - radius), center.map(|c| c + radius));
+        self.intersecting(reach)
+            .filter(|(_, b, _)| b.distance_squared(center) <= radius * radius)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    fn query_aabb(&self, min: &[f64; N], max: &[f64; N]) -> Vec<EntryId> {
+        self.intersecting(Aabb::new(*min, *max))
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+}
+
+impl<T, const N: usize> SpatialIndex<[f64; N]> for SpatialHashGrid<T, N> {
+    type Id = ItemId;
+    type Data = T;
+
+    fn len(&self) -> usize {
+        SpatialHashGrid::len(self)
+    }
+
+    fn insert(&mut self, point: [f64; N], data: T) -> Result<ItemId, OutOfBounds> {
+        Ok(SpatialHashGrid::insert(self, point, data))
+    }
+
+    fn remove(&mut self, id: ItemId) -> Option<([f64; N], T)> {
+        SpatialHashGrid::remove(self, id)
+    }
+
+    fn get(&self, id: ItemId) -> Option<([f64; N], &T)> {
+        SpatialHashGrid::get(self, id).map(|(p, d)| (*p, d))
+    }
+
+    fn nearest(&self, query: &[f64; N], k: usize) -> Vec<(ItemId, f64)> {
+        SpatialHashGrid::nearest(self, query, k)
+    }
+
+    fn within_radius(&self, center: &[f64; N], radius: f64) -> Vec<ItemId> {
+        SpatialHashGrid::within_radius(self, *center, radius)
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+
+    fn query_aabb(&self, min: &[f64; N], max: &[f64; N]) -> Vec<ItemId> {
+        SpatialHashGrid::query_aabb(self, Aabb::new(*min, *max))
+            .map(|(id, _, _)| id)
+            .collect()
+    }
+}
@@ -0,0 +1,421 @@
+//! Hierarchical cells on the unit sphere, in the style of Google's S2.
+//!
+//! The sphere is projected onto the six faces of a circumscribed cube, and
+//! each face is divided by a quadtree down to [`MAX_LEVEL`], where cells are
+//! about a centimetre across on the Earth. A quadratic correction applied to
+//! the face coordinates keeps cells of one level within a small factor of
+//! each other in area, unlike a plain cube projection. Cell edges are
+//! straight lines on the cube, and therefore great-circle arcs on the
+//! sphere.
+//!
+//! A [`CellId`] packs the face, the cell's path down the quadtree, and a
+//! trailing marker bit into a `u64`, following S2's layout. A cell's
+//! descendants occupy the contiguous id range
+//! [`range_min`](CellId::range_min)`..=`[`range_max`](CellId::range_max), so
+//! sorted cell ids make a spatial index with prefix-style range scans.
+//! Unlike S2, children are numbered in Morton order rather than along a
+//! Hilbert curve, so ids are not interoperable with the S2 library.
+
+use crate::morton;
+
+/// Deepest cell level.
+pub const MAX_LEVEL: u8 = 30;
+
+const POS_BITS: u32 = 2 * MAX_LEVEL as u32 + 1;
+const LEAF_CELLS: u64 = 1 << MAX_LEVEL;
+
+/// Converts degrees of latitude and longitude to a unit vector.
+pub fn lat_lon_to_point(lat: f64, lon: f64) -> [f64; 3] {
+    let (lat, lon) = (lat.to_radians(), lon.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+/// Converts a nonzero vector to degrees of latitude and longitude.
+pub fn point_to_lat_lon(p: &[f64; 3]) -> (f64, f64) {
+    let lat = p[2].atan2((p[0] * p[0] + p[1] * p[1]).sqrt());
+    let lon = p[1].atan2(p[0]);
+    (lat.to_degrees(), lon.to_degrees())
+}
+
+/// A spherical cap: the points within an angle of a center direction.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cap {
+    center: [f64; 3],
+    radius: f64,
+}
+
+impl Cap {
+    /// Creates a cap around `center`, which need not be normalized, with an
+    /// angular radius in radians.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `center` is zero or not finite, or `radius` is negative.
+    pub fn new(center: [f64; 3], radius: f64) -> Self {
+        let n = norm(&center);
+        assert!(
+            n > 0.0 && n.is_finite(),
+            "cap center must be a nonzero vector"
+        );
+        assert!(radius >= 0.0, "cap radius must be non-negative");
+        Cap {
+            center: center.map(|c| c / n),
+            radius,
+        }
+    }
+
+    /// Creates a cap from a center and radius in degrees.
+    pub fn from_degrees(lat: f64, lon: f64, radius: f64) -> Self {
+        Self::new(lat_lon_to_point(lat, lon), radius.to_radians())
+    }
+
+    /// The unit center vector.
+    pub fn center(&self) -> [f64; 3] {
+        self.center
+    }
+
+    /// The angular radius in radians.
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Whether the cap contains the direction `p`.
+    pub fn contains(&self, p: &[f64; 3]) -> bool {
+        angle(&self.center, p) <= self.radius
+    }
+
+    /// Whether the cap shares any point with `cell`.
+    pub fn intersects_cell(&self, cell: CellId) -> bool {
+        let v = cell.vertices();
+        if v.iter().any(|p| self.contains(p)) || cell.contains_point(&self.center) {
+            return true;
+        }
+        (0..4).any(|k| arc_distance(&self.center, &v[k], &v[(k + 1) % 4]) <= self.radius)
+    }
+
+    /// Whether `cell` lies entirely inside the cap.
+    ///
+    /// Exact for caps of at most a hemisphere, where the cap is convex;
+    /// larger caps only report containment of cells they hold comfortably.
+    pub fn contains_cell(&self, cell: CellId) -> bool {
+        let v = cell.vertices();
+        if self.radius <= std::f64::consts::FRAC_PI_2 {
+            return v.iter().all(|p| self.contains(p));
+        }
+        // The complement is convex: the cell is inside unless it reaches
+        // the complement's cap.
+        let anti = Cap {
+            center: self.center.map(|c| -c),
+            radius: std::f64::consts::PI - self.radius,
+        };
+        !anti.intersects_cell(cell)
+    }
+}
+
+/// Identifies a cell of the sphere at some level from 0 (a whole cube face)
+/// to [`MAX_LEVEL`].
+///
+/// Ids order first by face and then along the per-face Morton curve, with
+/// every cell's id in the middle of its descendants' ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CellId(u64);
+
+impl CellId {
+    /// The level-0 cell covering a whole cube face.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `face` is not in `0..6`.
+    pub fn from_face(face: u8) -> Self {
+        assert!(face < 6, "face must be in 0..6");
+        CellId((face as u64) << POS_BITS | 1 << (POS_BITS - 1))
+    }
+
+    /// The leaf cell containing the direction `p`, which must be nonzero.
+    pub fn from_point(p: &[f64; 3]) -> Self {
+        let (face, u, v) = xyz_to_face_uv(p);
+        let (i, j) = (st_to_ij(uv_to_st(u)), st_to_ij(uv_to_st(v)));
+        Self::from_face_ij(face, i, j)
+    }
+
+    /// The leaf cell containing a latitude and longitude in degrees.
+    pub fn from_lat_lon(lat: f64, lon: f64) -> Self {
+        Self::from_point(&lat_lon_to_point(lat, lon))
+    }
+
+    /// Rebuilds a cell from [`CellId::raw`], checking that it is well formed.
+    pub fn from_raw(id: u64) -> Option<Self> {
+        let tz = id.trailing_zeros();
+        let valid = id >> POS_BITS < 6 && tz.is_multiple_of(2) && tz < POS_BITS;
+        valid.then_some(CellId(id))
+    }
+
+    /// The packed id.
+    pub fn raw(&self) -> u64 {
+        self.0
+    }
+
+    /// The cube face, in `0..6`.
+    pub fn face(&self) -> u8 {
+        (self.0 >> POS_BITS) as u8
+    }
+
+    /// The level, from 0 for a face to [`MAX_LEVEL`] for a leaf.
+    pub fn level(&self) -> u8 {
+        MAX_LEVEL - (self.0.trailing_zeros() / 2) as u8
+    }
+
+    /// Whether this is a cell of [`MAX_LEVEL`].
+    pub fn is_leaf(&self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// The enclosing cell one level up, if any.
+    pub fn parent(&self) -> Option<CellId> {
+        let level = self.level();
+        (level > 0).then(|| self.parent_at(level - 1))
+    }
+
+    /// The enclosing cell at `level`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is deeper than this cell.
+    pub fn parent_at(&self, level: u8) -> CellId {
+        assert!(level <= self.level(), "parent level below the cell");
+        let lsb = lsb_for(level);
+        CellId((self.0 & lsb.wrapping_neg()) | lsb)
+    }
+
+    /// The four cells one level down, in Morton order; `None` for a leaf.
+    pub fn children(&self) -> Option<[CellId; 4]> {
+        if self.is_leaf() {
+            return None;
+        }
+        let lsb = self.lsb();
+        let child = lsb >> 2;
+        Some(std::array::from_fn(|k| {
+            CellId(self.0 - lsb + (2 * k as u64 + 1) * child)
+        }))
+    }
+
+    /// The smallest leaf id inside this cell.
+    pub fn range_min(&self) -> CellId {
+        CellId(self.0 - (self.lsb() - 1))
+    }
+
+    /// The largest leaf id inside this cell.
+    pub fn range_max(&self) -> CellId {
+        CellId(self.0 + (self.lsb() - 1))
+    }
+
+    /// Whether `other` is this cell or one of its descendants.
+    pub fn contains(&self, other: &CellId) -> bool {
+        self.range_min() <= *other && *other <= self.range_max()
+    }
+
+    /// Whether one of the cells contains the other.
+    pub fn intersects(&self, other: &CellId) -> bool {
+        other.range_min() <= self.range_max() && other.range_max() >= self.range_min()
+    }
+
+    /// Whether the direction `p` falls inside this cell.
+    pub fn contains_point(&self, p: &[f64; 3]) -> bool {
+        self.contains(&CellId::from_point(p))
+    }
+
+    /// The unit vector at the cell's center.
+    pub fn center(&self) -> [f64; 3] {
+        let (face, i, j, size) = self.face_ij();
+        let s = (i as f64 + size as f64 / 2.0) / LEAF_CELLS as f64;
+        let t = (j as f64 + size as f64 / 2.0) / LEAF_CELLS as f64;
+        normalize(face_uv_to_xyz(face, st_to_uv(s), st_to_uv(t)))
+    }
+
+    /// The cell's center in degrees of latitude and longitude.
+    pub fn center_lat_lon(&self) -> (f64, f64) {
+        point_to_lat_lon(&self.center())
+    }
+
+    /// The unit vectors at the cell's corners, counterclockwise as seen
+    /// from outside the sphere.
+    pub fn vertices(&self) -> [[f64; 3]; 4] {
+        let (face, i, j, size) = self.face_ij();
+        [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(di, dj)| {
+            let s = (i + di * size) as f64 / LEAF_CELLS as f64;
+            let t = (j + dj * size) as f64 / LEAF_CELLS as f64;
+            normalize(face_uv_to_xyz(face, st_to_uv(s), st_to_uv(t)))
+        })
+    }
+
+    /// The four cells of the same level sharing an edge with this one,
+    /// in the order below, right, above, left on its face. Neighbors across
+    /// a cube edge lie on the adjacent face.
+    pub fn edge_neighbors(&self) -> [CellId; 4] {
+        let level = self.level();
+        let (face, i, j, size) = self.face_ij();
+        let (i, j, size) = (i as i64, j as i64, size as i64);
+        let (ci, cj) = (i + size / 2, j + size / 2);
+        [(ci, j - 1), (i + size, cj), (ci, j + size), (i - 1, cj)]
+            .map(|(ni, nj)| Self::from_face_ij_wrap(face, ni, nj).parent_at(level))
+    }
+
+    /// The lowest set bit, which marks the level.
+    fn lsb(&self) -> u64 {
+        self.0 & self.0.wrapping_neg()
+    }
+
+    /// The face, the leaf coordinates of the cell's lower corner, and its
+    /// side in leaf cells.
+    fn face_ij(&self) -> (u8, u64, u64, u64) {
+        let size = 1u64 << (MAX_LEVEL - self.level());
+        let pos = (self.range_min().0 & ((1 << POS_BITS) - 1)) >> 1;
+        let (i, j) = morton::decode2(pos);
+        (self.face(), i as u64, j as u64, size)
+    }
+
+    fn from_face_ij(face: u8, i: u64, j: u64) -> CellId {
+        let pos = morton::encode2(i as u32, j as u32);
+        CellId((face as u64) << POS_BITS | pos << 1 | 1)
+    }
+
+    /// The leaf at leaf coordinates `(i, j)` of `face`, which may lie one
+    /// cell beyond the face's edge, in which case the leaf is found on the
+    /// neighboring face.
+    fn from_face_ij_wrap(face: u8, i: i64, j: i64) -> CellId {
+        let max = LEAF_CELLS as i64;
+        if (0..max).contains(&i) && (0..max).contains(&j) {
+            return Self::from_face_ij(face, i as u64, j as u64);
+        }
+        let i = i.clamp(-1, max);
+        let j = j.clamp(-1, max);
+        let s = (i as f64 + 0.5) / LEAF_CELLS as f64;
+        let t = (j as f64 + 0.5) / LEAF_CELLS as f64;
+        Self::from_point(&face_uv_to_xyz(face, st_to_uv(s), st_to_uv(t)))
+    }
+}
+
+/// Covers `cap` with cells no deeper than `max_level`.
+///
+/// Cells lying entirely inside the cap are kept as coarse as possible and
+/// cells on its boundary are refined down to `max_level`, so the union of
+/// the result contains the cap. The cells are disjoint and returned in
+/// order.
+///
+/// # Panics
+///
+/// Panics if `max_level` exceeds [`MAX_LEVEL`].
+pub fn covering(cap: &Cap, max_level: u8) -> Vec<CellId> {
+    assert!(
+        max_level <= MAX_LEVEL,
+        "max level must be at most {MAX_LEVEL}"
+    );
+    let mut out = Vec::new();
+    let mut stack: Vec<CellId> = (0..6).rev().map(CellId::from_face).collect();
+    while let Some(cell) = stack.pop() {
+        if !cap.intersects_cell(cell) {
+            continue;
+        }
+        if cell.level() == max_level || cap.contains_cell(cell) {
+            out.push(cell);
+            continue;
+        }
+        let children = cell.children().expect("non-leaf below max level");
+        stack.extend(children.into_iter().rev());
+    }
+    out
+}
+
+fn lsb_for(level: u8) -> u64 {
+    1 << (2 * (MAX_LEVEL - level) as u32)
+}
+
+/// Picks the face whose axis dominates `p` and projects onto it.
+fn xyz_to_face_uv(p: &[f64; 3]) -> (u8, f64, f64) {
+    let axis = (0..3)
+        .max_by(|&a, &b| p[a].abs().total_cmp(&p[b].abs()))
+        .expect("three axes");
+    let face = axis as u8 + if p[axis] < 0.0 { 3 } else { 0 };
+    let [x, y, z] = *p;
+    let (u, v) = match face {
+        0 => (y / x, z / x),
+        1 => (-x / y, z / y),
+        2 => (-x / z, -y / z),
+        3 => (z / x, y / x),
+        4 => (z / y, -x / y),
+        _ => (-y / z, -x / z),
+    };
+    (face, u, v)
+}
+
+fn face_uv_to_xyz(face: u8, u: f64, v: f64) -> [f64; 3] {
+    match face {
+        0 => [1.0, u, v],
+        1 => [-u, 1.0, v],
+        2 => [-u, -v, 1.0],
+        3 => [-1.0, -v, -u],
+        4 => [v, -1.0, -u],
+        _ => [v, u, -1.0],
+    }
+}
+
+/// S2's quadratic transform from cube coordinates in `[-1, 1]` to cell
+/// coordinates in `[0, 1]`, which evens out cell areas.
+fn uv_to_st(u: f64) -> f64 {
+    if u >= 0.0 {
+        0.5 * (1.0 + 3.0 * u).sqrt()
+    } else {
+        1.0 - 0.5 * (1.0 - 3.0 * u).sqrt()
+    }
+}
+
+fn st_to_uv(s: f64) -> f64 {
+    if s >= 0.5 {
+        (4.0 * s * s - 1.0) / 3.0
+    } else {
+        (1.0 - 4.0 * (1.0 - s) * (1.0 - s)) / 3.0
+    }
+}
+
+fn st_to_ij(s: f64) -> u64 {
+    ((s * LEAF_CELLS as f64).max(0.0) as u64).min(LEAF_CELLS - 1)
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn norm(a: &[f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: [f64; 3]) -> [f64; 3] {
+    let n = norm(&a);
+    a.map(|c| c / n)
+}
+
+/// The angle between two directions, robust for nearly parallel vectors.
+fn angle(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    norm(&cross(a, b)).atan2(dot(a, b))
+}
+
+/// The angle from unit vector `p` to the shorter great-circle arc `a`-`b`.
+fn arc_distance(p: &[f64; 3], a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    let n = cross(a, b);
+    // `p` projects into the arc's interior when it lies on the inner side
+    // of both planes through the normal and an endpoint.
+    if dot(&cross(&n, a), p) > 0.0 && dot(&cross(b, &n), p) > 0.0 {
+        let s = (dot(p, &n) / norm(&n)).abs().min(1.0);
+        s.asin()
+    } else {
+        angle(p, a).min(angle(p, b))
+    }
+}
@@ -0,0 +1,418 @@
+//! An ordered map stored as a splay tree.
+//!
+//! Sleator and Tarjan's splay tree keeps no balance information at all.
+//! Instead, every access rotates the node it reached up to the root, in
+//! pairs of rotations that also roughly halve the depth of the nodes along
+//! the way. Any single operation can take `O(n)`, but any sequence of `m`
+//! operations takes `O((m + n) log n)`, so each is `O(log n)` amortized.
+//!
+//! The point is what it does beyond that bound. Recently used keys sit near
+//! the root, so an access costs about the log of how many distinct keys
+//! were touched since the last access to it (the working-set bound).
+//! Repeatedly used keys are cheap, and so are scans in key order. For
+//! skewed access patterns that beats any tree balanced for the worst case.
+//!
+//! Because lookups restructure the tree, [`get`](SplayTree::get) takes
+//! `&mut self`. [`splay`](SplayTree::splay) exposes the restructuring
+//! directly, and [`peek`](SplayTree::peek) looks a key up without it.
+//! Nodes live in an arena with parent links, and splaying works bottom-up.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    left: u32,
+    right: u32,
+    parent: u32,
+}
+
+/// An ordered map from keys of type `K` to values of type `V`, restructured
+/// on every access.
+#[derive(Clone, Debug)]
+pub struct SplayTree<K, V> {
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<u32>,
+    root: u32,
+    len: usize,
+}
+
+impl<K: Ord, V> Default for SplayTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> SplayTree<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        SplayTree {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            root: NONE,
+            len: 0,
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NONE;
+        self.len = 0;
+    }
+
+    /// The entry at the root: the one most recently accessed.
+    pub fn root(&self) -> Option<(&K, &V)> {
+        self.entry(self.root)
+    }
+
+    /// Rotates the entry under `key` to the root, or if there is none, the
+    /// last entry on the search path for it: the key's predecessor or
+    /// successor. Returns whether `key` was found.
+    pub fn splay<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (n, found) = self.search(key);
+        self.splay_node(n);
+        found
+    }
+
+    /// Returns the value stored under `key`, splaying it to the root.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !self.splay(key) {
+            return None;
+        }
+        Some(&self.node(self.root).value)
+    }
+
+    /// Returns the value stored under `key` mutably, splaying it to the
+    /// root.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !self.splay(key) {
+            return None;
+        }
+        let root = self.root;
+        Some(&mut self.node_mut(root).value)
+    }
+
+    /// Returns the value stored under `key` without restructuring the tree,
+    /// so without the amortized guarantee.
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.search(key) {
+            (n, true) => Some(&self.node(n).value),
+            _ => None,
+        }
+    }
+
+    /// Whether an entry is stored under `key`, without restructuring the
+    /// tree.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.search(key).1
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.entry(self.extreme(self.root, false))
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.entry(self.extreme(self.root, true))
+    }
+
+    /// Inserts an entry, splaying it to the root, and returns the value it
+    /// replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if self.root != NONE && self.splay(&key) {
+            let root = self.root;
+            return Some(std::mem::replace(&mut self.node_mut(root).value, value));
+        }
+        let mut node = Node {
+            key,
+            value,
+            left: NONE,
+            right: NONE,
+            parent: NONE,
+        };
+        // The old root is the new key's neighbor, so it and one of its
+        // subtrees hang on one side.
+        let old = self.root;
+        if old != NONE {
+            let root = self.node_mut(old);
+            if node.key < root.key {
+                node.left = std::mem::replace(&mut root.left, NONE);
+                node.right = old;
+            } else {
+                node.right = std::mem::replace(&mut root.right, NONE);
+                node.left = old;
+            }
+        }
+        let (left, right) = (node.left, node.right);
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id as usize] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                (self.nodes.len() - 1) as u32
+            }
+        };
+        self.set_parent(left, id);
+        self.set_parent(right, id);
+        self.root = id;
+        self.len += 1;
+        None
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if self.root == NONE || !self.splay(key) {
+            return None;
+        }
+        let node = self.nodes[self.root as usize].take().expect("live node");
+        self.free.push(self.root);
+        self.len -= 1;
+        self.set_parent(node.left, NONE);
+        self.set_parent(node.right, NONE);
+        if node.left == NONE {
+            self.root = node.right;
+        } else {
+            // The largest key on the left, splayed up, has no right child.
+            self.root = node.left;
+            let max = self.extreme(node.left, true);
+            self.splay_node(max);
+            self.node_mut(max).right = node.right;
+            self.set_parent(node.right, max);
+        }
+        Some((node.key, node.value))
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Iterates over the entries in key order, without restructuring the
+    /// tree.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order,
+    /// without restructuring the tree.
+    pub fn range<'a, Q, R>(&'a self, range: R) -> impl Iterator<Item = (&'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<Q> + 'a,
+    {
+        let mut n = self.lower_bound(range.start_bound());
+        std::iter::from_fn(move || {
+            let (k, v) = self.entry(n)?;
+            let inside = match range.end_bound() {
+                Bound::Included(b) => k.borrow() <= b,
+                Bound::Excluded(b) => k.borrow() < b,
+                Bound::Unbounded => true,
+            };
+            if !inside {
+                return None;
+            }
+            n = self.successor(n);
+            Some((k, v))
+        })
+    }
+
+    /// The node holding `key`, or the last node on its search path; and
+    /// whether it was found.
+    fn search<Q>(&self, key: &Q) -> (u32, bool)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (mut n, mut last) = (self.root, NONE);
+        while n != NONE {
+            last = n;
+            let node = self.node(n);
+            n = match key.cmp(node.key.borrow()) {
+                Ordering::Less => node.left,
+                Ordering::Greater => node.right,
+                Ordering::Equal => return (n, true),
+            };
+        }
+        (last, false)
+    }
+
+    /// The first node whose key is above `bound`.
+    fn lower_bound<Q>(&self, bound: Bound<&Q>) -> u32
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (mut n, mut best) = (self.root, NONE);
+        while n != NONE {
+            let node = self.node(n);
+            let above = match bound {
+                Bound::Included(b) => node.key.borrow() >= b,
+                Bound::Excluded(b) => node.key.borrow() > b,
+                Bound::Unbounded => true,
+            };
+            if above {
+                best = n;
+                n = node.left;
+            } else {
+                n = node.right;
+            }
+        }
+        best
+    }
+
+    /// The in-order successor of node `n`.
+    fn successor(&self, mut n: u32) -> u32 {
+        let right = self.node(n).right;
+        if right != NONE {
+            return self.extreme(right, false);
+        }
+        loop {
+            let p = self.node(n).parent;
+            if p == NONE || self.node(p).left == n {
+                return p;
+            }
+            n = p;
+        }
+    }
+
+    /// The leftmost node under `n`, or with `rightmost` set, the rightmost.
+    fn extreme(&self, mut n: u32, rightmost: bool) -> u32 {
+        if n == NONE {
+            return NONE;
+        }
+        loop {
+            let node = self.node(n);
+            let next = if rightmost { node.right } else { node.left };
+            if next == NONE {
+                return n;
+            }
+            n = next;
+        }
+    }
+
+    /// Rotates node `x` to the root by zig-zig and zig-zag steps.
+    fn splay_node(&mut self, x: u32) {
+        if x == NONE {
+            return;
+        }
+        loop {
+            let p = self.node(x).parent;
+            if p == NONE {
+                break;
+            }
+            let g = self.node(p).parent;
+            if g != NONE {
+                let straight = (self.node(p).left == x) == (self.node(g).left == p);
+                self.rotate(if straight { p } else { x });
+            }
+            self.rotate(x);
+        }
+        self.root = x;
+    }
+
+    /// Rotates node `x` above its parent.
+    fn rotate(&mut self, x: u32) {
+        let p = self.node(x).parent;
+        let g = self.node(p).parent;
+        let moved = if self.node(p).left == x {
+            let moved = self.node(x).right;
+            self.node_mut(p).left = moved;
+            self.node_mut(x).right = p;
+            moved
+        } else {
+            let moved = self.node(x).left;
+            self.node_mut(p).right = moved;
+            self.node_mut(x).left = p;
+            moved
+        };
+        self.set_parent(moved, p);
+        self.node_mut(p).parent = x;
+        self.node_mut(x).parent = g;
+        if g != NONE {
+            let gn = self.node_mut(g);
+            if gn.left == p {
+                gn.left = x;
+            } else {
+                gn.right = x;
+            }
+        }
+    }
+
+    fn set_parent(&mut self, n: u32, parent: u32) {
+        if n != NONE {
+            self.node_mut(n).parent = parent;
+        }
+    }
+
+    fn entry(&self, n: u32) -> Option<(&K, &V)> {
+        let node = self.nodes.get(n as usize)?.as_ref()?;
+        Some((&node.key, &node.value))
+    }
+
+    fn node(&self, n: u32) -> &Node<K, V> {
+        self.nodes[n as usize].as_ref().expect("live node")
+    }
+
+    fn node_mut(&mut self, n: u32) -> &mut Node<K, V> {
+        self.nodes[n as usize].as_mut().expect("live node")
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for SplayTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = SplayTree::new();
+        for (k, v) in iter {
+            tree.insert(k, v);
+        }
+        tree
+    }
+}
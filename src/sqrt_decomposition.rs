@@ -0,0 +1,275 @@
+//! Square-root decomposition of a sequence into blocks with summaries.
+//!
+//! The sequence is cut into blocks of about `√n` elements, and each block
+//! keeps the [`Monoid`] combination of its elements and, for range
+//! updates, one pending [`Action`] that applies to all of them. A range
+//! query or update touches at most two blocks partially, element by
+//! element, and every block between them whole, through its summary, so
+//! both cost `O(√n)`; a point update rebuilds one block's summary in
+//! `O(√n)`.
+//!
+//! That is asymptotically worse than the [`SegmentTree`]'s `O(log n)`,
+//! but the work is a few linear scans over contiguous memory rather than
+//! a walk down a tree of scattered nodes, so for sequences of up to some
+//! hundreds of thousands of elements the decomposition is often as fast
+//! or faster, and its layout is easy to reason about. It takes the same
+//! monoids and actions as the segment tree, and like it never pushes
+//! pending updates during a query, so reads take `&self`: a pending
+//! update is applied to the combination of the elements a query reads
+//! from its block, which the action laws allow.
+//!
+//! The block size can be chosen explicitly, to favour queries with larger
+//! blocks or point updates with smaller ones.
+//!
+//! [`Monoid`]: crate::algebra::Monoid
+//! [`Action`]: crate::algebra::Action
+//! [`SegmentTree`]: crate::segment_tree::SegmentTree
+
+use std::ops::RangeBounds;
+
+use crate::algebra::{Action, Monoid};
+use crate::util::bounds;
+
+/// A sequence of `M::Value` in blocks, with range updates of type `U`.
+#[derive(Clone, Debug)]
+pub struct BlockDecomposition<M: Monoid, U = ()> {
+    monoid: M,
+    values: Vec<M::Value>,
+    block: usize,
+    /// The combination of each block's elements, with its pending update
+    /// applied.
+    sums: Vec<M::Value>,
+    /// An update applied to each block's summary but not yet to its
+    /// elements.
+    lazy: Vec<Option<U>>,
+}
+
+impl<M: Monoid, U: Action<M>> BlockDecomposition<M, U> {
+    /// Builds the blocks over `values` in `O(n)`, about `√n` elements to a
+    /// block.
+    pub fn new(monoid: M, values: Vec<M::Value>) -> Self {
+        let block = values.len().isqrt().max(1);
+        Self::with_block_size(monoid, values, block)
+    }
+
+    /// Builds the blocks over `values` in `O(n)`, `block` elements to a
+    /// block.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is zero.
+    pub fn with_block_size(monoid: M, values: Vec<M::Value>, block: usize) -> Self {
+        assert!(block > 0, "blocks must hold at least one element");
+        let sums = values
+            .chunks(block)
+            .map(|chunk| fold(&monoid, chunk))
+            .collect::<Vec<_>>();
+        let lazy = vec![None; sums.len()];
+        BlockDecomposition {
+            monoid,
+            values,
+            block,
+            sums,
+            lazy,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The monoid the blocks combine with.
+    pub fn monoid(&self) -> &M {
+        &self.monoid
+    }
+
+    /// Number of elements per block; the last block may hold fewer.
+    pub fn block_size(&self) -> usize {
+        self.block
+    }
+
+    /// The combination of the elements in `range`, in `O(√n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> M::Value {
+        let (lo, hi) = bounds(range, self.len());
+        let mut acc = self.monoid.identity();
+        let mut i = lo;
+        while i < hi {
+            let b = i / self.block;
+            let (start, end) = self.span(b);
+            if i == start && end <= hi {
+                acc = self.monoid.combine(&acc, &self.sums[b]);
+                i = end;
+            } else {
+                let stop = end.min(hi);
+                let part = self.partial(b, i, stop);
+                acc = self.monoid.combine(&acc, &part);
+                i = stop;
+            }
+        }
+        acc
+    }
+
+    /// The combination of every element, in `O(√n)`.
+    pub fn total(&self) -> M::Value {
+        fold(&self.monoid, &self.sums)
+    }
+
+    /// The element at `index`, in `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> M::Value {
+        let value = &self.values[index];
+        match &self.lazy[index / self.block] {
+            Some(u) => u.apply(&self.monoid, value, 1),
+            None => value.clone(),
+        }
+    }
+
+    /// Replaces the element at `index`, in `O(√n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: M::Value) {
+        assert!(
+            index < self.len(),
+            "index {index} out of bounds for length {}",
+            self.len()
+        );
+        let b = index / self.block;
+        self.push(b);
+        self.values[index] = value;
+        self.pull(b);
+    }
+
+    /// Applies `update` to every element in `range`, in `O(√n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds.
+    pub fn update(&mut self, range: impl RangeBounds<usize>, update: U) {
+        let (lo, hi) = bounds(range, self.len());
+        let mut i = lo;
+        while i < hi {
+            let b = i / self.block;
+            let (start, end) = self.span(b);
+            if i == start && end <= hi {
+                self.sums[b] = update.apply(&self.monoid, &self.sums[b], end - start);
+                self.lazy[b] = Some(match &self.lazy[b] {
+                    Some(earlier) => update.compose(earlier),
+                    None => update.clone(),
+                });
+                i = end;
+            } else {
+                let stop = end.min(hi);
+                self.push(b);
+                for v in &mut self.values[i..stop] {
+                    *v = update.apply(&self.monoid, v, 1);
+                }
+                self.pull(b);
+                i = stop;
+            }
+        }
+    }
+
+    /// Finds the largest `end` such that `pred` holds for the combination
+    /// of `start..end`, assuming `pred` is monotone: true for the empty
+    /// range and, once false, false for every longer range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start > len`.
+    pub fn max_right(&self, start: usize, pred: impl Fn(&M::Value) -> bool) -> usize {
+        assert!(
+            start <= self.len(),
+            "start {start} out of bounds for length {}",
+            self.len()
+        );
+        let mut acc = self.monoid.identity();
+        let mut i = start;
+        while i < self.len() {
+            let b = i / self.block;
+            let (first, end) = self.span(b);
+            if i == first {
+                let next = self.monoid.combine(&acc, &self.sums[b]);
+                if pred(&next) {
+                    acc = next;
+                    i = end;
+                    continue;
+                }
+            }
+            // The answer lies in this block: step through its elements.
+            while i < end {
+                let next = self.monoid.combine(&acc, &self.get(i));
+                if !pred(&next) {
+                    return i;
+                }
+                acc = next;
+                i += 1;
+            }
+        }
+        self.len()
+    }
+
+    /// Collects the current elements.
+    pub fn to_vec(&self) -> Vec<M::Value> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+
+    /// The positions `start..end` of block `b`.
+    fn span(&self, b: usize) -> (usize, usize) {
+        let start = b * self.block;
+        (start, (start + self.block).min(self.len()))
+    }
+
+    /// The combination of `lo..hi`, within block `b`, with the block's
+    /// pending update applied.
+    fn partial(&self, b: usize, lo: usize, hi: usize) -> M::Value {
+        let part = fold(&self.monoid, &self.values[lo..hi]);
+        match &self.lazy[b] {
+            Some(u) => u.apply(&self.monoid, &part, hi - lo),
+            None => part,
+        }
+    }
+
+    /// Applies block `b`'s pending update to its elements.
+    fn push(&mut self, b: usize) {
+        if let Some(u) = self.lazy[b].take() {
+            let (start, end) = self.span(b);
+            for v in &mut self.values[start..end] {
+                *v = u.apply(&self.monoid, v, 1);
+            }
+        }
+    }
+
+    /// Recomputes block `b`'s summary from its elements, which must have no
+    /// pending update.
+    fn pull(&mut self, b: usize) {
+        let (start, end) = self.span(b);
+        self.sums[b] = fold(&self.monoid, &self.values[start..end]);
+    }
+}
+
+impl<M: Monoid + Default, U: Action<M>> FromIterator<M::Value> for BlockDecomposition<M, U> {
+    fn from_iter<I: IntoIterator<Item = M::Value>>(iter: I) -> Self {
+        BlockDecomposition::new(M::default(), iter.into_iter().collect())
+    }
+}
+
+fn fold<M: Monoid>(monoid: &M, values: &[M::Value]) -> M::Value {
+    values
+        .iter()
+        .fold(monoid.identity(), |acc, v| monoid.combine(&acc, v))
+}
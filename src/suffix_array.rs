@@ -0,0 +1,244 @@
+//! A suffix array with longest-common-prefix information.
+//!
+//! The suffix array of a text lists the starting positions of all its
+//! suffixes in lexicographic order. Every substring is a prefix of some
+//! suffix, and the suffixes starting with a given pattern form one
+//! contiguous run of the array. Two binary searches find that run in
+//! `O(m log n)` for a pattern of length `m`. The structure costs four bytes
+//! per text byte alongside the text, a fraction of a suffix tree.
+//!
+//! Construction uses Nong, Zhang and Chan's SA-IS, which runs in `O(n)`.
+//! It classifies each suffix by whether it sorts before or after its
+//! successor, sorts a sample of them recursively, and induces the order
+//! of the rest from the sample. Kasai's algorithm then fills in the LCP
+//! array, the length of the prefix each suffix shares with the one before
+//! it in the order, also in `O(n)`. The LCP array answers questions about
+//! repeats, such as [`longest_repeated`](SuffixArray::longest_repeated).
+
+use std::ops::Range;
+
+const NONE: usize = usize::MAX;
+
+/// The suffix array and LCP array of a byte string.
+#[derive(Clone, Debug)]
+pub struct SuffixArray {
+    text: Vec<u8>,
+    suffixes: Vec<u32>,
+    lcp: Vec<u32>,
+}
+
+impl SuffixArray {
+    /// Builds the suffix and LCP arrays of `text` in `O(n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `text` is `u32::MAX` bytes or longer.
+    pub fn build(text: &[u8]) -> Self {
+        assert!(text.len() < u32::MAX as usize, "text is too long");
+        let s: Vec<usize> = text.iter().map(|&b| b as usize).collect();
+        let sa = sa_is(&s, 255);
+        let lcp = kasai(text, &sa);
+        SuffixArray {
+            text: text.to_vec(),
+            suffixes: sa.into_iter().map(|i| i as u32).collect(),
+            lcp,
+        }
+    }
+
+    /// The indexed text.
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+
+    /// Length of the text.
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    /// Whether the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The starting positions of the suffixes, in lexicographic order.
+    pub fn suffixes(&self) -> &[u32] {
+        &self.suffixes
+    }
+
+    /// For each suffix in order, the length of the prefix it shares with
+    /// the one before it; zero for the first.
+    pub fn lcp(&self) -> &[u32] {
+        &self.lcp
+    }
+
+    /// The run of [`suffixes`](Self::suffixes) that start with `pattern`.
+    pub fn range(&self, pattern: &[u8]) -> Range<usize> {
+        let prefix = |i: u32| {
+            let s = &self.text[i as usize..];
+            &s[..s.len().min(pattern.len())]
+        };
+        let start = self.suffixes.partition_point(|&i| prefix(i) < pattern);
+        let end = start + self.suffixes[start..].partition_point(|&i| prefix(i) == pattern);
+        start..end
+    }
+
+    /// The positions where `pattern` occurs, in the order of their suffixes.
+    pub fn positions(&self, pattern: &[u8]) -> &[u32] {
+        &self.suffixes[self.range(pattern)]
+    }
+
+    /// The number of occurrences of `pattern`, overlapping ones included.
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        self.range(pattern).len()
+    }
+
+    /// Whether `pattern` occurs in the text.
+    pub fn contains(&self, pattern: &[u8]) -> bool {
+        !self.range(pattern).is_empty()
+    }
+
+    /// A longest substring occurring at least twice, or `None` if no byte
+    /// repeats.
+    pub fn longest_repeated(&self) -> Option<&[u8]> {
+        let (i, &len) = self.lcp.iter().enumerate().max_by_key(|&(_, &l)| l)?;
+        if len == 0 {
+            return None;
+        }
+        let start = self.suffixes[i] as usize;
+        Some(&self.text[start..start + len as usize])
+    }
+}
+
+/// The suffix array of `s`, whose symbols are at most `upper`, by SA-IS.
+fn sa_is(s: &[usize], upper: usize) -> Vec<usize> {
+    let n = s.len();
+    match n {
+        0 => return Vec::new(),
+        1 => return vec![0],
+        2 => return if s[0] < s[1] { vec![0, 1] } else { vec![1, 0] },
+        _ => {}
+    }
+    // `smaller[i]` marks an S-type suffix, one that sorts before its
+    // successor.
+    let mut smaller = vec![false; n];
+    for i in (0..n - 1).rev() {
+        smaller[i] = if s[i] == s[i + 1] {
+            smaller[i + 1]
+        } else {
+            s[i] < s[i + 1]
+        };
+    }
+    // Bucket boundaries: `start_l[c]` is where the suffixes starting with c
+    // begin, and `start_s[c]` where the S-type ones among them begin.
+    let mut start_l = vec![0; upper + 1];
+    let mut start_s = vec![0; upper + 1];
+    for i in 0..n {
+        if smaller[i] {
+            start_l[s[i] + 1] += 1;
+        } else {
+            start_s[s[i]] += 1;
+        }
+    }
+    for c in 0..=upper {
+        start_s[c] += start_l[c];
+        if c < upper {
+            start_l[c + 1] += start_s[c];
+        }
+    }
+    let is_lms = |i: usize| i > 0 && !smaller[i - 1] && smaller[i];
+    let mut sa = vec![NONE; n];
+    let induce = |sa: &mut Vec<usize>, lms: &[usize]| {
+        sa.fill(NONE);
+        let mut next = start_s.clone();
+        for &d in lms {
+            sa[next[s[d]]] = d;
+            next[s[d]] += 1;
+        }
+        // L-type suffixes, left to right, from the suffix placed before them.
+        let mut next = start_l.clone();
+        sa[next[s[n - 1]]] = n - 1;
+        next[s[n - 1]] += 1;
+        for i in 0..n {
+            let v = sa[i];
+            if v != NONE && v >= 1 && !smaller[v - 1] {
+                sa[next[s[v - 1]]] = v - 1;
+                next[s[v - 1]] += 1;
+            }
+        }
+        // S-type suffixes, right to left, into the ends of their buckets.
+        let mut end = start_l.clone();
+        for i in (0..n).rev() {
+            let v = sa[i];
+            if v != NONE && v >= 1 && smaller[v - 1] {
+                end[s[v - 1] + 1] -= 1;
+                sa[end[s[v - 1] + 1]] = v - 1;
+            }
+        }
+    };
+    let lms: Vec<usize> = (1..n).filter(|&i| is_lms(i)).collect();
+    let mut lms_index = vec![NONE; n];
+    for (k, &i) in lms.iter().enumerate() {
+        lms_index[i] = k;
+    }
+    induce(&mut sa, &lms);
+    let m = lms.len();
+    if m == 0 {
+        return sa;
+    }
+    // Name the LMS substrings in sorted order, then sort the LMS suffixes
+    // by recursing on the names.
+    let sorted: Vec<usize> = sa
+        .iter()
+        .copied()
+        .filter(|&v| v != NONE && lms_index[v] != NONE)
+        .collect();
+    let end_of = |i: usize| lms.get(lms_index[i] + 1).copied().unwrap_or(n);
+    let mut names = vec![0; m];
+    let mut name = 0;
+    for w in 1..m {
+        let (mut l, mut r) = (sorted[w - 1], sorted[w]);
+        let (end_l, end_r) = (end_of(l), end_of(r));
+        let mut same = end_l - l == end_r - r;
+        if same {
+            while l < end_l && s[l] == s[r] {
+                l += 1;
+                r += 1;
+            }
+            same = l < n && r < n && s[l] == s[r];
+        }
+        if !same {
+            name += 1;
+        }
+        names[lms_index[sorted[w]]] = name;
+    }
+    let order = sa_is(&names, name);
+    let sorted: Vec<usize> = order.into_iter().map(|k| lms[k]).collect();
+    induce(&mut sa, &sorted);
+    sa
+}
+
+/// Kasai's LCP array for the suffix array `sa` of `text`.
+fn kasai(text: &[u8], sa: &[usize]) -> Vec<u32> {
+    let n = text.len();
+    let mut rank = vec![0; n];
+    for (r, &i) in sa.iter().enumerate() {
+        rank[i] = r;
+    }
+    let mut lcp = vec![0; n];
+    let mut h = 0;
+    // Each suffix shares at least one byte less with its predecessor than
+    // the suffix before it did, so `h` only drops by one per step.
+    for i in 0..n {
+        if rank[i] == 0 {
+            h = 0;
+            continue;
+        }
+        let j = sa[rank[i] - 1];
+        while i + h < n && j + h < n && text[i + h] == text[j + h] {
+            h += 1;
+        }
+        lcp[rank[i]] = h as u32;
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
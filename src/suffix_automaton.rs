@@ -0,0 +1,201 @@
+//! A suffix automaton, built online one byte at a time.
+//!
+//! The suffix automaton of a text is the smallest deterministic automaton
+//! accepting exactly its substrings. Each state stands for a class of
+//! substrings that end at the same set of positions. The states also form
+//! a tree through their suffix links, each pointing at the class of the
+//! longest suffix that occurs in more places. The automaton has at most
+//! `2n - 1` states and `3n - 4` transitions, and Blumer et al.'s
+//! construction appends one byte in amortized `O(1)` time. So unlike a
+//! [`SuffixArray`], it grows along with a text arriving as a stream.
+//!
+//! Following transitions from the start decides substring containment in
+//! `O(m)`. Every distinct substring is one path from the start, so the
+//! automaton keeps a running count of them as it grows. Walking a second
+//! string through it while falling back along suffix links on a mismatch
+//! finds the longest common substring in linear time.
+//!
+//! Transitions are kept as small sorted lists, which suits the few
+//! outgoing edges most states have.
+//!
+//! [`SuffixArray`]: crate::suffix_array::SuffixArray
+
+const NONE: u32 = u32::MAX;
+
+#[derive(Clone, Debug)]
+struct State {
+    /// Length of the longest substring in this state's class.
+    len: u32,
+    /// The state of the longest suffix in a different class.
+    link: u32,
+    /// Outgoing transitions, sorted by byte.
+    next: Vec<(u8, u32)>,
+}
+
+/// The suffix automaton of a byte string.
+#[derive(Clone, Debug)]
+pub struct SuffixAutomaton {
+    states: Vec<State>,
+    /// The state of the whole text.
+    last: u32,
+    distinct: u64,
+}
+
+impl Default for SuffixAutomaton {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SuffixAutomaton {
+    /// Creates the automaton of the empty string.
+    pub fn new() -> Self {
+        SuffixAutomaton {
+            states: vec![State {
+                len: 0,
+                link: NONE,
+                next: Vec::new(),
+            }],
+            last: 0,
+            distinct: 0,
+        }
+    }
+
+    /// Builds the automaton of `text` in `O(n)`.
+    pub fn build(text: &[u8]) -> Self {
+        let mut automaton = Self::new();
+        automaton.extend_from_slice(text);
+        automaton
+    }
+
+    /// Length of the text.
+    pub fn len(&self) -> usize {
+        self.states[self.last as usize].len as usize
+    }
+
+    /// Whether the text is empty.
+    pub fn is_empty(&self) -> bool {
+        self.last == 0
+    }
+
+    /// Number of states, the start state included.
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Appends a byte to the text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the text would reach `u32::MAX` bytes.
+    pub fn push(&mut self, byte: u8) {
+        let len = self.states[self.last as usize].len;
+        assert!(len < u32::MAX - 1, "text is too long");
+        let cur = self.states.len() as u32;
+        self.states.push(State {
+            len: len + 1,
+            link: 0,
+            next: Vec::new(),
+        });
+        // Every suffix of the old text without a `byte` edge gains one to
+        // the new state.
+        let mut p = self.last;
+        while p != NONE && self.step(p, byte).is_none() {
+            self.set(p, byte, cur);
+            p = self.states[p as usize].link;
+        }
+        if p != NONE {
+            let q = self.step(p, byte).expect("loop stopped at an edge");
+            if self.states[p as usize].len + 1 == self.states[q as usize].len {
+                self.states[cur as usize].link = q;
+            } else {
+                // `q`'s class splits: its shorter members now also end at
+                // the new position, so they move to a clone.
+                let clone = self.states.len() as u32;
+                let mut state = self.states[q as usize].clone();
+                state.len = self.states[p as usize].len + 1;
+                self.states.push(state);
+                while p != NONE && self.step(p, byte) == Some(q) {
+                    self.set(p, byte, clone);
+                    p = self.states[p as usize].link;
+                }
+                self.states[q as usize].link = clone;
+                self.states[cur as usize].link = clone;
+            }
+        }
+        self.last = cur;
+        let link = self.states[cur as usize].link;
+        self.distinct += u64::from(len + 1 - self.states[link as usize].len);
+    }
+
+    /// Appends bytes to the text.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.push(b);
+        }
+    }
+
+    /// Whether `pattern` is a substring of the text, in `O(m)`.
+    pub fn contains(&self, pattern: &[u8]) -> bool {
+        let mut v = 0;
+        for &b in pattern {
+            match self.step(v, b) {
+                Some(next) => v = next,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// The number of distinct non-empty substrings of the text.
+    pub fn count_distinct_substrings(&self) -> u64 {
+        self.distinct
+    }
+
+    /// A longest substring of `other` that is also a substring of the text,
+    /// in `O(m)` for `other` of length `m`.
+    pub fn longest_common_substring<'o>(&self, other: &'o [u8]) -> &'o [u8] {
+        let (mut v, mut len) = (0, 0);
+        let (mut best, mut end) = (0, 0);
+        for (i, &b) in other.iter().enumerate() {
+            // Shorten the match from the left until it can be extended.
+            while v != 0 && self.step(v, b).is_none() {
+                v = self.states[v as usize].link;
+                len = self.states[v as usize].len as usize;
+            }
+            match self.step(v, b) {
+                Some(next) => {
+                    v = next;
+                    len += 1;
+                }
+                None => len = 0,
+            }
+            if len > best {
+                (best, end) = (len, i + 1);
+            }
+        }
+        &other[end - best..end]
+    }
+
+    fn step(&self, v: u32, byte: u8) -> Option<u32> {
+        let next = &self.states[v as usize].next;
+        let i = next.binary_search_by_key(&byte, |e| e.0).ok()?;
+        Some(next[i].1)
+    }
+
+    fn set(&mut self, v: u32, byte: u8, to: u32) {
+        let next = &mut self.states[v as usize].next;
+        match next.binary_search_by_key(&byte, |e| e.0) {
+            Ok(i) => next[i].1 = to,
+            Err(i) => next.insert(i, (byte, to)),
+        }
+    }
+}
+
+impl Extend<u8> for SuffixAutomaton {
+    fn extend<I: IntoIterator<Item = u8>>(&mut self, iter: I) {
+        for b in iter {
+            self.push(b);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Synchronization primitives for the lock-free structures, swapped for
+//! loom's model-checked versions when built with `--cfg loom`.
+//!
+//! Loom explores every interleaving of a concurrent test, but only sees
+//! the atomics and cells it provides. The lock-free modules import these
+//! names from here rather than from `std`, so the same code runs under
+//! both. Loom's `UnsafeCell` hands out raw pointers through closures,
+//! which lets it track each access; the `std` version below mirrors that
+//! interface.
+
+#[cfg(loom)]
+pub(crate) use loom::cell::UnsafeCell;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic::{AtomicUsize, Ordering};
+
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// `std`'s `UnsafeCell` with loom's closure-based interface.
+#[cfg(not(loom))]
+#[derive(Debug)]
+pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+#[cfg(not(loom))]
+impl<T> UnsafeCell<T> {
+    pub(crate) fn new(value: T) -> Self {
+        UnsafeCell(std::cell::UnsafeCell::new(value))
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.0.get())
+    }
+
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
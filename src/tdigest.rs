@@ -0,0 +1,295 @@
+//! A t-digest: streaming quantiles with accurate tails.
+//!
+//! A t-digest (Dunning and Ertl, 2019) summarizes a stream of numbers as a
+//! sorted list of centroids, each a mean and a weight. Centroids near the
+//! median may absorb many samples but those near the extremes only a few,
+//! as bounded by the scale function `k(q) = δ/2π · asin(2q - 1)`: a
+//! centroid may span at most one unit of `k`. Quantiles are interpolated
+//! between neighbouring centroids, so the error at quantile `q` shrinks
+//! with `q(1 - q)`: tail percentiles such as the 99.9th stay accurate, from
+//! a digest of under a hundred centroids whatever the stream's length.
+//!
+//! This is the merging variant. Samples are appended to a buffer, and when
+//! it fills they are sorted and merged with the centroids in one pass,
+//! which greedily combines neighbours while the combined centroid stays
+//! within its `k` bound. Two digests merge the same way, so digests of
+//! shards or of successive time windows combine into one of all the data.
+//!
+//! The compression `δ` bounds the number of centroids to about `δ`; 100, the
+//! default, keeps around 60, and higher values trade space for accuracy.
+//! The exact minimum and maximum are kept too, so the extreme quantiles
+//! are never extrapolated beyond the data.
+
+use std::f64::consts::PI;
+
+/// The compression [`TDigest::new`] uses.
+pub const DEFAULT_COMPRESSION: f64 = 100.0;
+
+/// A cluster of samples, summarized by their mean and count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Centroid {
+    /// The mean of the samples.
+    pub mean: f64,
+    /// The number of samples, or their total weight.
+    pub weight: f64,
+}
+
+/// A t-digest of a stream of `f64` samples.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    compression: f64,
+    /// Merged centroids, sorted by mean.
+    centroids: Vec<Centroid>,
+    /// Samples not yet merged.
+    buffer: Vec<Centroid>,
+    /// Total weight of `centroids` and `buffer`.
+    weight: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for TDigest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TDigest {
+    /// Creates an empty digest with the default compression.
+    pub fn new() -> Self {
+        Self::with_compression(DEFAULT_COMPRESSION)
+    }
+
+    /// Creates an empty digest with the given compression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `compression` is less than 10 or not finite.
+    pub fn with_compression(compression: f64) -> Self {
+        assert!(
+            compression.is_finite() && compression >= 10.0,
+            "compression must be at least 10"
+        );
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            weight: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    /// The compression.
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    /// The total weight of the samples, their count if all were inserted
+    /// with weight 1.
+    pub fn count(&self) -> f64 {
+        self.weight
+    }
+
+    /// Whether nothing has been inserted.
+    pub fn is_empty(&self) -> bool {
+        self.weight == 0.0
+    }
+
+    /// The smallest sample.
+    pub fn min(&self) -> Option<f64> {
+        (!self.is_empty()).then_some(self.min)
+    }
+
+    /// The largest sample.
+    pub fn max(&self) -> Option<f64> {
+        (!self.is_empty()).then_some(self.max)
+    }
+
+    /// Removes every sample.
+    pub fn clear(&mut self) {
+        *self = Self::with_compression(self.compression);
+    }
+
+    /// Adds a sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is NaN.
+    pub fn insert(&mut self, value: f64) {
+        self.insert_weighted(value, 1.0);
+    }
+
+    /// Adds a sample standing for `weight` samples of the same value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is NaN or `weight` is not positive and finite.
+    pub fn insert_weighted(&mut self, value: f64, weight: f64) {
+        assert!(!value.is_nan(), "t-digest samples must not be NaN");
+        assert!(
+            weight > 0.0 && weight.is_finite(),
+            "weight must be positive and finite"
+        );
+        self.buffer.push(Centroid {
+            mean: value,
+            weight,
+        });
+        self.weight += weight;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        if self.buffer.len() >= self.buffer_capacity() {
+            self.compress();
+        }
+    }
+
+    /// Adds every sample of `other` to this digest.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.buffer.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.weight += other.weight;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.compress();
+    }
+
+    /// Merges buffered samples into the centroids.
+    pub fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.buffer);
+        all.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+        let total = self.weight;
+        let mut merged: Vec<Centroid> = Vec::with_capacity(2 * self.compression as usize);
+        let mut current = all[0];
+        // Weight to the left of `current`.
+        let mut before = 0.0;
+        let mut limit = self.k_inverse(self.k(0.0) + 1.0) * total;
+        for &c in &all[1..] {
+            if before + current.weight + c.weight <= limit {
+                let weight = current.weight + c.weight;
+                current.mean += (c.mean - current.mean) * c.weight / weight;
+                current.weight = weight;
+            } else {
+                before += current.weight;
+                merged.push(current);
+                limit = self.k_inverse(self.k(before / total) + 1.0) * total;
+                current = c;
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+        self.buffer = Vec::with_capacity(self.buffer_capacity());
+    }
+
+    /// The merged centroids, sorted by mean. Buffered samples are not
+    /// included until the next [`compress`](Self::compress).
+    pub fn centroids(&self) -> &[Centroid] {
+        &self.centroids
+    }
+
+    /// Estimates the value below which a fraction `q` of the samples lie,
+    /// merging buffered samples first. `None` if the digest is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `q` is between 0 and 1.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        assert!((0.0..=1.0).contains(&q), "quantile must be between 0 and 1");
+        if self.is_empty() {
+            return None;
+        }
+        self.compress();
+        let c = &self.centroids;
+        if c.len() == 1 {
+            return Some(lerp(self.min, self.max, q));
+        }
+        // Each centroid's weight is taken to be centred on its mean, with
+        // the minimum and maximum at the ends.
+        let target = q * self.weight;
+        let mut middle = c[0].weight / 2.0;
+        if target < middle {
+            return Some(lerp(self.min, c[0].mean, target / middle));
+        }
+        for i in 1..c.len() {
+            let next = middle + (c[i - 1].weight + c[i].weight) / 2.0;
+            if target <= next {
+                let t = (target - middle) / (next - middle);
+                return Some(lerp(c[i - 1].mean, c[i].mean, t));
+            }
+            middle = next;
+        }
+        let last = c[c.len() - 1];
+        let t = ((target - middle) / (last.weight / 2.0)).min(1.0);
+        Some(lerp(last.mean, self.max, t))
+    }
+
+    /// Estimates the fraction of samples at or below `value`, merging
+    /// buffered samples first. `None` if the digest is empty.
+    pub fn cdf(&mut self, value: f64) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        if value < self.min {
+            return Some(0.0);
+        }
+        if value >= self.max {
+            return Some(1.0);
+        }
+        self.compress();
+        let c = &self.centroids;
+        let total = self.weight;
+        if value < c[0].mean {
+            let t = (value - self.min) / (c[0].mean - self.min);
+            return Some(t * c[0].weight / 2.0 / total);
+        }
+        let mut middle = c[0].weight / 2.0;
+        for i in 1..c.len() {
+            let next = middle + (c[i - 1].weight + c[i].weight) / 2.0;
+            if value < c[i].mean {
+                let t = (value - c[i - 1].mean) / (c[i].mean - c[i - 1].mean);
+                return Some(lerp(middle, next, t) / total);
+            }
+            middle = next;
+        }
+        let last = c[c.len() - 1];
+        let t = (value - last.mean) / (self.max - last.mean);
+        Some(lerp(middle, total, t) / total)
+    }
+
+    /// The scale function, mapping a quantile to its centroid index.
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn k_inverse(&self, k: f64) -> f64 {
+        let angle = (k * 2.0 * PI / self.compression).min(PI / 2.0);
+        (angle.sin() + 1.0) / 2.0
+    }
+
+    fn buffer_capacity(&self) -> usize {
+        5 * self.compression as usize
+    }
+}
+
+impl Extend<f64> for TDigest {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl FromIterator<f64> for TDigest {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        let mut digest = TDigest::new();
+        digest.extend(iter);
+        digest
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
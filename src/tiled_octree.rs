@@ -0,0 +1,421 @@
+//! An unbounded 3D point index built from a grid of octree chunks.
+//!
+//! An [`Octree`] covers one fixed cube chosen up front, which rules it out
+//! for worlds that grow without limit, such as procedurally generated voxel
+//! terrain. A [`TiledOctree`] cuts space into cubic chunks of one fixed
+//! size and gives each occupied chunk its own octree, filed in a hash map
+//! under the chunk's integer coordinates. Only occupied chunks cost memory.
+//! A chunk is created by the first point inserted into it and dropped with
+//! its last point.
+//!
+//! Inserts, removals and moves are routed to the right chunk, and queries
+//! that cross chunk boundaries visit every chunk they touch, so callers see
+//! one index. Handles stay valid when a point moves between chunks.
+//!
+//! [`Octree`]: crate::octree::Octree
+
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::geom::Aabb;
+use crate::grid::for_each_cell;
+use crate::octree::{Octree, OutOfBounds, Point, PointId, MAX_DEPTH};
+use crate::orthtree::Orthtree;
+use crate::util::Total;
+
+/// Handle to a point stored in a [`TiledOctree`].
+///
+/// Handles of removed points may be reused by later insertions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TiledId(u32);
+
+/// Chunk coordinates beyond this magnitude are rejected, keeping chunk
+/// corners exactly representable.
+const MAX_CHUNK: f64 = (1u64 << 52) as f64;
+
+#[derive(Clone, Debug)]
+struct Entry<D> {
+    data: D,
+    chunk: [i64; 3],
+    /// The point's handle within its chunk's octree.
+    point: PointId,
+}
+
+/// An unbounded index mapping 3D points of type `P` to data of type `D`.
+#[derive(Clone, Debug)]
+pub struct TiledOctree<P, D> {
+    chunk_size: f64,
+    bucket: usize,
+    max_depth: u8,
+    /// Each chunk's octree stores the entry index of every point.
+    chunks: HashMap<[i64; 3], Octree<P, u32>>,
+    entries: Vec<Option<Entry<D>>>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl<P: Point<3>, D> TiledOctree<P, D> {
+    /// Creates an empty index of cubic chunks with edge length
+    /// `chunk_size`. Each chunk is an octree with a bucket capacity of 8
+    /// and a maximum depth of 16.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `chunk_size` is positive and finite.
+    pub fn new(chunk_size: f64) -> Self {
+        Self::with_limits(chunk_size, 8, 16)
+    }
+
+    /// Creates an empty index whose chunk octrees use the given leaf
+    /// capacity and depth limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `chunk_size` is positive and finite, if
+    /// `bucket_capacity` is zero, or if `max_depth` exceeds [`MAX_DEPTH`].
+    pub fn with_limits(chunk_size: f64, bucket_capacity: usize, max_depth: u8) -> Self {
+        assert!(
+            chunk_size > 0.0 && chunk_size.is_finite(),
+            "chunk size must be positive and finite"
+        );
+        assert!(bucket_capacity > 0, "bucket capacity must be positive");
+        assert!(
+            max_depth <= MAX_DEPTH,
+            "max depth must be at most {MAX_DEPTH}"
+        );
+        TiledOctree {
+            chunk_size,
+            bucket: bucket_capacity,
+            max_depth,
+            chunks: HashMap::new(),
+            entries: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// The edge length of each chunk.
+    pub fn chunk_size(&self) -> f64 {
+        self.chunk_size
+    }
+
+    /// Number of stored points.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the index holds no points.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of occupied chunks.
+    pub fn occupied_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Iterates over the coordinates of the occupied chunks, in unspecified
+    /// order.
+    pub fn chunks(&self) -> impl Iterator<Item = [i64; 3]> + '_ {
+        self.chunks.keys().copied()
+    }
+
+    /// Removes every point, invalidating all handles.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.entries.clear();
+        self.free.clear();
+        self.len = 0;
+    }
+
+    /// The chunk containing `p`, or `None` if `p` is not finite or too far
+    /// out to address.
+    pub fn chunk_of<Q: Point<3>>(&self, p: &Q) -> Option<[i64; 3]> {
+        let c = p.coords();
+        let mut chunk = [0; 3];
+        for a in 0..3 {
+            let t = (c[a] / self.chunk_size).floor();
+            if t.is_nan() || t.abs() >= MAX_CHUNK {
+                return None;
+            }
+            // Division can round across a chunk boundary; settle on the
+            // chunk whose box holds the point.
+            let mut k = t as i64;
+            if c[a] < k as f64 * self.chunk_size {
+                k -= 1;
+            } else if c[a] > (k + 1) as f64 * self.chunk_size {
+                k += 1;
+            }
+            chunk[a] = k;
+        }
+        Some(chunk)
+    }
+
+    /// The box covered by a chunk.
+    pub fn chunk_bounds(&self, chunk: [i64; 3]) -> Aabb<3> {
+        Aabb::new(
+            chunk.map(|k| k as f64 * self.chunk_size),
+            chunk.map(|k| (k + 1) as f64 * self.chunk_size),
+        )
+    }
+
+    /// Inserts a point, returning its handle, or an error if the point is
+    /// not finite or too far out to address.
+    pub fn insert(&mut self, point: P, data: D) -> Result<TiledId, OutOfBounds> {
+        let chunk = self.chunk_of(&point).ok_or(OutOfBounds)?;
+        let id = match self.free.last() {
+            Some(&id) => id,
+            None => self.entries.len() as u32,
+        };
+        let handle = self.link(chunk, point, id)?;
+        let entry = Entry {
+            data,
+            chunk,
+            point: handle,
+        };
+        if self.free.pop().is_some() {
+            self.entries[id as usize] = Some(entry);
+        } else {
+            self.entries.push(Some(entry));
+        }
+        self.len += 1;
+        Ok(TiledId(id))
+    }
+
+    /// Removes a point, returning its coordinates and data.
+    pub fn remove(&mut self, id: TiledId) -> Option<(P, D)> {
+        let entry = self.entries.get_mut(id.0 as usize)?.take()?;
+        let point = self.unlink(entry.chunk, entry.point);
+        self.free.push(id.0);
+        self.len -= 1;
+        Some((point, entry.data))
+    }
+
+    /// Moves a point to a new position, keeping its handle and data,
+    /// across chunk boundaries if need be.
+    ///
+    /// Returns the previous position, or `None` if `id` is not in the
+    /// index. Fails without modifying the index if `to` cannot be
+    /// addressed.
+    pub fn relocate(&mut self, id: TiledId, to: P) -> Result<Option<P>, OutOfBounds> {
+        let chunk = self.chunk_of(&to).ok_or(OutOfBounds)?;
+        let Some(entry) = self.entries.get(id.0 as usize).and_then(Option::as_ref) else {
+            return Ok(None);
+        };
+        let (from, old_handle) = (entry.chunk, entry.point);
+        if from == chunk {
+            let tree = self.chunks.get_mut(&chunk).expect("entry's chunk exists");
+            return tree.relocate(old_handle, to);
+        }
+        let handle = self.link(chunk, to, id.0)?;
+        let old = self.unlink(from, old_handle);
+        let entry = self.entries[id.0 as usize]
+            .as_mut()
+            .expect("entry checked above");
+        entry.chunk = chunk;
+        entry.point = handle;
+        Ok(Some(old))
+    }
+
+    /// Returns the position and data of a point.
+    pub fn get(&self, id: TiledId) -> Option<(&P, &D)> {
+        let e = self.entries.get(id.0 as usize)?.as_ref()?;
+        Some((self.position(e), &e.data))
+    }
+
+    /// Returns the position and mutable data of a point.
+    pub fn get_mut(&mut self, id: TiledId) -> Option<(&P, &mut D)> {
+        let e = self.entries.get_mut(id.0 as usize)?.as_mut()?;
+        let tree = &self.chunks[&e.chunk];
+        let (p, _) = tree.get(e.point).expect("entry's point exists");
+        Some((p, &mut e.data))
+    }
+
+    /// Iterates over every stored point in unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (TiledId, &P, &D)> + '_ {
+        self.entries.iter().enumerate().filter_map(|(i, e)| {
+            e.as_ref()
+                .map(|e| (TiledId(i as u32), self.position(e), &e.data))
+        })
+    }
+
+    /// Iterates over the points inside the closed box `query`.
+    pub fn within_box(&self, query: Aabb<3>) -> impl Iterator<Item = (TiledId, &P, &D)> + '_ {
+        self.touched(&query)
+            .into_iter()
+            .flat_map(move |tree| tree.within_box(query))
+            .map(|(_, _, &i)| self.item(i))
+    }
+
+    /// Iterates over the points within `radius` of `center`.
+    pub fn within_radius<Q: Point<3>>(
+        &self,
+        center: Q,
+        radius: f64,
+    ) -> impl Iterator<Item = (TiledId, &P, &D)> + '_ {
+        let c = center.coords();
+        let reach = Aabb::new(c.map(|x| x - radius), c.map(|x| x + radius));
+        self.touched(&reach)
+            .into_iter()
+            .flat_map(move |tree| tree.within_radius(c, radius))
+            .map(|(_, _, &i)| self.item(i))
+    }
+
+    /// Returns up to `k` points nearest to `query`, closest first, with their
+    /// Euclidean distances. Returns nothing if `query` cannot be addressed.
+    ///
+    /// Searches shells of chunks outward from the query's chunk, so the cost
+    /// grows with the distance to the `k`-th neighbor in chunks. Once a
+    /// shell would hold more chunks than are occupied, the remaining chunks
+    /// are visited nearest first instead.
+    pub fn nearest<Q: Point<3>>(&self, query: Q, k: usize) -> Vec<(TiledId, f64)> {
+        let q = query.coords();
+        let Some(center) = self.chunk_of(&query) else {
+            return Vec::new();
+        };
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        let mut best: BinaryHeap<(Total, u32)> = BinaryHeap::with_capacity(k + 1);
+        let visit = |tree: &Octree<P, u32>, best: &mut BinaryHeap<(Total, u32)>| {
+            for (handle, d) in tree.nearest(q, k) {
+                let (_, &i) = tree.get(handle).expect("neighbor exists");
+                let d2 = d * d;
+                if best.len() < k {
+                    best.push((Total(d2), i));
+                } else if d2 < worst(best) {
+                    best.pop();
+                    best.push((Total(d2), i));
+                }
+            }
+        };
+        let mut ring = 0u64;
+        loop {
+            let shell_chunks = (2 * ring + 1).checked_pow(3).unwrap_or(u64::MAX);
+            if shell_chunks > self.chunks.len() as u64 {
+                let mut rest: Vec<_> = self
+                    .chunks
+                    .iter()
+                    .filter(|(c, _)| ring_of(c, &center) >= ring)
+                    .map(|(c, tree)| (self.chunk_bounds(*c).distance_squared(&q), tree))
+                    .collect();
+                rest.sort_by(|a, b| a.0.total_cmp(&b.0));
+                for (d2, tree) in rest {
+                    if best.len() == k && d2 > worst(&best) {
+                        break;
+                    }
+                    visit(tree, &mut best);
+                }
+                break;
+            }
+            // Points in shell `ring` are at least `ring - 1` chunks away.
+            let reach = ring.saturating_sub(1) as f64 * self.chunk_size;
+            if best.len() == k && reach * reach > worst(&best) {
+                break;
+            }
+            let r = ring as i64;
+            let lo = center.map(|c| c - r);
+            let hi = center.map(|c| c + r);
+            for_each_cell(lo, hi, |chunk| {
+                let on_shell = (0..3).any(|a| chunk[a] == lo[a] || chunk[a] == hi[a]);
+                if let Some(tree) = self.chunks.get(&chunk).filter(|_| on_shell) {
+                    visit(tree, &mut best);
+                }
+            });
+            ring += 1;
+        }
+        let mut out: Vec<_> = best
+            .into_iter()
+            .map(|(d2, i)| (TiledId(i), d2.0.sqrt()))
+            .collect();
+        out.sort_by(|a, b| a.1.total_cmp(&b.1));
+        out
+    }
+
+    /// Returns the point nearest to `query` and its distance.
+    pub fn nearest_one<Q: Point<3>>(&self, query: Q) -> Option<(TiledId, f64)> {
+        self.nearest(query, 1).pop()
+    }
+
+    /// The occupied chunks overlapping `query`.
+    fn touched(&self, query: &Aabb<3>) -> Vec<&Octree<P, u32>> {
+        let (Some(lo), Some(hi)) = (self.chunk_of(&query.min), self.chunk_of(&query.max)) else {
+            // A box reaching past the addressable chunks can only be
+            // answered by checking them all.
+            return self
+                .chunks
+                .iter()
+                .filter(|(c, _)| self.chunk_bounds(**c).intersects(query))
+                .map(|(_, tree)| tree)
+                .collect();
+        };
+        let span = (0..3)
+            .map(|a| (hi[a] as i128 - lo[a] as i128 + 1).max(0) as u128)
+            .fold(1u128, u128::saturating_mul);
+        let mut trees = Vec::new();
+        if span > self.chunks.len() as u128 {
+            trees.extend(
+                self.chunks
+                    .iter()
+                    .filter(|(c, _)| (0..3).all(|a| lo[a] <= c[a] && c[a] <= hi[a]))
+                    .map(|(_, tree)| tree),
+            );
+        } else {
+            for_each_cell(lo, hi, |chunk| {
+                if let Some(tree) = self.chunks.get(&chunk) {
+                    trees.push(tree);
+                }
+            });
+        }
+        trees
+    }
+
+    fn position(&self, e: &Entry<D>) -> &P {
+        let (p, _) = self.chunks[&e.chunk]
+            .get(e.point)
+            .expect("entry's point exists");
+        p
+    }
+
+    fn item(&self, i: u32) -> (TiledId, &P, &D) {
+        let e = self.entries[i as usize].as_ref().expect("live entry");
+        (TiledId(i), self.position(e), &e.data)
+    }
+
+    /// Files `point` under entry `id` in its chunk, creating the chunk if
+    /// need be.
+    fn link(&mut self, chunk: [i64; 3], point: P, id: u32) -> Result<PointId, OutOfBounds> {
+        let bounds = self.chunk_bounds(chunk);
+        let (bucket, max_depth) = (self.bucket, self.max_depth);
+        let tree = self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| Orthtree::with_limits(bounds, bucket, max_depth));
+        let result = tree.insert(point, id);
+        if tree.is_empty() {
+            self.chunks.remove(&chunk);
+        }
+        result
+    }
+
+    /// Takes a point out of its chunk, dropping the chunk if it empties.
+    fn unlink(&mut self, chunk: [i64; 3], handle: PointId) -> P {
+        let tree = self.chunks.get_mut(&chunk).expect("entry's chunk exists");
+        let (point, _) = tree.remove(handle).expect("entry's point exists");
+        if tree.is_empty() {
+            self.chunks.remove(&chunk);
+        }
+        point
+    }
+}
+
+fn worst(best: &BinaryHeap<(Total, u32)>) -> f64 {
+    best.peek().map_or(f64::INFINITY, |b| b.0 .0)
+}
+
+/// Chebyshev distance between two chunks.
+fn ring_of(chunk: &[i64; 3], center: &[i64; 3]) -> u64 {
+    (0..3)
+        .map(|a| chunk[a].abs_diff(center[a]))
+        .max()
+        .unwrap_or(0)
+}
@@ -0,0 +1,318 @@
+//! An ordered map stored as a treap, with split, merge and order statistics.
+//!
+//! A treap is a binary search tree by key and, at the same time, a heap by
+//! a random priority drawn for each node. The random priorities give the
+//! tree the shape of one built by inserting its keys in random order, so
+//! the expected depth is `O(log n)` whatever the real insertion order.
+//!
+//! Every update reduces to two primitives. [`split`](Treap::split) cuts a
+//! tree into the keys below a pivot and the rest. [`merge`](Treap::merge)
+//! joins two trees whose key ranges don't overlap. Both walk one root-to-leaf
+//! path, so carving a range out of a map, or splicing two maps together,
+//! costs `O(log n)` rather than the `O(n)` a std map needs. Each node also
+//! counts its subtree, which gives [`select`](Treap::select) and
+//! [`rank`](Treap::rank) in `O(log n)`.
+//!
+//! Nodes are boxed rather than kept in an arena, so that halves of a split
+//! move between trees without copying.
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+use crate::rng::SplitMix64;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    priority: u64,
+    /// Number of nodes in this subtree.
+    size: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+impl<K, V> Node<K, V> {
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+/// An ordered map from keys of type `K` to values of type `V`.
+#[derive(Clone, Debug)]
+pub struct Treap<K, V> {
+    root: Link<K, V>,
+    rng: SplitMix64,
+}
+
+impl<K: Ord, V> Default for Treap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> Treap<K, V> {
+    /// Creates an empty map with a fixed default seed.
+    pub fn new() -> Self {
+        Self::with_seed(0x5eed)
+    }
+
+    /// Creates an empty map whose node priorities are drawn from `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Treap {
+            root: None,
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Some(&n.value),
+            };
+        }
+        None
+    }
+
+    /// Returns the value stored under `key` mutably.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &mut self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &mut n.left,
+                Ordering::Greater => &mut n.right,
+                Ordering::Equal => return Some(&mut n.value),
+            };
+        }
+        None
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.select(0)
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.select(self.len().checked_sub(1)?)
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(old) = self.get_mut(&key) {
+            return Some(std::mem::replace(old, value));
+        }
+        let node = Box::new(Node {
+            key,
+            value,
+            priority: self.rng.next_u64(),
+            size: 1,
+            left: None,
+            right: None,
+        });
+        let (left, right) = split(self.root.take(), &node.key, false);
+        self.root = merge(merge(left, Some(node)), right);
+        None
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (left, rest) = split(self.root.take(), key, false);
+        let (mid, right) = split(rest, key, true);
+        self.root = merge(left, right);
+        // `mid` holds only the key itself, if it was present.
+        mid.map(|n| (n.key, n.value))
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// Moves every entry with a key at or above `key` into a new treap,
+    /// which is returned, in `O(log n)` expected.
+    ///
+    /// The new treap continues from a seed drawn from this one's generator.
+    pub fn split<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (left, right) = split(self.root.take(), key, false);
+        self.root = left;
+        Treap {
+            root: right,
+            rng: SplitMix64::new(self.rng.next_u64()),
+        }
+    }
+
+    /// Moves every entry of `other` into this treap, in `O(log n)` expected.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless every key in this treap is below every key in `other`.
+    pub fn merge(&mut self, other: Self) {
+        if let (Some((a, _)), Some((b, _))) = (self.last_key_value(), other.first_key_value()) {
+            assert!(a < b, "merged treaps must not overlap");
+        }
+        self.root = merge(self.root.take(), other.root);
+    }
+
+    /// The entry with the `index`-th smallest key, counting from zero.
+    pub fn select(&self, mut index: usize) -> Option<(&K, &V)> {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let left = size(&n.left);
+            link = match index.cmp(&left) {
+                Ordering::Less => &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    index -= left + 1;
+                    &n.right
+                }
+            };
+        }
+        None
+    }
+
+    /// The number of keys below `key`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            if n.key.borrow() < key {
+                rank += size(&n.left) + 1;
+                link = &n.right;
+            } else {
+                link = &n.left;
+            }
+        }
+        rank
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut stack: Vec<&Node<K, V>> = Vec::new();
+        let mut link = self.root.as_deref();
+        std::iter::from_fn(move || {
+            while let Some(n) = link {
+                stack.push(n);
+                link = n.left.as_deref();
+            }
+            let n = stack.pop()?;
+            link = n.right.as_deref();
+            Some((&n.key, &n.value))
+        })
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for Treap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut treap = Treap::new();
+        for (k, v) in iter {
+            treap.insert(k, v);
+        }
+        treap
+    }
+}
+
+fn size<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |n| n.size)
+}
+
+/// Splits a subtree into the keys below `key`, or with `inclusive` set at
+/// most `key`, and the rest.
+fn split<K, V, Q>(link: Link<K, V>, key: &Q, inclusive: bool) -> (Link<K, V>, Link<K, V>)
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let Some(mut n) = link else {
+        return (None, None);
+    };
+    let goes_left = match n.key.borrow().cmp(key) {
+        Ordering::Less => true,
+        Ordering::Equal => inclusive,
+        Ordering::Greater => false,
+    };
+    if goes_left {
+        let (mid, right) = split(n.right.take(), key, inclusive);
+        n.right = mid;
+        n.update();
+        (Some(n), right)
+    } else {
+        let (left, mid) = split(n.left.take(), key, inclusive);
+        n.left = mid;
+        n.update();
+        (left, Some(n))
+    }
+}
+
+/// Joins two subtrees, every key of `a` being below every key of `b`.
+fn merge<K, V>(a: Link<K, V>, b: Link<K, V>) -> Link<K, V> {
+    match (a, b) {
+        (None, b) => b,
+        (a, None) => a,
+        (Some(mut a), Some(mut b)) => {
+            if a.priority >= b.priority {
+                a.right = merge(a.right.take(), Some(b));
+                a.update();
+                Some(a)
+            } else {
+                b.left = merge(Some(a), b.left.take());
+                b.update();
+                Some(b)
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! A lock-free stack with elimination backoff.
+//!
+//! Treiber's stack (1986) is a singly linked list whose head is swapped
+//! with a compare-and-swap: a push links its node to the current head and
+//! swings the head to it, a pop swings the head to the second node. Nodes
+//! are reclaimed through the crate's epoch-based reclamation, which both
+//! keeps a popped node alive while slower threads still read it and
+//! rules out the ABA problem, since no address is reused while a thread
+//! that loaded it is pinned.
+//!
+//! Under heavy contention every operation fights over the one head
+//! pointer. The elimination backoff of Hendler, Shavit and Yerushalmi
+//! (2004) turns that contention into progress: a push or pop whose
+//! compare-and-swap fails visits a random slot of a small side array
+//! instead of retrying at once. A push parks its node there for a moment,
+//! and a pop that finds a parked node takes it. A push and a pop that meet
+//! this way cancel out without touching the stack at all, which is
+//! linearizable because the pair could have run back to back. An
+//! operation that meets nobody goes back to the head.
+
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::epoch;
+
+/// Slots in the elimination array.
+const ELIMINATION_SLOTS: usize = 8;
+
+/// How long a push waits in the elimination array for a pop.
+const EXCHANGE_SPINS: usize = 64;
+
+struct Node<T> {
+    value: ManuallyDrop<T>,
+    next: *mut Node<T>,
+}
+
+// SAFETY: a node crosses threads only as a whole, once reachable from
+// one stack; its link is not dereferenced after it is retired.
+unsafe impl<T: Send> Send for Node<T> {}
+
+/// A lock-free LIFO stack that any number of threads can use through a
+/// shared reference.
+pub struct TreiberStack<T> {
+    head: AtomicPtr<Node<T>>,
+    /// Each slot is empty, holds a push's parked node, or holds `taken()`
+    /// after a pop claimed the node and before the push saw it.
+    elimination: [AtomicPtr<Node<T>>; ELIMINATION_SLOTS],
+}
+
+// SAFETY: values move between threads through the stack, which is sound
+// for `T: Send`; a node's value is read only by the one pop that unlinked
+// or claimed it.
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+impl<T: Send> Default for TreiberStack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send> TreiberStack<T> {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        TreiberStack {
+            head: AtomicPtr::new(ptr::null_mut()),
+            elimination: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+        }
+    }
+
+    /// Whether the stack was empty when checked.
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire).is_null()
+    }
+
+    /// Pushes `value` on top of the stack.
+    pub fn push(&self, value: T) {
+        let node = Box::into_raw(Box::new(Node {
+            value: ManuallyDrop::new(value),
+            next: ptr::null_mut(),
+        }));
+        loop {
+            let head = self.head.load(Ordering::Relaxed);
+            // SAFETY: the node is not shared until the exchange succeeds
+            // or a pop claims it.
+            unsafe { (*node).next = head };
+            if self
+                .head
+                .compare_exchange(head, node, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+                || self.offer(node)
+            {
+                return;
+            }
+        }
+    }
+
+    /// Pops the value on top of the stack.
+    pub fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            if head.is_null() {
+                return None;
+            }
+            // SAFETY: the guard keeps `head` allocated even if another pop
+            // unlinks it meanwhile.
+            let next = unsafe { (*head).next };
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: the exchange made this pop the node's only
+                // reader, and the node is unreachable for later pins. The
+                // value moves out here, so freeing the node later drops
+                // nothing borrowed.
+                unsafe {
+                    let value = ManuallyDrop::into_inner(ptr::read(&(*head).value));
+                    guard.defer_destroy(head);
+                    return Some(value);
+                }
+            }
+            if let Some(value) = self.take_offer() {
+                return Some(value);
+            }
+        }
+    }
+
+    /// Parks `node` in the elimination array for a pop to take; returns
+    /// whether one did.
+    fn offer(&self, node: *mut Node<T>) -> bool {
+        let slot = &self.elimination[random_slot()];
+        if slot
+            .compare_exchange(ptr::null_mut(), node, Ordering::Release, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        for _ in 0..EXCHANGE_SPINS {
+            if slot.load(Ordering::Relaxed) == taken() {
+                slot.store(ptr::null_mut(), Ordering::Relaxed);
+                return true;
+            }
+            std::hint::spin_loop();
+        }
+        match slot.compare_exchange(node, ptr::null_mut(), Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => false,
+            // A pop claimed the node at the last moment.
+            Err(_) => {
+                slot.store(ptr::null_mut(), Ordering::Relaxed);
+                true
+            }
+        }
+    }
+
+    /// Takes a node parked by a concurrent push, if one is waiting in a
+    /// random slot.
+    fn take_offer(&self) -> Option<T> {
+        let slot = &self.elimination[random_slot()];
+        let node = slot.load(Ordering::Relaxed);
+        if node.is_null() || node == taken() {
+            return None;
+        }
+        slot.compare_exchange(node, taken(), Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        // SAFETY: the exchange transferred the node, which never entered
+        // the stack, to this pop alone; the push no longer touches it.
+        let node = unsafe { Box::from_raw(node) };
+        Some(ManuallyDrop::into_inner(node.value))
+    }
+}
+
+impl<T> Drop for TreiberStack<T> {
+    fn drop(&mut self) {
+        // No operation is in flight, so the elimination array is empty and
+        // the nodes can be freed directly.
+        let mut node = *self.head.get_mut();
+        while !node.is_null() {
+            // SAFETY: every node in the stack is an unshared leaked box.
+            let mut boxed = unsafe { Box::from_raw(node) };
+            // SAFETY: the value is dropped once, here.
+            unsafe { ManuallyDrop::drop(&mut boxed.value) };
+            node = boxed.next;
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for TreiberStack<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TreiberStack").finish_non_exhaustive()
+    }
+}
+
+/// The marker a pop leaves in a slot whose node it took. Nodes are at
+/// least pointer-aligned, so no node lives at address 1.
+fn taken<T>() -> *mut Node<T> {
+    ptr::without_provenance_mut(1)
+}
+
+/// A slot picked by a per-thread linear congruential generator.
+fn random_slot() -> usize {
+    thread_local! {
+        static STATE: Cell<u64> = const { Cell::new(0x5eed) };
+    }
+    STATE.with(|state| {
+        let next = state
+            .get()
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        state.set(next);
+        (next >> 33) as usize % ELIMINATION_SLOTS
+    })
+}
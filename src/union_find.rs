@@ -0,0 +1,425 @@
+//! Disjoint sets with union by rank, path compression and rollback.
+//!
+//! Each set is a tree of elements pointing towards a root that stands for
+//! it. Union hangs the shallower tree under the deeper one's root, and
+//! find points every element on the path it walks straight at the root.
+//! Together they make any sequence of operations take `O(α(n))` amortized
+//! time each (Tarjan), where `α` is the inverse Ackermann function and
+//! never exceeds four in practice.
+//!
+//! Every set can carry a payload, such as its size, its minimum or the
+//! list of its members. A union merges the two payloads with a closure of
+//! the caller's choosing.
+//!
+//! In rollback mode the structure records every union and can undo them,
+//! latest first, back to an earlier [`snapshot`](UnionFind::snapshot).
+//! That is what offline dynamic connectivity needs: divide and conquer
+//! over time, adding edges on the way down and removing them on the way
+//! back up. Undoing a union resets a single parent pointer, so rollback
+//! mode gives up path compression, which would move pointers nobody
+//! recorded. Union by rank alone still bounds finds by `O(log n)`.
+//!
+//! [`WeightedUnionFind`] also tracks a potential for every element, known
+//! only relative to the rest of its set: each element keeps its offset
+//! from its parent, in an [`AbelianGroup`], and a find sums the offsets on
+//! its path as it compresses it. Relating two elements as "`a` is `b` plus
+//! 3" either joins their sets or, if the offset between them already
+//! follows from earlier relations, checks it, and reports a contradiction
+//! if it differs. That solves systems of difference constraints online,
+//! and with a group of integers mod 2 it checks bipartiteness.
+//!
+//! [`AbelianGroup`]: crate::algebra::AbelianGroup
+
+use std::error::Error;
+use std::fmt;
+
+use crate::algebra::AbelianGroup;
+
+/// An undoable union: the root that was hung under another, and what it
+/// changed there.
+#[derive(Clone, Debug)]
+struct Union<T> {
+    child: u32,
+    rank_grew: bool,
+    /// The surviving root's payload before the merge.
+    payload: T,
+}
+
+/// A partition of `0..len` into disjoint sets, each with a payload of type
+/// `T`.
+#[derive(Clone, Debug)]
+pub struct UnionFind<T = ()> {
+    parent: Vec<u32>,
+    rank: Vec<u8>,
+    /// Only the roots' payloads are current.
+    payloads: Vec<T>,
+    sets: usize,
+    /// The unions made so far, in rollback mode.
+    history: Option<Vec<Union<T>>>,
+}
+
+impl UnionFind {
+    /// Creates `len` singleton sets without payloads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is `u32::MAX` or more.
+    pub fn new(len: usize) -> Self {
+        Self::with_payloads(vec![(); len])
+    }
+
+    /// Merges the sets containing `a` and `b`. Returns whether they were
+    /// different sets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        self.union_with(a, b, |_, _| ())
+    }
+}
+
+impl<T> UnionFind<T> {
+    /// Creates one singleton set for each payload, element `i` holding
+    /// `payloads[i]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are `u32::MAX` payloads or more.
+    pub fn with_payloads(payloads: Vec<T>) -> Self {
+        let len = payloads.len();
+        assert!(len < u32::MAX as usize, "too many elements");
+        UnionFind {
+            parent: (0..len as u32).collect(),
+            rank: vec![0; len],
+            payloads,
+            sets: len,
+            history: None,
+        }
+    }
+
+    /// Switches to rollback mode, in which unions are recorded and path
+    /// compression is off.
+    pub fn with_rollback(mut self) -> Self {
+        self.history.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    /// The root of the set containing `x`, compressing the path to it
+    /// unless in rollback mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn find(&mut self, x: usize) -> usize {
+        let root = self.root(x);
+        if self.history.is_none() {
+            let mut x = x;
+            while x != root {
+                let next = self.parent[x] as usize;
+                self.parent[x] = root as u32;
+                x = next;
+            }
+        }
+        root
+    }
+
+    /// The root of the set containing `x`, without changing anything.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn root(&self, x: usize) -> usize {
+        let mut x = x;
+        while self.parent[x] as usize != x {
+            x = self.parent[x] as usize;
+        }
+        x
+    }
+
+    /// Whether `a` and `b` are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Merges the sets containing `a` and `b`, combining their payloads
+    /// with `merge`. Returns whether they were different sets; if not,
+    /// `merge` is not called.
+    ///
+    /// `merge` receives the payloads of `a`'s set and `b`'s set, in that
+    /// order, whichever root survives.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn union_with(&mut self, a: usize, b: usize, merge: impl FnOnce(&T, &T) -> T) -> bool {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        let merged = merge(&self.payloads[ra], &self.payloads[rb]);
+        let (root, child) = if self.rank[ra] < self.rank[rb] {
+            (rb, ra)
+        } else {
+            (ra, rb)
+        };
+        self.parent[child] = root as u32;
+        let rank_grew = self.rank[root] == self.rank[child];
+        if rank_grew {
+            self.rank[root] += 1;
+        }
+        let old = std::mem::replace(&mut self.payloads[root], merged);
+        if let Some(history) = &mut self.history {
+            history.push(Union {
+                child: child as u32,
+                rank_grew,
+                payload: old,
+            });
+        }
+        self.sets -= 1;
+        true
+    }
+
+    /// The payload of the set containing `x`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn payload(&mut self, x: usize) -> &T {
+        let root = self.find(x);
+        &self.payloads[root]
+    }
+
+    /// The payload of the set containing `x`, mutably.
+    ///
+    /// Changes made through it are not undone by
+    /// [`rollback`](Self::rollback).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn payload_mut(&mut self, x: usize) -> &mut T {
+        let root = self.find(x);
+        &mut self.payloads[root]
+    }
+
+    /// A point to [`rollback`](Self::rollback) to: the number of unions
+    /// recorded so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not in rollback mode.
+    pub fn snapshot(&self) -> usize {
+        self.history
+            .as_ref()
+            .expect("union-find is not in rollback mode")
+            .len()
+    }
+
+    /// Undoes the unions made since `snapshot`, latest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if not in rollback mode, or if `snapshot` is later than the
+    /// present.
+    pub fn rollback(&mut self, snapshot: usize) {
+        let history = self
+            .history
+            .as_mut()
+            .expect("union-find is not in rollback mode");
+        assert!(
+            snapshot <= history.len(),
+            "snapshot {snapshot} is later than the present"
+        );
+        for union in history.drain(snapshot..).rev() {
+            let child = union.child as usize;
+            let root = self.parent[child] as usize;
+            self.parent[child] = union.child;
+            if union.rank_grew {
+                self.rank[root] -= 1;
+            }
+            self.payloads[root] = union.payload;
+            self.sets += 1;
+        }
+    }
+}
+
+/// Returned when a relation contradicts the ones already recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contradiction<V> {
+    /// The offset the earlier relations imply.
+    pub implied: V,
+}
+
+impl<V> fmt::Display for Contradiction<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("relation contradicts an earlier one")
+    }
+}
+
+impl<V: fmt::Debug> Error for Contradiction<V> {}
+
+/// Disjoint sets whose elements carry potentials known relative to each
+/// other, the offsets being values of the group `G`.
+#[derive(Clone, Debug)]
+pub struct WeightedUnionFind<G: AbelianGroup> {
+    group: G,
+    parent: Vec<u32>,
+    rank: Vec<u8>,
+    /// Each element's potential minus its parent's.
+    offsets: Vec<G::Value>,
+    sets: usize,
+}
+
+impl<G: AbelianGroup> WeightedUnionFind<G> {
+    /// Creates `len` singleton sets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is `u32::MAX` or more.
+    pub fn new(group: G, len: usize) -> Self {
+        assert!(len < u32::MAX as usize, "too many elements");
+        WeightedUnionFind {
+            offsets: vec![group.identity(); len],
+            group,
+            parent: (0..len as u32).collect(),
+            rank: vec![0; len],
+            sets: len,
+        }
+    }
+
+    /// Number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    /// Whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint sets.
+    pub fn set_count(&self) -> usize {
+        self.sets
+    }
+
+    /// The group the offsets live in.
+    pub fn group(&self) -> &G {
+        &self.group
+    }
+
+    /// The root of the set containing `x`, and `x`'s potential minus the
+    /// root's, compressing the path.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is out of bounds.
+    pub fn find(&mut self, x: usize) -> (usize, G::Value) {
+        let mut path = Vec::new();
+        let mut root = x;
+        while self.parent[root] as usize != root {
+            path.push(root);
+            root = self.parent[root] as usize;
+        }
+        // From the top down, each parent's offset is already relative to
+        // the root.
+        for &v in path.iter().rev() {
+            let parent = self.parent[v] as usize;
+            if parent != root {
+                self.offsets[v] = self.group.combine(&self.offsets[v], &self.offsets[parent]);
+                self.parent[v] = root as u32;
+            }
+        }
+        let offset = if x == root {
+            self.group.identity()
+        } else {
+            self.offsets[x].clone()
+        };
+        (root, offset)
+    }
+
+    /// Whether `a` and `b` are in the same set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a).0 == self.find(b).0
+    }
+
+    /// `a`'s potential minus `b`'s, or `None` if they are in different sets
+    /// and so unrelated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn offset(&mut self, a: usize, b: usize) -> Option<G::Value> {
+        let (ra, da) = self.find(a);
+        let (rb, db) = self.find(b);
+        (ra == rb).then(|| self.group.difference(&da, &db))
+    }
+}
+
+impl<G: AbelianGroup> WeightedUnionFind<G>
+where
+    G::Value: PartialEq,
+{
+    /// Records that `a`'s potential is `b`'s plus `offset`. Returns whether
+    /// that joined two sets, or the implied offset if the relation
+    /// contradicts earlier ones, in which case nothing changes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either element is out of bounds.
+    pub fn relate(
+        &mut self,
+        a: usize,
+        b: usize,
+        offset: &G::Value,
+    ) -> Result<bool, Contradiction<G::Value>> {
+        let (ra, da) = self.find(a);
+        let (rb, db) = self.find(b);
+        let g = &self.group;
+        if ra == rb {
+            let implied = g.difference(&da, &db);
+            return if implied == *offset {
+                Ok(false)
+            } else {
+                Err(Contradiction { implied })
+            };
+        }
+        // `rb`'s potential minus `ra`'s.
+        let between = g.difference(&g.difference(&da, offset), &db);
+        if self.rank[ra] < self.rank[rb] {
+            self.offsets[ra] = g.inverse(&between);
+            self.parent[ra] = rb as u32;
+        } else {
+            self.offsets[rb] = between;
+            self.parent[rb] = ra as u32;
+            if self.rank[ra] == self.rank[rb] {
+                self.rank[ra] += 1;
+            }
+        }
+        self.sets -= 1;
+        Ok(true)
+    }
+}
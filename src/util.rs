@@ -0,0 +1,62 @@
+//! Small helpers shared across modules.
+
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+/// An `f64` ordered by [`f64::total_cmp`], so distances can key heaps.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Total(pub f64);
+
+impl PartialEq for Total {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Total {}
+
+impl PartialOrd for Total {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Total {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Reorders `items` so that position `i` holds the element previously at
+/// `order[i]`.
+pub(crate) fn permute<T>(items: &mut Vec<T>, order: &[usize]) {
+    let mut slots: Vec<Option<T>> = items.drain(..).map(Some).collect();
+    items.extend(
+        order
+            .iter()
+            .map(|&i| slots[i].take().expect("order is a permutation")),
+    );
+}
+
+/// Resolves `range` against a sequence of length `len` into `lo..hi`.
+///
+/// # Panics
+///
+/// Panics if the range is decreasing or ends past `len`.
+pub(crate) fn bounds(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let lo = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+    let hi = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        lo <= hi && hi <= len,
+        "range {lo}..{hi} out of bounds for length {len}"
+    );
+    (lo, hi)
+}
@@ -0,0 +1,388 @@
+//! A van Emde Boas tree over integer keys of bounded width.
+//!
+//! The van Emde Boas tree (van Emde Boas, 1975) stores a set of keys from
+//! a universe of `2^w`, splitting each key into its high and low halves.
+//! The keys sharing a high half form a cluster, itself a tree over the
+//! `2^(w/2)` low halves, and a summary tree records which clusters are
+//! nonempty. Each node keeps its minimum outside the clusters and caches
+//! its maximum, so every operation recurses into only one of a cluster and
+//! the summary. The width halves at each step, giving `O(log w)`, that is
+//! `O(log log U)`, for insert, remove, member, successor and predecessor,
+//! independent of how many keys are stored.
+//!
+//! Two standard reductions keep space in check. Clusters are allocated only
+//! when nonempty and kept in hash maps rather than arrays of `2^(w/2)`
+//! slots, so space is `O(n)` rather than `O(U)`; and universes of at most
+//! 64 keys are a single bitmap word, answered with bit tricks, which cuts
+//! the recursion's last levels.
+//!
+//! Keys are `u32` and the width is chosen per tree, up to 32, so the tree
+//! suits IPv4 routing tables, timer wheels and schedulers, where successor
+//! queries over a dense key range dominate.
+
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+use crate::probabilistic::StableHasher;
+
+type Clusters = HashMap<u32, Node, BuildHasherDefault<StableHasher>>;
+
+/// Widths up to this are stored as one bitmap word.
+const LEAF_BITS: u32 = 6;
+
+#[derive(Clone, Debug)]
+enum Node {
+    /// A nonempty universe of at most 64 keys.
+    Leaf(u64),
+    Branch(Box<Branch>),
+}
+
+#[derive(Clone, Debug)]
+struct Branch {
+    /// The least key, stored in no cluster.
+    min: u32,
+    /// The greatest key, also stored in its cluster unless it is `min`.
+    max: u32,
+    /// The high halves of the nonempty clusters.
+    summary: Option<Node>,
+    clusters: Clusters,
+}
+
+/// Bits in the low half of a key of width `bits`.
+fn low_bits(bits: u32) -> u32 {
+    bits / 2
+}
+
+fn split(x: u32, bits: u32) -> (u32, u32) {
+    let low = low_bits(bits);
+    (x >> low, x & ((1 << low) - 1))
+}
+
+fn join(high: u32, low: u32, bits: u32) -> u32 {
+    high << low_bits(bits) | low
+}
+
+impl Node {
+    fn new(x: u32, bits: u32) -> Self {
+        if bits <= LEAF_BITS {
+            Node::Leaf(1 << x)
+        } else {
+            Node::Branch(Box::new(Branch {
+                min: x,
+                max: x,
+                summary: None,
+                clusters: Clusters::default(),
+            }))
+        }
+    }
+
+    fn min(&self) -> u32 {
+        match self {
+            Node::Leaf(w) => w.trailing_zeros(),
+            Node::Branch(b) => b.min,
+        }
+    }
+
+    fn max(&self) -> u32 {
+        match self {
+            Node::Leaf(w) => 63 - w.leading_zeros(),
+            Node::Branch(b) => b.max,
+        }
+    }
+
+    fn contains(&self, x: u32, bits: u32) -> bool {
+        match self {
+            Node::Leaf(w) => w >> x & 1 == 1,
+            Node::Branch(b) => {
+                if x == b.min || x == b.max {
+                    return true;
+                }
+                let (h, l) = split(x, bits);
+                b.clusters
+                    .get(&h)
+                    .is_some_and(|c| c.contains(l, low_bits(bits)))
+            }
+        }
+    }
+
+    /// Adds `x`, returning whether it was absent.
+    fn insert(&mut self, mut x: u32, bits: u32) -> bool {
+        let b = match self {
+            Node::Leaf(w) => {
+                let absent = *w >> x & 1 == 0;
+                *w |= 1 << x;
+                return absent;
+            }
+            Node::Branch(b) => b,
+        };
+        if x == b.min || x == b.max {
+            return false;
+        }
+        if x < b.min {
+            std::mem::swap(&mut x, &mut b.min);
+        }
+        b.max = b.max.max(x);
+        let (h, l) = split(x, bits);
+        match b.clusters.get_mut(&h) {
+            Some(cluster) => cluster.insert(l, low_bits(bits)),
+            None => {
+                b.clusters.insert(h, Node::new(l, low_bits(bits)));
+                let high = bits - low_bits(bits);
+                match &mut b.summary {
+                    Some(summary) => {
+                        summary.insert(h, high);
+                    }
+                    None => b.summary = Some(Node::new(h, high)),
+                }
+                true
+            }
+        }
+    }
+
+    /// Removes `x`, returning `None` if it was absent and otherwise
+    /// whether the node is now empty.
+    fn remove(&mut self, mut x: u32, bits: u32) -> Option<bool> {
+        let b = match self {
+            Node::Leaf(w) => {
+                if *w >> x & 1 == 0 {
+                    return None;
+                }
+                *w &= !(1 << x);
+                return Some(*w == 0);
+            }
+            Node::Branch(b) => b,
+        };
+        if x == b.min {
+            let Some(summary) = &b.summary else {
+                return Some(true);
+            };
+            // Promote the least clustered key to be the new minimum.
+            let h = summary.min();
+            x = join(h, b.clusters[&h].min(), bits);
+            b.min = x;
+        }
+        let (h, l) = split(x, bits);
+        let cluster = b.clusters.get_mut(&h)?;
+        if cluster.remove(l, low_bits(bits))? {
+            b.clusters.remove(&h);
+            let summary = b.summary.as_mut().expect("nonempty cluster listed");
+            if summary
+                .remove(h, bits - low_bits(bits))
+                .expect("cluster listed")
+            {
+                b.summary = None;
+            }
+        }
+        if x == b.max {
+            b.max = match &b.summary {
+                Some(summary) => {
+                    let h = summary.max();
+                    join(h, b.clusters[&h].max(), bits)
+                }
+                None => b.min,
+            };
+        }
+        Some(false)
+    }
+
+    /// The least key above `x`.
+    fn successor(&self, x: u32, bits: u32) -> Option<u32> {
+        let b = match self {
+            Node::Leaf(w) => {
+                let above = w.checked_shr(x + 1)?.checked_shl(x + 1)?;
+                return (above != 0).then(|| above.trailing_zeros());
+            }
+            Node::Branch(b) => b,
+        };
+        if x < b.min {
+            return Some(b.min);
+        }
+        if x >= b.max {
+            return None;
+        }
+        let (h, l) = split(x, bits);
+        if let Some(cluster) = b.clusters.get(&h) {
+            if l < cluster.max() {
+                let l = cluster.successor(l, low_bits(bits))?;
+                return Some(join(h, l, bits));
+            }
+        }
+        let h = b.summary.as_ref()?.successor(h, bits - low_bits(bits))?;
+        Some(join(h, b.clusters[&h].min(), bits))
+    }
+
+    /// The greatest key below `x`.
+    fn predecessor(&self, x: u32, bits: u32) -> Option<u32> {
+        let b = match self {
+            Node::Leaf(w) => {
+                let below = w & ((1u64 << x) - 1);
+                return (below != 0).then(|| 63 - below.leading_zeros());
+            }
+            Node::Branch(b) => b,
+        };
+        if x > b.max {
+            return Some(b.max);
+        }
+        if x <= b.min {
+            return None;
+        }
+        let (h, l) = split(x, bits);
+        if let Some(cluster) = b.clusters.get(&h) {
+            if l > cluster.min() {
+                let l = cluster.predecessor(l, low_bits(bits))?;
+                return Some(join(h, l, bits));
+            }
+        }
+        match b
+            .summary
+            .as_ref()
+            .and_then(|s| s.predecessor(h, bits - low_bits(bits)))
+        {
+            Some(h) => Some(join(h, b.clusters[&h].max(), bits)),
+            None => Some(b.min),
+        }
+    }
+}
+
+/// A set of `u32` keys below `2^bits`, with `O(log log U)` operations.
+#[derive(Clone, Debug)]
+pub struct VebTree {
+    bits: u32,
+    root: Option<Node>,
+    len: usize,
+}
+
+impl VebTree {
+    /// Creates an empty tree over the keys below `2^bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `bits` is between 1 and 32.
+    pub fn new(bits: u32) -> Self {
+        assert!((1..=32).contains(&bits), "key width must be 1 to 32 bits");
+        VebTree {
+            bits,
+            root: None,
+            len: 0,
+        }
+    }
+
+    /// The key width in bits.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// The number of possible keys, `2^bits`.
+    pub fn universe(&self) -> u64 {
+        1 << self.bits
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// Whether `key` is stored.
+    pub fn contains(&self, key: u32) -> bool {
+        u64::from(key) < self.universe()
+            && self
+                .root
+                .as_ref()
+                .is_some_and(|r| r.contains(key, self.bits))
+    }
+
+    /// Adds `key`, returning whether it was absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not below `2^bits`.
+    pub fn insert(&mut self, key: u32) -> bool {
+        assert!(u64::from(key) < self.universe(), "key outside the universe");
+        let inserted = match &mut self.root {
+            Some(root) => root.insert(key, self.bits),
+            None => {
+                self.root = Some(Node::new(key, self.bits));
+                true
+            }
+        };
+        self.len += usize::from(inserted);
+        inserted
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: u32) -> bool {
+        if u64::from(key) >= self.universe() {
+            return false;
+        }
+        let Some(root) = &mut self.root else {
+            return false;
+        };
+        match root.remove(key, self.bits) {
+            None => false,
+            Some(emptied) => {
+                if emptied {
+                    self.root = None;
+                }
+                self.len -= 1;
+                true
+            }
+        }
+    }
+
+    /// The least key.
+    pub fn min(&self) -> Option<u32> {
+        self.root.as_ref().map(Node::min)
+    }
+
+    /// The greatest key.
+    pub fn max(&self) -> Option<u32> {
+        self.root.as_ref().map(Node::max)
+    }
+
+    /// The least key strictly above `key`.
+    pub fn successor(&self, key: u32) -> Option<u32> {
+        if u64::from(key) >= self.universe() {
+            return None;
+        }
+        self.root.as_ref()?.successor(key, self.bits)
+    }
+
+    /// The greatest key strictly below `key`.
+    pub fn predecessor(&self, key: u32) -> Option<u32> {
+        let root = self.root.as_ref()?;
+        if u64::from(key) >= self.universe() {
+            return Some(root.max());
+        }
+        root.predecessor(key, self.bits)
+    }
+
+    /// Iterates over the keys in ascending order, one successor query per
+    /// key.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        let mut next = self.min();
+        std::iter::from_fn(move || {
+            let key = next?;
+            next = self.successor(key);
+            Some(key)
+        })
+    }
+}
+
+impl Extend<u32> for VebTree {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
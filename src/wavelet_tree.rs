@@ -0,0 +1,242 @@
+//! A wavelet tree: rank, select and order statistics over an integer
+//! sequence in compressed space.
+//!
+//! A wavelet tree (Grossi, Gupta and Vitter, 2003) over values of `b` bits
+//! splits the sequence by the values' top bit, records that bit for every
+//! position in a bit vector, and recurses on the two halves with the next
+//! bit. Following a value down the tree by its bits, using rank on each
+//! level's bit vector to track its positions, answers in `O(b)` rank
+//! operations: how often a value occurs in a prefix (rank), where its
+//! `k`th occurrence is (select), what value sits at a position (access),
+//! the `k`th smallest value in a range (range quantile), and how many
+//! values of a range fall in an interval. The sequence itself is not
+//! stored: the bit vectors are the sequence, in `n b` bits plus the rank
+//! and select directories.
+//!
+//! The levels are laid out as a wavelet matrix (Claude, Navarro and
+//! Ordóñez, 2015): each level is one [`BitVector`] over all `n` positions,
+//! with the positions whose bit was zero moved before those whose bit was
+//! one, stably. That avoids storing a tree topology, works for any
+//! alphabet size without padding it to a power of two, and keeps the
+//! queries the same once a level's count of zeros is known.
+//!
+//! [`BitVector`]: crate::bit_vector::BitVector
+
+use std::ops::{Range, RangeBounds};
+
+use crate::bit_vector::BitVector;
+use crate::util::bounds;
+
+/// A wavelet tree over a sequence of `u64` values.
+#[derive(Clone, Debug)]
+pub struct WaveletTree {
+    /// One level per value bit, the most significant first.
+    levels: Vec<BitVector>,
+    /// Number of zeros in each level.
+    zeros: Vec<usize>,
+    len: usize,
+}
+
+impl WaveletTree {
+    /// Builds a wavelet tree over `values`, with as many levels as the
+    /// largest value has bits.
+    pub fn new(values: &[u64]) -> Self {
+        let max = values.iter().copied().max().unwrap_or(0);
+        let bits = (64 - max.leading_zeros()) as usize;
+        let mut current = values.to_vec();
+        let mut levels = Vec::with_capacity(bits);
+        let mut zeros = Vec::with_capacity(bits);
+        for level in 0..bits {
+            let bit = bits - 1 - level;
+            let bv: BitVector = current.iter().map(|&v| v >> bit & 1 == 1).collect();
+            zeros.push(bv.count_zeros());
+            levels.push(bv);
+            let (low, high): (Vec<u64>, Vec<u64>) =
+                current.iter().partition(|&&v| v >> bit & 1 == 0);
+            current = low;
+            current.extend(high);
+        }
+        WaveletTree {
+            levels,
+            zeros,
+            len: values.len(),
+        }
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of bits per value, the tree's height.
+    pub fn bits(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The value at position `i`, in `O(bits)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, mut i: usize) -> u64 {
+        assert!(
+            i < self.len,
+            "index {i} out of bounds for length {}",
+            self.len
+        );
+        let mut value = 0;
+        for (level, bv) in self.levels.iter().enumerate() {
+            let bit = bv.get(i);
+            value = value << 1 | u64::from(bit);
+            i = self.step(level, i, bit);
+        }
+        value
+    }
+
+    /// Iterates over the values.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    /// Number of occurrences of `value` before position `i`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is greater than the length.
+    pub fn rank(&self, value: u64, i: usize) -> usize {
+        assert!(
+            i <= self.len,
+            "rank {i} out of bounds for length {}",
+            self.len
+        );
+        if !self.fits(value) {
+            return 0;
+        }
+        let (mut lo, mut hi) = (0, i);
+        for level in 0..self.bits() {
+            let bit = self.bit(value, level);
+            lo = self.step(level, lo, bit);
+            hi = self.step(level, hi, bit);
+        }
+        hi - lo
+    }
+
+    /// The position of the occurrence of `value` with `k` occurrences
+    /// before it, or `None` if it occurs `k` times or fewer.
+    pub fn select(&self, value: u64, k: usize) -> Option<usize> {
+        if !self.fits(value) {
+            return None;
+        }
+        // Follow the value down to where its occurrences end up, then
+        // the chosen one back up.
+        let (mut lo, mut hi) = (0, self.len);
+        for level in 0..self.bits() {
+            let bit = self.bit(value, level);
+            lo = self.step(level, lo, bit);
+            hi = self.step(level, hi, bit);
+        }
+        if k >= hi - lo {
+            return None;
+        }
+        let mut position = lo + k;
+        for level in (0..self.bits()).rev() {
+            let bv = &self.levels[level];
+            position = if self.bit(value, level) {
+                bv.select1(position - self.zeros[level])
+            } else {
+                bv.select0(position)
+            }
+            .expect("rank bounds the occurrences");
+        }
+        Some(position)
+    }
+
+    /// The value that would sit at index `k` if the values in `range` were
+    /// sorted, or `None` if the range holds `k` values or fewer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn quantile(&self, range: impl RangeBounds<usize>, mut k: usize) -> Option<u64> {
+        let (mut lo, mut hi) = bounds(range, self.len);
+        if k >= hi - lo {
+            return None;
+        }
+        let mut value = 0;
+        for (level, bv) in self.levels.iter().enumerate() {
+            let zeros = bv.rank0(hi) - bv.rank0(lo);
+            let bit = k >= zeros;
+            if bit {
+                k -= zeros;
+            }
+            value = value << 1 | u64::from(bit);
+            lo = self.step(level, lo, bit);
+            hi = self.step(level, hi, bit);
+        }
+        Some(value)
+    }
+
+    /// Number of values in `range` that lie in `values`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn count(&self, range: impl RangeBounds<usize>, values: Range<u64>) -> usize {
+        let (lo, hi) = bounds(range, self.len);
+        if values.start >= values.end {
+            return 0;
+        }
+        self.count_less(lo, hi, values.end) - self.count_less(lo, hi, values.start)
+    }
+
+    /// Number of values in `lo..hi` less than `value`.
+    fn count_less(&self, mut lo: usize, mut hi: usize, value: u64) -> usize {
+        if !self.fits(value) {
+            return hi - lo;
+        }
+        let mut count = 0;
+        for (level, bv) in self.levels.iter().enumerate() {
+            let bit = self.bit(value, level);
+            if bit {
+                // Everything with a zero here is smaller.
+                count += bv.rank0(hi) - bv.rank0(lo);
+            }
+            lo = self.step(level, lo, bit);
+            hi = self.step(level, hi, bit);
+        }
+        count
+    }
+
+    /// Where position `i` of `level` moves on the next level, for positions
+    /// holding `bit` there.
+    fn step(&self, level: usize, i: usize, bit: bool) -> usize {
+        let bv = &self.levels[level];
+        if bit {
+            self.zeros[level] + bv.rank1(i)
+        } else {
+            bv.rank0(i)
+        }
+    }
+
+    /// The bit of `value` that `level` splits on.
+    fn bit(&self, value: u64, level: usize) -> bool {
+        value >> (self.bits() - 1 - level) & 1 == 1
+    }
+
+    /// Whether `value` has no more bits than the tree.
+    fn fits(&self, value: u64) -> bool {
+        self.bits() == 64 || value >> self.bits() == 0
+    }
+}
+
+impl FromIterator<u64> for WaveletTree {
+    fn from_iter<I: IntoIterator<Item = u64>>(iter: I) -> Self {
+        let values: Vec<u64> = iter.into_iter().collect();
+        WaveletTree::new(&values)
+    }
+}
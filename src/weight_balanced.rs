@@ -0,0 +1,701 @@
+//! A persistent weight-balanced tree, with join-based set algebra.
+//!
+//! A weight-balanced tree (Nievergelt and Reingold, 1972; Adams, 1993)
+//! balances each node by the sizes of its subtrees rather than their
+//! heights: the weight of a subtree is its size plus one, and neither
+//! child of a node may outweigh the other by more than a factor of
+//! [`DELTA`]. A single or double rotation, chosen by comparing the inner
+//! and outer grandchildren against [`RATIO`], restores the bound after an
+//! insertion or removal. The parameters `(3, 2)` are the only integer pair
+//! for which that is proved to always work (Hirai and Yamamoto, 2011).
+//!
+//! Because every node knows its size, the tree is also an order-statistic
+//! tree, and two trees meet through one primitive: [`join`] of a left
+//! tree, a key and a right tree descends the heavier side's spine until
+//! the weights match, and returns with rotations, in time logarithmic in
+//! the ratio of the sizes. [`split`] and [`merge`] follow from it, and
+//! union, intersection and difference split one tree by the root of the
+//! other and recurse, which costs `O(m log(n / m + 1))` for sizes
+//! `m <= n` (Blelloch, Ferizovic and Sun, 2016).
+//!
+//! The tree is persistent in the same way as the [`HamtMap`]: nodes are
+//! shared between clones through `Arc`, a clone is `O(1)`, and a write
+//! copies only the nodes on its path that another tree also holds, so a
+//! tree that is not shared is updated in place. The set operations take
+//! their operands by reference and share every untouched subtree with
+//! them.
+//!
+//! That makes the tree a store of snapshots: keep a clone of each version,
+//! in `O(1)` and with no copying, and read or iterate any of them later.
+//! [`diff`] lists the differences between two versions in key order, and
+//! skips every subtree the two still share, so comparing a snapshot with
+//! one derived from it by a few updates costs time in proportion to the
+//! updates, not the size of the map.
+//!
+//! [`join`]: WeightBalancedTree::join
+//! [`split`]: WeightBalancedTree::split
+//! [`merge`]: WeightBalancedTree::merge
+//! [`diff`]: WeightBalancedTree::diff
+//! [`HamtMap`]: crate::hamt::HamtMap
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+/// Neither child may weigh more than this many times its sibling.
+pub const DELTA: usize = 3;
+
+/// A rotation is single unless the inner grandchild weighs at least this
+/// many times the outer one.
+pub const RATIO: usize = 2;
+
+type Tree<K, V> = Option<Arc<Node<K, V>>>;
+
+/// The entries below a key, the entry at it and the entries above it.
+type Split<K, V> = (Tree<K, V>, Option<(K, V)>, Tree<K, V>);
+
+/// A difference between two versions of a map, as listed by
+/// [`WeightBalancedTree::diff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change<'a, K, V> {
+    /// The key is only in the newer map.
+    Added(&'a K, &'a V),
+    /// The key is only in the older map.
+    Removed(&'a K, &'a V),
+    /// The key is in both, with the older value and then the newer one.
+    Changed(&'a K, &'a V, &'a V),
+}
+
+#[derive(Clone, Debug)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    /// Number of nodes in this subtree.
+    size: usize,
+    left: Tree<K, V>,
+    right: Tree<K, V>,
+}
+
+impl<K, V> Node<K, V> {
+    fn update(&mut self) {
+        self.size = 1 + size(&self.left) + size(&self.right);
+    }
+}
+
+/// An ordered map from keys of type `K` to values of type `V`, balanced
+/// by subtree weight and persistent.
+#[derive(Clone)]
+pub struct WeightBalancedTree<K, V> {
+    root: Tree<K, V>,
+}
+
+impl<K: Ord + Clone, V: Clone> Default for WeightBalancedTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> WeightBalancedTree<K, V> {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        WeightBalancedTree { root: None }
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Whether the map holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            link = match key.cmp(n.key.borrow()) {
+                Ordering::Less => &n.left,
+                Ordering::Greater => &n.right,
+                Ordering::Equal => return Some(&n.value),
+            };
+        }
+        None
+    }
+
+    /// Whether an entry is stored under `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.select(0)
+    }
+
+    /// The entry with the largest key.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.select(self.len().checked_sub(1)?)
+    }
+
+    /// Inserts an entry, returning the value it replaced, if any. Copies
+    /// the nodes on the key's path that are shared with other trees.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        insert(&mut self.root, key, value)
+    }
+
+    /// Removes the entry stored under `key`, returning it.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        // Check first, so a miss copies nothing.
+        if !self.contains_key(key) {
+            return None;
+        }
+        Some(remove(&mut self.root, key))
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+
+    /// A copy of the map with `value` inserted under `key`, sharing all
+    /// but one path with this one.
+    pub fn inserted(&self, key: K, value: V) -> Self {
+        let mut map = self.clone();
+        map.insert(key, value);
+        map
+    }
+
+    /// A copy of the map without `key`.
+    pub fn removed<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut map = self.clone();
+        map.remove(key);
+        map
+    }
+
+    /// The entry with the `index`-th smallest key, counting from zero.
+    pub fn select(&self, mut index: usize) -> Option<(&K, &V)> {
+        let mut link = &self.root;
+        while let Some(n) = link {
+            let left = size(&n.left);
+            link = match index.cmp(&left) {
+                Ordering::Less => &n.left,
+                Ordering::Equal => return Some((&n.key, &n.value)),
+                Ordering::Greater => {
+                    index -= left + 1;
+                    &n.right
+                }
+            };
+        }
+        None
+    }
+
+    /// The number of keys below `key`.
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut rank = 0;
+        let mut link = &self.root;
+        while let Some(n) = link {
+            if n.key.borrow() < key {
+                rank += size(&n.left) + 1;
+                link = &n.right;
+            } else {
+                link = &n.left;
+            }
+        }
+        rank
+    }
+
+    /// The tree of the entries of `left`, then `key` and `value`, then the
+    /// entries of `right`, in `O(log(n / m + 1))` for sizes `m <= n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless every key of `left` is below `key` and every key of
+    /// `right` is above it.
+    pub fn join(left: Self, key: K, value: V, right: Self) -> Self {
+        if let Some((last, _)) = left.last_key_value() {
+            assert!(*last < key, "joined trees must be ordered");
+        }
+        if let Some((first, _)) = right.first_key_value() {
+            assert!(key < *first, "joined trees must be ordered");
+        }
+        WeightBalancedTree {
+            root: Some(join(left.root, key, value, right.root)),
+        }
+    }
+
+    /// Moves every entry of `other` into this tree, in `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless every key in this tree is below every key in `other`.
+    pub fn merge(&mut self, other: Self) {
+        if let (Some((a, _)), Some((b, _))) = (self.last_key_value(), other.first_key_value()) {
+            assert!(a < b, "merged trees must not overlap");
+        }
+        self.root = merge(self.root.take(), other.root);
+    }
+
+    /// The entries below `key`, the value stored under `key` if any, and
+    /// the entries above it, in `O(log n)`. Both trees share nodes with
+    /// this one.
+    pub fn split<Q>(&self, key: &Q) -> (Self, Option<V>, Self)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (left, mid, right) = split(self.root.clone(), key);
+        (
+            WeightBalancedTree { root: left },
+            mid.map(|(_, v)| v),
+            WeightBalancedTree { root: right },
+        )
+    }
+
+    /// The entries of either tree, taking this tree's value for a key in
+    /// both, in `O(m log(n / m + 1))`.
+    pub fn union(&self, other: &Self) -> Self {
+        WeightBalancedTree {
+            root: union(self.root.clone(), other.root.clone()),
+        }
+    }
+
+    /// The entries of this tree whose keys are also in `other`, in
+    /// `O(m log(n / m + 1))`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        WeightBalancedTree {
+            root: intersection(self.root.clone(), other.root.clone()),
+        }
+    }
+
+    /// The entries of this tree whose keys are not in `other`, in
+    /// `O(m log(n / m + 1))`.
+    pub fn difference(&self, other: &Self) -> Self {
+        WeightBalancedTree {
+            root: difference(self.root.clone(), other.root.clone()),
+        }
+    }
+
+    /// Whether the two maps are the same version: one is a clone of the
+    /// other with no writes to either since, in `O(1)`.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (&self.root, &other.root) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+
+    /// Lists the differences from `self` to `newer` in key order, skipping
+    /// the subtrees the two share.
+    pub fn diff<'a>(&'a self, newer: &'a Self) -> impl Iterator<Item = Change<'a, K, V>> + 'a
+    where
+        V: PartialEq,
+    {
+        let start = |tree: &'a Tree<K, V>| tree.iter().map(Pending::Tree).collect::<Vec<_>>();
+        let (mut old, mut new) = (start(&self.root), start(&newer.root));
+        std::iter::from_fn(move || loop {
+            // Whichever side comes first is opened up or reported; when
+            // both start at the same key, a subtree is opened before an
+            // entry, and the larger subtree first, so shared ones line up.
+            let old_side = match (old.last(), new.last()) {
+                (None, None) => return None,
+                (Some(Pending::Tree(a)), Some(Pending::Tree(b))) if Arc::ptr_eq(a, b) => {
+                    old.pop();
+                    new.pop();
+                    continue;
+                }
+                (Some(&a), Some(&b)) => match (a.first().cmp(b.first()), a, b) {
+                    (Ordering::Less, ..) => true,
+                    (Ordering::Greater, ..) => false,
+                    (_, Pending::Entry(x), Pending::Entry(y)) => {
+                        old.pop();
+                        new.pop();
+                        if std::ptr::eq(x, y) || x.value == y.value {
+                            continue;
+                        }
+                        return Some(Change::Changed(&x.key, &x.value, &y.value));
+                    }
+                    (_, Pending::Entry(_), Pending::Tree(_)) => false,
+                    (_, Pending::Tree(_), Pending::Entry(_)) => true,
+                    _ => a.len() >= b.len(),
+                },
+                (old_side, _) => old_side.is_some(),
+            };
+            let stack = if old_side { &mut old } else { &mut new };
+            match stack.pop().expect("nonempty side") {
+                Pending::Tree(n) => {
+                    stack.extend(n.right.iter().map(Pending::Tree));
+                    stack.push(Pending::Entry(n));
+                    stack.extend(n.left.iter().map(Pending::Tree));
+                }
+                Pending::Entry(n) if old_side => return Some(Change::Removed(&n.key, &n.value)),
+                Pending::Entry(n) => return Some(Change::Added(&n.key, &n.value)),
+            }
+        })
+    }
+
+    /// Iterates over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.range::<K, _>(..)
+    }
+
+    /// Iterates over the entries whose keys lie in `range`, in key order.
+    pub fn range<'a, Q, R>(&'a self, range: R) -> impl Iterator<Item = (&'a K, &'a V)> + 'a
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'a,
+        R: RangeBounds<Q> + 'a,
+    {
+        // The stack holds the nodes at or above the start bound whose left
+        // subtrees have been dealt with.
+        let mut stack: Vec<&Node<K, V>> = Vec::new();
+        let mut link = self.root.as_deref();
+        while let Some(n) = link {
+            let above = match range.start_bound() {
+                Bound::Included(b) => n.key.borrow() >= b,
+                Bound::Excluded(b) => n.key.borrow() > b,
+                Bound::Unbounded => true,
+            };
+            if above {
+                stack.push(n);
+                link = n.left.as_deref();
+            } else {
+                link = n.right.as_deref();
+            }
+        }
+        std::iter::from_fn(move || {
+            let n = stack.pop()?;
+            let inside = match range.end_bound() {
+                Bound::Included(b) => n.key.borrow() <= b,
+                Bound::Excluded(b) => n.key.borrow() < b,
+                Bound::Unbounded => true,
+            };
+            if !inside {
+                stack.clear();
+                return None;
+            }
+            let mut link = n.right.as_deref();
+            while let Some(c) = link {
+                stack.push(c);
+                link = c.left.as_deref();
+            }
+            Some((&n.key, &n.value))
+        })
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + PartialEq> PartialEq for WeightBalancedTree<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord + Clone, V: Clone + Eq> Eq for WeightBalancedTree<K, V> {}
+
+impl<K: Ord + Clone + fmt::Debug, V: Clone + fmt::Debug> fmt::Debug for WeightBalancedTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Extend<(K, V)> for WeightBalancedTree<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> FromIterator<(K, V)> for WeightBalancedTree<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut tree = WeightBalancedTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+/// What remains of one side of a diff: a subtree still to open, or a
+/// single entry.
+enum Pending<'a, K, V> {
+    Tree(&'a Arc<Node<K, V>>),
+    Entry(&'a Node<K, V>),
+}
+
+impl<K, V> Clone for Pending<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for Pending<'_, K, V> {}
+
+impl<'a, K, V> Pending<'a, K, V> {
+    /// The smallest key to come.
+    fn first(self) -> &'a K {
+        match self {
+            Pending::Tree(mut n) => {
+                while let Some(l) = &n.left {
+                    n = l;
+                }
+                &n.key
+            }
+            Pending::Entry(n) => &n.key,
+        }
+    }
+
+    /// Number of entries to come.
+    fn len(self) -> usize {
+        match self {
+            Pending::Tree(n) => n.size,
+            Pending::Entry(_) => 1,
+        }
+    }
+}
+
+fn size<K, V>(tree: &Tree<K, V>) -> usize {
+    tree.as_ref().map_or(0, |n| n.size)
+}
+
+fn weight<K, V>(tree: &Tree<K, V>) -> usize {
+    size(tree) + 1
+}
+
+fn node<K, V>(left: Tree<K, V>, key: K, value: V, right: Tree<K, V>) -> Arc<Node<K, V>> {
+    Arc::new(Node {
+        key,
+        value,
+        size: size(&left) + 1 + size(&right),
+        left,
+        right,
+    })
+}
+
+/// Takes a node apart, moving its fields out if no other tree holds it
+/// and cloning them otherwise.
+fn expose<K: Clone, V: Clone>(n: Arc<Node<K, V>>) -> (Tree<K, V>, K, V, Tree<K, V>) {
+    let n = Arc::unwrap_or_clone(n);
+    (n.left, n.key, n.value, n.right)
+}
+
+fn rotate_left<K: Clone, V: Clone>(t: &mut Arc<Node<K, V>>) {
+    let n = Arc::make_mut(t);
+    let mut r = n.right.take().expect("rotated node has a right child");
+    n.right = Arc::make_mut(&mut r).left.take();
+    n.update();
+    std::mem::swap(t, &mut r);
+    let top = Arc::make_mut(t);
+    top.left = Some(r);
+    top.update();
+}
+
+fn rotate_right<K: Clone, V: Clone>(t: &mut Arc<Node<K, V>>) {
+    let n = Arc::make_mut(t);
+    let mut l = n.left.take().expect("rotated node has a left child");
+    n.left = Arc::make_mut(&mut l).right.take();
+    n.update();
+    std::mem::swap(t, &mut l);
+    let top = Arc::make_mut(t);
+    top.right = Some(l);
+    top.update();
+}
+
+/// Restores the weight bound at `t`, whose children are balanced and off
+/// by at most one update or one join step.
+fn rebalance<K: Clone, V: Clone>(t: &mut Arc<Node<K, V>>) {
+    let (wl, wr) = (weight(&t.left), weight(&t.right));
+    if wr > DELTA * wl {
+        let r = t.right.as_ref().expect("heavy right child");
+        if weight(&r.left) >= RATIO * weight(&r.right) {
+            rotate_right(Arc::make_mut(t).right.as_mut().expect("heavy right child"));
+        }
+        rotate_left(t);
+    } else if wl > DELTA * wr {
+        let l = t.left.as_ref().expect("heavy left child");
+        if weight(&l.right) >= RATIO * weight(&l.left) {
+            rotate_left(Arc::make_mut(t).left.as_mut().expect("heavy left child"));
+        }
+        rotate_right(t);
+    }
+}
+
+fn insert<K: Ord + Clone, V: Clone>(tree: &mut Tree<K, V>, key: K, value: V) -> Option<V> {
+    let Some(t) = tree else {
+        *tree = Some(node(None, key, value, None));
+        return None;
+    };
+    let n = Arc::make_mut(t);
+    let old = match key.cmp(&n.key) {
+        Ordering::Equal => return Some(std::mem::replace(&mut n.value, value)),
+        Ordering::Less => insert(&mut n.left, key, value),
+        Ordering::Greater => insert(&mut n.right, key, value),
+    };
+    if old.is_none() {
+        n.update();
+        rebalance(t);
+    }
+    old
+}
+
+/// Removes `key`, which must be present, from below `tree`.
+fn remove<K, V, Q>(tree: &mut Tree<K, V>, key: &Q) -> (K, V)
+where
+    K: Ord + Clone + Borrow<Q>,
+    V: Clone,
+    Q: Ord + ?Sized,
+{
+    let t = tree.as_mut().expect("removed key is present");
+    let n = Arc::make_mut(t);
+    let entry = match key.cmp(n.key.borrow()) {
+        Ordering::Less => remove(&mut n.left, key),
+        Ordering::Greater => remove(&mut n.right, key),
+        Ordering::Equal => {
+            let (left, k, v, right) = expose(tree.take().expect("matched node"));
+            *tree = merge(left, right);
+            return (k, v);
+        }
+    };
+    n.update();
+    rebalance(t);
+    entry
+}
+
+/// Unlinks the least entry of a nonempty `tree`.
+fn pop_first<K: Clone, V: Clone>(tree: &mut Tree<K, V>) -> (K, V) {
+    let t = tree.as_mut().expect("nonempty subtree");
+    let n = Arc::make_mut(t);
+    if n.left.is_some() {
+        let entry = pop_first(&mut n.left);
+        n.update();
+        rebalance(t);
+        return entry;
+    }
+    let (_, k, v, right) = expose(tree.take().expect("nonempty subtree"));
+    *tree = right;
+    (k, v)
+}
+
+/// Joins `left`, the entry and `right`, whose keys are in that order.
+fn join<K: Clone, V: Clone>(
+    left: Tree<K, V>,
+    key: K,
+    value: V,
+    right: Tree<K, V>,
+) -> Arc<Node<K, V>> {
+    let (wl, wr) = (weight(&left), weight(&right));
+    if wr > DELTA * wl {
+        // Descend the right tree's left spine until the weights match.
+        let mut r = right.expect("heavy right tree");
+        let n = Arc::make_mut(&mut r);
+        n.left = Some(join(left, key, value, n.left.take()));
+        n.update();
+        rebalance(&mut r);
+        r
+    } else if wl > DELTA * wr {
+        let mut l = left.expect("heavy left tree");
+        let n = Arc::make_mut(&mut l);
+        n.right = Some(join(n.right.take(), key, value, right));
+        n.update();
+        rebalance(&mut l);
+        l
+    } else {
+        node(left, key, value, right)
+    }
+}
+
+/// Joins two trees, every key of `left` being below every key of `right`.
+fn merge<K: Clone, V: Clone>(left: Tree<K, V>, mut right: Tree<K, V>) -> Tree<K, V> {
+    if left.is_none() || right.is_none() {
+        return left.or(right);
+    }
+    let (key, value) = pop_first(&mut right);
+    Some(join(left, key, value, right))
+}
+
+/// Splits `tree` into the entries below `key`, the entry at it and the
+/// entries above it.
+fn split<K, V, Q>(tree: Tree<K, V>, key: &Q) -> Split<K, V>
+where
+    K: Clone + Borrow<Q>,
+    V: Clone,
+    Q: Ord + ?Sized,
+{
+    let Some(t) = tree else {
+        return (None, None, None);
+    };
+    let (left, k, v, right) = expose(t);
+    match key.cmp(k.borrow()) {
+        Ordering::Equal => (left, Some((k, v)), right),
+        Ordering::Less => {
+            let (ll, mid, lr) = split(left, key);
+            (ll, mid, Some(join(lr, k, v, right)))
+        }
+        Ordering::Greater => {
+            let (rl, mid, rr) = split(right, key);
+            (Some(join(left, k, v, rl)), mid, rr)
+        }
+    }
+}
+
+fn union<K: Ord + Clone, V: Clone>(a: Tree<K, V>, b: Tree<K, V>) -> Tree<K, V> {
+    let Some(b) = b else { return a };
+    if a.is_none() {
+        return Some(b);
+    }
+    let (bl, k, v, br) = expose(b);
+    let (al, mid, ar) = split(a, &k);
+    let (k, v) = mid.unwrap_or((k, v));
+    Some(join(union(al, bl), k, v, union(ar, br)))
+}
+
+fn intersection<K: Ord + Clone, V: Clone>(a: Tree<K, V>, b: Tree<K, V>) -> Tree<K, V> {
+    let (Some(_), Some(b)) = (&a, b) else {
+        return None;
+    };
+    let (bl, k, _, br) = expose(b);
+    let (al, mid, ar) = split(a, &k);
+    let (left, right) = (intersection(al, bl), intersection(ar, br));
+    match mid {
+        Some((k, v)) => Some(join(left, k, v, right)),
+        None => merge(left, right),
+    }
+}
+
+fn difference<K: Ord + Clone, V: Clone>(a: Tree<K, V>, b: Tree<K, V>) -> Tree<K, V> {
+    let Some(b) = b else { return a };
+    a.as_ref()?;
+    let (bl, k, _, br) = expose(b);
+    let (al, _, ar) = split(a, &k);
+    merge(difference(al, bl), difference(ar, br))
+}
@@ -0,0 +1,540 @@
+//! X-fast and y-fast tries over integer keys of bounded width.
+//!
+//! An x-fast trie (Willard, 1983) stores a set of `w`-bit keys as the
+//! binary trie of their bits, keeping one hash table per level that maps
+//! every prefix present at that level to the least and greatest keys below
+//! it. Whether a prefix is present is monotone in its length, so the
+//! longest prefix of a query that the trie holds is found by binary search
+//! over the levels, in `O(log w)` hash probes, and its stored extremes and
+//! a doubly linked list of the keys give the successor and predecessor.
+//! Updates touch all `w` levels, and the tables hold `O(n w)` entries.
+//!
+//! A y-fast trie (Willard, 1983) brings both down. Its keys are split into
+//! buckets of `Θ(w)` consecutive keys, each an ordinary balanced tree, and
+//! only one representative per bucket goes into an x-fast trie. A query
+//! finds its bucket in `O(log w)` and finishes inside it in `O(log w)`, so
+//! successor, predecessor and membership cost `O(log log U)`. Buckets
+//! split when they grow past `2w` keys and merge with a neighbor below
+//! `w / 4`, so the x-fast trie changes once per `Θ(w)` updates, updates
+//! cost `O(log log U)` amortized, and space is `O(n)`.
+//!
+//! Here a bucket's representative is a lower bound on its keys rather
+//! than a member, and the first bucket's is always zero, so every key has
+//! a bucket without any key being special. Levels are hashed with the
+//! crate's [`StableHasher`]. Compared with the [`VebTree`], the y-fast trie
+//! takes `u64` keys of any width up to 64 in linear space, at the cost of
+//! expected rather than worst-case bounds; `u32` keys are simply a trie of
+//! width 32.
+//!
+//! [`StableHasher`]: crate::probabilistic::StableHasher
+//! [`VebTree`]: crate::veb::VebTree
+
+use std::collections::{btree_set, BTreeSet, HashMap};
+use std::hash::BuildHasherDefault;
+use std::ops::{Bound, RangeBounds};
+
+use crate::probabilistic::StableHasher;
+
+type Table<V> = HashMap<u64, V, BuildHasherDefault<StableHasher>>;
+
+/// The least and greatest keys below a prefix.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    min: u64,
+    max: u64,
+}
+
+/// Neighbors of a key in sorted order.
+#[derive(Clone, Copy, Debug)]
+struct Links {
+    prev: Option<u64>,
+    next: Option<u64>,
+}
+
+/// A set of `u64` keys below `2^bits` with `O(log log U)` queries and
+/// `O(log U)` updates.
+#[derive(Clone, Debug)]
+pub struct XFastTrie {
+    bits: u32,
+    /// `levels[d]` maps each `d`-bit prefix present to its span.
+    levels: Vec<Table<Span>>,
+    /// The keys themselves, linked in order.
+    leaves: Table<Links>,
+}
+
+impl XFastTrie {
+    /// Creates an empty trie over the keys below `2^bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `bits` is between 1 and 64.
+    pub fn new(bits: u32) -> Self {
+        assert!((1..=64).contains(&bits), "key width must be 1 to 64 bits");
+        XFastTrie {
+            bits,
+            levels: (0..bits).map(|_| Table::default()).collect(),
+            leaves: Table::default(),
+        }
+    }
+
+    /// The key width in bits.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Whether the trie holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        self.levels.iter_mut().for_each(Table::clear);
+        self.leaves.clear();
+    }
+
+    /// Whether `key` is stored, in `O(1)`.
+    pub fn contains(&self, key: u64) -> bool {
+        self.leaves.contains_key(&key)
+    }
+
+    /// The least key.
+    pub fn min(&self) -> Option<u64> {
+        self.span(0, 0).map(|s| s.min)
+    }
+
+    /// The greatest key.
+    pub fn max(&self) -> Option<u64> {
+        self.span(0, 0).map(|s| s.max)
+    }
+
+    /// Adds `key`, returning whether it was absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not below `2^bits`.
+    pub fn insert(&mut self, key: u64) -> bool {
+        assert!(self.in_universe(key), "key outside the universe");
+        if self.contains(key) {
+            return false;
+        }
+        let (prev, next) = (self.predecessor(key), self.successor(key));
+        self.leaves.insert(key, Links { prev, next });
+        if let Some(p) = prev {
+            self.leaves.get_mut(&p).expect("linked key").next = Some(key);
+        }
+        if let Some(n) = next {
+            self.leaves.get_mut(&n).expect("linked key").prev = Some(key);
+        }
+        for d in 0..self.bits {
+            let p = self.prefix(key, d);
+            self.levels[d as usize]
+                .entry(p)
+                .and_modify(|s| {
+                    s.min = s.min.min(key);
+                    s.max = s.max.max(key);
+                })
+                .or_insert(Span { min: key, max: key });
+        }
+        true
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: u64) -> bool {
+        let Some(links) = self.leaves.remove(&key) else {
+            return false;
+        };
+        if let Some(p) = links.prev {
+            self.leaves.get_mut(&p).expect("linked key").next = links.next;
+        }
+        if let Some(n) = links.next {
+            self.leaves.get_mut(&n).expect("linked key").prev = links.prev;
+        }
+        // Recompute each ancestor's span from its children, deepest first.
+        for d in (0..self.bits).rev() {
+            let p = self.prefix(key, d);
+            let low = self.span(d + 1, p << 1);
+            let high = self.span(d + 1, p << 1 | 1);
+            let level = &mut self.levels[d as usize];
+            match (low, high) {
+                (None, None) => {
+                    level.remove(&p);
+                }
+                (l, h) => {
+                    let min = l.or(h).expect("a child").min;
+                    let max = h.or(l).expect("a child").max;
+                    level.insert(p, Span { min, max });
+                }
+            }
+        }
+        true
+    }
+
+    /// The least key strictly above `key`.
+    pub fn successor(&self, key: u64) -> Option<u64> {
+        if !self.in_universe(key) {
+            return None;
+        }
+        let d = self.longest_prefix(key)?;
+        if d == self.bits {
+            return self.leaves[&key].next;
+        }
+        let span = self.levels[d as usize][&self.prefix(key, d)];
+        if self.bit(key, d) == 0 {
+            // Only the upper child exists, and lies wholly above `key`.
+            Some(span.min)
+        } else {
+            self.leaves[&span.max].next
+        }
+    }
+
+    /// The greatest key strictly below `key`.
+    pub fn predecessor(&self, key: u64) -> Option<u64> {
+        if !self.in_universe(key) {
+            return self.max();
+        }
+        let d = self.longest_prefix(key)?;
+        if d == self.bits {
+            return self.leaves[&key].prev;
+        }
+        let span = self.levels[d as usize][&self.prefix(key, d)];
+        if self.bit(key, d) == 1 {
+            Some(span.max)
+        } else {
+            self.leaves[&span.min].prev
+        }
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut next = self.min();
+        std::iter::from_fn(move || {
+            let key = next?;
+            next = self.leaves[&key].next;
+            Some(key)
+        })
+    }
+
+    fn in_universe(&self, key: u64) -> bool {
+        key.checked_shr(self.bits).unwrap_or(0) == 0
+    }
+
+    /// The first `d` bits of `key`.
+    fn prefix(&self, key: u64, d: u32) -> u64 {
+        key.checked_shr(self.bits - d).unwrap_or(0)
+    }
+
+    /// Bit `d` of `key`, counting from the most significant.
+    fn bit(&self, key: u64, d: u32) -> u64 {
+        key >> (self.bits - d - 1) & 1
+    }
+
+    /// The span of the `d`-bit prefix `p`, or `None` if no key has it.
+    fn span(&self, d: u32, p: u64) -> Option<Span> {
+        if d == self.bits {
+            self.leaves
+                .contains_key(&p)
+                .then_some(Span { min: p, max: p })
+        } else {
+            self.levels[d as usize].get(&p).copied()
+        }
+    }
+
+    /// The length of the longest prefix of `key` that some stored key
+    /// shares, by binary search over the levels.
+    fn longest_prefix(&self, key: u64) -> Option<u32> {
+        if self.is_empty() {
+            return None;
+        }
+        let (mut lo, mut hi) = (0, self.bits);
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if self.span(mid, self.prefix(key, mid)).is_some() {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        Some(lo)
+    }
+}
+
+/// A set of `u64` keys below `2^bits` with `O(log log U)` operations and
+/// linear space.
+#[derive(Clone, Debug)]
+pub struct YFastTrie {
+    /// Bucket representatives; always holds zero.
+    reps: XFastTrie,
+    /// The keys of each bucket, all at least its representative and below
+    /// the next one.
+    buckets: Table<BTreeSet<u64>>,
+    len: usize,
+}
+
+impl YFastTrie {
+    /// Creates an empty trie over the keys below `2^bits`.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `bits` is between 1 and 64.
+    pub fn new(bits: u32) -> Self {
+        let mut reps = XFastTrie::new(bits);
+        reps.insert(0);
+        let mut buckets = Table::default();
+        buckets.insert(0, BTreeSet::new());
+        YFastTrie {
+            reps,
+            buckets,
+            len: 0,
+        }
+    }
+
+    /// The key width in bits.
+    pub fn bits(&self) -> u32 {
+        self.reps.bits()
+    }
+
+    /// Number of keys.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the trie holds no keys.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes every key.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.bits());
+    }
+
+    /// Whether `key` is stored.
+    pub fn contains(&self, key: u64) -> bool {
+        self.reps.in_universe(key) && self.buckets[&self.bucket_of(key)].contains(&key)
+    }
+
+    /// Adds `key`, returning whether it was absent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is not below `2^bits`.
+    pub fn insert(&mut self, key: u64) -> bool {
+        assert!(self.reps.in_universe(key), "key outside the universe");
+        let rep = self.bucket_of(key);
+        let bucket = self.buckets.get_mut(&rep).expect("listed bucket");
+        if !bucket.insert(key) {
+            return false;
+        }
+        self.len += 1;
+        if bucket.len() > self.max_bucket() {
+            self.split(rep);
+        }
+        true
+    }
+
+    /// Removes `key`, returning whether it was present.
+    pub fn remove(&mut self, key: u64) -> bool {
+        if !self.reps.in_universe(key) {
+            return false;
+        }
+        let rep = self.bucket_of(key);
+        let bucket = self.buckets.get_mut(&rep).expect("listed bucket");
+        if !bucket.remove(&key) {
+            return false;
+        }
+        self.len -= 1;
+        if bucket.len() < self.min_bucket() {
+            self.merge(rep);
+        }
+        true
+    }
+
+    /// The least key.
+    pub fn min(&self) -> Option<u64> {
+        if self.contains(0) {
+            Some(0)
+        } else {
+            self.successor(0)
+        }
+    }
+
+    /// The greatest key.
+    pub fn max(&self) -> Option<u64> {
+        let last = self.reps.max().expect("zero is a representative");
+        self.buckets[&last].last().copied()
+    }
+
+    /// The least key strictly above `key`.
+    pub fn successor(&self, key: u64) -> Option<u64> {
+        if !self.reps.in_universe(key) {
+            return None;
+        }
+        let rep = self.bucket_of(key);
+        let above = (Bound::Excluded(key), Bound::Unbounded);
+        if let Some(&k) = self.buckets[&rep].range(above).next() {
+            return Some(k);
+        }
+        let next = self.reps.successor(rep)?;
+        self.buckets[&next].first().copied()
+    }
+
+    /// The greatest key strictly below `key`.
+    pub fn predecessor(&self, key: u64) -> Option<u64> {
+        if !self.reps.in_universe(key) {
+            return self.max();
+        }
+        let rep = self.bucket_of(key);
+        if let Some(&k) = self.buckets[&rep].range(..key).next_back() {
+            return Some(k);
+        }
+        let prev = self.reps.predecessor(rep)?;
+        self.buckets[&prev].last().copied()
+    }
+
+    /// Iterates over the keys in `range`, in ascending order, in
+    /// `O(log log U)` plus `O(1)` amortized per key.
+    pub fn range<R: RangeBounds<u64>>(&self, range: R) -> Range<'_> {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = range.end_bound().cloned();
+        let past_end = match end {
+            Bound::Included(e) => e < start,
+            Bound::Excluded(e) => e <= start,
+            Bound::Unbounded => false,
+        };
+        let empty = matches!(range.start_bound(), Bound::Excluded(&u64::MAX))
+            || !self.reps.in_universe(start)
+            || past_end;
+        let rep = if empty {
+            None
+        } else {
+            Some(self.bucket_of(start))
+        };
+        Range {
+            trie: self,
+            keys: rep.map(|r| self.buckets[&r].range((Bound::Included(start), end))),
+            rep,
+            end,
+        }
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> Range<'_> {
+        self.range(..)
+    }
+
+    /// Buckets grow to at most this many keys.
+    fn max_bucket(&self) -> usize {
+        2 * self.bits() as usize
+    }
+
+    /// Buckets other than the first shrink to at least this many keys.
+    fn min_bucket(&self) -> usize {
+        (self.bits() as usize / 4).max(1)
+    }
+
+    /// The representative of the bucket that holds `key` if anything does.
+    fn bucket_of(&self, key: u64) -> u64 {
+        if self.reps.contains(key) {
+            key
+        } else {
+            self.reps
+                .predecessor(key)
+                .expect("zero is a representative")
+        }
+    }
+
+    /// Splits the bucket of `rep` at its median key, which becomes the
+    /// representative of the upper half.
+    fn split(&mut self, rep: u64) {
+        let bucket = self.buckets.get_mut(&rep).expect("listed bucket");
+        let median = *bucket
+            .iter()
+            .nth(bucket.len() / 2)
+            .expect("overfull bucket");
+        let upper = bucket.split_off(&median);
+        self.reps.insert(median);
+        self.buckets.insert(median, upper);
+    }
+
+    /// Folds an underfull bucket into a neighbor, splitting the result if
+    /// it overflows.
+    fn merge(&mut self, rep: u64) {
+        let (keep, fold) = if rep == 0 {
+            match self.reps.successor(0) {
+                Some(next) => (0, next),
+                None => return,
+            }
+        } else {
+            let prev = self.reps.predecessor(rep).expect("zero precedes");
+            (prev, rep)
+        };
+        let folded = self.buckets.remove(&fold).expect("listed bucket");
+        self.reps.remove(fold);
+        let bucket = self.buckets.get_mut(&keep).expect("listed bucket");
+        bucket.extend(folded);
+        if bucket.len() > self.max_bucket() {
+            self.split(keep);
+        }
+    }
+}
+
+impl Extend<u64> for XFastTrie {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl Extend<u64> for YFastTrie {
+    fn extend<I: IntoIterator<Item = u64>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+/// Iterator over a range of the keys of a [`YFastTrie`].
+pub struct Range<'a> {
+    trie: &'a YFastTrie,
+    /// The remaining keys in range of the current bucket.
+    keys: Option<btree_set::Range<'a, u64>>,
+    /// The current bucket's representative.
+    rep: Option<u64>,
+    end: Bound<u64>,
+}
+
+impl Iterator for Range<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(&k) = self.keys.as_mut()?.next() {
+                return Some(k);
+            }
+            // Representatives are stored keys of the x-fast trie, so
+            // stepping to the next is one lookup.
+            let next = self.trie.reps.successor(self.rep?);
+            let Some(next) = next.filter(|&n| match self.end {
+                Bound::Included(e) => n <= e,
+                Bound::Excluded(e) => n < e,
+                Bound::Unbounded => true,
+            }) else {
+                self.keys = None;
+                return None;
+            };
+            self.rep = Some(next);
+            self.keys = Some(self.trie.buckets[&next].range((Bound::Unbounded, self.end)));
+        }
+    }
+}
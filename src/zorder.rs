@@ -0,0 +1,222 @@
+//! A static index that is nothing but a Morton-sorted array.
+//!
+//! [`ZOrderIndex`] sorts `(key, payload)` pairs by the Morton code of their
+//! integer keys and keeps no other structure. Every cell of the implicit
+//! orthtree over the key space is a contiguous run of the array, so the
+//! array can stand in for a pointerless (linear) octree: a cell's contents
+//! are found with two binary searches, and the offsets of every cell at
+//! one depth compress into an Elias–Fano list.
+//!
+//! Box queries walk the code range between the box's corners. The range
+//! also contains codes outside the box; on meeting one, the query jumps
+//! straight to the next code inside the box, computed by Tropf and Herzog's
+//! BIGMIN. Its mirror image, LITMAX, gives the previous such code; together
+//! they split a box query into disjoint code ranges.
+
+use std::ops::Range;
+
+use crate::elias_fano::EliasFano;
+use crate::morton;
+
+/// A Morton-sorted array of `N`-dimensional `u32` keys with payloads of
+/// type `T`, for `N` up to 4.
+#[derive(Clone, Debug)]
+pub struct ZOrderIndex<T, const N: usize> {
+    codes: Vec<u128>,
+    items: Vec<([u32; N], T)>,
+}
+
+impl<T, const N: usize> Default for ZOrderIndex<T, N> {
+    fn default() -> Self {
+        ZOrderIndex {
+            codes: Vec::new(),
+            items: Vec::new(),
+        }
+    }
+}
+
+impl<T, const N: usize> ZOrderIndex<T, N> {
+    /// Sorts `items` into Morton order. Items with equal keys keep their
+    /// relative order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero or exceeds 4.
+    pub fn build(mut items: Vec<([u32; N], T)>) -> Self {
+        assert!((1..=4).contains(&N), "dimension must be between 1 and 4");
+        items.sort_by_cached_key(|(k, _)| morton::encode(k));
+        let codes = items.iter().map(|(k, _)| morton::encode(k)).collect();
+        ZOrderIndex { codes, items }
+    }
+
+    /// Number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the index holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The key and payload at a position in Morton order.
+    pub fn get(&self, index: usize) -> Option<(&[u32; N], &T)> {
+        self.items.get(index).map(|(k, v)| (k, v))
+    }
+
+    /// The Morton code of the item at `index`.
+    pub fn code(&self, index: usize) -> Option<u128> {
+        self.codes.get(index).copied()
+    }
+
+    /// Iterates over the items in Morton order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[u32; N], &T)> + '_ {
+        self.items.iter().enumerate().map(|(i, (k, v))| (i, k, v))
+    }
+
+    /// The positions of the items with keys equal to `key`.
+    pub fn find(&self, key: &[u32; N]) -> Range<usize> {
+        let code = morton::encode(key);
+        self.span(code, code)
+    }
+
+    /// The positions of the items in the orthtree cell of the given depth
+    /// containing `key`: those whose keys agree with it on the top `depth`
+    /// bits of every axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` exceeds 32.
+    pub fn cell(&self, key: &[u32; N], depth: u32) -> Range<usize> {
+        assert!(depth <= 32, "depth must be at most 32");
+        let free = (32 - depth) * N as u32;
+        let low = if free >= 128 {
+            u128::MAX
+        } else {
+            (1u128 << free) - 1
+        };
+        let code = morton::encode(key);
+        self.span(code & !low, code | low)
+    }
+
+    /// The position where each occupied orthtree cell of the given depth
+    /// starts, followed by the number of items: cell `k` in Morton order
+    /// holds the items at positions `starts[k]..starts[k + 1]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` exceeds 32.
+    pub fn cell_starts(&self, depth: u32) -> EliasFano {
+        assert!(depth <= 32, "depth must be at most 32");
+        let free = (32 - depth) * N as u32;
+        let prefix = |c: u128| c.checked_shr(free).unwrap_or(0);
+        let mut starts: Vec<u64> = (0..self.len())
+            .filter(|&i| i == 0 || prefix(self.codes[i]) != prefix(self.codes[i - 1]))
+            .map(|i| i as u64)
+            .collect();
+        starts.push(self.len() as u64);
+        EliasFano::new(&starts)
+    }
+
+    /// Iterates over the items whose keys lie in the closed box
+    /// `min..=max`, in Morton order.
+    pub fn within_box(
+        &self,
+        min: &[u32; N],
+        max: &[u32; N],
+    ) -> impl Iterator<Item = (usize, &[u32; N], &T)> + '_ {
+        let (min, max) = (*min, *max);
+        let empty = (0..N).any(|a| min[a] > max[a]);
+        let (zmin, zmax) = (morton::encode(&min), morton::encode(&max));
+        let mut i = if empty {
+            self.len()
+        } else {
+            self.codes.partition_point(|&c| c < zmin)
+        };
+        std::iter::from_fn(move || {
+            while i < self.len() && self.codes[i] <= zmax {
+                let (key, value) = &self.items[i];
+                if (0..N).all(|a| min[a] <= key[a] && key[a] <= max[a]) {
+                    i += 1;
+                    return Some((i - 1, key, value));
+                }
+                // Skip the run of codes that leaves the box.
+                let next = bigmin::<N>(self.codes[i], zmin, zmax);
+                i = self.codes.partition_point(|&c| c < next);
+            }
+            None
+        })
+    }
+
+    fn span(&self, lo: u128, hi: u128) -> Range<usize> {
+        self.codes.partition_point(|&c| c < lo)..self.codes.partition_point(|&c| c <= hi)
+    }
+}
+
+impl<T, const N: usize> FromIterator<([u32; N], T)> for ZOrderIndex<T, N> {
+    fn from_iter<I: IntoIterator<Item = ([u32; N], T)>>(iter: I) -> Self {
+        ZOrderIndex::build(iter.into_iter().collect())
+    }
+}
+
+/// The smallest code greater than `code` whose point lies in the box with
+/// corner codes `zmin` and `zmax`, for a `code` between them but outside
+/// the box.
+pub fn bigmin<const N: usize>(code: u128, mut zmin: u128, mut zmax: u128) -> u128 {
+    let mut best = zmax;
+    for p in (0..32 * N as u32).rev() {
+        match (bit(code, p), bit(zmin, p), bit(zmax, p)) {
+            (false, false, true) => {
+                best = first_above(zmin, p, N);
+                zmax = last_below(zmax, p, N);
+            }
+            (false, true, true) => return zmin,
+            (true, false, false) => return best,
+            (true, false, true) => zmin = first_above(zmin, p, N),
+            _ => {}
+        }
+    }
+    best
+}
+
+/// The largest code less than `code` whose point lies in the box with
+/// corner codes `zmin` and `zmax`, for a `code` between them but outside
+/// the box.
+pub fn litmax<const N: usize>(code: u128, mut zmin: u128, mut zmax: u128) -> u128 {
+    let mut best = zmin;
+    for p in (0..32 * N as u32).rev() {
+        match (bit(code, p), bit(zmin, p), bit(zmax, p)) {
+            (false, false, true) => zmax = last_below(zmax, p, N),
+            (false, true, true) => return best,
+            (true, false, false) => return zmax,
+            (true, false, true) => {
+                best = last_below(zmax, p, N);
+                zmin = first_above(zmin, p, N);
+            }
+            _ => {}
+        }
+    }
+    best
+}
+
+fn bit(code: u128, p: u32) -> bool {
+    (code >> p) & 1 == 1
+}
+
+/// The bits of code bit `p`'s axis strictly below `p`.
+fn axis_below(p: u32, n: usize) -> u128 {
+    let axis = p % n as u32;
+    (0..p / n as u32).fold(0, |acc, i| acc | 1 << (i * n as u32 + axis))
+}
+
+/// `code` with bit `p` set and the lower bits of its axis cleared: the
+/// smallest code on the upper side of the split at `p`.
+fn first_above(code: u128, p: u32, n: usize) -> u128 {
+    (code | 1 << p) & !axis_below(p, n)
+}
+
+/// `code` with bit `p` cleared and the lower bits of its axis set: the
+/// largest code on the lower side of the split at `p`.
+fn last_below(code: u128, p: u32, n: usize) -> u128 {
+    (code & !(1 << p)) | axis_below(p, n)
+}
@@ -0,0 +1,125 @@
+//! The dynamic AABB tree against a plain map of live proxies, under random
+//! inserts, removals and moves.
+
+use std::collections::HashMap;
+
+use datastructures::aabb_tree::{AabbTree, ProxyId};
+use datastructures::bvh::Ray;
+use datastructures::rtree::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+fn random_box(rng: &mut Rng) -> Aabb<2> {
+    let x = rng.range(0.0, 100.0);
+    let y = rng.range(0.0, 100.0);
+    Aabb::new([x, y], [x + rng.range(0.0, 5.0), y + rng.range(0.0, 5.0)])
+}
+
+/// The parameter at which `ray` enters `b`, by the slab test.
+fn entry(b: &Aabb<2>, ray: &Ray<2>) -> Option<f64> {
+    let (mut t0, mut t1) = (0.0f64, f64::INFINITY);
+    for a in 0..2 {
+        let inv = 1.0 / ray.direction[a];
+        let near = (b.min[a] - ray.origin[a]) * inv;
+        let far = (b.max[a] - ray.origin[a]) * inv;
+        t0 = t0.max(near.min(far));
+        t1 = t1.min(near.max(far));
+    }
+    (t0 <= t1).then_some(t0)
+}
+
+/// Every pair of intersecting live boxes, with the smaller id first.
+fn overlapping_pairs(live: &HashMap<ProxyId, (Aabb<2>, u32)>) -> Vec<(ProxyId, ProxyId)> {
+    let ids = sorted(live.keys().copied().collect());
+    let mut pairs = Vec::new();
+    for (i, a) in ids.iter().enumerate() {
+        for b in &ids[i + 1..] {
+            if live[a].0.intersects(&live[b].0) {
+                pairs.push((*a, *b));
+            }
+        }
+    }
+    pairs
+}
+
+/// Box, pair and ray queries agree with a scan as proxies come, go and
+/// move within and beyond their fattened bounds, and rotations keep the
+/// height logarithmic.
+#[test]
+fn random_operations_match_brute_force() {
+    let mut rng = Rng(11);
+    let mut tree: AabbTree<u32, 2> = AabbTree::new(0.5);
+    let mut live: HashMap<ProxyId, (Aabb<2>, u32)> = HashMap::new();
+    for step in 0..4000u32 {
+        match rng.below(4) {
+            0 | 1 => {
+                let b = random_box(&mut rng);
+                let id = tree.insert(b, step);
+                assert!(live.insert(id, (b, step)).is_none());
+            }
+            2 if !live.is_empty() => {
+                let id = *live.keys().next().unwrap();
+                let removed = tree.remove(id).map(|x| x.1);
+                assert_eq!(removed, live.remove(&id).map(|x| x.1));
+            }
+            _ if !live.is_empty() => {
+                let id = *live.keys().nth(rng.index(live.len())).unwrap();
+                let (b, _) = live[&id];
+                let d = [rng.range(-1.0, 1.0), rng.range(-1.0, 1.0)];
+                let moved = Aabb::new(
+                    [b.min[0] + d[0], b.min[1] + d[1]],
+                    [b.max[0] + d[0], b.max[1] + d[1]],
+                );
+                tree.relocate(id, moved, d).unwrap();
+                live.get_mut(&id).unwrap().0 = moved;
+                assert!(tree.fat_bounds(id).unwrap().contains(&moved));
+            }
+            _ => {}
+        }
+        assert_eq!(tree.len(), live.len());
+        if step % 50 != 0 {
+            continue;
+        }
+        let q = random_box(&mut rng);
+        let got: Vec<_> = tree.intersecting(q).map(|x| x.0).collect();
+        let want: Vec<_> = live
+            .iter()
+            .filter(|(_, (b, _))| b.intersects(&q))
+            .map(|x| *x.0)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+
+        assert_eq!(sorted(tree.overlapping_pairs()), overlapping_pairs(&live));
+
+        let n = live.len().max(2) as f64;
+        let height = tree.height();
+        assert!(
+            height as f64 <= 3.0 * n.log2() + 2.0,
+            "height {height} for {n}"
+        );
+
+        let ray = Ray::new([rng.range(0.0, 100.0), -1.0], [rng.range(-0.5, 0.5), 1.0]);
+        let boxes: HashMap<u32, Aabb<2>> = live.values().map(|(b, d)| (*d, *b)).collect();
+        let got = tree
+            .closest_hit(&ray, 1e9, |d, ray| entry(&boxes[d], ray))
+            .map(|x| x.2);
+        let want = live
+            .values()
+            .filter_map(|(b, _)| entry(b, &ray))
+            .min_by(f64::total_cmp);
+        assert_eq!(got, want);
+    }
+}
+
+/// Boxes inserted in sorted order, the worst case for a tree without
+/// rotations, still give a balanced tree.
+#[test]
+fn sorted_insertion_stays_balanced() {
+    let mut tree: AabbTree<(), 2> = AabbTree::new(0.0);
+    for i in 0..1024 {
+        let x = i as f64;
+        tree.insert(Aabb::new([x, 0.0], [x + 0.5, 1.0]), ());
+    }
+    assert!(tree.height() <= 25, "height {}", tree.height());
+}
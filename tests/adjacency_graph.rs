@@ -0,0 +1,186 @@
+//! The adjacency-list graph against a list of live nodes and edges, under
+//! random additions and removals with stale handles kept around, and its
+//! traversals and topological order against direct references.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use datastructures::adjacency_graph::{AdjacencyGraph, EdgeId, NodeId};
+
+mod common;
+use common::Rng;
+
+/// A live edge in the model: handle, source, target and payload.
+type Edge = (EdgeId, NodeId, NodeId, u32);
+
+/// Depth-first preorder from `u`, taking edges in the order they appear.
+fn dfs(u: NodeId, edges: &[Edge], seen: &mut HashSet<NodeId>, order: &mut Vec<NodeId>) {
+    if !seen.insert(u) {
+        return;
+    }
+    order.push(u);
+    for e in edges.iter().filter(|e| e.1 == u) {
+        dfs(e.2, edges, seen, order);
+    }
+}
+
+/// Breadth-first order from `u`.
+fn bfs(u: NodeId, edges: &[Edge]) -> Vec<NodeId> {
+    let mut seen = HashSet::from([u]);
+    let mut queue = VecDeque::from([u]);
+    let mut order = Vec::new();
+    while let Some(x) = queue.pop_front() {
+        order.push(x);
+        for e in edges.iter().filter(|e| e.1 == x) {
+            if seen.insert(e.2) {
+                queue.push_back(e.2);
+            }
+        }
+    }
+    order
+}
+
+/// Random node and edge additions and removals. Handles of removed nodes
+/// and edges stay dead even after their slots are reused.
+#[test]
+fn operations_match_model() {
+    let mut rng = Rng(21);
+    let mut g: AdjacencyGraph<u32, u32> = AdjacencyGraph::new();
+    let mut nodes: BTreeMap<NodeId, u32> = BTreeMap::new();
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut dead_nodes = Vec::new();
+    let mut dead_edges = Vec::new();
+    for step in 0..10000u32 {
+        let ids: Vec<NodeId> = nodes.keys().copied().collect();
+        match rng.below(10) {
+            0..=2 => {
+                nodes.insert(g.add_node(step), step);
+            }
+            3..=5 if !ids.is_empty() => {
+                let (u, v) = (ids[rng.index(ids.len())], ids[rng.index(ids.len())]);
+                edges.push((g.add_edge(u, v, step).unwrap(), u, v, step));
+            }
+            6 if !ids.is_empty() => {
+                let u = ids[rng.index(ids.len())];
+                assert_eq!(g.remove_node(u), nodes.remove(&u));
+                for e in edges.iter().filter(|e| e.1 == u || e.2 == u) {
+                    dead_edges.push(e.0);
+                }
+                edges.retain(|e| e.1 != u && e.2 != u);
+                dead_nodes.push(u);
+            }
+            7 if !edges.is_empty() => {
+                let e = edges.remove(rng.index(edges.len()));
+                assert_eq!(g.remove_edge(e.0), Some(e.3));
+                assert_eq!(g.remove_edge(e.0), None);
+                dead_edges.push(e.0);
+            }
+            _ => {}
+        }
+        assert_eq!(g.node_count(), nodes.len());
+        assert_eq!(g.edge_count(), edges.len());
+        for &d in &dead_nodes[dead_nodes.len().saturating_sub(5)..] {
+            assert!(!g.contains_node(d));
+            assert_eq!(g.node(d), None);
+            assert!(g.add_edge(d, d, 0).is_none());
+        }
+        for &d in &dead_edges[dead_edges.len().saturating_sub(5)..] {
+            assert!(!g.contains_edge(d));
+            assert_eq!(g.edge(d), None);
+        }
+        if step % 500 != 0 {
+            continue;
+        }
+        for &(e, u, v, w) in &edges {
+            assert_eq!(g.endpoints(e), Some((u, v)));
+            assert_eq!(g.edge(e), Some(&w));
+        }
+        assert_eq!(g.nodes().count(), nodes.len());
+        for (&u, &w) in &nodes {
+            assert_eq!(g.node(u), Some(&w));
+            let outs: Vec<(EdgeId, NodeId)> = edges
+                .iter()
+                .filter(|e| e.1 == u)
+                .map(|e| (e.0, e.2))
+                .collect();
+            let got: Vec<(EdgeId, NodeId)> = g.out_edges(u).map(|(e, v, _)| (e, v)).collect();
+            assert_eq!(got, outs);
+            assert_eq!(g.out_degree(u), outs.len());
+            let ins: Vec<(EdgeId, NodeId)> = edges
+                .iter()
+                .filter(|e| e.2 == u)
+                .map(|e| (e.0, e.1))
+                .collect();
+            let got: Vec<(EdgeId, NodeId)> = g.in_edges(u).map(|(e, v, _)| (e, v)).collect();
+            assert_eq!(got, ins);
+            assert_eq!(g.in_degree(u), ins.len());
+            let mut order = Vec::new();
+            dfs(u, &edges, &mut HashSet::new(), &mut order);
+            assert_eq!(g.dfs(u).collect::<Vec<_>>(), order);
+            assert_eq!(g.bfs(u).collect::<Vec<_>>(), bfs(u, &edges));
+        }
+    }
+    g.clear();
+    assert!(g.is_empty());
+    assert!(nodes.keys().all(|&u| !g.contains_node(u)));
+}
+
+/// Acyclic graphs get an order respecting every edge; graphs with a cycle
+/// report a node that lies on one.
+#[test]
+fn topological_order() {
+    let mut rng = Rng(4);
+    for _ in 0..200 {
+        let mut g = AdjacencyGraph::new();
+        let n = 1 + rng.index(30);
+        let ids: Vec<NodeId> = (0..n).map(|i| g.add_node(i)).collect();
+        let cyclic = rng.below(2) == 0;
+        for _ in 0..rng.below(60) {
+            let (a, b) = (rng.index(n), rng.index(n));
+            if cyclic || a < b {
+                g.add_edge(ids[a], ids[b], ());
+            }
+        }
+        match g.topological_order() {
+            Ok(order) => {
+                assert_eq!(order.len(), n);
+                assert!(g.topological().eq(order.iter().copied()));
+                let pos: HashMap<NodeId, usize> =
+                    order.iter().enumerate().map(|(i, &x)| (x, i)).collect();
+                for (_, u, v, _) in g.edges() {
+                    assert!(pos[&u] < pos[&v]);
+                }
+            }
+            Err(cycle) => {
+                assert!(cyclic);
+                let u = cycle.node;
+                assert!(g.successors(u).any(|s| g.dfs(s).any(|x| x == u)));
+                assert!(g.topological().count() < n);
+            }
+        }
+    }
+}
+
+/// Payload access, edge lookup between nodes, and neighbour lists.
+#[test]
+fn payloads_and_lookup() {
+    let mut g = AdjacencyGraph::new();
+    let a = g.add_node("a");
+    let b = g.add_node("b");
+    let ab = g.add_edge(a, b, 1).unwrap();
+    let ba = g.add_edge(b, a, 2).unwrap();
+    g.add_edge(a, b, 3).unwrap();
+    assert_eq!(g.find_edge(a, b), Some(ab));
+    assert_eq!(g.find_edge(b, b), None);
+    *g.node_mut(b).unwrap() = "z";
+    *g.edge_mut(ba).unwrap() += 10;
+    assert_eq!(g.node(b), Some(&"z"));
+    assert_eq!(g.edge(ba), Some(&12));
+    assert_eq!(g.successors(a).collect::<Vec<_>>(), [b, b]);
+    assert_eq!(g.predecessors(a).collect::<Vec<_>>(), [b]);
+    g.remove_node(b);
+    assert_eq!(g.edge_count(), 0);
+    assert_eq!(g.out_degree(a), 0);
+    let c = g.add_node("c");
+    assert_ne!(c, b);
+    assert!(!g.contains_node(b));
+}
@@ -0,0 +1,94 @@
+//! Budgeted approximate nearest-neighbor search on the k-d tree and the
+//! octree, against exact distances from a scan.
+
+use datastructures::geom::Aabb;
+use datastructures::kdtree::KdTree;
+use datastructures::octree::Octree;
+use datastructures::orthtree::Orthtree;
+use datastructures::spatial_index::ApproxNearest;
+
+mod common;
+use common::Rng;
+
+fn random_point(rng: &mut Rng) -> [f64; 3] {
+    std::array::from_fn(|_| rng.unit())
+}
+
+/// The sorted distances from `q` to every point.
+fn distances(points: &[[f64; 3]], q: &[f64; 3]) -> Vec<f64> {
+    let mut d: Vec<f64> = points
+        .iter()
+        .map(|p| (0..3).map(|a| (p[a] - q[a]).powi(2)).sum::<f64>().sqrt())
+        .collect();
+    d.sort_by(f64::total_cmp);
+    d
+}
+
+/// Checks the guarantees of an approximate answer against the true sorted
+/// distances: an exact answer is the true k nearest, neighbors closer than
+/// the bound are exact, and no neighbor is closer than the true one of the
+/// same rank. Returns whether the answer happens to be exact.
+fn check<I>(answer: &ApproxNearest<I>, truth: &[f64], k: usize) -> bool {
+    let truth = &truth[..k.min(truth.len())];
+    let matches = answer.neighbors.len() == truth.len()
+        && answer
+            .neighbors
+            .iter()
+            .zip(truth)
+            .all(|(g, w)| (g.1 - w).abs() < 1e-12);
+    if answer.exact {
+        assert!(matches);
+    }
+    for (n, w) in answer.neighbors.iter().zip(truth) {
+        if n.1 < answer.bound {
+            assert!((n.1 - w).abs() < 1e-12);
+        }
+        assert!(n.1 >= w - 1e-12);
+    }
+    matches
+}
+
+/// Every budget keeps the guarantees, larger budgets find the true
+/// neighbors more often, and an unlimited budget is always exact.
+#[test]
+fn answers_keep_their_guarantees() {
+    let mut rng = Rng(5);
+    let points: Vec<[f64; 3]> = (0..5000).map(|_| random_point(&mut rng)).collect();
+    let mut kd: KdTree<(), 3> = points.iter().map(|&p| (p, ())).collect();
+    for _ in 0..50 {
+        kd.insert(random_point(&mut rng), ());
+    }
+    for i in 0..100 {
+        kd.remove(i * 7);
+    }
+    let kd_points: Vec<[f64; 3]> = kd.iter().map(|(_, p, _)| *p).collect();
+    let mut octree: Octree<[f64; 3], ()> = Orthtree::new(Aabb::new([0.0; 3], [1.0; 3]));
+    for p in &points {
+        octree.insert(*p, ()).unwrap();
+    }
+
+    let (mut kd_hits, mut octree_hits) = (Vec::new(), Vec::new());
+    for budget in [1usize, 5, 20, 100, usize::MAX] {
+        let (mut kd_hit, mut octree_hit) = (0, 0);
+        for _ in 0..100 {
+            let q = random_point(&mut rng);
+            let answer = kd.nearest_approx(&q, 5, budget);
+            assert!(answer.visited <= budget);
+            kd_hit += check(&answer, &distances(&kd_points, &q), 5) as usize;
+            let answer = octree.nearest_approx(q, 5, budget);
+            octree_hit += check(&answer, &distances(&points, &q), 5) as usize;
+            if budget == usize::MAX {
+                assert!(answer.exact && kd.nearest_approx(&q, 5, budget).exact);
+            }
+        }
+        kd_hits.push(kd_hit);
+        octree_hits.push(octree_hit);
+    }
+    assert_eq!(kd_hits.last(), Some(&100));
+    assert_eq!(octree_hits.last(), Some(&100));
+    assert!(kd_hits[0] < kd_hits[3] && octree_hits[0] < octree_hits[3]);
+
+    assert!(kd.nearest_approx(&[0.5; 3], 0, 10).neighbors.is_empty());
+    let empty: KdTree<(), 2> = KdTree::default();
+    assert!(empty.nearest_approx(&[0.0; 2], 3, 10).exact);
+}
@@ -0,0 +1,95 @@
+//! The AVL tree against `std::collections::BTreeMap`, with the balance
+//! invariant and a user-defined augmentation checked node by node.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use datastructures::avl::{Augment, AvlTree, NodeRef, Size};
+
+mod common;
+use common::Rng;
+
+/// The largest value in each subtree.
+#[derive(Clone, Debug)]
+struct MaxValue(u32);
+
+impl Augment<u32, u32> for MaxValue {
+    fn compute(_: &u32, value: &u32, left: Option<&Self>, right: Option<&Self>) -> Self {
+        let children = left.into_iter().chain(right).map(|x| x.0);
+        MaxValue(children.fold(*value, u32::max))
+    }
+}
+
+/// Checks the subtree at `node` for balance and a correct augmentation,
+/// returning its largest value and height.
+fn check_subtree(node: Option<NodeRef<'_, u32, u32, MaxValue>>) -> (u32, usize) {
+    let Some(node) = node else {
+        return (0, 0);
+    };
+    let (left_max, left_height) = check_subtree(node.left());
+    let (right_max, right_height) = check_subtree(node.right());
+    assert!(left_height.abs_diff(right_height) <= 1);
+    let max = (*node.value()).max(left_max).max(right_max);
+    assert_eq!(node.augment().0, max);
+    (max, 1 + left_height.max(right_height))
+}
+
+/// Random inserts, removals and in-place modifications keep a size-counting
+/// tree and a max-augmented tree in step with the map.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(9);
+    let mut sized: AvlTree<u32, u32, Size> = AvlTree::new();
+    let mut maxed: AvlTree<u32, u32, MaxValue> = AvlTree::new();
+    let mut map = BTreeMap::new();
+    for step in 0..30000u32 {
+        let k = rng.below(2000) as u32;
+        let v = rng.below(1_000_000) as u32;
+        match rng.below(5) {
+            0 | 1 => {
+                assert_eq!(sized.remove(&k), map.remove(&k));
+                maxed.remove(&k);
+            }
+            2 => {
+                let returned = sized.modify(&k, |x| {
+                    *x = v;
+                    7
+                });
+                maxed.modify(&k, |x| *x = v);
+                assert_eq!(returned, map.get_mut(&k).map(|x| *x = v).map(|_| 7));
+            }
+            _ => {
+                assert_eq!(sized.insert(k, v), map.insert(k, v));
+                maxed.insert(k, v);
+            }
+        }
+        assert_eq!(sized.get(&k), map.get(&k));
+        assert_eq!(sized.contains_key(&k), map.contains_key(&k));
+        assert_eq!(sized.rank(&k), map.range(..k).count());
+        if step % 1000 != 0 {
+            continue;
+        }
+        assert!(sized.iter().eq(map.iter()));
+        assert!(maxed.iter().eq(map.iter()));
+        for (i, e) in map.iter().enumerate() {
+            assert_eq!(sized.select(i), Some(e));
+        }
+        let (max, height) = check_subtree(maxed.root_node());
+        assert_eq!(max, map.values().copied().max().unwrap_or(0));
+        assert_eq!(height, maxed.height());
+        assert_eq!(sized.augment().map_or(0, |s| s.0), map.len());
+
+        let lo = rng.below(2000) as u32;
+        let hi = lo + rng.below(500) as u32;
+        assert!(sized.range(lo..hi).eq(map.range(lo..hi)));
+        let bounds = (Bound::Excluded(lo), Bound::Included(hi));
+        assert!(sized.range(bounds).eq(map.range(bounds)));
+    }
+}
+
+/// Inserting `2^16` keys in order gives the minimal height.
+#[test]
+fn sorted_inserts_stay_balanced() {
+    let tree: AvlTree<u32, ()> = (0..1 << 16).map(|i| (i, ())).collect();
+    assert_eq!(tree.height(), 17);
+}
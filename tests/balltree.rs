@@ -0,0 +1,53 @@
+//! The ball tree against a brute-force scan, under each of the vector
+//! metrics.
+
+use datastructures::balltree::BallTree;
+use datastructures::metric::{Chebyshev, Cosine, Euclidean, Manhattan, Metric};
+
+mod common;
+use common::{sorted, Rng};
+
+fn random_vector(rng: &mut Rng) -> Vec<f64> {
+    (0..8).map(|_| rng.range(-1.0, 1.0)).collect()
+}
+
+fn check<M: Metric<Vec<f64>> + Copy>(metric: M, rng: &mut Rng) {
+    for n in [0usize, 1, 17, 300, 1500] {
+        let points: Vec<(Vec<f64>, usize)> = (0..n).map(|i| (random_vector(rng), i)).collect();
+        let tree = BallTree::build(points.clone(), metric);
+        for _ in 0..30 {
+            let q = random_vector(rng);
+            let k = rng.index(10);
+            let got: Vec<f64> = tree.nearest(&q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = points.iter().map(|(p, _)| metric.distance(&q, p)).collect();
+            want.sort_by(f64::total_cmp);
+            // A radius that takes in about a tenth of the points.
+            let r = want.get(n / 10).copied().unwrap_or(1.0);
+            want.truncate(k);
+            assert_eq!(got, want);
+
+            let got: Vec<usize> = tree
+                .within_radius(&q, r)
+                .iter()
+                .map(|x| *tree.get(x.0).unwrap().1)
+                .collect();
+            let want: Vec<usize> = points
+                .iter()
+                .filter(|(p, _)| metric.distance(&q, p) <= r)
+                .map(|x| x.1)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+        }
+    }
+}
+
+/// k-nearest and radius queries agree with a scan for every metric, in
+/// eight dimensions.
+#[test]
+fn queries_match_brute_force() {
+    let mut rng = Rng(11);
+    check(Euclidean, &mut rng);
+    check(Manhattan, &mut rng);
+    check(Cosine, &mut rng);
+    check(Chebyshev, &mut rng);
+}
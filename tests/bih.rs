@@ -0,0 +1,118 @@
+//! The bounding interval hierarchy against a scan of its primitives and
+//! against the BVH, for random rays, rays parallel to the axes and box
+//! queries, before and after the primitives move.
+
+use datastructures::bih::Bih;
+use datastructures::bvh::Bvh;
+use datastructures::geom::{Aabb, Ray, Sphere};
+use datastructures::naive;
+
+mod common;
+use common::{sorted, Rng};
+
+fn random_sphere(rng: &mut Rng) -> Sphere<3> {
+    let center = std::array::from_fn(|_| rng.range(-10.0, 10.0));
+    Sphere::new(center, rng.range(0.1, 1.0))
+}
+
+/// Checks closest-hit, any-hit and overlap queries on `bih`, whose
+/// primitives index into `spheres`.
+fn check(bih: &Bih<usize, 3>, spheres: &[Sphere<3>], rng: &mut Rng) {
+    let hit = |&i: &usize, ray: &Ray<3>| ray.hit_sphere(&spheres[i], f64::INFINITY);
+    for _ in 0..200 {
+        let origin = [rng.range(-12.0, 12.0), rng.range(-12.0, 12.0), -15.0];
+        let ray = Ray::new(origin, [rng.range(-0.5, 0.5), rng.range(-0.5, 0.5), 1.0]);
+        let max_t = rng.range(0.0, 40.0);
+        let got = bih.closest_hit(&ray, max_t, hit).map(|x| x.2);
+        let want = naive::closest_hit(spheres, &ray, max_t, |s, r| r.hit_sphere(s, max_t));
+        assert_eq!(got, want.map(|x| x.1));
+        assert_eq!(bih.any_hit(&ray, max_t, hit).is_some(), want.is_some());
+
+        let q = Aabb::new(
+            [origin[0] - 2.0, origin[1] - 2.0, -3.0],
+            [origin[0] + 2.0, origin[1] + 2.0, 3.0],
+        );
+        let got: Vec<usize> = bih.intersecting(q).map(|x| *x.2).collect();
+        let want: Vec<usize> = (0..spheres.len())
+            .filter(|&i| spheres[i].bounds().intersects(&q))
+            .collect();
+        assert_eq!(sorted(got), want);
+    }
+}
+
+/// Queries agree with a scan after the build, after every sphere moves and
+/// `refit` updates the bounds in place, and after `rebuild`.
+#[test]
+fn queries_match_brute_force() {
+    let mut rng = Rng(5);
+    for n in [0usize, 1, 3, 50, 500] {
+        let mut spheres: Vec<Sphere<3>> = (0..n).map(|_| random_sphere(&mut rng)).collect();
+        let mut bih = Bih::build((0..n).map(|i| (spheres[i].bounds(), i)).collect());
+        assert_eq!(bih.len(), n);
+        assert_eq!(bih.bounds().is_empty(), n == 0);
+        check(&bih, &spheres, &mut rng);
+
+        for s in &mut spheres {
+            s.center[2] += rng.range(-3.0, 3.0);
+        }
+        bih.refit(|&i| spheres[i].bounds());
+        check(&bih, &spheres, &mut rng);
+
+        bih.rebuild();
+        check(&bih, &spheres, &mut rng);
+    }
+}
+
+/// Boxes on an integer grid hit by axis-parallel rays along grid lines,
+/// which run along box faces and the scene's boundary: the hierarchy
+/// reports the same hits as a scan.
+#[test]
+fn axis_parallel_rays_along_faces() {
+    let mut rng = Rng(8);
+    let boxes: Vec<Aabb<3>> = (0..400)
+        .map(|_| {
+            let min: [f64; 3] = std::array::from_fn(|_| rng.below(20) as f64);
+            Aabb::new(min, min.map(|m| m + 1.0 + rng.below(3) as f64))
+        })
+        .collect();
+    let bih = Bih::build(boxes.iter().copied().zip(0..).collect());
+    assert_eq!(bih.bounds().min, [0.0; 3]);
+    for _ in 0..500 {
+        let axis = rng.index(3);
+        let mut origin: [f64; 3] = std::array::from_fn(|_| rng.below(23) as f64);
+        let mut direction = [0.0; 3];
+        direction[axis] = if rng.below(2) == 0 { 1.0 } else { -1.0 };
+        origin[axis] = if direction[axis] > 0.0 { -1.0 } else { 25.0 };
+        let ray = Ray::new(origin, direction);
+        let got = bih.closest_hit(&ray, 100.0, |&i, r| r.hit_aabb(&boxes[i], 100.0));
+        let want = naive::raycast(&boxes, &ray, 100.0);
+        assert_eq!(got.map(|x| x.2), want.map(|x| x.1), "{ray:?}");
+    }
+}
+
+/// A large scene traced by both hierarchies gives the same hit distances.
+#[test]
+fn agrees_with_the_bvh() {
+    let mut rng = Rng(9);
+    let boxes: Vec<Aabb<3>> = (0..20000)
+        .map(|_| {
+            let c = [
+                rng.range(0.0, 1000.0),
+                rng.range(0.0, 1000.0),
+                rng.range(0.0, 10.0),
+            ];
+            let e = rng.range(0.0, 2.0);
+            Aabb::new(c, c.map(|x| x + e))
+        })
+        .collect();
+    let items: Vec<(Aabb<3>, usize)> = boxes.iter().copied().zip(0..).collect();
+    let bih = Bih::build(items.clone());
+    let bvh = Bvh::build(items);
+    let hit = |&i: &usize, r: &Ray<3>| r.hit_aabb(&boxes[i], f64::INFINITY);
+    for _ in 0..500 {
+        let origin = [rng.range(0.0, 1000.0), rng.range(0.0, 1000.0), 20.0];
+        let ray = Ray::new(origin, [rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), -1.0]);
+        let got = bih.closest_hit(&ray, 1e9, hit).map(|x| x.2);
+        assert_eq!(got, bvh.closest_hit(&ray, 1e9, hit).map(|x| x.2));
+    }
+}
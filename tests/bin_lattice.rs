@@ -0,0 +1,98 @@
+//! The double-buffered bin lattice against a scan of each frame's points,
+//! for radius queries on both frames and for all close pairs.
+
+use datastructures::bin_lattice::BinLattice;
+use datastructures::geom::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+fn distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+/// Forty frames of random points, some outside the lattice: both buffers
+/// keep their frame's points, radius queries and pair enumeration match a
+/// scan, and every point lands in exactly one bin.
+#[test]
+fn frames_match_brute_force() {
+    let mut rng = Rng(5);
+    let mut lattice = BinLattice::new(Aabb::new([0.0; 3], [10.0, 7.0, 5.5]), 1.0);
+    assert_eq!(lattice.dims(), [10, 7, 6]);
+    assert!(lattice.is_empty());
+    assert_eq!(lattice.neighbors(&[1.0; 3], 5.0).count(), 0);
+    let mut previous: Vec<[f64; 3]> = Vec::new();
+    for frame in 1..=40u64 {
+        let n = rng.index(300);
+        let points: Vec<[f64; 3]> = (0..n)
+            .map(|_| {
+                [
+                    rng.range(-1.0, 11.0),
+                    rng.range(0.0, 3.0),
+                    rng.range(-1.0, 7.0),
+                ]
+            })
+            .collect();
+        lattice.rebuild(&points);
+        assert_eq!(lattice.frame(), frame);
+        assert_eq!(lattice.current().frame(), frame);
+        assert_eq!(lattice.previous().frame(), frame - 1);
+        assert_eq!(lattice.len(), n);
+        assert_eq!(lattice.previous().len(), previous.len());
+        for (i, p) in previous.iter().enumerate() {
+            assert_eq!(lattice.previous().position(i), Some(*p));
+        }
+        for (i, p) in points.iter().enumerate() {
+            assert_eq!(lattice.current().position(i), Some(*p));
+        }
+
+        for _ in 0..20 {
+            let q = [
+                rng.range(-2.0, 12.0),
+                rng.range(-2.0, 5.0),
+                rng.range(-2.0, 8.0),
+            ];
+            let r = rng.range(0.0, 3.0);
+            let got: Vec<usize> = lattice
+                .neighbors(&q, r)
+                .map(|(i, d)| {
+                    assert!((d - distance_squared(&points[i], &q)).abs() < 1e-12);
+                    i
+                })
+                .collect();
+            let want: Vec<usize> = (0..n)
+                .filter(|&i| distance_squared(&points[i], &q) <= r * r)
+                .collect();
+            assert_eq!(sorted(got), want);
+            let got: Vec<usize> = lattice.previous().neighbors(&q, r).map(|x| x.0).collect();
+            let want: Vec<usize> = (0..previous.len())
+                .filter(|&i| distance_squared(&previous[i], &q) <= r * r)
+                .collect();
+            assert_eq!(sorted(got), want);
+        }
+
+        let r = rng.range(0.0, 2.5);
+        let mut got = Vec::new();
+        lattice.for_each_pair(r, |i, j, _| got.push((i.min(j), i.max(j))));
+        let mut want = Vec::new();
+        for i in 0..n {
+            for j in i + 1..n {
+                if distance_squared(&points[i], &points[j]) <= r * r {
+                    want.push((i, j));
+                }
+            }
+        }
+        assert_eq!(sorted(got), want);
+
+        let binned: usize = (0..10)
+            .flat_map(|x| (0..7).flat_map(move |y| (0..6).map(move |z| [x, y, z])))
+            .map(|bin| lattice.current().bin_items(bin).count())
+            .sum();
+        assert_eq!(binned, n);
+        let order = sorted(lattice.current().order().to_vec());
+        assert_eq!(order, (0..n as u32).collect::<Vec<_>>());
+        assert_eq!(lattice.current().coords()[0].len(), n);
+        previous = points;
+    }
+    assert_eq!(lattice.neighbors(&[1.0; 3], -1.0).count(), 0);
+}
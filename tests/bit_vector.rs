@@ -0,0 +1,75 @@
+//! The bit vector's rank and select against a scan of the bits, for
+//! lengths and densities that exercise every level of the directory.
+
+use datastructures::bit_vector::BitVector;
+
+mod common;
+use common::Rng;
+
+/// Every rank and every select, both ways, for vectors from empty to
+/// several superblocks long, from all zeros to all ones.
+#[test]
+fn rank_and_select_match_scan() {
+    let mut rng = Rng(3);
+    let cases = [
+        (0, 50),
+        (1, 50),
+        (63, 50),
+        (64, 50),
+        (513, 1),
+        (200_000, 50),
+        (300_000, 99),
+        (140_000, 1),
+        (70_000, 100),
+        (70_000, 0),
+    ];
+    for (n, percent) in cases {
+        let bits: Vec<bool> = (0..n).map(|_| rng.below(100) < percent).collect();
+        let bv: BitVector = bits.iter().copied().collect();
+        assert_eq!(bv.len(), n);
+        assert_eq!(bv.is_empty(), n == 0);
+        assert!(bv.iter().eq(bits.iter().copied()));
+        let ones: Vec<usize> = (0..n).filter(|&i| bits[i]).collect();
+        let zeros: Vec<usize> = (0..n).filter(|&i| !bits[i]).collect();
+        assert_eq!(bv.count_ones(), ones.len());
+        assert_eq!(bv.count_zeros(), zeros.len());
+
+        let mut rank = 0;
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(bv.rank1(i), rank, "{n} bits, at {i}");
+            assert_eq!(bv.rank0(i), i - rank);
+            assert_eq!(bv.get(i), bit);
+            rank += usize::from(bit);
+        }
+        assert_eq!(bv.rank1(n), ones.len());
+        for (k, &p) in ones.iter().enumerate() {
+            assert_eq!(bv.select1(k), Some(p));
+        }
+        for (k, &p) in zeros.iter().enumerate() {
+            assert_eq!(bv.select0(k), Some(p));
+        }
+        assert_eq!(bv.select1(ones.len()), None);
+        assert_eq!(bv.select0(zeros.len()), None);
+    }
+}
+
+/// Packed words are taken as given, with bits past the length ignored.
+#[test]
+fn from_words_ignores_trailing_bits() {
+    let bv = BitVector::from_words(vec![u64::MAX, u64::MAX], 70);
+    assert_eq!(bv.len(), 70);
+    assert_eq!(bv.count_ones(), 70);
+    assert_eq!(bv.select1(69), Some(69));
+    assert_eq!(bv.select1(70), None);
+    assert_eq!(bv.select0(0), None);
+    let bv = BitVector::from_words(vec![0b1010], 4);
+    assert_eq!(bv.words(), [0b1010]);
+    assert_eq!(bv.rank1(4), 2);
+}
+
+/// Positions past the end are rejected.
+#[test]
+#[should_panic(expected = "index 10 out of bounds for length 10")]
+fn get_past_the_end_panics() {
+    BitVector::from_words(vec![0], 10).get(10);
+}
@@ -0,0 +1,144 @@
+//! The bitset against a `BTreeSet`, over pairs of random sets of
+//! different spans, and the octree's occupancy masks against the points
+//! of each child orthant.
+
+use std::collections::BTreeSet;
+
+use datastructures::bitset::BitSet;
+use datastructures::octree::{Octree, Orthant};
+use datastructures::rtree::Aabb;
+
+mod common;
+use common::Rng;
+
+fn random_set(rng: &mut Rng, n: usize, span: usize) -> (BitSet, BTreeSet<usize>) {
+    let mut set = BitSet::new();
+    let mut model = BTreeSet::new();
+    for _ in 0..n {
+        let i = rng.index(span);
+        assert_eq!(set.insert(i), model.insert(i));
+    }
+    (set, model)
+}
+
+/// Queries, ranges, and every set operation in both its operator and its
+/// in-place form, on sets whose word vectors differ in length.
+#[test]
+fn operations_match_btreeset() {
+    let mut rng = Rng(21);
+    for _ in 0..300 {
+        let span = 1 + rng.index(700);
+        let n = rng.index(200);
+        let (mut a, mut ma) = random_set(&mut rng, n, span);
+        let (n, other_span) = (rng.index(200), 1 + rng.index(700));
+        let (b, mb) = random_set(&mut rng, n, other_span);
+        for _ in 0..50 {
+            let i = rng.index(span);
+            assert_eq!(a.remove(i), ma.remove(&i));
+        }
+        assert!(a.iter().eq(ma.iter().copied()));
+        assert!((&a).into_iter().eq(ma.iter().copied()));
+        assert_eq!(a.len(), ma.len());
+        assert_eq!(a.is_empty(), ma.is_empty());
+        assert_eq!(a.min(), ma.first().copied());
+        assert_eq!(a.max(), ma.last().copied());
+        for q in 0..800 {
+            assert_eq!(a.rank(q), ma.range(..q).count());
+            assert_eq!(a.contains(q), ma.contains(&q));
+        }
+
+        let start = rng.index(800);
+        let end = start + rng.index(200);
+        let mut ranged = a.clone();
+        ranged.insert_range(start, end);
+        let mut model = ma.clone();
+        model.extend(start..end);
+        assert!(ranged.iter().eq(model.iter().copied()));
+
+        let union: Vec<usize> = ma.union(&mb).copied().collect();
+        let inter: Vec<usize> = ma.intersection(&mb).copied().collect();
+        let diff: Vec<usize> = ma.difference(&mb).copied().collect();
+        let sym: Vec<usize> = ma.symmetric_difference(&mb).copied().collect();
+        assert!((&a | &b).iter().eq(union.iter().copied()));
+        assert!((&a & &b).iter().eq(inter.iter().copied()));
+        assert!((&a - &b).iter().eq(diff.iter().copied()));
+        assert!((&a ^ &b).iter().eq(sym.iter().copied()));
+        assert_eq!(a.union(&b), &a | &b);
+        assert_eq!(a.intersection(&b), &a & &b);
+        assert_eq!(a.difference(&b), &a - &b);
+        assert_eq!(a.symmetric_difference(&b), &a ^ &b);
+        let mut x = a.clone();
+        x ^= &b;
+        x.symmetric_difference_with(&b);
+        assert_eq!(x, a);
+        x -= &b;
+        assert!(x.iter().eq(diff.iter().copied()));
+        x |= &b;
+        assert!(x.iter().eq(union.iter().copied()));
+        assert_eq!(a.intersection_len(&b), inter.len());
+        assert_eq!(a.is_subset(&b), ma.is_subset(&mb));
+        assert_eq!(a.is_disjoint(&b), ma.is_disjoint(&mb));
+        assert!(a.is_subset(&(&a | &b)));
+
+        let mut trailing: BitSet = ma.iter().copied().collect();
+        trailing.insert(5000);
+        trailing.remove(5000);
+        assert_eq!(trailing, a);
+        assert_eq!(BitSet::from_words(a.words().to_vec()), a);
+        a &= &b;
+        ma = inter.into_iter().collect();
+        assert!(a.iter().eq(ma.iter().copied()));
+    }
+}
+
+/// Members at word boundaries, and ranges that start and end inside words
+/// or span several.
+#[test]
+fn word_boundaries() {
+    let mut set = BitSet::with_capacity(256);
+    assert!(set.is_empty());
+    set.extend([0, 63, 64, 127, 128]);
+    assert_eq!(set.words(), [1 | 1 << 63, 1 | 1 << 63, 1]);
+    assert_eq!(set.rank(64), 2);
+    assert_eq!(set.rank(10_000), 5);
+    set.insert_range(60, 200);
+    assert_eq!(set.len(), 1 + 140);
+    assert_eq!(set.max(), Some(199));
+    set.insert_range(7, 7);
+    assert!(!set.contains(7));
+    set.clear();
+    assert_eq!(set.min(), None);
+    assert_eq!(
+        format!("{:?}", [3, 1].into_iter().collect::<BitSet>()),
+        "{1, 3}"
+    );
+}
+
+/// A cell's occupancy mask has bit `d` set exactly when its child `d`
+/// holds a point, at every depth above the limit.
+#[test]
+fn octree_occupancy() {
+    let mut rng = Rng(4);
+    let mut tree = Octree::with_limits(Aabb::new([0.0; 3], [1.0; 3]), 4, 6);
+    for _ in 0..300 {
+        let p: [f64; 3] = std::array::from_fn(|_| rng.below(1000) as f64 / 1000.0);
+        tree.insert(p, ()).unwrap();
+    }
+    for depth in 0..6u8 {
+        for _ in 0..40 {
+            let side = 1usize << depth;
+            let index = std::array::from_fn(|_| rng.index(side) as u32);
+            let orthant = Orthant { depth, index };
+            let mask = tree.occupancy(orthant).unwrap();
+            for d in 0..8 {
+                let occupied = tree.orthant_points(orthant.child(d)).next().is_some();
+                assert_eq!(mask.contains(d as usize), occupied);
+            }
+        }
+    }
+    let leaf = Orthant {
+        depth: 6,
+        index: [0; 3],
+    };
+    assert!(tree.occupancy(leaf).is_none());
+}
@@ -0,0 +1,131 @@
+//! The blocking queue under real threads, with every value delivered once
+//! and in order per producer, and its timeouts and shutdown on one
+//! thread. Waiters woken by another thread are checked in ways that hold
+//! whether or not they were already asleep.
+
+#![cfg(not(loom))]
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use datastructures::blocking_queue::{BlockingQueue, PopError, PushError};
+
+/// Four producers and three consumers through a queue of four, shut down
+/// by closing it once the producers are done.
+#[test]
+fn values_are_delivered_once_in_order() {
+    const PRODUCERS: u64 = 4;
+    const PER_PRODUCER: u64 = 5000;
+    let queue = Arc::new(BlockingQueue::new(4));
+    let producers: Vec<_> = (0..PRODUCERS)
+        .map(|p| {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..PER_PRODUCER {
+                    queue.push(p * PER_PRODUCER + i).unwrap();
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..3)
+        .map(|_| {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                let mut last = [None; PRODUCERS as usize];
+                let mut count = 0;
+                while let Some(value) = queue.pop() {
+                    assert!(queue.len() <= queue.capacity());
+                    let p = (value / PER_PRODUCER) as usize;
+                    assert!(last[p] < Some(value));
+                    last[p] = Some(value);
+                    count += 1;
+                }
+                count
+            })
+        })
+        .collect();
+    for producer in producers {
+        producer.join().unwrap();
+    }
+    assert!(queue.close());
+    assert!(!queue.close());
+    let total: u64 = consumers.into_iter().map(|c| c.join().unwrap()).sum();
+    assert_eq!(total, PRODUCERS * PER_PRODUCER);
+    assert!(queue.is_empty() && queue.is_closed());
+}
+
+/// The non-blocking and timed forms on a full and an empty queue, and
+/// what each form does once the queue is closed with a value left in it.
+#[test]
+fn timeouts_and_close() {
+    let queue = BlockingQueue::new(2);
+    assert_eq!(queue.try_pop(), Err(PopError::Empty));
+    let start = Instant::now();
+    let wait = Duration::from_millis(30);
+    assert_eq!(queue.pop_timeout(wait), Err(PopError::Timeout));
+    assert!(start.elapsed() >= wait);
+    queue.try_push(1).unwrap();
+    queue.push_timeout(2, Duration::ZERO).unwrap();
+    assert!(queue.is_full());
+    assert!(matches!(queue.try_push(3), Err(PushError::Full(3))));
+    let timed_out = queue.push_timeout(3, wait).unwrap_err();
+    assert_eq!(
+        format!("{timed_out} {timed_out:?}"),
+        "timed out waiting for room in the queue Timeout(..)"
+    );
+    assert_eq!(timed_out.into_inner(), 3);
+    assert_eq!(queue.pop_timeout(Duration::MAX), Ok(1));
+    assert_eq!(
+        format!("{queue:?}"),
+        "BlockingQueue { len: 1, capacity: 2, closed: false, .. }"
+    );
+
+    assert!(queue.close());
+    assert!(matches!(queue.push(9), Err(PushError::Closed(9))));
+    assert!(matches!(queue.try_push(9), Err(PushError::Closed(9))));
+    assert!(matches!(
+        queue.push_timeout(9, wait),
+        Err(PushError::Closed(9))
+    ));
+    assert_eq!(queue.try_pop(), Ok(2));
+    assert_eq!(queue.try_pop(), Err(PopError::Closed));
+    assert_eq!(queue.pop(), None);
+    assert_eq!(
+        queue.pop_timeout(Duration::from_secs(5)),
+        Err(PopError::Closed)
+    );
+    assert_eq!(PopError::Closed.to_string(), "queue is closed and empty");
+}
+
+/// Closing wakes consumers waiting on an empty queue, and draining wakes
+/// a producer waiting on a full one.
+#[test]
+fn waiters_are_woken() {
+    let queue = Arc::new(BlockingQueue::<u32>::new(1));
+    let waiting = Arc::clone(&queue);
+    let popper = thread::spawn(move || waiting.pop());
+    let waiting = Arc::clone(&queue);
+    let timed = thread::spawn(move || waiting.pop_timeout(Duration::from_secs(30)));
+    thread::sleep(Duration::from_millis(20));
+    queue.close();
+    assert_eq!(popper.join().unwrap(), None);
+    assert_eq!(timed.join().unwrap(), Err(PopError::Closed));
+
+    let queue = Arc::new(BlockingQueue::new(1));
+    queue.push(1).unwrap();
+    let waiting = Arc::clone(&queue);
+    let pusher = thread::spawn(move || waiting.push(2));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(queue.drain(), [1]);
+    pusher.join().unwrap().unwrap();
+    assert_eq!(queue.pop(), Some(2));
+    assert!(queue.drain().is_empty());
+}
+
+/// A queue must have room for at least one value.
+#[test]
+#[should_panic(expected = "capacity must be positive")]
+fn zero_capacity_panics() {
+    BlockingQueue::<u32>::new(0);
+}
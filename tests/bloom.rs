@@ -0,0 +1,194 @@
+//! The Bloom filter's false positive rate, serialized form and set
+//! operations, the stable hash it is built on, and the counting filter's
+//! removals and saturation.
+
+use datastructures::bloom::{BloomFilter, CountingBloomFilter};
+use datastructures::probabilistic::{stable_hash, DecodeError, Incompatible};
+
+/// A filter sized for 100,000 items at 1% finds every one of them, and
+/// about 1% of 100,000 others.
+#[test]
+fn false_positive_rate_near_target() {
+    let n = 100_000;
+    let mut filter = BloomFilter::new(n, 0.01);
+    assert!(filter.is_empty());
+    assert_eq!(filter.hash_count(), 7);
+    filter.extend(0..n as u64);
+    assert!((0..n as u64).all(|i| filter.contains(&i)));
+    let false_positives = (n as u64..2 * n as u64)
+        .filter(|i| filter.contains(i))
+        .count();
+    let rate = false_positives as f64 / n as f64;
+    assert!((0.007..0.013).contains(&rate), "{rate}");
+    assert!((filter.false_positive_rate() - 0.01).abs() < 0.002);
+    assert!((filter.estimated_len() / n as f64 - 1.0).abs() < 0.02);
+    assert!(!filter.insert(&0u64));
+    filter.clear();
+    assert!(filter.is_empty());
+    assert_eq!(filter.count_ones(), 0);
+}
+
+/// Very low rates are capped at 32 hashes.
+#[test]
+fn hash_count_is_capped() {
+    let filter = BloomFilter::new(10, 1e-30);
+    assert_eq!(filter.hash_count(), 32);
+}
+
+/// A filter reads back equal to itself, and damaged bytes are rejected.
+#[test]
+fn serialization_round_trips() {
+    let mut filter = BloomFilter::with_size(1000, 5, 9);
+    filter.extend(["a", "b", "c"]);
+    let bytes = filter.to_bytes();
+    assert_eq!(BloomFilter::from_bytes(&bytes), Ok(filter));
+    let short = &bytes[..bytes.len() - 1];
+    assert_eq!(BloomFilter::from_bytes(short), Err(DecodeError::Truncated));
+    assert_eq!(
+        BloomFilter::from_bytes(b"nope"),
+        Err(DecodeError::WrongFormat)
+    );
+
+    // Bits past the end must be clear.
+    let mut bytes = BloomFilter::with_size(10, 2, 0).to_bytes();
+    *bytes.last_mut().unwrap() = 0x80;
+    assert_eq!(BloomFilter::from_bytes(&bytes), Err(DecodeError::Invalid));
+
+    // The hash count sits after the four-byte tag.
+    for hashes in [0u32, 33, u32::MAX] {
+        let mut bytes = BloomFilter::with_size(10, 2, 0).to_bytes();
+        bytes[4..8].copy_from_slice(&hashes.to_le_bytes());
+        assert_eq!(BloomFilter::from_bytes(&bytes), Err(DecodeError::Invalid));
+    }
+}
+
+/// Union holds both filters' items, intersection neither's alone, and
+/// filters of different shapes do not combine.
+#[test]
+fn union_and_intersection() {
+    let mut a = BloomFilter::with_size(1000, 5, 1);
+    let mut b = BloomFilter::with_size(1000, 5, 1);
+    a.insert("x");
+    b.insert("y");
+    let mut union = a.clone();
+    union.union(&b).unwrap();
+    assert!(union.contains("x") && union.contains("y"));
+    let mut intersection = a.clone();
+    intersection.intersection(&b).unwrap();
+    assert!(!intersection.contains("x") && !intersection.contains("y"));
+    assert!(!a.is_compatible(&BloomFilter::with_size(1000, 5, 2)));
+    assert_eq!(
+        a.union(&BloomFilter::with_size(1000, 4, 1)),
+        Err(Incompatible)
+    );
+    assert_eq!(
+        a.union(&BloomFilter::with_size(999, 5, 1)),
+        Err(Incompatible)
+    );
+}
+
+/// The hash is pinned, so filters written by one build read back in
+/// another; integers hash alike whatever their width.
+#[test]
+fn stable_hash_is_fixed() {
+    assert_eq!(stable_hash(0, &1u32), 0xf500_7b52_92d2_f470);
+    assert_eq!(stable_hash(0, "abc"), 0xb297_3996_3627_42bf);
+    assert_eq!(stable_hash(0, &1u32), stable_hash(0, &1u64));
+    assert_eq!(stable_hash(0, &1u8), stable_hash(0, &1usize));
+    assert_ne!(stable_hash(0, &[1u8][..]), stable_hash(0, &[1u8, 0][..]));
+    assert_ne!(stable_hash(0, "abc"), stable_hash(1, "abc"));
+}
+
+/// Probing with more than 32 hashes is refused.
+#[test]
+#[should_panic(expected = "Bloom filter takes at most 32 hashes")]
+fn too_many_hashes_panics() {
+    BloomFilter::with_size(1000, 33, 0);
+}
+
+/// Every item inserted twice and half removed twice: the removed half is
+/// mostly gone, the rest all present, and the plain filter of the nonzero
+/// counters answers the same.
+#[test]
+fn counting_filter_removes_items() {
+    let n = 20_000u64;
+    let mut filter = CountingBloomFilter::new(n as usize, 0.01);
+    filter.extend(0..n);
+    filter.extend(0..n);
+    assert!((0..n).all(|i| filter.count(&i) >= 2));
+    for i in 0..n / 2 {
+        assert!(filter.remove(&i));
+        assert!(filter.remove(&i));
+    }
+    assert!((n / 2..n).all(|i| filter.contains(&i)));
+    let still_present = (0..n / 2).filter(|i| filter.contains(i)).count();
+    assert!(still_present < 400, "{still_present}");
+    let bloom = filter.to_bloom();
+    assert!((0..2 * n).all(|i| bloom.contains(&i) == filter.contains(&i)));
+
+    for i in n / 2..n {
+        filter.remove(&i);
+        filter.remove(&i);
+    }
+    assert_eq!(filter.saturated_count(), 0);
+    assert!(filter.is_empty());
+}
+
+/// A counter stops at 15 and then ignores removals.
+#[test]
+fn counting_filter_saturates() {
+    let mut filter = CountingBloomFilter::with_size(10, 1, 0);
+    for _ in 0..20 {
+        filter.insert("a");
+    }
+    assert_eq!(filter.count("a"), 15);
+    for _ in 0..20 {
+        filter.remove("a");
+    }
+    assert!(filter.contains("a"));
+    assert_eq!(filter.saturated_count(), 1);
+}
+
+/// A counting filter reads back equal to itself, and damaged bytes are
+/// rejected.
+#[test]
+fn counting_filter_serialization_round_trips() {
+    let mut filter = CountingBloomFilter::with_size(100, 3, 4);
+    filter.extend(["a", "b", "b"]);
+    let bytes = filter.to_bytes();
+    assert_eq!(CountingBloomFilter::from_bytes(&bytes), Ok(filter));
+    let short = &bytes[..bytes.len() - 1];
+    assert_eq!(
+        CountingBloomFilter::from_bytes(short),
+        Err(DecodeError::Truncated)
+    );
+    let plain = BloomFilter::with_size(100, 3, 4).to_bytes();
+    assert_eq!(
+        CountingBloomFilter::from_bytes(&plain),
+        Err(DecodeError::WrongFormat)
+    );
+
+    // Counters past the end must be zero.
+    let mut bytes = CountingBloomFilter::with_size(10, 2, 0).to_bytes();
+    *bytes.last_mut().unwrap() = 0x10;
+    assert_eq!(
+        CountingBloomFilter::from_bytes(&bytes),
+        Err(DecodeError::Invalid)
+    );
+
+    for hashes in [0u32, 33, u32::MAX] {
+        let mut bytes = CountingBloomFilter::with_size(10, 2, 0).to_bytes();
+        bytes[4..8].copy_from_slice(&hashes.to_le_bytes());
+        assert_eq!(
+            CountingBloomFilter::from_bytes(&bytes),
+            Err(DecodeError::Invalid)
+        );
+    }
+}
+
+/// The counting filter refuses more than 32 hashes too.
+#[test]
+#[should_panic(expected = "Bloom filter takes at most 32 hashes")]
+fn counting_filter_with_too_many_hashes_panics() {
+    CountingBloomFilter::with_size(1000, 33, 0);
+}
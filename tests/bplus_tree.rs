@@ -0,0 +1,112 @@
+//! The B+ tree against `std::collections::BTreeMap`, under random inserts
+//! and removals and after bulk loading, with ranges walked from both ends.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use datastructures::bplus_tree::BPlusTree;
+
+mod common;
+use common::Rng;
+
+fn random_bound(rng: &mut Rng) -> Bound<u32> {
+    let key = rng.below(600) as u32;
+    match rng.below(3) {
+        0 => Bound::Included(key),
+        1 => Bound::Excluded(key),
+        _ => Bound::Unbounded,
+    }
+}
+
+/// Whether `BTreeMap::range` would reject the bounds as out of order.
+fn is_reversed(lo: Bound<u32>, hi: Bound<u32>) -> bool {
+    match (lo, hi) {
+        (Bound::Included(x), Bound::Included(y)) => x > y,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            x >= y
+        }
+        _ => false,
+    }
+}
+
+/// Compares iteration both ways, the first and last entries, and random
+/// ranges consumed from a random mix of ends.
+fn check(tree: &BPlusTree<u32, u32>, map: &BTreeMap<u32, u32>, rng: &mut Rng) {
+    assert_eq!(tree.len(), map.len());
+    assert!(tree.iter().eq(map.iter()));
+    assert!(tree.iter().rev().eq(map.iter().rev()));
+    assert_eq!(tree.first_key_value(), map.first_key_value());
+    assert_eq!(tree.last_key_value(), map.last_key_value());
+    for _ in 0..20 {
+        let (lo, hi) = (random_bound(rng), random_bound(rng));
+        if is_reversed(lo, hi) {
+            assert_eq!(tree.range((lo, hi)).count(), 0);
+            continue;
+        }
+        let (mut got, mut want) = (tree.range((lo, hi)), map.range((lo, hi)));
+        loop {
+            let (x, y) = if rng.below(2) == 0 {
+                (got.next(), want.next())
+            } else {
+                (got.next_back(), want.next_back())
+            };
+            assert_eq!(x, y);
+            if x.is_none() {
+                break;
+            }
+        }
+    }
+}
+
+/// Random operations at several orders, down to an empty tree.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(7);
+    for order in [3usize, 4, 5, 8, 32] {
+        let mut tree = BPlusTree::with_order(order);
+        let mut map = BTreeMap::new();
+        for step in 0..20000 {
+            let k = rng.below(600) as u32;
+            if rng.below(3) == 0 {
+                assert_eq!(tree.remove(&k), map.remove(&k));
+            } else {
+                assert_eq!(tree.insert(k, step), map.insert(k, step));
+            }
+            assert_eq!(tree.get(&k), map.get(&k));
+            if step % 500 == 0 {
+                check(&tree, &map, &mut rng);
+            }
+        }
+        check(&tree, &map, &mut rng);
+        let keys: Vec<u32> = map.keys().copied().collect();
+        for k in keys {
+            assert_eq!(tree.remove(&k), map.remove(&k));
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+        check(&tree, &map, &mut rng);
+    }
+}
+
+/// A bulk-loaded tree matches the map it was built from, for sizes around
+/// one node, and stays correct under later operations.
+#[test]
+fn bulk_loaded_tree_matches_btreemap() {
+    let mut rng = Rng(8);
+    for order in [3usize, 4, 5, 8, 32] {
+        for n in [0usize, 1, 2, 3, order, order + 1, 100, 1000] {
+            let mut map: BTreeMap<u32, u32> = (0..n as u32).map(|i| (i * 2, i)).collect();
+            let mut tree = BPlusTree::bulk_load(order, map.clone());
+            check(&tree, &map, &mut rng);
+            for _ in 0..2000 {
+                let k = rng.below(2 * n as u64 + 2) as u32;
+                if rng.below(2) == 0 {
+                    assert_eq!(tree.remove(&k), map.remove(&k));
+                } else {
+                    assert_eq!(tree.insert(k, 1), map.insert(k, 1));
+                }
+            }
+            check(&tree, &map, &mut rng);
+        }
+    }
+}
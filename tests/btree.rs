@@ -0,0 +1,276 @@
+//! The B-tree against `std::collections::BTreeMap`, for map operations,
+//! range removal, cursor walks and edits, bulk loading and order
+//! statistics, at several orders.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use datastructures::btree::BTree;
+
+mod common;
+use common::Rng;
+
+fn entries<K: Ord + Copy, V: Copy>(tree: &BTree<K, V>) -> Vec<(K, V)> {
+    tree.iter().map(|(&k, &v)| (k, v)).collect()
+}
+
+/// The deepest a tree of `order` holding `len` entries may be: every node
+/// but the root has at least `ceil(order / 2)` children.
+fn max_height(order: usize, len: usize) -> usize {
+    let fanout = order.div_ceil(2) as f64;
+    1 + ((len.max(1) as f64 + 1.0) / 2.0).log(fanout).floor() as usize
+}
+
+/// Random inserts, removals and range drains match `BTreeMap`, as do
+/// lookups, ranges, the first and last entries and iteration, and the
+/// height stays within the B-tree bound.
+#[test]
+fn map_operations_match_btreemap() {
+    for order in [3, 4, 5, 8, 16] {
+        let mut rng = Rng(order as u64);
+        let mut tree = BTree::with_order(order);
+        let mut map = BTreeMap::new();
+        assert_eq!(tree.order(), order);
+        for step in 0..10000u32 {
+            let k = rng.below(600);
+            match rng.below(10) {
+                0..=4 => assert_eq!(tree.insert(k, step), map.insert(k, step)),
+                5..=7 => assert_eq!(tree.remove(&k), map.remove(&k)),
+                8 => {
+                    let end = k + rng.below(30);
+                    let got = tree.drain_range(k..end);
+                    let want: Vec<_> = map.range(k..end).map(|(&a, &b)| (a, b)).collect();
+                    for (a, _) in &want {
+                        map.remove(a);
+                    }
+                    assert_eq!(got, want);
+                }
+                _ => {
+                    if let Some(v) = tree.get_mut(&k) {
+                        *v += 1;
+                        *map.get_mut(&k).unwrap() += 1;
+                    }
+                }
+            }
+            assert_eq!(tree.len(), map.len());
+            assert_eq!(tree.get(&k), map.get(&k));
+            assert_eq!(tree.contains_key(&k), map.contains_key(&k));
+            assert!(tree.height() <= max_height(order, tree.len()));
+            if step % 101 != 0 {
+                continue;
+            }
+            assert_eq!(
+                entries(&tree),
+                map.iter().map(|(&a, &b)| (a, b)).collect::<Vec<_>>()
+            );
+            assert_eq!(tree.first_key_value(), map.first_key_value());
+            assert_eq!(tree.last_key_value(), map.last_key_value());
+            let (lo, hi) = (rng.below(600), rng.below(600));
+            let got: Vec<_> = tree.range(lo..=hi).collect();
+            let want: Vec<_> = if lo <= hi {
+                map.range(lo..=hi).collect()
+            } else {
+                Vec::new()
+            };
+            assert_eq!(got, want);
+            let got: Vec<_> = tree
+                .range((Bound::Excluded(lo), Bound::Unbounded))
+                .collect();
+            let want: Vec<_> = map.range((Bound::Excluded(lo), Bound::Unbounded)).collect();
+            assert_eq!(got, want);
+        }
+        let drained = tree.drain_range(..);
+        assert_eq!(drained.len(), map.len());
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+    }
+}
+
+/// Cursors walk forward and back through the ghost position, seek to
+/// bounds like `BTreeMap::range`, and edit the tree in place.
+#[test]
+fn cursors_walk_and_edit() {
+    let mut tree = BTree::bulk_load(4, (0..50u32).map(|i| (i * 2, i)));
+    let mut c = tree.cursor_front();
+    for i in 0..50 {
+        assert_eq!(c.peek(), Some((&(i * 2), &i)));
+        c.move_next();
+    }
+    assert_eq!(c.peek(), None);
+    c.move_next();
+    assert_eq!(c.peek(), Some((&0, &0)));
+    c.move_prev();
+    c.move_prev();
+    assert_eq!(c.peek(), Some((&98, &49)));
+    assert_eq!(tree.cursor_back().peek(), Some((&98, &49)));
+
+    assert_eq!(
+        tree.lower_bound(Bound::Included(&10)).peek(),
+        Some((&10, &5))
+    );
+    assert_eq!(
+        tree.lower_bound(Bound::Excluded(&10)).peek(),
+        Some((&12, &6))
+    );
+    assert_eq!(
+        tree.lower_bound(Bound::Included(&11)).peek(),
+        Some((&12, &6))
+    );
+    assert_eq!(tree.lower_bound(Bound::Excluded(&98)).peek(), None);
+    assert_eq!(
+        tree.upper_bound(Bound::Included(&10)).peek(),
+        Some((&10, &5))
+    );
+    assert_eq!(
+        tree.upper_bound(Bound::Excluded(&10)).peek(),
+        Some((&8, &4))
+    );
+    assert_eq!(tree.upper_bound(Bound::Excluded(&0)).peek(), None);
+    assert_eq!(
+        tree.upper_bound::<u32>(Bound::Unbounded).peek(),
+        Some((&98, &49))
+    );
+
+    // Fill every odd key through cursors, removing multiples of eight.
+    let mut c = tree.cursor_front_mut();
+    while let Some((&k, _)) = c.peek() {
+        if k % 8 == 0 {
+            assert_eq!(c.remove_current().map(|x| x.0), Some(k));
+            continue;
+        }
+        c.insert_after(k + 1, 100 + k + 1);
+        assert_eq!(c.peek().map(|x| *x.0), Some(k));
+        *c.peek_mut().unwrap().1 += 1000;
+        c.move_next();
+        c.move_next();
+    }
+    c.insert_before(200, 200);
+    assert_eq!(c.peek(), None);
+    let mut c = tree.lower_bound_mut(Bound::Included(&2));
+    c.insert_before(1, 1);
+    assert_eq!(c.peek().map(|x| *x.0), Some(2));
+    let mut c = tree.upper_bound_mut(Bound::Excluded(&0));
+    c.insert_after(0, 0);
+    assert_eq!(c.tree().first_key_value(), Some((&0, &0)));
+
+    let mut want = BTreeMap::from([(0, 0), (1, 1), (200, 200)]);
+    for k in (0..100).filter(|k| k % 2 == 0 && k % 8 != 0) {
+        want.insert(k, k / 2 + 1000);
+        want.insert(k + 1, 100 + k + 1);
+    }
+    assert_eq!(entries(&tree), want.into_iter().collect::<Vec<_>>());
+
+    let mut c = tree.cursor_back_mut();
+    c.move_next();
+    assert_eq!(c.remove_current(), None);
+    assert_eq!(tree.len(), 77);
+}
+
+/// Cursor inserts keep the order of the keys.
+#[test]
+#[should_panic(expected = "inserted key must fall between the cursor's neighbors")]
+fn cursor_insert_out_of_order_panics() {
+    let mut tree = BTree::bulk_load(3, [(1, ()), (3, ())]);
+    tree.cursor_front_mut().insert_after(4, ());
+}
+
+/// Bulk loading gives the same map as inserting one by one, with a height
+/// no greater, for sizes around each level boundary.
+#[test]
+fn bulk_load_matches_insertion() {
+    for order in [3, 4, 7, 16] {
+        for n in [0usize, 1, 2, 3, 10, 63, 64, 65, 1000] {
+            let items: Vec<(usize, usize)> = (0..n).map(|i| (i * 3, i)).collect();
+            let loaded = BTree::bulk_load(order, items.iter().copied());
+            let mut inserted = BTree::with_order(order);
+            for &(k, v) in &items {
+                inserted.insert(k, v);
+            }
+            assert_eq!(entries(&loaded), items);
+            assert_eq!(loaded.len(), n);
+            assert!(loaded.height() <= inserted.height());
+            assert!(loaded.height() <= max_height(order, n));
+
+            let mut tree = loaded.clone();
+            for &(k, _) in items.iter().step_by(2) {
+                assert!(tree.remove(&k).is_some());
+            }
+            tree.insert(1, 1);
+            assert_eq!(tree.len(), n - n.div_ceil(2) + 1);
+        }
+    }
+}
+
+/// Bulk loading rejects keys that are not strictly increasing.
+#[test]
+#[should_panic(expected = "bulk-loaded keys must be strictly increasing")]
+fn bulk_load_of_unsorted_keys_panics() {
+    BTree::bulk_load(4, [(1, ()), (1, ())]);
+}
+
+/// Checks `select` and `rank` against the sorted keys of `map`, for every
+/// position and for keys between stored ones.
+fn check_order_statistics(tree: &BTree<u64, u32>, map: &BTreeMap<u64, u32>) {
+    let keys: Vec<u64> = map.keys().copied().collect();
+    for (i, k) in keys.iter().enumerate() {
+        assert_eq!(tree.select(i).map(|x| x.0), Some(k));
+        assert_eq!(tree.rank(k), i);
+    }
+    assert_eq!(tree.select(keys.len()), None);
+    for q in 0..510 {
+        assert_eq!(tree.rank(&q), keys.partition_point(|&x| x < q));
+    }
+}
+
+/// `select` and `rank` stay right through inserts, removals, range drains
+/// and removals through a cursor, all of which update the subtree sizes.
+#[test]
+fn order_statistics_match_btreemap() {
+    for order in [3, 4, 5, 8] {
+        let mut rng = Rng(order as u64 * 7 + 1);
+        let mut tree = BTree::with_order(order);
+        let mut map = BTreeMap::new();
+        for step in 0..10000u32 {
+            let k = rng.below(500);
+            match rng.below(5) {
+                0 | 1 => assert_eq!(tree.insert(k, step), map.insert(k, step)),
+                2 => assert_eq!(tree.remove(&k), map.remove(&k)),
+                3 => {
+                    let got = tree.drain_range(k..k + 20);
+                    let want: Vec<_> = map.range(k..k + 20).map(|(&a, &b)| (a, b)).collect();
+                    for (a, _) in &want {
+                        map.remove(a);
+                    }
+                    assert_eq!(got, want);
+                }
+                _ => {
+                    let mut c = tree.lower_bound_mut(Bound::Included(&k));
+                    let removed = c.remove_current();
+                    let want = map.range(k..).next().map(|(&a, &b)| (a, b));
+                    assert_eq!(removed, want);
+                    if let Some((a, _)) = removed {
+                        map.remove(&a);
+                    }
+                }
+            }
+            if step % 97 == 0 {
+                check_order_statistics(&tree, &map);
+            }
+        }
+        check_order_statistics(&tree, &map);
+    }
+}
+
+/// A bulk-loaded tree has its subtree sizes set from the start.
+#[test]
+fn bulk_loaded_order_statistics() {
+    for order in [3, 4, 8] {
+        let tree = BTree::bulk_load(order, (0..1000u64).map(|i| (i * 2, i)));
+        for i in 0..1000 {
+            assert_eq!(tree.select(i as usize), Some((&(i * 2), &i)));
+            assert_eq!(tree.rank(&(i * 2)), i as usize);
+            assert_eq!(tree.rank(&(i * 2 + 1)), i as usize + 1);
+        }
+        assert_eq!(tree.select(1000), None);
+    }
+}
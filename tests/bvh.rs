@@ -0,0 +1,91 @@
+//! The SAH BVH over spheres, against a scan of every sphere, before and
+//! after the spheres move and the hierarchy is refitted and rebuilt.
+
+use datastructures::bvh::{Bvh, Ray};
+use datastructures::rtree::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+/// A sphere as its center and radius.
+type Sphere = ([f64; 3], f64);
+
+/// The nearest `t >= 0` at which `ray` meets `sphere`.
+fn hit_sphere(sphere: &Sphere, ray: &Ray<3>) -> Option<f64> {
+    let (center, r) = sphere;
+    let oc: [f64; 3] = std::array::from_fn(|a| ray.origin[a] - center[a]);
+    let a: f64 = ray.direction.iter().map(|d| d * d).sum();
+    let b: f64 = 2.0 * (0..3).map(|i| oc[i] * ray.direction[i]).sum::<f64>();
+    let c: f64 = oc.iter().map(|x| x * x).sum::<f64>() - r * r;
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let near = (-b - disc.sqrt()) / (2.0 * a);
+    let far = (-b + disc.sqrt()) / (2.0 * a);
+    [near, far].into_iter().find(|&t| t >= 0.0)
+}
+
+fn bounding_box(sphere: &Sphere) -> Aabb<3> {
+    Aabb::new(
+        sphere.0.map(|x| x - sphere.1),
+        sphere.0.map(|x| x + sphere.1),
+    )
+}
+
+/// Checks random ray and box queries on `bvh`, whose primitives index
+/// into `spheres`.
+fn check(bvh: &Bvh<usize, 3>, spheres: &[Sphere], rng: &mut Rng) {
+    let hit = |&i: &usize, ray: &Ray<3>| hit_sphere(&spheres[i], ray);
+    for _ in 0..200 {
+        let origin = [rng.range(-12.0, 12.0), rng.range(-12.0, 12.0), -15.0];
+        let direction = [rng.range(-0.5, 0.5), rng.range(-0.5, 0.5), 1.0];
+        let ray = Ray::new(origin, direction);
+        let got = bvh.closest_hit(&ray, f64::INFINITY, hit).map(|x| x.2);
+        let want = spheres
+            .iter()
+            .filter_map(|s| hit_sphere(s, &ray))
+            .min_by(f64::total_cmp);
+        assert_eq!(got, want);
+        let any = bvh.any_hit(&ray, f64::INFINITY, hit);
+        assert_eq!(any.is_some(), want.is_some());
+
+        let q = Aabb::new(
+            [origin[0] - 2.0, origin[1] - 2.0, -3.0],
+            [origin[0] + 2.0, origin[1] + 2.0, 3.0],
+        );
+        let got: Vec<usize> = bvh.intersecting(q).map(|x| *x.2).collect();
+        let want: Vec<usize> = (0..spheres.len())
+            .filter(|&i| bounding_box(&spheres[i]).intersects(&q))
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+    }
+}
+
+/// Closest-hit, any-hit and overlap queries agree with a scan, after the
+/// build, after every sphere moves and `refit` updates the bounds in place,
+/// and after `rebuild`.
+#[test]
+fn ray_and_box_queries_match_brute_force() {
+    let mut rng = Rng(5);
+    for n in [0usize, 1, 3, 50, 500] {
+        let mut spheres: Vec<Sphere> = (0..n)
+            .map(|_| {
+                let center = std::array::from_fn(|_| rng.range(-10.0, 10.0));
+                (center, rng.range(0.1, 1.0))
+            })
+            .collect();
+        let mut bvh = Bvh::build((0..n).map(|i| (bounding_box(&spheres[i]), i)).collect());
+        assert_eq!(bvh.len(), n);
+        check(&bvh, &spheres, &mut rng);
+
+        for s in &mut spheres {
+            s.0[2] += rng.range(-3.0, 3.0);
+        }
+        bvh.refit(|&i| bounding_box(&spheres[i]));
+        check(&bvh, &spheres, &mut rng);
+
+        bvh.rebuild();
+        check(&bvh, &spheres, &mut rng);
+    }
+}
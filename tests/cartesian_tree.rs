@@ -0,0 +1,103 @@
+//! The Cartesian tree against a linear scan for the leftmost minimum, on
+//! sequences with many ties and with few, and its shape against the
+//! definition: heap order, in-order positions, and consistent links.
+
+use datastructures::cartesian_tree::CartesianTree;
+use datastructures::sparse_table::FischerHeun;
+
+mod common;
+use common::Rng;
+
+/// The position of the leftmost minimum of `values[lo..hi]`.
+fn scan(values: &[u64], lo: usize, hi: usize) -> Option<usize> {
+    (lo..hi).min_by_key(|&i| (values[i], i))
+}
+
+/// The tree's links, depths and order, then random lowest common
+/// ancestors and range minima, for lengths around the block sizes of the
+/// underlying table.
+#[test]
+fn queries_match_scan() {
+    let mut rng = Rng(5);
+    for n in [0, 1, 2, 3, 7, 64, 65, 130, 1000] {
+        for range in [4, 1000] {
+            let values: Vec<u64> = (0..n).map(|_| rng.below(range)).collect();
+            let tree = CartesianTree::new(&values);
+            assert_eq!(tree.len(), n);
+            assert_eq!(tree.is_empty(), n == 0);
+            assert_eq!(tree.root(), scan(&values, 0, n));
+            let mut pre: Vec<usize> = tree.pre_order().collect();
+            pre.sort_unstable();
+            assert!(pre.into_iter().eq(0..n));
+            for i in 0..n {
+                match tree.parent(i) {
+                    Some(p) => {
+                        assert!(values[p] <= values[i]);
+                        assert!(tree.left(p) == Some(i) || tree.right(p) == Some(i));
+                        assert_eq!(tree.depth(i), tree.depth(p) + 1);
+                    }
+                    None => {
+                        assert_eq!(tree.root(), Some(i));
+                        assert_eq!(tree.depth(i), 0);
+                    }
+                }
+                if let Some(l) = tree.left(i) {
+                    assert!(l < i && values[l] > values[i]);
+                }
+                if let Some(r) = tree.right(i) {
+                    assert!(r > i && values[r] >= values[i]);
+                }
+            }
+            if n == 0 {
+                assert_eq!(tree.argmin(..), None);
+                continue;
+            }
+            for _ in 0..2000 {
+                let (a, b) = (rng.index(n), rng.index(n));
+                let (lo, hi) = (a.min(b), a.max(b));
+                assert_eq!(Some(tree.lca(a, b)), scan(&values, lo, hi + 1));
+                assert_eq!(tree.argmin(lo..hi), scan(&values, lo, hi));
+                assert_eq!(tree.argmin(lo..=hi), scan(&values, lo, hi + 1));
+            }
+        }
+    }
+}
+
+/// The tree a `FischerHeun` table hands out is the tree of its values, and
+/// a pre-order visits each node before its subtrees.
+#[test]
+fn from_fischer_heun() {
+    let values = vec![3, 1, 4, 1, 5, 9, 2, 6];
+    let tree = FischerHeun::new(values.clone()).cartesian_tree();
+    let direct = CartesianTree::new(&values);
+    assert_eq!(tree.root(), Some(1));
+    assert_eq!(
+        tree.pre_order().collect::<Vec<_>>(),
+        [1, 0, 3, 2, 6, 4, 5, 7]
+    );
+    assert!(tree.pre_order().eq(direct.pre_order()));
+    assert_eq!(tree.lca(4, 7), 6);
+    assert_eq!(tree.lca(0, 3), 1);
+}
+
+/// Monotone inputs make a single path, deep enough that any recursion in
+/// building or querying would overflow the stack.
+#[test]
+fn deep_chains() {
+    let ascending: Vec<u32> = (0..200_000).collect();
+    let tree = CartesianTree::new(&ascending);
+    assert_eq!(tree.depth(199_999), 199_999);
+    assert_eq!(tree.argmin(5000..150_000), Some(5000));
+    assert_eq!(tree.pre_order().count(), 200_000);
+    let descending: Vec<u32> = (0..200_000).rev().collect();
+    let tree = CartesianTree::new(&descending);
+    assert_eq!(tree.root(), Some(199_999));
+    assert_eq!(tree.argmin(5000..150_000), Some(149_999));
+}
+
+/// Ranges must lie within the sequence.
+#[test]
+#[should_panic(expected = "range 2..5 out of bounds for length 4")]
+fn range_out_of_bounds_panics() {
+    CartesianTree::new(&[1, 2, 3, 4]).argmin(2..5);
+}
@@ -0,0 +1,126 @@
+//! The catenable deque against `VecDeque`, for a pool of deques that hand
+//! pieces back and forth by appending and splitting, and clones that must
+//! be unaffected by later changes to the deque they were taken from.
+
+use std::collections::VecDeque;
+
+use datastructures::catenable_deque::CatenableDeque;
+use datastructures::finger_tree::Deque;
+
+mod common;
+use common::Rng;
+
+/// Random pushes, pops, appends, splits and reads across six deques, with
+/// the ends checked after every step and the whole sequence now and then.
+#[test]
+fn operations_match_vecdeque() {
+    let mut rng = Rng(4);
+    let mut pool: Vec<(CatenableDeque<u32>, VecDeque<u32>)> =
+        (0..6).map(|_| Default::default()).collect();
+    for step in 0..20_000 {
+        let i = rng.index(pool.len());
+        let j = rng.index(pool.len());
+        let (deque, model) = &mut pool[i];
+        match rng.below(10) {
+            0 | 1 => {
+                deque.push_back(step);
+                model.push_back(step);
+            }
+            2 => {
+                deque.push_front(step);
+                model.push_front(step);
+            }
+            3 => assert_eq!(deque.pop_front(), model.pop_front()),
+            4 => assert_eq!(deque.pop_back(), model.pop_back()),
+            5 | 6 if i != j => {
+                let (other, mut other_model) = std::mem::take(&mut pool[j]);
+                let pieces = pool[i].0.pieces() + other.pieces();
+                pool[i].0.append(other);
+                pool[i].1.append(&mut other_model);
+                assert_eq!(pool[i].0.pieces(), pieces);
+            }
+            7 => {
+                let at = rng.index(model.len() + 1);
+                let rest = deque.split_off(at);
+                let rest_model = model.split_off(at);
+                assert!(deque.pieces() <= 1 && rest.pieces() <= 1);
+                assert!(rest.iter().eq(&rest_model));
+                if i != j {
+                    pool[j] = (rest, rest_model);
+                }
+            }
+            8 => {
+                let k = rng.index(model.len() + 2);
+                assert_eq!(deque.get(k), model.get(k));
+            }
+            9 => {
+                deque.compact();
+                assert!(deque.pieces() <= 1);
+            }
+            _ => {}
+        }
+        let (deque, model) = &pool[i];
+        assert_eq!(
+            (deque.len(), deque.is_empty()),
+            (model.len(), model.is_empty())
+        );
+        assert_eq!((deque.front(), deque.back()), (model.front(), model.back()));
+        if step % 97 == 0 {
+            assert!(deque.iter().eq(model));
+            let rebuilt: CatenableDeque<u32> = model.iter().copied().collect();
+            assert_eq!(&rebuilt, deque);
+        }
+    }
+}
+
+/// A clone holding unjoined pieces keeps its contents while the original
+/// is split, popped and appended to, and can be split itself.
+#[test]
+fn clones_are_independent() {
+    let mut deque = CatenableDeque::new();
+    for chunk in 0..50 {
+        deque.append((chunk * 10..chunk * 10 + 10).collect());
+    }
+    assert_eq!(deque.pieces(), 50);
+    let mut copy = deque.clone();
+    let rest = deque.split_off(200);
+    assert_eq!(deque.pieces(), 1);
+    deque.pop_front();
+    deque.append(rest);
+    deque.push_back(-1);
+    assert_eq!(copy.pieces(), 50);
+    assert!(copy.iter().copied().eq(0..500));
+    assert_eq!(copy.get(321), Some(&321));
+    let tail = copy.split_off(499);
+    assert!(tail.iter().eq([499].iter()));
+    assert!(deque.iter().copied().eq((1..500).chain([-1])));
+}
+
+/// Conversions, extending and clearing, and splitting at the ends.
+#[test]
+fn conversions_and_edges() {
+    let mut deque: CatenableDeque<char> = Deque::from_iter("abc".chars()).into();
+    assert_eq!(deque.pieces(), 1);
+    assert_eq!(CatenableDeque::from(Deque::<char>::new()).pieces(), 0);
+    deque.extend("de".chars());
+    assert_eq!(format!("{deque:?}"), r#"['a', 'b', 'c', 'd', 'e']"#);
+    assert!(deque.split_off(5).is_empty());
+    let all = deque.split_off(0);
+    assert!(deque.is_empty() && deque.pieces() == 0);
+    assert_eq!(all.len(), 5);
+    deque.append(all);
+    deque.append(CatenableDeque::new());
+    assert_eq!(deque.pieces(), 1);
+    deque.clear();
+    assert_eq!(
+        (deque.len(), deque.pop_back(), deque.get(0)),
+        (0, None, None)
+    );
+}
+
+/// A split must fall within the deque.
+#[test]
+#[should_panic(expected = "split index out of bounds")]
+fn split_out_of_bounds_panics() {
+    CatenableDeque::from_iter([1, 2]).split_off(3);
+}
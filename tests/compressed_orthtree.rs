@@ -0,0 +1,122 @@
+//! The compressed octree against a list of live points, under random
+//! inserts, moves and removals, for uniform points and for tight clusters
+//! that force long compressed paths.
+
+use datastructures::compressed_orthtree::{
+    CompressedId, CompressedOctree, CompressedOrthtree, CompressedQuadtree,
+};
+use datastructures::geom::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+fn distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+/// Random operations over a cube of side 2e9, with points either uniform
+/// or packed around four centers at scales of 1 and 1e-3.
+fn matches_scan(capacity: usize, clustered: bool, seed: u64) {
+    let mut rng = Rng(seed);
+    let bounds = Aabb::new([-1e9; 3], [1e9; 3]);
+    let mut tree: CompressedOctree<[f64; 3], u32> =
+        CompressedOrthtree::with_bucket_capacity(bounds, capacity);
+    let mut live: Vec<(CompressedId, [f64; 3])> = Vec::new();
+    let centers: Vec<[f64; 3]> = (0..4)
+        .map(|_| std::array::from_fn(|_| rng.range(-1e9, 1e9)))
+        .collect();
+    let point = |rng: &mut Rng| -> [f64; 3] {
+        if !clustered {
+            return std::array::from_fn(|_| rng.range(-1e9, 1e9));
+        }
+        let c = centers[rng.index(4)];
+        let s = if rng.below(2) == 0 { 1e-3 } else { 1.0 };
+        std::array::from_fn(|a| (c[a] + rng.range(-s, s)).min(1e9))
+    };
+    for step in 0..6000 {
+        let op = rng.below(10);
+        if op < 6 || live.is_empty() {
+            let p = point(&mut rng);
+            live.push((tree.insert(p, step).unwrap(), p));
+        } else if op < 8 {
+            let (id, p) = live.swap_remove(rng.index(live.len()));
+            assert_eq!(tree.remove(id).unwrap().0, p);
+            assert!(tree.remove(id).is_none());
+        } else {
+            let i = rng.index(live.len());
+            let p = point(&mut rng);
+            assert_eq!(tree.relocate(live[i].0, p).unwrap(), Some(live[i].1));
+            live[i].1 = p;
+        }
+        assert_eq!(tree.len(), live.len());
+        if step % 97 != 0 {
+            continue;
+        }
+
+        let q = point(&mut rng);
+        let got = tree.nearest(q, 7);
+        let mut want: Vec<f64> = live
+            .iter()
+            .map(|x| distance_squared(&x.1, &q).sqrt())
+            .collect();
+        want.sort_by(f64::total_cmp);
+        want.truncate(7);
+        assert_eq!(got.len(), want.len());
+        for (g, w) in got.iter().zip(&want) {
+            assert!((g.1 - w).abs() <= 1e-9 * w.max(1.0), "{} {}", g.1, w);
+        }
+
+        let r = if clustered { 0.5 } else { 3e8 };
+        let got: Vec<_> = tree.within_radius(q, r).map(|x| x.0).collect();
+        let want: Vec<_> = live
+            .iter()
+            .filter(|x| distance_squared(&x.1, &q) <= r * r)
+            .map(|x| x.0)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+        let query = Aabb::new(q.map(|c| c - r), [q[0] + r, q[1] + r, q[2] + 2.0 * r]);
+        let got: Vec<_> = tree.within_box(query).map(|x| x.0).collect();
+        let want: Vec<_> = live
+            .iter()
+            .filter(|x| query.contains_point(&x.1))
+            .map(|x| x.0)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+        assert_eq!(tree.iter().count(), live.len());
+    }
+    // Compression keeps the height bounded by the bits of precision, not
+    // by how close the clustered points are.
+    assert!(tree.height() <= 64, "height {}", tree.height());
+    while let Some((id, _)) = live.pop() {
+        tree.remove(id).unwrap();
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.height(), 1);
+}
+
+/// Queries agree with a scan for several bucket sizes, on uniform and
+/// clustered points.
+#[test]
+fn queries_match_brute_force() {
+    for capacity in [1, 2, 8] {
+        for clustered in [false, true] {
+            matches_scan(capacity, clustered, capacity as u64 * 31 + clustered as u64);
+        }
+    }
+}
+
+/// Coincident points beyond the bucket capacity stay in one bucket, and
+/// points outside the bounds are rejected.
+#[test]
+fn duplicates_and_bounds() {
+    let bounds = Aabb::new([0.0; 2], [1.0; 2]);
+    let mut tree: CompressedQuadtree<[f64; 2], ()> =
+        CompressedOrthtree::with_bucket_capacity(bounds, 2);
+    for _ in 0..10 {
+        tree.insert([0.5, 0.5], ()).unwrap();
+    }
+    assert!(tree.insert([2.0, 0.0], ()).is_err());
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.nearest([0.0, 0.0], 3).len(), 3);
+    assert_eq!(tree.within_radius([0.5, 0.5], 0.0).count(), 10);
+}
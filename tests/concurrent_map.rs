@@ -0,0 +1,161 @@
+//! The concurrent hash map against `std::collections::HashMap` on one
+//! thread, and under concurrent readers and writers. That entries must not
+//! borrow is checked by a `compile_fail` example on the type.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use datastructures::concurrent_map::ConcurrentHashMap;
+
+mod common;
+use common::Rng;
+
+/// Random operations on one thread match a `HashMap`, with one shard and
+/// several, through enough inserts to grow every shard.
+#[test]
+fn operations_match_hashmap() {
+    let mut rng = Rng(5);
+    for shards in [1, 4] {
+        let map = ConcurrentHashMap::with_shards(shards);
+        assert_eq!(map.shard_count(), shards);
+        let mut model: HashMap<u64, u64> = HashMap::new();
+        for step in 0..30000u64 {
+            let k = rng.below(3000);
+            match rng.below(7) {
+                0 | 1 => assert_eq!(map.insert(k, step), model.insert(k, step)),
+                2 => assert_eq!(map.remove(&k), model.remove(&k)),
+                3 => {
+                    assert_eq!(map.get(&k), model.get(&k).copied());
+                    assert_eq!(map.read(&k, |v| v * 2), model.get(&k).map(|v| v * 2));
+                    assert_eq!(map.contains_key(&k), model.contains_key(&k));
+                }
+                4 => {
+                    let want = model.get_mut(&k).map(|v| {
+                        *v += 1;
+                        *v
+                    });
+                    assert_eq!(map.update(k, |v| v + 1), want);
+                }
+                5 => {
+                    let want = *model.entry(k).or_insert(7);
+                    assert_eq!(map.get_or_insert_with(k, || 7), want);
+                }
+                _ => {
+                    // Keep odd values, drop even ones, insert when absent.
+                    let want = match model.get(&k) {
+                        Some(v) if v % 2 == 0 => None,
+                        Some(&v) => Some(v),
+                        None => Some(step),
+                    };
+                    let got = map.compute(k, |v| match v {
+                        Some(v) if v % 2 == 0 => None,
+                        Some(&v) => Some(v),
+                        None => Some(step),
+                    });
+                    assert_eq!(got, want);
+                    match want {
+                        Some(v) => model.insert(k, v),
+                        None => model.remove(&k),
+                    };
+                }
+            }
+            assert_eq!(map.len(), model.len());
+        }
+        let mut seen = HashMap::new();
+        map.for_each(|&k, &v| assert!(seen.insert(k, v).is_none()));
+        assert_eq!(seen, model);
+        map.clear();
+        assert!(map.is_empty());
+        assert!(!map.contains_key(&1));
+    }
+}
+
+/// Concurrent read-modify-write updates of shared keys are atomic: no
+/// thread's update is lost.
+#[test]
+fn concurrent_updates_are_atomic() {
+    let map = Arc::new(ConcurrentHashMap::<u64, Vec<u64>>::with_shards(8));
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let map = Arc::clone(&map);
+            thread::spawn(move || {
+                let mut rng = Rng(t + 1);
+                for i in 0..20000u64 {
+                    let k = rng.below(500);
+                    map.compute(k, |v| {
+                        let mut v = v.cloned().unwrap_or_default();
+                        v.push(t);
+                        Some(v)
+                    });
+                    if i % 7 == 0 {
+                        map.read(&k, |v| assert!(!v.is_empty()));
+                    }
+                    if i % 13 == 0 {
+                        assert_eq!(map.update(k + 1000, |v| v.clone()), None);
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let mut total = 0;
+    map.for_each(|_, v| total += v.len());
+    assert_eq!(total, 8 * 20000);
+}
+
+/// Readers never see a torn or mismatched value while writers insert,
+/// remove and clear underneath them, through shard growth.
+#[test]
+fn readers_see_consistent_values() {
+    let map = Arc::new(ConcurrentHashMap::<u64, String>::with_shards(2));
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let map = Arc::clone(&map);
+            thread::spawn(move || {
+                let mut rng = Rng(t + 100);
+                for _ in 0..40000 {
+                    let k = rng.below(2000);
+                    if t >= 3 {
+                        if let Some(v) = map.get(&k) {
+                            assert_eq!(v, format!("v{k}"));
+                        }
+                    } else if rng.below(2000) == 0 {
+                        map.clear();
+                    } else if rng.below(2) == 0 {
+                        map.insert(k, format!("v{k}"));
+                    } else {
+                        map.remove(&k);
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+}
+
+/// Entries still in the map are dropped with it, at once, while copies
+/// displaced by growth wait for the epoch collector.
+#[test]
+fn drops_remaining_entries() {
+    let value = Arc::new(());
+    let map = ConcurrentHashMap::with_shards(2);
+    for k in 0..100 {
+        map.insert(k, Arc::clone(&value));
+    }
+    let before = Arc::strong_count(&value);
+    assert!(before > 100);
+    drop(map);
+    assert_eq!(Arc::strong_count(&value), before - 100);
+}
+
+/// A map needs somewhere to put its entries.
+#[test]
+#[should_panic(expected = "a map needs at least one shard")]
+fn zero_shards_panics() {
+    ConcurrentHashMap::<u32, u32>::with_shards(0);
+}
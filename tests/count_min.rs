@@ -0,0 +1,98 @@
+//! The count-min sketch against exact counts of a skewed stream, plain and
+//! with conservative update, and heavy hitters against the true top items.
+
+use std::collections::HashMap;
+
+use datastructures::count_min::{CountMinSketch, HeavyHitters};
+
+mod common;
+use common::Rng;
+
+/// A stream of 200,000 items: a tenth spread over five heavy items, the
+/// rest skewed toward the low end of a thousand others.
+fn skewed_stream(rng: &mut Rng) -> Vec<u32> {
+    (0..200_000)
+        .map(|_| {
+            if rng.below(10) == 0 {
+                5000 + rng.below(5) as u32
+            } else {
+                10_000 + (rng.below(1000) * rng.below(1000) / 1000) as u32
+            }
+        })
+        .collect()
+}
+
+/// Estimates never fall short of the true count, conservative update is
+/// never looser than plain, and both stay within the `ε N` bound for
+/// nearly every item.
+#[test]
+fn estimates_bound_true_counts() {
+    let mut rng = Rng(7);
+    let stream = skewed_stream(&mut rng);
+    let (epsilon, delta) = (0.001, 0.01);
+    let mut plain = CountMinSketch::new(epsilon, delta);
+    let mut conservative = CountMinSketch::new(epsilon, delta).with_conservative_update();
+    assert_eq!((plain.width(), plain.depth()), (2719, 5));
+    assert!(conservative.is_conservative() && !plain.is_conservative());
+    let mut counts: HashMap<u32, u64> = HashMap::new();
+    for x in &stream {
+        *counts.entry(*x).or_default() += 1;
+        plain.insert(x);
+        conservative.insert(x);
+    }
+    assert_eq!(plain.total(), stream.len() as u64);
+
+    let bound = epsilon * stream.len() as f64;
+    let (mut plain_error, mut conservative_error, mut over_bound) = (0, 0, 0);
+    for (k, &count) in &counts {
+        let (a, b) = (plain.estimate(k), conservative.estimate(k));
+        assert!(count <= b && b <= a);
+        plain_error += a - count;
+        conservative_error += b - count;
+        over_bound += usize::from((a - count) as f64 > bound);
+    }
+    assert!(over_bound as f64 <= 2.0 * delta * counts.len() as f64);
+    assert!(conservative_error * 2 < plain_error);
+    assert_eq!(plain.estimate(&1u32), 0);
+}
+
+/// Merging adds counts, and only sketches of the same shape merge.
+#[test]
+fn merge_adds_counts() {
+    let mut sketch = CountMinSketch::with_size(100, 4, 3);
+    assert!(sketch.is_empty());
+    assert_eq!(sketch.add("a", 7), 7);
+    sketch.insert("b");
+    let mut doubled = sketch.clone();
+    doubled.merge(&sketch).unwrap();
+    assert_eq!(doubled.estimate("a"), 14);
+    assert_eq!(doubled.total(), 16);
+    assert!(doubled
+        .merge(&CountMinSketch::with_size(100, 4, 4))
+        .is_err());
+    assert!(!doubled.is_compatible(&CountMinSketch::with_size(99, 4, 3)));
+    doubled.clear();
+    assert!(doubled.is_empty());
+    assert_eq!(doubled.estimate("a"), 0);
+}
+
+/// The five heavy items of the stream, each a fiftieth of it, are tracked
+/// and reported above 1.5%, ahead of everything else.
+#[test]
+fn heavy_hitters_find_the_top_items() {
+    let mut rng = Rng(7);
+    let stream = skewed_stream(&mut rng);
+    let mut hitters = HeavyHitters::new(10, 0.001, 0.01);
+    assert_eq!(hitters.capacity(), 10);
+    for &x in &stream {
+        hitters.insert(x);
+    }
+    let top = hitters.top();
+    assert_eq!(top.len(), 10);
+    assert!(top.windows(2).all(|w| w[0].1 >= w[1].1));
+    let mut heavy: Vec<u32> = top[..5].iter().map(|x| *x.0).collect();
+    heavy.sort();
+    assert_eq!(heavy, [5000, 5001, 5002, 5003, 5004]);
+    assert_eq!(hitters.above(0.015).len(), 5);
+    assert_eq!(hitters.estimate(&5000), hitters.sketch().estimate(&5000));
+}
@@ -0,0 +1,52 @@
+//! The cover tree against a plain map of live points, under random inserts,
+//! removals and queries.
+
+use std::collections::HashMap;
+
+use datastructures::covertree::{CoverId, CoverTree};
+use datastructures::metric::{Euclidean, Metric};
+
+mod common;
+use common::{sorted, Rng};
+
+/// k-nearest and radius queries agree with a scan, with points on a
+/// coarse lattice so that duplicates come up, and the occasional far
+/// outlier that forces a new top level.
+#[test]
+fn random_operations_match_brute_force() {
+    let mut rng = Rng(13);
+    let mut tree = CoverTree::new(Euclidean);
+    let mut live: HashMap<CoverId, Vec<f64>> = HashMap::new();
+    for step in 0..3000 {
+        let op = rng.below(10);
+        if op < 5 || live.is_empty() {
+            let scale = if rng.below(50) == 0 { 1000.0 } else { 1.0 };
+            let p: Vec<f64> = (0..4)
+                .map(|_| (rng.range(-scale, scale) * 4.0).round() / 4.0)
+                .collect();
+            live.insert(tree.insert(p.clone(), step), p);
+        } else if op < 7 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+        } else {
+            let q: Vec<f64> = (0..4).map(|_| rng.range(-1.5, 1.5)).collect();
+            let k = rng.index(8);
+            let got: Vec<f64> = tree.nearest(&q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = live.values().map(|p| Euclidean.distance(&q, p)).collect();
+            want.sort_by(f64::total_cmp);
+            let r = want.get(live.len() / 5).copied().unwrap_or(1.0);
+            want.truncate(k);
+            assert_eq!(got, want);
+
+            let got: Vec<_> = tree.within_radius(&q, r).iter().map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|x| Euclidean.distance(&q, x.1) <= r)
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+        }
+        assert_eq!(tree.len(), live.len());
+        assert_eq!(tree.iter().count(), live.len());
+    }
+}
@@ -0,0 +1,86 @@
+//! The compressed sparse row graph against adjacency lists, for random
+//! multigraphs with parallel edges and self-loops, and their transposes
+//! and undirected forms.
+
+use datastructures::csr_graph::CsrGraph;
+
+mod common;
+use common::{sorted, Rng};
+
+/// Rows, degrees, edge lookups and edge ids match adjacency lists built
+/// from the same edges, with parallel edges in input order.
+#[test]
+fn rows_match_adjacency_lists() {
+    let mut rng = Rng(3);
+    for _ in 0..50 {
+        let n = 1 + rng.index(40);
+        let m = rng.index(200);
+        let edges: Vec<(usize, usize, u32)> = (0..m as u32)
+            .map(|e| (rng.index(n), rng.index(n), e))
+            .collect();
+        let g = CsrGraph::build((0..n).collect(), edges.clone());
+        assert_eq!((g.node_count(), g.edge_count()), (n, m));
+        let mut adjacency = vec![Vec::new(); n];
+        for &(u, v, e) in &edges {
+            adjacency[u].push((v, e));
+        }
+        for (u, row) in adjacency.iter_mut().enumerate() {
+            row.sort_by_key(|&(v, _)| v);
+            assert_eq!(*g.node(u), u);
+            assert!(g.out_edges(u).map(|(v, &e)| (v, e)).eq(row.iter().copied()));
+            assert!(g.neighbors(u).eq(row.iter().map(|x| x.0)));
+            assert_eq!(g.degree(u), row.len());
+            for e in g.edge_range(u) {
+                assert_eq!(g.source(e), u);
+                assert_eq!((g.target(e), *g.edge(e)), row[e - g.edge_range(u).start]);
+            }
+            for v in 0..n {
+                let first = row.iter().find(|x| x.0 == v).map(|x| x.1);
+                assert_eq!(g.find_edge(u, v).map(|e| *g.edge(e)), first);
+            }
+        }
+        let listed: Vec<(usize, usize, u32)> = g.iter_edges().map(|(u, v, &e)| (u, v, e)).collect();
+        assert_eq!(sorted(listed), sorted(edges.clone()));
+
+        let t = g.transpose();
+        assert_eq!(t.edge_count(), m);
+        for (u, v, e) in g.iter_edges() {
+            assert!(t.out_edges(v).any(|(w, f)| w == u && f == e));
+        }
+        assert_eq!(t.transpose(), g);
+
+        let undirected = CsrGraph::build_undirected(vec![(); n], edges.clone());
+        let loops = edges.iter().filter(|e| e.0 == e.1).count();
+        assert_eq!(undirected.edge_count(), 2 * m - loops);
+        for &(u, v, e) in &edges {
+            assert!(undirected.out_edges(u).any(|x| x == (v, &e)));
+            assert!(undirected.out_edges(v).any(|x| x == (u, &e)));
+        }
+    }
+}
+
+/// Payloads can be edited in place, and graphs without payloads build
+/// from bare pairs.
+#[test]
+fn payloads_and_bare_edges() {
+    let mut g = CsrGraph::build(vec!["a", "b", "c"], vec![(2, 0, 1.5), (0, 1, 2.5)]);
+    *g.node_mut(1) = "z";
+    *g.edge_mut(g.find_edge(2, 0).unwrap()) *= 2.0;
+    assert_eq!(g.nodes(), ["a", "z", "c"]);
+    assert_eq!(g.out_edges(2).collect::<Vec<_>>(), [(0, &3.0)]);
+    assert_eq!(g.find_edge(1, 0), None);
+
+    let bare = CsrGraph::from_edges(3, [(0, 1), (1, 2)]);
+    assert_eq!(bare.neighbors(0).collect::<Vec<_>>(), [1]);
+    assert_eq!(bare.degree(2), 0);
+    let empty = CsrGraph::<(), ()>::default();
+    assert!(empty.is_empty());
+    assert_eq!(empty.edge_count(), 0);
+}
+
+/// Every edge endpoint must name a node.
+#[test]
+#[should_panic(expected = "edge endpoint out of range")]
+fn edge_out_of_range_panics() {
+    CsrGraph::from_edges(2, [(0, 2)]);
+}
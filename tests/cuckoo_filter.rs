@@ -0,0 +1,143 @@
+//! The cuckoo filter's false positive rate and fingerprint sizing, its
+//! behavior when full, and its serialized form at every fingerprint width.
+
+use datastructures::cuckoo_filter::{CuckooFilter, Full};
+use datastructures::probabilistic::DecodeError;
+
+/// The share of `n` items never inserted that the filter reports present.
+fn measured_rate(filter: &CuckooFilter, n: u64) -> f64 {
+    let false_positives = (n..2 * n).filter(|i| filter.contains(i)).count();
+    false_positives as f64 / n as f64
+}
+
+/// Filled to the expected count, filters for rates from 5% to one in a
+/// million find every item and come in under their target, but by less
+/// than the factor of two one bit fewer would give.
+#[test]
+fn false_positive_rate_near_target() {
+    let n = 100_000u64;
+    for target in [0.05, 0.01, 1e-3, 1e-4, 1e-6] {
+        let mut filter = CuckooFilter::new(n as usize, target);
+        for i in 0..n {
+            filter.insert(&i).unwrap();
+        }
+        assert_eq!(filter.len(), n as usize);
+        assert!((0..n).all(|i| filter.contains(&i)));
+        let predicted = 8.0 * filter.load_factor() / 2f64.powi(filter.fingerprint_bits() as i32);
+        assert!(predicted <= target && 2.0 * predicted > target, "{target}");
+        if target >= 1e-4 {
+            let rate = measured_rate(&filter, n);
+            assert!(
+                rate < 1.3 * target && rate > 0.3 * target,
+                "{target}: {rate}"
+            );
+        }
+    }
+}
+
+/// Rates too low even for 32-bit fingerprints get 32 bits rather than a
+/// panic.
+#[test]
+fn very_low_rates_use_the_widest_fingerprints() {
+    assert_eq!(CuckooFilter::new(1000, 1e-7).fingerprint_bits(), 26);
+    let mut filter = CuckooFilter::new(1000, 1e-12);
+    assert_eq!(filter.fingerprint_bits(), 32);
+    for i in 0..1000u64 {
+        filter.insert(&i).unwrap();
+    }
+    assert!((0..1000u64).all(|i| filter.contains(&i)));
+    assert!((1000..100_000u64).all(|i| !filter.contains(&i)));
+}
+
+/// Removing half the items keeps the other half.
+#[test]
+fn removal_keeps_the_rest() {
+    let n = 20_000u64;
+    let mut filter = CuckooFilter::new(n as usize, 0.001);
+    for i in 0..n {
+        filter.insert(&i).unwrap();
+    }
+    filter.insert(&0u64).unwrap();
+    for i in 0..n / 2 {
+        assert!(filter.remove(&i));
+    }
+    assert!(filter.contains(&0u64));
+    assert!(filter.remove(&0u64));
+    assert!((n / 2..n).all(|i| filter.contains(&i)));
+    assert_eq!(filter.len(), (n / 2) as usize);
+    filter.clear();
+    assert!(filter.is_empty());
+    assert!(!filter.contains(&n));
+}
+
+/// Filling a small filter until an insertion fails keeps every item,
+/// including the one set aside, and removals make room again.
+#[test]
+fn full_filter_keeps_every_item() {
+    let mut filter = CuckooFilter::with_size(16, 12, 3);
+    let mut inserted = Vec::new();
+    let mut i = 0u64;
+    while filter.insert(&i).is_ok() {
+        inserted.push(i);
+        i += 1;
+    }
+    assert!(inserted.len() > filter.capacity() * 9 / 10);
+    assert_eq!(filter.insert(&i), Err(Full));
+    assert!(inserted.iter().all(|x| filter.contains(x)));
+    let copy = CuckooFilter::from_bytes(&filter.to_bytes()).unwrap();
+    assert!(inserted.iter().all(|x| copy.contains(x)));
+
+    let mut removed = 0;
+    while filter.insert(&1_000_000u64).is_err() {
+        assert!(filter.remove(&inserted[removed]));
+        removed += 1;
+    }
+    for x in &inserted[removed..] {
+        assert!(filter.remove(x));
+    }
+    assert!(filter.remove(&1_000_000u64));
+    assert!(filter.is_empty());
+}
+
+/// Filters of every fingerprint width read back to the same bytes and
+/// answers, and damaged bytes are rejected.
+#[test]
+fn serialization_round_trips() {
+    for bits in 1..=32 {
+        let mut filter = CuckooFilter::with_size(10, bits, 9);
+        for i in 0..30u64 {
+            filter.insert(&i).unwrap();
+        }
+        let bytes = filter.to_bytes();
+        let copy = CuckooFilter::from_bytes(&bytes).unwrap();
+        assert_eq!(copy.to_bytes(), bytes);
+        assert!((0..100u64).all(|i| copy.contains(&i) == filter.contains(&i)));
+        let short = &bytes[..bytes.len() - 1];
+        assert_eq!(
+            CuckooFilter::from_bytes(short).err(),
+            Some(DecodeError::Truncated)
+        );
+    }
+    // The fingerprint width sits after the four-byte tag.
+    let mut bytes = CuckooFilter::with_size(4, 8, 0).to_bytes();
+    bytes[4..8].copy_from_slice(&33u32.to_le_bytes());
+    assert_eq!(
+        CuckooFilter::from_bytes(&bytes).err(),
+        Some(DecodeError::Invalid)
+    );
+
+    // Bits past the last slot must be clear.
+    let mut bytes = CuckooFilter::with_size(1, 3, 0).to_bytes();
+    *bytes.last_mut().unwrap() = 0x80;
+    assert_eq!(
+        CuckooFilter::from_bytes(&bytes).err(),
+        Some(DecodeError::Invalid)
+    );
+}
+
+/// Fingerprints wider than 32 bits are refused.
+#[test]
+#[should_panic(expected = "fingerprints must have 1 to 32 bits")]
+fn wide_fingerprints_panic() {
+    CuckooFilter::with_size(16, 33, 0);
+}
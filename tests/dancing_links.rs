@@ -0,0 +1,113 @@
+//! The exact cover solver against brute force over every subset of rows,
+//! with and without secondary columns, and the n-queens counts.
+
+use datastructures::dancing_links::ExactCover;
+
+mod common;
+use common::{sorted, Rng};
+
+/// N queens: ranks and files are primary, both diagonal directions are
+/// secondary, since not every diagonal holds a queen.
+fn queens(n: usize) -> usize {
+    let diagonals = 2 * n - 1;
+    let mut problem = ExactCover::with_secondary(2 * n, 2 * diagonals);
+    for r in 0..n {
+        for c in 0..n {
+            let anti = 2 * n + diagonals + (r + n - 1 - c);
+            problem.add_row(&[r, n + c, 2 * n + r + c, anti]);
+        }
+    }
+    problem.count_solutions()
+}
+
+/// Every set of rows covering each primary column exactly once and each
+/// secondary column at most once, where each row holds a primary column.
+fn brute_force(rows: &[Vec<usize>], columns: usize, primary: usize) -> Vec<Vec<usize>> {
+    let mut out = Vec::new();
+    for mask in 0u32..1 << rows.len() {
+        let chosen: Vec<usize> = (0..rows.len()).filter(|&i| mask >> i & 1 == 1).collect();
+        let mut count = vec![0; columns];
+        for &i in &chosen {
+            for &c in &rows[i] {
+                count[c] += 1;
+            }
+        }
+        let exact = count[..primary].iter().all(|&k| k == 1);
+        let useful = chosen.iter().all(|&i| rows[i].iter().any(|&c| c < primary));
+        if exact && useful && count[primary..].iter().all(|&k| k <= 1) {
+            out.push(chosen);
+        }
+    }
+    out
+}
+
+/// The queens counts for boards up to 8, and Knuth's example matrix with
+/// its single solution.
+#[test]
+fn known_problems() {
+    let counts: Vec<usize> = (1..=8).map(queens).collect();
+    assert_eq!(counts, [1, 0, 0, 2, 10, 4, 40, 92]);
+    let mut problem = ExactCover::new(7);
+    for row in [
+        &[2, 4, 5][..],
+        &[0, 3, 6],
+        &[1, 2, 5],
+        &[0, 3],
+        &[1, 6],
+        &[3, 4, 6],
+    ] {
+        problem.add_row(row);
+    }
+    assert_eq!(problem.row_count(), 6);
+    assert_eq!(problem.solve(), Some(vec![0, 3, 4]));
+    assert_eq!(problem.solutions().collect::<Vec<_>>(), [vec![0, 3, 4]]);
+    assert_eq!(ExactCover::new(0).count_solutions(), 1);
+}
+
+/// Random small matrices give the brute-force solutions, and abandoning
+/// an enumeration part way leaves the matrix intact for the next one.
+#[test]
+fn solutions_match_brute_force() {
+    let mut rng = Rng(12345);
+    for round in 0..400 {
+        let primary = 1 + rng.index(6);
+        let secondary = if round % 2 == 0 { 0 } else { rng.index(3) };
+        let columns = primary + secondary;
+        let n = rng.index(14);
+        let rows: Vec<Vec<usize>> = (0..n)
+            .map(|_| {
+                let mask = 1 + rng.below((1 << columns) - 1);
+                (0..columns).filter(|&c| mask >> c & 1 == 1).collect()
+            })
+            .collect();
+        let mut problem = ExactCover::with_secondary(primary, secondary);
+        assert_eq!(problem.column_count(), columns);
+        assert_eq!(problem.primary_count(), primary);
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(problem.add_row(row), i);
+        }
+        let want = sorted(brute_force(&rows, columns, primary));
+        assert_eq!(sorted(problem.solutions().collect()), want);
+        problem.solutions().next();
+        assert_eq!(sorted(problem.solutions().collect()), want);
+        assert_eq!(problem.count_solutions(), want.len());
+        assert_eq!(problem.solve().is_some(), !want.is_empty());
+        for (i, row) in rows.iter().enumerate() {
+            assert!(problem.row(i).eq(row.iter().copied()));
+        }
+    }
+}
+
+/// A row may not name a column twice.
+#[test]
+#[should_panic(expected = "column repeated in a row")]
+fn repeated_column_panics() {
+    ExactCover::new(3).add_row(&[0, 2, 0]);
+}
+
+/// A row names columns of the problem.
+#[test]
+#[should_panic(expected = "column out of range")]
+fn column_out_of_range_panics() {
+    ExactCover::new(3).add_row(&[3]);
+}
@@ -0,0 +1,237 @@
+//! Differential tests: the octree, the BVH and the BIH against the brute-force
+//! reference implementations in `naive`, on random inputs.
+
+use std::collections::HashMap;
+
+use datastructures::bih::Bih;
+use datastructures::bvh::Bvh;
+use datastructures::geom::{Aabb, Ray};
+use datastructures::naive::{self, NaiveIndex};
+use datastructures::octree::{Octree, PointId};
+use datastructures::spatial_index::SpatialIndex;
+
+/// A xorshift generator, so failures reproduce from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// A uniform float in `lo..hi`.
+    fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A point in the cube `0..size`, snapped to a coarse lattice on some
+    /// draws so that duplicates and boundary cases come up.
+    fn point(&mut self, size: f64) -> [f64; 3] {
+        let snap = self.below(4) == 0;
+        std::array::from_fn(|_| {
+            let x = self.range(0.0, size);
+            if snap {
+                (x / 8.0).floor() * 8.0
+            } else {
+                x
+            }
+        })
+    }
+}
+
+const SIZE: f64 = 64.0;
+
+fn sorted<T: Ord>(mut v: Vec<T>) -> Vec<T> {
+    v.sort();
+    v
+}
+
+#[test]
+fn octree_matches_naive() {
+    for seed in 1..=8u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let bounds = Aabb::new([0.0; 3], [SIZE; 3]);
+        let mut tree: Octree<[f64; 3], u32> = Octree::with_limits(bounds, 1 + rng.below(8), 12);
+        let mut naive: NaiveIndex<[f64; 3], u32, 3> = NaiveIndex::new();
+        // Octree handle to naive handle, and back.
+        let mut to_naive: HashMap<PointId, usize> = HashMap::new();
+        let mut to_tree: HashMap<usize, PointId> = HashMap::new();
+        for step in 0..3000u32 {
+            match rng.below(10) {
+                0..=5 => {
+                    let p = rng.point(SIZE);
+                    let t = tree.insert(p, step).expect("inside bounds");
+                    let n = naive.insert(p, step).unwrap();
+                    to_naive.insert(t, n);
+                    to_tree.insert(n, t);
+                }
+                6 | 7 if !to_naive.is_empty() => {
+                    let t = *to_naive.keys().nth(rng.below(to_naive.len())).unwrap();
+                    let n = to_naive.remove(&t).unwrap();
+                    to_tree.remove(&n);
+                    assert_eq!(tree.remove(t), naive.remove(n));
+                }
+                8 if !to_naive.is_empty() => {
+                    let t = *to_naive.keys().nth(rng.below(to_naive.len())).unwrap();
+                    let p = rng.point(SIZE);
+                    let old = tree.relocate(t, p).expect("inside bounds");
+                    assert_eq!(old, naive.relocate(to_naive[&t], p));
+                }
+                _ => {
+                    let outside = [SIZE + 1.0, 0.0, 0.0];
+                    assert!(tree.insert(outside, step).is_err());
+                }
+            }
+            assert_eq!(tree.len(), naive.len());
+            let q = rng.point(SIZE * 1.25);
+
+            let k = rng.below(12);
+            let got = tree.nearest(q, k);
+            let want = SpatialIndex::nearest(&naive, &q, k);
+            assert_eq!(got.len(), want.len(), "seed {seed} step {step}");
+            for (g, w) in got.iter().zip(&want) {
+                assert_eq!(g.1, w.1, "seed {seed} step {step}: k-NN distances");
+                let (p, _) = naive.get(to_naive[&g.0]).unwrap();
+                assert_eq!(tree.get(g.0).map(|(tp, _)| *tp), Some(p));
+            }
+
+            let r = rng.range(0.0, SIZE / 4.0);
+            let got: Vec<usize> = tree
+                .within_radius(q, r)
+                .map(|(id, _, _)| to_naive[&id])
+                .collect();
+            assert_eq!(
+                sorted(got),
+                sorted(naive.within_radius(&q, r)),
+                "seed {seed} step {step}: radius"
+            );
+
+            let other = rng.point(SIZE * 1.25);
+            let min = std::array::from_fn(|a| q[a].min(other[a]));
+            let max = std::array::from_fn(|a| q[a].max(other[a]));
+            let got: Vec<usize> = tree
+                .within_box(Aabb::new(min, max))
+                .map(|(id, _, _)| to_naive[&id])
+                .collect();
+            assert_eq!(
+                sorted(got),
+                sorted(naive.query_aabb(&min, &max)),
+                "seed {seed} step {step}: box"
+            );
+        }
+        for (&n, &t) in &to_tree {
+            assert_eq!(tree.get(t).map(|(p, d)| (*p, d)), naive.get(n));
+        }
+    }
+}
+
+#[test]
+fn bvh_matches_naive() {
+    let mut rng = Rng(0x5eed);
+    for round in 0..40 {
+        let boxes: Vec<Aabb<3>> = (0..rng.below(200))
+            .map(|_| {
+                let lo = rng.point(SIZE);
+                let extent = rng.range(0.0, 6.0);
+                Aabb::new(lo, lo.map(|c| c + extent))
+            })
+            .collect();
+        let points: Vec<[f64; 3]> = boxes.iter().map(|b| b.center()).collect();
+        let bvh = Bvh::build(
+            boxes
+                .iter()
+                .cloned()
+                .zip(0..)
+                .collect::<Vec<(Aabb<3>, usize)>>(),
+        );
+        for _ in 0..50 {
+            let origin = rng.point(SIZE);
+            let direction: [f64; 3] = std::array::from_fn(|_| rng.range(-1.0, 1.0));
+            let ray = Ray::new(origin, direction);
+            let max_t = rng.range(0.0, 2.0 * SIZE);
+            let got = bvh
+                .closest_hit(&ray, max_t, |&i, r| r.hit_aabb(&boxes[i], max_t))
+                .map(|(_, _, t)| t);
+            let want = naive::raycast(&boxes, &ray, max_t).map(|(_, t)| t);
+            assert_eq!(got, want, "round {round}: raycast");
+
+            let lo = rng.point(SIZE);
+            let query = Aabb::new(lo, lo.map(|c| c + rng.range(0.0, SIZE / 2.0)));
+            let got: Vec<usize> = bvh.intersecting(query).map(|(_, _, &i)| i).collect();
+            assert_eq!(
+                sorted(got),
+                naive::intersecting(&boxes, &query),
+                "round {round}: overlap"
+            );
+
+            let k = rng.below(8);
+            let want = naive::nearest(&points, &origin, k);
+            assert_eq!(want.len(), k.min(points.len()));
+            assert!(want.windows(2).all(|w| w[0].1 <= w[1].1));
+            let inside = naive::within_box(&points, &query);
+            assert!(inside.iter().all(|&i| query.contains_point(&points[i])));
+        }
+    }
+}
+
+#[test]
+fn bih_matches_naive() {
+    let mut rng = Rng(0xb1b);
+    for round in 0..40 {
+        let mut boxes: Vec<Aabb<3>> = (0..rng.below(200))
+            .map(|_| {
+                let lo = rng.point(SIZE);
+                let extent = rng.range(0.0, 6.0);
+                Aabb::new(lo, lo.map(|c| c + extent))
+            })
+            .collect();
+        let mut bih = Bih::build(
+            boxes
+                .iter()
+                .cloned()
+                .zip(0..)
+                .collect::<Vec<(Aabb<3>, usize)>>(),
+        );
+        // Every other round, shift the boxes and refit instead of rebuilding.
+        if round % 2 == 1 {
+            for b in &mut boxes {
+                let shift = rng.range(-4.0, 4.0);
+                *b = Aabb::new(b.min.map(|c| c + shift), b.max.map(|c| c + shift));
+            }
+            bih.refit(|&i| boxes[i]);
+        }
+        for _ in 0..50 {
+            let origin = rng.point(SIZE);
+            // Some rays run along an axis, parallel to the split planes.
+            let mut direction: [f64; 3] = std::array::from_fn(|_| rng.range(-1.0, 1.0));
+            if rng.below(4) == 0 {
+                direction = [0.0; 3];
+                direction[rng.below(3)] = if rng.below(2) == 0 { 1.0 } else { -1.0 };
+            }
+            let ray = Ray::new(origin, direction);
+            let max_t = rng.range(0.0, 2.0 * SIZE);
+            let got = bih
+                .closest_hit(&ray, max_t, |&i, r| r.hit_aabb(&boxes[i], max_t))
+                .map(|(_, _, t)| t);
+            let want = naive::raycast(&boxes, &ray, max_t).map(|(_, t)| t);
+            assert_eq!(got, want, "round {round}: raycast");
+            let any = bih.any_hit(&ray, max_t, |&i, r| r.hit_aabb(&boxes[i], max_t));
+            assert_eq!(any.is_some(), want.is_some(), "round {round}: any hit");
+
+            let lo = rng.point(SIZE);
+            let query = Aabb::new(lo, lo.map(|c| c + rng.range(0.0, SIZE / 2.0)));
+            let got: Vec<usize> = bih.intersecting(query).map(|(_, _, &i)| i).collect();
+            assert_eq!(
+                sorted(got),
+                naive::intersecting(&boxes, &query),
+                "round {round}: overlap"
+            );
+        }
+    }
+}
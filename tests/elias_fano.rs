@@ -0,0 +1,114 @@
+//! Elias–Fano sequences against the sorted values they encode, for dense,
+//! sparse and full-width universes, and Z-order cell offsets against
+//! `ZOrderIndex::cell`.
+
+use datastructures::elias_fano::EliasFano;
+use datastructures::zorder::ZOrderIndex;
+
+mod common;
+use common::{sorted, Rng};
+
+/// Iteration, access and successor queries match the sorted values, and
+/// the space stays within a few bits of `log₂(u / n)` per value.
+#[test]
+fn queries_match_sorted_values() {
+    let mut rng = Rng(4);
+    let cases = [
+        (0usize, 1u64),
+        (1, 1),
+        (1, u64::MAX),
+        (10, 10),
+        (1000, 100),
+        (1000, 1_000_000),
+        (5000, 1 << 40),
+        (3000, u64::MAX),
+    ];
+    for (n, u) in cases {
+        let value = |rng: &mut Rng| {
+            if u == u64::MAX {
+                rng.next_u64()
+            } else {
+                rng.below(u)
+            }
+        };
+        let values = sorted((0..n).map(|_| value(&mut rng)).collect());
+        let ef = EliasFano::new(&values);
+        assert_eq!(ef.len(), n);
+        assert_eq!(ef.is_empty(), n == 0);
+        assert!(ef.iter().eq(values.iter().copied()));
+        for (i, &v) in values.iter().enumerate() {
+            assert_eq!(ef.get(i), v);
+        }
+        for _ in 0..500 {
+            let x = if n > 0 && rng.below(2) == 0 {
+                values[rng.index(n)].wrapping_add(rng.below(3))
+            } else if u == u64::MAX {
+                rng.next_u64()
+            } else {
+                rng.below(u + 5)
+            };
+            let i = values.partition_point(|&y| y < x);
+            assert_eq!(ef.next_geq(x), values.get(i).map(|&y| (i, y)), "x {x}");
+        }
+        if n > 0 {
+            let max = *values.last().unwrap() as f64;
+            let bound = 3.0 + (max / n as f64).log2().max(0.0) + 128.0 / n as f64;
+            assert!(ef.bits_per_value() <= bound, "n {n} u {u}");
+        }
+    }
+}
+
+/// Runs of equal values, including a sequence of one value repeated.
+#[test]
+fn repeated_values() {
+    let values: Vec<u64> = [7u64; 100]
+        .into_iter()
+        .chain([9; 3])
+        .chain([1 << 50; 10])
+        .collect();
+    let ef = EliasFano::new(&values);
+    assert!(ef.iter().eq(values.iter().copied()));
+    assert_eq!(ef.next_geq(0), Some((0, 7)));
+    assert_eq!(ef.next_geq(8), Some((100, 9)));
+    assert_eq!(ef.next_geq(10), Some((103, 1 << 50)));
+    assert_eq!(ef.next_geq((1 << 50) + 1), None);
+    let zeros = EliasFano::new(&[0; 64]);
+    assert_eq!(zeros.get(63), 0);
+    assert_eq!(zeros.next_geq(0), Some((0, 0)));
+    assert_eq!(zeros.next_geq(1), None);
+}
+
+/// The offsets of the occupied cells at each depth partition the index
+/// into exactly the ranges `cell` reports.
+#[test]
+fn cell_starts_match_cells() {
+    let mut rng = Rng(5);
+    let items: Vec<([u32; 3], usize)> = (0..2000)
+        .map(|i| (std::array::from_fn(|_| rng.below(1 << 20) as u32), i))
+        .collect();
+    let z = ZOrderIndex::build(items);
+    for depth in [0, 1, 3, 6, 32] {
+        let starts: Vec<u64> = z.cell_starts(depth).iter().collect();
+        assert_eq!(starts.first(), Some(&0));
+        assert_eq!(starts.last(), Some(&2000));
+        for w in starts.windows(2) {
+            let (key, _) = z.get(w[0] as usize).unwrap();
+            assert_eq!(z.cell(key, depth), w[0] as usize..w[1] as usize);
+        }
+    }
+    assert_eq!(z.cell_starts(0).len(), 2);
+}
+
+/// Encoding requires sorted input.
+#[test]
+#[should_panic(expected = "Elias–Fano values must be non-decreasing")]
+fn unsorted_values_panic() {
+    EliasFano::new(&[1, 3, 2]);
+}
+
+/// Access past the end panics.
+#[test]
+#[should_panic(expected = "index 3 out of bounds for length 3")]
+fn get_out_of_bounds_panics() {
+    EliasFano::new(&[1, 2, 3]).get(3);
+}
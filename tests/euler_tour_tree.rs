@@ -0,0 +1,141 @@
+//! The Euler tour tree against adjacency sets searched depth first, under
+//! random links, cuts and value changes, with component and subtree
+//! aggregates compared against sums over the vertices reached.
+
+use std::collections::BTreeSet;
+
+use datastructures::algebra::{Monoid, Sum};
+use datastructures::euler_tour_tree::EulerTourTree;
+
+mod common;
+use common::Rng;
+
+/// The vertices reachable from `start`, not leaving it by the edge to
+/// `skip`: the subtree of `start` below `skip` when they are adjacent.
+fn reach(adjacency: &[BTreeSet<usize>], start: usize, skip: Option<usize>) -> BTreeSet<usize> {
+    let mut seen = BTreeSet::from([start]);
+    let mut stack = vec![start];
+    while let Some(x) = stack.pop() {
+        for &y in &adjacency[x] {
+            if !(x == start && Some(y) == skip) && seen.insert(y) {
+                stack.push(y);
+            }
+        }
+    }
+    seen
+}
+
+/// Random operations on forests of several sizes, with the component of
+/// one endpoint and the subtree below one of its edges checked each step.
+#[test]
+fn operations_match_model() {
+    let mut rng = Rng(5);
+    for n in [1, 2, 6, 40, 300] {
+        let mut values: Vec<u64> = (0..n as u64).map(|i| i * 7 + 1).collect();
+        let mut tree = EulerTourTree::with_values(Sum::<u64>::new(), values.clone());
+        assert_eq!(tree.len(), n);
+        let mut adjacency = vec![BTreeSet::new(); n];
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for step in 0..3000 {
+            let (u, v) = (rng.index(n), rng.index(n));
+            match rng.below(7) {
+                0..=2 => {
+                    let apart = !reach(&adjacency, u, None).contains(&v);
+                    assert_eq!(tree.link(u, v), apart);
+                    if apart {
+                        adjacency[u].insert(v);
+                        adjacency[v].insert(u);
+                        edges.push((u, v));
+                    }
+                }
+                3 if !edges.is_empty() => {
+                    let (a, b) = edges.swap_remove(rng.index(edges.len()));
+                    let (a, b) = if rng.below(2) == 0 { (a, b) } else { (b, a) };
+                    assert!(tree.cut(a, b));
+                    assert!(!tree.cut(a, b));
+                    adjacency[a].remove(&b);
+                    adjacency[b].remove(&a);
+                }
+                4 => {
+                    assert_eq!(tree.cut(u, v), adjacency[u].contains(&v));
+                    if adjacency[u].remove(&v) {
+                        adjacency[v].remove(&u);
+                        edges.retain(|&e| e != (u, v) && e != (v, u));
+                    }
+                }
+                5 => {
+                    values[u] = rng.below(1000);
+                    tree.set(u, values[u]);
+                }
+                _ => {}
+            }
+            assert_eq!(tree.edge_count(), edges.len());
+            assert_eq!(tree.has_edge(u, v), adjacency[u].contains(&v));
+            assert_eq!(*tree.get(u), values[u]);
+            let component = reach(&adjacency, u, None);
+            assert_eq!(tree.connected(u, v), component.contains(&v));
+            assert_eq!(tree.component_len(u), component.len());
+            let total: u64 = component.iter().map(|&x| values[x]).sum();
+            assert_eq!(tree.component(u), total);
+            if step % 7 == 0 {
+                let listed: Vec<usize> = tree.component_vertices(u).collect();
+                assert_eq!(listed.len(), component.len());
+                assert_eq!(listed.into_iter().collect::<BTreeSet<_>>(), component);
+            }
+            let k = rng.index(adjacency[u].len() + 1);
+            match adjacency[u].iter().nth(k).copied() {
+                Some(parent) => {
+                    let below = reach(&adjacency, u, Some(parent));
+                    assert_eq!(tree.subtree_len(u, parent), Some(below.len()));
+                    let sum: u64 = below.iter().map(|&x| values[x]).sum();
+                    assert_eq!(tree.subtree(u, parent), Some(sum));
+                    assert_eq!(tree.component_len(u), component.len());
+                }
+                None if !adjacency[u].contains(&v) => {
+                    assert_eq!(tree.subtree(u, v), None);
+                    assert_eq!(tree.subtree_len(u, v), None);
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Vertices added later join the forest like the initial ones.
+#[test]
+fn add_vertex() {
+    let mut tree = EulerTourTree::new(Sum::<u64>::new(), 2);
+    assert_eq!(tree.component(0), 0);
+    let v = tree.add_vertex(5);
+    assert_eq!((v, tree.len()), (2, 3));
+    assert!(tree.link(0, v));
+    assert!(tree.link(1, v));
+    assert!(!tree.link(0, 1));
+    tree.set(1, 10);
+    assert_eq!(tree.component(0), 15);
+    assert_eq!(tree.subtree(v, 0), Some(15));
+    assert_eq!(tree.subtree_len(0, v), Some(1));
+    assert_eq!(tree.monoid().identity(), 0);
+    assert!(!tree.is_empty());
+}
+
+/// A long path linked one edge at a time, with subtrees below edges along
+/// it, and then every other edge cut.
+#[test]
+fn long_path() {
+    let n = 50_000;
+    let mut tree = EulerTourTree::with_values(Sum::<u64>::new(), vec![1; n]);
+    for i in 1..n {
+        assert!(tree.link(i - 1, i));
+    }
+    assert_eq!(tree.component_len(0), n);
+    for i in (1..n).step_by(1000) {
+        assert_eq!(tree.subtree_len(i, i - 1), Some(n - i));
+    }
+    for i in (1..n).step_by(2) {
+        assert!(tree.cut(i - 1, i));
+    }
+    assert_eq!(tree.component_len(10), 2);
+    assert_eq!(tree.component(0), 1);
+    assert_eq!(tree.component(n - 2), 2);
+}
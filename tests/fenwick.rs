@@ -0,0 +1,157 @@
+//! The Fenwick trees against a plain array of sums, under random point and
+//! range updates, with prefix searches checked against a scan, and the 2D
+//! tree against a grid.
+
+use std::ops::Range;
+
+use datastructures::algebra::Sum;
+use datastructures::fenwick::{Fenwick, Fenwick2d, RangeAddFenwick, RangeSumFenwick};
+
+mod common;
+use common::Rng;
+
+fn random_range(rng: &mut Rng, len: usize) -> Range<usize> {
+    let (a, b) = (rng.index(len + 1), rng.index(len + 1));
+    a.min(b)..a.max(b)
+}
+
+/// All three trees track the same array through point adds, sets and
+/// range adds, and agree with it on every query.
+#[test]
+fn updates_and_queries_match_array() {
+    let mut rng = Rng(11);
+    for n in 0..40 {
+        let mut values: Vec<i64> = (0..n).map(|_| rng.below(10) as i64).collect();
+        let mut point: Fenwick<Sum<i64>> = values.iter().copied().collect();
+        let mut range_add = RangeAddFenwick::new(Sum::new(), values.clone());
+        let mut range_sum = RangeSumFenwick::new(Sum::new(), values.clone());
+        assert_eq!(point.len(), n);
+        assert_eq!(range_add.is_empty(), n == 0);
+        for _ in 0..300 {
+            let range = random_range(&mut rng, n);
+            if n > 0 && rng.below(2) == 0 {
+                let i = rng.index(n);
+                let delta = rng.below(10) as i64;
+                values[i] += delta;
+                point.add(i, &delta);
+                range_add.add(i..=i, &delta);
+                range_sum.add(i..i + 1, &delta);
+            } else {
+                // Non-negative deltas keep the values usable as counts.
+                let delta = rng.below(7) as i64;
+                for (i, v) in values
+                    .iter_mut()
+                    .enumerate()
+                    .take(range.end)
+                    .skip(range.start)
+                {
+                    *v += delta;
+                    point.set(i, v);
+                }
+                range_add.add(range.clone(), &delta);
+                range_sum.add(range.clone(), &delta);
+            }
+
+            let want: i64 = values[range.clone()].iter().sum();
+            assert_eq!(point.query(range.clone()), want);
+            assert_eq!(range_sum.query(range.clone()), want);
+            assert_eq!(point.prefix(range.end), values[..range.end].iter().sum());
+            assert_eq!(range_sum.prefix(range.end), point.prefix(range.end));
+            if n > 0 {
+                let i = rng.index(n);
+                assert_eq!(point.get(i), values[i]);
+                assert_eq!(range_add.get(i), values[i]);
+                assert_eq!(range_sum.get(i), values[i]);
+            }
+            assert_eq!(point.to_vec(), values);
+            assert_eq!(range_add.to_vec(), values);
+            assert_eq!(range_sum.to_vec(), values);
+        }
+    }
+}
+
+/// `max_prefix` finds the longest prefix under a bound and `kth` the
+/// element holding each unit, like a scan of the running sums.
+#[test]
+fn prefix_searches_match_scan() {
+    let mut rng = Rng(12);
+    for n in 0..60 {
+        let values: Vec<i64> = (0..n).map(|_| rng.below(4) as i64).collect();
+        let tree: Fenwick<Sum<i64>> = values.iter().copied().collect();
+        let running: Vec<i64> = values
+            .iter()
+            .scan(0, |acc, v| {
+                *acc += v;
+                Some(*acc)
+            })
+            .collect();
+        let total = running.last().copied().unwrap_or(0);
+        for k in 0..=total + 1 {
+            let longest = running.iter().take_while(|&&s| s <= k).count();
+            assert_eq!(tree.max_prefix(|&s| s <= k), longest);
+            let holder = running.iter().position(|&s| s > k);
+            assert_eq!(tree.kth(&k), holder);
+        }
+    }
+    let empty = Fenwick::with_len(Sum::<i64>::new(), 0);
+    assert_eq!(empty.max_prefix(|_| true), 0);
+    assert_eq!(empty.kth(&0), None);
+}
+
+/// Point updates are bounds-checked.
+#[test]
+#[should_panic(expected = "index 3 out of bounds for length 3")]
+fn add_out_of_bounds_panics() {
+    Fenwick::with_len(Sum::<i64>::new(), 3).add(3, &1);
+}
+
+/// Random cell adds and sets on grids from empty to 13 × 5, with random
+/// rectangles summed like a scan of the grid.
+#[test]
+fn grid_updates_and_queries_match_array() {
+    let mut rng = Rng(5);
+    for (rows, cols) in [(0, 0), (0, 3), (1, 1), (3, 7), (8, 8), (13, 5)] {
+        let mut grid: Vec<i64> = (0..rows * cols).map(|_| rng.below(9) as i64 - 4).collect();
+        let mut tree = Fenwick2d::new(Sum::new(), rows, cols, grid.clone());
+        assert_eq!((tree.rows(), tree.cols()), (rows, cols));
+        let empty = Fenwick2d::with_size(Sum::<i64>::new(), rows, cols);
+        assert_eq!(empty.query(.., ..), 0);
+        for _ in 0..400 {
+            if rows > 0 && cols > 0 && rng.below(2) == 0 {
+                let (i, j) = (rng.index(rows), rng.index(cols));
+                let v = rng.below(20) as i64 - 10;
+                if rng.below(2) == 0 {
+                    grid[i * cols + j] += v;
+                    tree.add(i, j, &v);
+                } else {
+                    grid[i * cols + j] = v;
+                    tree.set(i, j, &v);
+                }
+            }
+            let (r, c) = (random_range(&mut rng, rows), random_range(&mut rng, cols));
+            let want: i64 = r
+                .clone()
+                .flat_map(|i| c.clone().map(move |j| (i, j)))
+                .map(|(i, j)| grid[i * cols + j])
+                .sum();
+            assert_eq!(tree.query(r.clone(), c.clone()), want);
+            let prefix: i64 = (0..r.end)
+                .flat_map(|i| (0..c.end).map(move |j| (i, j)))
+                .map(|(i, j)| grid[i * cols + j])
+                .sum();
+            assert_eq!(tree.prefix(r.end, c.end), prefix);
+        }
+        for i in 0..rows {
+            for j in 0..cols {
+                assert_eq!(tree.get(i, j), grid[i * cols + j]);
+            }
+        }
+    }
+}
+
+/// The grid must hold exactly `rows · cols` cells.
+#[test]
+#[should_panic(expected = "grid is not 2 × 3")]
+fn grid_of_the_wrong_size_panics() {
+    Fenwick2d::new(Sum::<i64>::new(), 2, 3, vec![0; 5]);
+}
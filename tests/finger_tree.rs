@@ -0,0 +1,266 @@
+//! The finger tree and the structures built on it against `Vec` models:
+//! the deque over a pool of shared versions, the priority queue, the
+//! ordered sequence, and a tree over a custom measure split by weight.
+
+use std::cmp::Ordering;
+
+use datastructures::algebra::Monoid;
+use datastructures::finger_tree::{Deque, FingerTree, Measure, OrderedSequence, PriorityQueue};
+
+mod common;
+use common::Rng;
+
+/// Random operations on versions drawn from a pool, including splits and
+/// appends of one version onto another.
+#[test]
+fn deque_versions_match_vec() {
+    let mut rng = Rng(7);
+    let mut pool: Vec<(Deque<u64>, Vec<u64>)> = vec![(Deque::new(), Vec::new())];
+    for step in 0..20000u64 {
+        let i = rng.index(pool.len());
+        let (mut d, mut want) = pool[i].clone();
+        match rng.below(9) {
+            0 | 1 => {
+                d.push_back(step);
+                want.push(step);
+            }
+            2 | 3 => {
+                d.push_front(step);
+                want.insert(0, step);
+            }
+            4 => {
+                let front = (!want.is_empty()).then(|| want.remove(0));
+                assert_eq!(d.pop_front(), front);
+            }
+            5 => assert_eq!(d.pop_back(), want.pop()),
+            6 => {
+                let at = rng.index(want.len() + 1);
+                let rest = d.split_off(at);
+                let rest_want = want.split_off(at);
+                assert!(rest.iter().eq(&rest_want));
+                if rng.below(2) == 0 {
+                    d.append(rest);
+                    want.extend(rest_want);
+                }
+            }
+            7 => {
+                let (other, other_want) = pool[rng.index(pool.len())].clone();
+                d.append(other);
+                want.extend(other_want);
+            }
+            _ => {
+                if !want.is_empty() {
+                    let k = rng.index(want.len());
+                    assert_eq!(d.get(k), Some(&want[k]));
+                }
+                assert_eq!(d.get(want.len()), None);
+            }
+        }
+        assert_eq!(d.len(), want.len());
+        assert_eq!(d.is_empty(), want.is_empty());
+        assert_eq!(d.front(), want.first());
+        assert_eq!(d.back(), want.last());
+        if step % 50 == 0 {
+            assert!(d.iter().eq(&want));
+            assert!(pool[i].0.iter().eq(&pool[i].1));
+        }
+        if want.len() > 3000 {
+            continue;
+        }
+        if pool.len() < 20 {
+            pool.push((d, want));
+        } else {
+            pool[rng.index(20)] = (d, want);
+        }
+    }
+    for (d, want) in &pool {
+        assert!(d.iter().eq(want));
+        assert_eq!(*d, want.iter().copied().collect::<Deque<_>>());
+    }
+}
+
+/// A job ordered by priority alone, so equal priorities show which of
+/// them the queue picks.
+#[derive(Clone, Debug)]
+struct Job {
+    priority: u64,
+    id: u64,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority)
+    }
+}
+
+/// The queue pops the greatest job, the earliest pushed among equals.
+#[test]
+fn priority_queue_matches_vec() {
+    let mut rng = Rng(9);
+    let mut queue = PriorityQueue::new();
+    let mut model: Vec<Job> = Vec::new();
+    for id in 0..20000u64 {
+        if rng.below(3) != 0 {
+            let job = Job {
+                priority: rng.below(50),
+                id,
+            };
+            queue.push(job.clone());
+            model.push(job);
+        } else {
+            let max = model.iter().map(|j| j.priority).max();
+            let first = model.iter().position(|j| Some(j.priority) == max);
+            let want = first.map(|i| model.remove(i).id);
+            assert_eq!(queue.peek().map(|j| j.id), want);
+            assert_eq!(queue.pop().map(|j| j.id), want);
+        }
+        assert_eq!(queue.len(), model.len());
+        assert_eq!(queue.is_empty(), model.is_empty());
+    }
+    assert!(queue.iter().map(|j| j.id).eq(model.iter().map(|j| j.id)));
+}
+
+/// Appending one queue to another melds them, without changing the
+/// original.
+#[test]
+fn priority_queue_append() {
+    let a: PriorityQueue<u32> = (0..100).collect();
+    let b: PriorityQueue<u32> = (50..150).collect();
+    let mut c = a.clone();
+    c.append(b);
+    let mut got = Vec::new();
+    while let Some(x) = c.pop() {
+        got.push(x);
+    }
+    let mut want: Vec<u32> = (0..100).chain(50..150).collect();
+    want.sort_by(|x, y| y.cmp(x));
+    assert_eq!(got, want);
+    assert_eq!(a.len(), 100);
+    assert_eq!(a.peek(), Some(&99));
+}
+
+/// Inserts with duplicates, removals, rank and split queries match a
+/// sorted `Vec`.
+#[test]
+fn ordered_sequence_matches_sorted_vec() {
+    let mut rng = Rng(11);
+    let mut seq = OrderedSequence::new();
+    let mut want: Vec<u64> = Vec::new();
+    for step in 0..20000 {
+        let x = rng.below(300);
+        match rng.below(7) {
+            0 | 1 => {
+                seq.insert(x);
+                want.insert(want.partition_point(|&y| y <= x), x);
+            }
+            2 => {
+                let found = want.binary_search(&x).ok();
+                if let Some(p) = found {
+                    want.remove(p);
+                }
+                assert_eq!(seq.remove(&x), found.is_some());
+            }
+            3 => {
+                assert_eq!(seq.contains(&x), want.contains(&x));
+                assert_eq!(seq.rank(&x), want.partition_point(|&y| y < x));
+            }
+            4 => {
+                let (left, right) = seq.split(&x);
+                let p = want.partition_point(|&y| y < x);
+                assert!(left.iter().eq(&want[..p]));
+                assert!(right.iter().eq(&want[p..]));
+            }
+            5 => {
+                if rng.below(2) == 0 {
+                    assert_eq!(seq.pop_first(), (!want.is_empty()).then(|| want.remove(0)));
+                } else {
+                    assert_eq!(seq.pop_last(), want.pop());
+                }
+            }
+            _ => {
+                if !want.is_empty() {
+                    let k = rng.index(want.len());
+                    assert_eq!(seq.get(k), Some(&want[k]));
+                }
+                assert_eq!(seq.get(want.len()), None);
+            }
+        }
+        assert_eq!(seq.len(), want.len());
+        assert_eq!(seq.first(), want.first());
+        assert_eq!(seq.last(), want.last());
+        if step % 100 == 0 {
+            assert!(seq.iter().eq(&want));
+        }
+    }
+    let collected: OrderedSequence<u64> = want.iter().rev().copied().collect();
+    assert!(collected.iter().eq(&want));
+}
+
+/// Measures each element by its value, so a prefix measures its total.
+#[derive(Clone, Default)]
+struct Total;
+
+impl Monoid for Total {
+    type Value = u64;
+
+    fn identity(&self) -> u64 {
+        0
+    }
+
+    fn combine(&self, a: &u64, b: &u64) -> u64 {
+        a + b
+    }
+}
+
+impl Measure<u64> for Total {
+    fn measure(&self, item: &u64) -> u64 {
+        *item
+    }
+}
+
+/// `find` and `split` with a custom measure locate the first element at
+/// which the running total passes a threshold.
+#[test]
+fn custom_measure_splits_by_total() {
+    let mut rng = Rng(12);
+    let items: Vec<u64> = (0..2000).map(|_| rng.below(10)).collect();
+    let tree: FingerTree<u64, Total> = items.iter().copied().collect();
+    let total: u64 = items.iter().sum();
+    assert_eq!(tree.measure(), total);
+    for _ in 0..300 {
+        let threshold = rng.below(total + 5);
+        let mut sum = 0;
+        let at = items.iter().position(|&x| {
+            sum += x;
+            sum > threshold
+        });
+        assert_eq!(tree.find(|&m| m > threshold), at.map(|i| &items[i]));
+        let (left, right) = tree.split(|&m| m > threshold);
+        let at = at.unwrap_or(items.len());
+        assert!(left.iter().eq(&items[..at]));
+        assert!(right.iter().eq(&items[at..]));
+    }
+    assert!(FingerTree::<u64, Total>::default().is_empty());
+}
+
+/// Splitting a deque past its end panics.
+#[test]
+#[should_panic(expected = "split index out of bounds")]
+fn deque_split_out_of_bounds_panics() {
+    let mut d: Deque<u32> = (0..3).collect();
+    d.split_off(4);
+}
@@ -0,0 +1,96 @@
+//! The FM-index against a scan of its text, for small and large alphabets
+//! and several sample rates, and against the suffix array it was built
+//! from.
+
+use datastructures::fm_index::FmIndex;
+use datastructures::suffix_array::SuffixArray;
+
+mod common;
+use common::Rng;
+
+fn random_text(rng: &mut Rng, len: usize, sigma: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| (rng.below(sigma) as u8).wrapping_add(b'a'))
+        .collect()
+}
+
+/// The start of every occurrence of `pattern` in `text`, by scanning; the
+/// empty pattern occurs at every position, but not past the end.
+fn occurrences(text: &[u8], pattern: &[u8]) -> Vec<usize> {
+    (0..text.len())
+        .filter(|&i| text[i..].starts_with(pattern))
+        .collect()
+}
+
+/// Counts and positions of substrings and random patterns match a scan,
+/// including the empty pattern and patterns longer than the text.
+#[test]
+fn queries_match_scan() {
+    let mut rng = Rng(2);
+    for (n, sigma) in [
+        (0usize, 2u64),
+        (1, 2),
+        (7, 1),
+        (100, 2),
+        (1000, 4),
+        (1000, 256),
+    ] {
+        let text = random_text(&mut rng, n, sigma);
+        for rate in [1, 3, 32] {
+            let fm = FmIndex::with_sample_rate(&text, rate);
+            assert_eq!(fm.len(), n);
+            assert_eq!(fm.is_empty(), n == 0);
+            assert_eq!(fm.sample_rate(), rate);
+            for _ in 0..100 {
+                let m = rng.index(6);
+                let pattern = if n > m && rng.below(2) == 0 {
+                    let start = rng.index(n - m);
+                    text[start..start + m].to_vec()
+                } else {
+                    random_text(&mut rng, m, sigma)
+                };
+                let want = occurrences(&text, &pattern);
+                assert_eq!(fm.count(&pattern), want.len(), "{pattern:?}");
+                assert_eq!(fm.contains(&pattern), !want.is_empty());
+                assert_eq!(fm.locate(&pattern), want);
+            }
+        }
+    }
+}
+
+/// An index built from a suffix array answers like the suffix array.
+#[test]
+fn agrees_with_suffix_array() {
+    let text = b"mississippi banana bandana".repeat(20);
+    let sa = SuffixArray::build(&text);
+    let fm = FmIndex::from_suffix_array(&sa, 5);
+    for pattern in [&b"ssi"[..], b"ana", b"a b", b"x", b"mississippi banana"] {
+        assert_eq!(fm.count(pattern), sa.count(pattern));
+        let mut want: Vec<usize> = sa.positions(pattern).iter().map(|&p| p as usize).collect();
+        want.sort_unstable();
+        assert_eq!(fm.locate(pattern), want);
+    }
+    assert_eq!(FmIndex::new(&text).locate(b"ssi"), fm.locate(b"ssi"));
+}
+
+/// The empty pattern occurs at every text position, as the suffix array
+/// counts it, and nowhere in an empty text.
+#[test]
+fn empty_pattern() {
+    let text = b"abracadabra";
+    let fm = FmIndex::with_sample_rate(text, 4);
+    assert_eq!(fm.count(b""), SuffixArray::build(text).count(b""));
+    assert_eq!(fm.count(b""), text.len());
+    assert_eq!(fm.locate(b""), (0..text.len()).collect::<Vec<_>>());
+    assert!(fm.contains(b""));
+    let empty = FmIndex::new(b"");
+    assert_eq!((empty.count(b""), empty.contains(b"")), (0, false));
+    assert!(empty.locate(b"").is_empty());
+}
+
+/// Sampling needs a positive rate.
+#[test]
+#[should_panic(expected = "sample rate must be positive")]
+fn zero_sample_rate_panics() {
+    FmIndex::with_sample_rate(b"abc", 0);
+}
@@ -0,0 +1,115 @@
+//! The gap buffer against a `String` and a cursor offset, under random
+//! cursor moves, typing and deletion of mixed-width text, and conversions
+//! to and from a rope.
+
+use datastructures::gap_buffer::GapBuffer;
+use datastructures::rope::Rope;
+
+mod common;
+use common::Rng;
+
+/// A random char boundary of `s`.
+fn boundary(rng: &mut Rng, s: &str) -> usize {
+    let ends: Vec<usize> = s.char_indices().map(|x| x.0).chain([s.len()]).collect();
+    ends[rng.index(ends.len())]
+}
+
+fn random_range(rng: &mut Rng, s: &str) -> (usize, usize) {
+    let (a, b) = (boundary(rng, s), boundary(rng, s));
+    (a.min(b), a.max(b))
+}
+
+/// Every edit leaves the same text and cursor as the same edit on a
+/// `String`, and the halves around the cursor split the text there.
+#[test]
+fn edits_match_string() {
+    let mut rng = Rng(3);
+    for round in 0..200 {
+        let initial = if round % 2 == 0 {
+            "héllo wörld\nabc"
+        } else {
+            ""
+        };
+        let mut buffer = GapBuffer::from(initial);
+        let mut s = initial.to_string();
+        let mut cursor = s.len();
+        for _ in 0..300 {
+            match rng.below(8) {
+                0 => {
+                    cursor = boundary(&mut rng, &s);
+                    buffer.set_cursor(cursor);
+                }
+                1 | 2 => {
+                    let texts = ["a", "é", "xyz\n", "ñ😀", ""];
+                    let text = texts[rng.index(texts.len())];
+                    buffer.insert(text);
+                    s.insert_str(cursor, text);
+                    cursor += text.len();
+                }
+                3 => {
+                    let c = ['q', 'ß', '😀'][rng.index(3)];
+                    buffer.insert_char(c);
+                    s.insert(cursor, c);
+                    cursor += c.len_utf8();
+                }
+                4 => {
+                    let before = s[..cursor].chars().next_back();
+                    assert_eq!(buffer.delete_backward(), before);
+                    if let Some(c) = before {
+                        cursor -= c.len_utf8();
+                        s.remove(cursor);
+                    }
+                }
+                5 => {
+                    let after = s[cursor..].chars().next();
+                    assert_eq!(buffer.delete_forward(), after);
+                    if after.is_some() {
+                        s.remove(cursor);
+                    }
+                }
+                6 => {
+                    let (a, b) = random_range(&mut rng, &s);
+                    buffer.remove(a..b);
+                    s.replace_range(a..b, "");
+                    cursor = a;
+                }
+                _ => {
+                    let (a, b) = random_range(&mut rng, &s);
+                    assert_eq!(buffer.slice(a..b), &s[a..b]);
+                }
+            }
+            assert_eq!(buffer.cursor(), cursor);
+            assert_eq!(buffer.to_string(), s);
+            assert_eq!(buffer.len(), s.len());
+            assert_eq!(buffer.is_empty(), s.is_empty());
+            assert!(buffer.len() <= buffer.capacity());
+            assert_eq!(buffer.as_slices(), (&s[..cursor], &s[cursor..]));
+        }
+        let rope = Rope::from(&buffer);
+        assert_eq!(rope.to_string(), s);
+        assert_eq!(GapBuffer::from(&rope).to_string(), s);
+    }
+}
+
+/// Typing into a preallocated buffer fills it without growing, and one
+/// more byte at least doubles it.
+#[test]
+fn capacity_grows_by_doubling() {
+    let mut buffer = GapBuffer::with_capacity(100);
+    for _ in 0..100 {
+        buffer.insert_char('x');
+    }
+    assert_eq!(buffer.capacity(), 100);
+    buffer.set_cursor(50);
+    buffer.insert("y");
+    assert!(buffer.capacity() >= 200);
+    assert_eq!(buffer.cursor(), 51);
+    assert_eq!(buffer.slice(49..52), "xyx");
+}
+
+/// The cursor cannot be moved inside a multi-byte char.
+#[test]
+#[should_panic(expected = "byte offset is not on a char boundary")]
+fn cursor_off_a_char_boundary_panics() {
+    GapBuffer::from("😀").set_cursor(2);
+}
@@ -0,0 +1,89 @@
+//! Geohash encoding, neighbors and box coverings, against published
+//! values and random round trips.
+
+use datastructures::geohash::{covering, Direction, Geohash, LatLonBox};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Known encodings and neighbors, and the wrap at the antimeridian and the
+/// missing neighbor past a pole.
+#[test]
+fn known_cells_and_neighbors() {
+    let g = Geohash::encode(57.64911, 10.40744, 11).unwrap();
+    assert_eq!(g.to_string(), "u4pruydqqvj");
+    assert_eq!("U4PRUYDQQVJ".parse::<Geohash>().unwrap(), g);
+
+    let cell: Geohash = "ezs42".parse().unwrap();
+    let (lat, lon) = cell.center();
+    assert!(
+        (lat - 42.605).abs() < 0.03 && (lon + 5.603).abs() < 0.03,
+        "{lat} {lon}"
+    );
+    let parent = cell.parent().unwrap();
+    assert_eq!(parent.to_string(), "ezs4");
+    assert!(parent.contains(&cell));
+    assert_eq!(cell.children().count(), 32);
+
+    let neighbor = |d| cell.neighbor(d).unwrap().to_string();
+    assert_eq!(neighbor(Direction::North), "ezs48");
+    assert_eq!(neighbor(Direction::East), "ezs43");
+    assert_eq!(neighbor(Direction::South), "ezs40");
+    assert_eq!(neighbor(Direction::West), "ezefr");
+    assert_eq!(
+        cell.neighbors().map(|n| n.unwrap().to_string()),
+        ["ezs48", "ezs49", "ezs43", "ezs41", "ezs40", "ezefp", "ezefr", "ezefx"]
+    );
+
+    let west_edge = Geohash::encode(0.0, -179.99, 4).unwrap();
+    let wrapped = west_edge.neighbor(Direction::West).unwrap();
+    assert!(wrapped.bounds().max_lon > 179.9);
+    let north_pole = Geohash::encode(89.99, 0.0, 3).unwrap();
+    assert!(north_pole.neighbor(Direction::North).is_none());
+}
+
+/// Random points round-trip through their text form and lie in their
+/// cell, and a box covering is sorted, free of nested cells and covers
+/// every point of the box.
+#[test]
+fn random_round_trips_and_coverings() {
+    let mut rng = Rng(5);
+    for _ in 0..2000 {
+        let lat = rng.range(-90.0, 90.0);
+        let lon = rng.range(-180.0, 180.0);
+        let precision = 1 + rng.below(12) as u8;
+        let g = Geohash::encode(lat, lon, precision).unwrap();
+        assert!(g.bounds().contains(lat, lon));
+        assert_eq!(g.to_string().parse::<Geohash>().unwrap(), g);
+        for d in Direction::ALL {
+            if let Some(n) = g.neighbor(d) {
+                assert_eq!(n.precision(), precision);
+                assert_ne!(n, g);
+            }
+        }
+    }
+    for _ in 0..30 {
+        let lat = rng.range(-80.0, 70.0);
+        let lon = rng.range(-180.0, 170.0);
+        let area = LatLonBox::new(
+            lat,
+            lon,
+            lat + rng.range(0.0, 10.0),
+            lon + rng.range(0.0, 10.0),
+        );
+        let precision = 2 + rng.below(3) as u8;
+        let cover = covering(&area, precision);
+        assert_eq!(sorted(cover.clone()), cover);
+        for w in cover.windows(2) {
+            assert!(!w[0].contains(&w[1]));
+        }
+        for _ in 0..200 {
+            let lat = rng.range(area.min_lat, area.max_lat);
+            let lon = rng.range(area.min_lon, area.max_lon);
+            let g = Geohash::encode(lat, lon, precision).unwrap();
+            assert!(cover.iter().any(|c| c.contains(&g)));
+        }
+    }
+    let world = LatLonBox::new(-90.0, -180.0, 90.0, 180.0);
+    assert_eq!(covering(&world, 5).len(), 32);
+}
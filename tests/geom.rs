@@ -0,0 +1,566 @@
+//! The geometric predicates: boundary-touching and degenerate shapes
+//! checked by hand, and random shapes checked against point sampling and
+//! ray marching.
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use datastructures::geom::{Aabb, Frustum, Obb, Plane, Ray, Side, Sphere};
+
+mod common;
+use common::Rng;
+
+/// The axes of a 45-degree turn about z.
+const TURNED: [[f64; 3]; 3] = [
+    [FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0],
+    [-FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0],
+    [0.0, 0.0, 1.0],
+];
+
+fn unit_cube() -> Aabb<3> {
+    Aabb::new([0.0; 3], [1.0; 3])
+}
+
+fn random_point(rng: &mut Rng, spread: f64) -> [f64; 3] {
+    std::array::from_fn(|_| rng.range(-spread, spread))
+}
+
+/// A box with a random corner and random edge lengths below `size`.
+fn random_box(rng: &mut Rng, size: f64) -> Aabb<3> {
+    let min = random_point(rng, 3.0);
+    Aabb::new(min, min.map(|m| m + rng.range(0.0, size)))
+}
+
+/// The axes of a uniformly random rotation, from a normalized quaternion.
+fn random_rotation(rng: &mut Rng) -> [[f64; 3]; 3] {
+    let q: [f64; 4] = std::array::from_fn(|_| rng.range(-1.0, 1.0));
+    let n = q.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let [w, x, y, z] = q.map(|c| c / n);
+    [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + z * w),
+            2.0 * (x * z - y * w),
+        ],
+        [
+            2.0 * (x * y - z * w),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z + x * w),
+        ],
+        [
+            2.0 * (x * z + y * w),
+            2.0 * (y * z - x * w),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ]
+}
+
+fn random_obb(rng: &mut Rng) -> Obb {
+    let center = random_point(rng, 3.0);
+    let axes = random_rotation(rng);
+    Obb::new(center, axes, std::array::from_fn(|_| rng.range(0.1, 2.0)))
+}
+
+/// A random point inside `obb`.
+fn sample(rng: &mut Rng, obb: &Obb) -> [f64; 3] {
+    let local: [f64; 3] = std::array::from_fn(|j| rng.range(-1.0, 1.0) * obb.half_extents[j]);
+    std::array::from_fn(|a| obb.center[a] + (0..3).map(|j| local[j] * obb.axes[j][a]).sum::<f64>())
+}
+
+fn corners(bbox: &Aabb<3>) -> [[f64; 3]; 8] {
+    std::array::from_fn(|i| {
+        std::array::from_fn(|a| {
+            if i >> a & 1 == 1 {
+                bbox.max[a]
+            } else {
+                bbox.min[a]
+            }
+        })
+    })
+}
+
+fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f64>().sqrt()
+}
+
+/// The side of a plane the given signed distances lie on.
+fn side_of_distances(distances: &[f64]) -> Side {
+    if distances.iter().all(|&d| d > 0.0) {
+        Side::Front
+    } else if distances.iter().all(|&d| d < 0.0) {
+        Side::Back
+    } else {
+        Side::Straddling
+    }
+}
+
+/// The first parameter in `[0, max_t]`, in steps of `max_t / 4000`, at
+/// which the ray is inside the shape.
+fn march(ray: &Ray<3>, max_t: f64, inside: impl Fn(&[f64; 3]) -> bool) -> Option<f64> {
+    (0..=4000)
+        .map(|i| i as f64 * max_t / 4000.0)
+        .find(|&t| inside(&ray.at(t)))
+}
+
+/// Boxes are closed: sharing a face, an edge or a single corner counts as
+/// intersecting, and the overlap is a non-empty flat box.
+#[test]
+fn boxes_touching_at_the_boundary_intersect() {
+    let cube = unit_cube();
+    let face = Aabb::new([1.0, 0.0, 0.0], [2.0, 1.0, 1.0]);
+    let edge = Aabb::new([1.0, 1.0, 0.0], [2.0, 2.0, 1.0]);
+    let corner = Aabb::new([1.0; 3], [2.0; 3]);
+    for other in [face, edge, corner] {
+        assert!(cube.intersects(&other) && other.intersects(&cube));
+        let overlap = cube.intersection(&other);
+        assert!(!overlap.is_empty());
+        assert_eq!(overlap.area(), 0.0);
+        assert!(cube.contains(&overlap) && other.contains(&overlap));
+    }
+    assert_eq!(cube.intersection(&corner), Aabb::point([1.0; 3]));
+
+    let apart = Aabb::new([1.0 + 1e-9, 0.0, 0.0], [2.0, 1.0, 1.0]);
+    assert!(!cube.intersects(&apart));
+    assert!(cube.intersection(&apart).is_empty());
+    assert!(cube.distance_squared(&apart.min) > 0.0);
+
+    for p in corners(&cube) {
+        assert!(cube.contains_point(&p));
+        assert_eq!(cube.distance_squared(&p), 0.0);
+        assert!(cube.contains(&Aabb::point(p)));
+    }
+    assert!(cube.contains(&cube));
+    assert!(!cube.contains_point(&[0.5, 0.5, -1e-12]));
+    assert_eq!(cube.distance_squared(&[2.0, 3.0, 0.5]), 5.0);
+    assert_eq!(cube.margin(), 3.0);
+}
+
+/// A point box intersects exactly the boxes containing its point, and the
+/// empty box meets nothing, has no size and is the identity for union.
+#[test]
+fn point_and_empty_boxes() {
+    let cube = unit_cube();
+    let point = Aabb::point([1.0, 0.5, 0.0]);
+    assert!(!point.is_empty());
+    assert_eq!((point.area(), point.margin()), (0.0, 0.0));
+    assert_eq!(point.center(), [1.0, 0.5, 0.0]);
+    assert!(point.intersects(&cube) && point.intersects(&point));
+    assert!(point.contains(&point) && cube.contains(&point));
+    assert!(!Aabb::point([1.0, 0.5, -0.1]).intersects(&cube));
+
+    let empty = Aabb::<3>::empty();
+    assert!(empty.is_empty());
+    assert_eq!((empty.area(), empty.margin()), (0.0, 0.0));
+    assert!(!empty.intersects(&cube) && !cube.intersects(&empty));
+    assert!(!empty.intersects(&empty));
+    assert!(!empty.contains_point(&[0.5; 3]));
+    assert_eq!(empty.union(&cube), cube);
+    assert_eq!(cube.union(&empty), cube);
+    assert_eq!(empty.union(&point), point);
+    assert!(cube.intersection(&empty).is_empty());
+}
+
+/// Spheres are closed: tangent spheres and a sphere touching a box corner
+/// intersect, and a zero-radius sphere behaves as its center.
+#[test]
+fn spheres_at_tangency() {
+    let unit = Sphere::new([0.0; 3], 1.0);
+    let tangent = Sphere::new([2.0, 0.0, 0.0], 1.0);
+    assert!(unit.intersects(&tangent) && tangent.intersects(&unit));
+    assert!(!unit.intersects(&Sphere::new([2.0 + 1e-9, 0.0, 0.0], 1.0)));
+
+    let dot = Sphere::new([1.0, 0.0, 0.0], 0.0);
+    assert!(dot.contains_point(&dot.center));
+    assert!(!dot.contains_point(&[1.0, 1e-9, 0.0]));
+    assert!(unit.contains(&dot) && unit.intersects(&dot));
+    assert!(unit.contains(&unit) && dot.contains(&dot));
+    assert!(!dot.contains(&unit));
+    assert!(Sphere::new([0.5, 0.0, 0.0], 0.5).contains(&dot));
+    assert!(!Sphere::new([0.5, 0.0, 0.0], 0.4).contains(&dot));
+    assert_eq!(dot.bounds(), Aabb::point(dot.center));
+
+    let cube = unit_cube();
+    let at_corner = Sphere::new([-1.0, -1.0, 0.5], 2f64.sqrt());
+    assert!(at_corner.intersects_aabb(&cube) && cube.intersects_sphere(&at_corner));
+    assert!(!Sphere::new([-1.0, -1.0, 0.5], 1.414).intersects_aabb(&cube));
+    assert!(Sphere::new([1.0, 0.5, 0.5], 0.0).intersects_aabb(&cube));
+
+    // The flat box's far corner lies on the sphere.
+    let flat = Aabb::new([0.0; 3], [3.0, 4.0, 0.0]);
+    assert!(Sphere::new([0.0; 3], 5.0).contains_aabb(&flat));
+    assert!(!Sphere::new([0.0; 3], 4.999).contains_aabb(&flat));
+    assert!(Sphere::new([1.0; 3], 0.0).contains_aabb(&Aabb::point([1.0; 3])));
+}
+
+/// Negative radii are rejected.
+#[test]
+#[should_panic(expected = "radius must be non-negative")]
+fn negative_radius_panics() {
+    Sphere::new([0.0; 2], -1.0);
+}
+
+/// Rays starting inside or on a shape hit it at zero, rays along a face or
+/// grazing an edge or a tangent point hit it, and `max_t` is inclusive.
+#[test]
+fn rays_on_the_boundary() {
+    let cube = unit_cube();
+    let hit = |origin, direction| Ray::new(origin, direction).hit_aabb(&cube, 10.0);
+    assert_eq!(hit([0.5; 3], [1.0, 0.0, 0.0]), Some(0.0));
+    assert_eq!(hit([1.0, 0.5, 0.5], [1.0, 0.0, 0.0]), Some(0.0));
+    assert_eq!(hit([-1.0, 0.5, 0.5], [2.0, 0.0, 0.0]), Some(0.5));
+    assert_eq!(hit([-1.0, 0.5, 0.5], [-1.0, 0.0, 0.0]), None);
+    assert_eq!(hit([0.5, 0.5, 0.5], [0.0; 3]), Some(0.0));
+    assert_eq!(hit([2.0, 0.5, 0.5], [0.0; 3]), None);
+
+    // Parallel to a slab: inside it, on either face of it, or outside.
+    assert_eq!(hit([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0]), Some(1.0));
+    assert_eq!(hit([-1.0, 0.0, 0.5], [1.0, 0.0, 0.0]), Some(1.0));
+    assert_eq!(hit([-1.0, 1.0, 0.5], [1.0, -0.0, 0.0]), Some(1.0));
+    assert_eq!(hit([-1.0, 1.0, 1.0], [1.0, 0.0, 0.0]), Some(1.0));
+    assert_eq!(hit([-1.0, 1.0 + 1e-9, 0.5], [1.0, 0.0, 0.0]), None);
+
+    // Grazing an edge and a corner.
+    assert_eq!(hit([0.0, 2.0, 0.5], [1.0, -1.0, 0.0]), Some(1.0));
+    assert_eq!(hit([2.0, 2.0, 2.0], [-1.0, -1.0, -1.0]), Some(1.0));
+    assert_eq!(hit([0.0, 2.0 + 1e-9, 0.5], [1.0, -1.0, 0.0]), None);
+
+    let ray = Ray::new([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0]);
+    assert_eq!(ray.hit_aabb(&cube, 1.0), Some(1.0));
+    assert_eq!(ray.hit_aabb(&cube, 0.5), None);
+    assert_eq!(ray.hit_aabb(&Aabb::point([1.0, 0.5, 0.5]), 5.0), Some(2.0));
+    assert_eq!(ray.hit_aabb(&Aabb::empty(), 5.0), None);
+
+    let unit = Sphere::new([0.0; 3], 1.0);
+    assert_eq!(
+        Ray::new([1.0, 0.0, 0.0], [1.0, 0.0, 0.0]).hit_sphere(&unit, 5.0),
+        Some(0.0)
+    );
+    assert_eq!(
+        Ray::new([-2.0, 1.0, 0.0], [1.0, 0.0, 0.0]).hit_sphere(&unit, 5.0),
+        Some(2.0)
+    );
+    assert_eq!(
+        Ray::new([-2.0, 1.0, 0.0], [1.0, 0.0, 0.0]).hit_sphere(&unit, 1.0),
+        None
+    );
+    assert_eq!(
+        Ray::new([2.0, 0.0, 0.0], [1.0, 0.0, 0.0]).hit_sphere(&unit, 5.0),
+        None
+    );
+    assert_eq!(
+        Ray::new([2.0, 0.0, 0.0], [0.0; 3]).hit_sphere(&unit, 5.0),
+        None
+    );
+    let dot = Sphere::new([1.0, 0.0, 0.0], 0.0);
+    assert_eq!(
+        Ray::new([0.0; 3], [1.0, 0.0, 0.0]).hit_sphere(&dot, 5.0),
+        Some(1.0)
+    );
+
+    let floor = Plane::new([0.0, 0.0, 2.0], 0.0);
+    assert_eq!(
+        Ray::new([3.0, 0.0, 0.0], [1.0, 0.0, 0.0]).hit_plane(&floor, 5.0),
+        Some(0.0)
+    );
+    assert_eq!(
+        Ray::new([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]).hit_plane(&floor, 5.0),
+        None
+    );
+    assert_eq!(
+        Ray::new([0.0, 0.0, 1.0], [0.0, 0.0, 1.0]).hit_plane(&floor, 5.0),
+        None
+    );
+    assert_eq!(
+        Ray::new([0.0, 0.0, 1.0], [0.0, 0.0, -0.5]).hit_plane(&floor, 5.0),
+        Some(2.0)
+    );
+    assert_eq!(
+        Ray::new([0.0, 0.0, 1.0], [0.0, 0.0, -0.5]).hit_plane(&floor, 1.5),
+        None
+    );
+
+    let turned = Obb::new([0.0; 3], TURNED, [1.0; 3]);
+    let ray = Ray::new([-3.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+    let t = ray.hit_obb(&turned, 5.0).unwrap();
+    assert!((t - (3.0 - 2f64.sqrt())).abs() < 1e-12);
+    assert_eq!(
+        Ray::new([0.0; 3], [0.0, 0.0, 1.0]).hit_obb(&turned, 5.0),
+        Some(0.0)
+    );
+}
+
+/// Shapes touching a plane straddle it, whatever the normal's length.
+#[test]
+fn planes_classify_touching_shapes_as_straddling() {
+    let cube = unit_cube();
+    let face = Plane::new([2.0, 0.0, 0.0], 2.0);
+    assert_eq!(face.side_of_point(&[1.0, 7.0, -3.0]), Side::Straddling);
+    assert_eq!(face.side_of_point(&[1.5, 0.0, 0.0]), Side::Front);
+    assert_eq!(face.signed_distance(&[1.5, 0.0, 0.0]), 0.5);
+    assert_eq!(face.normalized(), Plane::new([1.0, 0.0, 0.0], 1.0));
+    assert_eq!(cube.side_of(&face), Side::Straddling);
+    assert_eq!(
+        Aabb::new([1.0, 0.0, 0.0], [2.0; 3]).side_of(&face),
+        Side::Straddling
+    );
+    assert_eq!(
+        Aabb::new([1.5, 0.0, 0.0], [2.0; 3]).side_of(&face),
+        Side::Front
+    );
+    assert_eq!(
+        Aabb::point([1.0, 0.2, 0.3]).side_of(&face),
+        Side::Straddling
+    );
+    assert_eq!(Aabb::point([0.9, 0.2, 0.3]).side_of(&face), Side::Back);
+
+    let through_corner = Plane::from_point_normal(&[1.0; 3], [1.0; 3]);
+    assert_eq!(cube.side_of(&through_corner), Side::Straddling);
+    let past_corner = Plane::from_point_normal(&[1.1; 3], [1.0; 3]);
+    assert_eq!(cube.side_of(&past_corner), Side::Back);
+    assert_eq!(
+        Obb::from_aabb(&cube).side_of(&through_corner),
+        Side::Straddling
+    );
+    assert_eq!(Obb::from_aabb(&cube).side_of(&past_corner), Side::Back);
+
+    let tangent = Sphere::new([0.0, 0.0, 0.0], 1.0);
+    assert_eq!(tangent.side_of(&face), Side::Straddling);
+    assert_eq!(Sphere::new([0.0; 3], 0.99).side_of(&face), Side::Back);
+    assert_eq!(
+        Sphere::new([1.0, 0.0, 0.0], 0.0).side_of(&face),
+        Side::Straddling
+    );
+    assert_eq!(
+        Sphere::new([3.0, 0.0, 0.0], 1.0).side_of(&face),
+        Side::Front
+    );
+}
+
+/// Oriented boxes touching face to face or corner to face intersect, a
+/// box with zero extents is a point, and an axis-aligned one agrees with
+/// `Aabb`.
+#[test]
+fn oriented_boxes_at_the_boundary() {
+    let cube = Obb::from_aabb(&unit_cube());
+    assert_eq!(cube.bounds(), unit_cube());
+    let face = Obb::from_aabb(&Aabb::new([1.0, 0.0, 0.0], [2.0, 1.0, 1.0]));
+    let apart = Obb::from_aabb(&Aabb::new([1.0 + 1e-6, 0.0, 0.0], [2.0, 1.0, 1.0]));
+    assert!(cube.intersects(&face) && face.intersects(&cube));
+    assert!(!cube.intersects(&apart) && !apart.intersects(&cube));
+    assert!(cube.intersects_aabb(&Aabb::new([1.0; 3], [2.0; 3])));
+
+    // The turned box reaches sqrt(2) along x, with a corner on the x axis.
+    let turned = Obb::new([0.0; 3], TURNED, [1.0; 3]);
+    let reach = 2f64.sqrt();
+    let touching = Aabb::new([reach, -0.5, -0.5], [reach + 1.0, 0.5, 0.5]);
+    let beyond = Aabb::new([reach + 1e-6, -0.5, -0.5], [reach + 1.0, 0.5, 0.5]);
+    assert!(turned.intersects_aabb(&touching));
+    assert!(!turned.intersects_aabb(&beyond));
+    assert!(turned.contains_point(&[reach - 1e-12, 0.0, 0.0]));
+    assert!(!turned.contains_point(&[reach + 1e-9, 0.0, 0.0]));
+    assert!(turned.intersects_sphere(&Sphere::new([reach + 1.0, 0.0, 0.0], 1.0)));
+    assert!(!turned.intersects_sphere(&Sphere::new([reach + 1.0, 0.0, 0.0], 0.999)));
+
+    let point = Obb::new([0.5, 0.25, 1.0], TURNED, [0.0; 3]);
+    assert!(point
+        .corners()
+        .iter()
+        .all(|c| distance(c, &point.center) < 1e-15));
+    assert!(point.contains_point(&point.center));
+    assert!(!point.contains_point(&[0.5, 0.25, 1.0 + 1e-9]));
+    assert!(point.intersects(&cube) && cube.intersects(&point));
+    assert!(point.intersects(&point));
+    assert!(!point.intersects(&apart));
+    assert_eq!(point.distance_squared(&[0.5, 0.25, 3.0]), 4.0);
+    assert_eq!(point.closest_point(&[9.0, 9.0, 9.0]), point.center);
+    assert!(Obb::from_aabb(&Aabb::point([1.0; 3])).intersects(&cube));
+}
+
+/// A frustum holds the points on its planes, meets boxes and spheres that
+/// touch it from outside, and contains only shapes that keep off every
+/// plane.
+#[test]
+fn frustum_boundary() {
+    let cube = Frustum::new([
+        Plane::new([1.0, 0.0, 0.0], 0.0),
+        Plane::new([-1.0, 0.0, 0.0], -1.0),
+        Plane::new([0.0, 1.0, 0.0], 0.0),
+        Plane::new([0.0, -1.0, 0.0], -1.0),
+        Plane::new([0.0, 0.0, 1.0], 0.0),
+        Plane::new([0.0, 0.0, -1.0], -1.0),
+    ]);
+    for p in corners(&unit_cube()) {
+        assert!(cube.contains_point(&p));
+    }
+    assert!(cube.contains_point(&[0.5, 1.0, 0.5]));
+    assert!(!cube.contains_point(&[0.5, 1.0 + 1e-12, 0.5]));
+
+    assert!(cube.intersects_aabb(&Aabb::new([1.0; 3], [2.0; 3])));
+    assert!(!cube.intersects_aabb(&Aabb::new([1.0 + 1e-9, 0.0, 0.0], [2.0; 3])));
+    assert!(cube.intersects_sphere(&Sphere::new([2.0, 0.5, 0.5], 1.0)));
+    assert!(!cube.intersects_sphere(&Sphere::new([2.0, 0.5, 0.5], 0.9)));
+    assert!(cube.intersects_aabb(&Aabb::point([1.0, 0.0, 0.5])));
+    assert!(cube.intersects_sphere(&Sphere::new([1.0, 0.0, 0.5], 0.0)));
+
+    assert!(cube.contains_aabb(&Aabb::new([0.25; 3], [0.75; 3])));
+    assert!(!cube.contains_aabb(&unit_cube()));
+    assert!(cube.contains_sphere(&Sphere::new([0.5; 3], 0.49)));
+    assert!(!cube.contains_sphere(&Sphere::new([0.5; 3], 0.5)));
+}
+
+/// The separating axis test agrees with sampling both boxes and is
+/// symmetric; closest points and distances agree with sampled points;
+/// ray hits agree with marching; and plane sides agree with the corners.
+#[test]
+fn oriented_boxes_match_sampling() {
+    let mut rng = Rng(1);
+    let (mut overlapping, mut apart) = (0, 0);
+    for _ in 0..3000 {
+        let (a, b) = (random_obb(&mut rng), random_obb(&mut rng));
+        let hit = a.intersects(&b);
+        assert_eq!(hit, b.intersects(&a));
+        if a.corners().iter().any(|c| b.contains_point(c))
+            || b.corners().iter().any(|c| a.contains_point(c))
+        {
+            assert!(hit);
+        }
+        if hit {
+            overlapping += 1;
+        } else {
+            apart += 1;
+            for _ in 0..200 {
+                assert!(!b.contains_point(&sample(&mut rng, &a)));
+            }
+        }
+        for c in a.corners() {
+            assert!(a.bounds().distance_squared(&c) < 1e-18);
+            assert!(a.distance_squared(&c) < 1e-18);
+        }
+
+        let p = random_point(&mut rng, 5.0);
+        let d = distance(&a.closest_point(&p), &p);
+        assert!((d * d - a.distance_squared(&p)).abs() < 1e-9);
+        for _ in 0..50 {
+            assert!(distance(&sample(&mut rng, &a), &p) >= d - 1e-9);
+        }
+
+        let (x, y) = (random_box(&mut rng, 3.0), random_box(&mut rng, 3.0));
+        assert_eq!(Obb::from_aabb(&x).intersects_aabb(&y), x.intersects(&y));
+
+        let ray = Ray::new(random_point(&mut rng, 6.0), random_point(&mut rng, 1.0));
+        let t = ray.hit_obb(&a, 10.0);
+        if let Some(t) = t {
+            assert!(a.distance_squared(&ray.at(t)) < 1e-9);
+        }
+        if let Some(first) = march(&ray, 10.0, |q| a.contains_point(q)) {
+            assert!(t.expect("the marched ray hits") <= first + 1e-9);
+        }
+
+        let plane =
+            Plane::from_point_normal(&random_point(&mut rng, 3.0), random_point(&mut rng, 1.0));
+        let heights = a.corners().map(|c| plane.signed_distance(&c));
+        assert_eq!(a.side_of(&plane), side_of_distances(&heights));
+        let heights = corners(&x).map(|c| plane.signed_distance(&c));
+        assert_eq!(x.side_of(&plane), side_of_distances(&heights));
+    }
+    assert!(overlapping > 300 && apart > 300, "{overlapping} {apart}");
+}
+
+/// Sphere hits agree with marching, sphere-box tests with the box's
+/// corners and its closest point, and plane hits and sides with signed
+/// distances.
+#[test]
+fn spheres_and_rays_match_marching() {
+    let mut rng = Rng(2);
+    for _ in 0..5000 {
+        let s = Sphere::new(random_point(&mut rng, 3.0), rng.range(0.0, 2.0));
+        let ray = Ray::new(random_point(&mut rng, 6.0), random_point(&mut rng, 1.0));
+        let t = ray.hit_sphere(&s, 10.0);
+        if let Some(first) = march(&ray, 10.0, |q| s.contains_point(q)) {
+            assert!(t.expect("the marched ray hits") <= first + 1e-9);
+        }
+        if let Some(t) = t {
+            assert!(distance(&ray.at(t), &s.center) <= s.radius + 1e-9);
+        }
+
+        let b = random_box(&mut rng, 2.0);
+        let inside = corners(&b).iter().all(|c| s.contains_point(c));
+        assert_eq!(s.contains_aabb(&b), inside);
+        let closest: [f64; 3] = std::array::from_fn(|a| s.center[a].clamp(b.min[a], b.max[a]));
+        assert_eq!(s.intersects_aabb(&b), s.contains_point(&closest));
+        if let Some(t) = ray.hit_aabb(&b, 10.0) {
+            assert!(b.distance_squared(&ray.at(t)) < 1e-9);
+        }
+        if let Some(first) = march(&ray, 10.0, |q| b.contains_point(q)) {
+            assert!(ray.hit_aabb(&b, 10.0).expect("the marched ray hits") <= first + 1e-9);
+        }
+
+        let other = Sphere::new(random_point(&mut rng, 3.0), rng.range(0.0, 2.0));
+        assert_eq!(s.intersects(&other), other.intersects(&s));
+        assert!(s.bounds().contains(&Aabb::point(s.center)));
+
+        let plane = Plane::new(random_point(&mut rng, 1.0), rng.range(-2.0, 2.0));
+        if let Some(t) = ray.hit_plane(&plane, 10.0) {
+            assert!(plane.signed_distance(&ray.at(t)).abs() < 1e-6);
+        }
+        let height = plane.signed_distance(&s.center);
+        let want = side_of_distances(&[height - s.radius, height + s.radius]);
+        assert_eq!(s.side_of(&plane), want);
+    }
+}
+
+/// A perspective frustum extracted from its matrix agrees with the clip
+/// space test away from its planes, and the box and sphere culling tests
+/// are consistent with point containment.
+#[test]
+fn frustum_matches_clip_space() {
+    // Field of view 90 degrees, aspect 1, near plane 1 and far plane 10,
+    // looking down -z.
+    let (n, f) = (1.0, 10.0);
+    let m = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, -(f + n) / (f - n), -2.0 * f * n / (f - n)],
+        [0.0, 0.0, -1.0, 0.0],
+    ];
+    let frustum = Frustum::from_matrix(&m);
+    let in_clip_space = |p: &[f64; 3]| {
+        let c: [f64; 4] =
+            std::array::from_fn(|i| (0..3).map(|j| m[i][j] * p[j]).sum::<f64>() + m[i][3]);
+        (0..3).all(|i| -c[3] <= c[i] && c[i] <= c[3])
+    };
+    let mut rng = Rng(3);
+    let mut inside = 0;
+    for _ in 0..20000 {
+        let p = random_point(&mut rng, 12.0);
+        if in_clip_space(&p) {
+            inside += 1;
+        }
+        let margin = frustum
+            .planes
+            .iter()
+            .map(|pl| pl.signed_distance(&p).abs())
+            .fold(f64::INFINITY, f64::min);
+        if margin > 1e-9 {
+            assert_eq!(frustum.contains_point(&p), in_clip_space(&p), "{p:?}");
+        }
+
+        let b = Aabb::new(p, p.map(|x| x + rng.range(0.0, 3.0)));
+        let strictly_inside =
+            |c: &[f64; 3]| frustum.planes.iter().all(|pl| pl.signed_distance(c) > 0.0);
+        assert_eq!(
+            frustum.contains_aabb(&b),
+            corners(&b).iter().all(strictly_inside)
+        );
+        if corners(&b).iter().any(|c| frustum.contains_point(c)) {
+            assert!(frustum.intersects_aabb(&b));
+        }
+        let s = Sphere::new(p, rng.range(0.0, 1.0));
+        if frustum.contains_point(&p) {
+            assert!(frustum.intersects_sphere(&s));
+        }
+        if frustum.contains_sphere(&s) {
+            assert!(frustum.contains_point(&p));
+        }
+    }
+    assert!(inside > 100);
+}
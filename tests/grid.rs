@@ -0,0 +1,71 @@
+//! The spatial hash grid against a plain map of live points, under random
+//! inserts, removals, relocations and queries.
+
+use std::collections::HashMap;
+
+use datastructures::grid::{ItemId, SpatialHashGrid};
+use datastructures::rtree::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+fn random_point(rng: &mut Rng) -> [f64; 3] {
+    [
+        rng.range(-20.0, 20.0),
+        rng.range(-20.0, 20.0),
+        rng.range(-3.0, 3.0),
+    ]
+}
+
+/// Radius, box and k-nearest queries agree with a brute-force scan, with
+/// queries both smaller and larger than a cell.
+#[test]
+fn random_operations_match_brute_force() {
+    let mut rng = Rng(9);
+    let mut grid = SpatialHashGrid::new(1.5);
+    let mut live: HashMap<ItemId, [f64; 3]> = HashMap::new();
+    for step in 0..4000 {
+        let op = rng.below(10);
+        if op < 4 || live.is_empty() {
+            let p = random_point(&mut rng);
+            live.insert(grid.insert(p, step), p);
+        } else if op < 6 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(grid.remove(id).unwrap().0, live.remove(&id).unwrap());
+        } else if op < 8 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            let p = random_point(&mut rng);
+            grid.relocate(id, p).unwrap();
+            live.insert(id, p);
+        } else {
+            let q = random_point(&mut rng);
+            let r = rng.range(0.0, 5.0);
+            let distance = |p: &[f64; 3]| (0..3).map(|a| (p[a] - q[a]).powi(2)).sum::<f64>().sqrt();
+
+            let got: Vec<_> = grid.within_radius(q, r).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|x| distance(x.1) <= r)
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let b = Aabb::new(q.map(|c| c - r), q.map(|c| c + r / 2.0));
+            let got: Vec<_> = grid.query_aabb(b).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|x| b.contains_point(x.1))
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let k = rng.index(7);
+            let got: Vec<f64> = grid.nearest(&q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = live.values().map(distance).collect();
+            want.sort_by(f64::total_cmp);
+            want.truncate(k);
+            assert_eq!(got, want);
+        }
+        assert_eq!(grid.len(), live.len());
+    }
+}
@@ -0,0 +1,124 @@
+//! The hash array mapped trie against `std::collections::HashMap`, over a
+//! pool of versions that share structure, with well-spread keys and with
+//! keys whose hashes collide.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use datastructures::hamt::HamtMap;
+
+mod common;
+use common::Rng;
+
+/// A key hashing to one of seven values, so full hashes collide often.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Colliding(u64);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 % 7).hash(state);
+    }
+}
+
+fn check<K: Hash + Eq + Clone + Debug>(map: &HamtMap<K, u64>, want: &HashMap<K, u64>) {
+    assert_eq!(map.len(), want.len());
+    assert_eq!(map.is_empty(), want.is_empty());
+    assert_eq!(map.iter().len(), want.len());
+    for (k, v) in want {
+        assert_eq!(map.get(k), Some(v));
+        assert!(map.contains_key(k));
+    }
+    let got: HashMap<K, u64> = map.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    assert_eq!(&got, want);
+    assert_eq!(map.keys().count(), want.len());
+    assert_eq!(map.values().sum::<u64>(), want.values().sum::<u64>());
+}
+
+/// Random inserts, removals and transient batches applied to versions
+/// drawn from a pool, keys drawn from `0..range` through `key`.
+fn matches_hashmap<K: Hash + Eq + Clone + Debug>(key: impl Fn(u64) -> K, seed: u64, range: u64) {
+    let mut rng = Rng(seed);
+    let mut pool = vec![(HamtMap::new(), HashMap::new())];
+    for step in 0..10000u64 {
+        let i = rng.index(pool.len());
+        let (mut map, mut want) = pool[i].clone();
+        let k = key(rng.below(range));
+        match rng.below(4) {
+            0 | 1 => assert_eq!(map.insert(k.clone(), step), want.insert(k, step)),
+            2 => {
+                assert_eq!(map.remove(&k), want.remove(&k));
+                assert_eq!(map.get(&k), None);
+            }
+            _ => {
+                let mut t = map.transient();
+                for _ in 0..10 {
+                    let k = key(rng.below(range));
+                    if rng.below(3) == 0 {
+                        assert_eq!(t.remove(&k), want.remove(&k));
+                    } else {
+                        assert_eq!(t.insert(k.clone(), step), want.insert(k.clone(), step));
+                    }
+                    assert_eq!(t.get(&k), want.get(&k));
+                }
+                assert_eq!(t.len(), want.len());
+                assert_eq!(t.is_empty(), want.is_empty());
+                map = t.persistent();
+            }
+        }
+        if step % 10 == 0 {
+            check(&map, &want);
+            check(&pool[i].0, &pool[i].1);
+        }
+        if pool.len() < 30 {
+            pool.push((map, want));
+        } else {
+            pool[rng.index(30)] = (map, want);
+        }
+    }
+    for (map, want) in &pool {
+        check(map, want);
+    }
+}
+
+/// Dense and sparse key ranges over many versions.
+#[test]
+fn versions_match_hashmap() {
+    matches_hashmap(|x| x, 1, 500);
+    matches_hashmap(|x| x, 2, 50);
+}
+
+/// Keys sharing a full hash land in collision nodes, which insert, remove
+/// and collapse like the rest of the trie.
+#[test]
+fn colliding_keys_match_hashmap() {
+    matches_hashmap(Colliding, 3, 60);
+}
+
+/// The persistent forms leave the original map unchanged, and removing
+/// every key of a large map empties it.
+#[test]
+fn large_map_and_persistent_updates() {
+    let map: HamtMap<u64, u64> = (0..100_000).map(|i| (i, i * 2)).collect();
+    let other = map.removed(&5).inserted(100_001, 1);
+    assert_eq!(map.len(), 100_000);
+    assert_eq!(other.len(), 100_000);
+    assert_eq!(map[&5], 10);
+    assert!(!other.contains_key(&5));
+    assert_eq!(other[&100_001], 1);
+    assert_ne!(map, other);
+    let mut emptied = map.clone();
+    for i in 0..100_000 {
+        assert_eq!(emptied.remove(&i), Some(i * 2));
+    }
+    assert!(emptied.is_empty());
+    assert_eq!(emptied, HamtMap::new());
+    assert_eq!(map.len(), 100_000);
+    let mut cleared = map.clone();
+    cleared.clear();
+    assert!(cleared.is_empty());
+    assert_eq!(
+        format!("{:?}", HamtMap::<u8, u8>::from_iter([(1, 2)])),
+        "{1: 2}"
+    );
+}
@@ -0,0 +1,248 @@
+//! The meldable heaps against `BTreeSet`s of their items, through the
+//! shared `MeldableHeap` interface, with items moving between heaps by
+//! melding.
+
+use std::collections::BTreeSet;
+
+use datastructures::fibonacci_heap::FibonacciHeap;
+use datastructures::heap::MeldableHeap;
+use datastructures::leftist_heap::{LeftistHeap, SkewHeap};
+use datastructures::pairing_heap::PairingHeap;
+
+mod common;
+use common::Rng;
+
+/// Items are `(key, id)` with unique ids, so the set orders them as the
+/// heap must.
+type Item = (u64, u64);
+
+/// A heap, the handles of its items, and the items it should hold.
+struct Model<H: MeldableHeap<Item>> {
+    heap: H,
+    handles: Vec<(H::Handle, Item)>,
+    items: BTreeSet<Item>,
+}
+
+impl<H: MeldableHeap<Item>> Default for Model<H> {
+    fn default() -> Self {
+        Model {
+            heap: H::default(),
+            handles: Vec::new(),
+            items: BTreeSet::new(),
+        }
+    }
+}
+
+/// Random operations spread over four heaps, melding one into another now
+/// and then, and finally popping each heap empty.
+fn matches_btreeset<H: MeldableHeap<Item>>(seed: u64, steps: usize) {
+    let mut rng = Rng(seed);
+    let mut models: Vec<Model<H>> = (0..4).map(|_| Model::default()).collect();
+    let mut id = 0;
+    for _ in 0..steps {
+        let i = rng.index(models.len());
+        match rng.below(20) {
+            0..=7 => {
+                id += 1;
+                let item = (rng.below(1000), id);
+                let m = &mut models[i];
+                m.handles.push((m.heap.push(item), item));
+                m.items.insert(item);
+            }
+            8..=10 => {
+                let m = &mut models[i];
+                let popped = m.heap.pop();
+                assert_eq!(popped, m.items.pop_first());
+                if let Some(item) = popped {
+                    // The popped item's handle is stale, even once its
+                    // slot is reused.
+                    let j = m.handles.iter().position(|h| h.1 == item).unwrap();
+                    let (handle, _) = m.handles.swap_remove(j);
+                    assert_eq!(m.heap.get(handle), None);
+                }
+            }
+            11..=13 if !models[i].handles.is_empty() => {
+                let m = &mut models[i];
+                let j = rng.index(m.handles.len());
+                let (handle, item) = m.handles[j];
+                assert_eq!(m.heap.get(handle), Some(&item));
+                let lower = (item.0.saturating_sub(rng.below(500)), item.1);
+                m.heap.decrease_key(handle, lower);
+                m.items.remove(&item);
+                m.items.insert(lower);
+                m.handles[j].1 = lower;
+            }
+            14 | 15 if !models[i].handles.is_empty() => {
+                let m = &mut models[i];
+                let (handle, item) = m.handles.swap_remove(rng.index(m.handles.len()));
+                assert_eq!(m.heap.remove(handle), item);
+                assert!(m.items.remove(&item));
+                assert_eq!(m.heap.get(handle), None);
+            }
+            16 => {
+                // The melded heap's handles stay valid in the result.
+                let k = (i + 1 + rng.index(3)) % models.len();
+                let other = std::mem::take(&mut models[k]);
+                let m = &mut models[i];
+                m.heap.meld(other.heap);
+                m.items.extend(other.items);
+                m.handles.extend(other.handles);
+            }
+            _ => {}
+        }
+        let m = &models[i];
+        assert_eq!(m.heap.len(), m.items.len());
+        assert_eq!(m.heap.is_empty(), m.items.is_empty());
+        assert_eq!(m.heap.peek(), m.items.first());
+    }
+    for mut m in models {
+        let popped: Vec<Item> = std::iter::from_fn(|| m.heap.pop()).collect();
+        assert_eq!(popped, m.items.into_iter().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn pairing_heap_matches_btreeset() {
+    matches_btreeset::<PairingHeap<Item>>(1, 20000);
+}
+
+#[test]
+fn fibonacci_heap_matches_btreeset() {
+    matches_btreeset::<FibonacciHeap<Item>>(2, 20000);
+}
+
+#[test]
+fn leftist_heap_matches_btreeset() {
+    matches_btreeset::<LeftistHeap<Item>>(3, 20000);
+}
+
+#[test]
+fn skew_heap_matches_btreeset() {
+    matches_btreeset::<SkewHeap<Item>>(4, 20000);
+}
+
+/// A heap built by melding one-item heaps into it from both sides, big
+/// into small as often as small into big, keeps every item's handle;
+/// handles of removed items and of other heaps reach nothing.
+fn handles_survive_melds<H: MeldableHeap<u64>>() {
+    let mut all = H::default();
+    let mut handles = Vec::new();
+    for i in 0..20000 {
+        let mut one = H::default();
+        handles.push(one.push(i));
+        if i % 2 == 0 {
+            one.meld(all);
+            all = one;
+        } else {
+            all.meld(one);
+        }
+    }
+    assert_eq!(all.len(), 20000);
+    for (i, &handle) in handles.iter().enumerate() {
+        assert_eq!(all.get(handle), Some(&(i as u64)));
+    }
+    all.decrease_key(handles[500], 1);
+    assert_eq!(all.remove(handles[7]), 7);
+    assert_eq!(all.pop(), Some(0));
+    assert_eq!(all.peek(), Some(&1));
+    for gone in [handles[0], handles[7]] {
+        assert_eq!(all.get(gone), None);
+    }
+    // The freed slots are reused without reviving their old handles.
+    let fresh = [all.push(1), all.push(2)];
+    assert_eq!(all.get(handles[0]), None);
+    assert_eq!(all.get(handles[7]), None);
+    assert_eq!(all.get(fresh[1]), Some(&2));
+    let mut other = H::default();
+    let foreign = other.push(3);
+    assert_eq!(all.get(foreign), None);
+    assert_eq!(all.remove(handles[500]), 1);
+    let popped: Vec<u64> = std::iter::from_fn(|| all.pop()).collect();
+    let mut want: Vec<u64> = (1..20000).filter(|&i| i != 7 && i != 500).collect();
+    want.extend([1, 2]);
+    want.sort_unstable();
+    assert_eq!(popped, want);
+}
+
+#[test]
+fn pairing_heap_handles_survive_melds() {
+    handles_survive_melds::<PairingHeap<u64>>();
+}
+
+#[test]
+fn fibonacci_heap_handles_survive_melds() {
+    handles_survive_melds::<FibonacciHeap<u64>>();
+}
+
+#[test]
+fn leftist_heap_handles_survive_melds() {
+    handles_survive_melds::<LeftistHeap<u64>>();
+}
+
+#[test]
+fn skew_heap_handles_survive_melds() {
+    handles_survive_melds::<SkewHeap<u64>>();
+}
+
+/// Sorted and reverse-sorted pushes, which give the skew heap its longest
+/// right spines, pop back in order.
+#[test]
+fn leftist_and_skew_heaps_sorted_inputs() {
+    let mut leftist: LeftistHeap<u64> = (0..50000).collect();
+    let mut skew: SkewHeap<u64> = (0..50000).rev().collect();
+    skew.extend(50000..60000);
+    for i in 0..50000 {
+        assert_eq!(leftist.pop(), Some(i));
+        assert_eq!(skew.pop(), Some(i));
+    }
+    assert!(leftist.is_empty());
+    assert_eq!(skew.len(), 10000);
+    let handle = skew.peek_handle().unwrap();
+    assert_eq!(skew.get(handle), Some(&50000));
+    assert_eq!(skew.iter().count(), 10000);
+    skew.clear();
+    assert!(!skew.contains(handle));
+}
+
+/// Consolidation after decreasing keys deep in the trees: push a run,
+/// pop once to build trees, decrease everything below the root, then pop
+/// in order.
+#[test]
+fn fibonacci_heap_cascading_cuts() {
+    let mut heap = FibonacciHeap::new();
+    let handles: Vec<_> = (0..1000u64).map(|i| heap.push(2 * i + 1000)).collect();
+    assert_eq!(heap.pop(), Some(1000));
+    for (i, &h) in handles.iter().enumerate().skip(1).rev() {
+        heap.decrease_key(h, i as u64);
+    }
+    let popped: Vec<u64> = std::iter::from_fn(|| heap.pop()).collect();
+    assert_eq!(popped, (1..1000).collect::<Vec<_>>());
+}
+
+/// Decreasing to a greater item would break the heap order.
+#[test]
+#[should_panic(expected = "new item is greater than the current one")]
+fn fibonacci_heap_increase_key_panics() {
+    let mut heap = FibonacciHeap::new();
+    let h = heap.push(5);
+    heap.decrease_key(h, 6);
+}
+
+/// Decreasing to a greater item would break the heap order.
+#[test]
+#[should_panic(expected = "decrease_key must not increase the item")]
+fn leftist_heap_increase_key_panics() {
+    let mut heap = LeftistHeap::new();
+    let h = heap.push(5);
+    heap.decrease_key(h, 6);
+}
+
+/// A removed item's handle cannot be used again.
+#[test]
+#[should_panic(expected = "handle refers to a removed item")]
+fn skew_heap_removed_handle_panics() {
+    let mut heap = SkewHeap::new();
+    let h = heap.push(5);
+    heap.remove(h);
+    heap.remove(h);
+}
@@ -0,0 +1,224 @@
+//! The heavy-light decomposition against parent pointers found by a
+//! breadth-first search, on random trees mixing long paths with bushy
+//! parts, with path and subtree aggregates kept in a lazy segment tree.
+
+use datastructures::algebra::{Increment, Sum};
+use datastructures::csr_graph::CsrGraph;
+use datastructures::heavy_light::{HeavyLight, Segment};
+use datastructures::segment_tree::SegmentTree;
+
+mod common;
+use common::Rng;
+
+/// A tree on shuffled labels in which each node hangs off its predecessor
+/// a third of the time and off a random earlier node otherwise.
+fn random_tree(rng: &mut Rng, n: usize) -> (Vec<(usize, usize)>, usize) {
+    let mut labels: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        labels.swap(i, rng.index(i + 1));
+    }
+    let edges = (1..n)
+        .map(|i| {
+            let p = if rng.below(3) == 0 {
+                i - 1
+            } else {
+                rng.index(i)
+            };
+            (labels[i], labels[p])
+        })
+        .collect();
+    (edges, labels[0])
+}
+
+/// The tree rooted at `root` as parents, depths and subtree sizes.
+struct Rooted {
+    parent: Vec<Option<usize>>,
+    depth: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl Rooted {
+    /// Roots the tree by a breadth-first search from `root`.
+    fn new(n: usize, edges: &[(usize, usize)], root: usize) -> Self {
+        let mut adjacency = vec![Vec::new(); n];
+        for &(a, b) in edges {
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+        }
+        let mut parent = vec![None; n];
+        let mut depth = vec![0; n];
+        let mut order = vec![root];
+        let mut i = 0;
+        while i < order.len() {
+            let u = order[i];
+            for &v in &adjacency[u] {
+                if v != root && parent[v].is_none() {
+                    parent[v] = Some(u);
+                    depth[v] = depth[u] + 1;
+                    order.push(v);
+                }
+            }
+            i += 1;
+        }
+        let mut size = vec![1; n];
+        for &v in order.iter().rev() {
+            if let Some(p) = parent[v] {
+                size[p] += size[v];
+            }
+        }
+        Rooted {
+            parent,
+            depth,
+            size,
+        }
+    }
+
+    /// The nodes from `u` up to the common ancestor and down to `v`.
+    fn path(&self, mut u: usize, mut v: usize) -> Vec<usize> {
+        let (mut up, mut down) = (Vec::new(), Vec::new());
+        while u != v {
+            if self.depth[u] >= self.depth[v] {
+                up.push(u);
+                u = self.parent[u].unwrap();
+            } else {
+                down.push(v);
+                v = self.parent[v].unwrap();
+            }
+        }
+        up.push(u);
+        up.extend(down.into_iter().rev());
+        up
+    }
+
+    /// Whether `top` lies on the path from `x` to the root.
+    fn is_below(&self, mut x: usize, top: usize) -> bool {
+        while x != top {
+            match self.parent[x] {
+                Some(p) => x = p,
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+/// The nodes a list of segments covers, in path order.
+fn expand(hl: &HeavyLight, segments: Vec<Segment>) -> Vec<usize> {
+    segments
+        .into_iter()
+        .flat_map(|s| {
+            let mut nodes: Vec<usize> = s.range.map(|i| hl.node(i)).collect();
+            if s.reversed {
+                nodes.reverse();
+            }
+            nodes
+        })
+        .collect()
+}
+
+/// The tree's shape and layout, then random paths and subtrees queried
+/// and updated through a segment tree over the heavy-first order.
+#[test]
+fn decomposition_matches_search() {
+    let mut rng = Rng(11);
+    for n in [1, 2, 3, 10, 100, 1000] {
+        for _ in 0..5 {
+            let (edges, root) = random_tree(&mut rng, n);
+            let hl = HeavyLight::from_edges(n, edges.iter().copied(), root);
+            let tree = Rooted::new(n, &edges, root);
+            assert_eq!((hl.len(), hl.root()), (n, root));
+            for v in 0..n {
+                assert_eq!(hl.parent(v), tree.parent[v]);
+                assert_eq!(hl.depth(v), tree.depth[v]);
+                assert_eq!(hl.subtree_size(v), tree.size[v]);
+                assert_eq!(hl.node(hl.position(v)), v);
+                assert_eq!(hl.subtree(v).len(), tree.size[v]);
+                assert_eq!(hl.subtree(v).start, hl.position(v));
+                let head = hl.head(v);
+                assert!(tree.is_below(v, head));
+                let along = hl.depth(v) - hl.depth(head);
+                assert_eq!(hl.position(v), hl.position(head) + along);
+            }
+
+            let values: Vec<i64> = (0..n).map(|_| rng.below(100) as i64).collect();
+            let mut model = values.clone();
+            let mut sums: SegmentTree<Sum<i64>, Increment<i64>> =
+                SegmentTree::new(Sum::new(), hl.arrange(&values));
+            let light_edges = 2 * (usize::BITS - n.leading_zeros()) as usize + 2;
+            for _ in 0..300 {
+                let (u, v) = (rng.index(n), rng.index(n));
+                let path = tree.path(u, v);
+                assert_eq!(expand(&hl, hl.path(u, v)), path);
+                assert!(hl.path(u, v).len() <= light_edges);
+                let w = hl.lca(u, v);
+                let edges: Vec<usize> = path.iter().copied().filter(|&x| x != w).collect();
+                assert_eq!(expand(&hl, hl.path_edges(u, v)), edges);
+                assert_eq!(hl.distance(u, v), path.len() - 1);
+                assert!(hl.is_ancestor(w, u) && hl.is_ancestor(w, v));
+                assert_eq!(hl.is_ancestor(u, v), tree.is_below(v, u));
+                let along: i64 = path.iter().map(|&x| model[x]).sum();
+                assert_eq!(hl.query_path(&sums, u, v), along);
+                match rng.below(3) {
+                    0 => {
+                        let d = rng.below(10) as i64;
+                        hl.update_path(&mut sums, u, v, Increment(d));
+                        for &x in &path {
+                            model[x] += d;
+                        }
+                    }
+                    1 => {
+                        let d = rng.below(10) as i64;
+                        hl.update_subtree(&mut sums, u, Increment(d));
+                        for (x, value) in model.iter_mut().enumerate() {
+                            if tree.is_below(x, u) {
+                                *value += d;
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                let below: i64 = (0..n)
+                    .filter(|&x| tree.is_below(x, v))
+                    .map(|x| model[x])
+                    .sum();
+                assert_eq!(hl.query_subtree(&sums, v), below);
+            }
+        }
+    }
+}
+
+/// A graph built with both directions of each edge decomposes the same as
+/// the edge list, and a long path is a single heavy path.
+#[test]
+fn from_graph_and_long_path() {
+    let edges = [(0, 1, ()), (1, 2, ()), (1, 3, ()), (3, 4, ())];
+    let graph = CsrGraph::build_undirected(vec![(); 5], edges.to_vec());
+    let hl = HeavyLight::from_graph(&graph, 0);
+    let same = HeavyLight::from_edges(5, edges.iter().map(|e| (e.0, e.1)), 0);
+    for v in 0..5 {
+        assert_eq!(hl.position(v), same.position(v));
+    }
+    assert_eq!(hl.head(4), 0);
+    assert_eq!(hl.head(2), 2);
+    assert_eq!(hl.lca(2, 4), 1);
+
+    let n = 200_000;
+    let hl = HeavyLight::from_edges(n, (1..n).map(|i| (i - 1, i)), 0);
+    assert_eq!(hl.path(0, n - 1).len(), 1);
+    assert_eq!(hl.lca(n - 1, 5), 5);
+    assert_eq!(hl.distance(3, n - 1), n - 4);
+}
+
+/// Edges that close a cycle do not form a tree.
+#[test]
+#[should_panic(expected = "not a tree")]
+fn cycle_panics() {
+    HeavyLight::from_edges(3, [(0, 1), (1, 2), (2, 0)], 0);
+}
+
+/// The root must be one of the nodes.
+#[test]
+#[should_panic(expected = "root 3 out of range for 3 nodes")]
+fn root_out_of_range_panics() {
+    HeavyLight::from_edges(3, [(0, 1), (1, 2)], 3);
+}
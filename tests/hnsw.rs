@@ -0,0 +1,64 @@
+//! HNSW search quality: recall against exact k-nearest neighbors, on random
+//! points in several dimensions.
+
+use datastructures::hnsw::{Hnsw, HnswConfig};
+use datastructures::metric::{Euclidean, Metric};
+
+mod common;
+use common::Rng;
+
+/// At least 90% of the true 10 nearest neighbors are found, results come
+/// sorted with exact distances, stored points find themselves, and no node
+/// exceeds the layer-0 degree bound of `2 * m`.
+#[test]
+fn recall_against_exact_neighbors() {
+    let mut rng = Rng(7);
+    for (n, dim) in [(0usize, 4usize), (1, 4), (50, 8), (3000, 16), (3000, 32)] {
+        let points: Vec<Vec<f64>> = (0..n)
+            .map(|_| (0..dim).map(|_| rng.unit()).collect())
+            .collect();
+        let config = HnswConfig {
+            m: 12,
+            ef_construction: 100,
+            ..Default::default()
+        };
+        let mut index = Hnsw::with_config(Euclidean, config);
+        for (i, p) in points.iter().enumerate() {
+            assert_eq!(index.insert(p.clone(), i), i);
+        }
+        assert_eq!(index.len(), n);
+
+        let (mut found, mut total) = (0, 0);
+        for _ in 0..100 {
+            let q: Vec<f64> = (0..dim).map(|_| rng.unit()).collect();
+            let mut exact: Vec<(usize, f64)> = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, Euclidean.distance(&q, p)))
+                .collect();
+            exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+            exact.truncate(10);
+            let got = index.search(&q, 10, 100);
+            assert_eq!(got.len(), exact.len());
+            assert!(got.windows(2).all(|w| w[0].1 <= w[1].1));
+            for g in &got {
+                assert!((Euclidean.distance(&q, &points[g.0]) - g.1).abs() < 1e-12);
+            }
+            total += exact.len();
+            found += exact
+                .iter()
+                .filter(|e| got.iter().any(|g| g.0 == e.0))
+                .count();
+        }
+        if total > 0 {
+            let recall = found as f64 / total as f64;
+            assert!(recall > 0.9, "n {n} dim {dim}: recall {recall}");
+        }
+        for i in (0..n).step_by(97) {
+            assert_eq!(index.nearest_one(&points[i]), Some((i, 0.0)));
+        }
+        for i in 0..n {
+            assert!(index.neighbors(i).count() <= 24);
+        }
+    }
+}
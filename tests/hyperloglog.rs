@@ -0,0 +1,109 @@
+//! The HyperLogLog sketch's estimates across the range from empty to a
+//! million distinct items, its serialized forms, and merging sparse and
+//! dense sketches.
+
+use datastructures::hyperloglog::{HyperLogLog, MAX_PRECISION, MIN_PRECISION};
+use datastructures::probabilistic::DecodeError;
+
+fn sketch(precision: u8, items: impl IntoIterator<Item = u64>) -> HyperLogLog {
+    let mut sketch = HyperLogLog::new(precision);
+    for i in items {
+        sketch.insert(&i);
+    }
+    sketch
+}
+
+/// Every item inserted twice: estimates fall within five standard errors,
+/// plus a little slack for the tiny counts, and read back unchanged.
+#[test]
+fn estimates_within_error() {
+    for precision in [MIN_PRECISION, 10, 12, 14] {
+        for n in [0u64, 1, 10, 100, 1000, 10_000, 100_000, 1_000_000] {
+            let sketch = sketch(precision, (0..n).chain(0..n));
+            assert_eq!(sketch.is_empty(), n == 0);
+            let estimate = sketch.estimate();
+            let error = if n == 0 {
+                estimate
+            } else {
+                (estimate - n as f64) / n as f64
+            };
+            let slack = if n < 20 { 0.2 } else { 0.0 };
+            assert!(
+                error.abs() < 5.0 * sketch.relative_error() + slack,
+                "precision {precision}, {n} items: {estimate}"
+            );
+            let bytes = sketch.to_bytes();
+            assert_eq!(HyperLogLog::from_bytes(&bytes).as_ref(), Ok(&sketch));
+            if n > 0 {
+                let short = &bytes[..bytes.len() - 1];
+                assert!(HyperLogLog::from_bytes(short).is_err());
+            }
+        }
+    }
+}
+
+/// A small sketch stays sparse, and is much smaller written out, until it
+/// has more entries than the dense array would hold.
+#[test]
+fn small_sketches_stay_sparse() {
+    let small = sketch(12, 0..30);
+    assert!(small.is_sparse());
+    let large = sketch(12, 0..100_000);
+    assert!(!large.is_sparse());
+    assert!(small.to_bytes().len() * 20 < large.to_bytes().len());
+    let mut cleared = large.clone();
+    cleared.clear();
+    assert!(cleared.is_sparse() && cleared.is_empty());
+    assert_eq!(cleared.estimate(), 0.0);
+}
+
+/// Merging overlapping shards, sparse or dense, in any order gives the
+/// sketch of their union; sketches of other precisions or seeds do not
+/// merge.
+#[test]
+fn merge_gives_the_union() {
+    let a = sketch(12, 0..50_000);
+    let b = sketch(12, 25_000..100_000);
+    let small = sketch(12, 0..30);
+    let mut union = a.clone();
+    union.merge(&b).unwrap();
+    union.merge(&small).unwrap();
+    assert_eq!(union.estimate(), sketch(12, 0..100_000).estimate());
+
+    let mut sparse_first = small.clone();
+    sparse_first.merge(&a).unwrap();
+    let mut dense_first = a.clone();
+    dense_first.merge(&small).unwrap();
+    assert_eq!(sparse_first.estimate(), dense_first.estimate());
+    let mut both_sparse = small.clone();
+    both_sparse.merge(&sketch(12, 20..60)).unwrap();
+    assert_eq!(both_sparse, sketch(12, 0..60));
+
+    let mut a = a;
+    assert!(a.merge(&HyperLogLog::new(11)).is_err());
+    assert!(a.merge(&HyperLogLog::with_seed(12, 1)).is_err());
+    assert!(!a.is_compatible(&HyperLogLog::with_seed(12, 1)));
+}
+
+/// Bytes of another format or an unsupported precision are rejected.
+#[test]
+fn decoding_rejects_bad_headers() {
+    assert_eq!(
+        HyperLogLog::from_bytes(b"nope"),
+        Err(DecodeError::WrongFormat)
+    );
+    // The precision sits after the four-byte tag.
+    let mut bytes = HyperLogLog::new(MAX_PRECISION).to_bytes();
+    assert!(HyperLogLog::from_bytes(&bytes).is_ok());
+    for precision in [MIN_PRECISION - 1, MAX_PRECISION + 1] {
+        bytes[4] = precision;
+        assert_eq!(HyperLogLog::from_bytes(&bytes), Err(DecodeError::Invalid));
+    }
+}
+
+/// Precisions outside the supported range are refused.
+#[test]
+#[should_panic(expected = "precision must be between 4 and 18")]
+fn precision_out_of_range_panics() {
+    HyperLogLog::new(MAX_PRECISION + 1);
+}
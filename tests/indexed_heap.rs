@@ -0,0 +1,85 @@
+//! The indexed d-ary heap against a map of ids to priorities and a
+//! `BTreeSet` ordering them, at several arities.
+
+use std::collections::{BTreeSet, HashMap};
+
+use datastructures::indexed_heap::IndexedHeap;
+
+mod common;
+use common::Rng;
+
+/// Random inserts, priority changes, removals and pops over a few hundred
+/// ids; ties between equal priorities may pop in any order.
+#[test]
+fn operations_match_map() {
+    let mut rng = Rng(6);
+    for arity in [2, 3, 4, 8] {
+        let mut heap = IndexedHeap::with_arity(arity);
+        assert_eq!(heap.arity(), arity);
+        let mut map: HashMap<usize, u64> = HashMap::new();
+        let mut order: BTreeSet<(u64, usize)> = BTreeSet::new();
+        for _ in 0..20000 {
+            let id = rng.index(300);
+            match rng.below(5) {
+                0 | 1 => {
+                    let p = rng.below(100);
+                    let old = map.insert(id, p);
+                    if let Some(o) = old {
+                        order.remove(&(o, id));
+                    }
+                    order.insert((p, id));
+                    assert_eq!(heap.insert(id, p), old);
+                }
+                2 => match heap.pop() {
+                    None => assert!(order.is_empty()),
+                    Some((i, p)) => {
+                        assert_eq!(order.first().map(|x| x.0), Some(p));
+                        assert!(order.remove(&(p, i)));
+                        map.remove(&i);
+                    }
+                },
+                3 => {
+                    let old = map.remove(&id);
+                    if let Some(o) = old {
+                        order.remove(&(o, id));
+                    }
+                    assert_eq!(heap.remove(id), old);
+                }
+                _ => {
+                    if let Some(&old) = map.get(&id) {
+                        let p = rng.below(100);
+                        assert_eq!(heap.change_priority(id, p), old);
+                        order.remove(&(old, id));
+                        order.insert((p, id));
+                        map.insert(id, p);
+                    }
+                }
+            }
+            assert_eq!(heap.len(), order.len());
+            assert_eq!(heap.contains(id), map.contains_key(&id));
+            assert_eq!(heap.priority(id), map.get(&id));
+            let top = heap.peek();
+            assert_eq!(top.map(|x| *x.1), order.first().map(|x| x.0));
+            assert_eq!(top.map(|x| map[&x.0]), order.first().map(|x| x.0));
+        }
+        heap.clear();
+        assert!(heap.is_empty());
+        assert!((0..300).all(|id| !heap.contains(id)));
+    }
+}
+
+/// A unary heap would not be a tree.
+#[test]
+#[should_panic(expected = "arity must be at least 2")]
+fn arity_of_one_panics() {
+    IndexedHeap::<u64>::with_arity(1);
+}
+
+/// Only queued ids can have their priority changed.
+#[test]
+#[should_panic(expected = "id is not in the heap")]
+fn change_priority_of_missing_id_panics() {
+    let mut heap = IndexedHeap::new();
+    heap.insert(3, 1);
+    heap.change_priority(4, 0);
+}
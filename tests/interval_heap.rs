@@ -0,0 +1,88 @@
+//! The interval heap against a multiset counted in a `BTreeMap`, popping
+//! from both ends.
+
+use std::collections::BTreeMap;
+
+use datastructures::interval_heap::IntervalHeap;
+
+mod common;
+use common::{sorted, Rng};
+
+/// Removes one copy of `item` from the multiset.
+fn take(counts: &mut BTreeMap<u64, usize>, item: Option<u64>) {
+    if let Some(k) = item {
+        let count = counts.get_mut(&k).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&k);
+        }
+    }
+}
+
+/// Random pushes of duplicate-heavy items and pops from either end, with
+/// both ends peeked after every step.
+#[test]
+fn operations_match_multiset() {
+    let mut rng = Rng(12);
+    for _ in 0..100 {
+        let mut heap = IntervalHeap::new();
+        let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+        let mut len = 0;
+        for _ in 0..500 {
+            match rng.below(5) {
+                0 | 1 => {
+                    let x = rng.below(50);
+                    heap.push(x);
+                    *counts.entry(x).or_default() += 1;
+                    len += 1;
+                }
+                2 => {
+                    let want = counts.keys().next().copied();
+                    assert_eq!(heap.pop_min(), want);
+                    take(&mut counts, want);
+                    len -= usize::from(want.is_some());
+                }
+                _ => {
+                    let want = counts.keys().next_back().copied();
+                    assert_eq!(heap.pop_max(), want);
+                    take(&mut counts, want);
+                    len -= usize::from(want.is_some());
+                }
+            }
+            assert_eq!(heap.len(), len);
+            assert_eq!(heap.peek_min(), counts.keys().next());
+            assert_eq!(heap.peek_max(), counts.keys().next_back());
+        }
+        let want: Vec<u64> = counts
+            .iter()
+            .flat_map(|(&k, &c)| std::iter::repeat_n(k, c))
+            .collect();
+        assert_eq!(sorted(heap.iter().copied().collect()), want);
+        assert_eq!(heap.into_sorted_vec(), want);
+    }
+}
+
+/// Keeping the 10 largest of a stream by evicting the minimum, and
+/// collecting and extending, give what sorting gives.
+#[test]
+fn bounded_buffer_keeps_the_largest() {
+    let mut rng = Rng(13);
+    let stream: Vec<u64> = (0..1000).map(|_| rng.below(10_000)).collect();
+    let mut best = IntervalHeap::with_capacity(11);
+    for &x in &stream {
+        best.push(x);
+        if best.len() > 10 {
+            best.pop_min();
+        }
+    }
+    let mut want = sorted(stream.clone());
+    assert_eq!(best.into_sorted_vec(), want[want.len() - 10..]);
+
+    let mut heap: IntervalHeap<u64> = stream[..500].iter().copied().collect();
+    heap.extend(stream[500..].iter().copied());
+    assert_eq!(heap.peek_max(), want.last());
+    assert_eq!(heap.pop_min(), Some(want.remove(0)));
+    heap.clear();
+    assert!(heap.is_empty());
+    assert_eq!(heap.pop_max(), None);
+}
@@ -0,0 +1,95 @@
+//! The interval set against a bitmap of covered points, under random
+//! inserts and removals, with queries over random windows.
+
+use std::ops::Range;
+
+use datastructures::interval_set::IntervalSet;
+
+mod common;
+use common::Rng;
+
+/// The maximal runs of `bits[lo..hi]` equal to `value`.
+fn runs(bits: &[bool], lo: usize, hi: usize, value: bool) -> Vec<Range<u32>> {
+    let mut out = Vec::new();
+    let mut i = lo;
+    while i < hi {
+        if bits[i] != value {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < hi && bits[i] == value {
+            i += 1;
+        }
+        out.push(start as u32..i as u32);
+    }
+    out
+}
+
+/// The stored ranges are exactly the runs of the bitmap, so touching
+/// ranges merge, and every query agrees with it.
+#[test]
+fn operations_match_bitmap() {
+    let mut rng = Rng(13);
+    let mut set = IntervalSet::new();
+    let mut bits = vec![false; 120];
+    for _ in 0..20000 {
+        let a = rng.below(110) as u32;
+        let b = a + rng.below(12) as u32;
+        let value = rng.below(2) == 0;
+        if value {
+            set.insert(a..b);
+        } else {
+            set.remove(a..b);
+        }
+        bits[a as usize..b as usize].fill(value);
+
+        let stored = runs(&bits, 0, 120, true);
+        assert!(set.iter().eq(stored.iter().cloned()));
+        assert!(set.iter().rev().eq(stored.iter().rev().cloned()));
+        assert_eq!(set.len(), stored.len());
+        assert_eq!(set.first(), stored.first().map(|r| r.start));
+        assert_eq!(set.end(), stored.last().map(|r| r.end));
+
+        let (x, y) = (rng.index(120), rng.index(120));
+        let (lo, hi) = (x.min(y), x.max(y));
+        let window = lo as u32..hi as u32;
+        assert!(set.gaps(window.clone()).eq(runs(&bits, lo, hi, false)));
+        assert_eq!(set.covers(window.clone()), bits[lo..hi].iter().all(|&b| b));
+        assert_eq!(
+            set.overlaps(window.clone()),
+            bits[lo..hi].iter().any(|&b| b)
+        );
+        let want: Vec<Range<u32>> = stored
+            .iter()
+            .filter(|r| lo < hi && r.start < window.end && window.start < r.end)
+            .cloned()
+            .collect();
+        assert!(set.overlapping(window).eq(want));
+        assert_eq!(set.contains(x as u32), bits[x]);
+        let covering = stored.iter().find(|r| r.contains(&(x as u32))).cloned();
+        assert_eq!(set.get(x as u32), covering);
+    }
+}
+
+/// Collecting merges overlapping and touching ranges, and removing from
+/// the middle of a range splits it.
+#[test]
+fn merge_and_split() {
+    let mut set: IntervalSet<i64> = [5..8, 0..2, 2..3, 7..10, 20..20].into_iter().collect();
+    assert_eq!(set.iter().collect::<Vec<_>>(), [0..3, 5..10]);
+    set.remove(6..7);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [0..3, 5..6, 7..10]);
+    set.extend([-5..0, 3..5]);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [-5..6, 7..10]);
+    assert!(set.covers(4..4));
+    assert!(!set.overlaps(6..7));
+    assert_eq!(
+        set.gaps(-10..20).collect::<Vec<_>>(),
+        [-10..-5, 6..7, 10..20]
+    );
+    let copy = set.clone();
+    set.clear();
+    assert!(set.is_empty());
+    assert_ne!(set, copy);
+}
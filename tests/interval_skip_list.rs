@@ -0,0 +1,114 @@
+//! The interval skip list against a map of live intervals, under random
+//! inserts and removals that keep adding and dropping end points, with
+//! stabbing and overlap queries compared against a scan.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use datastructures::interval_skip_list::{IntervalId, IntervalSkipList};
+
+mod common;
+use common::Rng;
+
+/// Random operations over spans from a handful of points, where most end
+/// points are shared, to thousands, where most are new. Some intervals
+/// are empty or reversed and contain no points.
+#[test]
+fn operations_match_scan() {
+    let mut rng = Rng(21);
+    for span in [3, 20, 200, 2000] {
+        let mut list = IntervalSkipList::with_seed(span);
+        let mut live: BTreeMap<IntervalId, (Range<i64>, u64)> = BTreeMap::new();
+        let mut ids: Vec<IntervalId> = Vec::new();
+        for step in 0..3000 {
+            if rng.below(5) < 3 || ids.is_empty() {
+                let start = rng.below(span) as i64;
+                let end = start + rng.below(span / 3 + 2) as i64 - 1;
+                let id = list.insert(start..end, step);
+                assert!(live.insert(id, (start..end, step)).is_none());
+                ids.push(id);
+            } else {
+                let id = ids.swap_remove(rng.index(ids.len()));
+                assert_eq!(list.remove(id), live.remove(&id));
+                assert_eq!(list.remove(id), None);
+                assert_eq!(list.get(id), None);
+            }
+            assert_eq!(list.len(), live.len());
+            let p = rng.below(span + 2) as i64 - 1;
+            let mut stabbed: Vec<IntervalId> = list
+                .stabbing(p)
+                .map(|(id, range, &value)| {
+                    assert_eq!(live[&id], (range.clone(), value));
+                    id
+                })
+                .collect();
+            stabbed.sort_unstable();
+            let want: Vec<IntervalId> = live
+                .iter()
+                .filter(|(_, (range, _))| range.contains(&p))
+                .map(|(&id, _)| id)
+                .collect();
+            assert_eq!(stabbed, want);
+            assert_eq!(list.contains_point(p), !want.is_empty());
+            let q = p + rng.below(span / 4 + 2) as i64 - 1;
+            let mut overlapping: Vec<IntervalId> =
+                list.overlapping(p..q).map(|(id, _, _)| id).collect();
+            overlapping.sort_unstable();
+            let want: Vec<IntervalId> = live
+                .iter()
+                .filter(|(_, (r, _))| r.start < r.end && p < q && r.start < q && p < r.end)
+                .map(|(&id, _)| id)
+                .collect();
+            assert_eq!(overlapping, want);
+        }
+        let mut listed: Vec<IntervalId> = list.iter().map(|(id, _, _)| id).collect();
+        listed.sort_unstable();
+        assert!(listed.iter().eq(live.keys()));
+        let copy = list.clone();
+        while let Some(id) = ids.pop() {
+            assert!(list.remove(id).is_some());
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.stabbing(1).count(), 0);
+        assert_eq!(copy.len(), live.len());
+        let p = span as i64 / 2;
+        let held = live
+            .values()
+            .filter(|(range, _)| range.contains(&p))
+            .count();
+        assert_eq!(copy.stabbing(p).count(), held);
+    }
+}
+
+/// A sweep line over many overlapping intervals, inserted and removed in
+/// order of their end points.
+#[test]
+fn sweep() {
+    let mut list = IntervalSkipList::new();
+    let ids: Vec<IntervalId> = (0..50_000).map(|i| list.insert(i..i + 50, i)).collect();
+    assert_eq!(list.stabbing(30_000).count(), 50);
+    assert_eq!(list.overlapping(100..200).count(), 149);
+    for (i, id) in ids.into_iter().enumerate() {
+        assert_eq!(list.remove(id), Some((i as i64..i as i64 + 50, i as i64)));
+    }
+    assert!(list.is_empty());
+}
+
+/// Values can be changed in place, handles of removed intervals are
+/// reused, and clearing forgets every interval.
+#[test]
+fn values_handles_and_clear() {
+    let mut list: IntervalSkipList<i32, &str> = [(1..3, "a"), (2..5, "b")].into_iter().collect();
+    let b = list.stabbing(4).next().unwrap().0;
+    *list.get_mut(b).unwrap().1 = "c";
+    assert_eq!(list.get(b), Some((&(2..5), &"c")));
+    assert_eq!(list.remove(b), Some((2..5, "c")));
+    let d = list.insert(0..1, "d");
+    assert_eq!(d, b);
+    assert!(list.contains_point(0) && !list.contains_point(3));
+    assert_eq!(list.overlapping(3..3).count(), 0);
+    list.clear();
+    assert!(list.is_empty());
+    assert_eq!(list.get(d), None);
+    assert_eq!(list.stabbing(2).count(), 0);
+}
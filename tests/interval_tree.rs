@@ -0,0 +1,55 @@
+//! The interval tree against a plain map of live intervals, under random
+//! inserts, removals and queries.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use datastructures::interval_tree::{IntervalId, IntervalTree};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Stabbing and overlap queries agree with a scan, empty intervals and
+/// queries included, and iteration runs in order of start.
+#[test]
+fn random_operations_match_brute_force() {
+    let mut rng = Rng(17);
+    let mut tree = IntervalTree::new();
+    let mut live: HashMap<IntervalId, Range<i64>> = HashMap::new();
+    for step in 0..5000 {
+        let op = rng.below(10);
+        if op < 5 || live.is_empty() {
+            let start = rng.below(1000) as i64;
+            let range = start..start + rng.below(60) as i64;
+            live.insert(tree.insert(range.clone(), step), range);
+        } else if op < 7 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+        } else {
+            let p = rng.below(1100) as i64 - 50;
+            let got: Vec<_> = tree.stabbing(p).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|x| x.1.contains(&p))
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let q = p..p + rng.below(40) as i64;
+            let got: Vec<_> = tree.overlapping(q.clone()).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|x| {
+                    let r = x.1;
+                    !r.is_empty() && !q.is_empty() && r.start < q.end && q.start < r.end
+                })
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+        }
+        assert_eq!(tree.len(), live.len());
+    }
+    let starts: Vec<i64> = tree.iter().map(|x| x.1.start).collect();
+    assert!(starts.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(starts.len(), live.len());
+}
@@ -0,0 +1,135 @@
+//! The intrusive red-black tree against a sorted list of linked items,
+//! with each item carrying links into two trees ordered by different keys.
+
+use datastructures::intrusive_rbtree::{Adapter, RbLink, RbTree};
+
+mod common;
+use common::Rng;
+
+#[derive(Clone, Debug, Default)]
+struct Task {
+    deadline: u32,
+    name: String,
+    by_deadline: RbLink,
+    by_name: RbLink,
+}
+
+struct ByDeadline;
+
+impl Adapter for ByDeadline {
+    type Item = Task;
+    type Key = u32;
+
+    fn key(item: &Task) -> &u32 {
+        &item.deadline
+    }
+
+    fn link(item: &Task) -> &RbLink {
+        &item.by_deadline
+    }
+
+    fn link_mut(item: &mut Task) -> &mut RbLink {
+        &mut item.by_deadline
+    }
+}
+
+struct ByName;
+
+impl Adapter for ByName {
+    type Item = Task;
+    type Key = String;
+
+    fn key(item: &Task) -> &String {
+        &item.name
+    }
+
+    fn link(item: &Task) -> &RbLink {
+        &item.by_name
+    }
+
+    fn link_mut(item: &mut Task) -> &mut RbLink {
+        &mut item.by_name
+    }
+}
+
+/// Checks the deadline tree against `linked`, the indices of its items in
+/// the order they were linked: iteration, both neighbors of every item,
+/// and lookups around every deadline.
+fn check(tree: &RbTree<ByDeadline>, tasks: &[Task], linked: &[usize]) {
+    // A stable sort keeps equal deadlines in insertion order.
+    let mut want = linked.to_vec();
+    want.sort_by_key(|&i| tasks[i].deadline);
+    assert_eq!(tree.len(), want.len());
+    let got: Vec<usize> = tree.iter(tasks).map(|x| x.0).collect();
+    assert_eq!(got, want);
+    assert_eq!(tree.first(tasks), want.first().copied());
+    assert_eq!(tree.last(tasks), want.last().copied());
+    for (pos, &i) in want.iter().enumerate() {
+        assert!(tasks[i].by_deadline.is_linked());
+        assert_eq!(tree.next(tasks, i), want.get(pos + 1).copied());
+        assert_eq!(tree.prev(tasks, i), pos.checked_sub(1).map(|p| want[p]));
+    }
+    for key in 0..=101 {
+        let lower = want.iter().position(|&i| tasks[i].deadline >= key);
+        let upper = want.iter().position(|&i| tasks[i].deadline > key);
+        assert_eq!(tree.lower_bound(tasks, &key), lower.map(|p| want[p]));
+        assert_eq!(tree.upper_bound(tasks, &key), upper.map(|p| want[p]));
+        let found = lower.filter(|&p| tasks[want[p]].deadline == key);
+        assert_eq!(tree.find(tasks, &key), found.map(|p| want[p]));
+    }
+}
+
+/// Random links and unlinks in the deadline tree, with many equal
+/// deadlines, while every item stays in the name tree untouched.
+#[test]
+fn operations_match_sorted_list() {
+    let mut rng = Rng(4);
+    let mut tasks: Vec<Task> = (0..300)
+        .map(|i| Task {
+            deadline: rng.below(100) as u32,
+            name: format!("task {i:03}"),
+            ..Task::default()
+        })
+        .collect();
+    let mut by_name = RbTree::<ByName>::new();
+    for i in (0..tasks.len()).rev() {
+        by_name.insert(&mut tasks, i);
+    }
+    let mut tree = RbTree::<ByDeadline>::new();
+    let mut linked: Vec<usize> = Vec::new();
+    for step in 0..5000 {
+        let i = rng.index(tasks.len());
+        if let Some(pos) = linked.iter().position(|&x| x == i) {
+            tree.remove(&mut tasks, i);
+            linked.remove(pos);
+            assert!(!tasks[i].by_deadline.is_linked());
+        } else {
+            tree.insert(&mut tasks, i);
+            linked.push(i);
+        }
+        if step % 50 == 0 {
+            check(&tree, &tasks, &linked);
+        }
+    }
+    check(&tree, &tasks, &linked);
+
+    let names: Vec<usize> = by_name.iter(&tasks).map(|x| x.0).collect();
+    assert_eq!(names, (0..tasks.len()).collect::<Vec<_>>());
+    assert_eq!(by_name.find(&tasks, "task 123"), Some(123));
+
+    tree.clear(&mut tasks);
+    assert!(tree.is_empty());
+    assert!(tasks.iter().all(|t| !t.by_deadline.is_linked()));
+    assert!(tasks.iter().all(|t| t.by_name.is_linked()));
+    check(&tree, &tasks, &[]);
+}
+
+/// An item cannot be linked into a tree twice.
+#[test]
+#[should_panic(expected = "item is already in a tree")]
+fn double_insert_panics() {
+    let mut tasks = vec![Task::default()];
+    let mut tree = RbTree::<ByDeadline>::new();
+    tree.insert(&mut tasks, 0);
+    tree.insert(&mut tasks, 0);
+}
@@ -0,0 +1,51 @@
+//! The static k-d tree against a brute-force scan of its points.
+
+use datastructures::kdtree::KdTree;
+
+mod common;
+use common::{sorted, Rng};
+
+/// k-nearest, radius and box queries agree with a scan, for empty, tiny
+/// and larger trees, with one axis snapped to a few values so that many
+/// points tie on it.
+#[test]
+fn queries_match_brute_force() {
+    let mut rng = Rng(7);
+    for n in [0usize, 1, 2, 5, 100, 1000] {
+        let points: Vec<([f64; 3], usize)> = (0..n)
+            .map(|i| ([rng.unit(), rng.unit(), rng.below(5) as f64 / 5.0], i))
+            .collect();
+        let tree = KdTree::build(points.clone());
+        assert_eq!(tree.len(), n);
+        for _ in 0..50 {
+            let q = [rng.range(-0.2, 1.2), rng.unit(), rng.unit()];
+            let distance = |p: &[f64; 3]| (0..3).map(|a| (p[a] - q[a]).powi(2)).sum::<f64>().sqrt();
+
+            let k = rng.index(10);
+            let got: Vec<f64> = tree.nearest(&q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = points.iter().map(|(p, _)| distance(p)).collect();
+            want.sort_by(f64::total_cmp);
+            want.truncate(k);
+            assert_eq!(got, want);
+
+            let r = rng.range(0.0, 0.5);
+            let got: Vec<usize> = tree.within_radius(&q, r).map(|x| *x.2).collect();
+            let want: Vec<usize> = points
+                .iter()
+                .filter(|(p, _)| distance(p) <= r)
+                .map(|x| x.1)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let lo = [q[0] - r, q[1] - r, q[2] - r];
+            let hi = [q[0] + r, q[1] + r / 2.0, q[2] + r];
+            let got: Vec<usize> = tree.within_box(&lo, &hi).map(|x| *x.2).collect();
+            let want: Vec<usize> = points
+                .iter()
+                .filter(|(p, _)| (0..3).all(|a| lo[a] <= p[a] && p[a] <= hi[a]))
+                .map(|x| x.1)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! The link-cut tree against adjacency sets searched breadth first, under
+//! random links, cuts and value changes, with path aggregates taken in a
+//! non-commutative monoid so that reversed paths would show.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use datastructures::algebra::{Monoid, Sum};
+use datastructures::link_cut::LinkCutTree;
+
+mod common;
+use common::Rng;
+
+/// Concatenation of node labels, which records the order of the path.
+#[derive(Clone, Debug)]
+struct Concat;
+
+impl Monoid for Concat {
+    type Value = Vec<u32>;
+
+    fn identity(&self) -> Vec<u32> {
+        Vec::new()
+    }
+
+    fn combine(&self, a: &Vec<u32>, b: &Vec<u32>) -> Vec<u32> {
+        [a.as_slice(), b].concat()
+    }
+}
+
+/// The parent of every node reachable from `root`, and `root` for itself.
+fn parents(adjacency: &[BTreeSet<usize>], root: usize) -> Vec<Option<usize>> {
+    let mut parent = vec![None; adjacency.len()];
+    parent[root] = Some(root);
+    let mut queue = VecDeque::from([root]);
+    while let Some(x) = queue.pop_front() {
+        for &y in &adjacency[x] {
+            if parent[y].is_none() {
+                parent[y] = Some(x);
+                queue.push_back(y);
+            }
+        }
+    }
+    parent
+}
+
+/// The nodes on the path from `u` to `v`, in order.
+fn path(adjacency: &[BTreeSet<usize>], u: usize, v: usize) -> Option<Vec<usize>> {
+    let parent = parents(adjacency, u);
+    parent[v]?;
+    let mut out = vec![v];
+    while out[out.len() - 1] != u {
+        out.push(parent[out[out.len() - 1]].unwrap());
+    }
+    out.reverse();
+    Some(out)
+}
+
+/// Random operations on forests of several sizes. After a path query from
+/// `u` the root is `u`, so the common ancestor of `v` and any node on the
+/// path is that node, and of two arbitrary nodes it is where their paths
+/// to `u` meet.
+#[test]
+fn operations_match_model() {
+    let mut rng = Rng(17);
+    for n in [1, 2, 5, 30, 200] {
+        let labels: Vec<Vec<u32>> = (0..n as u32).map(|i| vec![i]).collect();
+        let mut tree = LinkCutTree::with_values(Concat, labels);
+        assert_eq!(tree.len(), n);
+        let mut adjacency = vec![BTreeSet::new(); n];
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for _ in 0..3000 {
+            let (u, v) = (rng.index(n), rng.index(n));
+            match rng.below(6) {
+                0 | 1 => {
+                    let apart = path(&adjacency, u, v).is_none();
+                    assert_eq!(tree.link(u, v), apart);
+                    if apart {
+                        adjacency[u].insert(v);
+                        adjacency[v].insert(u);
+                        edges.push((u, v));
+                    }
+                }
+                2 if !edges.is_empty() && rng.below(2) == 0 => {
+                    let (a, b) = edges.swap_remove(rng.index(edges.len()));
+                    let (a, b) = if rng.below(2) == 0 { (a, b) } else { (b, a) };
+                    assert!(tree.cut(a, b));
+                    adjacency[a].remove(&b);
+                    adjacency[b].remove(&a);
+                }
+                2 => {
+                    let adjacent = adjacency[u].contains(&v);
+                    assert_eq!(tree.cut(u, v), adjacent);
+                    if adjacent {
+                        adjacency[u].remove(&v);
+                        adjacency[v].remove(&u);
+                        edges.retain(|&e| e != (u, v) && e != (v, u));
+                    }
+                }
+                3 => {
+                    let value = vec![1000 + rng.below(50) as u32, u as u32];
+                    tree.set(u, value.clone());
+                    assert_eq!(tree.get(u), &value);
+                }
+                _ => {}
+            }
+            let expected = path(&adjacency, u, v);
+            assert_eq!(tree.connected(u, v), expected.is_some());
+            let want = expected.as_ref().map(|p| {
+                let values: Vec<Vec<u32>> = p.iter().map(|&x| tree.get(x).clone()).collect();
+                values.concat()
+            });
+            assert_eq!(tree.path(u, v), want);
+            let Some(p) = expected else {
+                assert_eq!(tree.lca(u, v), None);
+                continue;
+            };
+            assert_eq!(tree.find_root(v), u);
+            let w = p[rng.index(p.len())];
+            assert_eq!(tree.lca(v, w), Some(w));
+            let (y, z) = (rng.index(n), rng.index(n));
+            if let (Some(to_y), Some(to_z)) = (path(&adjacency, u, y), path(&adjacency, u, z)) {
+                let shared = to_y.iter().zip(&to_z).take_while(|(a, b)| a == b).count();
+                assert_eq!(tree.lca(y, z), Some(to_y[shared - 1]));
+            }
+        }
+    }
+}
+
+/// Rerooting by everting, then growing the forest with new nodes.
+#[test]
+fn evert_and_add_node() {
+    let mut tree = LinkCutTree::new(Sum::<u64>::new(), 4);
+    assert_eq!(tree.get(2), &0);
+    for v in 0..4 {
+        tree.set(v, 1 << v);
+    }
+    assert!(tree.link(0, 1));
+    assert!(tree.link(1, 2));
+    assert!(!tree.link(2, 0));
+    assert_eq!(tree.find_root(0), 2);
+    tree.evert(0);
+    assert_eq!(tree.find_root(2), 0);
+    assert_eq!(tree.lca(1, 2), Some(1));
+    let e = tree.add_node(16);
+    assert_eq!((e, tree.len()), (4, 5));
+    assert!(tree.link(e, 2));
+    assert_eq!(tree.path(e, 0), Some(16 + 4 + 2 + 1));
+    assert!(!tree.cut(e, 0));
+    assert!(!tree.cut(3, 3));
+    assert!(tree.cut(1, 2));
+    assert_eq!(tree.path(e, 0), None);
+    assert_eq!(tree.monoid().identity(), 0);
+    assert!(!tree.is_empty());
+}
+
+/// A long path, whose depth would defeat any recursion, answers every
+/// distance query.
+#[test]
+fn long_path() {
+    let n = 50_000;
+    let mut tree = LinkCutTree::with_values(Sum::<u64>::new(), vec![1; n]);
+    for i in 1..n {
+        assert!(tree.link(i - 1, i));
+    }
+    assert_eq!(tree.path(0, n - 1), Some(n as u64));
+    let mut rng = Rng(1);
+    for _ in 0..20_000 {
+        let (a, b) = (rng.index(n), rng.index(n));
+        assert_eq!(tree.path(a, b), Some(a.abs_diff(b) as u64 + 1));
+    }
+}
+
+/// Nodes are numbered below the node count.
+#[test]
+#[should_panic(expected = "node 3 out of bounds for 3 nodes")]
+fn node_out_of_bounds_panics() {
+    LinkCutTree::new(Sum::<u64>::new(), 3).evert(3);
+}
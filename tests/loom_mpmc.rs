@@ -0,0 +1,123 @@
+//! Model-checked tests of the MPMC queue: loom runs each test under every
+//! interleaving of its threads' atomic operations.
+//!
+//! Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom_mpmc`.
+
+#![cfg(loom)]
+
+use loom::sync::Arc;
+use loom::thread;
+
+use datastructures::mpmc::MpmcQueue;
+
+/// Values pushed by concurrent producers are each popped exactly once.
+#[test]
+fn concurrent_pushes_are_popped_once() {
+    loom::model(|| {
+        let queue = Arc::new(MpmcQueue::new(2));
+        let producers: Vec<_> = (0..2)
+            .map(|i| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || queue.push(i).expect("room for both"))
+            })
+            .collect();
+        let mut popped = Vec::new();
+        popped.extend(queue.pop());
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        while let Some(value) = queue.pop() {
+            popped.push(value);
+        }
+        popped.sort_unstable();
+        assert_eq!(popped, [0, 1]);
+    });
+}
+
+/// Concurrent consumers never take the same value.
+#[test]
+fn concurrent_pops_take_distinct_values() {
+    loom::model(|| {
+        let queue = Arc::new(MpmcQueue::new(2));
+        queue.push(0).unwrap();
+        queue.push(1).unwrap();
+        let consumers: Vec<_> = (0..2)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || queue.pop())
+            })
+            .collect();
+        let mut popped: Vec<i32> = consumers
+            .into_iter()
+            .map(|c| c.join().unwrap().expect("a value each"))
+            .collect();
+        popped.sort_unstable();
+        assert_eq!(popped, [0, 1]);
+        assert!(queue.pop().is_none());
+    });
+}
+
+/// A push into a full queue fails unless a concurrent pop has freed a
+/// slot, and no value is lost either way.
+#[test]
+fn full_queue_rejects_until_popped() {
+    loom::model(|| {
+        let queue = Arc::new(MpmcQueue::new(2));
+        queue.push(0).unwrap();
+        queue.push(1).unwrap();
+        let consumer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || queue.pop())
+        };
+        let pushed = queue.push(2).is_ok();
+        assert_eq!(consumer.join().unwrap(), Some(0));
+        let mut rest = Vec::new();
+        while let Some(value) = queue.pop() {
+            rest.push(value);
+        }
+        let expected: &[i32] = if pushed { &[1, 2] } else { &[1] };
+        assert_eq!(rest, expected);
+    });
+}
+
+/// One producer's values reach one consumer in order, across laps of the
+/// ring.
+#[test]
+fn single_producer_order_is_kept() {
+    loom::model(|| {
+        let queue = Arc::new(MpmcQueue::new(2));
+        let producer = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..3 {
+                    while queue.push(i).is_err() {
+                        thread::yield_now();
+                    }
+                }
+            })
+        };
+        let mut popped = Vec::new();
+        while popped.len() < 3 {
+            match queue.pop() {
+                Some(value) => popped.push(value),
+                None => thread::yield_now(),
+            }
+        }
+        producer.join().unwrap();
+        assert_eq!(popped, [0, 1, 2]);
+    });
+}
+
+/// Dropping the queue drops the values still in it.
+#[test]
+fn drop_releases_remaining_values() {
+    loom::model(|| {
+        let value = Arc::new(());
+        let queue = MpmcQueue::new(4);
+        queue.push(Arc::clone(&value)).unwrap();
+        queue.push(Arc::clone(&value)).unwrap();
+        drop(queue.pop());
+        drop(queue);
+        assert_eq!(Arc::strong_count(&value), 1);
+    });
+}
@@ -0,0 +1,139 @@
+//! The loose octree against a plain map of live oriented boxes, under
+//! random inserts, moves and removals, for several looseness factors and
+//! depth limits.
+
+use std::collections::HashMap;
+
+use datastructures::geom::{Aabb, Obb, Ray, Sphere};
+use datastructures::loose_octree::{LooseOctree, ObbId};
+use datastructures::naive;
+
+mod common;
+use common::{sorted, Rng};
+
+/// A randomly rotated box in `0..100` by `0..100` by `0..50`, mostly of
+/// middling size with some slivers and a few much larger ones.
+fn random_obb(rng: &mut Rng) -> Obb {
+    let q: [f64; 4] = std::array::from_fn(|_| rng.range(-1.0, 1.0));
+    let n = q.iter().map(|x| x * x).sum::<f64>().sqrt();
+    let [w, x, y, z] = q.map(|c| c / n);
+    let axes = [
+        [
+            1.0 - 2.0 * (y * y + z * z),
+            2.0 * (x * y + w * z),
+            2.0 * (x * z - w * y),
+        ],
+        [
+            2.0 * (x * y - w * z),
+            1.0 - 2.0 * (x * x + z * z),
+            2.0 * (y * z + w * x),
+        ],
+        [
+            2.0 * (x * z + w * y),
+            2.0 * (y * z - w * x),
+            1.0 - 2.0 * (x * x + y * y),
+        ],
+    ];
+    let size = match rng.below(10) {
+        0 => 40.0,
+        1..=3 => 0.2,
+        _ => 3.0,
+    };
+    let center = [
+        rng.range(0.0, 100.0),
+        rng.range(0.0, 100.0),
+        rng.range(0.0, 50.0),
+    ];
+    Obb::new(center, axes, std::array::from_fn(|_| rng.range(0.0, size)))
+}
+
+/// Ids of the live boxes matching `keep`, sorted.
+fn matching(live: &HashMap<ObbId, Obb>, keep: impl Fn(&Obb) -> bool) -> Vec<ObbId> {
+    sorted(live.iter().filter(|x| keep(x.1)).map(|x| *x.0).collect())
+}
+
+/// Every query kind, ray casts and the overlapping pairs agree with a scan
+/// as boxes are inserted, nudged, replaced and removed, and out-of-bounds
+/// centers are rejected.
+#[test]
+fn queries_match_brute_force() {
+    for (looseness, depth) in [(2.0, 10u8), (1.5, 6), (3.0, 20), (2.0, 0)] {
+        let mut rng = Rng(depth as u64 + 7);
+        let bounds = Aabb::new([0.0; 3], [100.0, 100.0, 50.0]);
+        let mut tree = LooseOctree::with_limits(bounds, looseness, depth);
+        let mut live: HashMap<ObbId, Obb> = HashMap::new();
+        let outside = Obb::from_aabb(&Aabb::new([100.0, -1.0, -1.0], [102.0, 1.0, 1.0]));
+        assert!(tree.insert(outside, 0).is_err());
+        for step in 0..3000 {
+            match rng.below(10) {
+                0..=5 => {
+                    let obb = random_obb(&mut rng);
+                    live.insert(tree.insert(obb, step).unwrap(), obb);
+                }
+                6 | 7 if !live.is_empty() => {
+                    let id = *live.keys().nth(rng.index(live.len())).unwrap();
+                    let mut obb = live[&id];
+                    obb.center[0] = (obb.center[0] + rng.range(-2.0, 2.0)).clamp(0.0, 100.0);
+                    if rng.below(3) == 0 {
+                        obb = random_obb(&mut rng);
+                    }
+                    assert_eq!(tree.relocate(id, obb).unwrap(), Some(live[&id]));
+                    live.insert(id, obb);
+                }
+                8 | 9 if !live.is_empty() => {
+                    let id = *live.keys().nth(rng.index(live.len())).unwrap();
+                    assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+                    assert!(tree.remove(id).is_none());
+                }
+                _ => {}
+            }
+            assert_eq!(tree.len(), live.len());
+            if step % 50 != 0 {
+                continue;
+            }
+
+            let q = random_obb(&mut rng);
+            let got = sorted(tree.intersecting(q).map(|x| x.0).collect());
+            assert_eq!(got, matching(&live, |o| o.intersects(&q)));
+            let b = q.bounds();
+            let got = sorted(tree.intersecting_aabb(b).map(|x| x.0).collect());
+            assert_eq!(got, matching(&live, |o| o.intersects_aabb(&b)));
+            let s = Sphere::new(q.center, rng.range(0.0, 10.0));
+            let got = sorted(tree.intersecting_sphere(s).map(|x| x.0).collect());
+            assert_eq!(got, matching(&live, |o| o.intersects_sphere(&s)));
+            let got = sorted(tree.containing_point(q.center).map(|x| x.0).collect());
+            assert_eq!(got, matching(&live, |o| o.contains_point(&q.center)));
+
+            let origin = [
+                rng.range(-10.0, 110.0),
+                rng.range(-10.0, 110.0),
+                rng.range(-10.0, 60.0),
+            ];
+            let ray = Ray::new(origin, std::array::from_fn(|_| rng.range(-1.0, 1.0)));
+            let max_t = rng.range(0.0, 200.0);
+            let items: Vec<(ObbId, Obb)> = live.iter().map(|(id, o)| (*id, *o)).collect();
+            let want = naive::closest_hit(&items, &ray, max_t, |o, r| r.hit_obb(&o.1, max_t));
+            assert_eq!(tree.raycast(&ray, max_t).map(|x| x.1), want.map(|x| x.1));
+
+            if step % 500 == 0 {
+                let got: Vec<_> = tree
+                    .intersecting_pairs()
+                    .into_iter()
+                    .map(|(a, b)| (a.min(b), a.max(b)))
+                    .collect();
+                let mut want = Vec::new();
+                for (i, (a, x)) in items.iter().enumerate() {
+                    for (b, y) in &items[i + 1..] {
+                        if x.intersects(y) {
+                            want.push((*a.min(b), *a.max(b)));
+                        }
+                    }
+                }
+                assert_eq!(sorted(got), sorted(want));
+            }
+        }
+        tree.clear();
+        assert!(tree.is_empty());
+        assert_eq!(tree.iter().count(), 0);
+    }
+}
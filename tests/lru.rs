@@ -0,0 +1,143 @@
+//! The weighted LRU cache against a recency-ordered list of entries, with
+//! every eviction reported to the callback in order.
+
+use std::sync::{Arc, Mutex};
+
+use datastructures::lru::LruCache;
+
+mod common;
+use common::Rng;
+
+/// A cached entry in the model: key, value and weight.
+type Entry = (u64, u64, u64);
+
+/// Random inserts of varying weight, lookups, removals and pops match a
+/// list kept most recent first, and each insert evicts exactly the least
+/// recent entries needed to fit the capacity.
+#[test]
+fn operations_match_recency_list() {
+    let mut rng = Rng(3);
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&evicted);
+    let mut cache = LruCache::new(50)
+        .with_eviction_callback(move |k: u64, v: u64| log.lock().unwrap().push((k, v)));
+    let mut model: Vec<Entry> = Vec::new();
+    for step in 0..20000u64 {
+        let k = rng.below(40);
+        let pos = model.iter().position(|e| e.0 == k);
+        match rng.below(7) {
+            0 | 1 => {
+                let w = rng.below(12);
+                let old = pos.map(|i| model.remove(i).1);
+                assert_eq!(cache.insert_weighted(k, step, w), old);
+                model.insert(0, (k, step, w));
+                let mut want = Vec::new();
+                while model.iter().map(|e| e.2).sum::<u64>() > 50 {
+                    let e = model.pop().unwrap();
+                    want.push((e.0, e.1));
+                }
+                assert_eq!(*evicted.lock().unwrap(), want);
+                evicted.lock().unwrap().clear();
+            }
+            2 => {
+                assert_eq!(cache.get(&k).copied(), pos.map(|i| model[i].1));
+                if let Some(i) = pos {
+                    let e = model.remove(i);
+                    model.insert(0, e);
+                }
+            }
+            3 => {
+                if let Some(v) = cache.get_mut(&k) {
+                    *v += 1;
+                }
+                if let Some(i) = pos {
+                    let mut e = model.remove(i);
+                    e.1 += 1;
+                    model.insert(0, e);
+                }
+            }
+            4 => {
+                assert_eq!(cache.peek(&k).copied(), pos.map(|i| model[i].1));
+                assert_eq!(cache.weight_of(&k), pos.map(|i| model[i].2));
+                assert_eq!(cache.contains(&k), pos.is_some());
+            }
+            5 => assert_eq!(cache.remove(&k), pos.map(|i| model.remove(i).1)),
+            _ => {
+                if rng.below(10) == 0 {
+                    assert_eq!(cache.pop_lru(), model.pop().map(|e| (e.0, e.1)));
+                }
+            }
+        }
+        assert_eq!(cache.len(), model.len());
+        assert_eq!(cache.weight(), model.iter().map(|e| e.2).sum::<u64>());
+        let want: Vec<(u64, u64)> = model.iter().map(|e| (e.0, e.1)).collect();
+        let got: Vec<(u64, u64)> = cache.iter().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(got, want);
+        assert!(cache
+            .iter()
+            .rev()
+            .map(|(&k, &v)| (k, v))
+            .eq(want.iter().rev().copied()));
+        assert_eq!(
+            cache.peek_lru().map(|(&k, &v)| (k, v)),
+            want.last().copied()
+        );
+    }
+    assert!(evicted.lock().unwrap().is_empty());
+}
+
+/// Shrinking the capacity evicts the least recent entries through the
+/// callback; clearing does not.
+#[test]
+fn capacity_changes_and_clear() {
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&evicted);
+    let mut cache = LruCache::new(5)
+        .with_eviction_callback(move |k: u32, v: u32| log.lock().unwrap().push((k, v)));
+    cache.extend([(1, 10), (2, 20), (3, 30), (4, 40)]);
+    assert_eq!(cache.get(&1), Some(&10));
+    cache.set_capacity(2);
+    assert_eq!(cache.capacity(), 2);
+    assert_eq!(*evicted.lock().unwrap(), [(2, 20), (3, 30)]);
+    assert_eq!(format!("{cache:?}"), "{1: 10, 4: 40}");
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.weight(), 0);
+    assert_eq!(evicted.lock().unwrap().len(), 2);
+    cache.insert(5, 50);
+    assert_eq!(cache.peek_lru(), Some((&5, &50)));
+}
+
+/// An entry heavier than the capacity is rejected, going straight to the
+/// callback without evicting anything, though it still replaces the
+/// value under its key.
+#[test]
+fn oversized_entry_is_rejected() {
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&evicted);
+    let mut cache = LruCache::new(5)
+        .with_eviction_callback(move |k: u32, v: u32| log.lock().unwrap().push((k, v)));
+    cache.insert(1, 1);
+    cache.insert_weighted(0, 0, 0);
+    assert_eq!(cache.insert_weighted(2, 2, 9), None);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.insert_weighted(1, 11, 6), Some(1));
+    assert_eq!(*evicted.lock().unwrap(), [(2, 2), (1, 11)]);
+    assert_eq!(cache.iter().collect::<Vec<_>>(), [(&0, &0)]);
+    assert_eq!(cache.weight(), 0);
+}
+
+/// Weights near `u64::MAX` evict what they must instead of overflowing the
+/// total.
+#[test]
+fn huge_weights_do_not_overflow() {
+    let mut cache = LruCache::new(u64::MAX);
+    cache.insert_weighted(1, 1, u64::MAX - 1);
+    cache.insert(2, 2);
+    assert_eq!(cache.weight(), u64::MAX);
+    cache.insert_weighted(3, 3, 2);
+    assert_eq!(cache.iter().collect::<Vec<_>>(), [(&3, &3), (&2, &2)]);
+    assert_eq!(cache.weight(), 3);
+    cache.insert_weighted(4, 4, u64::MAX);
+    assert_eq!(cache.iter().collect::<Vec<_>>(), [(&4, &4)]);
+}
@@ -0,0 +1,96 @@
+//! LSH search quality: recall against exact k-nearest neighbors on
+//! clustered data, for each hash family.
+
+use datastructures::lsh::{Lsh, LshConfig, LshFamily};
+use datastructures::metric::{Cosine, Euclidean, Metric};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Indexes 2000 points in 50 tight clusters and queries near stored
+/// points: multi-probe search finds at least 80% of the true 5 nearest,
+/// and never fewer than a single probe does.
+fn check_recall<M: Metric<Vec<f64>> + Copy>(metric: M, family: LshFamily) {
+    let mut rng = Rng(3);
+    let (dim, n) = (64, 2000);
+    let centers: Vec<Vec<f64>> = (0..50)
+        .map(|_| (0..dim).map(|_| rng.range(-1.0, 1.0)).collect())
+        .collect();
+    let points: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            centers[i % 50]
+                .iter()
+                .map(|x| x + rng.range(-0.1, 0.1))
+                .collect()
+        })
+        .collect();
+    let config = LshConfig {
+        family,
+        tables: 10,
+        hashes: 10,
+        probes: 20,
+        seed: 1,
+    };
+    let mut index = Lsh::new(metric, dim, config);
+    for (i, p) in points.iter().enumerate() {
+        assert_eq!(index.insert(p.clone(), i), i);
+    }
+    let (mut found, mut found_single, mut total) = (0, 0, 0);
+    for i in 0..100 {
+        let q: Vec<f64> = points[i * 7]
+            .iter()
+            .map(|x| x + rng.range(-0.025, 0.025))
+            .collect();
+        let mut exact: Vec<(usize, f64)> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i, metric.distance(&q, p)))
+            .collect();
+        exact.sort_by(|a, b| a.1.total_cmp(&b.1));
+        exact.truncate(5);
+        let got = index.nearest(&q, 5);
+        let single = index.search(&q, 5, 0);
+        assert!(got.windows(2).all(|w| w[0].1 <= w[1].1));
+        for g in &got {
+            assert!((metric.distance(&q, &points[g.0]) - g.1).abs() < 1e-12);
+        }
+
+        let candidates = index.candidates(&q, 20);
+        let mut unique = sorted(candidates.clone());
+        unique.dedup();
+        assert_eq!(unique.len(), candidates.len());
+        assert!(index
+            .candidates(&q, 0)
+            .iter()
+            .all(|x| candidates.contains(x)));
+
+        total += exact.len();
+        found += exact
+            .iter()
+            .filter(|e| got.iter().any(|g| g.0 == e.0))
+            .count();
+        found_single += exact
+            .iter()
+            .filter(|e| single.iter().any(|g| g.0 == e.0))
+            .count();
+    }
+    let recall = found as f64 / total as f64;
+    assert!(recall > 0.8, "{family:?}: recall {recall}");
+    assert!(found >= found_single);
+    for i in (0..n).step_by(101) {
+        let (_, d) = index.nearest_one(&points[i]).unwrap();
+        assert!(d < 1e-6, "point {i} at {d}");
+    }
+}
+
+/// Random-hyperplane hashing under the cosine distance.
+#[test]
+fn hyperplane_recall() {
+    check_recall(Cosine, LshFamily::Hyperplane);
+}
+
+/// p-stable projections under the Euclidean distance.
+#[test]
+fn p_stable_recall() {
+    check_recall(Euclidean, LshFamily::PStable { width: 4.0 });
+}
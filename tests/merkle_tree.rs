@@ -0,0 +1,86 @@
+//! The Merkle tree against a recursive reference root, with inclusion
+//! proofs for every leaf of every earlier tree size, and tampered proofs
+//! rejected.
+
+use datastructures::merkle_tree::{MerkleHasher, MerkleProof, MerkleTree, StableMerkleHasher};
+
+/// The root of `leaves` computed directly: the left subtree holds the
+/// largest power of two of leaves smaller than the whole.
+fn reference(hasher: &StableMerkleHasher, leaves: &[u64]) -> u64 {
+    if leaves.len() == 1 {
+        return leaves[0];
+    }
+    let mut k = 1;
+    while k * 2 < leaves.len() {
+        k *= 2;
+    }
+    hasher.combine(
+        &reference(hasher, &leaves[..k]),
+        &reference(hasher, &leaves[k..]),
+    )
+}
+
+fn leaves(n: u64) -> Vec<u64> {
+    (0..n)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+        .collect()
+}
+
+/// Pushing one leaf at a time keeps the root equal to the reference.
+#[test]
+fn roots_match_reference() {
+    let hasher = StableMerkleHasher::default();
+    let leaves = leaves(70);
+    let mut tree = MerkleTree::new(hasher);
+    assert_eq!(tree.root(), None);
+    assert!(tree.is_empty());
+    for (n, &leaf) in leaves.iter().enumerate() {
+        assert_eq!(tree.push(leaf), n);
+        assert_eq!(tree.root(), Some(reference(&hasher, &leaves[..=n])));
+        assert_eq!(tree.leaf(n), Some(&leaf));
+    }
+    assert_eq!(tree.len(), 70);
+    assert_eq!(tree.leaves(), leaves);
+    assert_eq!(tree.leaf(70), None);
+    let collected: MerkleTree<StableMerkleHasher> = leaves.iter().copied().collect();
+    assert_eq!(collected.root(), tree.root());
+    let built = MerkleTree::from_leaves(hasher, leaves.iter().copied());
+    assert_eq!(built.root(), tree.root());
+    let other = MerkleTree::from_leaves(StableMerkleHasher::new(1), leaves.iter().copied());
+    assert_ne!(other.root(), tree.root());
+}
+
+/// Every leaf has a proof against the root of every tree size that holds
+/// it, and changing the leaf, the index or the path breaks the proof.
+#[test]
+fn proofs_verify_for_every_size() {
+    let hasher = StableMerkleHasher::default();
+    let leaves = leaves(70);
+    let tree = MerkleTree::from_leaves(hasher, leaves.iter().copied());
+    for len in 1..=70 {
+        let root = tree.root_at(len).unwrap();
+        assert_eq!(root, reference(&hasher, &leaves[..len]));
+        for (i, leaf) in leaves[..len].iter().enumerate() {
+            let proof = tree.prove_at(i, len).unwrap();
+            assert_eq!((proof.index(), proof.tree_len()), (i, len));
+            assert!(proof.verify(&hasher, leaf, &root), "{i} of {len}");
+            assert!(!proof.verify(&hasher, &(leaf ^ 1), &root));
+            if len > 1 {
+                let path = proof.path().to_vec();
+                let moved = MerkleProof::from_parts((i + 1) % len, len, path.clone());
+                assert!(!moved.verify(&hasher, leaf, &root));
+                let short = MerkleProof::from_parts(i, len, path[1..].to_vec());
+                assert!(!short.verify(&hasher, leaf, &root));
+                let mut long = path;
+                long.push(root);
+                let long = MerkleProof::from_parts(i, len, long);
+                assert!(!long.verify(&hasher, leaf, &root));
+            }
+        }
+        assert!(tree.prove_at(len, len).is_none());
+    }
+    assert_eq!(tree.prove(69), tree.prove_at(69, 70));
+    assert!(tree.root_at(0).is_none());
+    assert!(tree.root_at(71).is_none());
+    assert!(tree.prove_at(0, 71).is_none());
+}
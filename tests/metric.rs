@@ -0,0 +1,63 @@
+//! The metric axioms the metric trees prune by, for each vector metric.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use datastructures::metric::{Chebyshev, Cosine, Euclidean, Manhattan, Metric};
+
+mod common;
+use common::Rng;
+
+/// A random vector, sometimes all zeros and sometimes a multiple of
+/// `other`, so that the edge cases come up.
+fn random_vector(rng: &mut Rng, other: &[f64]) -> Vec<f64> {
+    match rng.below(6) {
+        0 => vec![0.0; 4],
+        1 => other.iter().map(|x| x * -1.5).collect(),
+        _ => (0..4).map(|_| rng.range(-1.0, 1.0)).collect(),
+    }
+}
+
+fn check_axioms<M: Metric<[f64]>>(metric: M, rng: &mut Rng) {
+    let mut c = vec![0.0; 4];
+    for _ in 0..2000 {
+        let a = random_vector(rng, &c);
+        let b = random_vector(rng, &a);
+        c = random_vector(rng, &b);
+        let ab = metric.distance(&a, &b);
+        assert_eq!(metric.distance(&a, &a), 0.0, "{a:?}");
+        assert!(ab >= 0.0);
+        assert_eq!(ab, metric.distance(&b, &a));
+        let via = metric.distance(&a, &c) + metric.distance(&c, &b);
+        assert!(ab <= via + 1e-12, "{a:?} {b:?} {c:?}: {ab} > {via}");
+    }
+}
+
+/// Every metric is zero from a point to itself, symmetric and satisfies
+/// the triangle inequality, zero vectors included.
+#[test]
+fn metrics_satisfy_the_axioms() {
+    let mut rng = Rng(0x5eed);
+    check_axioms(Euclidean, &mut rng);
+    check_axioms(Manhattan, &mut rng);
+    check_axioms(Chebyshev, &mut rng);
+    check_axioms(Cosine, &mut rng);
+}
+
+/// The cosine distance is the angle between the vectors, with the zero
+/// vector at zero from itself and a right angle from everything else.
+#[test]
+fn cosine_is_the_angle() {
+    let zero = [0.0, 0.0];
+    assert_eq!(Cosine.distance(&zero[..], &zero[..]), 0.0);
+    assert_eq!(Cosine.distance(&zero[..], &[3.0, 4.0][..]), FRAC_PI_2);
+    assert_eq!(Cosine.distance(&[3.0, 4.0][..], &zero[..]), FRAC_PI_2);
+    let x = [1.0, 2.0, 3.0];
+    assert_eq!(Cosine.distance(&x[..], &x[..]), 0.0);
+    assert!((Cosine.distance(&x[..], &[-1.0, -2.0, -3.0][..]) - PI).abs() < 1e-12);
+    assert!((Cosine.distance(&[1.0, 0.0][..], &[0.0, 2.0][..]) - FRAC_PI_2).abs() < 1e-12);
+    assert!((Cosine.distance(&[1.0, 0.0][..], &[1.0, 1.0][..]) - PI / 4.0).abs() < 1e-12);
+    // Nearly parallel vectors keep their small angle rather than rounding
+    // to zero, as `acos` of the dot product would.
+    let tiny = Cosine.distance(&[1.0, 0.0][..], &[1.0, 1e-9][..]);
+    assert!((tiny - 1e-9).abs() < 1e-15, "{tiny}");
+}
@@ -0,0 +1,22 @@
+//! Morton codes: the generic encoder against the fixed-dimension ones, and
+//! decoding as its inverse.
+
+use datastructures::morton;
+
+mod common;
+use common::Rng;
+
+/// The generic encoding matches the 2D and 3D shortcuts and decodes back
+/// to the input.
+#[test]
+fn encodings_agree_and_round_trip() {
+    let mut rng = Rng(9);
+    for _ in 0..100 {
+        let k = [rng.next_u64() as u32, rng.next_u64() as u32];
+        assert_eq!(morton::encode(&k) as u64, morton::encode2(k[0], k[1]));
+        assert_eq!(morton::decode::<2>(morton::encode(&k)), k);
+        let k: [u32; 3] = std::array::from_fn(|_| rng.below(1 << 21) as u32);
+        assert_eq!(morton::encode(&k) as u64, morton::encode3(k[0], k[1], k[2]));
+        assert_eq!(morton::decode::<3>(morton::encode(&k)), k);
+    }
+}
@@ -0,0 +1,99 @@
+//! The MPMC queue under real threads: every value is delivered exactly
+//! once and each producer's values arrive in order. The exhaustive
+//! interleavings of small cases are in `loom_mpmc.rs`.
+
+#![cfg(not(loom))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use datastructures::mpmc::MpmcQueue;
+
+/// Four producers and four consumers through a small queue.
+#[test]
+fn concurrent_values_are_delivered_once_in_order() {
+    const PRODUCERS: u64 = 4;
+    const PER_PRODUCER: u64 = 50_000;
+    let queue = Arc::new(MpmcQueue::new(100));
+    assert_eq!(queue.capacity(), 128);
+    let popped = Arc::new(AtomicUsize::new(0));
+    let producers: Vec<_> = (0..PRODUCERS)
+        .map(|t| {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                for i in 0..PER_PRODUCER {
+                    let mut value = t * PER_PRODUCER + i;
+                    while let Err(v) = queue.push(value) {
+                        value = v;
+                        thread::yield_now();
+                    }
+                }
+            })
+        })
+        .collect();
+    let consumers: Vec<_> = (0..4)
+        .map(|_| {
+            let (queue, popped) = (Arc::clone(&queue), Arc::clone(&popped));
+            thread::spawn(move || {
+                let mut got = Vec::new();
+                let mut last = [None; PRODUCERS as usize];
+                while popped.load(Ordering::Relaxed) < (PRODUCERS * PER_PRODUCER) as usize {
+                    let Some(v) = queue.pop() else {
+                        thread::yield_now();
+                        continue;
+                    };
+                    popped.fetch_add(1, Ordering::Relaxed);
+                    let t = (v / PER_PRODUCER) as usize;
+                    assert!(last[t] < Some(v), "{v} arrived after {:?}", last[t]);
+                    last[t] = Some(v);
+                    got.push(v);
+                }
+                got
+            })
+        })
+        .collect();
+    for p in producers {
+        p.join().unwrap();
+    }
+    let mut all: Vec<u64> = consumers
+        .into_iter()
+        .flat_map(|c| c.join().unwrap())
+        .collect();
+    all.sort_unstable();
+    assert_eq!(all, (0..PRODUCERS * PER_PRODUCER).collect::<Vec<_>>());
+    assert!(queue.is_empty());
+}
+
+/// The capacity rounds up to a power of two, at least two, and a full
+/// queue hands pushed values back.
+#[test]
+fn full_and_empty() {
+    let queue = MpmcQueue::new(0);
+    assert_eq!(queue.capacity(), 2);
+    assert_eq!(queue.pop(), None);
+    queue.push(1).unwrap();
+    queue.push(2).unwrap();
+    assert_eq!(queue.push(3), Err(3));
+    assert!(queue.is_full());
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some(1));
+    queue.push(3).unwrap();
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(3));
+    assert!(queue.is_empty());
+}
+
+/// Values left in the queue are dropped with it.
+#[test]
+fn drops_remaining_values() {
+    let value = Arc::new(());
+    let queue = MpmcQueue::new(8);
+    for _ in 0..5 {
+        queue.push(Arc::clone(&value)).unwrap();
+    }
+    drop(queue.pop());
+    assert_eq!(Arc::strong_count(&value), 5);
+    drop(queue);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
@@ -0,0 +1,93 @@
+//! The M-tree against a scan of its points under several metrics and node
+//! capacities, and its pruning measured in distance computations.
+
+use std::cell::Cell;
+
+use datastructures::metric::{Euclidean, Manhattan, Metric};
+use datastructures::mtree::MTree;
+
+mod common;
+use common::{sorted, Rng};
+
+/// Inserts 2000 points in four dimensions, checking k-nearest and radius
+/// queries along the way. With `duplicates` the coordinates come from a
+/// three-value grid, so many points coincide.
+fn matches_scan<M: Metric<Vec<f64>> + Clone>(
+    metric: M,
+    capacity: usize,
+    seed: u64,
+    duplicates: bool,
+) {
+    let mut rng = Rng(seed);
+    let mut tree = MTree::with_node_capacity(metric.clone(), capacity);
+    let mut points: Vec<Vec<f64>> = Vec::new();
+    assert_eq!(tree.height(), 0);
+    assert!(tree.nearest(&vec![0.0; 4], 3).is_empty());
+    for step in 0..2000 {
+        let p: Vec<f64> = if duplicates {
+            (0..4).map(|_| rng.below(3) as f64).collect()
+        } else {
+            (0..4).map(|_| rng.range(0.0, 10.0)).collect()
+        };
+        assert_eq!(tree.insert(p.clone(), step), step);
+        points.push(p);
+        if step % 50 != 0 && step >= 40 {
+            continue;
+        }
+        for _ in 0..5 {
+            let q: Vec<f64> = (0..4).map(|_| rng.range(-1.0, 11.0)).collect();
+            let k = rng.index(8);
+            let got: Vec<f64> = tree.nearest(&q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = points.iter().map(|p| metric.distance(&q, p)).collect();
+            want.sort_by(f64::total_cmp);
+            want.truncate(k);
+            assert_eq!(got, want);
+
+            let r = rng.range(0.0, 5.0);
+            let got: Vec<usize> = tree.within_radius(&q, r).iter().map(|x| x.0).collect();
+            let want: Vec<usize> = (0..points.len())
+                .filter(|&i| metric.distance(&q, &points[i]) <= r)
+                .collect();
+            assert_eq!(sorted(got), want);
+        }
+    }
+    assert_eq!(tree.len(), 2000);
+    assert!(tree.height() >= 2);
+    assert_eq!(*tree.get(7).unwrap().1, 7);
+}
+
+/// Queries agree with a scan for small and large nodes, under the
+/// Euclidean and Manhattan metrics, with and without duplicate points.
+#[test]
+fn queries_match_brute_force() {
+    for capacity in [4, 5, 16, 32] {
+        let seed = capacity as u64;
+        matches_scan(Euclidean, capacity, seed, false);
+        matches_scan(Manhattan, capacity, seed + 100, false);
+        matches_scan(Euclidean, capacity, seed + 200, true);
+    }
+}
+
+/// A 5-nearest query over 20000 planar points computes far fewer
+/// distances than a scan would.
+#[test]
+fn queries_prune_most_points() {
+    let calls = Cell::new(0usize);
+    let metric = |a: &Vec<f64>, b: &Vec<f64>| {
+        calls.set(calls.get() + 1);
+        Euclidean.distance(a, b)
+    };
+    let mut rng = Rng(1);
+    let mut tree = MTree::new(&metric);
+    for i in 0..20000 {
+        let p: Vec<f64> = (0..2).map(|_| rng.range(0.0, 100.0)).collect();
+        tree.insert(p, i);
+    }
+    calls.set(0);
+    for _ in 0..100 {
+        let q: Vec<f64> = (0..2).map(|_| rng.range(0.0, 100.0)).collect();
+        assert_eq!(tree.nearest(&q, 5).len(), 5);
+    }
+    let per_query = calls.get() / 100;
+    assert!(per_query < 1500, "{per_query} distances per query");
+}
@@ -0,0 +1,65 @@
+//! The nested containment list against a scan of its intervals, built both
+//! from unsorted items and from items already sorted by start.
+
+use std::ops::Range;
+
+use datastructures::nclist::NcList;
+
+mod common;
+use common::{sorted, Rng};
+
+/// A random half-open interval in `0..1500`: a tenth are empty, and a
+/// third of the rest are long enough to nest many others.
+fn random_interval(rng: &mut Rng) -> Range<i64> {
+    let start = rng.below(1000) as i64;
+    let len = match rng.below(30) {
+        0..=2 => 0,
+        3..=11 => rng.below(500),
+        _ => rng.below(20),
+    };
+    start..start + len as i64
+}
+
+/// Overlap and stabbing queries match a scan, including queries that are
+/// empty or fall outside every interval, and `build` and `from_sorted`
+/// agree.
+#[test]
+fn queries_match_brute_force() {
+    let mut rng = Rng(3);
+    for n in [0usize, 1, 5, 100, 3000] {
+        let mut items: Vec<(Range<i64>, usize)> =
+            (0..n).map(|i| (random_interval(&mut rng), i)).collect();
+        let built = NcList::build(items.clone());
+        items.sort_by_key(|x| x.0.start);
+        for (i, x) in items.iter_mut().enumerate() {
+            x.1 = i;
+        }
+        let list = NcList::from_sorted(&items);
+        assert_eq!(built.len(), n);
+        assert_eq!(list.len(), n);
+        for (i, x) in items.iter().enumerate() {
+            assert_eq!(list.get(i), Some((&x.0, &i)));
+        }
+        let all = sorted(built.iter().map(|x| *x.2).collect());
+        assert_eq!(all, (0..n).collect::<Vec<_>>());
+
+        for _ in 0..300 {
+            let s = rng.below(1100) as i64 - 50;
+            let query = s..s + rng.below(60) as i64;
+            let got = sorted(list.overlapping(query.clone()).map(|x| x.0).collect());
+            let want: Vec<usize> = (0..n)
+                .filter(|&i| {
+                    let r = &items[i].0;
+                    r.start < query.end && query.start < r.end && !r.is_empty() && !query.is_empty()
+                })
+                .collect();
+            assert_eq!(got, want);
+            assert_eq!(built.overlapping(query).count(), want.len());
+
+            let got = sorted(list.stabbing(s).map(|x| x.0).collect());
+            let want: Vec<usize> = (0..n).filter(|&i| items[i].0.contains(&s)).collect();
+            assert_eq!(got, want);
+            assert_eq!(list.contains_point(s), !want.is_empty());
+        }
+    }
+}
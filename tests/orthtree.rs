@@ -0,0 +1,251 @@
+//! The N-dimensional orthtree, in one through five dimensions and through
+//! the quadtree and octree aliases, against a plain map of live points,
+//! and replicas compared and synchronised through orthant hashes.
+
+use std::collections::HashMap;
+
+use datastructures::octree::Octree;
+use datastructures::orthtree::{Orthant, Orthtree, OutOfBounds, PointId};
+use datastructures::quadtree::{Quadtree, Rect};
+use datastructures::rtree::Aabb;
+
+mod common;
+use common::{sorted, Rng};
+
+fn distance<const N: usize>(a: &[f64; N], b: &[f64; N]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Random inserts, removals, relocations and queries in `N` dimensions,
+/// checking the structural invariants as it goes.
+fn random_operations<const N: usize>(seed: u64) {
+    let mut rng = Rng(seed);
+    let bounds = Aabb::new([-1.0; N], [2.0; N]);
+    let mut tree: Orthtree<[f64; N], u32, N> = Orthtree::with_limits(bounds, 3, 10);
+    let mut live: HashMap<PointId, [f64; N]> = HashMap::new();
+    for step in 0..3000 {
+        let op = rng.below(10);
+        let p: [f64; N] = std::array::from_fn(|_| rng.range(-1.0, 2.0));
+        if op < 5 || live.is_empty() {
+            live.insert(tree.insert(p, step).unwrap(), p);
+        } else if op < 7 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+            assert!(!tree.contains(id));
+        } else if op < 8 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            let old = tree.relocate(id, p).unwrap();
+            assert_eq!(old, live.insert(id, p));
+        } else {
+            let r = rng.range(0.0, 1.0);
+            let got: Vec<_> = tree.within_radius(p, r).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|(_, q)| distance(&p, q) <= r)
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let query = Aabb::new(p.map(|v| v - r), p.map(|v| v + r * 0.5));
+            let got: Vec<_> = tree.within_box(query).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|(_, q)| query.contains_point(q))
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let k = rng.index(6);
+            let got: Vec<f64> = tree.nearest(p, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = live.values().map(|q| distance(&p, q)).collect();
+            want.sort_by(f64::total_cmp);
+            assert_eq!(tree.nearest_one(p).map(|x| x.1), want.first().copied());
+            want.truncate(k);
+            assert_eq!(got, want);
+        }
+        assert_eq!(tree.len(), live.len());
+        if step % 100 == 0 {
+            tree.check_invariants();
+        }
+    }
+    tree.check_invariants();
+    for (id, p) in &live {
+        assert_eq!(tree.get(*id).map(|x| *x.0), Some(*p));
+    }
+    let outside = [3.0; N];
+    assert_eq!(tree.insert(outside, 0), Err(OutOfBounds));
+    tree.clear();
+    assert!(tree.is_empty());
+    tree.check_invariants();
+}
+
+/// The generic tree agrees with a scan in every dimension up to five,
+/// where a node has 32 children.
+#[test]
+fn random_operations_in_each_dimension() {
+    random_operations::<1>(1);
+    random_operations::<2>(2);
+    random_operations::<3>(3);
+    random_operations::<4>(4);
+    random_operations::<5>(5);
+}
+
+/// The quadtree alias takes tuple points, and queries may use a different
+/// point type from the one stored.
+#[test]
+fn quadtree_alias_with_tuple_points() {
+    let mut tree: Quadtree<(f64, f64), &str> = Quadtree::new(Rect::new([0.0, 0.0], [4.0, 4.0]));
+    let a = tree.insert((1.0, 1.0), "a").unwrap();
+    let b = tree.insert((3.0, 1.0), "b").unwrap();
+    tree.insert((3.0, 3.0), "c").unwrap();
+    assert_eq!(tree.nearest_one([0.0, 0.0]), Some((a, 2f64.sqrt())));
+    assert_eq!(tree.nearest((3.0, 0.0), 1), vec![(b, 1.0)]);
+    *tree.get_mut(b).unwrap().1 = "B";
+    assert_eq!(tree.get(b), Some((&(3.0, 1.0), &"B")));
+    let boxed: Vec<_> = tree.within_box(Rect::new([0.0, 0.0], [4.0, 1.0])).collect();
+    assert_eq!(boxed.len(), 2);
+    assert!(tree.insert((4.5, 0.0), "out").is_err());
+}
+
+/// The octree alias takes `f32` tuples, whose distances are measured in
+/// `f64`, and many copies of one point fill a leaf at the depth limit.
+#[test]
+fn octree_alias_with_f32_points() {
+    let bounds = Aabb::new([0.0; 3], [1.0; 3]);
+    let mut tree: Octree<(f32, f32, f32), u8> = Octree::with_limits(bounds, 2, 4);
+    tree.insert((0.5, 0.5, 0.5), 1).unwrap();
+    let (_, d) = tree.nearest_one([0.0, 0.0, 0.0]).unwrap();
+    assert!((d - 0.75f64.sqrt()).abs() < 1e-12);
+    let copies: Vec<_> = (0..40)
+        .map(|i| tree.insert((0.25, 0.25, 0.25), i).unwrap())
+        .collect();
+    tree.check_invariants();
+    assert_eq!(tree.within_radius([0.25, 0.25, 0.25], 0.0).count(), 40);
+    for id in copies {
+        tree.remove(id).unwrap();
+    }
+    tree.check_invariants();
+    assert_eq!(tree.len(), 1);
+}
+
+fn grid_point(rng: &mut Rng) -> [f64; 3] {
+    std::array::from_fn(|_| rng.below(1000) as f64 / 10.0)
+}
+
+/// Two trees built from the same points in different orders, one with
+/// extra points inserted and removed on the way, hash alike; after one
+/// diverges, the diff covers every change, and copying the diverging
+/// orthants across makes them agree again.
+#[test]
+fn replicas_diff_and_sync() {
+    let mut rng = Rng(5);
+    let bounds = Aabb::new([0.0; 3], [100.0; 3]);
+    for round in 0..10 {
+        let points: Vec<([f64; 3], u32)> = (0..1000).map(|i| (grid_point(&mut rng), i)).collect();
+        let mut a = Octree::with_limits(bounds, 4, 10);
+        let mut b = Octree::with_limits(bounds, 4, 10);
+        let ids: Vec<PointId> = points
+            .iter()
+            .map(|&(p, d)| a.insert(p, d).unwrap())
+            .collect();
+        let extra: Vec<PointId> = (0..300)
+            .map(|_| b.insert(grid_point(&mut rng), 9999).unwrap())
+            .collect();
+        for &(p, d) in points.iter().rev() {
+            b.insert(p, d).unwrap();
+        }
+        for id in extra {
+            b.remove(id).unwrap();
+        }
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert!(a.diff(&mut b).is_empty());
+
+        let mut changed = Vec::new();
+        for j in 0..=round {
+            let id = ids[(j * 97) % ids.len()];
+            match j % 3 {
+                0 => changed.push(a.remove(id).unwrap().0),
+                1 => {
+                    let to = grid_point(&mut rng);
+                    changed.push(a.relocate(id, to).unwrap().unwrap());
+                    changed.push(to);
+                }
+                _ => {
+                    let (p, d) = a.get_mut(id).unwrap();
+                    *d += 1;
+                    changed.push(*p);
+                }
+            }
+        }
+        a.check_invariants();
+        assert_ne!(a.content_hash(), b.content_hash());
+        let diff = a.diff(&mut b);
+        assert!(diff.windows(2).all(|w| w[0] < w[1]));
+        for p in &changed {
+            let covered = diff
+                .iter()
+                .any(|&o| a.orthant_bounds(o).unwrap().contains_point(p));
+            assert!(covered, "{p:?}");
+        }
+
+        for &o in &diff {
+            let theirs: Vec<PointId> = b.orthant_points(o).map(|x| x.0).collect();
+            for id in theirs {
+                b.remove(id).unwrap();
+            }
+            let mine: Vec<([f64; 3], u32)> = a.orthant_points(o).map(|x| (*x.1, *x.2)).collect();
+            for (p, d) in mine {
+                b.insert(p, d).unwrap();
+            }
+        }
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert!(a.diff(&mut b).is_empty());
+
+        // Orthants below a leaf hash their share of its points.
+        let mut o = Orthant::ROOT;
+        for _ in 0..10 {
+            o = o.child(rng.below(8) as u32);
+            assert_eq!(a.orthant_hash(o), b.orthant_hash(o));
+        }
+        assert_eq!(a.orthant_hash(o.child(0)), None);
+        let total: usize = (0..8)
+            .map(|d| a.orthant_points(Orthant::ROOT.child(d)).count())
+            .sum();
+        assert_eq!(total, a.len());
+    }
+}
+
+/// Orthants navigate by child digit and back, and cover the slices of the
+/// bounds their index names.
+#[test]
+fn orthant_navigation() {
+    let bounds = Aabb::new([0.0; 2], [8.0; 2]);
+    let tree: Quadtree<[f64; 2], ()> = Quadtree::with_limits(bounds, 4, 3);
+    assert_eq!(Orthant::<2>::ROOT.parent(), None);
+    let o = Orthant::ROOT.child(0b01).child(0b10);
+    assert_eq!(o.depth, 2);
+    assert_eq!(o.parent(), Some(Orthant::ROOT.child(0b01)));
+    assert_eq!(o.parent().unwrap().parent(), Some(Orthant::ROOT));
+    assert_eq!(tree.orthant_bounds(Orthant::ROOT), Some(bounds));
+    assert_eq!(
+        tree.orthant_bounds(o),
+        Some(Aabb::new([4.0, 2.0], [6.0, 4.0]))
+    );
+    let deepest = o.child(3);
+    assert!(tree.orthant_bounds(deepest).is_some());
+    assert_eq!(tree.orthant_bounds(deepest.child(0)), None);
+}
+
+/// Only trees over the same grid can be compared.
+#[test]
+#[should_panic(expected = "trees must share bounds and limits to be compared")]
+fn diff_of_different_limits_panics() {
+    let bounds = Aabb::new([0.0; 3], [1.0; 3]);
+    let mut a: Octree<[f64; 3], ()> = Octree::with_limits(bounds, 4, 10);
+    let mut b = Octree::with_limits(bounds, 4, 9);
+    a.diff(&mut b);
+}
@@ -0,0 +1,175 @@
+//! The pairing heap against a `BTreeSet` of its items, under random pushes,
+//! pops, decreases, removals through handles and melds.
+
+use std::collections::BTreeSet;
+
+use datastructures::pairing_heap::{Handle, PairingHeap};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Items are `(key, id)` with unique ids, so the set orders them as the
+/// heap must.
+type Item = (u64, u32);
+
+/// Random operations with handles kept for every live item, popping
+/// everything at the end.
+#[test]
+fn operations_match_btreeset() {
+    let mut rng = Rng(8);
+    let mut id = 0;
+    for _ in 0..50 {
+        let mut heap = PairingHeap::new();
+        let mut set: BTreeSet<Item> = BTreeSet::new();
+        let mut handles: Vec<(Handle, Item)> = Vec::new();
+        for _ in 0..2000 {
+            match rng.below(6) {
+                0 | 1 => {
+                    id += 1;
+                    let item = (rng.below(1000), id);
+                    handles.push((heap.push(item), item));
+                    set.insert(item);
+                }
+                2 => {
+                    let popped = heap.pop();
+                    assert_eq!(popped, set.pop_first());
+                    if let Some(item) = popped {
+                        // The popped item's handle is stale, even once its
+                        // slot is reused.
+                        let i = handles.iter().position(|h| h.1 == item).unwrap();
+                        let (handle, _) = handles.swap_remove(i);
+                        assert_eq!(heap.get(handle), None);
+                    }
+                }
+                3 if !handles.is_empty() => {
+                    let i = rng.index(handles.len());
+                    let (handle, item) = handles[i];
+                    assert_eq!(heap.get(handle), Some(&item));
+                    let lower = (item.0.saturating_sub(rng.below(500)), item.1);
+                    heap.decrease_key(handle, lower);
+                    set.remove(&item);
+                    set.insert(lower);
+                    handles[i].1 = lower;
+                }
+                4 if !handles.is_empty() => {
+                    let (handle, item) = handles.swap_remove(rng.index(handles.len()));
+                    assert_eq!(heap.remove(handle), item);
+                    assert!(!heap.contains(handle));
+                    set.remove(&item);
+                }
+                5 => {
+                    let mut other = PairingHeap::new();
+                    for _ in 0..rng.below(5) {
+                        id += 1;
+                        let item = (rng.below(1000), id);
+                        handles.push((other.push(item), item));
+                        set.insert(item);
+                    }
+                    if rng.below(2) == 0 {
+                        heap.meld(other);
+                    } else {
+                        other.meld(heap);
+                        heap = other;
+                    }
+                }
+                _ => {}
+            }
+            assert_eq!(heap.len(), set.len());
+            assert_eq!(heap.peek(), set.first());
+            assert_eq!(heap.peek_handle().and_then(|h| heap.get(h)), set.first());
+        }
+        let items: Vec<Item> = heap.iter().copied().collect();
+        assert_eq!(sorted(items), set.iter().copied().collect::<Vec<_>>());
+        let popped: Vec<Item> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, set.into_iter().collect::<Vec<_>>());
+        assert!(heap.is_empty());
+    }
+}
+
+/// Handles into both heaps stay valid across a meld, whichever is larger,
+/// and `clear` empties the heap.
+#[test]
+fn handles_survive_meld() {
+    let mut heap = PairingHeap::new();
+    let handles: Vec<Handle> = (0..10).map(|i| heap.push(i * 10)).collect();
+    let mut other = PairingHeap::new();
+    let theirs: Vec<Handle> = [5, 15, 25].map(|x| other.push(x)).to_vec();
+    other.meld(heap);
+    let heap = other;
+    assert_eq!(heap.len(), 13);
+    assert_eq!(heap.get(theirs[2]), Some(&25));
+    let mut smaller = PairingHeap::new();
+    let late = smaller.push(7);
+    smaller.meld(heap);
+    let mut heap = smaller;
+    heap.decrease_key(theirs[1], 6);
+    assert_eq!(heap.remove(late), 7);
+    assert_eq!(heap.remove(theirs[1]), 6);
+    heap.decrease_key(handles[9], 1);
+    assert_eq!(heap.remove(handles[0]), 0);
+    assert_eq!(heap.pop(), Some(1));
+    assert_eq!(heap.pop(), Some(5));
+    assert_eq!(heap.get(handles[5]), Some(&50));
+    heap.clear();
+    assert!(heap.is_empty());
+    assert_eq!(heap.peek(), None);
+    assert_eq!(heap.get(handles[5]), None);
+}
+
+/// A handle from a popped item does not reach the item pushed into its
+/// slot, nor does a handle from another heap or from before a clone.
+#[test]
+fn stale_and_foreign_handles() {
+    let mut heap = PairingHeap::new();
+    let old = heap.push(1);
+    assert_eq!(heap.pop(), Some(1));
+    let new = heap.push(2);
+    assert_eq!((heap.get(old), heap.get(new)), (None, Some(&2)));
+    assert!(!heap.contains(old));
+    let mut other = PairingHeap::new();
+    other.push(3);
+    assert_eq!(other.get(new), None);
+    let copy = heap.clone();
+    assert_eq!(copy.get(new), None);
+    assert_eq!(copy.peek(), Some(&2));
+    heap.meld(copy);
+    assert_eq!(heap.get(new), Some(&2));
+    assert_eq!(heap.len(), 2);
+}
+
+/// Melding a growing heap into a fresh one-item heap, over and over, moves
+/// each item a logarithmic number of times rather than at every meld.
+#[test]
+fn chained_melds_into_small_heaps() {
+    let mut all = PairingHeap::new();
+    let mut handles = Vec::new();
+    for i in 0..100_000 {
+        let mut one = PairingHeap::new();
+        handles.push(one.push(i));
+        one.meld(all);
+        all = one;
+    }
+    assert_eq!(all.len(), 100_000);
+    all.decrease_key(handles[77_777], -1);
+    assert_eq!(all.pop(), Some(-1));
+    assert_eq!(all.get(handles[5]), Some(&5));
+}
+
+/// Decreasing to a greater item would break the heap order.
+#[test]
+#[should_panic(expected = "new item is greater than the current one")]
+fn increase_key_panics() {
+    let mut heap = PairingHeap::new();
+    let h = heap.push(5);
+    heap.decrease_key(h, 6);
+}
+
+/// Handles of removed items are rejected.
+#[test]
+#[should_panic(expected = "handle refers to a removed item")]
+fn removed_handle_panics() {
+    let mut heap = PairingHeap::new();
+    let h = heap.push(5);
+    heap.pop();
+    heap.remove(h);
+}
@@ -0,0 +1,121 @@
+//! The persistent segment tree against a list of every version's elements,
+//! with updates branching from random earlier versions, and the range
+//! quantile structure built on it against sorting each range.
+
+use datastructures::algebra::{Min, Monoid, Sum};
+use datastructures::persistent_segment_tree::{PersistentSegmentTree, RangeQuantiles};
+
+mod common;
+use common::Rng;
+
+/// Concatenation, which catches aggregates combined out of order.
+#[derive(Clone, Default)]
+struct Concat;
+
+impl Monoid for Concat {
+    type Value = Vec<u8>;
+
+    fn identity(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn combine(&self, a: &Vec<u8>, b: &Vec<u8>) -> Vec<u8> {
+        [a.as_slice(), b].concat()
+    }
+}
+
+/// A random range `a..b` with `0 <= a <= b <= n`.
+fn random_range(rng: &mut Rng, n: usize) -> (usize, usize) {
+    let a = rng.index(n + 1);
+    (a, a + rng.index(n - a + 1))
+}
+
+/// Each update makes a new version from a random old one, and every
+/// version keeps answering for its own elements.
+#[test]
+fn versions_match_history() {
+    let mut rng = Rng(3);
+    for n in [0, 1, 2, 7, 64, 100] {
+        let mut history: Vec<Vec<u8>> = vec![(0..n as u8).collect()];
+        let mut concat =
+            PersistentSegmentTree::new(Concat, history[0].iter().map(|&x| vec![x]).collect());
+        let mut mins = PersistentSegmentTree::with_len(Min::<i64>::new(), n);
+        for i in 0..n {
+            let latest = mins.latest();
+            mins.set(latest, i, i as i64);
+        }
+        let offset = mins.latest();
+        assert_eq!((concat.len(), concat.is_empty()), (n, n == 0));
+        for _ in 0..800 {
+            if n > 0 {
+                let (v, i, x) = (rng.index(history.len()), rng.index(n), rng.below(256) as u8);
+                let mut next = history[v].clone();
+                next[i] = x;
+                history.push(next);
+                assert_eq!(concat.set(v, i, vec![x]), history.len() - 1);
+                mins.set(v + offset, i, i64::from(x));
+            }
+            assert_eq!(concat.versions(), history.len());
+            assert_eq!(concat.latest(), history.len() - 1);
+            let v = rng.index(history.len());
+            let elements = &history[v];
+            let (lo, hi) = random_range(&mut rng, n);
+            assert_eq!(concat.query(v, lo..hi), elements[lo..hi]);
+            let least = elements[lo..hi].iter().map(|&x| i64::from(x)).min();
+            assert_eq!(mins.query(v + offset, lo..hi), least.unwrap_or(i64::MAX));
+            assert_eq!(concat.total(v), *elements);
+            let flat: Vec<u8> = concat.to_vec(v).concat();
+            assert_eq!(flat, *elements);
+            if n > 0 {
+                let i = rng.index(n);
+                assert_eq!(concat.get(v, i), [elements[i]]);
+            }
+        }
+    }
+    let sums: PersistentSegmentTree<Sum<u64>> = (1..=10).collect();
+    assert_eq!(sums.total(0), 55);
+    assert_eq!(sums.monoid().identity(), 0);
+}
+
+/// Order statistics and value counts over random ranges, with duplicates
+/// and values outside the stored ones.
+#[test]
+fn quantiles_match_sorting() {
+    let mut rng = Rng(9);
+    for n in [0, 1, 5, 50, 500] {
+        let values: Vec<i32> = (0..n).map(|_| rng.below(40) as i32 - 20).collect();
+        let quantiles = RangeQuantiles::new(&values);
+        assert_eq!((quantiles.len(), quantiles.is_empty()), (n, n == 0));
+        for _ in 0..2000 {
+            let (lo, hi) = random_range(&mut rng, n);
+            let mut range = values[lo..hi].to_vec();
+            range.sort_unstable();
+            let k = rng.index(range.len() + 2);
+            assert_eq!(quantiles.quantile(lo..hi, k), range.get(k));
+            assert_eq!(quantiles.min(lo..hi), range.first());
+            let middle = range.len().saturating_sub(1) / 2;
+            assert_eq!(quantiles.median(lo..hi), range.get(middle));
+            let (a, b) = (rng.below(50) as i32 - 25, rng.below(50) as i32 - 25);
+            let count = |keep: &dyn Fn(i32) -> bool| range.iter().filter(|&&x| keep(x)).count();
+            let half_open = count(&|x| a <= x && x < b);
+            assert_eq!(quantiles.count(lo..hi, a..b), half_open);
+            assert_eq!(quantiles.count(lo..hi, a..=b), count(&|x| a <= x && x <= b));
+            assert_eq!(quantiles.count(lo..hi, a..), count(&|x| a <= x));
+            assert_eq!(quantiles.count(lo..hi, ..), range.len());
+        }
+    }
+}
+
+/// Versions are numbered below the version count.
+#[test]
+#[should_panic(expected = "version 1 out of bounds for 1 versions")]
+fn missing_version_panics() {
+    PersistentSegmentTree::new(Sum::<u64>::new(), vec![1, 2]).query(1, ..);
+}
+
+/// Updates must lie within the sequence.
+#[test]
+#[should_panic(expected = "index 2 out of bounds for length 2")]
+fn set_out_of_bounds_panics() {
+    PersistentSegmentTree::new(Sum::<u64>::new(), vec![1, 2]).set(0, 2, 5);
+}
@@ -0,0 +1,95 @@
+//! The PH-tree against a `BTreeMap` of the same keys, in several
+//! dimensions and key densities.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use datastructures::phtree::{f64_to_key, key_to_f64, PhTree};
+
+mod common;
+use common::Rng;
+
+/// Orders keys by their bit-interleaved (Morton) code, the order window
+/// queries report in.
+fn morton_order<const N: usize>(a: &[u64; N], b: &[u64; N]) -> Ordering {
+    for bit in (0..64).rev() {
+        for d in (0..N).rev() {
+            let (x, y) = ((a[d] >> bit) & 1, (b[d] >> bit) & 1);
+            if x != y {
+                return x.cmp(&y);
+            }
+        }
+    }
+    Ordering::Equal
+}
+
+/// Random inserts, removals and window queries, with coordinates below
+/// `spread`, or spread over all 64 bits if it is zero.
+fn random_operations<const N: usize>(seed: u64, spread: u64) {
+    let mut rng = Rng(seed);
+    let mut tree: PhTree<u32, N> = PhTree::new();
+    let mut model: BTreeMap<[u64; N], u32> = BTreeMap::new();
+    let key = |rng: &mut Rng| -> [u64; N] {
+        std::array::from_fn(|_| {
+            if spread == 0 {
+                rng.next_u64()
+            } else {
+                rng.below(spread)
+            }
+        })
+    };
+    for step in 0..4000u32 {
+        let k = key(&mut rng);
+        match rng.below(5) {
+            0..=2 => assert_eq!(tree.insert(k, step), model.insert(k, step)),
+            3 => {
+                let k = if !model.is_empty() && rng.below(2) == 0 {
+                    *model.keys().nth(rng.index(model.len())).unwrap()
+                } else {
+                    k
+                };
+                assert_eq!(tree.remove(&k), model.remove(&k));
+            }
+            _ => {
+                let other = key(&mut rng);
+                let min: [u64; N] = std::array::from_fn(|a| k[a].min(other[a]));
+                let max: [u64; N] = std::array::from_fn(|a| k[a].max(other[a]));
+                let got: Vec<_> = tree.window(min, max).map(|(k, v)| (*k, *v)).collect();
+                let mut want: Vec<_> = model
+                    .iter()
+                    .filter(|(k, _)| (0..N).all(|a| min[a] <= k[a] && k[a] <= max[a]))
+                    .map(|(k, v)| (*k, *v))
+                    .collect();
+                want.sort_by(|a, b| morton_order(&a.0, &b.0));
+                assert_eq!(got, want);
+            }
+        }
+        assert_eq!(tree.len(), model.len());
+        assert_eq!(tree.get(&k), model.get(&k));
+    }
+    assert_eq!(tree.iter().count(), model.len());
+}
+
+/// The tree agrees with the map in one to six dimensions, for dense keys
+/// that share long prefixes and sparse keys that share almost none.
+#[test]
+fn random_operations_match_btree_map() {
+    random_operations::<1>(1, 1000);
+    random_operations::<2>(2, 64);
+    random_operations::<3>(3, 0);
+    random_operations::<6>(4, 8);
+    random_operations::<2>(5, 0);
+}
+
+/// The float key encoding preserves order, signed zeros and infinities
+/// included, and round-trips exactly.
+#[test]
+fn float_keys_preserve_order() {
+    let xs = [-f64::INFINITY, -3.5, -0.0, 0.0, 1e-300, 2.0, f64::INFINITY];
+    for w in xs.windows(2) {
+        assert!(f64_to_key(w[0]) < f64_to_key(w[1]));
+    }
+    for x in xs {
+        assert_eq!(key_to_f64(f64_to_key(x)).to_bits(), x.to_bits());
+    }
+}
@@ -0,0 +1,109 @@
+//! The piece table against a `String` and a list of its past contents,
+//! under random edits, undos and redos, and conversions to and from a rope.
+
+use datastructures::piece_table::PieceTable;
+use datastructures::rope::Rope;
+
+mod common;
+use common::Rng;
+
+/// A random char boundary of `s`.
+fn boundary(rng: &mut Rng, s: &str) -> usize {
+    let ends: Vec<usize> = s.char_indices().map(|x| x.0).chain([s.len()]).collect();
+    ends[rng.index(ends.len())]
+}
+
+fn random_range(rng: &mut Rng, s: &str) -> (usize, usize) {
+    let (a, b) = (boundary(rng, s), boundary(rng, s));
+    (a.min(b), a.max(b))
+}
+
+/// Every non-empty edit is one undo step; undo and redo walk the list of
+/// past contents, and a new edit discards the contents after the current
+/// one.
+#[test]
+fn edits_and_history_match_string() {
+    let mut rng = Rng(7);
+    for round in 0..200 {
+        let initial = if round % 2 == 0 {
+            "héllo wörld\nabc"
+        } else {
+            ""
+        };
+        let mut table = PieceTable::from(initial);
+        let mut history = vec![initial.to_string()];
+        let mut now = 0;
+        for _ in 0..200 {
+            let mut s = history[now].clone();
+            let edited = match rng.below(10) {
+                0..=3 => {
+                    let at = boundary(&mut rng, &s);
+                    let texts = ["a", "é", "xyz\n", "ñö", ""];
+                    let text = texts[rng.index(texts.len())];
+                    table.insert(at, text);
+                    s.insert_str(at, text);
+                    Some(s)
+                }
+                4 | 5 => {
+                    let (a, b) = random_range(&mut rng, &s);
+                    table.remove(a..b);
+                    s.replace_range(a..b, "");
+                    Some(s)
+                }
+                6 => {
+                    assert_eq!(table.undo(), now > 0);
+                    now = now.saturating_sub(1);
+                    None
+                }
+                7 => {
+                    assert_eq!(table.redo(), now + 1 < history.len());
+                    now = (now + 1).min(history.len() - 1);
+                    None
+                }
+                _ => {
+                    let (a, b) = random_range(&mut rng, &s);
+                    assert_eq!(table.slice(a..b), &s[a..b]);
+                    None
+                }
+            };
+            if let Some(s) = edited.filter(|s| *s != history[now]) {
+                history.truncate(now + 1);
+                history.push(s);
+                now += 1;
+            }
+            let s = &history[now];
+            assert_eq!(table.to_string(), *s);
+            assert_eq!(table.len(), s.len());
+            assert_eq!(table.is_empty(), s.is_empty());
+            assert_eq!(table.chunks().count(), table.piece_count());
+        }
+        let s = &history[now];
+        let rope = Rope::from(&table);
+        assert_eq!(rope.to_string(), *s);
+        assert_eq!(PieceTable::from(&rope).to_string(), *s);
+    }
+}
+
+/// Typing at the end of the last insertion extends one piece, while each
+/// keystroke stays its own undo step.
+#[test]
+fn typing_extends_one_piece() {
+    let mut table = PieceTable::from("ab");
+    for (i, c) in "hello".char_indices() {
+        table.insert(1 + i, &c.to_string());
+    }
+    assert_eq!(table.to_string(), "ahellob");
+    assert_eq!(table.piece_count(), 3);
+    assert!(table.undo());
+    assert_eq!(table.to_string(), "ahellb");
+    assert!(table.redo());
+    assert!(!table.redo());
+    assert_eq!(table.to_string(), "ahellob");
+}
+
+/// Removing inside a multi-byte char is rejected.
+#[test]
+#[should_panic(expected = "not on a char boundary")]
+fn remove_off_a_char_boundary_panics() {
+    PieceTable::from("é").remove(0..1);
+}
@@ -0,0 +1,84 @@
+//! The quadtree against a plain map of live points, under random inserts,
+//! removals, relocations and queries.
+
+use std::collections::HashMap;
+
+use datastructures::quadtree::{PointId, Quadtree, Rect};
+
+mod common;
+use common::{sorted, Rng};
+
+fn distance(p: &[f64; 2], q: &[f64; 2]) -> f64 {
+    ((p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2)).sqrt()
+}
+
+/// Radius, box and k-nearest queries agree with a brute-force scan.
+#[test]
+fn random_operations_match_brute_force() {
+    let mut rng = Rng(1);
+    let bounds = Rect::new([-10.0, -5.0], [10.0, 5.0]);
+    let mut tree: Quadtree<[f64; 2], usize> = Quadtree::with_limits(bounds, 4, 12);
+    let mut live: HashMap<PointId, [f64; 2]> = HashMap::new();
+    for step in 0..5000 {
+        let op = rng.below(10);
+        if op < 5 || live.is_empty() {
+            let p = [rng.range(-10.0, 10.0), rng.range(-5.0, 5.0)];
+            let id = tree.insert(p, step).unwrap();
+            assert!(live.insert(id, p).is_none());
+        } else if op < 7 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            let (p, _) = tree.remove(id).unwrap();
+            assert_eq!(live.remove(&id).unwrap(), p);
+        } else if op < 8 {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            let p = [rng.range(-10.0, 10.0), rng.range(-5.0, 5.0)];
+            tree.relocate(id, p).unwrap().unwrap();
+            live.insert(id, p);
+        } else {
+            let q = [rng.range(-12.0, 12.0), rng.range(-6.0, 6.0)];
+            let r = rng.range(0.0, 3.0);
+            let got: Vec<_> = tree.within_radius(q, r).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|(_, p)| distance(p, &q) <= r)
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let rect = Rect::new([q[0] - r, q[1] - r * 0.5], [q[0] + r * 0.7, q[1] + r]);
+            let got: Vec<_> = tree.within_box(rect).map(|x| x.0).collect();
+            let want: Vec<_> = live
+                .iter()
+                .filter(|(_, p)| rect.contains_point(p))
+                .map(|x| *x.0)
+                .collect();
+            assert_eq!(sorted(got), sorted(want));
+
+            let k = rng.index(8);
+            let got: Vec<f64> = tree.nearest(q, k).iter().map(|x| x.1).collect();
+            let mut want: Vec<f64> = live.values().map(|p| distance(p, &q)).collect();
+            want.sort_by(f64::total_cmp);
+            want.truncate(k);
+            assert_eq!(got, want);
+        }
+        assert_eq!(tree.len(), live.len());
+    }
+    assert!(tree.insert([11.0, 0.0], 0).is_err());
+    assert_eq!(tree.iter().count(), live.len());
+}
+
+/// Many copies of one point sit in a single leaf at the depth limit.
+#[test]
+fn duplicate_points() {
+    let bounds = Rect::new([0.0, 0.0], [1.0, 1.0]);
+    let mut tree: Quadtree<[f64; 2], ()> = Quadtree::with_limits(bounds, 2, 6);
+    let ids: Vec<_> = (0..50)
+        .map(|_| tree.insert([1.0, 1.0], ()).unwrap())
+        .collect();
+    assert_eq!(tree.nearest([0.0, 0.0], 3).len(), 3);
+    for id in ids {
+        tree.remove(id).unwrap();
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.within_box(bounds).count(), 0);
+}
@@ -0,0 +1,115 @@
+//! The radix trie against `std::collections::BTreeMap`, over short keys
+//! from a small alphabet so that prefixes are shared heavily, and its fuzzy
+//! search against edit distances computed directly.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use datastructures::radix_trie::RadixTrie;
+
+mod common;
+use common::Rng;
+
+/// A key of up to six letters from `alphabet`.
+fn random_key(rng: &mut Rng, alphabet: &[u8]) -> Vec<u8> {
+    let len = rng.index(7);
+    (0..len)
+        .map(|_| alphabet[rng.index(alphabet.len())])
+        .collect()
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &x) in a.iter().enumerate() {
+        let mut next = vec![i + 1];
+        for (j, &y) in b.iter().enumerate() {
+            let cost = row[j] + usize::from(x != y);
+            next.push(cost.min(row[j + 1] + 1).min(next[j] + 1));
+        }
+        row = next;
+    }
+    row[b.len()]
+}
+
+/// Random inserts and removals match the map, as do iteration, prefix
+/// iteration and longest-prefix matching; compression keeps the node count
+/// linear, and removing everything leaves only the root.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(13);
+    let mut trie = RadixTrie::new();
+    let mut map: BTreeMap<Vec<u8>, u32> = BTreeMap::new();
+    for step in 0..40000u32 {
+        let k = random_key(&mut rng, b"abc");
+        if rng.below(3) == 0 {
+            assert_eq!(trie.remove(&k), map.remove(&k));
+        } else {
+            assert_eq!(trie.insert(&k, step), map.insert(k.clone(), step));
+        }
+        assert_eq!(trie.get(&k), map.get(&k));
+        assert_eq!(trie.len(), map.len());
+        if step % 500 != 0 {
+            continue;
+        }
+        let entries = |m: &BTreeMap<Vec<u8>, u32>| -> Vec<(Vec<u8>, u32)> {
+            m.iter().map(|(k, v)| (k.clone(), *v)).collect()
+        };
+        let got: Vec<_> = trie.iter().map(|(k, v)| (k, *v)).collect();
+        assert_eq!(got, entries(&map));
+
+        let prefix = random_key(&mut rng, b"abc");
+        let got: Vec<_> = trie.iter_prefix(&prefix).map(|(k, v)| (k, *v)).collect();
+        let want: Vec<_> = entries(&map)
+            .into_iter()
+            .filter(|(k, _)| k.starts_with(&prefix))
+            .collect();
+        assert_eq!(got, want, "prefix {prefix:?}");
+
+        let q = random_key(&mut rng, b"abc");
+        let got = trie.longest_prefix_match(&q).map(|(k, v)| (k.to_vec(), *v));
+        let want = entries(&map)
+            .into_iter()
+            .filter(|(k, _)| q.starts_with(k))
+            .max_by_key(|(k, _)| k.len());
+        assert_eq!(got, want);
+        assert!(trie.node_count() <= 2 * map.len() + 1);
+    }
+    let keys: Vec<_> = map.keys().cloned().collect();
+    for k in keys {
+        assert!(trie.remove(&k).is_some());
+    }
+    assert!(trie.is_empty());
+    assert_eq!(trie.node_count(), 1);
+}
+
+/// Fuzzy search finds exactly the keys within each edit distance of the
+/// query, in key order, with their distances.
+#[test]
+fn fuzzy_search_matches_brute_force() {
+    let mut rng = Rng(17);
+    let mut trie = RadixTrie::new();
+    let mut keys = BTreeSet::new();
+    for i in 0..3000u32 {
+        let k = random_key(&mut rng, b"abcd");
+        trie.insert(&k, i);
+        keys.insert(k);
+    }
+    for _ in 0..300 {
+        let q = random_key(&mut rng, b"abcd");
+        for max in 0..4 {
+            let want: Vec<_> = keys
+                .iter()
+                .map(|k| (k.clone(), edit_distance(k, &q)))
+                .filter(|x| x.1 <= max)
+                .collect();
+            let got: Vec<_> = trie
+                .search_fuzzy(&q, max)
+                .map(|(k, v, d)| {
+                    assert_eq!(trie.get(&k), Some(v));
+                    (k, d)
+                })
+                .collect();
+            assert_eq!(got, want);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The ring buffer against `std::collections::VecDeque`, at capacities
+//! from zero up, with the contents checked as slices and through
+//! iterators consumed from both ends.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+
+use datastructures::ring_buffer::RingBuffer;
+
+mod common;
+use common::Rng;
+
+/// Compares the contents every way the buffer exposes them.
+fn check<const N: usize>(ring: &mut RingBuffer<u64, N>, model: &VecDeque<u64>, rng: &mut Rng) {
+    let want: Vec<u64> = model.iter().copied().collect();
+    assert_eq!(ring.len(), model.len());
+    assert_eq!(ring.is_empty(), model.is_empty());
+    assert_eq!(ring.is_full(), model.len() == N);
+    assert_eq!(ring.front(), model.front());
+    assert_eq!(ring.back(), model.back());
+    let (a, b) = ring.as_slices();
+    assert_eq!([a, b].concat(), want);
+    assert_eq!(ring.iter().len(), want.len());
+    assert!(ring.iter().rev().eq(model.iter().rev()));
+    for (i, x) in want.iter().enumerate() {
+        assert_eq!(ring.get(i), Some(x));
+    }
+    assert_eq!(ring.get(want.len()), None);
+    let (c, d) = ring.as_mut_slices();
+    assert_eq!(c.len() + d.len(), want.len());
+    let (mut got, mut expected) = (ring.iter(), model.iter());
+    loop {
+        let (x, y) = if rng.below(2) == 0 {
+            (got.next(), expected.next())
+        } else {
+            (got.next_back(), expected.next_back())
+        };
+        assert_eq!(x, y);
+        if x.is_none() {
+            break;
+        }
+    }
+}
+
+/// Random pushes of both kinds, pops from both ends, in-place edits and
+/// clears.
+fn matches_vecdeque<const N: usize>(rng: &mut Rng) {
+    let mut ring: RingBuffer<u64, N> = RingBuffer::new();
+    let mut model = VecDeque::new();
+    for step in 0..5000u64 {
+        match rng.below(7) {
+            0 => {
+                let want = if model.len() == N {
+                    Err(step)
+                } else {
+                    model.push_back(step);
+                    Ok(())
+                };
+                assert_eq!(ring.try_push(step), want);
+            }
+            1 | 2 => {
+                let want = if N == 0 {
+                    Some(step)
+                } else {
+                    let oldest = if model.len() == N {
+                        model.pop_front()
+                    } else {
+                        None
+                    };
+                    model.push_back(step);
+                    oldest
+                };
+                assert_eq!(ring.push_overwrite(step), want);
+            }
+            3 => assert_eq!(ring.pop_front(), model.pop_front()),
+            4 => assert_eq!(ring.pop_back(), model.pop_back()),
+            5 => {
+                assert_eq!(*ring.make_contiguous(), *model.make_contiguous());
+                if !model.is_empty() {
+                    let i = rng.index(model.len());
+                    *ring.get_mut(i).unwrap() += 1;
+                    model[i] += 1;
+                }
+            }
+            _ => {
+                if rng.below(20) == 0 {
+                    ring.clear();
+                    model.clear();
+                }
+            }
+        }
+        check(&mut ring, &model, rng);
+    }
+}
+
+/// The buffer matches a deque capped at its capacity, including the
+/// degenerate zero-capacity buffer that holds nothing.
+#[test]
+fn operations_match_vecdeque() {
+    let mut rng = Rng(4);
+    matches_vecdeque::<0>(&mut rng);
+    matches_vecdeque::<1>(&mut rng);
+    matches_vecdeque::<7>(&mut rng);
+    matches_vecdeque::<64>(&mut rng);
+}
+
+/// Collecting and extending overwrite the oldest items once full.
+#[test]
+fn collect_keeps_the_newest() {
+    let ring: RingBuffer<u8, 3> = (0..10).collect();
+    assert_eq!(format!("{ring:?}"), "[7, 8, 9]");
+    let mut ring: RingBuffer<u8, 4> = RingBuffer::default();
+    ring.extend([1, 2]);
+    ring.extend([3, 4, 5]);
+    assert!(ring.iter().eq(&[2, 3, 4, 5]));
+    let copy = ring.clone();
+    ring.clear();
+    assert_eq!(copy.len(), 4);
+    assert_eq!(ring.get_mut(0), None);
+}
+
+/// An item without a `Default`, counting its drops.
+struct Counted<'a>(u32, &'a Cell<usize>);
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.1.set(self.1.get() + 1);
+    }
+}
+
+/// Items need no `Default`, and every item is dropped exactly once: when
+/// popped, overwritten, cleared or dropped with its buffer, wherever the
+/// live range wraps to.
+#[test]
+fn items_are_dropped_once() {
+    let drops = Cell::new(0);
+    let mut ring: RingBuffer<Counted, 4> = RingBuffer::new();
+    for i in 0..6 {
+        ring.push_overwrite(Counted(i, &drops));
+    }
+    assert_eq!(drops.get(), 2);
+    assert!(ring.iter().map(|c| c.0).eq(2..6));
+    assert_eq!(ring.pop_front().map(|c| c.0), Some(2));
+    assert_eq!(ring.pop_back().map(|c| c.0), Some(5));
+    assert_eq!(drops.get(), 4);
+    assert!(ring.try_push(Counted(6, &drops)).is_ok());
+    assert!(ring.iter().map(|c| c.0).eq([3, 4, 6]));
+    assert!(!ring.as_slices().1.is_empty());
+    drop(ring);
+    assert_eq!(drops.get(), 7);
+
+    let mut ring: RingBuffer<Counted, 3> = (0..5).map(|i| Counted(i, &drops)).collect();
+    assert_eq!(drops.get(), 9);
+    ring.clear();
+    assert_eq!(drops.get(), 12);
+    ring.extend((0..2).map(|i| Counted(i, &drops)));
+    drop(ring);
+    assert_eq!(drops.get(), 14);
+
+    let strings: RingBuffer<String, 2> = ["a", "b", "c"].map(String::from).into_iter().collect();
+    let copy = strings.clone();
+    drop(strings);
+    assert_eq!(format!("{copy:?}"), r#"["b", "c"]"#);
+}
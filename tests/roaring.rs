@@ -0,0 +1,97 @@
+//! The roaring bitmap against a `BTreeSet<u32>`, with chunks dense enough
+//! to switch between array and bitmap containers both ways.
+
+use std::collections::BTreeSet;
+
+use datastructures::roaring::RoaringBitmap;
+
+mod common;
+use common::Rng;
+
+/// Rank and select of `bitmap` at random points match those of `keys`.
+fn check_rank_select(rng: &mut Rng, bitmap: &RoaringBitmap, keys: &[u32]) {
+    for _ in 0..200 {
+        let k = rng.below(7 << 16) as u32;
+        assert_eq!(bitmap.rank(k), keys.partition_point(|&x| x < k) as u64);
+        assert_eq!(bitmap.contains(k), keys.binary_search(&k).is_ok());
+        let n = rng.index(keys.len() + 2);
+        assert_eq!(bitmap.select(n as u64), keys.get(n).copied());
+    }
+}
+
+/// Up to three chunks of 65,536 keys, each holding from ten to 60,000 of
+/// them, inserted into a bitmap and a set alike.
+fn random_bitmap(rng: &mut Rng) -> (RoaringBitmap, BTreeSet<u32>) {
+    let mut bitmap = RoaringBitmap::new();
+    let mut set = BTreeSet::new();
+    for _ in 0..rng.below(4) {
+        let high = rng.below(6) as u32;
+        let count = [10, 3000, 5000, 60000][rng.index(4)];
+        for _ in 0..count {
+            let k = high << 16 | rng.below(1 << 16) as u32;
+            assert_eq!(bitmap.insert(k), set.insert(k));
+        }
+    }
+    (bitmap, set)
+}
+
+/// Set operations, rank and select, and removals that empty most chunks,
+/// after which the bitmap equals one built from the remaining keys and
+/// still ranks and selects like it.
+#[test]
+fn operations_match_btreeset() {
+    let mut rng = Rng(5);
+    for _ in 0..20 {
+        let (mut a, mut sa) = random_bitmap(&mut rng);
+        let (b, sb) = random_bitmap(&mut rng);
+        assert_eq!(a.len(), sa.len() as u64);
+        assert_eq!(a.is_empty(), sa.is_empty());
+        assert!(a.iter().eq(sa.iter().copied()));
+        assert_eq!(a.min(), sa.first().copied());
+        assert_eq!(a.max(), sa.last().copied());
+
+        let union = a.union(&b);
+        assert!(union.iter().eq(sa.union(&sb).copied()));
+        let intersection = a.intersection(&b);
+        assert!(intersection.iter().eq(sa.intersection(&sb).copied()));
+        assert_eq!(a.intersection_len(&b), intersection.len());
+        let difference = a.difference(&b);
+        assert!(difference.iter().eq(sa.difference(&sb).copied()));
+
+        let keys: Vec<u32> = sa.iter().copied().collect();
+        check_rank_select(&mut rng, &a, &keys);
+        let keys: Vec<u32> = sa.union(&sb).copied().collect();
+        check_rank_select(&mut rng, &union, &keys);
+
+        for _ in 0..70000 {
+            let k = rng.below(7 << 16) as u32;
+            assert_eq!(a.remove(k), sa.remove(&k));
+        }
+        assert!(a.iter().eq(sa.iter().copied()));
+        assert_eq!(a, sa.iter().copied().collect::<RoaringBitmap>());
+        let keys: Vec<u32> = sa.iter().copied().collect();
+        check_rank_select(&mut rng, &a, &keys);
+        assert_eq!(a.len(), sa.len() as u64);
+    }
+}
+
+/// The smallest and largest keys, in chunks at both ends of the range.
+#[test]
+fn extreme_keys() {
+    let mut bitmap = RoaringBitmap::new();
+    bitmap.extend([u32::MAX, 0]);
+    assert_eq!(bitmap.rank(u32::MAX), 1);
+    assert_eq!(bitmap.rank(0), 0);
+    assert_eq!(bitmap.rank(1), 1);
+    assert_eq!(bitmap.select(1), Some(u32::MAX));
+    assert_eq!(bitmap.max(), Some(u32::MAX));
+    assert_eq!((&bitmap).into_iter().collect::<Vec<_>>(), [0, u32::MAX]);
+    assert!(bitmap.remove(0) && bitmap.remove(u32::MAX));
+    assert!(bitmap.is_empty());
+    assert_eq!(bitmap, RoaringBitmap::new());
+
+    let mut full: RoaringBitmap = (0..1 << 16).collect();
+    assert_eq!(full.len(), 1 << 16);
+    full.clear();
+    assert_eq!(full.min(), None);
+}
@@ -0,0 +1,162 @@
+//! The rope against a `String` under random edits of mixed-width text, for
+//! slices, position conversions, lines and tree height.
+
+use datastructures::rope::Rope;
+
+mod common;
+use common::Rng;
+
+/// Mostly short runs of one- to four-byte chars and line breaks, with the
+/// occasional run of several kilobytes.
+fn random_text(rng: &mut Rng) -> String {
+    let len = match rng.below(10) {
+        0 => rng.below(5000),
+        1..=3 => rng.below(50),
+        _ => rng.below(4),
+    };
+    let chars = ['a', 'b', '\n', 'é', '😀', 'z'];
+    (0..len).map(|_| chars[rng.index(chars.len())]).collect()
+}
+
+/// A random char boundary of `s`.
+fn boundary(rng: &mut Rng, s: &str) -> usize {
+    let mut at = rng.index(s.len() + 1);
+    while !s.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+fn random_range(rng: &mut Rng, s: &str) -> (usize, usize) {
+    let (a, b) = (boundary(rng, s), boundary(rng, s));
+    (a.min(b), a.max(b))
+}
+
+/// Compares the whole text, its totals and height, then random slices and
+/// the char and line positions around their starts.
+fn check(rope: &Rope, s: &str, rng: &mut Rng) {
+    assert_eq!(rope.to_string(), s);
+    assert_eq!(rope.len_chars(), s.chars().count());
+    assert_eq!(rope.len_lines(), s.matches('\n').count() + 1);
+    assert_eq!(rope.is_empty(), s.is_empty());
+    let leaves = rope.chunks().count().max(1) as f64;
+    assert!(rope.height() as f64 <= 1.45 * leaves.log2() + 2.0);
+    for _ in 0..20 {
+        let (a, b) = random_range(rng, s);
+        assert_eq!(rope.slice(a..b), &s[a..b]);
+        let chars = s[..a].chars().count();
+        assert_eq!(rope.byte_to_char(a), chars);
+        assert_eq!(rope.char_to_byte(chars), a);
+        let inside = rng.index(s.len() + 1);
+        let containing = s.char_indices().filter(|&(i, _)| i <= inside).count();
+        let want = if inside == s.len() {
+            containing
+        } else {
+            containing - 1
+        };
+        assert_eq!(rope.byte_to_char(inside), want);
+        let line = s[..a].matches('\n').count();
+        assert_eq!(rope.byte_to_line(a), line);
+        let start = s[..a].rfind('\n').map_or(0, |i| i + 1);
+        assert_eq!(rope.line_to_byte(line), start);
+        assert_eq!(rope.line(line), s[start..].split('\n').next().unwrap());
+    }
+    let len = s.chars().count();
+    let (x, y) = (rng.index(len + 1), rng.index(len + 1));
+    let (x, y) = (x.min(y), x.max(y));
+    let want: String = s.chars().skip(x).take(y - x).collect();
+    assert_eq!(rope.slice_chars(x..y), want);
+}
+
+/// Random inserts at byte and char positions and random removals by bytes
+/// and chars, with the front cut away whenever the text passes 200 KB.
+#[test]
+fn edits_match_string() {
+    let mut rng = Rng(29);
+    let mut rope = Rope::new();
+    let mut s = String::new();
+    for step in 0..10000 {
+        match rng.below(5) {
+            0 => {
+                let (a, b) = random_range(&mut rng, &s);
+                rope.remove(a..b);
+                s.replace_range(a..b, "");
+            }
+            1 => {
+                let len = s.chars().count();
+                let (x, y) = (rng.index(len + 1), rng.index(len + 1));
+                let (x, y) = (x.min(y), x.max(y));
+                rope.remove_chars(x..y);
+                s = s.chars().take(x).chain(s.chars().skip(y)).collect();
+            }
+            2 => {
+                let text = random_text(&mut rng);
+                let at = rng.index(s.chars().count() + 1);
+                rope.insert_at_char(at, &text);
+                let byte = s.char_indices().nth(at).map_or(s.len(), |x| x.0);
+                s.insert_str(byte, &text);
+            }
+            _ => {
+                let text = random_text(&mut rng);
+                let at = boundary(&mut rng, &s);
+                rope.insert(at, &text);
+                s.insert_str(at, &text);
+            }
+        }
+        if s.len() > 200_000 {
+            let cut = boundary(&mut rng, &s);
+            rope.remove(0..cut);
+            s.replace_range(0..cut, "");
+        }
+        assert_eq!(rope.len_bytes(), s.len());
+        if step % 200 == 0 {
+            check(&rope, &s, &mut rng);
+        }
+    }
+    check(&rope, &s, &mut rng);
+    check(&Rope::from(s.as_str()), &s, &mut rng);
+}
+
+/// Typing one byte at a time, in the middle and at scattered positions,
+/// keeps the chunks close to full.
+#[test]
+fn typing_keeps_chunks_full() {
+    let mut rope = Rope::new();
+    for i in 0..100_000 {
+        let at = if i % 2 == 0 {
+            rope.len_bytes() / 2
+        } else {
+            i * 7919 % (rope.len_bytes() + 1)
+        };
+        rope.insert(at, "x");
+    }
+    assert_eq!(rope.len_bytes(), 100_000);
+    assert!(rope.chunks().count() < 200);
+}
+
+/// A byte offset inside a multi-byte char maps to the index of that char,
+/// including in a char that starts a later chunk.
+#[test]
+fn byte_to_char_inside_a_char() {
+    let rope = Rope::from("aé€b");
+    let chars: Vec<usize> = (0..=rope.len_bytes())
+        .map(|at| rope.byte_to_char(at))
+        .collect();
+    assert_eq!(chars, [0, 1, 1, 2, 2, 2, 3, 4]);
+    let long = "€".repeat(10_000);
+    let rope = Rope::from(long.as_str());
+    assert!(rope.chunks().count() > 1);
+    for at in 0..long.len() {
+        assert_eq!(rope.byte_to_char(at), at / 3);
+    }
+    assert_eq!(rope.byte_to_char(long.len()), 10_000);
+}
+
+/// Inserting inside a multi-byte char is rejected.
+#[test]
+#[should_panic(expected = "byte offset is not on a char boundary")]
+fn insert_off_a_char_boundary_panics() {
+    let mut rope = Rope::new();
+    rope.insert(0, "é");
+    rope.insert(1, "x");
+}
@@ -0,0 +1,160 @@
+//! The RRB vector against `Vec`, over a pool of versions that share
+//! structure: pushes, pops, concatenation, slicing, splitting and
+//! transient edits leave every older version unchanged.
+
+use datastructures::rrb_vector::RrbVector;
+
+mod common;
+use common::Rng;
+
+fn check(v: &RrbVector<u64>, want: &[u64]) {
+    assert_eq!(v.len(), want.len());
+    assert_eq!(v.is_empty(), want.is_empty());
+    assert!(v.iter().eq(want));
+    assert_eq!(v.iter().len(), want.len());
+    for (i, x) in want.iter().enumerate() {
+        assert_eq!(v[i], *x);
+    }
+    assert_eq!(v.get(want.len()), None);
+    assert_eq!(v.first(), want.first());
+    assert_eq!(v.last(), want.last());
+}
+
+/// Random operations on versions drawn from a pool, each derived version
+/// checked against its `Vec` and a sample of the originals rechecked.
+#[test]
+fn versions_match_vec() {
+    let mut rng = Rng(6);
+    let mut pool: Vec<(RrbVector<u64>, Vec<u64>)> = vec![(RrbVector::new(), Vec::new())];
+    let mut next = 0u64;
+    for step in 0..3000 {
+        let i = rng.index(pool.len());
+        let (mut v, mut want) = pool[i].clone();
+        match rng.below(8) {
+            0 | 1 => {
+                let most = if rng.below(4) == 0 { 3000 } else { 40 };
+                for _ in 0..rng.below(most) {
+                    v.push_back(next);
+                    want.push(next);
+                    next += 1;
+                }
+            }
+            2 => {
+                for _ in 0..rng.below(50) {
+                    assert_eq!(v.pop_back(), want.pop());
+                }
+            }
+            3 => {
+                let (other, other_want) = &pool[rng.index(pool.len())];
+                v.append(other);
+                want.extend_from_slice(other_want);
+            }
+            4 => {
+                let lo = rng.index(want.len() + 1);
+                let hi = lo + rng.index(want.len() - lo + 1);
+                v = v.slice(lo..hi);
+                want = want[lo..hi].to_vec();
+            }
+            5 => {
+                let at = rng.index(want.len() + 1);
+                let rest = v.split_off(at);
+                let rest_want = want.split_off(at);
+                check(&rest, &rest_want);
+                pool.push((rest, rest_want));
+            }
+            6 => {
+                if !want.is_empty() {
+                    let k = rng.index(want.len());
+                    assert_eq!(v.set(k, next), want[k]);
+                    want[k] = next;
+                    next += 1;
+                }
+            }
+            _ => {
+                let mut t = v.transient();
+                for _ in 0..rng.below(200) {
+                    t.push_back(next);
+                    want.push(next);
+                    next += 1;
+                }
+                if rng.below(3) == 0 {
+                    assert_eq!(t.pop_back(), want.pop());
+                }
+                if !want.is_empty() {
+                    let k = rng.index(want.len());
+                    *t.get_mut(k).unwrap() += 1;
+                    want[k] += 1;
+                    assert_eq!(t.get(k), Some(&want[k]));
+                }
+                assert_eq!(t.len(), want.len());
+                assert_eq!(t.is_empty(), want.is_empty());
+                v = t.persistent();
+            }
+        }
+        check(&v, &want);
+        if step % 10 == 0 {
+            check(&pool[i].0, &pool[i].1);
+        }
+        if pool.len() < 40 {
+            pool.push((v, want));
+        } else {
+            pool[i] = (v, want);
+        }
+    }
+    for (v, want) in &pool {
+        check(v, want);
+    }
+}
+
+/// Many small, unevenly sized concatenations, which the relaxed nodes
+/// absorb without losing elements or order.
+#[test]
+fn many_small_appends() {
+    let mut v = RrbVector::new();
+    let mut want = Vec::new();
+    for k in 0..3000u64 {
+        let piece: RrbVector<u64> = (0..k % 7).collect();
+        v.append(&piece);
+        want.extend(0..k % 7);
+    }
+    check(&v, &want);
+    let mut rest = v.split_off(want.len() / 3);
+    check(&v, &want[..want.len() / 3]);
+    rest.append(&v);
+    let mut rotated = want[want.len() / 3..].to_vec();
+    rotated.extend_from_slice(&want[..want.len() / 3]);
+    check(&rest, &rotated);
+}
+
+/// Collecting, comparing, clearing and formatting.
+#[test]
+fn collect_and_compare() {
+    let v: RrbVector<u64> = (0..100_000).collect();
+    check(&v, &(0..100_000).collect::<Vec<_>>());
+    let mut w = v.slice(..50_000);
+    w.extend(50_000..100_000);
+    assert_eq!(w, v);
+    w.clear();
+    assert!(w.is_empty());
+    assert_ne!(w, v);
+    assert_eq!(
+        format!("{:?}", (0..3u64).collect::<RrbVector<_>>()),
+        "[0, 1, 2]"
+    );
+}
+
+/// Setting past the end panics.
+#[test]
+#[should_panic(expected = "index 3 out of bounds for length 3")]
+fn set_out_of_bounds_panics() {
+    let mut v: RrbVector<u32> = (0..3).collect();
+    v.set(3, 0);
+}
+
+/// Splitting past the end panics.
+#[test]
+#[should_panic(expected = "split index 4 out of bounds for length 3")]
+fn split_out_of_bounds_panics() {
+    let mut v: RrbVector<u32> = (0..3).collect();
+    v.split_off(4);
+}
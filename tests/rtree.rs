@@ -0,0 +1,181 @@
+//! The R*-tree against a plain map of live boxes, under random inserts and
+//! removals, both from empty and after bulk loading, with and without
+//! Hilbert ordering.
+
+use std::collections::HashMap;
+
+use datastructures::rtree::{Aabb, EntryId, RTree};
+
+mod common;
+use common::{sorted, Rng};
+
+/// A box of side up to 5 somewhere in `0..100` squared.
+fn random_box(rng: &mut Rng) -> Aabb<2> {
+    let x = rng.range(0.0, 100.0);
+    let y = rng.range(0.0, 100.0);
+    Aabb::new([x, y], [x + rng.range(0.0, 5.0), y + rng.range(0.0, 5.0)])
+}
+
+/// Checks a random query of each kind against a scan of `live`.
+fn check(tree: &RTree<usize, 2>, live: &HashMap<EntryId, Aabb<2>>, rng: &mut Rng) {
+    assert_eq!(tree.len(), live.len());
+    let lo = [rng.range(0.0, 90.0), rng.range(0.0, 90.0)];
+    let q = Aabb::new(
+        lo,
+        [lo[0] + rng.range(0.0, 20.0), lo[1] + rng.range(0.0, 20.0)],
+    );
+
+    let got: Vec<_> = tree.intersecting(q).map(|x| x.0).collect();
+    let want: Vec<_> = live
+        .iter()
+        .filter(|x| x.1.intersects(&q))
+        .map(|x| *x.0)
+        .collect();
+    assert_eq!(sorted(got), sorted(want));
+
+    let got: Vec<_> = tree.contained_in(q).map(|x| x.0).collect();
+    let want: Vec<_> = live
+        .iter()
+        .filter(|x| q.contains(x.1))
+        .map(|x| *x.0)
+        .collect();
+    assert_eq!(sorted(got), sorted(want));
+
+    let p = Aabb::point(lo);
+    let got: Vec<_> = tree.containing(p).map(|x| x.0).collect();
+    let want: Vec<_> = live
+        .iter()
+        .filter(|x| x.1.contains(&p))
+        .map(|x| *x.0)
+        .collect();
+    assert_eq!(sorted(got), sorted(want));
+
+    let k = rng.index(6);
+    let got: Vec<f64> = tree.nearest(&lo, k).iter().map(|x| x.1).collect();
+    let mut want: Vec<f64> = live
+        .values()
+        .map(|b| b.distance_squared(&lo).sqrt())
+        .collect();
+    want.sort_by(f64::total_cmp);
+    want.truncate(k);
+    assert_eq!(got, want);
+}
+
+/// Removes every entry, checking queries along the way.
+fn drain(tree: &mut RTree<usize, 2>, live: &mut HashMap<EntryId, Aabb<2>>, rng: &mut Rng) {
+    let ids: Vec<_> = live.keys().copied().collect();
+    for id in ids {
+        assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+        if rng.below(10) == 0 {
+            check(tree, live, rng);
+        }
+    }
+    assert!(tree.is_empty());
+}
+
+/// Inserts and removals through node splits, forced reinsertion and
+/// condensing, down to an empty tree.
+#[test]
+fn dynamic_operations_match_brute_force() {
+    let mut rng = Rng(3);
+    let mut tree = RTree::with_max_entries(6);
+    let mut live = HashMap::new();
+    for step in 0..4000 {
+        if rng.below(3) < 2 || live.is_empty() {
+            let b = random_box(&mut rng);
+            live.insert(tree.insert(b, step), b);
+        } else {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+        }
+        if step % 20 == 0 {
+            check(&tree, &live, &mut rng);
+        }
+    }
+    drain(&mut tree, &mut live, &mut rng);
+}
+
+/// A bulk-loaded tree answers queries like one built by insertion, and
+/// stays correct as half of its entries are removed.
+#[test]
+fn bulk_loaded_tree_matches_brute_force() {
+    let mut rng = Rng(4);
+    for n in [0usize, 1, 7, 100, 2000] {
+        let items: Vec<_> = (0..n).map(|i| (random_box(&mut rng), i)).collect();
+        let mut tree = RTree::bulk_load_with(8, items);
+        let mut live: HashMap<EntryId, Aabb<2>> = tree.iter().map(|(id, b, _)| (id, *b)).collect();
+        assert_eq!(live.len(), n);
+        for _ in 0..20 {
+            check(&tree, &live, &mut rng);
+        }
+        for _ in 0..n / 2 {
+            let id = *live.keys().next().unwrap();
+            tree.remove(id);
+            live.remove(&id);
+        }
+        for _ in 0..20 {
+            check(&tree, &live, &mut rng);
+        }
+    }
+}
+
+/// Random inserts and removals on a Hilbert-ordered tree, some of them
+/// outside its frame, keeping the height logarithmic, then a full drain.
+fn churn(mut tree: RTree<usize, 2>, mut live: HashMap<EntryId, Aabb<2>>, seed: u64) {
+    let mut rng = Rng(seed);
+    for step in 0..4000 {
+        if rng.below(3) < 2 || live.is_empty() {
+            let b = if rng.below(20) == 0 {
+                Aabb::new([-50.0, 120.0], [-40.0, 130.0])
+            } else {
+                random_box(&mut rng)
+            };
+            live.insert(tree.insert(b, step), b);
+        } else {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            assert_eq!(tree.remove(id).unwrap().0, live.remove(&id).unwrap());
+        }
+        if step % 20 == 0 {
+            check(&tree, &live, &mut rng);
+        }
+        let bound = (live.len().max(2) as f64).log2().ceil() as usize + 2;
+        assert!(
+            tree.height() <= bound,
+            "height {} len {}",
+            tree.height(),
+            live.len()
+        );
+    }
+    drain(&mut tree, &mut live, &mut rng);
+    assert_eq!(tree.height(), 1);
+}
+
+/// Hilbert-ordered insertion matches the scan for several node sizes.
+#[test]
+fn hilbert_ordered_tree_matches_brute_force() {
+    for max in [4, 5, 6, 16] {
+        let tree = RTree::hilbert_ordered_with(max, Aabb::new([0.0, 0.0], [100.0, 100.0]));
+        assert!(tree.hilbert_frame().is_some());
+        churn(tree, HashMap::new(), max as u64);
+    }
+}
+
+/// A Hilbert bulk-loaded tree matches the scan, then keeps its ordering
+/// through later inserts and removals, and through `clear`.
+#[test]
+fn hilbert_loaded_tree_matches_brute_force() {
+    let mut rng = Rng(9);
+    for n in [0usize, 1, 7, 17, 100, 2000] {
+        let items: Vec<_> = (0..n).map(|i| (random_box(&mut rng), i)).collect();
+        let tree = RTree::hilbert_load_with(8, items);
+        let live: HashMap<EntryId, Aabb<2>> = tree.iter().map(|(id, b, _)| (id, *b)).collect();
+        assert_eq!(live.len(), n);
+        for _ in 0..20 {
+            check(&tree, &live, &mut rng);
+        }
+        churn(tree, live, n as u64);
+    }
+    let mut tree = RTree::hilbert_load((0..10).map(|i| (random_box(&mut rng), i)).collect());
+    tree.clear();
+    assert!(tree.hilbert_frame().is_some());
+}
@@ -0,0 +1,94 @@
+//! The scapegoat tree against a `BTreeMap` for balance parameters across
+//! their range, with the height held to the logarithmic bound throughout.
+
+use std::collections::BTreeMap;
+
+use datastructures::scapegoat::{ScapegoatTree, DEFAULT_ALPHA};
+
+mod common;
+use common::Rng;
+
+/// The most nodes on a path for `len` entries: the tree's depth bound on
+/// its high-water mark, which removals keep within `len / alpha`, counted
+/// in nodes rather than edges.
+fn height_bound(len: usize, alpha: f64) -> usize {
+    let high_water = len.max(1) as f64 / alpha;
+    (high_water.ln() / (1.0 / alpha).ln()).floor() as usize + 2
+}
+
+/// Random inserts, removals and edits, with ranges, extremes and the
+/// height checked periodically.
+#[test]
+fn operations_match_btreemap() {
+    for (i, alpha) in [0.5, 0.6, DEFAULT_ALPHA, 0.75, 0.9, 0.99]
+        .into_iter()
+        .enumerate()
+    {
+        let mut rng = Rng(i as u64 + 3);
+        let mut tree = ScapegoatTree::with_alpha(alpha);
+        assert_eq!(tree.alpha(), alpha);
+        let mut model = BTreeMap::new();
+        for step in 0..20000u64 {
+            let k = rng.below(3000);
+            match rng.below(10) {
+                0..=5 => assert_eq!(tree.insert(k, step), model.insert(k, step)),
+                6 | 7 => assert_eq!(tree.remove_entry(&k), model.remove_entry(&k)),
+                8 => assert_eq!(tree.remove(&k), model.remove(&k)),
+                _ => {
+                    if let Some(v) = tree.get_mut(&k) {
+                        *v += 1;
+                    }
+                    if let Some(v) = model.get_mut(&k) {
+                        *v += 1;
+                    }
+                }
+            }
+            assert_eq!(tree.len(), model.len());
+            assert_eq!(tree.get(&k), model.get(&k));
+            assert_eq!(tree.contains_key(&k), model.contains_key(&k));
+            if step % 997 != 0 {
+                continue;
+            }
+            assert!(tree.iter().eq(model.iter()));
+            let (a, b) = (rng.below(3000), rng.below(3000));
+            let (a, b) = (a.min(b), a.max(b));
+            assert!(tree.range(a..b).eq(model.range(a..b)));
+            assert!(tree.range(a..=b).eq(model.range(a..=b)));
+            assert!(tree.range(b..).eq(model.range(b..)));
+            assert_eq!(tree.first_key_value(), model.first_key_value());
+            assert_eq!(tree.last_key_value(), model.last_key_value());
+            let bound = height_bound(tree.len(), alpha);
+            assert!(tree.height() <= bound, "{} > {bound}", tree.height());
+        }
+    }
+}
+
+/// Ascending inserts, the worst case for an unbalanced tree, stay within
+/// the bound, and a rebalance leaves the tree perfectly balanced.
+#[test]
+fn sequential_inserts_stay_shallow() {
+    for alpha in [0.5, DEFAULT_ALPHA, 0.9] {
+        let mut tree: ScapegoatTree<u32, ()> = (0..10000).map(|k| (k, ())).collect();
+        assert!(tree.height() <= height_bound(10000, DEFAULT_ALPHA));
+        tree = ScapegoatTree::with_alpha(alpha);
+        tree.extend((0..10000).rev().map(|k| (k, ())));
+        assert!(tree.height() <= height_bound(10000, alpha));
+        tree.rebalance();
+        assert_eq!(tree.height(), 14);
+        for k in 0..9000 {
+            tree.remove(&k);
+        }
+        assert!(tree.height() <= height_bound(1000, alpha));
+        tree.clear();
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+    }
+    assert!(ScapegoatTree::<u8, u8>::default().is_empty());
+}
+
+/// The balance parameter must lie in `[0.5, 1)`.
+#[test]
+#[should_panic(expected = "alpha must be in [0.5, 1)")]
+fn alpha_out_of_range_panics() {
+    ScapegoatTree::<u8, u8>::with_alpha(1.0);
+}
@@ -0,0 +1,116 @@
+//! The lazy segment tree against a plain vector, for several monoids and
+//! actions.
+
+use datastructures::algebra::{Assign, Increment, Max, Min, Sum};
+use datastructures::segment_tree::SegmentTree;
+
+mod common;
+use common::Rng;
+
+/// A random range `a..b` with `0 <= a <= b <= n`.
+fn random_range(rng: &mut Rng, n: usize) -> (usize, usize) {
+    let a = rng.index(n + 1);
+    let b = rng.index(n + 1);
+    (a.min(b), a.max(b))
+}
+
+/// A random nonempty range `a..b` with `b <= n`.
+fn random_nonempty_range(rng: &mut Rng, n: usize) -> (usize, usize) {
+    let a = rng.index(n);
+    (a, a + 1 + rng.index(n - a))
+}
+
+/// Range increments and point sets under sum queries, empty ranges
+/// included.
+#[test]
+fn sums_under_increments() {
+    let mut rng = Rng(7);
+    for n in [1usize, 2, 3, 7, 33, 100] {
+        let mut v: Vec<i64> = (0..n).map(|_| rng.below(100) as i64).collect();
+        let mut tree: SegmentTree<Sum<i64>, Increment<i64>> = v.iter().copied().collect();
+        for _ in 0..500 {
+            let (a, b) = random_range(&mut rng, n);
+            match rng.below(3) {
+                0 => {
+                    let d = rng.below(20) as i64 - 10;
+                    for x in &mut v[a..b] {
+                        *x += d;
+                    }
+                    tree.update(a..b, Increment(d));
+                }
+                1 if a < n => {
+                    let x = rng.below(50) as i64;
+                    v[a] = x;
+                    tree.set(a, x);
+                }
+                _ => {}
+            }
+            assert_eq!(tree.query(a..b), v[a..b].iter().sum::<i64>());
+            assert_eq!(tree.total(), v.iter().sum::<i64>());
+        }
+        assert_eq!(tree.to_vec(), v);
+    }
+}
+
+/// Range assignment under min and sum, and range increments under max,
+/// each tree against its own copy of the values.
+#[test]
+fn min_max_and_sum_under_assignment() {
+    let mut rng = Rng(9);
+    for n in [1usize, 5, 64, 77] {
+        let mut v: Vec<i32> = (0..n).map(|_| rng.below(100) as i32).collect();
+        let mut w = v.clone();
+        let mut min: SegmentTree<Min<i32>, Assign<i32>> = v.iter().copied().collect();
+        let mut sum: SegmentTree<Sum<i32>, Assign<i32>> = v.iter().copied().collect();
+        let mut max: SegmentTree<Max<i32>, Increment<i32>> = v.iter().copied().collect();
+        for _ in 0..500 {
+            let (a, b) = random_nonempty_range(&mut rng, n);
+            let x = rng.below(100) as i32;
+            if rng.below(2) == 0 {
+                for y in &mut v[a..b] {
+                    *y = x;
+                }
+                min.update(a..b, Assign(x));
+                sum.update(a..b, Assign(x));
+                for y in &mut w[a..b] {
+                    *y += x - 50;
+                }
+                max.update(a..b, Increment(x - 50));
+            }
+            let (a, b) = random_nonempty_range(&mut rng, n);
+            assert_eq!(min.query(a..b), *v[a..b].iter().min().unwrap());
+            assert_eq!(sum.query(a..=b - 1), v[a..b].iter().sum::<i32>());
+            assert_eq!(max.query(a..b), *w[a..b].iter().max().unwrap());
+        }
+    }
+}
+
+/// `max_right` finds the longest prefix from a start whose sum stays
+/// within a limit, and an empty tree answers with the identity.
+#[test]
+fn max_right_finds_the_longest_prefix() {
+    let mut rng = Rng(3);
+    let n = 50;
+    let mut v: Vec<u64> = (0..n).map(|_| rng.below(10)).collect();
+    let mut tree: SegmentTree<Sum<u64>, Increment<u64>> = v.iter().copied().collect();
+    for _ in 0..300 {
+        let (a, b) = random_range(&mut rng, n);
+        let d = rng.below(3);
+        for x in &mut v[a..b] {
+            *x += d;
+        }
+        tree.update(a..b, Increment(d));
+        let start = rng.index(n + 1);
+        let limit = rng.below(200);
+        let mut end = start;
+        let mut acc = 0;
+        while end < n && acc + v[end] <= limit {
+            acc += v[end];
+            end += 1;
+        }
+        assert_eq!(tree.max_right(start, |s| *s <= limit), end);
+    }
+    let empty: SegmentTree<Max<f64>> = SegmentTree::new(Max::new(), vec![]);
+    assert_eq!(empty.total(), f64::NEG_INFINITY);
+    assert_eq!(empty.query(..), f64::NEG_INFINITY);
+}
@@ -0,0 +1,152 @@
+//! The sharded LRU cache against the single-threaded cache it wraps, and
+//! under concurrent use from several threads.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use datastructures::lru::LruCache;
+use datastructures::sharded_cache::ShardedLruCache;
+
+mod common;
+use common::Rng;
+
+/// With one shard the cache behaves exactly like an `LruCache` of the same
+/// capacity, and counts every lookup as a hit or a miss.
+#[test]
+fn one_shard_matches_lru_cache() {
+    let mut rng = Rng(6);
+    let cache = ShardedLruCache::with_shards(30, 1);
+    let mut model = LruCache::new(30);
+    let (mut hits, mut misses) = (0, 0);
+    for step in 0..20000u64 {
+        let k = rng.below(50);
+        match rng.below(6) {
+            0 => assert_eq!(cache.insert(k, step), model.insert(k, step)),
+            1 => {
+                let w = rng.below(8);
+                assert_eq!(
+                    cache.insert_weighted(k, step, w),
+                    model.insert_weighted(k, step, w)
+                );
+            }
+            2 => {
+                let want = model.get(&k).copied();
+                if want.is_some() {
+                    hits += 1;
+                } else {
+                    misses += 1;
+                }
+                assert_eq!(cache.get(&k), want);
+            }
+            3 => {
+                assert_eq!(cache.peek(&k), model.peek(&k).copied());
+                assert_eq!(cache.contains(&k), model.contains(&k));
+            }
+            4 => assert_eq!(cache.remove(&k), model.remove(&k)),
+            _ => {
+                let want = match model.get(&k) {
+                    Some(&v) => {
+                        hits += 1;
+                        v
+                    }
+                    None => {
+                        misses += 1;
+                        model.insert(k, step);
+                        step
+                    }
+                };
+                assert_eq!(cache.get_or_insert_with(k, || step), want);
+            }
+        }
+        assert_eq!(cache.len(), model.len());
+        assert_eq!(cache.weight(), model.weight());
+    }
+    assert_eq!((cache.hits(), cache.misses()), (hits, misses));
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+/// Threads sharing a cache see consistent values, every lookup is counted
+/// once, and the total weight stays within the shards' capacities.
+#[test]
+fn concurrent_use() {
+    let evicted = Arc::new(AtomicU64::new(0));
+    let count = Arc::clone(&evicted);
+    let cache = Arc::new(
+        ShardedLruCache::with_shards(1000, 6).with_eviction_callback(move |_: u64, _: u64| {
+            count.fetch_add(1, Ordering::Relaxed);
+        }),
+    );
+    assert_eq!(cache.shard_count(), 8);
+    assert_eq!(cache.capacity(), 1000);
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                for i in 0..5000u64 {
+                    let k = (i * 7 + t) % 3000;
+                    assert_eq!(cache.get_or_insert_with(k, || k * 2), k * 2);
+                    if i % 5 == 0 {
+                        cache.insert(k, k * 2);
+                    }
+                    if i % 11 == 0 {
+                        cache.remove(&k);
+                    }
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert!(cache.weight() <= 1000);
+    assert_eq!(cache.hits() + cache.misses(), 8 * 5000);
+    assert!(evicted.load(Ordering::Relaxed) > 0);
+}
+
+/// Concurrent callers of `get_or_insert_with` for the same keys compute
+/// each value once.
+#[test]
+fn get_or_insert_with_computes_once() {
+    let cache = Arc::new(ShardedLruCache::<u64, u64>::with_shards(4000, 4));
+    let computed = Arc::new(AtomicU64::new(0));
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let (cache, computed) = (Arc::clone(&cache), Arc::clone(&computed));
+            thread::spawn(move || {
+                for k in 0..500 {
+                    cache.get_or_insert_with(k, || {
+                        computed.fetch_add(1, Ordering::Relaxed);
+                        k
+                    });
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(computed.load(Ordering::Relaxed), 500);
+    assert_eq!(cache.len(), 500);
+    assert_eq!(cache.misses(), 500);
+}
+
+/// Borrowed forms of the key work for lookups.
+#[test]
+fn borrowed_lookups() {
+    let cache = ShardedLruCache::<String, u32>::new(10);
+    cache.insert("a".into(), 1);
+    assert_eq!(cache.get("a"), Some(1));
+    assert_eq!(cache.peek("b"), None);
+    assert!(cache.contains("a"));
+    assert_eq!(cache.remove("a"), Some(1));
+    assert!(cache.is_empty());
+}
+
+/// A cache needs somewhere to put its entries.
+#[test]
+#[should_panic(expected = "a cache needs at least one shard")]
+fn zero_shards_panics() {
+    ShardedLruCache::<u32, u32>::with_shards(10, 0);
+}
@@ -0,0 +1,100 @@
+//! The skip list against `std::collections::BTreeMap`, under random
+//! inserts, removals and pops, with ranges walked from both ends.
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use datastructures::skip_list::SkipList;
+
+mod common;
+use common::Rng;
+
+fn random_bound(rng: &mut Rng) -> Bound<u32> {
+    let key = rng.below(600) as u32;
+    match rng.below(3) {
+        0 => Bound::Included(key),
+        1 => Bound::Excluded(key),
+        _ => Bound::Unbounded,
+    }
+}
+
+/// Whether `BTreeMap::range` would reject the bounds as out of order.
+fn is_reversed(lo: Bound<u32>, hi: Bound<u32>) -> bool {
+    match (lo, hi) {
+        (Bound::Included(x), Bound::Included(y)) => x > y,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            x >= y
+        }
+        _ => false,
+    }
+}
+
+/// Compares iteration both ways, the first and last entries, and random
+/// ranges consumed from a random mix of ends.
+fn check(list: &SkipList<u32, u32>, map: &BTreeMap<u32, u32>, rng: &mut Rng) {
+    assert_eq!(list.len(), map.len());
+    assert!(list.iter().eq(map.iter()));
+    assert!(list.iter().rev().eq(map.iter().rev()));
+    assert_eq!(list.first_key_value(), map.first_key_value());
+    assert_eq!(list.last_key_value(), map.last_key_value());
+    for _ in 0..20 {
+        let (lo, hi) = (random_bound(rng), random_bound(rng));
+        if is_reversed(lo, hi) {
+            assert_eq!(list.range((lo, hi)).count(), 0);
+            continue;
+        }
+        let (mut got, mut want) = (list.range((lo, hi)), map.range((lo, hi)));
+        loop {
+            let (x, y) = if rng.below(2) == 0 {
+                (got.next(), want.next())
+            } else {
+                (got.next_back(), want.next_back())
+            };
+            assert_eq!(x, y);
+            if x.is_none() {
+                break;
+            }
+        }
+    }
+}
+
+/// Random operations under several seeds, down to an empty list, then
+/// pops from both ends of a refilled list.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(7);
+    for seed in [3, 4, 5, 8, 32] {
+        let mut list = SkipList::with_seed(seed);
+        let mut map = BTreeMap::new();
+        for step in 0..20000 {
+            let k = rng.below(600) as u32;
+            if rng.below(3) == 0 {
+                assert_eq!(list.remove(&k), map.remove(&k));
+            } else {
+                assert_eq!(list.insert(k, step), map.insert(k, step));
+            }
+            assert_eq!(list.get(&k), map.get(&k));
+            assert_eq!(list.contains_key(&k), map.contains_key(&k));
+            if step % 500 == 0 {
+                check(&list, &map, &mut rng);
+            }
+        }
+        check(&list, &map, &mut rng);
+        let keys: Vec<u32> = map.keys().copied().collect();
+        for k in keys {
+            assert_eq!(list.remove(&k), map.remove(&k));
+        }
+        assert!(list.is_empty());
+        check(&list, &map, &mut rng);
+
+        for k in 0..150 {
+            let k = rng.below(600) as u32 + k;
+            assert_eq!(list.insert(k, k), map.insert(k, k));
+        }
+        for _ in 0..100 {
+            assert_eq!(list.pop_first(), map.pop_first());
+            assert_eq!(list.pop_last(), map.pop_last());
+        }
+        check(&list, &map, &mut rng);
+    }
+}
@@ -0,0 +1,109 @@
+//! The slab against a `BTreeMap` from key to value, under random inserts,
+//! removals, retains and compactions that the caller may stop part way.
+
+use std::collections::BTreeMap;
+
+use datastructures::slab::Slab;
+
+mod common;
+use common::Rng;
+
+/// Random operations. Insertion lands on the key announced beforehand,
+/// and every key a compaction moves is reported, so applying the renames
+/// to the model keeps it in step.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(11);
+    let mut slab = Slab::new();
+    let mut model: BTreeMap<usize, u64> = BTreeMap::new();
+    for step in 0..40000u64 {
+        match rng.below(10) {
+            0..=4 => {
+                let key = slab.vacant_key();
+                assert!(!model.contains_key(&key));
+                assert_eq!(slab.insert(step), key);
+                model.insert(key, step);
+            }
+            5..=7 => {
+                let k = rng.index(slab.len() * 2 + 1);
+                assert_eq!(slab.remove(k), model.remove(&k));
+            }
+            8 => {
+                if rng.below(20) == 0 {
+                    slab.retain(|k, v| !(k as u64 + *v).is_multiple_of(3));
+                    model.retain(|&k, v| !(k as u64 + *v).is_multiple_of(3));
+                }
+            }
+            _ => {
+                if rng.below(30) == 0 {
+                    let limit = rng.index(50);
+                    let mut renames = Vec::new();
+                    slab.compact(|v, from, to| {
+                        if renames.len() == limit {
+                            return false;
+                        }
+                        renames.push((from, to, *v));
+                        true
+                    });
+                    let finished = renames.len() < limit;
+                    for (from, to, v) in renames {
+                        assert_eq!(model.remove(&from), Some(v));
+                        assert!(model.insert(to, v).is_none());
+                    }
+                    if finished {
+                        assert!(model.keys().copied().eq(0..model.len()));
+                        assert_eq!(slab.vacant_key(), model.len());
+                    }
+                }
+            }
+        }
+        assert_eq!(slab.len(), model.len());
+        if step % 97 == 0 {
+            let pairs = model.iter().map(|(&k, &v)| (k, v));
+            assert!(slab.iter().map(|(k, &v)| (k, v)).eq(pairs.clone()));
+            assert!(slab.iter().rev().map(|(k, &v)| (k, v)).eq(pairs.rev()));
+            for (&k, v) in &model {
+                assert!(slab.contains(k));
+                assert_eq!(slab[k], *v);
+            }
+        }
+    }
+    let drained: Vec<u64> = slab.drain().collect();
+    assert_eq!(drained, model.values().copied().collect::<Vec<_>>());
+    assert!(slab.is_empty());
+    assert_eq!(slab.insert(5), 0);
+}
+
+/// Freed keys are reused most recent first, and editing goes through
+/// `get_mut`, `iter_mut` and indexing alike.
+#[test]
+fn key_reuse_and_edits() {
+    let mut slab: Slab<String> = ["a", "b", "c", "d"].map(String::from).into_iter().collect();
+    assert!(slab.capacity() >= 4);
+    slab.remove(1);
+    slab.remove(2);
+    assert_eq!(slab.get(1), None);
+    assert_eq!(slab.insert("x".into()), 2);
+    assert_eq!(slab.insert("y".into()), 1);
+    assert_eq!(slab.insert("z".into()), 4);
+    slab.get_mut(0).unwrap().push('!');
+    for (k, v) in slab.iter_mut() {
+        v.push_str(&k.to_string());
+    }
+    slab[3].insert(0, '>');
+    let all: Vec<&str> = slab.iter().map(|(_, v)| v.as_str()).collect();
+    assert_eq!(all, ["a!0", "y1", "x2", ">d3", "z4"]);
+    slab.clear();
+    assert_eq!(slab.vacant_key(), 0);
+    assert!(Slab::<u8>::with_capacity(10).is_empty());
+}
+
+/// Indexing a vacant key panics.
+#[test]
+#[should_panic(expected = "vacant slab key")]
+fn vacant_index_panics() {
+    let mut slab = Slab::new();
+    let k = slab.insert(1);
+    slab.remove(k);
+    let _ = slab[k];
+}
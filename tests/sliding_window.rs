@@ -0,0 +1,112 @@
+//! The monotonic queue and the two-stack sliding window against a
+//! `VecDeque` of the values in the window, with commutative monoids and a
+//! non-commutative one whose aggregate depends on order.
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+use datastructures::algebra::{Max, Min, Monoid, Sum};
+use datastructures::sliding_window::{MonotonicQueue, SlidingWindow};
+
+mod common;
+use common::Rng;
+
+/// String concatenation, which catches aggregates combined out of order.
+#[derive(Clone, Default)]
+struct Concat;
+
+impl Monoid for Concat {
+    type Value = String;
+
+    fn identity(&self) -> String {
+        String::new()
+    }
+
+    fn combine(&self, a: &String, b: &String) -> String {
+        format!("{a}{b}")
+    }
+}
+
+fn letter(v: i64) -> char {
+    (b'a' + (v + 25) as u8 % 26) as char
+}
+
+/// Random pushes and pops, keeping every window's aggregate equal to a
+/// fold over the model.
+#[test]
+fn windows_match_vecdeque() {
+    let mut rng = Rng(5);
+    let mut model: VecDeque<i64> = VecDeque::new();
+    let mut least = MonotonicQueue::new();
+    let mut greatest = MonotonicQueue::new();
+    let mut sum = SlidingWindow::new(Sum::<i64>::new());
+    let mut min = SlidingWindow::new(Min::<i64>::new());
+    let mut max = SlidingWindow::new(Max::<i64>::new());
+    let mut concat = SlidingWindow::<Concat>::default();
+    for _ in 0..30000 {
+        if rng.below(3) < 2 && model.len() < 300 {
+            let v = rng.below(50) as i64 - 25;
+            model.push_back(v);
+            least.push(v);
+            greatest.push(Reverse(v));
+            sum.push(v);
+            min.push(v);
+            max.push(v);
+            concat.push(letter(v).to_string());
+        } else {
+            let oldest = model.pop_front();
+            assert_eq!(least.pop(), oldest.is_some());
+            assert_eq!(greatest.pop(), oldest.is_some());
+            assert_eq!(sum.pop(), oldest);
+            assert_eq!(min.pop(), oldest);
+            assert_eq!(max.pop(), oldest);
+            assert_eq!(concat.pop(), oldest.map(|v| letter(v).to_string()));
+        }
+        assert_eq!(least.len(), model.len());
+        assert_eq!(least.is_empty(), model.is_empty());
+        assert_eq!(least.min(), model.iter().min());
+        assert_eq!(greatest.min().map(|r| r.0), model.iter().max().copied());
+        assert_eq!(sum.len(), model.len());
+        assert_eq!(sum.aggregate(), model.iter().sum::<i64>());
+        assert_eq!(
+            min.aggregate(),
+            model.iter().copied().min().unwrap_or(i64::MAX)
+        );
+        assert_eq!(
+            max.aggregate(),
+            model.iter().copied().max().unwrap_or(i64::MIN)
+        );
+        assert_eq!(
+            concat.aggregate(),
+            model.iter().map(|&v| letter(v)).collect::<String>()
+        );
+        assert_eq!(sum.front(), model.front());
+        assert!(sum.iter().eq(model.iter()));
+        assert!(sum.iter().rev().eq(model.iter().rev()));
+    }
+}
+
+/// A fixed-width window evicts the oldest value once full.
+#[test]
+fn fixed_width_window() {
+    let mut w = SlidingWindow::new(Sum::<u32>::new());
+    let mut evicted = Vec::new();
+    let sums: Vec<u32> = (1..=6)
+        .map(|v| {
+            evicted.extend(w.push_evict(v, 3));
+            w.aggregate()
+        })
+        .collect();
+    assert_eq!(sums, [1, 3, 6, 9, 12, 15]);
+    assert_eq!(evicted, [1, 2, 3]);
+    w.extend([10, 20]);
+    assert_eq!(w.len(), 5);
+    w.clear();
+    assert!(w.is_empty());
+    assert_eq!(w.aggregate(), 0);
+    let mut q = MonotonicQueue::new();
+    q.push(3);
+    q.clear();
+    assert_eq!(q.min(), None);
+    assert!(!q.pop());
+}
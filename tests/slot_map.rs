@@ -0,0 +1,145 @@
+//! The slot map and its secondary map against a `HashMap`, with removed
+//! keys kept around to check they stay stale after their slots are reused,
+//! and the octree's point handles, which are slot map keys.
+
+use std::collections::HashMap;
+
+use datastructures::octree::Octree;
+use datastructures::rtree::Aabb;
+use datastructures::slot_map::{Key, SecondaryMap, SlotMap};
+
+mod common;
+use common::Rng;
+
+/// A distinct key type, as a map's users would declare one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Entity(Key);
+
+impl From<Key> for Entity {
+    fn from(key: Key) -> Self {
+        Entity(key)
+    }
+}
+
+impl From<Entity> for Key {
+    fn from(entity: Entity) -> Self {
+        entity.0
+    }
+}
+
+/// Random inserts and removals. A removed key misses in both maps even
+/// once a later insertion reuses its slot under a new generation.
+#[test]
+fn operations_match_hashmap() {
+    let mut rng = Rng(3);
+    let mut map: SlotMap<Entity, u64> = SlotMap::new();
+    let mut extra: SecondaryMap<Entity, u64> = SecondaryMap::new();
+    let mut model: HashMap<Entity, u64> = HashMap::new();
+    let mut live: Vec<Entity> = Vec::new();
+    let mut dead: Vec<Entity> = Vec::new();
+    for step in 0..30000u64 {
+        match rng.below(6) {
+            0..=2 => {
+                let k = map.insert(step);
+                assert!(model.insert(k, step).is_none());
+                assert_eq!(extra.insert(k, step * 2), None);
+                live.push(k);
+            }
+            3 | 4 if !live.is_empty() => {
+                let k = live.swap_remove(rng.index(live.len()));
+                let v = model.remove(&k);
+                assert_eq!(map.remove(k), v);
+                assert_eq!(extra.remove(k), v.map(|v| v * 2));
+                dead.push(k);
+            }
+            _ => {
+                if let Some(&k) = live.first() {
+                    *map.get_mut(k).unwrap() += 1;
+                    *model.get_mut(&k).unwrap() += 1;
+                    extra[k] += 2;
+                }
+            }
+        }
+        assert_eq!(map.len(), model.len());
+        assert_eq!(extra.len(), model.len());
+        if let Some(&k) = dead.get(rng.index(dead.len().max(1))) {
+            assert!(!map.contains_key(k));
+            assert_eq!(map.get(k), None);
+            assert_eq!(map.remove(k), None);
+            assert!(!extra.contains_key(k));
+        }
+        if step % 501 == 0 {
+            for (k, v) in map.iter() {
+                assert_eq!(model.get(&k), Some(v));
+                assert_eq!(extra.get(k), Some(&(v * 2)));
+                assert_eq!(map[k], *v);
+            }
+            assert_eq!(map.keys().count(), model.len());
+            assert_eq!(extra.iter().count(), model.len());
+        }
+    }
+    map.clear();
+    assert!(map.is_empty());
+    let k = map.insert(1);
+    assert!(live.iter().all(|&l| l != k && map.get(l).is_none()));
+}
+
+/// Retaining frees the dropped slots, a value can hold its own key, and a
+/// secondary entry under an older key of a slot is replaced silently.
+#[test]
+fn retain_and_self_keys() {
+    let mut map: SlotMap<Key, (Key, u32)> = SlotMap::with_capacity(8);
+    let keys: Vec<Key> = (0..8).map(|i| map.insert_with_key(|k| (k, i))).collect();
+    for (k, v) in map.iter() {
+        assert_eq!(v.0, k);
+    }
+    let mut extra = SecondaryMap::new();
+    for &k in &keys {
+        extra.insert(k, k.index());
+    }
+    map.retain(|_, v| v.1 % 2 == 0);
+    assert_eq!(map.values().map(|v| v.1).collect::<Vec<_>>(), [0, 2, 4, 6]);
+    for v in map.values_mut() {
+        v.1 += 100;
+    }
+    extra.retain(|k, _| map.contains_key(k));
+    assert_eq!(extra.len(), 4);
+    let reused = map.insert((keys[0], 0));
+    assert_eq!(reused.index() % 2, 1);
+    assert_eq!(reused.generation(), 1);
+    assert_eq!(extra.insert(reused, 9), None);
+    assert_eq!(extra.get(keys[reused.index() as usize]), None);
+    assert_eq!(extra[reused], 9);
+    assert_eq!(map[keys[2]].1, 102);
+}
+
+/// Indexing with a stale key panics.
+#[test]
+#[should_panic(expected = "stale or invalid slot map key")]
+fn stale_index_panics() {
+    let mut map: SlotMap<Key, u8> = SlotMap::new();
+    let k = map.insert(1);
+    map.remove(k);
+    map.insert(2);
+    let _ = map[k];
+}
+
+/// Octree handles go stale on removal and on clearing, even when a new
+/// point lands in the same slot.
+#[test]
+fn octree_handles_are_generational() {
+    let mut tree = Octree::new(Aabb::new([0.0; 3], [1.0; 3]));
+    let a = tree.insert([0.5, 0.5, 0.5], 1).unwrap();
+    tree.remove(a);
+    let b = tree.insert([0.25, 0.5, 0.5], 2).unwrap();
+    assert_ne!(a, b);
+    assert_eq!(tree.get(a), None);
+    assert_eq!(tree.get(b).map(|(_, d)| *d), Some(2));
+    assert_eq!(tree.remove(a), None);
+    tree.clear();
+    assert_eq!(tree.get(b), None);
+    let c = tree.insert([0.1, 0.1, 0.1], 3).unwrap();
+    assert_eq!(tree.get(b), None);
+    assert!(tree.get(c).is_some());
+    tree.check_invariants();
+}
@@ -0,0 +1,99 @@
+//! The sparse set and sparse map against a `HashMap`, under random inserts,
+//! removals, edits and clears, with the dense and sparse arrays checked to
+//! point at each other.
+
+use std::collections::HashMap;
+
+use datastructures::sparse_set::{SparseMap, SparseSet};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Random operations on keys beyond the initial universe. Every dense slot
+/// holds a member whose index points back at it, including after clears
+/// leave stale sparse entries behind.
+#[test]
+fn operations_match_hashmap() {
+    let mut rng = Rng(9);
+    let mut set = SparseSet::with_universe(10);
+    let mut map = SparseMap::new();
+    let mut model: HashMap<usize, u64> = HashMap::new();
+    for step in 0..50000u64 {
+        let k = rng.index(300);
+        match rng.below(9) {
+            0..=3 => {
+                assert_eq!(set.insert(k), !model.contains_key(&k));
+                assert_eq!(map.insert(k, step), model.insert(k, step));
+            }
+            4..=6 => {
+                assert_eq!(set.remove(k), model.contains_key(&k));
+                assert_eq!(map.remove(k), model.remove(&k));
+            }
+            7 => {
+                if let Some(v) = map.get_mut(k) {
+                    *v += 1;
+                    *model.get_mut(&k).unwrap() += 1;
+                }
+            }
+            _ => {
+                if rng.below(50) == 0 {
+                    set.clear();
+                    map.clear();
+                    model.clear();
+                }
+            }
+        }
+        assert_eq!(set.len(), model.len());
+        assert_eq!(map.len(), model.len());
+        assert_eq!(set.is_empty(), model.is_empty());
+        assert_eq!(set.contains(k), model.contains_key(&k));
+        assert_eq!(map.contains_key(k), model.contains_key(&k));
+        assert_eq!(map.get(k), model.get(&k));
+        if step % 101 != 0 {
+            continue;
+        }
+        for (i, &key) in set.as_slice().iter().enumerate() {
+            assert_eq!(set.index_of(key), Some(i));
+            assert!(model.contains_key(&key));
+        }
+        for (i, &key) in map.keys().iter().enumerate() {
+            assert_eq!(map.index_of(key), Some(i));
+            assert_eq!(map.values()[i], model[&key]);
+        }
+        assert_eq!(
+            sorted(map.iter().map(|(k, &v)| (k, v)).collect()),
+            sorted(model.iter().map(|(&k, &v)| (k, v)).collect())
+        );
+        assert_eq!(set, set.iter().rev().collect::<SparseSet>());
+    }
+}
+
+/// Values can be edited through the packed slice or the iterator, and
+/// collecting keeps the last value for a repeated key.
+#[test]
+fn packed_values() {
+    let mut map: SparseMap<u32> = [(3, 1), (7, 2), (3, 5)].into_iter().collect();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(3), Some(&5));
+    for v in map.values_mut() {
+        *v *= 10;
+    }
+    for (k, v) in map.iter_mut() {
+        *v += k as u32;
+    }
+    assert_eq!(map.get(3), Some(&53));
+    assert_eq!(map.get(7), Some(&27));
+    map.extend([(100, 0)]);
+    assert_eq!(map.index_of(100), Some(2));
+    assert_eq!(map.remove(3), Some(53));
+    assert_eq!(map.keys(), [100, 7]);
+    assert!(SparseMap::<()>::with_universe(5).is_empty());
+
+    let mut set = SparseSet::new();
+    set.extend([4, 1, 4, 9]);
+    assert_eq!(set.as_slice(), [4, 1, 9]);
+    let other: SparseSet = [9, 4, 1].into_iter().collect();
+    assert_eq!(set, other);
+    set.remove(1);
+    assert_ne!(set, other);
+}
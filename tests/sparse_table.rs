@@ -0,0 +1,75 @@
+//! The sparse table and the Fischer–Heun structure against a scan of every
+//! queried range, for sizes around the block and power-of-two boundaries.
+
+use std::cmp::Reverse;
+
+use datastructures::algebra::{Gcd, Max, Min};
+use datastructures::sparse_table::{FischerHeun, SparseTable};
+
+mod common;
+use common::Rng;
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The leftmost position of the least value in `lo..hi`.
+fn leftmost_min<T: Ord>(values: &[T], lo: usize, hi: usize) -> Option<usize> {
+    (lo..hi).min_by_key(|&i| (&values[i], i))
+}
+
+/// Minimum, maximum and gcd tables, and minimum positions found by the
+/// Fischer–Heun structure, with ties going to the leftmost.
+#[test]
+fn queries_match_scan() {
+    let mut rng = Rng(9);
+    for n in [0, 1, 2, 3, 63, 64, 65, 127, 128, 129, 300, 1000] {
+        let values: Vec<i64> = (0..n).map(|_| rng.below(20) as i64).collect();
+        let multiples: Vec<u64> = (0..n).map(|_| 6 * (rng.below(10) + 1)).collect();
+        let min: SparseTable<Min<i64>> = values.iter().copied().collect();
+        let max = SparseTable::new(Max::new(), values.clone());
+        let gcds = SparseTable::new(Gcd::<u64>::new(), multiples.clone());
+        let fh: FischerHeun<i64> = values.iter().copied().collect();
+        let reversed: Vec<Reverse<i64>> = values.iter().copied().map(Reverse).collect();
+        let fh_max = FischerHeun::new(reversed.clone());
+        assert_eq!(min.len(), n);
+        assert_eq!(fh.len(), n);
+        assert_eq!(fh.values(), &values[..]);
+        for _ in 0..2000 {
+            let (a, b) = (rng.index(n + 1), rng.index(n + 1));
+            let (lo, hi) = (a.min(b), a.max(b));
+            let s = &values[lo..hi];
+            assert_eq!(
+                min.query(lo..hi),
+                s.iter().copied().min().unwrap_or(i64::MAX)
+            );
+            assert_eq!(
+                max.query(lo..hi),
+                s.iter().copied().max().unwrap_or(i64::MIN)
+            );
+            let want = multiples[lo..hi].iter().fold(0, |a, &b| gcd(a, b));
+            assert_eq!(gcds.query(lo..hi), want);
+            assert_eq!(fh.argmin(lo..hi), leftmost_min(&values, lo, hi));
+            assert_eq!(fh.min(lo..hi), s.iter().min());
+            assert_eq!(fh_max.argmin(lo..hi), leftmost_min(&reversed, lo, hi));
+        }
+        if n > 0 {
+            let i = rng.index(n);
+            assert_eq!(*min.get(i), values[i]);
+            assert_eq!(min.query(i..=i), values[i]);
+            assert_eq!(fh.argmin(..), leftmost_min(&values, 0, n));
+        }
+    }
+}
+
+/// Ranges past the end are rejected.
+#[test]
+#[should_panic(expected = "range 1..4 out of bounds for length 3")]
+fn query_out_of_bounds_panics() {
+    let table: SparseTable<Min<i64>> = [1, 2, 3].into_iter().collect();
+    table.query(1..4);
+}
@@ -0,0 +1,115 @@
+//! One generic workload run through the `SpatialIndex` trait against every
+//! implementation, checked against a plain map of live points.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use datastructures::grid::SpatialHashGrid;
+use datastructures::kdtree::KdTree;
+use datastructures::octree::Octree;
+use datastructures::rtree::{Aabb, RTree};
+use datastructures::spatial_index::SpatialIndex;
+
+mod common;
+use common::Rng;
+
+type Live<Id> = HashMap<Id, ([f64; 3], u32)>;
+
+fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn random_point(rng: &mut Rng) -> [f64; 3] {
+    std::array::from_fn(|_| rng.range(0.0, 100.0))
+}
+
+/// Sorts ids by their debug form, since the trait does not require `Ord`.
+fn sorted_ids<Id: Debug>(mut ids: Vec<Id>) -> Vec<Id> {
+    ids.sort_by_key(|id| format!("{id:?}"));
+    ids
+}
+
+/// Random inserts and removals through the trait, starting from `live`,
+/// with every kind of query checked against a scan now and then.
+fn exercise<S>(mut index: S, seed: u64, mut live: Live<S::Id>)
+where
+    S: SpatialIndex<[f64; 3], Data = u32>,
+{
+    let mut rng = Rng(seed);
+    for step in 1000..7000u32 {
+        if rng.below(3) > 0 || live.is_empty() {
+            let p = random_point(&mut rng);
+            let id = index.insert(p, step).unwrap();
+            assert!(live.insert(id, (p, step)).is_none());
+        } else {
+            let id = *live.keys().nth(rng.index(live.len())).unwrap();
+            let entry = live.remove(&id).unwrap();
+            assert_eq!(index.remove(id), Some(entry));
+            assert!(index.get(id).is_none());
+        }
+        assert_eq!(index.len(), live.len());
+        if step % 97 != 0 {
+            continue;
+        }
+        for (id, (p, data)) in &live {
+            assert_eq!(index.get(*id), Some((*p, data)));
+        }
+        let q = random_point(&mut rng);
+        let r = rng.range(0.0, 30.0);
+        let want = live
+            .iter()
+            .filter(|(_, v)| distance(&v.0, &q) <= r)
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(sorted_ids(index.within_radius(&q, r)), sorted_ids(want));
+
+        let lo = q.map(|c| c - r);
+        let hi = q.map(|c| c + r * 0.5);
+        let want = live
+            .iter()
+            .filter(|(_, v)| (0..3).all(|a| lo[a] <= v.0[a] && v.0[a] <= hi[a]))
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(sorted_ids(index.query_aabb(&lo, &hi)), sorted_ids(want));
+
+        let got = index.nearest(&q, 7);
+        let mut want: Vec<f64> = live.values().map(|v| distance(&v.0, &q)).collect();
+        want.sort_by(f64::total_cmp);
+        want.truncate(7);
+        assert_eq!(got.len(), want.len());
+        for (g, w) in got.iter().zip(&want) {
+            assert!((g.1 - w).abs() < 1e-9);
+            assert!((distance(&live[&g.0].0, &q) - g.1).abs() < 1e-9);
+        }
+        assert_eq!(index.nearest_one(&q).map(|x| x.1), want.first().copied());
+    }
+}
+
+/// The k-d tree, R*-tree, hash grid and octree all behave the same
+/// through the trait, including a k-d tree built from a batch.
+#[test]
+fn every_index_matches_brute_force() {
+    exercise(KdTree::default(), 1, HashMap::new());
+    let batch: Vec<([f64; 3], u32)> = (0..500u32).map(|i| ([i as f64 / 5.0; 3], i)).collect();
+    let live = batch.iter().enumerate().map(|(i, &e)| (i, e)).collect();
+    exercise(batch.into_iter().collect::<KdTree<u32, 3>>(), 2, live);
+    exercise(RTree::new(), 3, HashMap::new());
+    exercise(SpatialHashGrid::new(7.0), 4, HashMap::new());
+    let bounds = Aabb::new([0.0; 3], [100.0; 3]);
+    exercise(Octree::<[f64; 3], u32>::new(bounds), 5, HashMap::new());
+}
+
+/// Bounded indexes reject points outside their bounds through the trait,
+/// and the trait is object safe.
+#[test]
+fn out_of_bounds_and_trait_objects() {
+    let mut octree = Octree::<[f64; 3], u32>::new(Aabb::new([0.0; 3], [1.0; 3]));
+    assert!(SpatialIndex::insert(&mut octree, [2.0; 3], 0).is_err());
+    let index: Box<dyn SpatialIndex<[f64; 3], Id = usize, Data = u32>> =
+        Box::new(KdTree::default());
+    assert!(index.is_empty());
+}
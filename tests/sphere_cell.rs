@@ -0,0 +1,105 @@
+//! The cube-face cell hierarchy on the sphere: cell ids, parents, children
+//! and neighbors, and cap coverings, at random points.
+
+use datastructures::sphere_cell::{covering, point_to_lat_lon, Cap, CellId};
+
+mod common;
+use common::Rng;
+
+/// A uniform random unit vector.
+fn random_point(rng: &mut Rng) -> [f64; 3] {
+    loop {
+        let p = [
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
+            rng.range(-1.0, 1.0),
+        ];
+        let n = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+        if n > 0.1 && n <= 1.0 {
+            return p.map(|c| c / n);
+        }
+    }
+}
+
+fn distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum::<f64>().sqrt()
+}
+
+/// A point's leaf cell round-trips through its raw id and lat/long, and
+/// at every level its ancestor contains it, has four children exactly one
+/// of which holds it, and four edge neighbors that share an edge with it.
+#[test]
+fn cell_hierarchy_at_random_points() {
+    let mut rng = Rng(2);
+    for _ in 0..2000 {
+        let p = random_point(&mut rng);
+        let leaf = CellId::from_point(&p);
+        assert!(leaf.is_leaf());
+        assert_eq!(CellId::from_raw(leaf.raw()), Some(leaf));
+        assert!(distance(&leaf.center(), &p) < 1e-8);
+        let (lat, lon) = point_to_lat_lon(&p);
+        assert_eq!(CellId::from_lat_lon(lat, lon), leaf);
+
+        let level = rng.below(31) as u8;
+        let cell = leaf.parent_at(level);
+        assert_eq!(cell.level(), level);
+        assert!(cell.contains(&leaf) && cell.contains_point(&p));
+        if let Some(children) = cell.children() {
+            assert_eq!(children.iter().filter(|k| k.contains(&leaf)).count(), 1);
+            for child in children {
+                assert_eq!(child.parent(), Some(cell));
+            }
+        }
+        for n in cell.edge_neighbors() {
+            assert_eq!(n.level(), level);
+            assert_ne!(n, cell);
+            assert!(
+                n.edge_neighbors().contains(&cell),
+                "level {level} {cell:?} {n:?}"
+            );
+            let shared = cell
+                .vertices()
+                .iter()
+                .filter(|v| n.vertices().iter().any(|w| distance(v, w) < 1e-12))
+                .count();
+            // Past level 25 the cells are too small to compare vertices
+            // at this tolerance.
+            if level <= 25 {
+                assert_eq!(shared, 2);
+            }
+        }
+    }
+    assert_eq!(CellId::from_raw(0), None);
+    assert_eq!(CellId::from_face(3).level(), 0);
+}
+
+/// A cap's covering is sorted and disjoint, covers every point inside the
+/// cap, and its coarser cells lie wholly within the cap.
+#[test]
+fn cap_coverings_cover_the_cap() {
+    let mut rng = Rng(4);
+    for round in 0..40 {
+        let center = random_point(&mut rng);
+        let radius = if round % 10 == 0 {
+            rng.range(1.6, 3.0)
+        } else {
+            rng.range(0.001, 0.5)
+        };
+        let cap = Cap::new(center, radius);
+        let cover = covering(&cap, 7);
+        for w in cover.windows(2) {
+            assert!(w[0].range_max() < w[1].range_min());
+        }
+        for _ in 0..500 {
+            let p = random_point(&mut rng);
+            if cap.contains(&p) {
+                assert!(cover.iter().any(|k| k.contains_point(&p)));
+            }
+        }
+        if radius <= 1.6 {
+            for cell in cover.iter().filter(|k| k.level() < 7) {
+                assert!(cell.vertices().iter().all(|v| cap.contains(v)));
+            }
+        }
+    }
+}
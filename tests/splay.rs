@@ -0,0 +1,63 @@
+//! The splay tree against `std::collections::BTreeMap`, with the splaying
+//! checked by the key left at the root.
+
+use std::collections::BTreeMap;
+
+use datastructures::splay::SplayTree;
+
+mod common;
+use common::Rng;
+
+/// Random operations match the map; a found lookup, a splay and an insert
+/// each leave their key at the root, while `peek` does not restructure.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(5);
+    let mut tree = SplayTree::new();
+    let mut map = BTreeMap::new();
+    for step in 0..50000u32 {
+        let k = rng.below(1000) as u32;
+        match rng.below(6) {
+            0 | 1 => assert_eq!(tree.remove(&k), map.remove(&k)),
+            2 => {
+                assert_eq!(tree.get(&k), map.get(&k));
+                if map.contains_key(&k) {
+                    assert_eq!(tree.root().map(|e| *e.0), Some(k));
+                }
+            }
+            3 => assert_eq!(tree.splay(&k), map.contains_key(&k)),
+            _ => {
+                assert_eq!(tree.insert(k, step), map.insert(k, step));
+                assert_eq!(tree.root().map(|e| *e.0), Some(k));
+            }
+        }
+        let root = tree.root().map(|e| *e.0);
+        assert_eq!(tree.peek(&k), map.get(&k));
+        assert_eq!(tree.root().map(|e| *e.0), root);
+        assert_eq!(tree.len(), map.len());
+        if step % 1000 != 0 {
+            continue;
+        }
+        assert!(tree.iter().eq(map.iter()));
+        let a = rng.below(1000) as u32;
+        let b = a + rng.below(300) as u32;
+        assert!(tree.range(a..b).eq(map.range(a..b)));
+        assert!(tree.range(a..=b).eq(map.range(a..=b)));
+        assert_eq!(tree.first_key_value(), map.first_key_value());
+        assert_eq!(tree.last_key_value(), map.last_key_value());
+    }
+}
+
+/// Looking up and then removing every key of a large tree in order, the
+/// access pattern with the worst single steps, completes.
+#[test]
+fn sequential_access() {
+    let mut tree: SplayTree<u32, u32> = (0..200000).map(|i| (i, i)).collect();
+    for i in 0..200000 {
+        assert_eq!(tree.get(&i), Some(&i));
+    }
+    for i in 0..200000 {
+        assert_eq!(tree.remove(&i), Some(i));
+    }
+    assert!(tree.is_empty());
+}
@@ -0,0 +1,126 @@
+//! The block decomposition against the lazy segment tree taking the same
+//! monoids and actions, for lengths and block sizes that leave partial
+//! blocks at either end of most ranges.
+
+use datastructures::algebra::{Assign, Increment, Max, Min, Monoid, Sum};
+use datastructures::segment_tree::SegmentTree;
+use datastructures::sqrt_decomposition::BlockDecomposition;
+
+mod common;
+use common::Rng;
+
+/// A random range `a..b` with `0 <= a <= b <= n`.
+fn random_range(rng: &mut Rng, n: usize) -> (usize, usize) {
+    let a = rng.index(n + 1);
+    let b = rng.index(n + 1);
+    (a.min(b), a.max(b))
+}
+
+/// Range increments under sums and range assignments under minima, mixed
+/// with point sets, with every query compared after each step.
+#[test]
+fn operations_match_segment_tree() {
+    let mut rng = Rng(3);
+    for n in [0, 1, 2, 5, 17, 100, 1000] {
+        for block in [1, 3, 7, 32] {
+            let values: Vec<i64> = (0..n).map(|_| rng.below(100) as i64).collect();
+            let mut sums: BlockDecomposition<Sum<i64>, Increment<i64>> =
+                BlockDecomposition::with_block_size(Sum::new(), values.clone(), block);
+            let mut sum_tree: SegmentTree<Sum<i64>, Increment<i64>> =
+                SegmentTree::new(Sum::new(), values.clone());
+            let mut mins: BlockDecomposition<Min<i64>, Assign<i64>> =
+                BlockDecomposition::new(Min::new(), values.clone());
+            let mut min_tree: SegmentTree<Min<i64>, Assign<i64>> =
+                SegmentTree::new(Min::new(), values);
+            assert_eq!((sums.len(), sums.block_size()), (n, block));
+            for _ in 0..2000 {
+                let (lo, hi) = random_range(&mut rng, n);
+                match rng.below(5) {
+                    0 => {
+                        let d = rng.below(20) as i64 - 10;
+                        sums.update(lo..hi, Increment(d));
+                        sum_tree.update(lo..hi, Increment(d));
+                    }
+                    1 => {
+                        let v = rng.below(100) as i64;
+                        mins.update(lo..hi, Assign(v));
+                        min_tree.update(lo..hi, Assign(v));
+                    }
+                    2 if n > 0 => {
+                        let (i, v) = (rng.index(n), rng.below(100) as i64);
+                        sums.set(i, v);
+                        sum_tree.set(i, v);
+                        mins.set(i, v);
+                        min_tree.set(i, v);
+                    }
+                    _ => {}
+                }
+                assert_eq!(sums.query(lo..hi), sum_tree.query(lo..hi));
+                assert_eq!(mins.query(lo..hi), min_tree.query(lo..hi));
+                assert_eq!(sums.total(), sum_tree.total());
+                assert_eq!(mins.total(), min_tree.total());
+                if n > 0 {
+                    let i = rng.index(n);
+                    assert_eq!(sums.get(i), sum_tree.get(i));
+                    assert_eq!(mins.get(i), min_tree.get(i));
+                }
+            }
+            assert_eq!(sums.to_vec(), sum_tree.to_vec());
+            assert_eq!(mins.to_vec(), min_tree.to_vec());
+        }
+    }
+}
+
+/// Searching for the longest prefix under a bound, with a pending update
+/// covering part of the sequence.
+#[test]
+fn max_right_matches_segment_tree() {
+    let mut rng = Rng(8);
+    for n in [0, 1, 9, 100, 500] {
+        for block in [1, 4, 22] {
+            let values: Vec<i64> = (0..n).map(|_| rng.below(10) as i64).collect();
+            let mut blocks: BlockDecomposition<Sum<i64>, Increment<i64>> =
+                BlockDecomposition::with_block_size(Sum::new(), values.clone(), block);
+            let mut tree: SegmentTree<Sum<i64>, Increment<i64>> =
+                SegmentTree::new(Sum::new(), values);
+            blocks.update(n / 3..n / 2, Increment(2));
+            tree.update(n / 3..n / 2, Increment(2));
+            for _ in 0..200 {
+                let start = rng.index(n + 1);
+                let limit = rng.below(200) as i64;
+                let end = blocks.max_right(start, |&s| s <= limit);
+                assert_eq!(end, tree.max_right(start, |&s| s <= limit));
+                assert!(blocks.query(start..end) <= limit);
+            }
+        }
+    }
+}
+
+/// Without an action the decomposition still takes point sets, and it can
+/// be collected with the default block size.
+#[test]
+fn point_updates_only() {
+    let mut blocks: BlockDecomposition<Max<i64>> = (0..50).collect();
+    assert!(blocks.block_size() * blocks.block_size() >= 49);
+    assert_eq!(blocks.query(10..20), 19);
+    blocks.set(12, 100);
+    assert_eq!(blocks.query(..13), 100);
+    assert_eq!(blocks.query(13..), 49);
+    assert_eq!(blocks.query(5..5), i64::MIN);
+    assert_eq!(blocks.monoid().identity(), i64::MIN);
+    assert!(!blocks.is_empty());
+}
+
+/// A block must hold at least one element.
+#[test]
+#[should_panic(expected = "blocks must hold at least one element")]
+fn zero_block_size_panics() {
+    BlockDecomposition::<Sum<i64>>::with_block_size(Sum::new(), vec![1], 0);
+}
+
+/// Point sets must lie within the sequence.
+#[test]
+#[should_panic(expected = "index 3 out of bounds for length 3")]
+fn set_out_of_bounds_panics() {
+    BlockDecomposition::<Sum<i64>>::new(Sum::new(), vec![1, 2, 3]).set(3, 0);
+}
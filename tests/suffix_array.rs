@@ -0,0 +1,86 @@
+//! The suffix array against suffixes sorted directly, and its LCP array,
+//! pattern search and longest repeat against direct comparisons.
+
+use datastructures::suffix_array::SuffixArray;
+
+mod common;
+use common::Rng;
+
+/// `len` letters from the first `sigma` of the alphabet.
+fn random_text(rng: &mut Rng, len: usize, sigma: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| (rng.below(sigma) as u8).wrapping_add(b'a'))
+        .collect()
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Texts from empty to 300 letters over alphabets of one to five letters:
+/// the suffix order, every LCP, every pattern's positions and the longest
+/// repeat match a direct computation.
+#[test]
+fn matches_sorted_suffixes() {
+    let mut rng = Rng(19);
+    for round in 0..400 {
+        let n = if round < 10 { round } else { rng.index(300) };
+        let sigma = 1 + rng.below(if round % 3 == 0 { 2 } else { 5 });
+        let text = random_text(&mut rng, n, sigma);
+        let sa = SuffixArray::build(&text);
+        assert_eq!(sa.text(), &text[..]);
+        assert_eq!(sa.len(), n);
+        let mut want: Vec<u32> = (0..n as u32).collect();
+        want.sort_by(|&a, &b| text[a as usize..].cmp(&text[b as usize..]));
+        assert_eq!(
+            sa.suffixes(),
+            &want[..],
+            "{}",
+            String::from_utf8_lossy(&text)
+        );
+        let lcp: Vec<usize> = (1..n)
+            .map(|i| common_prefix(&text[want[i - 1] as usize..], &text[want[i] as usize..]))
+            .collect();
+        for (i, &l) in lcp.iter().enumerate() {
+            assert_eq!(sa.lcp()[i + 1] as usize, l);
+        }
+
+        for _ in 0..20 {
+            let len = rng.index(5);
+            let pattern = random_text(&mut rng, len, sigma);
+            let want: Vec<u32> = (0..n)
+                .filter(|&i| text[i..].starts_with(&pattern))
+                .map(|i| i as u32)
+                .collect();
+            let mut got = sa.positions(&pattern).to_vec();
+            got.sort();
+            assert_eq!(got, want);
+            assert_eq!(sa.count(&pattern), want.len());
+            assert_eq!(sa.contains(&pattern), !want.is_empty());
+        }
+
+        // The longest repeat is the longest common prefix of two suffixes.
+        let best = lcp.iter().copied().max().filter(|&l| l > 0);
+        let repeated = sa.longest_repeated();
+        assert_eq!(repeated.map(<[u8]>::len), best);
+        if let Some(r) = repeated {
+            let starts = (0..=n - r.len()).filter(|&i| text[i..].starts_with(r));
+            assert!(starts.count() >= 2);
+        }
+    }
+}
+
+/// A long text over four letters comes out sorted.
+#[test]
+fn long_text_is_sorted() {
+    let text: Vec<u8> = (0..200_000u64)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8 % 4)
+        .collect();
+    let sa = SuffixArray::build(&text);
+    assert_eq!(sa.len(), text.len());
+    let suffixes = sa.suffixes();
+    assert!(suffixes
+        .windows(2)
+        .step_by(17)
+        .all(|w| text[w[0] as usize..] < text[w[1] as usize..]));
+}
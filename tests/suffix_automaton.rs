@@ -0,0 +1,61 @@
+//! The suffix automaton against sets of substrings, grown one byte at a
+//! time, for substring tests, distinct-substring counts and the longest
+//! common substring with another text.
+
+use std::collections::HashSet;
+
+use datastructures::suffix_automaton::SuffixAutomaton;
+
+mod common;
+use common::Rng;
+
+fn random_text(rng: &mut Rng, len: usize, sigma: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| (rng.below(sigma) as u8).wrapping_add(b'a'))
+        .collect()
+}
+
+fn is_substring(text: &[u8], pattern: &[u8]) -> bool {
+    pattern.is_empty() || text.windows(pattern.len()).any(|w| w == pattern)
+}
+
+/// After every push the distinct-substring count matches a set of every
+/// substring; the finished automaton stays within `2n` states and answers
+/// substring and longest-common-substring queries like a scan.
+#[test]
+fn matches_substring_sets() {
+    let mut rng = Rng(23);
+    for round in 0..300 {
+        let n = rng.index(60);
+        let sigma = 1 + round % 4;
+        let text = random_text(&mut rng, n, sigma);
+        let mut sam = SuffixAutomaton::new();
+        let mut substrings: HashSet<&[u8]> = HashSet::new();
+        for (i, &b) in text.iter().enumerate() {
+            sam.push(b);
+            substrings.extend((0..=i).map(|start| &text[start..=i]));
+            assert_eq!(sam.count_distinct_substrings(), substrings.len() as u64);
+        }
+        assert_eq!(sam.len(), n);
+        assert!(sam.state_count() <= (2 * n).max(2));
+        let built = SuffixAutomaton::build(&text);
+        assert_eq!(built.count_distinct_substrings(), substrings.len() as u64);
+
+        for _ in 0..30 {
+            let len = rng.index(6);
+            let pattern = random_text(&mut rng, len, sigma);
+            assert_eq!(sam.contains(&pattern), is_substring(&text, &pattern));
+        }
+
+        let len = rng.index(40);
+        let other = random_text(&mut rng, len, sigma);
+        let lcs = sam.longest_common_substring(&other);
+        let best = (0..=other.len())
+            .rev()
+            .find(|&l| l == 0 || other.windows(l).any(|w| is_substring(&text, w)))
+            .unwrap();
+        assert_eq!(lcs.len(), best);
+        assert!(sam.contains(lcs));
+        assert!(is_substring(&other, lcs));
+    }
+}
@@ -0,0 +1,105 @@
+//! The t-digest's quantiles and CDF against a sorted sample, in the middle
+//! and the tails, for one digest and for merged halves.
+
+use datastructures::tdigest::TDigest;
+
+mod common;
+use common::Rng;
+
+/// `n` samples of an exponential distribution with mean 10, sorted.
+fn exponential(rng: &mut Rng, n: usize) -> Vec<f64> {
+    let mut xs: Vec<f64> = (0..n).map(|_| -(1.0 - rng.unit()).ln() * 10.0).collect();
+    xs.sort_by(f64::total_cmp);
+    xs
+}
+
+/// Checks that each quantile estimate has about the right rank, with the
+/// allowed error shrinking toward the tails, and that the CDF inverts the
+/// exact quantiles.
+fn check(digest: &mut TDigest, xs: &[f64]) {
+    let n = xs.len() as f64;
+    for q in [0.0, 0.001, 0.01, 0.1, 0.5, 0.9, 0.99, 0.999, 0.9999, 1.0] {
+        let estimate = digest.quantile(q).unwrap();
+        let rank = xs.partition_point(|&x| x < estimate) as f64 / n;
+        let tolerance = 0.02 * (q * (1.0 - q)).sqrt() + 2e-4;
+        assert!((rank - q).abs() < tolerance, "q {q}: rank {rank}");
+        let exact = xs[((q * n) as usize).min(xs.len() - 1)];
+        let cdf = digest.cdf(exact).unwrap();
+        assert!((cdf - q).abs() < tolerance, "q {q}: cdf {cdf}");
+    }
+    assert_eq!(digest.quantile(0.0), xs.first().copied());
+    assert_eq!(digest.quantile(1.0), xs.last().copied());
+}
+
+/// A digest of 200,000 samples in random order, in under a hundred
+/// centroids.
+#[test]
+fn quantiles_match_sorted_sample() {
+    let mut rng = Rng(11);
+    let xs = exponential(&mut rng, 200_000);
+    let mut shuffled = xs.clone();
+    for i in (1..shuffled.len()).rev() {
+        shuffled.swap(i, rng.index(i + 1));
+    }
+    let mut digest: TDigest = shuffled.into_iter().collect();
+    digest.compress();
+    assert!(digest.centroids().len() < 100);
+    assert_eq!(digest.count(), xs.len() as f64);
+    assert_eq!(digest.min(), xs.first().copied());
+    assert_eq!(digest.max(), xs.last().copied());
+    check(&mut digest, &xs);
+}
+
+/// Digests of alternate samples merge into one as accurate as a digest of
+/// them all, and a higher compression keeps more centroids.
+#[test]
+fn merged_halves_match_sorted_sample() {
+    let mut rng = Rng(12);
+    let xs = exponential(&mut rng, 200_000);
+    let (mut a, mut b) = (TDigest::new(), TDigest::new());
+    for (i, &x) in xs.iter().enumerate() {
+        if i % 2 == 0 {
+            a.insert(x);
+        } else {
+            b.insert(x);
+        }
+    }
+    a.merge(&b);
+    assert_eq!(a.count(), xs.len() as f64);
+    check(&mut a, &xs);
+
+    let mut fine = TDigest::with_compression(500.0);
+    for &x in &xs {
+        fine.insert(x);
+    }
+    fine.compress();
+    assert_eq!(fine.compression(), 500.0);
+    assert!(fine.centroids().len() > 2 * a.centroids().len());
+}
+
+/// Empty, one-sample and weighted digests.
+#[test]
+fn small_digests() {
+    let mut digest = TDigest::new();
+    assert!(digest.is_empty());
+    assert_eq!(digest.quantile(0.5), None);
+    assert_eq!(digest.cdf(0.0), None);
+    digest.insert(3.0);
+    assert_eq!(digest.quantile(0.3), Some(3.0));
+    assert_eq!(digest.cdf(3.0), Some(1.0));
+    assert_eq!(digest.cdf(2.9), Some(0.0));
+
+    digest.clear();
+    digest.insert_weighted(0.0, 3.0);
+    digest.insert_weighted(10.0, 1.0);
+    assert_eq!(digest.count(), 4.0);
+    assert!(digest.quantile(0.25).unwrap() < 5.0);
+    assert!(digest.cdf(5.0).unwrap() > 0.5);
+}
+
+/// NaN has no place in the order.
+#[test]
+#[should_panic(expected = "t-digest samples must not be NaN")]
+fn nan_sample_panics() {
+    TDigest::new().insert(f64::NAN);
+}
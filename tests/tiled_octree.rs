@@ -0,0 +1,126 @@
+//! The tiled octree against a list of live points, under random inserts,
+//! moves and removals across many chunks, including points on chunk
+//! boundaries.
+
+use datastructures::geom::Aabb;
+use datastructures::tiled_octree::{TiledId, TiledOctree};
+
+mod common;
+use common::{sorted, Rng};
+
+fn distance_squared(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+/// Random operations over points spread to `spread` in each direction; a
+/// fifth of the points sit on the chunk boundaries `x = k * size` and
+/// `z = -size`.
+fn matches_scan(size: f64, spread: f64, seed: u64) {
+    let mut rng = Rng(seed);
+    let mut tree: TiledOctree<[f64; 3], usize> = TiledOctree::with_limits(size, 4, 12);
+    let mut live: Vec<(TiledId, [f64; 3], usize)> = Vec::new();
+    let point = |rng: &mut Rng| -> [f64; 3] {
+        if rng.below(5) == 0 {
+            let x = (rng.below(20) as f64 - 10.0) * size;
+            [x, rng.range(-spread, spread), -size]
+        } else {
+            std::array::from_fn(|_| rng.range(-spread, spread))
+        }
+    };
+    for step in 0..4000 {
+        let op = rng.below(10);
+        if op < 6 || live.is_empty() {
+            let p = point(&mut rng);
+            live.push((tree.insert(p, step).unwrap(), p, step));
+        } else if op < 8 {
+            let (id, p, data) = live.swap_remove(rng.index(live.len()));
+            assert_eq!(tree.remove(id), Some((p, data)));
+        } else {
+            let i = rng.index(live.len());
+            let p = if rng.below(2) == 0 {
+                point(&mut rng)
+            } else {
+                let [x, y, z] = live[i].1;
+                [x + rng.range(-size, size), y, z]
+            };
+            assert_eq!(tree.relocate(live[i].0, p).unwrap(), Some(live[i].1));
+            live[i].1 = p;
+        }
+        assert_eq!(tree.len(), live.len());
+        if step % 53 != 0 {
+            continue;
+        }
+
+        for x in &live {
+            assert_eq!(tree.get(x.0), Some((&x.1, &x.2)));
+        }
+        let q = point(&mut rng);
+        let got = tree.nearest(q, 6);
+        let mut want: Vec<f64> = live
+            .iter()
+            .map(|x| distance_squared(&x.1, &q).sqrt())
+            .collect();
+        want.sort_by(f64::total_cmp);
+        want.truncate(6);
+        assert_eq!(got.len(), want.len());
+        for (g, w) in got.iter().zip(&want) {
+            assert!((g.1 - w).abs() < 1e-9);
+        }
+
+        let r = spread * 0.2;
+        let got: Vec<_> = tree.within_radius(q, r).map(|x| x.0).collect();
+        let want: Vec<_> = live
+            .iter()
+            .filter(|x| distance_squared(&x.1, &q) <= r * r)
+            .map(|x| x.0)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+        let query = Aabb::new(q.map(|c| c - r), [q[0] + r, q[1] + 2.0 * r, q[2] + r]);
+        let got: Vec<_> = tree.within_box(query).map(|x| x.0).collect();
+        let want: Vec<_> = live
+            .iter()
+            .filter(|x| query.contains_point(&x.1))
+            .map(|x| x.0)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+        let everything = Aabb::new([-1e300; 3], [1e300; 3]);
+        assert_eq!(tree.within_box(everything).count(), live.len());
+        assert_eq!(tree.iter().count(), live.len());
+    }
+    assert!(tree.occupied_chunks() > 1);
+    while let Some((id, _, _)) = live.pop() {
+        tree.remove(id).unwrap();
+    }
+    assert_eq!(tree.occupied_chunks(), 0);
+}
+
+/// Queries agree with a scan for small and large chunks, over ranges from
+/// a few chunks wide to a million.
+#[test]
+fn queries_match_brute_force() {
+    matches_scan(10.0, 100.0, 11);
+    matches_scan(1.0, 1e6, 12);
+    matches_scan(64.0, 50.0, 13);
+}
+
+/// Points whose chunk coordinates cannot be represented are rejected,
+/// distant chunks work together, and chunks round toward negative
+/// infinity.
+#[test]
+fn chunk_coordinates() {
+    let mut tree: TiledOctree<[f64; 3], ()> = TiledOctree::new(1.0);
+    assert!(tree.insert([f64::NAN, 0.0, 0.0], ()).is_err());
+    assert!(tree.insert([1e300, 0.0, 0.0], ()).is_err());
+    assert_eq!(tree.occupied_chunks(), 0);
+    let a = tree.insert([0.1, 0.0, 0.0], ()).unwrap();
+    tree.insert([1e9, 0.0, 0.0], ()).unwrap();
+    assert!(tree.relocate(a, [f64::INFINITY, 0.0, 0.0]).is_err());
+    assert_eq!(tree.get(a), Some((&[0.1, 0.0, 0.0], &())));
+    assert_eq!(tree.occupied_chunks(), 2);
+    assert_eq!(tree.nearest([1e9 - 5.0, 0.0, 0.0], 2).len(), 2);
+    assert_eq!(tree.chunk_of(&[-0.5, 2.0, -1.0]), Some([-1, 2, -1]));
+    assert_eq!(
+        tree.chunk_bounds([-1, 2, -1]),
+        Aabb::new([-1.0, 2.0, -1.0], [0.0, 3.0, 0.0])
+    );
+}
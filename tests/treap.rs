@@ -0,0 +1,67 @@
+//! The treap against `std::collections::BTreeMap`, under random inserts,
+//! removals, splits and merges, with order statistics checked throughout.
+
+use std::collections::BTreeMap;
+
+use datastructures::treap::Treap;
+
+mod common;
+use common::Rng;
+
+/// Compares the whole treap with `map`, including `select` and `rank` at
+/// every position.
+fn check(treap: &Treap<u32, u32>, map: &BTreeMap<u32, u32>) {
+    assert_eq!(treap.len(), map.len());
+    assert!(treap.iter().eq(map.iter()));
+    assert_eq!(treap.first_key_value(), map.first_key_value());
+    assert_eq!(treap.last_key_value(), map.last_key_value());
+    for (i, (k, v)) in map.iter().enumerate() {
+        assert_eq!(treap.select(i), Some((k, v)));
+        assert_eq!(treap.rank(k), i);
+    }
+    assert_eq!(treap.select(map.len()), None);
+}
+
+/// A split matches `BTreeMap::split_off`, and merging the halves back, or
+/// carrying on with the upper half alone, keeps matching.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(3);
+    let mut treap = Treap::new();
+    let mut map = BTreeMap::new();
+    for step in 0..20000u32 {
+        let k = rng.below(1000) as u32;
+        match rng.below(10) {
+            0 => {
+                let mut upper = treap.split(&k);
+                let mut map_upper = map.split_off(&k);
+                check(&treap, &map);
+                check(&upper, &map_upper);
+                if rng.below(2) == 0 {
+                    treap.merge(upper);
+                    map.append(&mut map_upper);
+                } else {
+                    std::mem::swap(&mut treap, &mut upper);
+                    std::mem::swap(&mut map, &mut map_upper);
+                }
+            }
+            1..=3 => assert_eq!(treap.remove(&k), map.remove(&k)),
+            _ => assert_eq!(treap.insert(k, step), map.insert(k, step)),
+        }
+        assert_eq!(treap.get(&k), map.get(&k));
+        assert_eq!(treap.rank(&k), map.range(..k).count());
+        if step % 1000 == 0 {
+            check(&treap, &map);
+        }
+    }
+    check(&treap, &map);
+}
+
+/// A treap collected from sorted input selects by position.
+#[test]
+fn large_treap_selects() {
+    let treap: Treap<u32, u32> = (0..100000).map(|i| (i, i)).collect();
+    assert_eq!(treap.len(), 100000);
+    assert_eq!(treap.select(77777), Some((&77777, &77777)));
+    assert_eq!(treap.rank(&77777), 77777);
+}
@@ -0,0 +1,91 @@
+//! The Treiber stack against a `Vec` on one thread, and under concurrent
+//! pushes and pops, with every value delivered and dropped exactly once.
+
+use std::sync::Arc;
+use std::thread;
+
+use datastructures::treiber_stack::TreiberStack;
+
+mod common;
+use common::Rng;
+
+/// Random pushes and pops on one thread match a `Vec` used as a stack.
+#[test]
+fn operations_match_vec() {
+    let mut rng = Rng(9);
+    let stack = TreiberStack::new();
+    let mut model = Vec::new();
+    for step in 0..20000u32 {
+        if rng.below(2) == 0 {
+            stack.push(step);
+            model.push(step);
+        } else {
+            assert_eq!(stack.pop(), model.pop());
+        }
+        assert_eq!(stack.is_empty(), model.is_empty());
+    }
+}
+
+/// Eight threads each push their own values and pop as they go; popped
+/// and leftover values together are every pushed value once.
+#[test]
+fn concurrent_values_are_popped_once() {
+    const PER_THREAD: u64 = 20000;
+    let stack = Arc::new(TreiberStack::default());
+    let threads: Vec<_> = (0..8)
+        .map(|t| {
+            let stack = Arc::clone(&stack);
+            thread::spawn(move || {
+                let mut popped = Vec::new();
+                for i in 0..PER_THREAD {
+                    stack.push(t * PER_THREAD + i);
+                    if i % 2 == 1 {
+                        popped.extend(stack.pop());
+                    }
+                }
+                popped
+            })
+        })
+        .collect();
+    let mut all: Vec<u64> = threads
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+    while let Some(v) = stack.pop() {
+        all.push(v);
+    }
+    all.sort_unstable();
+    assert_eq!(all, (0..8 * PER_THREAD).collect::<Vec<_>>());
+    assert!(stack.is_empty());
+}
+
+/// Popped values are handed over, not dropped, and values still on the
+/// stack are dropped with it.
+#[test]
+fn values_are_dropped_once() {
+    let value = Arc::new(());
+    let stack = Arc::new(TreiberStack::new());
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let (stack, value) = (Arc::clone(&stack), Arc::clone(&value));
+            thread::spawn(move || {
+                let mut kept = Vec::new();
+                for i in 0..5000 {
+                    stack.push(Arc::clone(&value));
+                    if i % 3 == 0 {
+                        kept.extend(stack.pop());
+                    }
+                }
+                kept
+            })
+        })
+        .collect();
+    let kept: Vec<Arc<()>> = threads
+        .into_iter()
+        .flat_map(|t| t.join().unwrap())
+        .collect();
+    assert_eq!(Arc::strong_count(&value), 1 + 4 * 5000);
+    drop(kept);
+    drop(stack);
+    assert_eq!(Arc::strong_count(&value), 1);
+}
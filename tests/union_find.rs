@@ -0,0 +1,133 @@
+//! The union-find against an array of set labels, with payloads merged per
+//! set, rollback against saved copies of the labels, and the weighted
+//! union-find against hidden potentials.
+
+use datastructures::algebra::Sum;
+use datastructures::union_find::{Contradiction, UnionFind, WeightedUnionFind};
+
+mod common;
+use common::Rng;
+
+/// Relabels `b`'s set as `a`'s, returning whether they were apart.
+fn merge(labels: &mut [usize], a: usize, b: usize) -> bool {
+    let (la, lb) = (labels[a], labels[b]);
+    for l in labels.iter_mut().filter(|l| **l == lb) {
+        *l = la;
+    }
+    la != lb
+}
+
+fn set_count(labels: &[usize]) -> usize {
+    let mut distinct = labels.to_vec();
+    distinct.sort();
+    distinct.dedup();
+    distinct.len()
+}
+
+/// Random unions, with payloads holding each set's size and least member.
+#[test]
+fn unions_match_labels() {
+    let mut rng = Rng(1);
+    let n = 50;
+    let mut plain = UnionFind::new(n);
+    let mut sized = UnionFind::with_payloads((0..n).map(|i| (1, i)).collect());
+    let mut labels: Vec<usize> = (0..n).collect();
+    assert_eq!(plain.len(), n);
+    assert_eq!(plain.set_count(), n);
+    for _ in 0..200 {
+        let (a, b) = (rng.index(n), rng.index(n));
+        let merged = merge(&mut labels, a, b);
+        assert_eq!(plain.union(a, b), merged);
+        let combine = |x: &(usize, usize), y: &(usize, usize)| (x.0 + y.0, x.1.min(y.1));
+        assert_eq!(sized.union_with(a, b, combine), merged);
+        for x in 0..n {
+            let members: Vec<usize> = (0..n).filter(|&y| labels[y] == labels[x]).collect();
+            assert_eq!(*sized.payload(x), (members.len(), members[0]));
+            assert_eq!(plain.same(x, a), labels[x] == labels[a]);
+            assert_eq!(plain.find(x), plain.root(x));
+        }
+        assert_eq!(plain.set_count(), set_count(&labels));
+    }
+}
+
+/// Snapshots taken at random and rolled back to in stack order restore
+/// the sets and payloads as they were.
+#[test]
+fn rollback_restores_snapshots() {
+    let mut rng = Rng(2);
+    let n = 30;
+    let mut uf = UnionFind::with_payloads(vec![1; n]).with_rollback();
+    let mut saved: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut labels: Vec<usize> = (0..n).collect();
+    for _ in 0..2000 {
+        match rng.below(4) {
+            0 => saved.push((uf.snapshot(), labels.clone())),
+            1 => {
+                if let Some((snapshot, old)) = saved.pop() {
+                    uf.rollback(snapshot);
+                    labels = old;
+                }
+            }
+            _ => {
+                let (a, b) = (rng.index(n), rng.index(n));
+                let merged = merge(&mut labels, a, b);
+                assert_eq!(uf.union_with(a, b, |x, y| x + y), merged);
+            }
+        }
+        for x in 0..n {
+            let size = labels.iter().filter(|&&l| l == labels[x]).count();
+            assert_eq!(*uf.payload(x), size);
+            assert_eq!(uf.root(x) == uf.root(0), labels[x] == labels[0]);
+        }
+        assert_eq!(uf.set_count(), set_count(&labels));
+    }
+    uf.rollback(0);
+    assert_eq!(uf.set_count(), n);
+}
+
+/// Rolling back needs the history that rollback mode keeps.
+#[test]
+#[should_panic(expected = "union-find is not in rollback mode")]
+fn snapshot_without_rollback_mode_panics() {
+    UnionFind::new(3).snapshot();
+}
+
+/// A snapshot cannot be rolled forward to.
+#[test]
+#[should_panic(expected = "snapshot 1 is later than the present")]
+fn rollback_to_the_future_panics() {
+    UnionFind::new(3).with_rollback().rollback(1);
+}
+
+/// Relations drawn from hidden potentials are accepted and imply the right
+/// offset between every related pair; relations off by one within a set
+/// are rejected with the offset already implied, and change nothing.
+#[test]
+fn weighted_offsets_match_potentials() {
+    let mut rng = Rng(4);
+    let n = 20;
+    for _ in 0..50 {
+        let potential: Vec<i64> = (0..n).map(|_| rng.below(100) as i64).collect();
+        let mut uf = WeightedUnionFind::new(Sum::new(), n);
+        let mut labels: Vec<usize> = (0..n).collect();
+        for _ in 0..100 {
+            let (a, b) = (rng.index(n), rng.index(n));
+            let offset = potential[a] - potential[b];
+            if labels[a] == labels[b] && rng.below(5) == 0 {
+                let implied = Contradiction { implied: offset };
+                assert_eq!(uf.relate(a, b, &(offset + 1)), Err(implied));
+            } else {
+                let merged = merge(&mut labels, a, b);
+                assert_eq!(uf.relate(a, b, &offset), Ok(merged));
+            }
+            assert_eq!(uf.set_count(), set_count(&labels));
+            for x in 0..n {
+                for y in 0..n {
+                    let want = (labels[x] == labels[y]).then(|| potential[x] - potential[y]);
+                    assert_eq!(uf.offset(x, y), want);
+                    assert_eq!(uf.same(x, y), want.is_some());
+                }
+            }
+        }
+    }
+}
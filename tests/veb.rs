@@ -0,0 +1,71 @@
+//! The van Emde Boas tree against a `BTreeSet`, for key widths from one bit
+//! to the full 32, with keys drawn from a window of the universe so that
+//! wide trees see dense clusters.
+
+use std::collections::BTreeSet;
+
+use datastructures::veb::VebTree;
+
+mod common;
+use common::Rng;
+
+/// Random inserts, removals and lookups, with successor and predecessor
+/// queried at every step, including queries past the universe.
+#[test]
+fn operations_match_btreeset() {
+    for bits in [1u32, 3, 6, 7, 8, 11, 13, 16, 20, 32] {
+        let mut rng = Rng(u64::from(bits) + 100);
+        let universe = 1u64 << bits;
+        let span = universe.min(3000);
+        let offset = rng.below(universe - span + 1);
+        let mut tree = VebTree::new(bits);
+        assert_eq!((tree.bits(), tree.universe()), (bits, universe));
+        let mut model = BTreeSet::new();
+        for step in 0..20000 {
+            let k = (offset + rng.below(span)) as u32;
+            match rng.below(3) {
+                0 => assert_eq!(tree.insert(k), model.insert(k)),
+                1 => assert_eq!(tree.remove(k), model.remove(&k)),
+                _ => assert_eq!(tree.contains(k), model.contains(&k)),
+            }
+            assert_eq!(tree.len(), model.len());
+            assert_eq!(tree.is_empty(), model.is_empty());
+            let q = (offset + rng.below(span + 2)).min(u64::from(u32::MAX)) as u32;
+            let inside = u64::from(q) < universe;
+            let above = model.range(q.saturating_add(1)..).next().copied();
+            let above = above.filter(|_| inside && q != u32::MAX);
+            assert_eq!(tree.successor(q), above, "{bits} {q}");
+            assert_eq!(tree.predecessor(q), model.range(..q).next_back().copied());
+            assert_eq!(tree.min(), model.first().copied());
+            assert_eq!(tree.max(), model.last().copied());
+            if step % 1000 == 0 {
+                assert!(tree.iter().eq(model.iter().copied()));
+            }
+        }
+        tree.clear();
+        assert_eq!(tree.min(), None);
+    }
+}
+
+/// The extreme keys of the full 32-bit universe.
+#[test]
+fn full_width_extremes() {
+    let mut tree = VebTree::new(32);
+    tree.extend([u32::MAX, 0, 1 << 16]);
+    assert_eq!(tree.successor(0), Some(1 << 16));
+    assert_eq!(tree.successor(1 << 16), Some(u32::MAX));
+    assert_eq!(tree.predecessor(u32::MAX), Some(1 << 16));
+    assert_eq!(tree.successor(u32::MAX), None);
+    assert_eq!(tree.predecessor(0), None);
+    assert!(tree.remove(1 << 16));
+    assert_eq!(tree.iter().collect::<Vec<_>>(), [0, u32::MAX]);
+    assert!(!VebTree::new(4).remove(16));
+    assert!(!VebTree::new(4).contains(16));
+}
+
+/// Keys must fit the tree's width.
+#[test]
+#[should_panic(expected = "key outside the universe")]
+fn key_outside_universe_panics() {
+    VebTree::new(4).insert(16);
+}
@@ -0,0 +1,69 @@
+//! The wavelet tree's rank, select, range quantiles and range counts
+//! against scans of the sequence, from one symbol to 40-bit values.
+
+use datastructures::wavelet_tree::WaveletTree;
+
+mod common;
+use common::{sorted, Rng};
+
+/// Random queries over sequences of various lengths and alphabets, with
+/// values looked up both present and not.
+#[test]
+fn queries_match_scan() {
+    let mut rng = Rng(9);
+    for (n, sigma) in [
+        (0, 1),
+        (1, 1),
+        (50, 1),
+        (300, 5),
+        (2000, 100),
+        (1000, 1 << 40),
+    ] {
+        let values: Vec<u64> = (0..n).map(|_| rng.below(sigma)).collect();
+        let tree = WaveletTree::new(&values);
+        assert_eq!(tree.len(), n);
+        assert_eq!(tree.is_empty(), n == 0);
+        assert!(tree.iter().eq(values.iter().copied()));
+        for _ in 0..300 {
+            let x = if n > 0 && rng.below(2) == 0 {
+                values[rng.index(n)]
+            } else {
+                rng.below(sigma + 3)
+            };
+            let i = rng.index(n + 1);
+            assert_eq!(
+                tree.rank(x, i),
+                values[..i].iter().filter(|&&y| y == x).count()
+            );
+            if i < n {
+                assert_eq!(tree.get(i), values[i]);
+            }
+            let positions: Vec<usize> = (0..n).filter(|&j| values[j] == x).collect();
+            let k = rng.index(positions.len() + 1);
+            assert_eq!(tree.select(x, k), positions.get(k).copied());
+
+            let (a, b) = (rng.index(n + 1), rng.index(n + 1));
+            let (a, b) = (a.min(b), a.max(b));
+            let window = sorted(values[a..b].to_vec());
+            let k = rng.index(window.len() + 1);
+            assert_eq!(tree.quantile(a..b, k), window.get(k).copied());
+            let (lo, hi) = (rng.below(sigma + 2), rng.below(sigma + 2));
+            let want = window.iter().filter(|&&y| lo <= y && y < hi).count();
+            assert_eq!(tree.count(a..b, lo..hi), want);
+        }
+    }
+}
+
+/// The largest value works like any other, and an empty value range
+/// counts nothing.
+#[test]
+fn extreme_values() {
+    let tree = WaveletTree::new(&[u64::MAX, 0, u64::MAX]);
+    assert_eq!(tree.bits(), 64);
+    assert_eq!(tree.rank(u64::MAX, 3), 2);
+    assert_eq!(tree.select(u64::MAX, 1), Some(2));
+    assert_eq!(tree.quantile(.., 2), Some(u64::MAX));
+    assert_eq!(tree.count(.., 1..u64::MAX), 0);
+    assert_eq!(tree.count(.., 0..1), 1);
+    assert_eq!(tree.count(1..3, 5..5), 0);
+}
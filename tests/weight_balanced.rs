@@ -0,0 +1,231 @@
+//! The persistent weight-balanced tree against a `BTreeMap`, with saved
+//! versions checked to stay intact under later writes, and its join-based
+//! operations and the differences between versions against the same
+//! operations on the model.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use datastructures::weight_balanced::{Change, WeightBalancedTree};
+
+mod common;
+use common::Rng;
+
+type Tree = WeightBalancedTree<u64, u64>;
+
+fn entries(tree: &Tree) -> BTreeMap<u64, u64> {
+    tree.iter().map(|(&k, &v)| (k, v)).collect()
+}
+
+/// A change as the key with the old and new values, either of them absent.
+type Owned = (u64, Option<u64>, Option<u64>);
+
+/// The change with its key and values copied out.
+fn owned(change: Change<'_, u64, u64>) -> Owned {
+    match change {
+        Change::Added(&k, &v) => (k, None, Some(v)),
+        Change::Removed(&k, &v) => (k, Some(v), None),
+        Change::Changed(&k, &a, &b) => (k, Some(a), Some(b)),
+    }
+}
+
+/// The keys whose values differ between the two maps, in key order.
+fn model_diff(old: &BTreeMap<u64, u64>, new: &BTreeMap<u64, u64>) -> Vec<Owned> {
+    let keys: BTreeSet<u64> = old.keys().chain(new.keys()).copied().collect();
+    keys.into_iter()
+        .map(|k| (k, old.get(&k).copied(), new.get(&k).copied()))
+        .filter(|(_, a, b)| a != b)
+        .collect()
+}
+
+/// Random inserts and removals, with order statistics, split, join and
+/// merge checked periodically, and every saved version unchanged at the
+/// end.
+#[test]
+fn operations_match_btreemap() {
+    let mut rng = Rng(11);
+    let mut tree = Tree::new();
+    let mut model = BTreeMap::new();
+    let mut versions = Vec::new();
+    for step in 0..20000u64 {
+        let k = rng.below(2000);
+        match rng.below(10) {
+            0..=5 => assert_eq!(tree.insert(k, step), model.insert(k, step)),
+            6 | 7 => assert_eq!(tree.remove_entry(&k), model.remove_entry(&k)),
+            _ => assert_eq!(tree.remove(&k), model.remove(&k)),
+        }
+        assert_eq!(tree.len(), model.len());
+        assert_eq!(tree.get(&k), model.get(&k));
+        if step % 500 == 0 {
+            versions.push((tree.clone(), model.clone()));
+        }
+        if step % 777 != 0 {
+            continue;
+        }
+        let i = rng.index(model.len() + 1);
+        assert_eq!(tree.select(i), model.iter().nth(i));
+        assert_eq!(tree.rank(&k), model.range(..k).count());
+        assert_eq!(tree.first_key_value(), model.first_key_value());
+        assert_eq!(tree.last_key_value(), model.last_key_value());
+        let (a, b) = (rng.below(2000), rng.below(2000));
+        let (a, b) = (a.min(b), a.max(b));
+        assert!(tree.range(a..b).eq(model.range(a..b)));
+
+        let (left, mid, right) = tree.split(&k);
+        assert_eq!(mid, model.get(&k).copied());
+        assert!(left.iter().eq(model.range(..k)));
+        assert!(right.iter().eq(model.range(k + 1..)));
+        let mut joined = Tree::join(left.clone(), k, 7, right.clone());
+        assert_eq!(joined.len(), left.len() + right.len() + 1);
+        assert_eq!(joined.get(&k), Some(&7));
+        joined.remove(&k);
+        let mut merged = left.clone();
+        merged.merge(right);
+        assert_eq!(joined, merged);
+        assert_eq!(tree.removed(&k), merged);
+        assert_eq!(tree.inserted(k, 9).get(&k), Some(&9));
+        assert_eq!(tree.get(&k), model.get(&k));
+    }
+    for (tree, model) in &versions {
+        assert_eq!(entries(tree), *model);
+    }
+}
+
+/// Union, intersection and difference between saved versions, which
+/// share most of their nodes, leave both operands as they were.
+#[test]
+fn set_operations_between_versions() {
+    let mut rng = Rng(6);
+    let mut tree = Tree::new();
+    let mut versions = Vec::new();
+    for step in 0..5000u64 {
+        let k = rng.below(1000);
+        if rng.below(3) == 0 {
+            tree.remove(&k);
+        } else {
+            tree.insert(k, step);
+        }
+        if step % 250 == 0 {
+            versions.push((tree.clone(), entries(&tree)));
+        }
+    }
+    for _ in 0..200 {
+        let (a, ma) = &versions[rng.index(versions.len())];
+        let (b, mb) = &versions[rng.index(versions.len())];
+        let mut union = mb.clone();
+        union.extend(ma);
+        assert_eq!(entries(&a.union(b)), union);
+        let inter: BTreeMap<u64, u64> = ma
+            .iter()
+            .filter(|e| mb.contains_key(e.0))
+            .map(|(&k, &v)| (k, v))
+            .collect();
+        assert_eq!(entries(&a.intersection(b)), inter);
+        let diff: BTreeMap<u64, u64> = ma
+            .iter()
+            .filter(|e| !mb.contains_key(e.0))
+            .map(|(&k, &v)| (k, v))
+            .collect();
+        assert_eq!(entries(&a.difference(b)), diff);
+    }
+    for (tree, model) in &versions {
+        assert_eq!(entries(tree), *model);
+    }
+}
+
+/// Ascending inserts and removals, long chains of joins onto one side,
+/// and set operations between very different sizes.
+#[test]
+fn skewed_workloads() {
+    let mut tree: WeightBalancedTree<u64, ()> = (0..50_000).map(|k| (k, ())).collect();
+    for k in (0..50_000).step_by(2) {
+        tree.remove(&k);
+    }
+    assert_eq!(tree.len(), 25_000);
+    assert_eq!(tree.select(100), Some((&201, &())));
+    let mut chain = WeightBalancedTree::new();
+    for k in 0..20_000u64 {
+        chain = WeightBalancedTree::join(chain, k, (), WeightBalancedTree::new());
+    }
+    let small: WeightBalancedTree<u64, ()> = (0..50).map(|k| (k * 400, ())).collect();
+    assert_eq!(chain.intersection(&small), small);
+    assert_eq!(small.union(&chain).len(), 20_000);
+    assert_eq!(chain.difference(&small).len(), 20_000 - 50);
+    chain.clear();
+    assert!(chain.is_empty());
+}
+
+/// Versions branched from random earlier ones, compared pairwise. Values
+/// come from a small range, so some writes put back the value already
+/// there and must not be reported.
+#[test]
+fn diff_between_versions() {
+    let mut rng = Rng(8);
+    let mut versions: Vec<(Tree, BTreeMap<u64, u64>)> = vec![(Tree::new(), BTreeMap::new())];
+    for _ in 0..300 {
+        let (mut tree, mut model) = versions[rng.index(versions.len())].clone();
+        let saved = tree.clone();
+        assert!(tree.ptr_eq(&saved));
+        for _ in 0..rng.below(30) {
+            let k = rng.below(300);
+            if rng.below(3) == 0 {
+                tree.remove(&k);
+                model.remove(&k);
+            } else {
+                let v = rng.below(4);
+                tree.insert(k, v);
+                model.insert(k, v);
+            }
+        }
+        versions.push((tree, model));
+        for _ in 0..5 {
+            let (a, a_model) = &versions[rng.index(versions.len())];
+            let (b, b_model) = &versions[rng.index(versions.len())];
+            let changes: Vec<Owned> = a.diff(b).map(owned).collect();
+            assert_eq!(changes, model_diff(a_model, b_model));
+            assert_eq!(a.diff(a).count(), 0);
+        }
+    }
+    assert!(Tree::new().ptr_eq(&Tree::new()));
+    let one: Tree = [(1, 1)].into_iter().collect();
+    assert!(!one.ptr_eq(&one.inserted(1, 1)));
+}
+
+/// A few writes to a clone of a large map are found without visiting the
+/// subtrees the two versions still share.
+#[test]
+fn diff_skips_shared_subtrees() {
+    let big: Tree = (0..100_000).map(|i| (i, i)).collect();
+    let mut next = big.clone();
+    for k in [5, 50_000, 99_999] {
+        next.insert(k, 0);
+    }
+    next.remove(&77);
+    next.insert(200_000, 1);
+    assert!(!big.ptr_eq(&next));
+    let expected = [
+        Change::Changed(&5, &5, &0),
+        Change::Removed(&77, &77),
+        Change::Changed(&50_000, &50_000, &0),
+        Change::Changed(&99_999, &99_999, &0),
+        Change::Added(&200_000, &1),
+    ];
+    for _ in 0..1000 {
+        assert!(big.diff(&next).eq(expected));
+    }
+}
+
+/// Joining requires the key to fall between the two trees.
+#[test]
+#[should_panic(expected = "joined trees must be ordered")]
+fn unordered_join_panics() {
+    let left: Tree = [(5, 0)].into_iter().collect();
+    Tree::join(left, 3, 0, Tree::new());
+}
+
+/// Merging requires the trees not to overlap.
+#[test]
+#[should_panic(expected = "merged trees must not overlap")]
+fn overlapping_merge_panics() {
+    let mut left: Tree = [(5, 0)].into_iter().collect();
+    left.merge([(5, 1)].into_iter().collect());
+}
@@ -0,0 +1,104 @@
+//! The x-fast and y-fast tries against a `BTreeSet`, for key widths from
+//! one bit to the full 64, with keys drawn from the top of wide universes
+//! so that the extreme keys and dense buckets both come up.
+
+use std::collections::BTreeSet;
+
+use datastructures::yfast_trie::{XFastTrie, YFastTrie};
+
+mod common;
+use common::Rng;
+
+/// Random inserts and removals, enough to split and merge y-fast buckets,
+/// with every query checked after each step.
+#[test]
+fn operations_match_btreeset() {
+    for bits in [1u32, 2, 3, 5, 8, 13, 32, 63, 64] {
+        let mut rng = Rng(u64::from(bits) * 7 + 1);
+        let mask = u64::MAX >> (64 - bits);
+        let span = if bits > 10 { 2000 } else { mask };
+        let base = if bits > 12 { mask - span } else { 0 };
+        for _ in 0..2 {
+            let mut x = XFastTrie::new(bits);
+            let mut y = YFastTrie::new(bits);
+            assert_eq!((x.bits(), y.bits()), (bits, bits));
+            let mut model = BTreeSet::new();
+            for _ in 0..1500 {
+                let k = base + rng.below(span + 1);
+                match rng.below(10) {
+                    0..=4 => {
+                        let absent = model.insert(k);
+                        assert_eq!(x.insert(k), absent);
+                        assert_eq!(y.insert(k), absent);
+                    }
+                    5..=7 => {
+                        let present = model.remove(&k);
+                        assert_eq!(x.remove(k), present);
+                        assert_eq!(y.remove(k), present);
+                    }
+                    _ => {}
+                }
+                let q = base + rng.below(span + 1);
+                let above = model.range(q.saturating_add(1)..).next().copied();
+                let above = above.filter(|_| q != u64::MAX);
+                let below = model.range(..q).next_back().copied();
+                assert_eq!(x.successor(q), above);
+                assert_eq!(y.successor(q), above);
+                assert_eq!(x.predecessor(q), below);
+                assert_eq!(y.predecessor(q), below);
+                assert_eq!(x.contains(q), model.contains(&q));
+                assert_eq!(y.contains(q), model.contains(&q));
+                assert_eq!((x.len(), y.len()), (model.len(), model.len()));
+                assert_eq!(y.is_empty(), model.is_empty());
+                assert_eq!(x.min(), model.first().copied());
+                assert_eq!(y.min(), model.first().copied());
+                assert_eq!(x.max(), model.last().copied());
+                assert_eq!(y.max(), model.last().copied());
+                let a = base + rng.below(span + 1);
+                let b = base + rng.below(span + 1);
+                let (a, b) = (a.min(b), a.max(b));
+                assert!(y.range(a..=b).eq(model.range(a..=b).copied()));
+                assert!(y.range(a..b).eq(model.range(a..b).copied()));
+                assert!(y.range(..a).eq(model.range(..a).copied()));
+                assert_eq!(y.range(b..a).count(), 0);
+            }
+            assert!(x.iter().eq(model.iter().copied()));
+            assert!(y.iter().eq(model.iter().copied()));
+            if bits < 64 {
+                assert_eq!(y.successor(mask + 1), None);
+                assert_eq!(y.predecessor(mask + 1), model.last().copied());
+                assert!(!x.contains(mask + 1));
+                assert!(!y.contains(mask + 1));
+            }
+        }
+    }
+}
+
+/// Extending and clearing, and the extremes of the 64-bit universe.
+#[test]
+fn extend_and_clear() {
+    let mut x = XFastTrie::new(64);
+    let mut y = YFastTrie::new(64);
+    let keys = [u64::MAX, 0, 1 << 40, u64::MAX - 1];
+    x.extend(keys);
+    y.extend(keys);
+    assert_eq!(
+        y.iter().collect::<Vec<_>>(),
+        [0, 1 << 40, u64::MAX - 1, u64::MAX]
+    );
+    assert!(x.iter().eq(y.iter()));
+    assert_eq!(y.predecessor(0), None);
+    assert_eq!(x.successor(u64::MAX - 1), Some(u64::MAX));
+    x.clear();
+    y.clear();
+    assert!(x.is_empty() && y.is_empty());
+    assert_eq!((x.min(), y.max()), (None, None));
+    assert_eq!(y.iter().count(), 0);
+}
+
+/// Keys must fit the trie's width.
+#[test]
+#[should_panic(expected = "key outside the universe")]
+fn key_outside_universe_panics() {
+    YFastTrie::new(8).insert(256);
+}
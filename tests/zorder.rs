@@ -0,0 +1,80 @@
+//! The Z-order sorted-array index against a scan of its items, and its
+//! BIGMIN and LITMAX jumps against a linear search of the codes.
+
+use datastructures::morton;
+use datastructures::zorder::{bigmin, litmax, ZOrderIndex};
+
+mod common;
+use common::{sorted, Rng};
+
+/// Box, cell and exact-match queries over items whose coordinates lie
+/// below `spread`.
+fn random_queries<const N: usize>(seed: u64, spread: u64) {
+    let mut rng = Rng(seed);
+    let items: Vec<([u32; N], usize)> = (0..3000)
+        .map(|i| (std::array::from_fn(|_| rng.below(spread) as u32), i))
+        .collect();
+    let index: ZOrderIndex<usize, N> = items.iter().cloned().collect();
+    assert!((1..index.len()).all(|i| index.code(i - 1) <= index.code(i)));
+    for _ in 0..300 {
+        let a: [u32; N] = std::array::from_fn(|_| rng.below(spread) as u32);
+        let b: [u32; N] = std::array::from_fn(|_| rng.below(spread) as u32);
+        let min = std::array::from_fn(|i| a[i].min(b[i]));
+        let max = std::array::from_fn(|i| a[i].max(b[i]));
+        let inside = |k: &[u32; N]| (0..N).all(|i| min[i] <= k[i] && k[i] <= max[i]);
+        let got: Vec<usize> = index.within_box(&min, &max).map(|x| *x.2).collect();
+        let want: Vec<usize> = items
+            .iter()
+            .filter(|(k, _)| inside(k))
+            .map(|x| x.1)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+
+        let depth = 32 - rng.below(33) as u32;
+        let shift = 32 - depth;
+        let got: Vec<usize> = index
+            .cell(&a, depth)
+            .map(|i| *index.get(i).unwrap().1)
+            .collect();
+        let want: Vec<usize> = items
+            .iter()
+            .filter(|(k, _)| (0..N).all(|i| (k[i] as u64) >> shift == (a[i] as u64) >> shift))
+            .map(|x| x.1)
+            .collect();
+        assert_eq!(sorted(got), sorted(want));
+        assert_eq!(
+            index.find(&a).len(),
+            items.iter().filter(|x| x.0 == a).count()
+        );
+
+        // Small codes can be searched linearly for the next and previous
+        // code inside the box.
+        if spread > 64 {
+            continue;
+        }
+        let (zmin, zmax) = (morton::encode(&min), morton::encode(&max));
+        let in_box = |c: u128| inside(&morton::decode::<N>(c));
+        for _ in 0..5 {
+            let c = zmin + (rng.next_u64() as u128) % (zmax - zmin + 1);
+            if in_box(c) {
+                continue;
+            }
+            let next = (c + 1..=zmax).find(|&x| in_box(x)).unwrap();
+            let previous = (zmin..c).rev().find(|&x| in_box(x)).unwrap();
+            assert_eq!(bigmin::<N>(c, zmin, zmax), next);
+            assert_eq!(litmax::<N>(c, zmin, zmax), previous);
+        }
+    }
+}
+
+/// Queries agree with a scan in one to four dimensions, over dense small
+/// grids and the full 32-bit range.
+#[test]
+fn queries_match_brute_force() {
+    random_queries::<1>(1, 1000);
+    random_queries::<2>(2, 64);
+    random_queries::<3>(3, 16);
+    random_queries::<4>(4, 8);
+    random_queries::<2>(5, 1 << 32);
+    random_queries::<3>(6, 1 << 32);
+}